@@ -0,0 +1,39 @@
+-- Create merchants table
+CREATE TABLE IF NOT EXISTS merchants (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    mcc TEXT NOT NULL,
+    country TEXT NOT NULL,
+    currency TEXT NOT NULL,
+    acquirer_mid TEXT,
+    address TEXT,
+    status TEXT NOT NULL DEFAULT 'ACTIVE' CHECK(status IN ('ACTIVE', 'SUSPENDED', 'CLOSED')),
+    tid_sequence INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_merchants_status ON merchants(status);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_merchants_acquirer_mid ON merchants(acquirer_mid);
+
+-- Create stores table
+CREATE TABLE IF NOT EXISTS stores (
+    id TEXT PRIMARY KEY NOT NULL,
+    merchant_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    address TEXT,
+    status TEXT NOT NULL DEFAULT 'ACTIVE' CHECK(status IN ('ACTIVE', 'CLOSED')),
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (merchant_id) REFERENCES merchants(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_stores_merchant_id ON stores(merchant_id);
+
+-- 设备门店分配及终端号
+ALTER TABLE devices ADD COLUMN store_id TEXT REFERENCES stores(id) ON DELETE SET NULL;
+ALTER TABLE devices ADD COLUMN terminal_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_devices_store_id ON devices(store_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_devices_merchant_terminal ON devices(merchant_id, terminal_id);
+CREATE INDEX IF NOT EXISTS idx_transactions_merchant_id ON transactions(merchant_id);
@@ -0,0 +1,105 @@
+-- Create tenants table
+CREATE TABLE IF NOT EXISTS tenants (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'ACTIVE' CHECK(status IN ('ACTIVE', 'SUSPENDED')),
+    bdk TEXT,
+    rate_limit_rps INTEGER,
+    rate_limit_burst INTEGER,
+    branding TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- 默认租户，承载多租户改造前的全部数据
+INSERT OR IGNORE INTO tenants (id, name, status, created_at, updated_at)
+VALUES ('default', 'Default Tenant', 'ACTIVE', datetime('now'), datetime('now'));
+
+-- Create api_keys table
+CREATE TABLE IF NOT EXISTS api_keys (
+    id TEXT PRIMARY KEY NOT NULL,
+    tenant_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    key_prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL DEFAULT 'operator',
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    last_used_at TEXT,
+    revoked_at TEXT,
+    FOREIGN KEY (tenant_id) REFERENCES tenants(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_tenant_id ON api_keys(tenant_id);
+
+-- 业务表增加租户字段
+ALTER TABLE devices ADD COLUMN tenant_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE merchants ADD COLUMN tenant_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE audit_logs ADD COLUMN tenant_id TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX IF NOT EXISTS idx_devices_tenant_id ON devices(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_merchants_tenant_id ON merchants(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_tenant_id ON audit_logs(tenant_id);
+
+-- 版本号唯一性改为租户内唯一，需要重建sdk_versions和kernels表
+CREATE TABLE sdk_versions_new (
+    id TEXT PRIMARY KEY NOT NULL,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    version TEXT NOT NULL,
+    update_type TEXT NOT NULL,
+    status TEXT NOT NULL,
+    download_url TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    file_size INTEGER NOT NULL,
+    release_notes TEXT NOT NULL,
+    min_os_version TEXT,
+    target_devices TEXT,
+    distribution_strategy TEXT,
+    created_at TEXT NOT NULL,
+    released_at TEXT,
+    UNIQUE (tenant_id, version)
+);
+
+INSERT INTO sdk_versions_new (
+    id, version, update_type, status, download_url, checksum, file_size, release_notes,
+    min_os_version, target_devices, distribution_strategy, created_at, released_at
+)
+SELECT
+    id, version, update_type, status, download_url, checksum, file_size, release_notes,
+    min_os_version, target_devices, distribution_strategy, created_at, released_at
+FROM sdk_versions;
+
+DROP TABLE sdk_versions;
+ALTER TABLE sdk_versions_new RENAME TO sdk_versions;
+
+CREATE INDEX IF NOT EXISTS idx_sdk_versions_version ON sdk_versions(version);
+CREATE INDEX IF NOT EXISTS idx_sdk_versions_status ON sdk_versions(status);
+CREATE INDEX IF NOT EXISTS idx_sdk_versions_created_at ON sdk_versions(created_at);
+CREATE INDEX IF NOT EXISTS idx_sdk_versions_tenant_id ON sdk_versions(tenant_id);
+
+CREATE TABLE kernels_new (
+    id TEXT PRIMARY KEY,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    version TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    file_size INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'draft',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (tenant_id, version)
+);
+
+INSERT INTO kernels_new (id, version, file_path, file_hash, file_size, status, created_at, updated_at)
+SELECT id, version, file_path, file_hash, file_size, status, created_at, updated_at FROM kernels;
+
+DROP TABLE kernels;
+ALTER TABLE kernels_new RENAME TO kernels;
+
+CREATE INDEX IF NOT EXISTS idx_kernels_version ON kernels(version);
+CREATE INDEX IF NOT EXISTS idx_kernels_status ON kernels(status);
+CREATE INDEX IF NOT EXISTS idx_kernels_tenant_id ON kernels(tenant_id);
+
+-- 收单商户号改为租户内唯一
+DROP INDEX IF EXISTS idx_merchants_acquirer_mid;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_merchants_tenant_acquirer_mid ON merchants(tenant_id, acquirer_mid);
@@ -0,0 +1,102 @@
+-- 设备生命周期：新增DECOMMISSIONED和REPLACED状态，并记录状态变更历史
+--
+-- SQLite无法修改CHECK约束，需要重建devices表。迁移在事务中执行，无法关闭外键，
+-- 删除旧表会级联删除/置空子表数据，因此先备份子表数据，重建后再恢复。
+
+CREATE TABLE health_checks_backup AS SELECT * FROM health_checks;
+CREATE TABLE threat_events_backup AS SELECT * FROM threat_events;
+CREATE TABLE transactions_backup AS SELECT * FROM transactions;
+CREATE TABLE audit_logs_device_backup AS
+    SELECT id, device_id FROM audit_logs WHERE device_id IS NOT NULL;
+
+CREATE TABLE devices_new (
+    id TEXT PRIMARY KEY NOT NULL,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    imei TEXT NOT NULL UNIQUE,
+    model TEXT NOT NULL,
+    os_version TEXT NOT NULL,
+    tee_type TEXT NOT NULL CHECK(tee_type IN ('QTEE', 'TRUSTZONE')),
+    device_mode TEXT NOT NULL DEFAULT 'FULL_POS' CHECK(device_mode IN ('FULL_POS', 'PINPAD')),
+    public_key BLOB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN (
+        'PENDING', 'ACTIVE', 'SUSPENDED', 'REVOKED', 'REJECTED', 'DECOMMISSIONED', 'REPLACED'
+    )),
+    merchant_id TEXT,
+    merchant_name TEXT,
+    store_id TEXT REFERENCES stores(id) ON DELETE SET NULL,
+    terminal_id TEXT,
+    security_score INTEGER NOT NULL DEFAULT 0 CHECK(security_score >= 0 AND security_score <= 100),
+    current_ksn TEXT NOT NULL,
+    ipek_injected_at TEXT,
+    key_remaining_count INTEGER NOT NULL DEFAULT 1000000,
+    key_total_count INTEGER NOT NULL DEFAULT 1000000,
+    registered_at TEXT NOT NULL,
+    approved_at TEXT,
+    approved_by TEXT,
+    last_active_at TEXT,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    nfc_present BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+INSERT INTO devices_new (
+    id, tenant_id, imei, model, os_version, tee_type, device_mode, public_key, status,
+    merchant_id, merchant_name, store_id, terminal_id, security_score, current_ksn,
+    ipek_injected_at, key_remaining_count, key_total_count, registered_at, approved_at,
+    approved_by, last_active_at, updated_at, nfc_present
+)
+SELECT
+    id, tenant_id, imei, model, os_version, tee_type, device_mode, public_key, status,
+    merchant_id, merchant_name, store_id, terminal_id, security_score, current_ksn,
+    ipek_injected_at, key_remaining_count, key_total_count, registered_at, approved_at,
+    approved_by, last_active_at, updated_at, nfc_present
+FROM devices;
+
+DROP TABLE devices;
+ALTER TABLE devices_new RENAME TO devices;
+
+CREATE INDEX IF NOT EXISTS idx_devices_status ON devices(status);
+CREATE INDEX IF NOT EXISTS idx_devices_merchant_id ON devices(merchant_id);
+CREATE INDEX IF NOT EXISTS idx_devices_security_score ON devices(security_score);
+CREATE INDEX IF NOT EXISTS idx_devices_imei ON devices(imei);
+CREATE INDEX IF NOT EXISTS idx_devices_registered_at ON devices(registered_at);
+CREATE INDEX IF NOT EXISTS idx_devices_last_active_at ON devices(last_active_at);
+CREATE INDEX IF NOT EXISTS idx_devices_store_id ON devices(store_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_devices_merchant_terminal ON devices(merchant_id, terminal_id);
+CREATE INDEX IF NOT EXISTS idx_devices_tenant_id ON devices(tenant_id);
+
+-- 恢复子表数据
+DELETE FROM health_checks;
+INSERT INTO health_checks SELECT * FROM health_checks_backup;
+DELETE FROM threat_events;
+INSERT INTO threat_events SELECT * FROM threat_events_backup;
+DELETE FROM transactions;
+INSERT INTO transactions SELECT * FROM transactions_backup;
+UPDATE audit_logs
+SET device_id = (SELECT b.device_id FROM audit_logs_device_backup b WHERE b.id = audit_logs.id)
+WHERE id IN (SELECT id FROM audit_logs_device_backup);
+
+DROP TABLE health_checks_backup;
+DROP TABLE threat_events_backup;
+DROP TABLE transactions_backup;
+DROP TABLE audit_logs_device_backup;
+
+-- Create device_status_history table
+CREATE TABLE IF NOT EXISTS device_status_history (
+    id TEXT PRIMARY KEY NOT NULL,
+    device_id TEXT NOT NULL,
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    changed_by TEXT NOT NULL,
+    reason TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_status_history_device_id
+    ON device_status_history(device_id, created_at);
+
+-- 已有设备补录当前状态作为时间线起点
+INSERT INTO device_status_history (id, device_id, from_status, to_status, changed_by, reason, created_at)
+SELECT lower(hex(randomblob(16))), id, NULL, status, COALESCE(approved_by, 'system'),
+       'Initial status', COALESCE(approved_at, registered_at)
+FROM devices;
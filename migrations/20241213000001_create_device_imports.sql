@@ -0,0 +1,38 @@
+-- Create device_import_jobs table
+CREATE TABLE IF NOT EXISTS device_import_jobs (
+    id TEXT PRIMARY KEY NOT NULL,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN ('PENDING', 'RUNNING', 'COMPLETED', 'FAILED')),
+    format TEXT NOT NULL CHECK(format IN ('CSV', 'JSON')),
+    total_rows INTEGER NOT NULL DEFAULT 0,
+    success_rows INTEGER NOT NULL DEFAULT 0,
+    failed_rows INTEGER NOT NULL DEFAULT 0,
+    errors TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    completed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_import_jobs_tenant_id ON device_import_jobs(tenant_id, created_at);
+
+-- Create device_preapprovals table
+CREATE TABLE IF NOT EXISTS device_preapprovals (
+    id TEXT PRIMARY KEY NOT NULL,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    job_id TEXT NOT NULL,
+    imei TEXT NOT NULL,
+    model TEXT NOT NULL,
+    store_id TEXT REFERENCES stores(id) ON DELETE SET NULL,
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN ('PENDING', 'CONSUMED')),
+    device_id TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    consumed_at TEXT,
+    FOREIGN KEY (job_id) REFERENCES device_import_jobs(id) ON DELETE CASCADE
+);
+
+-- 同一IMEI同时只能有一个待使用的预审批
+CREATE UNIQUE INDEX IF NOT EXISTS idx_device_preapprovals_pending_imei
+    ON device_preapprovals(imei) WHERE status = 'PENDING';
+CREATE INDEX IF NOT EXISTS idx_device_preapprovals_job_id ON device_preapprovals(job_id);
@@ -0,0 +1,37 @@
+-- Create device_tags table
+CREATE TABLE IF NOT EXISTS device_tags (
+    device_id TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (device_id, tag),
+    FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_tags_tag ON device_tags(tag);
+
+-- Create device_groups table
+CREATE TABLE IF NOT EXISTS device_groups (
+    id TEXT PRIMARY KEY NOT NULL,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    name TEXT NOT NULL,
+    description TEXT,
+    group_type TEXT NOT NULL CHECK(group_type IN ('STATIC', 'DYNAMIC')),
+    rules TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (tenant_id, name)
+);
+
+-- Create device_group_members table（仅静态分组使用）
+CREATE TABLE IF NOT EXISTS device_group_members (
+    group_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    added_by TEXT NOT NULL,
+    added_at TEXT NOT NULL,
+    PRIMARY KEY (group_id, device_id),
+    FOREIGN KEY (group_id) REFERENCES device_groups(id) ON DELETE CASCADE,
+    FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_group_members_device_id ON device_group_members(device_id);
@@ -0,0 +1,28 @@
+-- Create device_commands table
+CREATE TABLE IF NOT EXISTS device_commands (
+    id TEXT PRIMARY KEY NOT NULL,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    device_id TEXT NOT NULL,
+    command_type TEXT NOT NULL CHECK(command_type IN (
+        'LOCK', 'ZEROIZE_KEYS', 'FORCE_HEALTH_CHECK', 'UPLOAD_LOGS', 'UPDATE_CONFIG', 'REBOOT_SDK'
+    )),
+    payload TEXT,
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN (
+        'PENDING', 'DELIVERED', 'ACKNOWLEDGED', 'SUCCEEDED', 'FAILED', 'TIMED_OUT', 'CANCELLED'
+    )),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 3,
+    timeout_seconds INTEGER NOT NULL DEFAULT 300,
+    result TEXT,
+    error TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    delivered_at TEXT,
+    acknowledged_at TEXT,
+    completed_at TEXT,
+    deadline_at TEXT,
+    FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_commands_device_id ON device_commands(device_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_device_commands_status ON device_commands(status, deadline_at);
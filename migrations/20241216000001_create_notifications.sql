@@ -0,0 +1,27 @@
+-- Create notifications table（通知持久化，seq单调递增用于断线重连补发）
+CREATE TABLE IF NOT EXISTS notifications (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    tenant_id TEXT,
+    notification_type TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    title TEXT NOT NULL,
+    message TEXT NOT NULL,
+    device_id TEXT,
+    merchant_id TEXT,
+    threat_id TEXT,
+    data TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_tenant_seq ON notifications(tenant_id, seq);
+
+-- Create notification_reads table（按用户记录已读状态）
+CREATE TABLE IF NOT EXISTS notification_reads (
+    seq INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    read_at TEXT NOT NULL,
+    PRIMARY KEY (seq, user_id),
+    FOREIGN KEY (seq) REFERENCES notifications(seq) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_reads_user ON notification_reads(user_id, seq);
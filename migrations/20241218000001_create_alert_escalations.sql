@@ -0,0 +1,13 @@
+-- Create alert_escalations table（外部告警的未确认升级跟踪）
+CREATE TABLE IF NOT EXISTS alert_escalations (
+    id TEXT PRIMARY KEY NOT NULL,
+    notification_seq INTEGER NOT NULL,
+    route_name TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN ('PENDING', 'ACKNOWLEDGED', 'ESCALATED')),
+    due_at TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    resolved_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_alert_escalations_due ON alert_escalations(status, due_at);
+CREATE INDEX IF NOT EXISTS idx_alert_escalations_seq ON alert_escalations(notification_seq);
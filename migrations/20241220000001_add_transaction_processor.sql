@@ -0,0 +1,10 @@
+-- 记录处理交易的支付连接器和处理器应答
+-- 2024-12-20
+ALTER TABLE transactions
+ADD COLUMN processor TEXT;
+ALTER TABLE transactions
+ADD COLUMN processor_reference TEXT;
+-- 批准金额（分），部分批准时小于交易金额
+ALTER TABLE transactions
+ADD COLUMN approved_amount INTEGER;
+CREATE INDEX idx_transactions_processor_reference ON transactions(processor, processor_reference);
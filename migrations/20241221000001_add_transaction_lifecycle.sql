@@ -0,0 +1,13 @@
+-- 交易生命周期：关联原交易、预授权有效期和结算标记
+-- 2024-12-21
+-- 退款、撤销、请款和追加授权引用的原交易
+ALTER TABLE transactions
+ADD COLUMN original_transaction_id TEXT REFERENCES transactions(id);
+-- 预授权有效期，过期后不能再请款或追加授权
+ALTER TABLE transactions
+ADD COLUMN auth_expires_at TEXT;
+-- 结算时间，已结算的交易不能撤销
+ALTER TABLE transactions
+ADD COLUMN settled_at TEXT;
+CREATE INDEX idx_transactions_original_transaction_id ON transactions(original_transaction_id);
+CREATE INDEX idx_transactions_auth_expires_at ON transactions(auth_expires_at);
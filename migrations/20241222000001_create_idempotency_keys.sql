@@ -0,0 +1,16 @@
+-- Create idempotency_keys table（交易请求幂等键，按设备隔离）
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    device_id TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'IN_PROGRESS' CHECK(status IN ('IN_PROGRESS', 'COMPLETED')),
+    response_status INTEGER,
+    response_body TEXT,
+    transaction_id TEXT,
+    created_at TEXT NOT NULL,
+    locked_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    PRIMARY KEY (device_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires_at ON idempotency_keys(expires_at);
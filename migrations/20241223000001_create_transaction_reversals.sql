@@ -0,0 +1,38 @@
+-- 结果未知交易的冲正队列和交易状态历史
+-- 2024-12-23
+-- 客户端生成的交易ID，设备丢失应答时据此申请冲正
+ALTER TABLE transactions
+ADD COLUMN client_transaction_id TEXT;
+CREATE INDEX idx_transactions_client_transaction_id ON transactions(device_id, client_transaction_id);
+
+-- 冲正队列（每笔交易最多一条，后台任务按退避策略重试）
+CREATE TABLE IF NOT EXISTS transaction_reversals (
+    id TEXT PRIMARY KEY NOT NULL,
+    transaction_id TEXT NOT NULL UNIQUE REFERENCES transactions(id),
+    reason TEXT NOT NULL CHECK(reason IN ('PROCESSOR_TIMEOUT', 'DEVICE_REQUEST', 'OPERATOR_REQUEST')),
+    requested_by TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN ('PENDING', 'COMPLETED', 'FAILED')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TEXT NOT NULL,
+    last_error TEXT,
+    response_code TEXT,
+    created_at TEXT NOT NULL,
+    completed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_reversals_due ON transaction_reversals(status, next_attempt_at);
+
+-- 交易状态历史（只追加）
+CREATE TABLE IF NOT EXISTS transaction_events (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    transaction_id TEXT NOT NULL REFERENCES transactions(id),
+    event_type TEXT NOT NULL,
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    response_code TEXT,
+    details TEXT,
+    actor TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_events_transaction ON transaction_events(transaction_id, seq);
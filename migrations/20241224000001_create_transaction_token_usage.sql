@@ -0,0 +1,10 @@
+-- Create transaction_token_usage table（已使用的交易令牌，Redis不可用时用于防重放）
+CREATE TABLE IF NOT EXISTS transaction_token_usage (
+    jti TEXT PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    transaction_id TEXT NOT NULL,
+    used_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_token_usage_expires_at ON transaction_token_usage(expires_at);
@@ -0,0 +1,50 @@
+-- 结算批次：按设备和商户自动开批，日切或设备结算时关批并提交处理器
+-- 2024-12-25
+CREATE TABLE IF NOT EXISTS settlement_batches (
+    id TEXT PRIMARY KEY NOT NULL,
+    device_id TEXT NOT NULL REFERENCES devices(id),
+    merchant_id TEXT,
+    -- 设备内递增的批次号
+    batch_number INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'OPEN' CHECK(status IN ('OPEN', 'CLOSED', 'SETTLED', 'FAILED')),
+    -- 关批时计入合计的交易笔数
+    transaction_count INTEGER NOT NULL DEFAULT 0,
+    processor TEXT,
+    processor_reference TEXT,
+    response_code TEXT,
+    failure_reason TEXT CHECK(failure_reason IN ('REJECTED', 'TOTALS_MISMATCH', 'SUBMISSION_FAILED')),
+    last_error TEXT,
+    -- 提交处理器的次数，关批后由后台任务重试
+    submit_attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TEXT,
+    -- 对账不平时是否已上送批内明细
+    uploaded INTEGER NOT NULL DEFAULT 0,
+    opened_at TEXT NOT NULL,
+    closed_at TEXT,
+    closed_by TEXT,
+    settled_at TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- 每个设备和商户同时只有一个开放批次
+CREATE UNIQUE INDEX IF NOT EXISTS idx_settlement_batches_open
+    ON settlement_batches(device_id, IFNULL(merchant_id, '')) WHERE status = 'OPEN';
+CREATE UNIQUE INDEX IF NOT EXISTS idx_settlement_batches_number
+    ON settlement_batches(device_id, batch_number);
+CREATE INDEX IF NOT EXISTS idx_settlement_batches_status ON settlement_batches(status, opened_at);
+
+-- 关批时按交易类型和币种汇总的合计
+CREATE TABLE IF NOT EXISTS settlement_batch_totals (
+    batch_id TEXT NOT NULL REFERENCES settlement_batches(id),
+    transaction_type TEXT NOT NULL,
+    currency TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (batch_id, transaction_type, currency)
+);
+
+-- 已批准交易所属的结算批次
+ALTER TABLE transactions
+ADD COLUMN batch_id TEXT REFERENCES settlement_batches(id);
+CREATE INDEX idx_transactions_batch_id ON transactions(batch_id);
@@ -0,0 +1,41 @@
+-- 对账：导入收单机构清算文件与本地交易逐笔核对
+-- 2024-12-26
+CREATE TABLE IF NOT EXISTS reconciliation_runs (
+    id TEXT PRIMARY KEY NOT NULL,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    -- 清算文件格式名称（配置项 reconciliation.layouts）
+    layout TEXT NOT NULL,
+    file_name TEXT,
+    merchant_id TEXT,
+    -- 清算文件覆盖的交易日期（YYYY-MM-DD）
+    period_start TEXT NOT NULL,
+    period_end TEXT NOT NULL,
+    total_records INTEGER NOT NULL DEFAULT 0,
+    matched_count INTEGER NOT NULL DEFAULT 0,
+    amount_mismatch_count INTEGER NOT NULL DEFAULT 0,
+    unmatched_ours_count INTEGER NOT NULL DEFAULT 0,
+    unmatched_theirs_count INTEGER NOT NULL DEFAULT 0,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_reconciliation_runs_tenant_id ON reconciliation_runs(tenant_id, created_at);
+
+CREATE TABLE IF NOT EXISTS reconciliation_items (
+    id TEXT PRIMARY KEY NOT NULL,
+    run_id TEXT NOT NULL,
+    category TEXT NOT NULL CHECK(category IN ('MATCHED', 'AMOUNT_MISMATCH', 'UNMATCHED_OURS', 'UNMATCHED_THEIRS')),
+    -- 清算文件行号，本地单边交易为空
+    line_number INTEGER,
+    transaction_id TEXT,
+    rrn TEXT,
+    authorization_code TEXT,
+    transaction_date TEXT,
+    currency TEXT,
+    our_amount INTEGER,
+    their_amount INTEGER,
+    details TEXT,
+    FOREIGN KEY (run_id) REFERENCES reconciliation_runs(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_reconciliation_items_run_id ON reconciliation_items(run_id, category);
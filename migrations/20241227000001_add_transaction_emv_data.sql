@@ -0,0 +1,4 @@
+-- 非接/芯片交易的EMV数据：只保存白名单标签（BER-TLV十六进制），不含磁道等效数据和主账号
+-- 2024-12-27
+ALTER TABLE transactions
+ADD COLUMN emv_data TEXT;
@@ -0,0 +1,28 @@
+-- 后续交易在提交处理器前原子占用原交易：退款累加已占用的退款金额，请款、撤销和追加授权占用原交易
+-- 2024-12-28
+ALTER TABLE transactions
+ADD COLUMN refunded_amount INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE transactions
+ADD COLUMN pending_follow_up_id TEXT;
+
+-- 已批准和结果未决的退款计入已占用金额
+UPDATE transactions
+SET refunded_amount = (
+    SELECT COALESCE(SUM(COALESCE(r.approved_amount, r.amount)), 0)
+    FROM transactions r
+    WHERE r.original_transaction_id = transactions.id
+      AND r.transaction_type = 'Refund'
+      AND r.status IN ('Approved', 'Pending', 'Unknown')
+);
+
+UPDATE transactions
+SET pending_follow_up_id = (
+    SELECT f.id
+    FROM transactions f
+    WHERE f.original_transaction_id = transactions.id
+      AND f.transaction_type IN ('Capture', 'Void', 'PreAuth')
+      AND f.status IN ('Pending', 'Unknown')
+    ORDER BY f.created_at DESC
+    LIMIT 1
+);
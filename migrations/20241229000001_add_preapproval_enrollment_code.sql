@@ -0,0 +1,4 @@
+-- 预审批的一次性注册码（SHA-256），设备注册时携带匹配的注册码才自动激活
+-- 2024-12-29
+ALTER TABLE device_preapprovals
+ADD COLUMN enrollment_code_hash TEXT;
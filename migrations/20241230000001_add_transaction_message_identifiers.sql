@@ -0,0 +1,13 @@
+-- 提交ISO 8583收单机构的报文数据元：发送前保存，冲正报文据此引用原报文（字段90/56）
+-- 2024-12-30
+ALTER TABLE transactions
+ADD COLUMN message_mti TEXT;
+
+ALTER TABLE transactions
+ADD COLUMN stan TEXT;
+
+ALTER TABLE transactions
+ADD COLUMN rrn TEXT;
+
+ALTER TABLE transactions
+ADD COLUMN transmission_time TEXT;
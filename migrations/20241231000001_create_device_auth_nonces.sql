@@ -0,0 +1,10 @@
+-- Create device_auth_nonces table（设备签名请求已使用的随机数，用于防重放）
+-- 2024-12-31
+CREATE TABLE IF NOT EXISTS device_auth_nonces (
+    device_id TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    PRIMARY KEY (device_id, nonce)
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_auth_nonces_expires_at ON device_auth_nonces(expires_at);
@@ -0,0 +1,8 @@
+-- 租户专属BDK不再明文保存：本地BDK以密钥加密密钥包装后存入bdk_wrapped，
+-- 或只保存HSM中的密钥引用bdk_key_ref；原bdk列的明文在启动时包装后清空
+-- 2024-12-31
+ALTER TABLE tenants
+ADD COLUMN bdk_wrapped TEXT;
+
+ALTER TABLE tenants
+ADD COLUMN bdk_key_ref TEXT;
@@ -2,11 +2,12 @@ use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Json},
+    Extension,
 };
 use serde::Deserialize;
 use std::sync::Arc;
 
-use crate::{api::AppState, utils::error::AppError};
+use crate::{api::AppState, models::TenantContext, utils::error::AppError};
 
 /// 审计日志列表查询参数
 #[derive(Debug, Deserialize)]
@@ -26,11 +27,13 @@ pub struct ListAuditLogsQuery {
 /// GET /api/v1/audit/logs
 pub async fn list_logs(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListAuditLogsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response = state
         .audit_service
+        .for_tenant(&tenant)
         .list_logs(
             query.operation_type.as_deref(),
             None, // operator
@@ -57,10 +60,11 @@ pub async fn list_logs(
 /// GET /api/v1/audit/logs/:log_id
 pub async fn get_log(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(log_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let response = state.audit_service.get_log(&log_id).await?;
+    let response = state.audit_service.for_tenant(&tenant).get_log(&log_id).await?;
 
     let wrapped_response = serde_json::json!({
         "code": 200,
@@ -76,12 +80,14 @@ pub async fn get_log(
 /// GET /api/v1/audit/device/:device_id/logs
 pub async fn get_device_logs(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     Query(query): Query<ListAuditLogsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层，只获取特定设备的日志
     let response = state
         .audit_service
+        .for_tenant(&tenant)
         .list_logs(
             query.operation_type.as_deref(),
             None, // operator
@@ -102,6 +108,7 @@ pub async fn get_device_logs(
 /// GET /api/v1/audit/operator/:operator_id/logs
 pub async fn get_operator_logs(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(operator_id): Path<String>,
     Query(query): Query<ListAuditLogsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -109,6 +116,7 @@ pub async fn get_operator_logs(
     // 简化实现，实际应该有专门的方法
     let response = state
         .audit_service
+        .for_tenant(&tenant)
         .list_logs(
             query.operation_type.as_deref(),
             Some(&operator_id),
@@ -156,11 +164,13 @@ pub async fn get_audit_statistics(
 /// GET /api/v1/audit/export
 pub async fn export_logs(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListAuditLogsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 获取日志
     let response = state
         .audit_service
+        .for_tenant(&tenant)
         .list_logs(
             query.operation_type.as_deref(),
             None,
@@ -2,6 +2,7 @@ use axum::{
     extract::State,
     http::StatusCode,
     response::{IntoResponse, Json},
+    Extension,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -9,8 +10,9 @@ use std::sync::Arc;
 use crate::{
     api::AppState,
     dto::{request::LoginRequest, response::LoginResponse},
+    security::crypto,
     utils::error::AppError,
-    models::OperationResult,
+    models::{OperationResult, DEFAULT_TENANT_ID, TenantContext},
 };
 
 /// 刷新Token请求
@@ -46,25 +48,40 @@ pub async fn login(
         .validate()
         .map_err(|e| AppError::Validation(e))?;
 
+    // 超级管理员只能使用配置的账号登录
+    let super_admin = state
+        .config
+        .security
+        .super_admin
+        .as_ref()
+        .filter(|admin| admin.username == request.username);
+
     // TODO: 实际应用中应该从数据库验证用户凭证
     // 这里使用硬编码的测试用户
-    let (user_id, username, role) = if request.username == "admin" && request.password == "admin123" {
+    let (user_id, username, role) = if let Some(admin) = super_admin {
+        if !crypto::verify_password(&request.password, &admin.password_hash)? {
+            return Err(AppError::InvalidCredentials);
+        }
+        (admin.username.clone(), admin.username.clone(), "super_admin".to_string())
+    } else if request.username == "admin" && request.password == "admin123" {
         ("admin_001".to_string(), "admin".to_string(), "admin".to_string())
     } else if request.username == "operator" && request.password == "operator123" {
         ("operator_001".to_string(), "operator".to_string(), "operator".to_string())
     } else {
         return Err(AppError::InvalidCredentials);
     };
+    // 超级管理员和测试用户均属于默认租户
+    let tenant_id = DEFAULT_TENANT_ID.to_string();
 
     // 生成access token
     let access_token = state
         .jwt_service
-        .generate_token(&user_id, &username, &role)?;
+        .generate_token(&user_id, &username, &role, &tenant_id)?;
 
     // 生成refresh token
     let refresh_token = state
         .jwt_service
-        .generate_refresh_token(&user_id, &username, &role)?;
+        .generate_refresh_token(&user_id, &username, &role, &tenant_id)?;
 
     // 记录审计日志
     state
@@ -92,6 +109,7 @@ pub async fn login(
             user_id,
             username,
             role,
+            tenant_id,
         },
     };
 
@@ -155,6 +173,7 @@ pub async fn refresh_token(
 /// POST /api/v1/auth/logout
 pub async fn logout(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     request: axum::extract::Request,
 ) -> Result<impl IntoResponse, AppError> {
     // 从请求扩展中提取用户信息
@@ -163,6 +182,7 @@ pub async fn logout(
     // 记录审计日志
     state
         .audit_service
+        .for_tenant(&tenant)
         .log_operation(
             "USER_LOGOUT".to_string(),
             claims.sub.clone(),
@@ -198,6 +218,7 @@ pub async fn get_current_user(
         user_id: claims.sub,
         username: claims.username,
         role: claims.role,
+        tenant_id: claims.tenant_id,
     };
 
     Ok((StatusCode::OK, Json(user_info)))
@@ -2,6 +2,7 @@ use axum::{
     extract::State,
     http::StatusCode,
     response::{IntoResponse, Json},
+    Extension,
 };
 use std::sync::Arc;
 
@@ -10,6 +11,7 @@ use crate::{
     dto::response::{
         AbnormalDevice, DashboardHealthOverviewResponse, ScoreDistribution, StatusDistribution,
     },
+    models::TenantContext,
     utils::error::AppError,
 };
 
@@ -18,9 +20,10 @@ use crate::{
 /// GET /api/v1/dashboard/health-overview
 pub async fn get_health_overview(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
 ) -> Result<impl IntoResponse, AppError> {
     // 获取设备统计
-    let device_stats = state.device_service.get_device_statistics().await?;
+    let device_stats = state.device_service.for_tenant(&tenant).get_device_statistics().await?;
     
     // TODO: 这里应该从数据库聚合真实数据
     // 目前为了演示，我们基于设备统计构建响应
@@ -72,7 +75,7 @@ pub async fn get_health_overview(
     
     // 获取最近异常设备 (真实数据)
     // 查询所有设备，然后过滤出异常设备
-    let all_devices_response = state.device_service.list_devices(
+    let all_devices_response = state.device_service.for_tenant(&tenant).list_devices(
         None, // status filter
         None, // merchant_id filter  
         50,   // limit - 获取更多设备以便过滤
@@ -91,7 +94,7 @@ pub async fn get_health_overview(
         .map(|device| {
             AbnormalDevice {
                 id: device.id,
-                merchant_name: "未分配商户".to_string(), // TODO: 从device model添加merchant_name字段
+                merchant_name: device.merchant_name.unwrap_or_else(|| "未分配商户".to_string()),
                 security_score: device.security_score,
                 last_check_at: device.registered_at, // 使用registered_at作为最后检查时间的近似值
             }
@@ -10,10 +10,13 @@ use std::sync::Arc;
 use crate::{
     api::{middleware::extract_user_id, AppState},
     dto::{
-        request::{ApproveDeviceRequest, DeviceOperationRequest, RegisterDeviceRequest, RejectDeviceRequest},
+        request::{
+            ApproveDeviceRequest, DeviceOperationRequest, RegisterDeviceRequest, RejectDeviceRequest,
+            ReplaceDeviceRequest, SetDeviceTagsRequest,
+        },
         response::{DeviceListResponse, DeviceResponse, RegisterDeviceResponse, DeviceStatisticsResponse},
     },
-    models::DeviceStatus,
+    models::{DeviceStatus, TenantContext},
     utils::error::AppError,
 };
 
@@ -33,15 +36,19 @@ pub struct ListDevicesQuery {
 /// POST /api/v1/devices/register
 pub async fn register_device(
     State(state): State<Arc<AppState>>,
+    tenant: Option<Extension<TenantContext>>,
     Json(req): Json<RegisterDeviceRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     // 设备注册是公开端点，使用系统用户作为操作员
     let operator_id = "system";
 
+    // 携带租户API密钥时注册到对应租户，否则注册到默认租户；已预审批的IMEI注册到预审批所属租户
+    let tenant = tenant.map(|Extension(t)| t).unwrap_or_default();
+
     // 调用服务层
     let response_data = state
-        .device_service
-        .register_device(req, &operator_id)
+        .device_import_service
+        .register_device(&tenant, req, operator_id)
         .await?;
 
     let wrapped_response = serde_json::json!({
@@ -58,6 +65,7 @@ pub async fn register_device(
 /// GET /api/v1/devices
 pub async fn list_devices(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListDevicesQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     let page = query.page.unwrap_or(1);
@@ -70,6 +78,7 @@ pub async fn list_devices(
     // 调用服务层
     let response_data = state
         .device_service
+        .for_tenant(&tenant)
         .list_devices(
             query.status,
             query.search.as_deref(),
@@ -92,10 +101,11 @@ pub async fn list_devices(
 /// GET /api/v1/devices/:device_id
 pub async fn get_device(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let response = state.device_service.get_device(&device_id).await?;
+    let response = state.device_service.for_tenant(&tenant).get_device(&device_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -105,6 +115,7 @@ pub async fn get_device(
 /// POST /api/v1/devices/:device_id/approve
 pub async fn approve_device(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<ApproveDeviceRequest>,
@@ -115,6 +126,7 @@ pub async fn approve_device(
     // 调用服务层
     let response = state
         .device_service
+        .for_tenant(&tenant)
         .approve_device(req)
         .await?;
 
@@ -131,6 +143,7 @@ pub async fn approve_device(
 /// POST /api/v1/devices/:device_id/reject
 pub async fn reject_device(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<RejectDeviceRequest>,
@@ -141,6 +154,7 @@ pub async fn reject_device(
     // 调用服务层
     state
         .device_service
+        .for_tenant(&tenant)
         .reject_device(req)
         .await?;
 
@@ -163,6 +177,7 @@ pub async fn reject_device(
 /// POST /api/v1/devices/:device_id/suspend
 pub async fn suspend_device(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<DeviceOperationRequest>,
@@ -177,6 +192,7 @@ pub async fn suspend_device(
     // 调用服务层
     state
         .device_service
+        .for_tenant(&tenant)
         .suspend_device(&device_id, &operator_id, &req.reason)
         .await?;
 
@@ -199,6 +215,7 @@ pub async fn suspend_device(
 /// POST /api/v1/devices/:device_id/resume
 pub async fn resume_device(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     request: axum::extract::Request,
 ) -> Result<impl IntoResponse, AppError> {
@@ -208,6 +225,7 @@ pub async fn resume_device(
     // 调用服务层
     state
         .device_service
+        .for_tenant(&tenant)
         .resume_device(&device_id, &operator_id)
         .await?;
 
@@ -230,6 +248,7 @@ pub async fn resume_device(
 /// POST /api/v1/devices/:device_id/revoke
 pub async fn revoke_device(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<DeviceOperationRequest>,
@@ -244,6 +263,7 @@ pub async fn revoke_device(
     // 调用服务层
     state
         .device_service
+        .for_tenant(&tenant)
         .revoke_device(&device_id, &operator_id, &req.reason)
         .await?;
 
@@ -261,14 +281,131 @@ pub async fn revoke_device(
     Ok((StatusCode::OK, Json(response)))
 }
 
+/// 退役设备处理器
+///
+/// POST /api/v1/devices/:device_id/decommission
+pub async fn decommission_device(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(device_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<DeviceOperationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    // 验证请求
+    req.validate().map_err(AppError::BadRequest)?;
+
+    state
+        .device_service
+        .for_tenant(&tenant)
+        .decommission_device(&device_id, &claims.sub, &req.reason)
+        .await?;
+
+    #[derive(Serialize)]
+    struct DecommissionResponse {
+        message: String,
+        device_id: String,
+    }
+
+    let response = DecommissionResponse {
+        message: "Device decommissioned successfully".to_string(),
+        device_id,
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 替换设备处理器
+///
+/// POST /api/v1/devices/:device_id/replace
+pub async fn replace_device(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(device_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<ReplaceDeviceRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let replacement_device_id = req.replacement_device_id.clone();
+
+    state
+        .device_service
+        .for_tenant(&tenant)
+        .replace_device(&device_id, req, &claims.sub)
+        .await?;
+
+    #[derive(Serialize)]
+    struct ReplaceResponse {
+        message: String,
+        device_id: String,
+        replacement_device_id: String,
+    }
+
+    let response = ReplaceResponse {
+        message: "Device replaced successfully".to_string(),
+        device_id,
+        replacement_device_id,
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 设备状态时间线处理器
+///
+/// GET /api/v1/devices/:device_id/timeline
+pub async fn get_device_timeline(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_service
+        .for_tenant(&tenant)
+        .get_device_timeline(&device_id)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取设备标签处理器
+///
+/// GET /api/v1/devices/:device_id/tags
+pub async fn get_device_tags(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.device_service.for_tenant(&tenant).get_device_tags(&device_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 设置设备标签处理器
+///
+/// PUT /api/v1/devices/:device_id/tags
+pub async fn set_device_tags(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(device_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<SetDeviceTagsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_service
+        .for_tenant(&tenant)
+        .set_device_tags(&device_id, req, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
 /// 获取设备统计信息处理器
 ///
 /// GET /api/v1/devices/statistics
 pub async fn get_device_statistics(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let stats = state.device_service.get_device_statistics().await?;
+    let stats = state.device_service.for_tenant(&tenant).get_device_statistics().await?;
 
     let response = DeviceStatisticsResponse {
         total: stats.total,
@@ -0,0 +1,172 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Extension,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    api::{middleware::DeviceCredentials, AppState},
+    dto::request::{CommandResultRequest, CreateDeviceCommandRequest},
+    models::TenantContext,
+    utils::error::AppError,
+};
+
+/// 设备指令列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListDeviceCommandsQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 创建设备指令处理器
+///
+/// POST /api/v1/devices/:device_id/commands
+pub async fn create_device_command(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Path(device_id): Path<String>,
+    Json(req): Json<CreateDeviceCommandRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_command_service
+        .for_tenant(&tenant)
+        .create_command(&device_id, req, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// 设备指令列表处理器
+///
+/// GET /api/v1/devices/:device_id/commands
+pub async fn list_device_commands(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(device_id): Path<String>,
+    Query(query): Query<ListDeviceCommandsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let response = state
+        .device_command_service
+        .for_tenant(&tenant)
+        .list_commands(&device_id, page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取设备指令处理器
+///
+/// GET /api/v1/commands/:command_id
+pub async fn get_device_command(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(command_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_command_service
+        .for_tenant(&tenant)
+        .get_command(&command_id)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 取消设备指令处理器
+///
+/// POST /api/v1/commands/:command_id/cancel
+pub async fn cancel_device_command(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Path(command_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_command_service
+        .for_tenant(&tenant)
+        .cancel_command(&command_id, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 在线设备列表处理器
+///
+/// GET /api/v1/devices/connected
+pub async fn list_connected_devices(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_command_service
+        .for_tenant(&tenant)
+        .list_connected_devices()
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 设备轮询指令处理器（设备端调用）
+///
+/// GET /api/v1/devices/:device_id/commands/poll
+pub async fn poll_device_commands(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    credentials: DeviceCredentials,
+) -> Result<impl IntoResponse, AppError> {
+    authenticate(&state, &device_id, &credentials).await?;
+
+    let commands = state.device_command_service.poll_commands(&device_id).await?;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "commands": commands }))))
+}
+
+/// 设备确认指令处理器（设备端调用）
+///
+/// POST /api/v1/devices/:device_id/commands/:command_id/ack
+pub async fn acknowledge_device_command(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, command_id)): Path<(String, String)>,
+    credentials: DeviceCredentials,
+) -> Result<impl IntoResponse, AppError> {
+    authenticate(&state, &device_id, &credentials).await?;
+
+    state.device_command_service.acknowledge(&device_id, &command_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 设备上报指令结果处理器（设备端调用）
+///
+/// POST /api/v1/devices/:device_id/commands/:command_id/result
+pub async fn report_device_command_result(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, command_id)): Path<(String, String)>,
+    credentials: DeviceCredentials,
+    Json(req): Json<CommandResultRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    authenticate(&state, &device_id, &credentials).await?;
+
+    let response = state.device_command_service.submit_result(&device_id, &command_id, req).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 校验设备签名凭证
+async fn authenticate(
+    state: &AppState,
+    device_id: &str,
+    credentials: &DeviceCredentials,
+) -> Result<(), AppError> {
+    state
+        .device_service
+        .authenticate_device(device_id, credentials)
+        .await
+        .map(|_| ())
+}
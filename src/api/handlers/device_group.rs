@@ -0,0 +1,164 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Extension,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    api::AppState,
+    dto::request::{CreateDeviceGroupRequest, GroupMembersRequest, UpdateDeviceGroupRequest},
+    models::TenantContext,
+    utils::error::AppError,
+};
+
+/// 设备分组列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListDeviceGroupsQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 创建设备分组处理器
+///
+/// POST /api/v1/device-groups
+pub async fn create_device_group(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<CreateDeviceGroupRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_group_service
+        .for_tenant(&tenant)
+        .create_group(req, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// 设备分组列表处理器
+///
+/// GET /api/v1/device-groups
+pub async fn list_device_groups(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Query(query): Query<ListDeviceGroupsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let response = state
+        .device_group_service
+        .for_tenant(&tenant)
+        .list_groups(page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取设备分组详情处理器
+///
+/// GET /api/v1/device-groups/:group_id
+pub async fn get_device_group(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(group_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.device_group_service.for_tenant(&tenant).get_group(&group_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 更新设备分组处理器
+///
+/// PUT /api/v1/device-groups/:group_id
+pub async fn update_device_group(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(group_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<UpdateDeviceGroupRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_group_service
+        .for_tenant(&tenant)
+        .update_group(&group_id, req, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 删除设备分组处理器
+///
+/// DELETE /api/v1/device-groups/:group_id
+pub async fn delete_device_group(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(group_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .device_group_service
+        .for_tenant(&tenant)
+        .delete_group(&group_id, &claims.sub)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 添加静态分组成员处理器
+///
+/// POST /api/v1/device-groups/:group_id/members
+pub async fn add_device_group_members(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(group_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<GroupMembersRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .device_group_service
+        .for_tenant(&tenant)
+        .add_members(&group_id, req, &claims.sub)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 移除静态分组成员处理器
+///
+/// DELETE /api/v1/device-groups/:group_id/members/:device_id
+pub async fn remove_device_group_member(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path((group_id, device_id)): Path<(String, String)>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .device_group_service
+        .for_tenant(&tenant)
+        .remove_member(&group_id, &device_id, &claims.sub)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 解析分组设备处理器
+///
+/// GET /api/v1/device-groups/:group_id/devices
+pub async fn list_device_group_devices(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(group_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .device_group_service
+        .for_tenant(&tenant)
+        .list_group_devices(&group_id)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, Response, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    api::AppState,
+    models::{ImportFormat, TenantContext},
+    utils::error::AppError,
+};
+
+/// 导入任务列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListImportJobsQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 提交设备批量导入
+///
+/// POST /api/v1/devices/imports
+///
+/// 请求体为CSV（Content-Type: text/csv）或JSON数组（Content-Type: application/json）
+pub async fn create_device_import(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, AppError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    let format = if content_type.contains("csv") {
+        ImportFormat::Csv
+    } else if content_type.contains("json") {
+        ImportFormat::Json
+    } else {
+        return Err(AppError::BadRequest(
+            "Content-Type must be text/csv or application/json".to_string(),
+        ));
+    };
+
+    let response = state
+        .device_import_service
+        .for_tenant(&tenant)
+        .start_import(format, body, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::ACCEPTED, Json(response)))
+}
+
+/// 导入任务列表
+///
+/// GET /api/v1/devices/imports
+pub async fn list_device_imports(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Query(query): Query<ListImportJobsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let response = state
+        .device_import_service
+        .for_tenant(&tenant)
+        .list_jobs(page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取导入任务详情
+///
+/// GET /api/v1/devices/imports/:job_id
+pub async fn get_device_import(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(job_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.device_import_service.for_tenant(&tenant).get_job(&job_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 下载导入错误报告
+///
+/// GET /api/v1/devices/imports/:job_id/report
+pub async fn download_device_import_report(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(job_id): Path<String>,
+) -> Result<Response<Body>, AppError> {
+    let report = state.device_import_service.for_tenant(&tenant).get_report(&job_id).await?;
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/csv; charset=utf-8")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"device-import-{}.csv\"", job_id),
+        )
+        .body(Body::from(report))
+        .map_err(|e| AppError::InternalWithMessage(format!("Failed to build response: {}", e)))?;
+
+    Ok(response)
+}
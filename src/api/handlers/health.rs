@@ -13,6 +13,7 @@ use crate::{
         request::HealthCheckRequest,
         response::{HealthCheckResponse, HealthOverviewResponse},
     },
+    models::TenantContext,
     utils::error::AppError,
 };
 
@@ -31,6 +32,7 @@ pub struct ListHealthChecksQuery {
 /// POST /api/v1/health/submit
 pub async fn submit_health_check(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<HealthCheckRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -40,26 +42,29 @@ pub async fn submit_health_check(
     let operator_id = claims.sub;
 
     // 调用服务层
-    let mut response = state.health_check_service.submit_health_check(req, &operator_id).await?;
+    let mut response = state
+        .health_check_service
+        .for_tenant(&tenant)
+        .submit_health_check(req, &operator_id)
+        .await?;
 
     // 如果安全评分合格（>=60），生成交易令牌
     if response.security_score >= 60 {
-        // 构建HealthCheck对象用于生成令牌
-        let health_check = crate::models::HealthCheck {
-            id: response.check_id.clone(),
-            device_id: device_id.clone(),
-            security_score: response.security_score,
-            root_status: false,
-            bootloader_status: false,
-            system_integrity: false,
-            app_integrity: false,
-            tee_status: false,
-            recommended_action: crate::models::RecommendedAction::None,
-            details: None,
-            created_at: response.checked_at.clone(),
+        // 使用刚保存的健康检查记录生成令牌
+        let token_result = match state
+            .health_check_service
+            .for_tenant(&tenant)
+            .get_latest_check(&device_id)
+            .await
+        {
+            Ok(Some(health_check)) => {
+                state.transaction_token_service.generate_token(&device_id, &health_check).await
+            }
+            Ok(None) => Err(AppError::HealthCheckRequired("NO_HEALTH_CHECK")),
+            Err(e) => Err(e),
         };
 
-        match state.transaction_token_service.generate_token(&device_id, &health_check).await {
+        match token_result {
             Ok(token) => {
                 response.transaction_token = Some(token);
                 tracing::info!(
@@ -79,22 +84,6 @@ pub async fn submit_health_check(
         }
     }
 
-    // 发送通知（如果安全评分低于60）
-    if response.security_score < 60 {
-        let message = format!(
-            "设备 {} 安全评分降至 {}，建议操作：{:?}",
-            device_id, response.security_score, response.recommended_action
-        );
-        
-        // 异步发送通知，不阻塞响应
-        let notification_service = state.notification_service.clone();
-        tokio::spawn(async move {
-            notification_service
-                .send_security_alert(device_id, response.security_score, message)
-                .await;
-        });
-    }
-
     Ok((StatusCode::OK, Json(response)))
 }
 
@@ -103,11 +92,13 @@ pub async fn submit_health_check(
 /// GET /api/v1/health/checks
 pub async fn list_health_checks(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListHealthChecksQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response = state
         .health_check_service
+        .for_tenant(&tenant)
         .list_health_checks(
             query.device_id.as_deref(),
             query.start_time.as_deref(),
@@ -127,11 +118,13 @@ pub async fn list_health_checks(
 /// GET /api/v1/health/:device_id/overview
 pub async fn get_health_overview(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response = state
         .health_check_service
+        .for_tenant(&tenant)
         .get_health_overview(&device_id)
         .await?;
 
@@ -143,11 +136,13 @@ pub async fn get_health_overview(
 /// POST /api/v1/health/:device_id/initial-check
 pub async fn perform_initial_check(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response = state
         .health_check_service
+        .for_tenant(&tenant)
         .perform_initial_check(&device_id)
         .await?;
 
@@ -176,8 +171,17 @@ pub async fn health_check(State(state): State<Arc<AppState>>) -> Result<impl Int
         database: String,
         redis: String,
         hsm: String,
+        payment_processors: std::collections::HashMap<String, String>,
     }
 
+    let payment_processors = state
+        .payment_router
+        .health_check()
+        .await
+        .into_iter()
+        .map(|(name, healthy)| (name, if healthy { "ok" } else { "unavailable" }.to_string()))
+        .collect();
+
     let response = HealthCheckResponse {
         status: "healthy".to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
@@ -195,6 +199,7 @@ pub async fn health_check(State(state): State<Arc<AppState>>) -> Result<impl Int
                 "not_configured"
             }
             .to_string(),
+            payment_processors,
         },
     };
 
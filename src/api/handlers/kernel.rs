@@ -5,11 +5,11 @@ use axum::{
     extract::{Multipart, Path, Query, State},
     http::{header, Response, StatusCode},
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{api::AppState, utils::error::AppError};
+use crate::{api::AppState, models::TenantContext, utils::error::AppError};
 
 #[derive(Debug, Deserialize)]
 pub struct ListKernelsQuery {
@@ -28,6 +28,7 @@ pub struct UploadKernelResponse {
 /// POST /api/v1/kernels
 pub async fn upload_kernel(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     mut multipart: Multipart,
 ) -> Result<impl IntoResponse, AppError> {
     let mut file_bytes: Option<Vec<u8>> = None;
@@ -65,7 +66,7 @@ pub async fn upload_kernel(
         version.ok_or_else(|| AppError::BadRequest("Missing version field".to_string()))?;
     let filename = filename.unwrap_or_else(|| "kernel.wasm".to_string());
 
-    let kernel = state.kernel_service.upload_kernel(&version, file_bytes, &filename).await?;
+    let kernel = state.kernel_service.for_tenant(&tenant).upload_kernel(&version, file_bytes, &filename).await?;
 
     let response = UploadKernelResponse {
         id: kernel.id,
@@ -81,9 +82,10 @@ pub async fn upload_kernel(
 /// GET /api/v1/kernels
 pub async fn list_kernels(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListKernelsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let kernels = state.kernel_service.list_kernels(query.status.as_deref()).await?;
+    let kernels = state.kernel_service.for_tenant(&tenant).list_kernels(query.status.as_deref()).await?;
 
     Ok(Json(kernels))
 }
@@ -93,9 +95,10 @@ pub async fn list_kernels(
 /// GET /api/v1/kernels/:version
 pub async fn get_kernel(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(version): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
-    let kernel = state.kernel_service.get_kernel(&version).await?;
+    let kernel = state.kernel_service.for_tenant(&tenant).get_kernel(&version).await?;
 
     Ok(Json(kernel))
 }
@@ -105,9 +108,10 @@ pub async fn get_kernel(
 /// GET /api/v1/kernels/:version/download
 pub async fn download_kernel(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(version): Path<String>,
 ) -> Result<Response<Body>, AppError> {
-    let file_bytes = state.kernel_service.download_kernel(&version).await?;
+    let file_bytes = state.kernel_service.for_tenant(&tenant).download_kernel(&version).await?;
 
     let response = Response::builder()
         .status(StatusCode::OK)
@@ -125,9 +129,10 @@ pub async fn download_kernel(
 /// POST /api/v1/kernels/:version/publish
 pub async fn publish_kernel(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(version): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
-    state.kernel_service.publish_kernel(&version).await?;
+    state.kernel_service.for_tenant(&tenant).publish_kernel(&version).await?;
 
     Ok(StatusCode::OK)
 }
@@ -137,9 +142,10 @@ pub async fn publish_kernel(
 /// DELETE /api/v1/kernels/:version
 pub async fn delete_kernel(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(version): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
-    state.kernel_service.delete_kernel(&version).await?;
+    state.kernel_service.for_tenant(&tenant).delete_kernel(&version).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -13,6 +13,7 @@ use crate::{
         request::{EncryptPinRequest, InjectKeyRequest, UpdateKeyRequest},
         response::{InjectKeyResponse, KeyStatusResponse, UpdateKeyResponse},
     },
+    models::TenantContext,
     utils::error::AppError,
 };
 
@@ -21,6 +22,7 @@ use crate::{
 /// POST /api/v1/keys/inject
 pub async fn inject_key(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<InjectKeyRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -28,7 +30,7 @@ pub async fn inject_key(
     let operator_id = claims.sub;
 
     // 调用服务层
-    let response = state.key_management_service.inject_key(req, &operator_id).await?;
+    let response = state.key_management_service.for_tenant(&tenant).inject_key(req, &operator_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -54,10 +56,11 @@ pub async fn inject_key_public(
 /// GET /api/v1/keys/:device_id/status
 pub async fn get_key_status(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let response = state.key_management_service.get_key_status(&device_id).await?;
+    let response = state.key_management_service.for_tenant(&tenant).get_key_status(&device_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -67,6 +70,7 @@ pub async fn get_key_status(
 /// POST /api/v1/keys/:device_id/update
 pub async fn update_key(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<UpdateKeyRequest>,
@@ -75,7 +79,7 @@ pub async fn update_key(
     let operator_id = claims.sub;
 
     // 调用服务层
-    let response = state.key_management_service.update_key(req, &operator_id).await?;
+    let response = state.key_management_service.for_tenant(&tenant).update_key(req, &operator_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -85,6 +89,7 @@ pub async fn update_key(
 /// POST /api/v1/keys/encrypt-pin
 pub async fn encrypt_pin(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<EncryptPinRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -92,7 +97,7 @@ pub async fn encrypt_pin(
     let operator_id = claims.sub;
 
     // 调用服务层
-    let response = state.key_management_service.encrypt_pin(req, &operator_id).await?;
+    let response = state.key_management_service.for_tenant(&tenant).encrypt_pin(req, &operator_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -102,10 +107,11 @@ pub async fn encrypt_pin(
 /// GET /api/v1/keys/:device_id/check-update
 pub async fn check_key_update_needed(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let needs_update = state.key_management_service.check_key_update_needed(&device_id).await?;
+    let needs_update = state.key_management_service.for_tenant(&tenant).check_key_update_needed(&device_id).await?;
 
     #[derive(serde::Serialize)]
     struct CheckUpdateResponse {
@@ -126,9 +132,10 @@ pub async fn check_key_update_needed(
 /// GET /api/v1/keys/devices-needing-update
 pub async fn get_devices_needing_key_update(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let device_ids = state.key_management_service.get_devices_needing_key_update().await?;
+    let device_ids = state.key_management_service.for_tenant(&tenant).get_devices_needing_key_update().await?;
 
     #[derive(serde::Serialize)]
     struct DevicesNeedingUpdateResponse {
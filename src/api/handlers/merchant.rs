@@ -0,0 +1,172 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Extension,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    api::AppState,
+    dto::request::{
+        AssignDeviceRequest, CreateMerchantRequest, CreateStoreRequest, UpdateMerchantRequest,
+        UpdateStoreRequest,
+    },
+    models::{MerchantStatus, TenantContext},
+    utils::error::AppError,
+};
+
+/// 商户列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListMerchantsQuery {
+    pub status: Option<MerchantStatus>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 创建商户处理器
+///
+/// POST /api/v1/merchants
+pub async fn create_merchant(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<CreateMerchantRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.merchant_service.for_tenant(&tenant).create_merchant(req, &claims.sub).await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// 商户列表处理器
+///
+/// GET /api/v1/merchants
+pub async fn list_merchants(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Query(query): Query<ListMerchantsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let response = state
+        .merchant_service
+        .for_tenant(&tenant)
+        .list_merchants(query.status, page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取商户详情处理器
+///
+/// GET /api/v1/merchants/:merchant_id
+pub async fn get_merchant(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(merchant_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.merchant_service.for_tenant(&tenant).get_merchant(&merchant_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 更新商户处理器
+///
+/// PUT /api/v1/merchants/:merchant_id
+pub async fn update_merchant(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(merchant_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<UpdateMerchantRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.merchant_service.for_tenant(&tenant).update_merchant(&merchant_id, req, &claims.sub).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 创建门店处理器
+///
+/// POST /api/v1/merchants/:merchant_id/stores
+pub async fn create_store(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(merchant_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<CreateStoreRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.merchant_service.for_tenant(&tenant).create_store(&merchant_id, req, &claims.sub).await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// 商户门店列表处理器
+///
+/// GET /api/v1/merchants/:merchant_id/stores
+pub async fn list_stores(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(merchant_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.merchant_service.for_tenant(&tenant).list_stores(&merchant_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取门店详情处理器
+///
+/// GET /api/v1/stores/:store_id
+pub async fn get_store(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(store_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.merchant_service.for_tenant(&tenant).get_store(&store_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 更新门店处理器
+///
+/// PUT /api/v1/stores/:store_id
+pub async fn update_store(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(store_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<UpdateStoreRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.merchant_service.for_tenant(&tenant).update_store(&store_id, req, &claims.sub).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 门店设备列表处理器
+///
+/// GET /api/v1/stores/:store_id/devices
+pub async fn list_store_devices(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(store_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let devices = state.merchant_service.for_tenant(&tenant).list_store_devices(&store_id).await?;
+
+    Ok((StatusCode::OK, Json(devices)))
+}
+
+/// 分配设备到门店处理器
+///
+/// POST /api/v1/devices/:device_id/assign
+pub async fn assign_device(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(device_id): Path<String>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<AssignDeviceRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.merchant_service.for_tenant(&tenant).assign_device(&device_id, req, &claims.sub).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -2,14 +2,23 @@ pub mod audit;
 pub mod auth;
 pub mod dashboard;
 pub mod device;
+pub mod device_command;
+pub mod device_group;
+pub mod device_import;
 pub mod health;
 pub mod kernel;
 pub mod key;
+pub mod merchant;
+pub mod notification;
 pub mod pinpad;
+pub mod reconciliation;
+pub mod settlement;
+pub mod tenant;
 pub mod threat;
 pub mod transaction;
 pub mod upload;
 pub mod version;
+pub mod webhook;
 
 pub use audit::{
     export_logs, get_audit_statistics, get_device_logs, get_log, get_operator_logs, list_logs,
@@ -17,8 +26,21 @@ pub use audit::{
 pub use auth::{get_current_user, login, logout, refresh_token, verify_token};
 pub use dashboard::get_health_overview as get_dashboard_health_overview;
 pub use device::{
-    approve_device, get_device, get_device_statistics, list_devices, register_device,
-    reject_device, resume_device, revoke_device, suspend_device,
+    approve_device, decommission_device, get_device, get_device_statistics, get_device_tags,
+    get_device_timeline, list_devices, register_device, reject_device, replace_device,
+    resume_device, revoke_device, set_device_tags, suspend_device,
+};
+pub use device_command::{
+    acknowledge_device_command, cancel_device_command, create_device_command, get_device_command,
+    list_connected_devices, list_device_commands, poll_device_commands,
+    report_device_command_result,
+};
+pub use device_group::{
+    add_device_group_members, create_device_group, delete_device_group, get_device_group,
+    list_device_group_devices, list_device_groups, remove_device_group_member, update_device_group,
+};
+pub use device_import::{
+    create_device_import, download_device_import_report, get_device_import, list_device_imports,
 };
 pub use health::{
     get_health_overview, get_health_statistics, health_check, list_health_checks,
@@ -40,17 +62,34 @@ pub use key::{
     check_key_update_needed, encrypt_pin, get_devices_needing_key_update, get_key_status,
     inject_key, inject_key_public, update_key,
 };
+pub use merchant::{
+    assign_device, create_merchant, create_store, get_merchant, get_store, list_merchants,
+    list_store_devices, list_stores, update_merchant, update_store,
+};
+pub use notification::{acknowledge_notification, acknowledge_notifications, list_notifications};
 pub use pinpad::{
     attest_pinpad, get_device_pin_statistics, get_pinpad_device_status, list_pin_encryption_logs,
 };
+pub use reconciliation::{
+    create_reconciliation_run, download_reconciliation_report, get_reconciliation_run,
+    list_reconciliation_items, list_reconciliation_runs,
+};
+pub use settlement::{
+    close_device_batch, get_settlement_batch, list_settlement_batches, reopen_settlement_batch,
+};
+pub use tenant::{
+    create_api_key, create_tenant, get_tenant, get_tenant_branding, list_api_keys, list_tenants,
+    revoke_api_key, update_tenant,
+};
 pub use threat::{
     get_device_threat_history, get_threat, get_threat_statistics, list_threats, report_threat,
     resolve_threat,
 };
 pub use transaction::{
     attest_transaction, attest_transaction_public, get_device_transaction_history, get_transaction,
-    get_transaction_statistics, list_transactions, process_transaction, process_transaction_public,
-    request_transaction_token, verify_transaction_token,
+    get_transaction_events, get_transaction_statistics, list_transactions, process_transaction,
+    process_transaction_public, request_transaction_token, reverse_device_transaction,
+    reverse_transaction, verify_transaction_token,
 };
 pub use upload::*;
 pub use version::{
@@ -58,3 +97,7 @@ pub use version::{
     get_outdated_devices, get_push_task, get_update_dashboard, get_version, get_version_statistics,
     list_push_tasks, list_versions, update_version,
 };
+pub use webhook::{
+    create_webhook, delete_webhook, get_webhook, get_webhook_delivery, list_webhook_deliveries,
+    list_webhooks, redeliver_webhook, update_webhook,
+};
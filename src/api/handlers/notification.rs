@@ -0,0 +1,79 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Extension,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    api::AppState, dto::request::AckNotificationsRequest, models::TenantContext,
+    utils::error::AppError,
+};
+
+/// 历史通知查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListNotificationsQuery {
+    /// 返回序列号小于该值的通知，用于向前翻页
+    pub before_seq: Option<i64>,
+    /// 返回序列号大于该值的通知
+    pub after_seq: Option<i64>,
+    pub unread_only: Option<bool>,
+    pub limit: Option<i64>,
+}
+
+/// 历史通知列表处理器
+///
+/// GET /api/v1/notifications
+pub async fn list_notifications(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Query(query): Query<ListNotificationsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .notification_service
+        .list_history(
+            &tenant,
+            &claims.sub,
+            query.before_seq,
+            query.after_seq,
+            query.unread_only.unwrap_or(false),
+            query.limit.unwrap_or(20).clamp(1, 100),
+        )
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 标记通知已读处理器
+///
+/// POST /api/v1/notifications/:seq/ack
+pub async fn acknowledge_notification(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Path(seq): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    state.notification_service.acknowledge(&tenant, &claims.sub, seq).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 批量标记通知已读处理器
+///
+/// POST /api/v1/notifications/ack
+pub async fn acknowledge_notifications(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<AckNotificationsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let acknowledged = state
+        .notification_service
+        .acknowledge_up_to(&tenant, &claims.sub, req.up_to_seq)
+        .await?;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "acknowledged": acknowledged }))))
+}
@@ -10,6 +10,7 @@ use std::sync::Arc;
 use crate::{
     api::AppState,
     dto::request::{AttestPinpadRequest, EncryptPinRequest},
+    models::TenantContext,
     utils::error::AppError,
 };
 
@@ -28,10 +29,11 @@ pub struct ListPinEncryptionLogsQuery {
 /// POST /api/v1/pinpad/attest
 pub async fn attest_pinpad(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Json(req): Json<AttestPinpadRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用设备服务的PINPad鉴证方法
-    let response = state.device_service.attest_pinpad_device(req).await?;
+    let response = state.device_service.for_tenant(&tenant).attest_pinpad_device(req).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -41,6 +43,7 @@ pub async fn attest_pinpad(
 /// POST /api/v1/pinpad/encrypt
 pub async fn encrypt_pin(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<EncryptPinRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -48,7 +51,7 @@ pub async fn encrypt_pin(
     let operator_id = claims.sub;
 
     // 调用密钥管理服务的PIN加密方法
-    let response = state.key_management_service.encrypt_pin(req, &operator_id).await?;
+    let response = state.key_management_service.for_tenant(&tenant).encrypt_pin(req, &operator_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -58,11 +61,13 @@ pub async fn encrypt_pin(
 /// GET /api/v1/pinpad/logs
 pub async fn list_pin_encryption_logs(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListPinEncryptionLogsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 从审计日志中获取PIN加密相关的日志
     let response = state
         .audit_service
+        .for_tenant(&tenant)
         .list_logs(
             Some("PIN_ENCRYPTION"),
             None,
@@ -112,10 +117,11 @@ pub async fn get_device_pin_statistics(
 /// GET /api/v1/pinpad/device/:device_id/status
 pub async fn get_pinpad_device_status(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 获取设备信息并验证是否为PINPad模式
-    let device = state.device_service.get_device(&device_id).await?;
+    let device = state.device_service.for_tenant(&tenant).get_device(&device_id).await?;
 
     #[derive(serde::Serialize)]
     struct PinpadStatusResponse {
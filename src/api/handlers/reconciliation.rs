@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, Response, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    api::AppState,
+    models::{ReconciliationCategory, TenantContext},
+    utils::error::AppError,
+};
+
+/// 导入清算文件的查询参数
+#[derive(Debug, Deserialize)]
+pub struct CreateReconciliationRunQuery {
+    /// 清算文件格式名称（配置项 `reconciliation.layouts`）
+    pub layout: String,
+    pub file_name: Option<String>,
+    /// 只对账该商户的交易
+    pub merchant_id: Option<String>,
+}
+
+/// 对账任务列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListReconciliationRunsQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 对账结果明细查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListReconciliationItemsQuery {
+    pub category: Option<ReconciliationCategory>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 导入清算文件并对账
+///
+/// POST /api/v1/reconciliation/runs?layout=acquirer_csv
+///
+/// 请求体为清算文件原文
+pub async fn create_reconciliation_run(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Query(query): Query<CreateReconciliationRunQuery>,
+    body: String,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .reconciliation_service
+        .for_tenant(&tenant)
+        .run_reconciliation(&query.layout, &body, query.file_name, query.merchant_id, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// 对账任务列表
+///
+/// GET /api/v1/reconciliation/runs
+pub async fn list_reconciliation_runs(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Query(query): Query<ListReconciliationRunsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let response = state
+        .reconciliation_service
+        .for_tenant(&tenant)
+        .list_runs(page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取对账任务详情
+///
+/// GET /api/v1/reconciliation/runs/:run_id
+pub async fn get_reconciliation_run(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(run_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.reconciliation_service.for_tenant(&tenant).get_run(&run_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 对账结果明细
+///
+/// GET /api/v1/reconciliation/runs/:run_id/items
+pub async fn list_reconciliation_items(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(run_id): Path<String>,
+    Query(query): Query<ListReconciliationItemsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(50).clamp(1, 500);
+
+    let response = state
+        .reconciliation_service
+        .for_tenant(&tenant)
+        .list_items(&run_id, query.category, page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 下载对账差异报告
+///
+/// GET /api/v1/reconciliation/runs/:run_id/report
+pub async fn download_reconciliation_report(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(run_id): Path<String>,
+) -> Result<Response<Body>, AppError> {
+    let report = state
+        .reconciliation_service
+        .for_tenant(&tenant)
+        .get_exception_report(&run_id)
+        .await?;
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/csv; charset=utf-8")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"reconciliation-{}.csv\"", run_id),
+        )
+        .body(Body::from(report))
+        .map_err(|e| AppError::InternalWithMessage(format!("Failed to build response: {}", e)))?;
+
+    Ok(response)
+}
@@ -0,0 +1,97 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Extension,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    api::{middleware::DeviceCredentials, AppState},
+    models::{BatchStatus, TenantContext},
+    utils::error::AppError,
+};
+
+/// 结算批次列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListSettlementBatchesQuery {
+    pub device_id: Option<String>,
+    pub status: Option<BatchStatus>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 设备结算处理器（设备端调用，使用设备签名认证）
+///
+/// 关闭设备的开放批次并立即提交结算
+///
+/// POST /api/v1/devices/:device_id/settlement/close
+pub async fn close_device_batch(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    credentials: DeviceCredentials,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .device_service
+        .authenticate_device(&device_id, &credentials)
+        .await?;
+
+    let batches = state
+        .settlement_service
+        .close_device_batches(&device_id, &format!("device:{}", device_id))
+        .await?;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "batches": batches }))))
+}
+
+/// 结算批次列表处理器
+///
+/// GET /api/v1/settlement/batches
+pub async fn list_settlement_batches(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Query(query): Query<ListSettlementBatchesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let response = state
+        .settlement_service
+        .for_tenant(&tenant)
+        .list_batches(query.device_id.as_deref(), query.status, page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取结算批次详情处理器
+///
+/// GET /api/v1/settlement/batches/:batch_id
+pub async fn get_settlement_batch(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(batch_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.settlement_service.for_tenant(&tenant).get_batch(&batch_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 重新开放失败的结算批次处理器
+///
+/// POST /api/v1/settlement/batches/:batch_id/reopen
+pub async fn reopen_settlement_batch(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Path(batch_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .settlement_service
+        .for_tenant(&tenant)
+        .reopen_batch(&batch_id, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
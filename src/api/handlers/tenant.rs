@@ -0,0 +1,159 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Extension,
+};
+use std::sync::Arc;
+
+use crate::{
+    api::AppState,
+    dto::request::{CreateApiKeyRequest, CreateTenantRequest, UpdateTenantRequest},
+    models::TenantContext,
+    security::jwt::Claims,
+    utils::error::AppError,
+};
+
+/// 创建租户处理器（仅超级管理员）
+///
+/// POST /api/v1/tenants
+pub async fn create_tenant(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateTenantRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_super_admin(&claims)?;
+
+    let response = state.tenant_service.create_tenant(req, &claims.sub).await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// 租户列表处理器（仅超级管理员）
+///
+/// GET /api/v1/tenants
+pub async fn list_tenants(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    require_super_admin(&claims)?;
+
+    let response = state.tenant_service.list_tenants().await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取租户详情处理器
+///
+/// GET /api/v1/tenants/:tenant_id
+pub async fn get_tenant(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(tenant_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_tenant_access(&claims, &tenant_id)?;
+
+    let response = state.tenant_service.get_tenant(&tenant_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 更新租户处理器（仅超级管理员）
+///
+/// PUT /api/v1/tenants/:tenant_id
+pub async fn update_tenant(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(tenant_id): Path<String>,
+    Json(req): Json<UpdateTenantRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_super_admin(&claims)?;
+
+    let response = state.tenant_service.update_tenant(&tenant_id, req, &claims.sub).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 创建API密钥处理器
+///
+/// POST /api/v1/tenants/:tenant_id/api-keys
+pub async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(tenant_id): Path<String>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_tenant_admin(&claims, &tenant_id)?;
+
+    let response = state.tenant_service.create_api_key(&tenant_id, req, &claims.sub).await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// API密钥列表处理器
+///
+/// GET /api/v1/tenants/:tenant_id/api-keys
+pub async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(tenant_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_tenant_admin(&claims, &tenant_id)?;
+
+    let response = state.tenant_service.list_api_keys(&tenant_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 吊销API密钥处理器
+///
+/// DELETE /api/v1/tenants/:tenant_id/api-keys/:key_id
+pub async fn revoke_api_key(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path((tenant_id, key_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    require_tenant_admin(&claims, &tenant_id)?;
+
+    state.tenant_service.revoke_api_key(&tenant_id, &key_id, &claims.sub).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 获取当前租户品牌配置处理器
+///
+/// GET /api/v1/tenant/branding
+pub async fn get_tenant_branding(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.tenant_service.get_branding(&tenant.tenant_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+fn require_super_admin(claims: &Claims) -> Result<(), AppError> {
+    if claims.is_super_admin() {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("Super admin role required".to_string()))
+    }
+}
+
+fn require_tenant_access(claims: &Claims, tenant_id: &str) -> Result<(), AppError> {
+    if claims.is_super_admin() || claims.tenant_id == tenant_id {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("Access to other tenants is not allowed".to_string()))
+    }
+}
+
+fn require_tenant_admin(claims: &Claims, tenant_id: &str) -> Result<(), AppError> {
+    require_tenant_access(claims, tenant_id)?;
+
+    if claims.is_super_admin() || claims.role == "admin" {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("Admin role required".to_string()))
+    }
+}
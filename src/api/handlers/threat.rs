@@ -9,7 +9,7 @@ use std::sync::Arc;
 
 use crate::{
     api::{middleware::extract_user_id, AppState},
-    models::{ThreatSeverity, ThreatStatus, ThreatType},
+    models::{ThreatSeverity, ThreatStatus, ThreatType, TenantContext},
     utils::error::AppError,
 };
 
@@ -37,11 +37,13 @@ pub struct ResolveThreatRequest {
 /// GET /api/v1/threats
 pub async fn list_threats(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListThreatsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response_data = state
         .threat_detection_service
+        .for_tenant(&tenant)
         .list_threats(
             query.device_id.as_deref(),
             query.status,
@@ -66,6 +68,7 @@ pub async fn list_threats(
 /// GET /api/v1/threats/:threat_id
 pub async fn get_threat(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(threat_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层获取威胁详情
@@ -73,6 +76,7 @@ pub async fn get_threat(
     // 简化实现，实际应该有专门的get_threat方法
     let threats = state
         .threat_detection_service
+        .for_tenant(&tenant)
         .list_threats(None, None, None, None, 1, 1000)
         .await?;
 
@@ -90,6 +94,7 @@ pub async fn get_threat(
 /// POST /api/v1/threats/:threat_id/resolve
 pub async fn resolve_threat(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(threat_id): Path<String>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<ResolveThreatRequest>,
@@ -100,6 +105,7 @@ pub async fn resolve_threat(
     // 调用服务层
     state
         .threat_detection_service
+        .for_tenant(&tenant)
         .resolve_threat(&threat_id, &operator_id, req.resolution_notes)
         .await?;
 
@@ -122,10 +128,12 @@ pub async fn resolve_threat(
 /// GET /api/v1/threats/statistics
 pub async fn get_threat_statistics(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let stats = state
         .threat_detection_service
+        .for_tenant(&tenant)
         .get_threat_statistics()
         .await?;
 
@@ -137,12 +145,14 @@ pub async fn get_threat_statistics(
 /// GET /api/v1/threats/device/:device_id/history
 pub async fn get_device_threat_history(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     Query(query): Query<ListThreatsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层，只获取特定设备的威胁
     let response = state
         .threat_detection_service
+        .for_tenant(&tenant)
         .list_threats(
             Some(&device_id),
             query.status,
@@ -1,22 +1,31 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::{IntoResponse, Json},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
     Extension,
 };
 use serde::Deserialize;
 use std::sync::Arc;
 
 use crate::{
-    api::AppState,
+    api::{middleware::DeviceCredentials, AppState},
     dto::{
-        request::{AttestTransactionRequest, ProcessTransactionRequest},
-        response::{AttestTransactionResponse, ProcessTransactionResponse},
+        request::{
+            AttestTransactionRequest, ProcessTransactionRequest, ReverseTransactionRequest,
+        },
+        response::AttestTransactionResponse,
     },
-    models::TransactionStatus,
+    models::{TransactionStatus, TenantContext},
+    services::{IdempotentOutcome, TransactionService},
     utils::error::AppError,
 };
 
+/// 幂等键请求头
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// 重放响应标记头
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
 /// 交易列表查询参数
 #[derive(Debug, Deserialize)]
 pub struct ListTransactionsQuery {
@@ -33,55 +42,24 @@ pub struct ListTransactionsQuery {
 /// POST /api/v1/transactions/request-token
 pub async fn request_transaction_token(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<RequestTransactionTokenRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     // 验证设备存在
     let device = state
         .device_service
+        .for_tenant(&tenant)
         .get_device(&req.device_id)
         .await?;
 
-    // 获取最新的健康检查记录
-    let health_checks = state
+    // 获取最新的健康检查记录，过期或未通过时由令牌服务要求重新检查
+    let health_check = state
         .health_check_service
-        .list_health_checks(Some(&req.device_id), None, None, 1, 1)
-        .await?;
-
-    if health_checks.checks.is_empty() {
-        return Err(AppError::BadRequest(
-            "No health check found for device. Please perform health check first.".to_string()
-        ));
-    }
-
-    let latest_check = &health_checks.checks[0];
-
-    // 检查健康检查是否过期（5分钟内有效）
-    let check_time = chrono::DateTime::parse_from_rfc3339(&latest_check.checked_at)
-        .map_err(|e| AppError::InternalWithMessage(format!("Invalid timestamp: {}", e)))?;
-    let now = chrono::Utc::now();
-    let age = now.signed_duration_since(check_time.with_timezone(&chrono::Utc));
-
-    if age.num_seconds() > 300 {
-        return Err(AppError::BadRequest(
-            "Health check expired. Please perform a new health check.".to_string()
-        ));
-    }
-
-    // 构建HealthCheck对象
-    let health_check = crate::models::HealthCheck {
-        id: latest_check.check_id.clone(),
-        device_id: req.device_id.clone(),
-        security_score: latest_check.security_score,
-        root_status: false,
-        bootloader_status: false,
-        system_integrity: false,
-        app_integrity: false,
-        tee_status: false,
-        recommended_action: crate::models::RecommendedAction::None,
-        details: None,
-        created_at: latest_check.checked_at.clone(),
-    };
+        .for_tenant(&tenant)
+        .get_latest_check(&req.device_id)
+        .await?
+        .ok_or(AppError::HealthCheckRequired("NO_HEALTH_CHECK"))?;
 
     // 生成交易令牌
     let token = state
@@ -138,6 +116,7 @@ pub async fn verify_transaction_token(
 /// POST /api/v1/transactions/attest
 pub async fn attest_transaction(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<AttestTransactionRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -145,7 +124,7 @@ pub async fn attest_transaction(
     let operator_id = claims.sub;
 
     // 调用服务层
-    let response = state.transaction_service.attest_transaction(req, &operator_id).await?;
+    let response = state.transaction_service.for_tenant(&tenant).attest_transaction(req, &operator_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -184,100 +163,204 @@ pub struct VerifyTransactionTokenRequest {
 /// POST /api/v1/transactions/process
 pub async fn process_transaction(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
+    headers: HeaderMap,
     Json(req): Json<ProcessTransactionRequest>,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<Response, AppError> {
     // 提取操作员ID
     let operator_id = claims.sub;
 
-    // 1. 验证交易令牌
-    let token_claims = state
-        .transaction_token_service
-        .verify_token(&req.transaction_token, &req.device_id)
-        .await?;
+    let service = state.transaction_service.for_tenant(&tenant);
+    process_idempotent(&state, &headers, &service, req, &operator_id).await
+}
 
-    tracing::info!(
-        "Transaction token verified for device {}, jti: {}",
-        req.device_id,
-        token_claims.jti
-    );
+/// 交易处理处理器（公开，设备端使用）
+///
+/// POST /api/v1/transactions/process
+pub async fn process_transaction_public(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<ProcessTransactionRequest>,
+) -> Result<Response, AppError> {
+    // 设备端调用，使用设备ID作为操作员ID
+    let operator_id = format!("device:{}", req.device_id);
 
-    // 2. 验证交易金额不超过令牌允许的最大金额
-    if req.amount > token_claims.max_amount {
-        return Err(AppError::BadRequest(format!(
-            "Transaction amount {} exceeds maximum allowed amount {} for security score {}",
-            req.amount, token_claims.max_amount, token_claims.security_score
-        )));
+    process_idempotent(&state, &headers, &state.transaction_service, req, &operator_id).await
+}
+
+/// 幂等处理交易
+///
+/// 携带幂等键时先领取幂等键：重复请求直接返回首次处理的响应（不再校验已使用的交易令牌）。
+/// 校验失败时尚未产生副作用，释放幂等键以便修正后重试；占用令牌后的结果（包括错误）
+/// 均保存为首次处理的响应，重试时原样返回，不会重复提交处理器
+async fn process_idempotent(
+    state: &AppState,
+    headers: &HeaderMap,
+    service: &TransactionService,
+    mut req: ProcessTransactionRequest,
+    operator_id: &str,
+) -> Result<Response, AppError> {
+    let Some(key) = idempotency_key(headers, &req)? else {
+        let response = service.process_transaction(req, operator_id).await?;
+        return Ok((StatusCode::OK, Json(response)).into_response());
+    };
+
+    let device_id = req.device_id.clone();
+    if let IdempotentOutcome::Replay { status, body } =
+        state.idempotency_service.begin(&device_id, &key, &req).await?
+    {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+        return Ok(json_body_response(status, body, true));
     }
 
-    // 3. 处理交易
-    let response = state.transaction_service.process_transaction(req, &operator_id).await?;
+    // 交易记录保存幂等键，设备丢失应答时可据此申请冲正
+    req.client_transaction_id = Some(key.clone());
+
+    let prepared = match service.prepare_transaction(req).await {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            if let Err(release_err) = state.idempotency_service.release(&device_id, &key).await {
+                tracing::warn!("Failed to release idempotency key {}: {}", key, release_err);
+            }
+            return Err(e);
+        },
+    };
+    let transaction_id = prepared.transaction_id().to_string();
+
+    let (status, body) = match service.execute_transaction(prepared, operator_id).await {
+        Ok(response) => {
+            let body = serde_json::to_string(&response).map_err(|e| {
+                AppError::InternalWithMessage(format!("Failed to serialize response: {}", e))
+            })?;
+            (StatusCode::OK, body)
+        },
+        Err(e) => {
+            let response = e.into_response();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map_err(|e| {
+                    AppError::InternalWithMessage(format!("Failed to read error response: {}", e))
+                })?;
+            (status, String::from_utf8_lossy(&body).into_owned())
+        },
+    };
 
-    // 4. 标记令牌已使用
     if let Err(e) = state
-        .transaction_token_service
-        .mark_token_used(&token_claims, &response.transaction_id)
+        .idempotency_service
+        .complete(&device_id, &key, status.as_u16(), &body, Some(&transaction_id))
         .await
     {
         tracing::warn!(
-            "Failed to mark transaction token as used: {}. Transaction {} completed successfully.",
+            "Failed to store idempotent response for key {}: {}. Transaction {} finished with status {}.",
+            key,
             e,
-            response.transaction_id
+            transaction_id,
+            status
         );
-        // 不阻塞交易响应，只记录警告
     }
 
-    Ok((StatusCode::OK, Json(response)))
+    Ok(json_body_response(status, body, false))
 }
 
-/// 交易处理处理器（公开，设备端使用）
+/// 设备申请冲正处理器（设备端调用，使用设备签名认证）
 ///
-/// POST /api/v1/transactions/process
-pub async fn process_transaction_public(
+/// POST /api/v1/devices/:device_id/transactions/reverse
+pub async fn reverse_device_transaction(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ProcessTransactionRequest>,
+    Path(device_id): Path<String>,
+    credentials: DeviceCredentials,
+    Json(req): Json<ReverseTransactionRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    // 设备端调用，使用设备ID作为操作员ID
-    let operator_id = format!("device:{}", req.device_id);
+    state
+        .device_service
+        .authenticate_device(&device_id, &credentials)
+        .await?;
 
-    // 1. 验证交易令牌
-    let token_claims = state
-        .transaction_token_service
-        .verify_token(&req.transaction_token, &req.device_id)
+    // 原请求仍在处理中时无法判断结果，由设备稍后重试
+    if let Some(client_transaction_id) = &req.client_transaction_id {
+        if state.idempotency_service.is_in_progress(&device_id, client_transaction_id).await? {
+            return Err(AppError::RequestInProgress);
+        }
+    }
+
+    let response = state.transaction_service.request_device_reversal(&device_id, &req).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 操作员申请冲正处理器
+///
+/// POST /api/v1/transactions/:transaction_id/reverse
+pub async fn reverse_transaction(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Path(transaction_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .transaction_service
+        .for_tenant(&tenant)
+        .request_operator_reversal(&transaction_id, &claims.sub)
         .await?;
 
-    tracing::info!(
-        "Transaction token verified for device {}, jti: {}",
-        req.device_id,
-        token_claims.jti
-    );
+    Ok((StatusCode::OK, Json(response)))
+}
 
-    // 2. 验证交易金额不超过令牌允许的最大金额
-    if req.amount > token_claims.max_amount {
-        return Err(AppError::BadRequest(format!(
-            "Transaction amount {} exceeds maximum allowed amount {} for security score {}",
-            req.amount, token_claims.max_amount, token_claims.security_score
-        )));
-    }
+/// 获取交易状态历史处理器
+///
+/// GET /api/v1/transactions/:transaction_id/events
+pub async fn get_transaction_events(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(transaction_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let events = state
+        .transaction_service
+        .for_tenant(&tenant)
+        .get_transaction_events(&transaction_id)
+        .await?;
 
-    // 3. 处理交易
-    let response = state.transaction_service.process_transaction(req, &operator_id).await?;
+    Ok((StatusCode::OK, Json(serde_json::json!({ "events": events }))))
+}
 
-    // 4. 标记令牌已使用
-    if let Err(e) = state
-        .transaction_token_service
-        .mark_token_used(&token_claims, &response.transaction_id)
-        .await
-    {
-        tracing::warn!(
-            "Failed to mark transaction token as used: {}. Transaction {} completed successfully.",
-            e,
-            response.transaction_id
-        );
-        // 不阻塞交易响应，只记录警告
+/// 解析幂等键：优先使用 `Idempotency-Key` 请求头，其次使用请求体中的 `clientTransactionId`
+fn idempotency_key(
+    headers: &HeaderMap,
+    req: &ProcessTransactionRequest,
+) -> Result<Option<String>, AppError> {
+    let header_key = match headers.get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => Some(
+            value
+                .to_str()
+                .map_err(|_| AppError::BadRequest("Invalid Idempotency-Key header".to_string()))?
+                .trim()
+                .to_string(),
+        ),
+        None => None,
+    };
+
+    match (header_key, req.client_transaction_id.as_deref()) {
+        (Some(header_key), Some(client_id)) if header_key != client_id => {
+            Err(AppError::BadRequest(
+                "Idempotency-Key header does not match clientTransactionId".to_string(),
+            ))
+        },
+        (Some(header_key), _) => Ok(Some(header_key)),
+        (None, client_id) => Ok(client_id.map(str::to_string)),
     }
+}
 
-    Ok((StatusCode::OK, Json(response)))
+/// 以JSON原文构造响应，保证重放的响应体与首次响应逐字节一致
+fn json_body_response(status: StatusCode, body: String, replayed: bool) -> Response {
+    let mut response = (status, [(header::CONTENT_TYPE, "application/json")], body).into_response();
+    if replayed {
+        response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    }
+    response
 }
 
 /// 列出交易记录处理器
@@ -285,11 +368,13 @@ pub async fn process_transaction_public(
 /// GET /api/v1/transactions
 pub async fn list_transactions(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListTransactionsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response_data = state
         .transaction_service
+        .for_tenant(&tenant)
         .list_transactions(
             query.device_id.as_deref(),
             query.status,
@@ -308,11 +393,13 @@ pub async fn list_transactions(
 /// GET /api/v1/transactions/:transaction_id
 pub async fn get_transaction(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(transaction_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response = state
         .transaction_service
+        .for_tenant(&tenant)
         .get_transaction(&transaction_id)
         .await?;
 
@@ -324,12 +411,14 @@ pub async fn get_transaction(
 /// GET /api/v1/transactions/device/:device_id/history
 pub async fn get_device_transaction_history(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
     Query(query): Query<ListTransactionsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层，只获取特定设备的交易
     let response = state
         .transaction_service
+        .for_tenant(&tenant)
         .list_transactions(
             Some(&device_id),
             query.status,
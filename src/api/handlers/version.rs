@@ -13,7 +13,7 @@ use crate::{
         request::{CreateVersionRequest, UpdateVersionRequest, CreatePushTaskRequest},
         response::VersionResponse,
     },
-    models::{UpdateType, VersionStatus},
+    models::{UpdateType, VersionStatus, TenantContext},
     utils::error::AppError,
 };
 
@@ -40,6 +40,7 @@ pub struct ListPushTasksQuery {
 /// POST /api/v1/versions
 pub async fn create_version(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<CreateVersionRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -47,7 +48,7 @@ pub async fn create_version(
     let operator_id = claims.sub;
 
     // 调用服务层
-    let response = state.version_service.create_version(req, &operator_id).await?;
+    let response = state.version_service.for_tenant(&tenant).create_version(req, &operator_id).await?;
 
     Ok((StatusCode::CREATED, Json(response)))
 }
@@ -57,11 +58,13 @@ pub async fn create_version(
 /// GET /api/v1/versions
 pub async fn list_versions(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListVersionsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response = state
         .version_service
+        .for_tenant(&tenant)
         .list_versions(
             query.status,
             query.update_type,
@@ -78,10 +81,11 @@ pub async fn list_versions(
 /// GET /api/v1/versions/:version_id
 pub async fn get_version(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(version_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let response = state.version_service.get_version(&version_id).await?;
+    let response = state.version_service.for_tenant(&tenant).get_version(&version_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -91,6 +95,7 @@ pub async fn get_version(
 /// PUT /api/v1/versions/:version_id
 pub async fn update_version(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(version_id): Path<String>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<UpdateVersionRequest>,
@@ -101,6 +106,7 @@ pub async fn update_version(
     // 调用服务层
     let response = state
         .version_service
+        .for_tenant(&tenant)
         .update_version(&version_id, req, &operator_id)
         .await?;
 
@@ -112,9 +118,10 @@ pub async fn update_version(
 /// GET /api/v1/versions/statistics
 pub async fn get_version_statistics(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let stats = state.version_service.get_version_statistics().await?;
+    let stats = state.version_service.for_tenant(&tenant).get_version_statistics().await?;
 
     Ok((StatusCode::OK, Json(stats)))
 }
@@ -124,9 +131,10 @@ pub async fn get_version_statistics(
 /// GET /api/v1/versions/compatibility
 pub async fn get_compatibility_matrix(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let matrix = state.version_service.get_compatibility_matrix().await?;
+    let matrix = state.version_service.for_tenant(&tenant).get_compatibility_matrix().await?;
 
     Ok((StatusCode::OK, Json(matrix)))
 }
@@ -136,6 +144,7 @@ pub async fn get_compatibility_matrix(
 /// POST /api/v1/versions/push
 pub async fn create_push_task(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Extension(claims): Extension<crate::security::jwt::Claims>,
     Json(req): Json<CreatePushTaskRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -145,6 +154,7 @@ pub async fn create_push_task(
     // 调用服务层
     let response = state
         .version_service
+        .for_tenant(&tenant)
         .create_push_task(req, &operator_id)
         .await?;
 
@@ -156,11 +166,13 @@ pub async fn create_push_task(
 /// GET /api/v1/versions/push
 pub async fn list_push_tasks(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Query(query): Query<ListPushTasksQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response = state
         .version_service
+        .for_tenant(&tenant)
         .list_push_tasks(
             query.version_id.as_deref(),
             query.status.as_deref(),
@@ -177,10 +189,11 @@ pub async fn list_push_tasks(
 /// GET /api/v1/versions/push/:task_id
 pub async fn get_push_task(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(task_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let response = state.version_service.get_push_task(&task_id).await?;
+    let response = state.version_service.for_tenant(&tenant).get_push_task(&task_id).await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -190,11 +203,13 @@ pub async fn get_push_task(
 /// GET /api/v1/versions/available/:device_id
 pub async fn get_available_version(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
     Path(device_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
     let response = state
         .version_service
+        .for_tenant(&tenant)
         .get_available_version(&device_id)
         .await?;
 
@@ -206,9 +221,10 @@ pub async fn get_available_version(
 /// GET /api/v1/versions/outdated-devices
 pub async fn get_outdated_devices(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let response = state.version_service.get_outdated_devices().await?;
+    let response = state.version_service.for_tenant(&tenant).get_outdated_devices().await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
@@ -218,9 +234,10 @@ pub async fn get_outdated_devices(
 /// GET /api/v1/versions/update-dashboard
 pub async fn get_update_dashboard(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
 ) -> Result<impl IntoResponse, AppError> {
     // 调用服务层
-    let response = state.version_service.get_update_dashboard().await?;
+    let response = state.version_service.for_tenant(&tenant).get_update_dashboard().await?;
 
     Ok((StatusCode::OK, Json(response)))
 }
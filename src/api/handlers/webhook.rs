@@ -0,0 +1,171 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Extension,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    api::AppState,
+    dto::request::{CreateWebhookRequest, UpdateWebhookRequest},
+    models::{TenantContext, WebhookDeliveryStatus},
+    utils::error::AppError,
+};
+
+/// Webhook订阅列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListWebhooksQuery {
+    pub merchant_id: Option<String>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// Webhook投递列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListWebhookDeliveriesQuery {
+    pub status: Option<WebhookDeliveryStatus>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 创建Webhook订阅处理器
+///
+/// POST /api/v1/webhooks
+pub async fn create_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .webhook_service
+        .for_tenant(&tenant)
+        .create_subscription(req, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Webhook订阅列表处理器
+///
+/// GET /api/v1/webhooks
+pub async fn list_webhooks(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Query(query): Query<ListWebhooksQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let response = state
+        .webhook_service
+        .for_tenant(&tenant)
+        .list_subscriptions(query.merchant_id.as_deref(), page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取Webhook订阅处理器
+///
+/// GET /api/v1/webhooks/:webhook_id
+pub async fn get_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(webhook_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.webhook_service.for_tenant(&tenant).get_subscription(&webhook_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 更新Webhook订阅处理器
+///
+/// PUT /api/v1/webhooks/:webhook_id
+pub async fn update_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Path(webhook_id): Path<String>,
+    Json(req): Json<UpdateWebhookRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .webhook_service
+        .for_tenant(&tenant)
+        .update_subscription(&webhook_id, req, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 删除Webhook订阅处理器
+///
+/// DELETE /api/v1/webhooks/:webhook_id
+pub async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Path(webhook_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .webhook_service
+        .for_tenant(&tenant)
+        .delete_subscription(&webhook_id, &claims.sub)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Webhook投递列表处理器
+///
+/// GET /api/v1/webhooks/:webhook_id/deliveries
+pub async fn list_webhook_deliveries(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(webhook_id): Path<String>,
+    Query(query): Query<ListWebhookDeliveriesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let response = state
+        .webhook_service
+        .for_tenant(&tenant)
+        .list_deliveries(&webhook_id, query.status, page_size, (page - 1) * page_size)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 获取Webhook投递处理器
+///
+/// GET /api/v1/webhook-deliveries/:delivery_id
+pub async fn get_webhook_delivery(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Path(delivery_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state.webhook_service.for_tenant(&tenant).get_delivery(&delivery_id).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 重新投递Webhook处理器
+///
+/// POST /api/v1/webhook-deliveries/:delivery_id/redeliver
+pub async fn redeliver_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantContext>,
+    Extension(claims): Extension<crate::security::jwt::Claims>,
+    Path(delivery_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .webhook_service
+        .for_tenant(&tenant)
+        .redeliver(&delivery_id, &claims.sub)
+        .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
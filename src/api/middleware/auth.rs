@@ -1,51 +1,273 @@
 use axum::{
-    extract::{Request, State},
-    http::StatusCode,
+    async_trait,
+    extract::{FromRequestParts, OriginalUri, Query, Request, State},
+    http::{header, request::Parts, StatusCode},
     middleware::Next,
     response::Response,
 };
+use serde::Deserialize;
 use std::sync::Arc;
 
-use crate::{api::AppState, security::jwt::Claims, utils::error::AppError};
+pub use crate::dto::DeviceCredentials;
+
+use crate::{
+    api::AppState,
+    models::{TenantContext, TenantStatus},
+    security::{crypto, jwt::Claims},
+    utils::error::AppError,
+};
+
+/// API密钥请求头
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// 超级管理员指定目标租户的请求头
+pub const TENANT_HEADER: &str = "X-Tenant-ID";
+
+/// 设备签名时间戳请求头
+pub const DEVICE_TIMESTAMP_HEADER: &str = "X-Device-Timestamp";
+
+/// 设备签名随机数请求头
+pub const DEVICE_NONCE_HEADER: &str = "X-Device-Nonce";
+
+/// 设备签名请求头
+pub const DEVICE_SIGNATURE_HEADER: &str = "X-Device-Signature";
+
+/// 设备端请求体大小上限
+const DEVICE_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// 设备请求体摘要，由 [`device_body_digest_middleware`] 注入请求扩展
+#[derive(Debug, Clone)]
+pub struct DeviceBodyDigest(pub String);
+
+/// WebSocket客户端无法设置请求头时使用的查询参数凭证
+#[derive(Deserialize)]
+struct DeviceQueryCredentials {
+    timestamp: i64,
+    nonce: String,
+    signature: String,
+}
+
+/// 设备凭证
+///
+/// 从 `X-Device-Timestamp` / `X-Device-Nonce` / `X-Device-Signature` 请求头读取，
+/// 仅WebSocket升级请求可改用 `timestamp` / `nonce` / `signature` 查询参数。
+/// 路由须经过 [`device_body_digest_middleware`]，否则无法得到请求体摘要。
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for DeviceCredentials {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(|v| v.trim().to_string())
+        };
+
+        let (timestamp, nonce, signature) = match (
+            header(DEVICE_TIMESTAMP_HEADER),
+            header(DEVICE_NONCE_HEADER),
+            header(DEVICE_SIGNATURE_HEADER),
+        ) {
+            (Some(timestamp), Some(nonce), Some(signature)) => {
+                let timestamp = timestamp
+                    .parse()
+                    .map_err(|_| AppError::Unauthorized("Invalid device timestamp".to_string()))?;
+                (timestamp, nonce, signature)
+            },
+            _ if is_websocket_upgrade(parts) => {
+                let Query(query) = Query::<DeviceQueryCredentials>::from_request_parts(parts, state)
+                    .await
+                    .map_err(|_| {
+                        AppError::Unauthorized("Missing device credentials".to_string())
+                    })?;
+                (query.timestamp, query.nonce, query.signature)
+            },
+            _ => return Err(AppError::Unauthorized("Missing device credentials".to_string())),
+        };
+
+        let DeviceBodyDigest(body_sha256) =
+            parts.extensions.get::<DeviceBodyDigest>().cloned().ok_or_else(|| {
+                AppError::InternalWithMessage("Device request digest not available".to_string())
+            })?;
+
+        // 嵌套路由会去掉前缀，签名使用设备实际请求的完整路径
+        let path = parts
+            .extensions
+            .get::<OriginalUri>()
+            .map(|uri| uri.path().to_string())
+            .unwrap_or_else(|| parts.uri.path().to_string());
+
+        Ok(Self {
+            timestamp,
+            nonce,
+            signature,
+            method: parts.method.as_str().to_string(),
+            path,
+            body_sha256,
+        })
+    }
+}
+
+/// 设备请求体摘要中间件
+///
+/// 读取请求体计算SHA-256摘要并注入请求扩展，供 [`DeviceCredentials`] 校验签名
+pub async fn device_body_digest_middleware(
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let (mut parts, body) = request.into_parts();
+    let bytes = axum::body::to_bytes(body, DEVICE_BODY_LIMIT)
+        .await
+        .map_err(|_| AppError::BadRequest("Request body too large".to_string()))?;
+
+    parts.extensions.insert(DeviceBodyDigest(crypto::sha256_hash_hex(&bytes)));
+
+    Ok(next.run(Request::from_parts(parts, axum::body::Body::from(bytes))).await)
+}
+
+fn is_websocket_upgrade(parts: &Parts) -> bool {
+    parts
+        .headers
+        .get(header::UPGRADE)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+}
 
 /// JWT认证中间件
 ///
-/// 从请求头中提取JWT token，验证并将Claims注入到请求扩展中
+/// 从请求头中提取JWT token（或租户API密钥），验证并将Claims和租户上下文注入到请求扩展中
 pub async fn auth_middleware(
     State(state): State<Arc<AppState>>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, AppError> {
-    // 从Authorization头中提取token
-    let auth_header = request
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .ok_or_else(|| AppError::Unauthorized("Missing authorization header".to_string()))?;
+    let claims = if let Some(api_key) = header_value(&request, API_KEY_HEADER) {
+        // 使用租户API密钥认证
+        claims_from_api_key(&state, &api_key).await?
+    } else {
+        // 从Authorization头中提取token
+        let auth_header = request
+            .headers()
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .ok_or_else(|| AppError::Unauthorized("Missing authorization header".to_string()))?;
 
-    // 检查Bearer前缀
-    if !auth_header.starts_with("Bearer ") {
-        return Err(AppError::Unauthorized(
-            "Invalid authorization header format".to_string(),
-        ));
-    }
+        // 检查Bearer前缀
+        if !auth_header.starts_with("Bearer ") {
+            return Err(AppError::Unauthorized(
+                "Invalid authorization header format".to_string(),
+            ));
+        }
+
+        // 提取token
+        let token = auth_header.trim_start_matches("Bearer ").trim();
 
-    // 提取token
-    let token = auth_header.trim_start_matches("Bearer ").trim();
+        // 验证token
+        state
+            .jwt_service
+            .verify_token(token)
+            .map_err(|e| AppError::Unauthorized(format!("Token verification failed: {}", e)))?
+    };
 
-    // 验证token
-    let claims = state
-        .jwt_service
-        .verify_token(token)
-        .map_err(|e| AppError::Unauthorized(format!("Token verification failed: {}", e)))?;
+    // 解析租户上下文
+    let requested_tenant = header_value(&request, TENANT_HEADER);
+    let tenant = authorize_tenant(&state, &claims, requested_tenant.as_deref()).await?;
 
-    // 将Claims注入到请求扩展中，供后续处理器使用
+    // 将Claims和租户上下文注入到请求扩展中，供后续处理器使用
     request.extensions_mut().insert(claims);
+    request.extensions_mut().insert(tenant);
 
     // 继续处理请求
     Ok(next.run(request).await)
 }
 
+/// 解析租户上下文并校验租户状态，停用租户的用户不能继续访问
+pub async fn authorize_tenant(
+    state: &AppState,
+    claims: &Claims,
+    requested_tenant: Option<&str>,
+) -> Result<TenantContext, AppError> {
+    let tenant = resolve_tenant_context(claims, requested_tenant)?;
+
+    if !tenant.cross_tenant && !claims.is_super_admin() {
+        match state.tenant_service.get_cached(&tenant.tenant_id).await? {
+            Some(t) if t.status == TenantStatus::Active.as_str() => {},
+            Some(_) => return Err(AppError::Forbidden("Tenant is suspended".to_string())),
+            None => return Err(AppError::Forbidden("Tenant not found".to_string())),
+        }
+    }
+
+    Ok(tenant)
+}
+
+/// 可选的API密钥中间件
+///
+/// 设备端公开接口使用：携带API密钥时将租户上下文注入请求扩展，未携带时按默认租户处理
+pub async fn optional_api_key_middleware(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let Some(api_key) = header_value(&request, API_KEY_HEADER) {
+        let claims = claims_from_api_key(&state, &api_key).await?;
+        request.extensions_mut().insert(TenantContext::new(claims.tenant_id));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// 根据Claims解析租户上下文
+///
+/// 超级管理员可通过 `X-Tenant-ID` 指定租户，未指定时跨租户访问；其他用户只能访问自己的租户
+pub fn resolve_tenant_context(
+    claims: &Claims,
+    requested_tenant: Option<&str>,
+) -> Result<TenantContext, AppError> {
+    if claims.is_super_admin() {
+        return Ok(match requested_tenant {
+            Some(tenant_id) => TenantContext::new(tenant_id),
+            None => TenantContext::cross_tenant(claims.tenant_id.clone()),
+        });
+    }
+
+    match requested_tenant {
+        Some(tenant_id) if tenant_id != claims.tenant_id => Err(AppError::Forbidden(
+            "Cross-tenant access requires super admin role".to_string(),
+        )),
+        _ => Ok(TenantContext::new(claims.tenant_id.clone())),
+    }
+}
+
+/// 使用API密钥生成Claims
+async fn claims_from_api_key(state: &AppState, api_key: &str) -> Result<Claims, AppError> {
+    let key = state
+        .tenant_service
+        .resolve_api_key(api_key)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))?;
+
+    let now = chrono::Utc::now().timestamp();
+    Ok(Claims {
+        sub: format!("apikey:{}", key.id),
+        username: key.name,
+        role: key.role,
+        tenant_id: key.tenant_id,
+        exp: now,
+        iat: now,
+    })
+}
+
+fn header_value(request: &Request, name: &str) -> Option<String> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|h| h.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
 /// 可选的JWT认证中间件
 ///
 /// 如果存在token则验证，不存在则继续处理
@@ -145,6 +367,7 @@ mod tests {
             sub: "user123".to_string(),
             username: "testuser".to_string(),
             role: "admin".to_string(),
+            tenant_id: "default".to_string(),
             exp: chrono::Utc::now().timestamp() + 3600,
             iat: chrono::Utc::now().timestamp(),
         };
@@ -165,6 +388,7 @@ mod tests {
             sub: "user123".to_string(),
             username: "testuser".to_string(),
             role: "admin".to_string(),
+            tenant_id: "default".to_string(),
             exp: chrono::Utc::now().timestamp() + 3600,
             iat: chrono::Utc::now().timestamp(),
         };
@@ -183,6 +407,7 @@ mod tests {
             sub: "user123".to_string(),
             username: "testuser".to_string(),
             role: "admin".to_string(),
+            tenant_id: "default".to_string(),
             exp: chrono::Utc::now().timestamp() + 3600,
             iat: chrono::Utc::now().timestamp(),
         };
@@ -192,4 +417,86 @@ mod tests {
         assert!(has_role(&request, "admin").unwrap());
         assert!(!has_role(&request, "user").unwrap());
     }
+
+    fn claims_with(role: &str, tenant_id: &str) -> Claims {
+        Claims {
+            sub: "user123".to_string(),
+            username: "testuser".to_string(),
+            role: role.to_string(),
+            tenant_id: tenant_id.to_string(),
+            exp: chrono::Utc::now().timestamp() + 3600,
+            iat: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_tenant_context_for_tenant_user() {
+        let claims = claims_with("admin", "acq-1");
+
+        let tenant = resolve_tenant_context(&claims, None).unwrap();
+        assert_eq!(tenant, TenantContext::new("acq-1"));
+
+        let tenant = resolve_tenant_context(&claims, Some("acq-1")).unwrap();
+        assert_eq!(tenant, TenantContext::new("acq-1"));
+
+        assert!(resolve_tenant_context(&claims, Some("acq-2")).is_err());
+    }
+
+    async fn device_credentials(
+        request: HttpRequest<()>,
+    ) -> Result<DeviceCredentials, AppError> {
+        let (mut parts, _) = request.into_parts();
+        parts.extensions.insert(DeviceBodyDigest(crypto::sha256_hash_hex(b"")));
+        DeviceCredentials::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_device_credentials_from_headers() {
+        let request = HttpRequest::builder()
+            .method("POST")
+            .uri("/devices/d-1/settlement/close")
+            .header(DEVICE_TIMESTAMP_HEADER, "1700000000")
+            .header(DEVICE_NONCE_HEADER, "n-1")
+            .header(DEVICE_SIGNATURE_HEADER, "c2ln")
+            .body(())
+            .unwrap();
+
+        let credentials = device_credentials(request).await.unwrap();
+        assert_eq!(credentials.timestamp, 1700000000);
+        assert_eq!(credentials.nonce, "n-1");
+        assert_eq!(credentials.method, "POST");
+        assert_eq!(credentials.path, "/devices/d-1/settlement/close");
+        assert_eq!(
+            credentials.signing_message("d-1"),
+            format!(
+                "d-1:1700000000:n-1:POST:/devices/d-1/settlement/close:{}",
+                crypto::sha256_hash_hex(b"")
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_device_query_credentials_only_on_websocket_upgrade() {
+        let uri = "/devices/d-1/ws?timestamp=1700000000&nonce=n-1&signature=c2ln";
+
+        let request = HttpRequest::builder().uri(uri).body(()).unwrap();
+        assert!(matches!(device_credentials(request).await, Err(AppError::Unauthorized(_))));
+
+        let request =
+            HttpRequest::builder().uri(uri).header(header::UPGRADE, "websocket").body(()).unwrap();
+        let credentials = device_credentials(request).await.unwrap();
+        assert_eq!(credentials.signature, "c2ln");
+        assert_eq!(credentials.path, "/devices/d-1/ws");
+    }
+
+    #[test]
+    fn test_resolve_tenant_context_for_super_admin() {
+        let claims = claims_with("super_admin", "default");
+
+        let tenant = resolve_tenant_context(&claims, None).unwrap();
+        assert!(tenant.cross_tenant);
+
+        let tenant = resolve_tenant_context(&claims, Some("acq-2")).unwrap();
+        assert_eq!(tenant, TenantContext::new("acq-2"));
+    }
 }
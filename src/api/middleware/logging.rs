@@ -1,11 +1,15 @@
 use axum::{
     extract::{ConnectInfo, Request},
+    http::Uri,
     middleware::Next,
     response::Response,
 };
 use std::{net::SocketAddr, time::Instant};
 use tracing::{info, warn};
 
+/// 设备凭证查询参数（WebSocket升级请求使用），记录日志前脱敏
+const REDACTED_QUERY_PARAMS: &[&str] = &["timestamp", "nonce", "signature"];
+
 /// 请求日志中间件
 ///
 /// 记录每个HTTP请求的详细信息，分离请求和响应日志
@@ -16,6 +20,7 @@ pub async fn logging_middleware(
 ) -> Response {
     let method = request.method().clone();
     let uri = request.uri().clone();
+    let logged_uri = redact_uri(&uri);
     let request_id = uuid::Uuid::new_v4();
 
     // 提取用户信息（如果已认证）
@@ -50,22 +55,7 @@ pub async fn logging_middleware(
         },
     };
 
-    // 尝试将请求体解析为 JSON 字符串以便打印
-    let body_str = if !bytes.is_empty() {
-        match std::str::from_utf8(&bytes) {
-            Ok(s) => {
-                // 尝试格式化 JSON
-                if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(s) {
-                    serde_json::to_string_pretty(&json_value).unwrap_or_else(|_| s.to_string())
-                } else {
-                    s.to_string()
-                }
-            },
-            Err(_) => format!("<binary data, {} bytes>", bytes.len()),
-        }
-    } else {
-        String::from("<empty>")
-    };
+    let body_str = format_request_body(&bytes);
 
     // 构建Header
     let header = format!(
@@ -85,7 +75,7 @@ pub async fn logging_middleware(
          └─ Processing...",
         header,
         method,
-        uri,
+        logged_uri,
         matched_path,
         addr.ip(),
         user_display,
@@ -158,7 +148,7 @@ pub async fn logging_middleware(
             {}",
         status.as_u16(),
         method,
-        uri,
+        logged_uri,
         matched_path,
         duration.as_millis(),
         addr.ip(),
@@ -214,7 +204,7 @@ pub async fn structured_logging_middleware(
     let log_entry = serde_json::json!({
         "timestamp": chrono::Utc::now().to_rfc3339(),
         "method": method.to_string(),
-        "uri": uri.to_string(),
+        "uri": redact_uri(&uri),
         "version": format!("{:?}", version),
         "status": status.as_u16(),
         "duration_ms": duration.as_millis(),
@@ -257,7 +247,7 @@ pub async fn error_logging_middleware(
     if status.is_client_error() || status.is_server_error() {
         warn!(
             method = %method,
-            uri = %uri,
+            uri = %redact_uri(&uri),
             status = %status.as_u16(),
             client_ip = %addr.ip(),
             user_id = ?user_id,
@@ -287,7 +277,7 @@ pub async fn slow_request_logging_middleware(
             if duration.as_millis() > threshold_ms as u128 {
                 warn!(
                     method = %method,
-                    uri = %uri,
+                    uri = %redact_uri(&uri),
                     duration_ms = duration.as_millis(),
                     threshold_ms = threshold_ms,
                     "Slow request detected"
@@ -323,6 +313,60 @@ pub async fn request_id_middleware(request: Request, next: Next) -> Response {
     response
 }
 
+/// 辅助函数：脱敏URI中的设备凭证查询参数
+pub fn redact_uri(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if REDACTED_QUERY_PARAMS.contains(&key) => format!("{}=***", key),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("{}?{}", uri.path(), query)
+}
+
+/// 辅助函数：格式化请求体以便打印
+///
+/// 只打印可解析的JSON并脱敏其中的EMV数据；无法解析的请求体可能包含未脱敏的卡数据，只记录长度
+fn format_request_body(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return String::from("<empty>");
+    }
+
+    match serde_json::from_slice::<serde_json::Value>(bytes) {
+        Ok(mut json_value) => {
+            redact_emv_fields(&mut json_value);
+            serde_json::to_string_pretty(&json_value)
+                .unwrap_or_else(|_| format!("<{} bytes>", bytes.len()))
+        },
+        Err(_) => format!("<non-JSON body, {} bytes>", bytes.len()),
+    }
+}
+
+/// 辅助函数：脱敏请求体中的EMV数据（`emvData` 字段）
+fn redact_emv_fields(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                match field {
+                    serde_json::Value::String(data) if key == "emvData" => {
+                        *data = crate::models::redact_emv_data(data);
+                    },
+                    _ => redact_emv_fields(field),
+                }
+            }
+        },
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_emv_fields),
+        _ => {},
+    }
+}
+
 /// 辅助函数：为请求体添加缩进，使其在日志中更易读
 fn indent_body(body: &str) -> String {
     body.lines()
@@ -365,4 +409,40 @@ mod tests {
         // 验证响应头中包含X-Request-ID
         assert!(response.headers().contains_key("X-Request-ID"));
     }
+
+    #[test]
+    fn test_redact_emv_fields() {
+        let mut body = serde_json::json!({
+            "deviceId": "device-1",
+            "emvData": "9F360200425A084111111111111111",
+        });
+        redact_emv_fields(&mut body);
+
+        assert_eq!(body["emvData"], "9F360200425A08****************");
+        assert_eq!(body["deviceId"], "device-1");
+    }
+
+    #[test]
+    fn test_redact_uri() {
+        let uri: Uri = "/devices/d-1/ws?timestamp=1700000000&nonce=abc&signature=c2ln&x=1"
+            .parse()
+            .unwrap();
+        assert_eq!(redact_uri(&uri), "/devices/d-1/ws?timestamp=***&nonce=***&signature=***&x=1");
+
+        let uri: Uri = "/devices/d-1/commands/poll".parse().unwrap();
+        assert_eq!(redact_uri(&uri), "/devices/d-1/commands/poll");
+    }
+
+    #[test]
+    fn test_format_request_body_skips_non_json() {
+        assert_eq!(format_request_body(b""), "<empty>");
+        assert_eq!(
+            format_request_body(b"emvData=9F360200425A084111111111111111"),
+            "<non-JSON body, 38 bytes>"
+        );
+
+        let body = format_request_body(br#"{"emvData":"9F360200425A084111111111111111"}"#);
+        assert!(body.contains("9F360200425A08****************"));
+        assert!(!body.contains("4111111111111111"));
+    }
 }
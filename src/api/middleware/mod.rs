@@ -6,8 +6,11 @@ pub mod rate_limit;
 pub mod tracing;
 
 pub use auth::{
-    auth_middleware, extract_claims, extract_role, extract_user_id, extract_username, has_role,
-    optional_auth_middleware, require_role,
+    auth_middleware, authorize_tenant, device_body_digest_middleware, extract_claims,
+    extract_role, extract_user_id, extract_username, has_role, optional_api_key_middleware,
+    optional_auth_middleware, require_role, resolve_tenant_context, DeviceBodyDigest,
+    DeviceCredentials, API_KEY_HEADER, DEVICE_NONCE_HEADER, DEVICE_SIGNATURE_HEADER,
+    DEVICE_TIMESTAMP_HEADER, TENANT_HEADER,
 };
 pub use logging::{
     error_logging_middleware, logging_middleware, request_id_middleware,
@@ -19,7 +22,7 @@ pub use metrics::{
 };
 pub use prometheus::{metrics_handler, prometheus_middleware, PrometheusMetrics};
 pub use rate_limit::{
-    rate_limit_layer, rate_limit_middleware, user_rate_limit_middleware, RateLimitConfig,
-    RateLimiter,
+    rate_limit_layer, rate_limit_middleware, tenant_rate_limit_middleware,
+    user_rate_limit_middleware, RateLimitConfig, RateLimiter, TenantRateLimiter,
 };
 pub use tracing::{extract_trace_context, tracing_middleware, TraceContext};
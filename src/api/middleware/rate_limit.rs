@@ -12,6 +12,8 @@ use std::{
 };
 use tokio::sync::Mutex;
 
+use crate::{models::TenantContext, services::TenantService};
+
 /// 速率限制器配置
 #[derive(Debug, Clone)]
 pub struct RateLimitConfig {
@@ -105,14 +107,31 @@ impl RateLimiter {
 
     /// 检查是否允许请求
     pub async fn check_rate_limit(&self, key: &str) -> Result<(), Duration> {
+        self.check_rate_limit_with(key, &self.config).await
+    }
+
+    /// 使用指定配置检查是否允许请求（如租户级限流）
+    ///
+    /// 配置变更后已有的桶会按新配置调整容量和补充速率
+    pub async fn check_rate_limit_with(
+        &self,
+        key: &str,
+        config: &RateLimitConfig,
+    ) -> Result<(), Duration> {
         let mut buckets = self.buckets.lock().await;
 
-        let bucket = buckets.entry(key.to_string()).or_insert_with(|| {
-            TokenBucket::new(
-                self.config.burst_size as f64,
-                self.config.requests_per_second as f64,
-            )
-        });
+        let capacity = config.burst_size as f64;
+        let refill_rate = config.requests_per_second as f64;
+
+        let bucket = buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket::new(capacity, refill_rate));
+
+        if bucket.capacity != capacity || bucket.refill_rate != refill_rate {
+            bucket.capacity = capacity;
+            bucket.refill_rate = refill_rate;
+            bucket.tokens = bucket.tokens.min(capacity);
+        }
 
         if bucket.try_consume() {
             Ok(())
@@ -190,6 +209,70 @@ pub async fn user_rate_limit_middleware(
     }
 }
 
+/// 租户级速率限制器
+///
+/// 租户配置了限流参数时使用租户配置，否则使用默认配置
+#[derive(Clone)]
+pub struct TenantRateLimiter {
+    limiter: RateLimiter,
+    tenant_service: Arc<TenantService>,
+}
+
+impl TenantRateLimiter {
+    /// 创建新的租户级速率限制器
+    pub fn new(config: RateLimitConfig, tenant_service: Arc<TenantService>) -> Self {
+        Self { limiter: RateLimiter::new(config), tenant_service }
+    }
+
+    /// 检查租户是否允许请求
+    pub async fn check(&self, tenant_id: &str) -> Result<(), Duration> {
+        let tenant = self.tenant_service.get_cached(tenant_id).await.ok().flatten();
+
+        let default = &self.limiter.config;
+        let config = RateLimitConfig {
+            requests_per_second: tenant
+                .as_ref()
+                .and_then(|t| t.rate_limit_rps)
+                .map(|rps| rps as u32)
+                .unwrap_or(default.requests_per_second),
+            burst_size: tenant
+                .as_ref()
+                .and_then(|t| t.rate_limit_burst)
+                .map(|burst| burst as u32)
+                .unwrap_or(default.burst_size),
+        };
+
+        self.limiter.check_rate_limit_with(&format!("tenant:{}", tenant_id), &config).await
+    }
+}
+
+/// 基于租户的速率限制中间件
+///
+/// 需要在认证中间件之后使用
+pub async fn tenant_rate_limit_middleware(
+    limiter: axum::extract::State<Arc<TenantRateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(tenant) = request.extensions().get::<TenantContext>().cloned() else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(&tenant.tenant_id).await {
+        Ok(()) => next.run(request).await,
+        Err(wait_time) => {
+            let retry_after = wait_time.as_secs().max(1);
+
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [("Retry-After", retry_after.to_string())],
+                format!("Tenant rate limit exceeded. Retry after {} seconds", retry_after),
+            )
+                .into_response()
+        }
+    }
+}
+
 /// 创建速率限制层
 pub fn rate_limit_layer(config: RateLimitConfig) -> Arc<RateLimiter> {
     Arc::new(RateLimiter::new(config))
@@ -267,6 +350,23 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_rate_limiter_with_custom_config() {
+        let limiter = RateLimiter::new(RateLimitConfig::default());
+        let config = RateLimitConfig {
+            requests_per_second: 1,
+            burst_size: 3,
+        };
+
+        for _ in 0..3 {
+            assert!(limiter.check_rate_limit_with("tenant:acq-1", &config).await.is_ok());
+        }
+        assert!(limiter.check_rate_limit_with("tenant:acq-1", &config).await.is_err());
+
+        // 其他租户使用默认配置，不受影响
+        assert!(limiter.check_rate_limit("tenant:acq-2").await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_cleanup_expired() {
         let config = RateLimitConfig {
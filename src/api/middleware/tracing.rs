@@ -4,6 +4,8 @@ use axum::{
     response::Response,
 };
 use tracing::{info_span, Instrument};
+
+use super::logging::redact_uri;
 use uuid::Uuid;
 
 /// 分布式追踪中间件
@@ -50,7 +52,7 @@ pub async fn tracing_middleware(
             span_id = %span_id,
             parent_span_id = %parent_id,
             method = %request.method(),
-            uri = %request.uri(),
+            uri = %redact_uri(request.uri()),
         )
     } else {
         info_span!(
@@ -58,7 +60,7 @@ pub async fn tracing_middleware(
             trace_id = %trace_id,
             span_id = %span_id,
             method = %request.method(),
-            uri = %request.uri(),
+            uri = %redact_uri(request.uri()),
         )
     };
 
@@ -3,23 +3,26 @@ pub mod middleware;
 pub mod routes;
 pub mod websocket;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use redis::Client as RedisClient;
 pub use routes::create_router;
 use sqlx::SqlitePool;
-pub use websocket::{ConnectionPool, NotificationService};
+pub use websocket::{ConnectionPool, DeviceChannel, NotificationFanout, NotificationService};
 
 use crate::{
-    infrastructure::{Config, HsmClient},
+    api::middleware::PrometheusMetrics,
+    infrastructure::{Config, HsmClient, PaymentRouter},
     repositories::{
-        AuditLogRepository, DeviceRepository, HealthCheckRepository, KernelRepository,
-        ThreatRepository, TransactionRepository, VersionRepository,
+        ApiKeyRepository, AuditLogRepository, DeviceCommandRepository, DeviceGroupRepository, DeviceImportRepository,
+        DeviceRepository, HealthCheckRepository, IdempotencyRepository, KernelRepository, MerchantRepository, NotificationRepository, OutboxRepository, ReconciliationRepository, SettlementRepository, StoreRepository, TenantRepository, ThreatRepository,
+        TransactionRepository, TransactionTokenRepository, VersionRepository, WebhookRepository,
     },
-    security::{DukptKeyDerivation, JwtService},
+    security::{DukptKeyDerivation, JwtService, KeyEncryptionKey},
     services::{
-        AuditService, DeviceService, HealthCheckService, KernelService, KeyManagementService,
-        ThreatDetectionService, TransactionService, TransactionTokenService, VersionService,
+        AlertDispatcher, AuditService, DeviceCommandService, DeviceGroupService, DeviceImportService, DeviceService, HealthCheckService, IdempotencyService, KernelService, KeyManagementService,
+        MerchantService, NotificationServiceWrapper, OutboxDispatcher, ReconciliationService, SettlementService, TenantService, ThreatDetectionService, TransactionService,
+        TransactionTokenService, VersionService, WebhookService,
     },
 };
 
@@ -31,10 +34,12 @@ pub struct AppState {
     pub db_pool: SqlitePool,
     pub redis_client: Option<RedisClient>,
     pub hsm_client: Option<HsmClient>,
+    pub payment_router: Arc<PaymentRouter>,
 
     // WebSocket
     pub ws_pool: ConnectionPool,
     pub notification_service: Arc<NotificationService>,
+    pub device_channel: DeviceChannel,
 
     // 安全模块
     pub jwt_service: Arc<JwtService>,
@@ -50,6 +55,18 @@ pub struct AppState {
     pub threat_detection_service: Arc<ThreatDetectionService>,
     pub version_service: Arc<VersionService>,
     pub kernel_service: Arc<KernelService>,
+    pub merchant_service: Arc<MerchantService>,
+    pub tenant_service: Arc<TenantService>,
+    pub device_import_service: Arc<DeviceImportService>,
+    pub device_group_service: Arc<DeviceGroupService>,
+    pub device_command_service: Arc<DeviceCommandService>,
+    pub webhook_service: Arc<WebhookService>,
+    pub outbox_dispatcher: Arc<OutboxDispatcher>,
+    pub idempotency_service: Arc<IdempotencyService>,
+    pub settlement_service: Arc<SettlementService>,
+    pub reconciliation_service: Arc<ReconciliationService>,
+    /// 外部告警分发，未启用时为None
+    pub alert_dispatcher: Option<Arc<AlertDispatcher>>,
 }
 
 impl AppState {
@@ -77,31 +94,35 @@ impl AppState {
 
         let dukpt = Arc::new(DukptKeyDerivation::new(config.security.bdk.clone().into_bytes()));
 
-        // 初始化Repositories
-        let device_repo = DeviceRepository::new(db_pool.clone());
-        let audit_repo = AuditLogRepository::new(db_pool.clone());
-        let health_check_repo = HealthCheckRepository::new(db_pool.clone());
-        let threat_repo = ThreatRepository::new(db_pool.clone());
-        let transaction_repo = TransactionRepository::new(db_pool.clone());
-        let version_repo = VersionRepository::new(db_pool.clone());
-        let kernel_repo = KernelRepository::new(db_pool.clone());
-
-        // 初始化Services
-        let device_service = Arc::new(DeviceService::new(
-            device_repo.clone(),
-            audit_repo.clone(),
-            (*dukpt).clone(),
-            hsm_client.clone(),
-        ));
+        // 租户专属BDK的密钥加密密钥
+        let kek = config
+            .security
+            .key_encryption_key
+            .as_deref()
+            .map(KeyEncryptionKey::from_hex)
+            .transpose()?;
 
-        let key_management_service = Arc::new(KeyManagementService::new(
-            device_repo.clone(),
-            audit_repo.clone(),
-            (*dukpt).clone(),
-            hsm_client.clone(),
-        ));
+        // 初始化Repositories
+        let device_repo = DeviceRepository::unscoped(db_pool.clone());
+        let audit_repo = AuditLogRepository::unscoped(db_pool.clone());
+        let health_check_repo = HealthCheckRepository::unscoped(db_pool.clone());
+        let threat_repo = ThreatRepository::unscoped(db_pool.clone());
+        let transaction_repo = TransactionRepository::unscoped(db_pool.clone());
+        let version_repo = VersionRepository::unscoped(db_pool.clone());
+        let kernel_repo = KernelRepository::unscoped(db_pool.clone());
+        let merchant_repo = MerchantRepository::unscoped(db_pool.clone());
+        let store_repo = StoreRepository::unscoped(db_pool.clone());
+        let tenant_repo = TenantRepository::new(db_pool.clone());
+        let api_key_repo = ApiKeyRepository::new(db_pool.clone());
+        let device_import_repo = DeviceImportRepository::unscoped(db_pool.clone());
+        let device_group_repo = DeviceGroupRepository::unscoped(db_pool.clone());
+        let device_command_repo = DeviceCommandRepository::unscoped(db_pool.clone());
+        let webhook_repo = WebhookRepository::unscoped(db_pool.clone());
+
+        // 初始化WebSocket连接池
+        let ws_pool = websocket::create_connection_pool();
 
-        // 初始化Redis客户端包装器（用于TransactionTokenService）
+        // 初始化Redis客户端包装器（用于TransactionTokenService和跨节点通知分发）
         let redis_wrapper = match crate::infrastructure::redis::RedisClient::new(
             &crate::infrastructure::redis::RedisConfig {
                 url: config.redis.url.clone(),
@@ -118,47 +139,239 @@ impl AppState {
             },
         };
 
-        let transaction_token_service =
-            Arc::new(TransactionTokenService::new(jwt_service.clone(), redis_wrapper));
+        // 初始化跨节点通知分发，Redis不可用时仅推送给本节点的连接
+        let notification_fanout = match &redis_wrapper {
+            Some(redis) => {
+                let fanout = Arc::new(NotificationFanout::new(
+                    redis.clone(),
+                    uuid::Uuid::new_v4().to_string(),
+                ));
+                match fanout.clone().subscribe(ws_pool.clone()).await {
+                    Ok(_) => Some(fanout),
+                    Err(e) => {
+                        tracing::warn!("Failed to subscribe notification channel: {}", e);
+                        None
+                    },
+                }
+            },
+            None => None,
+        };
+
+        // 初始化通知服务
+        let mut notification_service = NotificationService::new(ws_pool.clone())
+            .with_store(NotificationRepository::unscoped(db_pool.clone()));
+        if let Some(fanout) = notification_fanout {
+            notification_service = notification_service.with_fanout(fanout);
+        }
+
+        // 外部告警渠道（邮件、短信/IM）
+        let alert_dispatcher = if config.alerting.enabled {
+            let dispatcher = Arc::new(AlertDispatcher::from_config(
+                &config.alerting,
+                NotificationRepository::unscoped(db_pool.clone()),
+            )?);
+            notification_service = notification_service.with_alerting(dispatcher.clone());
+            Some(dispatcher)
+        } else {
+            None
+        };
+        let notification_service = Arc::new(notification_service);
+
+        // 商户Webhook投递
+        let webhook_service = WebhookService::new(
+            webhook_repo.clone(),
+            merchant_repo.clone(),
+            audit_repo.clone(),
+        )
+        .with_retry_policy(
+            config.webhook.max_attempts,
+            Duration::from_secs(config.webhook.retry_base_seconds),
+        )
+        .with_timeout(Duration::from_secs(config.webhook.timeout_seconds));
+
+        // 业务服务通过通知包装器推送告警
+        let notifier = NotificationServiceWrapper::new(Arc::new(notification_service.clone()))
+            .with_cooldown(Duration::from_secs(config.notification.alert_cooldown_seconds));
+
+        // 领域事件由发件箱分发器转发给通知、Webhook和指标
+        let outbox_dispatcher = Arc::new(
+            OutboxDispatcher::new(
+                OutboxRepository::new(db_pool.clone()),
+                device_repo.clone(),
+                threat_repo.clone(),
+            )
+            .with_notifier(notifier.clone())
+            .with_webhooks(webhook_service.clone())
+            .with_metrics(PrometheusMetrics::global().await)
+            .with_retry_policy(
+                config.outbox.max_attempts,
+                Duration::from_secs(config.outbox.retry_base_seconds),
+            ),
+        );
+
+        // 初始化Services
+        let device_service = Arc::new(
+            DeviceService::new(
+                device_repo.clone(),
+                threat_repo.clone(),
+                audit_repo.clone(),
+                (*dukpt).clone(),
+                hsm_client.clone(),
+            ),
+        );
+
+        let key_management_service = Arc::new(
+            KeyManagementService::new(
+                device_repo.clone(),
+                audit_repo.clone(),
+                (*dukpt).clone(),
+                hsm_client.clone(),
+            )
+            .with_tenant_repo(tenant_repo.clone(), kek.clone())
+            .with_notifier(notifier.clone()),
+        );
+
+        let transaction_token_service = Arc::new(
+            TransactionTokenService::new(jwt_service.clone(), redis_wrapper)
+                .with_usage_repo(TransactionTokenRepository::new(db_pool.clone()))
+                .with_health_check_max_age(Duration::from_secs(
+                    config.security.health_check_max_age_seconds,
+                )),
+        );
+
+        let payment_router = Arc::new(PaymentRouter::from_config(&config.payment)?);
+        let settlement_repo = SettlementRepository::unscoped(db_pool.clone());
+
+        let transaction_service = Arc::new(
+            TransactionService::new(
+                transaction_repo.clone(),
+                device_repo.clone(),
+                audit_repo.clone(),
+                health_check_repo.clone(),
+                (*dukpt).clone(),
+                hsm_client.clone(),
+                transaction_token_service.clone(),
+            )
+            .with_payment_router(payment_router.clone())
+            .with_merchant_repo(merchant_repo.clone())
+            .with_settlement_repo(settlement_repo.clone())
+            .with_preauth_validity(chrono::Duration::hours(config.payment.preauth_validity_hours))
+            .with_reversal_policy(
+                config.payment.reversal_max_attempts,
+                Duration::from_secs(config.payment.reversal_retry_base_seconds),
+            )
+            .with_notifier(notifier.clone()),
+        );
+
+        let audit_service = Arc::new(AuditService::new(audit_repo.clone()));
+
+        let threat_detection_service = Arc::new(
+            ThreatDetectionService::new(
+                threat_repo.clone(),
+                device_repo.clone(),
+                health_check_repo.clone(),
+                audit_repo.clone(),
+            ),
+        );
+
+        let health_check_service = Arc::new(
+            HealthCheckService::new(
+                health_check_repo.clone(),
+                device_repo.clone(),
+                threat_repo.clone(),
+                audit_repo.clone(),
+                (*threat_detection_service).clone(),
+            )
+            .with_notifier(notifier.clone()),
+        );
 
-        let transaction_service = Arc::new(TransactionService::new(
-            transaction_repo.clone(),
+        let version_service = Arc::new(VersionService::new(
+            version_repo.clone(),
             device_repo.clone(),
             audit_repo.clone(),
-            (*dukpt).clone(),
-            hsm_client.clone(),
-            transaction_token_service.clone(),
         ));
 
-        let audit_service = Arc::new(AuditService::new(audit_repo.clone()));
+        let kernel_service =
+            Arc::new(KernelService::new(kernel_repo.clone(), "uploads".to_string()));
 
-        let threat_detection_service = Arc::new(ThreatDetectionService::new(
-            threat_repo.clone(),
+        let merchant_service = Arc::new(MerchantService::new(
+            merchant_repo.clone(),
+            store_repo.clone(),
             device_repo.clone(),
-            health_check_repo.clone(),
             audit_repo.clone(),
         ));
 
-        let health_check_service = Arc::new(HealthCheckService::new(
-            health_check_repo.clone(),
+        let mut tenant_service =
+            TenantService::new(tenant_repo.clone(), api_key_repo.clone(), audit_repo.clone());
+        if let Some(kek) = kek {
+            tenant_service = tenant_service.with_key_encryption_key(kek);
+        }
+        // 升级前以明文保存的租户BDK在启动时包装
+        let wrapped = tenant_service.wrap_plaintext_bdks().await?;
+        if wrapped > 0 {
+            tracing::info!("Wrapped {} plaintext tenant BDKs", wrapped);
+        }
+        let tenant_service = Arc::new(tenant_service);
+
+        let device_import_service = Arc::new(DeviceImportService::new(
+            device_import_repo.clone(),
             device_repo.clone(),
-            threat_repo.clone(),
+            store_repo.clone(),
             audit_repo.clone(),
-            (*threat_detection_service).clone(),
+            (*device_service).clone(),
+            (*merchant_service).clone(),
         ));
 
-        let version_service = Arc::new(VersionService::new(
-            version_repo.clone(),
+        let device_group_service = Arc::new(DeviceGroupService::new(
+            device_group_repo.clone(),
             device_repo.clone(),
             audit_repo.clone(),
         ));
 
-        let kernel_service =
-            Arc::new(KernelService::new(kernel_repo.clone(), "uploads".to_string()));
+        // 初始化设备指令通道
+        let device_channel = DeviceChannel::new();
+        let device_command_service = Arc::new(DeviceCommandService::new(
+            device_command_repo.clone(),
+            device_repo.clone(),
+            audit_repo.clone(),
+            device_channel.clone(),
+        ));
 
-        // 初始化WebSocket连接池和通知服务
-        let ws_pool = websocket::create_connection_pool();
-        let notification_service = Arc::new(NotificationService::new(ws_pool.clone()));
+        let idempotency_service = Arc::new(
+            IdempotencyService::new(IdempotencyRepository::new(db_pool.clone())).with_expiry(
+                Duration::from_secs(config.idempotency.key_ttl_seconds),
+                Duration::from_secs(config.idempotency.lock_timeout_seconds),
+            ),
+        );
+
+        let settlement_service = Arc::new(
+            SettlementService::new(
+                settlement_repo,
+                transaction_repo.clone(),
+                device_repo.clone(),
+                audit_repo.clone(),
+            )
+            .with_payment_router(payment_router.clone())
+            .with_merchant_repo(merchant_repo.clone())
+            .with_cutoff(config.settlement.cutoff().unwrap_or_default())
+            .with_retry_policy(
+                config.settlement.max_submit_attempts,
+                Duration::from_secs(config.settlement.retry_base_seconds),
+            ),
+        );
+
+        let reconciliation_service = Arc::new(
+            ReconciliationService::new(
+                ReconciliationRepository::unscoped(db_pool.clone()),
+                transaction_repo.clone(),
+                audit_repo.clone(),
+            )
+            .with_layouts(config.reconciliation.layouts.clone())
+            .with_tolerance(
+                config.reconciliation.amount_tolerance,
+                config.reconciliation.date_tolerance_days,
+            ),
+        );
 
         tracing::info!("Application state initialized successfully");
 
@@ -167,8 +380,10 @@ impl AppState {
             db_pool,
             redis_client,
             hsm_client,
+            payment_router,
             ws_pool,
             notification_service,
+            device_channel,
             jwt_service,
             dukpt,
             device_service,
@@ -180,6 +395,17 @@ impl AppState {
             threat_detection_service,
             version_service,
             kernel_service,
+            merchant_service,
+            tenant_service,
+            device_import_service,
+            device_group_service,
+            device_command_service,
+            webhook_service: Arc::new(webhook_service),
+            outbox_dispatcher,
+            idempotency_service,
+            settlement_service,
+            reconciliation_service,
+            alert_dispatcher,
         })
     }
 
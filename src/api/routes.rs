@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use axum::{
     middleware,
-    routing::{get, post, put},
+    routing::{delete, get, post, put},
     Router,
 };
 use tower_http::{
@@ -13,7 +13,7 @@ use tower_http::{
 use crate::api::{
     handlers, middleware as api_middleware,
     middleware::{metrics_handler, MetricsCollector},
-    websocket::websocket_handler,
+    websocket::{device_websocket_handler, websocket_handler},
     AppState,
 };
 
@@ -24,6 +24,10 @@ pub fn create_router(state: Arc<AppState>) -> Router {
 
     // 创建速率限制器
     let rate_limiter = api_middleware::rate_limit_layer(api_middleware::RateLimitConfig::default());
+    let tenant_rate_limiter = Arc::new(api_middleware::TenantRateLimiter::new(
+        api_middleware::RateLimitConfig::default(),
+        state.tenant_service.clone(),
+    ));
 
     // 配置CORS
     let cors = CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any);
@@ -39,7 +43,13 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/auth/refresh", post(handlers::refresh_token))
         .route("/auth/verify", post(handlers::verify_token))
         // 设备注册（公开）
-        .route("/devices/register", post(handlers::register_device))
+        .route(
+            "/devices/register",
+            post(handlers::register_device).layer(middleware::from_fn_with_state(
+                state.clone(),
+                api_middleware::optional_api_key_middleware,
+            )),
+        )
         // 公开的内核下载端点（用于 demo）
         .route("/public/kernels", get(handlers::list_stable_kernels_public))
         .route("/public/kernels/latest", get(handlers::get_latest_kernel_public))
@@ -51,9 +61,32 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         // 交易鉴证和处理（公开，设备端调用）
         .route("/transactions/attest", post(handlers::attest_transaction_public))
         .route("/transactions/process", post(handlers::process_transaction_public))
-        // WebSocket连接
+        // 管理端WebSocket连接（升级时校验JWT）
         .route("/ws", get(websocket_handler));
 
+    // 设备端路由（使用设备签名认证，签名覆盖请求体摘要）
+    let device_routes = Router::new()
+        // 设备指令通道
+        .route("/devices/:device_id/ws", get(device_websocket_handler))
+        .route("/devices/:device_id/commands/poll", get(handlers::poll_device_commands))
+        .route(
+            "/devices/:device_id/commands/:command_id/ack",
+            post(handlers::acknowledge_device_command),
+        )
+        .route(
+            "/devices/:device_id/commands/:command_id/result",
+            post(handlers::report_device_command_result),
+        )
+        .route(
+            "/devices/:device_id/transactions/reverse",
+            post(handlers::reverse_device_transaction),
+        )
+        .route(
+            "/devices/:device_id/settlement/close",
+            post(handlers::close_device_batch),
+        )
+        .layer(middleware::from_fn(api_middleware::device_body_digest_middleware));
+
     // 受保护的路由（需要认证）
     let protected_routes = Router::new()
         // 认证相关
@@ -67,6 +100,16 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         // 设备管理
         .route("/devices", get(handlers::list_devices))
         .route("/devices/statistics", get(handlers::get_device_statistics))
+        .route("/devices/connected", get(handlers::list_connected_devices))
+        .route(
+            "/devices/imports",
+            post(handlers::create_device_import).get(handlers::list_device_imports),
+        )
+        .route("/devices/imports/:job_id", get(handlers::get_device_import))
+        .route(
+            "/devices/imports/:job_id/report",
+            get(handlers::download_device_import_report),
+        )
         .route("/devices/:device_id", get(handlers::get_device))
         .route(
             "/devices/:device_id/approve",
@@ -88,6 +131,113 @@ pub fn create_router(state: Arc<AppState>) -> Router {
             "/devices/:device_id/revoke",
             post(handlers::revoke_device),
         )
+        .route(
+            "/devices/:device_id/decommission",
+            post(handlers::decommission_device),
+        )
+        .route(
+            "/devices/:device_id/replace",
+            post(handlers::replace_device),
+        )
+        .route(
+            "/devices/:device_id/timeline",
+            get(handlers::get_device_timeline),
+        )
+        .route(
+            "/devices/:device_id/assign",
+            post(handlers::assign_device),
+        )
+        .route(
+            "/devices/:device_id/tags",
+            get(handlers::get_device_tags).put(handlers::set_device_tags),
+        )
+        // 设备远程指令
+        .route(
+            "/devices/:device_id/commands",
+            post(handlers::create_device_command).get(handlers::list_device_commands),
+        )
+        .route("/commands/:command_id", get(handlers::get_device_command))
+        .route("/commands/:command_id/cancel", post(handlers::cancel_device_command))
+        // 通知历史
+        .route("/notifications", get(handlers::list_notifications))
+        .route("/notifications/ack", post(handlers::acknowledge_notifications))
+        .route("/notifications/:seq/ack", post(handlers::acknowledge_notification))
+        // 设备分组
+        .route(
+            "/device-groups",
+            post(handlers::create_device_group).get(handlers::list_device_groups),
+        )
+        .route(
+            "/device-groups/:group_id",
+            get(handlers::get_device_group)
+                .put(handlers::update_device_group)
+                .delete(handlers::delete_device_group),
+        )
+        .route(
+            "/device-groups/:group_id/members",
+            post(handlers::add_device_group_members),
+        )
+        .route(
+            "/device-groups/:group_id/members/:device_id",
+            delete(handlers::remove_device_group_member),
+        )
+        .route(
+            "/device-groups/:group_id/devices",
+            get(handlers::list_device_group_devices),
+        )
+        // 商户与门店管理
+        .route(
+            "/merchants",
+            post(handlers::create_merchant).get(handlers::list_merchants),
+        )
+        .route(
+            "/merchants/:merchant_id",
+            get(handlers::get_merchant).put(handlers::update_merchant),
+        )
+        .route(
+            "/merchants/:merchant_id/stores",
+            post(handlers::create_store).get(handlers::list_stores),
+        )
+        .route(
+            "/stores/:store_id",
+            get(handlers::get_store).put(handlers::update_store),
+        )
+        .route("/stores/:store_id/devices", get(handlers::list_store_devices))
+        // 商户Webhook
+        .route("/webhooks", post(handlers::create_webhook).get(handlers::list_webhooks))
+        .route(
+            "/webhooks/:webhook_id",
+            get(handlers::get_webhook)
+                .put(handlers::update_webhook)
+                .delete(handlers::delete_webhook),
+        )
+        .route(
+            "/webhooks/:webhook_id/deliveries",
+            get(handlers::list_webhook_deliveries),
+        )
+        .route(
+            "/webhook-deliveries/:delivery_id",
+            get(handlers::get_webhook_delivery),
+        )
+        .route(
+            "/webhook-deliveries/:delivery_id/redeliver",
+            post(handlers::redeliver_webhook),
+        )
+        // 租户管理
+        .route("/tenants", post(handlers::create_tenant).get(handlers::list_tenants))
+        .route(
+            "/tenants/:tenant_id",
+            get(handlers::get_tenant).put(handlers::update_tenant),
+        )
+        .route(
+            "/tenants/:tenant_id/api-keys",
+            post(handlers::create_api_key).get(handlers::list_api_keys),
+        )
+        .route(
+            "/tenants/:tenant_id/api-keys/:key_id",
+            delete(handlers::revoke_api_key),
+        )
+        .route("/tenant/branding", get(handlers::get_tenant_branding))
         // 密钥管理
         .route("/keys/inject", post(handlers::inject_key))
         .route("/keys/:device_id/status", get(handlers::get_key_status))
@@ -142,6 +292,38 @@ pub fn create_router(state: Arc<AppState>) -> Router {
             "/transactions/:transaction_id",
             get(handlers::get_transaction),
         )
+        .route(
+            "/transactions/:transaction_id/reverse",
+            post(handlers::reverse_transaction),
+        )
+        .route(
+            "/transactions/:transaction_id/events",
+            get(handlers::get_transaction_events),
+        )
+        // 结算批次
+        .route("/settlement/batches", get(handlers::list_settlement_batches))
+        .route(
+            "/settlement/batches/:batch_id",
+            get(handlers::get_settlement_batch),
+        )
+        .route(
+            "/settlement/batches/:batch_id/reopen",
+            post(handlers::reopen_settlement_batch),
+        )
+        // 对账
+        .route(
+            "/reconciliation/runs",
+            post(handlers::create_reconciliation_run).get(handlers::list_reconciliation_runs),
+        )
+        .route("/reconciliation/runs/:run_id", get(handlers::get_reconciliation_run))
+        .route(
+            "/reconciliation/runs/:run_id/items",
+            get(handlers::list_reconciliation_items),
+        )
+        .route(
+            "/reconciliation/runs/:run_id/report",
+            get(handlers::download_reconciliation_report),
+        )
         .route(
             "/transactions/device/:device_id/history",
             get(handlers::get_device_transaction_history),
@@ -202,6 +384,11 @@ pub fn create_router(state: Arc<AppState>) -> Router {
             "/audit/operator/:operator_id/logs",
             get(handlers::get_operator_logs),
         )
+        // 应用租户级速率限制中间件（需在认证之后执行）
+        .layer(middleware::from_fn_with_state(
+            tenant_rate_limiter,
+            api_middleware::tenant_rate_limit_middleware,
+        ))
         // 应用认证中间件
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -211,6 +398,7 @@ pub fn create_router(state: Arc<AppState>) -> Router {
     // API v1路由
     let api_v1 = Router::new()
         .merge(public_routes)
+        .merge(device_routes)
         .merge(protected_routes)
         // 应用日志中间件
         .layer(middleware::from_fn(api_middleware::logging_middleware))
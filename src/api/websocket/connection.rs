@@ -1,11 +1,16 @@
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Query, State,
     },
+    http::{header::AUTHORIZATION, HeaderMap},
     response::IntoResponse,
 };
-use futures::{sink::SinkExt, stream::StreamExt};
+use futures::{
+    sink::{Sink, SinkExt},
+    stream::{Stream, StreamExt},
+};
+use serde::Deserialize;
 use std::{
     collections::HashMap,
     sync::Arc,
@@ -15,7 +20,15 @@ use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use crate::api::AppState;
+use super::{
+    notification::{Notification, NotificationService},
+    subscription::{tenant_can_receive, Subscription},
+};
+use crate::{
+    api::{middleware::authorize_tenant, AppState},
+    models::TenantContext,
+    utils::error::AppError,
+};
 
 /// WebSocket连接信息
 #[derive(Debug, Clone)]
@@ -26,129 +39,183 @@ pub struct ConnectionInfo {
     pub last_ping: Instant,
 }
 
+/// 管理端WebSocket连接
+#[derive(Debug, Clone)]
+pub struct ClientConnection {
+    pub info: ConnectionInfo,
+    /// 连接用户的租户上下文
+    pub tenant: TenantContext,
+    /// 通知订阅条件
+    pub subscription: Subscription,
+    /// 重连补发已覆盖的最大序列号，实时推送跳过不大于该值的通知
+    pub replayed_seq: i64,
+    pub tx: mpsc::UnboundedSender<Message>,
+}
+
 /// WebSocket连接池
-pub type ConnectionPool = Arc<RwLock<HashMap<String, mpsc::UnboundedSender<Message>>>>;
+pub type ConnectionPool = Arc<RwLock<HashMap<String, ClientConnection>>>;
 
 /// 创建新的连接池
 pub fn create_connection_pool() -> ConnectionPool {
     Arc::new(RwLock::new(HashMap::new()))
 }
 
-/// WebSocket连接处理器
+/// 管理端WebSocket认证参数
+#[derive(Debug, Deserialize)]
+pub struct WsAuthQuery {
+    pub token: Option<String>,
+    /// 上次收到的通知序列号，重连时补发之后的通知
+    pub last_seq: Option<i64>,
+}
+
+/// 管理端WebSocket连接处理器
+///
+/// 升级前校验JWT：优先读取 `Authorization: Bearer` 请求头，
+/// 无法设置请求头的浏览器客户端可使用 `token` 查询参数
+///
+/// GET /api/v1/ws
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    headers: HeaderMap,
+    Query(query): Query<WsAuthQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string())
+        .or(query.token)
+        .ok_or_else(|| AppError::Unauthorized("Missing authorization token".to_string()))?;
+
+    let claims = state.jwt_service.verify_token(&token)?;
+    let tenant = authorize_tenant(&state, &claims, None).await?;
+
+    let last_seq = query.last_seq;
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, state, claims.sub, tenant, last_seq)))
 }
 
 /// 处理WebSocket连接
-async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
+async fn handle_socket(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    user_id: String,
+    tenant: TenantContext,
+    last_seq: Option<i64>,
+) {
+    let (sender, receiver) = socket.split();
+    serve_connection(
+        state.notification_service.clone(),
+        sender,
+        receiver,
+        user_id,
+        tenant,
+        last_seq,
+    )
+    .await;
+}
+
+/// 运行管理端连接：注册到连接池并补发 `last_seq` 之后的通知，处理心跳与订阅消息，断开后清理
+///
+/// 与具体传输解耦，`sender` / `receiver` 为WebSocket分离后的两端
+pub async fn serve_connection<S, R>(
+    notifications: Arc<NotificationService>,
+    mut sender: S,
+    mut receiver: R,
+    user_id: String,
+    tenant: TenantContext,
+    last_seq: Option<i64>,
+) where
+    S: Sink<Message> + Unpin + Send + 'static,
+    R: Stream<Item = Result<Message, axum::Error>> + Unpin + Send + 'static,
+{
+    let pool = notifications.pool().clone();
     let connection_id = Uuid::new_v4().to_string();
-    info!("New WebSocket connection: {}", connection_id);
+    info!("New WebSocket connection: {} (user {})", connection_id, user_id);
 
     // 创建消息通道
     let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
 
-    // 将连接添加到连接池
-    {
-        let mut pool = state.ws_pool.write().await;
-        pool.insert(connection_id.clone(), tx);
-    }
-
-    // 分离socket为发送和接收部分
-    let (mut sender, mut receiver) = socket.split();
-
-    // 发送欢迎消息
+    // 发送欢迎消息，补发的通知经消息通道排在其后
     let welcome_msg = serde_json::json!({
         "type": "connected",
         "connection_id": connection_id,
         "timestamp": chrono::Utc::now().to_rfc3339()
     });
-    
+
     if let Ok(msg_text) = serde_json::to_string(&welcome_msg) {
         let _ = sender.send(Message::Text(msg_text)).await;
     }
 
+    // 将连接添加到连接池
+    let now = Instant::now();
+    let connection = ClientConnection {
+        info: ConnectionInfo {
+            id: connection_id.clone(),
+            user_id: Some(user_id),
+            connected_at: now,
+            last_ping: now,
+        },
+        tenant,
+        subscription: Subscription::default(),
+        replayed_seq: 0,
+        tx,
+    };
+    notifications.register_connection(connection_id.clone(), connection, last_seq).await;
+
     // 心跳任务
     let connection_id_clone = connection_id.clone();
-    let state_clone = state.clone();
+    let pool_clone = pool.clone();
     let heartbeat_task = tokio::spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_secs(30));
+        // 跳过立即触发的第一次tick
+        interval.tick().await;
         loop {
             interval.tick().await;
-            
-            // 检查连接是否还在池中
-            let exists = {
-                let pool = state_clone.ws_pool.read().await;
-                pool.contains_key(&connection_id_clone)
-            };
-            
-            if !exists {
-                break;
-            }
-            
-            // 发送ping消息
+
+            // 发送ping消息，连接已不在池中时退出
             let ping_msg = serde_json::json!({
                 "type": "ping",
                 "timestamp": chrono::Utc::now().to_rfc3339()
             });
-            
-            if let Ok(msg_text) = serde_json::to_string(&ping_msg) {
-                let pool = state_clone.ws_pool.read().await;
-                if let Some(tx) = pool.get(&connection_id_clone) {
-                    let _ = tx.send(Message::Text(msg_text));
-                }
+
+            let pool = pool_clone.read().await;
+            match pool.get(&connection_id_clone) {
+                Some(conn) => {
+                    let _ = conn.tx.send(Message::Text(ping_msg.to_string()));
+                },
+                None => break,
             }
         }
     });
 
     // 接收客户端消息任务
     let connection_id_clone = connection_id.clone();
-    let state_clone = state.clone();
+    let pool_clone = pool.clone();
     let receive_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
                 Message::Text(text) => {
                     debug!("Received text message from {}: {}", connection_id_clone, text);
-                    
-                    // 处理客户端消息
-                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
-                        if let Some(msg_type) = json.get("type").and_then(|v| v.as_str()) {
-                            match msg_type {
-                                "pong" => {
-                                    debug!("Received pong from {}", connection_id_clone);
-                                }
-                                "subscribe" => {
-                                    // 处理订阅请求
-                                    if let Some(topics) = json.get("topics").and_then(|v| v.as_array()) {
-                                        info!("Client {} subscribed to topics: {:?}", connection_id_clone, topics);
-                                    }
-                                }
-                                _ => {
-                                    warn!("Unknown message type: {}", msg_type);
-                                }
-                            }
-                        }
-                    }
-                }
+                    handle_client_message(&pool_clone, &connection_id_clone, &text).await;
+                },
                 Message::Binary(_) => {
                     debug!("Received binary message from {}", connection_id_clone);
-                }
+                },
                 Message::Ping(data) => {
                     debug!("Received ping from {}", connection_id_clone);
-                    let pool = state_clone.ws_pool.read().await;
-                    if let Some(tx) = pool.get(&connection_id_clone) {
-                        let _ = tx.send(Message::Pong(data));
+                    let pool = pool_clone.read().await;
+                    if let Some(conn) = pool.get(&connection_id_clone) {
+                        let _ = conn.tx.send(Message::Pong(data));
                     }
-                }
+                },
                 Message::Pong(_) => {
                     debug!("Received pong from {}", connection_id_clone);
-                }
+                },
                 Message::Close(_) => {
                     info!("Client {} requested close", connection_id_clone);
                     break;
-                }
+                },
             }
         }
     });
@@ -176,20 +243,59 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     // 清理连接
     heartbeat_task.abort();
     {
-        let mut pool = state.ws_pool.write().await;
+        let mut pool = pool.write().await;
         pool.remove(&connection_id);
     }
-    
+
     info!("WebSocket connection closed: {}", connection_id);
 }
 
+/// 客户端上行消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Pong,
+    /// 设置订阅条件（替换已有订阅）
+    Subscribe(Subscription),
+    /// 取消过滤，恢复接收全部通知
+    Unsubscribe,
+}
+
+/// 处理客户端文本消息
+async fn handle_client_message(pool: &ConnectionPool, connection_id: &str, text: &str) {
+    let subscription = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(ClientMessage::Pong) => {
+            debug!("Received pong from {}", connection_id);
+            return;
+        },
+        Ok(ClientMessage::Subscribe(subscription)) => subscription,
+        Ok(ClientMessage::Unsubscribe) => Subscription::default(),
+        Err(e) => {
+            warn!("Invalid message from {}: {}", connection_id, e);
+            return;
+        },
+    };
+
+    info!("Client {} subscribed: {:?}", connection_id, subscription);
+
+    let mut pool = pool.write().await;
+    if let Some(conn) = pool.get_mut(connection_id) {
+        let reply = serde_json::json!({
+            "type": "subscribed",
+            "subscription": subscription,
+        });
+        conn.subscription = subscription;
+        let _ = conn.tx.send(Message::Text(reply.to_string()));
+    }
+}
+
 /// 向所有连接的客户端广播消息
 pub async fn broadcast_message(pool: &ConnectionPool, message: Message) {
     let pool_read = pool.read().await;
     let mut failed_connections = Vec::new();
     
-    for (conn_id, tx) in pool_read.iter() {
-        if tx.send(message.clone()).is_err() {
+    for (conn_id, conn) in pool_read.iter() {
+        if conn.tx.send(message.clone()).is_err() {
             failed_connections.push(conn_id.clone());
         }
     }
@@ -206,6 +312,48 @@ pub async fn broadcast_message(pool: &ConnectionPool, message: Message) {
     }
 }
 
+/// 向租户和订阅条件匹配的客户端发送通知，返回送达的连接数
+pub async fn deliver_notification(
+    pool: &ConnectionPool,
+    notification: &Notification,
+    message: Message,
+) -> usize {
+    let pool_read = pool.read().await;
+    let mut delivered = 0;
+    let mut failed_connections = Vec::new();
+
+    for (conn_id, conn) in pool_read.iter() {
+        if !tenant_can_receive(&conn.tenant, notification) || !conn.subscription.matches(notification)
+        {
+            continue;
+        }
+
+        // 已在重连补发中发送过
+        if notification.seq.is_some_and(|seq| seq <= conn.replayed_seq) {
+            continue;
+        }
+
+        if conn.tx.send(message.clone()).is_ok() {
+            delivered += 1;
+        } else {
+            failed_connections.push(conn_id.clone());
+        }
+    }
+
+    drop(pool_read);
+
+    // 清理失败的连接
+    if !failed_connections.is_empty() {
+        let mut pool_write = pool.write().await;
+        for conn_id in failed_connections {
+            pool_write.remove(&conn_id);
+            warn!("Removed failed connection: {}", conn_id);
+        }
+    }
+
+    delivered
+}
+
 /// 向特定连接发送消息
 pub async fn send_to_connection(
     pool: &ConnectionPool,
@@ -214,8 +362,8 @@ pub async fn send_to_connection(
 ) -> Result<(), String> {
     let pool_read = pool.read().await;
     
-    if let Some(tx) = pool_read.get(connection_id) {
-        tx.send(message)
+    if let Some(conn) = pool_read.get(connection_id) {
+        conn.tx.send(message)
             .map_err(|e| format!("Failed to send message: {}", e))?;
         Ok(())
     } else {
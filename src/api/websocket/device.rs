@@ -0,0 +1,199 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    response::IntoResponse,
+};
+use futures::{sink::SinkExt, stream::StreamExt};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    api::{middleware::DeviceCredentials, AppState},
+    utils::error::AppError,
+};
+
+/// 设备连接
+struct DeviceConnection {
+    connection_id: String,
+    connected_at: String,
+    tx: mpsc::UnboundedSender<Message>,
+}
+
+/// 在线设备连接信息
+#[derive(Debug, Clone)]
+pub struct DeviceConnectionInfo {
+    pub device_id: String,
+    pub connection_id: String,
+    pub connected_at: String,
+}
+
+/// 设备WebSocket通道
+///
+/// 按设备ID维护在线连接，同一设备重复连接时新连接替换旧连接
+#[derive(Clone, Default)]
+pub struct DeviceChannel {
+    connections: Arc<RwLock<HashMap<String, DeviceConnection>>>,
+}
+
+impl DeviceChannel {
+    /// 创建新的设备通道
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册设备连接，返回连接ID
+    pub async fn register(&self, device_id: &str, tx: mpsc::UnboundedSender<Message>) -> String {
+        let connection_id = Uuid::new_v4().to_string();
+        let mut connections = self.connections.write().await;
+        connections.insert(
+            device_id.to_string(),
+            DeviceConnection {
+                connection_id: connection_id.clone(),
+                connected_at: chrono::Utc::now().to_rfc3339(),
+                tx,
+            },
+        );
+
+        connection_id
+    }
+
+    /// 注销设备连接（仅当仍为该连接时）
+    pub async fn unregister(&self, device_id: &str, connection_id: &str) {
+        let mut connections = self.connections.write().await;
+        if connections.get(device_id).is_some_and(|c| c.connection_id == connection_id) {
+            connections.remove(device_id);
+        }
+    }
+
+    /// 设备是否在线
+    pub async fn is_connected(&self, device_id: &str) -> bool {
+        self.connections.read().await.contains_key(device_id)
+    }
+
+    /// 向设备发送JSON消息，设备不在线或发送失败时返回false
+    pub async fn send_json(&self, device_id: &str, message: &serde_json::Value) -> bool {
+        let connections = self.connections.read().await;
+        match connections.get(device_id) {
+            Some(connection) => connection.tx.send(Message::Text(message.to_string())).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 在线设备ID列表
+    pub async fn connected_device_ids(&self) -> Vec<String> {
+        self.connections.read().await.keys().cloned().collect()
+    }
+
+    /// 在线设备连接列表
+    pub async fn connections(&self) -> Vec<DeviceConnectionInfo> {
+        self.connections
+            .read()
+            .await
+            .iter()
+            .map(|(device_id, c)| DeviceConnectionInfo {
+                device_id: device_id.clone(),
+                connection_id: c.connection_id.clone(),
+                connected_at: c.connected_at.clone(),
+            })
+            .collect()
+    }
+}
+
+/// 设备上行消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum DeviceMessage {
+    Pong,
+    /// 确认收到指令
+    CommandAck {
+        command_id: String,
+    },
+    /// 上报指令执行结果
+    CommandResult {
+        command_id: String,
+        success: bool,
+        #[serde(default)]
+        result: Option<serde_json::Value>,
+        #[serde(default)]
+        error: Option<String>,
+    },
+}
+
+/// 设备WebSocket连接处理器
+///
+/// 升级前校验设备签名凭证（见 [`DeviceCredentials`]），仅向该设备单播消息
+///
+/// GET /api/v1/devices/:device_id/ws
+pub async fn device_websocket_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    credentials: DeviceCredentials,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .device_service
+        .authenticate_device(&device_id, &credentials)
+        .await?;
+    state.device_command_service.connect_device(&device_id).await?;
+
+    Ok(ws.on_upgrade(move |socket| handle_device_socket(socket, state, device_id)))
+}
+
+/// 处理设备WebSocket连接
+async fn handle_device_socket(socket: WebSocket, state: Arc<AppState>, device_id: String) {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+    let connection_id = state.device_channel.register(&device_id, tx).await;
+    info!("Device {} connected: {}", device_id, connection_id);
+
+    let (mut sender, mut receiver) = socket.split();
+
+    let send_task = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if sender.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // 连接建立后补发离线期间的待投递指令
+    if let Err(e) = state.device_command_service.deliver_pending(&device_id).await {
+        warn!("Failed to deliver pending commands to {}: {}", device_id, e);
+    }
+
+    while let Some(Ok(msg)) = receiver.next().await {
+        match msg {
+            Message::Text(text) => match serde_json::from_str::<DeviceMessage>(&text) {
+                Ok(DeviceMessage::Pong) => debug!("Received pong from device {}", device_id),
+                Ok(DeviceMessage::CommandAck { command_id }) => {
+                    if let Err(e) =
+                        state.device_command_service.acknowledge(&device_id, &command_id).await
+                    {
+                        warn!("Failed to acknowledge command {}: {}", command_id, e);
+                    }
+                },
+                Ok(DeviceMessage::CommandResult { command_id, success, result, error }) => {
+                    if let Err(e) = state
+                        .device_command_service
+                        .report_result(&device_id, &command_id, success, result, error)
+                        .await
+                    {
+                        warn!("Failed to record result of command {}: {}", command_id, e);
+                    }
+                },
+                Err(e) => warn!("Invalid message from device {}: {}", device_id, e),
+            },
+            Message::Close(_) => break,
+            _ => {},
+        }
+    }
+
+    send_task.abort();
+    state.device_channel.unregister(&device_id, &connection_id).await;
+
+    info!("Device {} disconnected: {}", device_id, connection_id);
+}
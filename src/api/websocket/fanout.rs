@@ -0,0 +1,132 @@
+use axum::extract::ws::Message;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use super::{
+    connection::{deliver_notification, ConnectionPool},
+    notification::Notification,
+};
+use crate::{infrastructure::redis::RedisClient, utils::error::AppError};
+
+/// 跨节点通知频道
+pub const NOTIFICATION_CHANNEL: &str = "softpos:notifications";
+
+/// 订阅断开后的最大重连间隔
+const MAX_RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// 跨节点广播的通知信封
+#[derive(Debug, Serialize, Deserialize)]
+struct FanoutEnvelope {
+    /// 发布节点ID，节点收到自己发布的消息时忽略
+    node_id: String,
+    notification: Notification,
+}
+
+/// 基于Redis发布订阅的跨节点通知分发
+///
+/// 每个节点将本地产生的通知发布到共享频道，并订阅该频道把其他节点的通知推送给本地连接
+pub struct NotificationFanout {
+    redis: RedisClient,
+    node_id: String,
+    channel: String,
+}
+
+impl NotificationFanout {
+    /// 创建跨节点分发器
+    pub fn new(redis: RedisClient, node_id: impl Into<String>) -> Self {
+        Self { redis, node_id: node_id.into(), channel: NOTIFICATION_CHANNEL.to_string() }
+    }
+
+    /// 使用指定频道（多套环境共用Redis时隔离）
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = channel.into();
+        self
+    }
+
+    /// 当前节点ID
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// 将本地通知发布给其他节点
+    pub async fn publish(&self, notification: &Notification) -> Result<(), AppError> {
+        let envelope =
+            FanoutEnvelope { node_id: self.node_id.clone(), notification: notification.clone() };
+        let payload = serde_json::to_string(&envelope).map_err(|e| {
+            AppError::InternalWithMessage(format!("Failed to serialize notification: {}", e))
+        })?;
+
+        self.redis.publish(&self.channel, &payload).await?;
+
+        Ok(())
+    }
+
+    /// 订阅其他节点的通知并推送给本地连接
+    ///
+    /// 首次订阅成功后返回，之后在后台任务中接收消息，连接断开时按指数退避重新订阅
+    pub async fn subscribe(
+        self: Arc<Self>,
+        pool: ConnectionPool,
+    ) -> Result<JoinHandle<()>, AppError> {
+        let mut messages = self.redis.subscribe(&self.channel).await?;
+        info!("Node {} subscribed to notification channel {}", self.node_id, self.channel);
+
+        Ok(tokio::spawn(async move {
+            loop {
+                while let Some(msg) = messages.next().await {
+                    match msg.get_payload::<String>() {
+                        Ok(payload) => self.handle_message(&pool, &payload).await,
+                        Err(e) => warn!("Invalid notification fanout payload: {}", e),
+                    }
+                }
+
+                warn!("Notification channel subscription lost, resubscribing");
+                let mut backoff = Duration::from_secs(1);
+                loop {
+                    tokio::time::sleep(backoff).await;
+                    match self.redis.subscribe(&self.channel).await {
+                        Ok(resubscribed) => {
+                            info!("Node {} resubscribed to {}", self.node_id, self.channel);
+                            messages = resubscribed;
+                            break;
+                        },
+                        Err(e) => {
+                            error!("Failed to resubscribe to {}: {}", self.channel, e);
+                            backoff = (backoff * 2).min(MAX_RESUBSCRIBE_BACKOFF);
+                        },
+                    }
+                }
+            }
+        }))
+    }
+
+    /// 处理其他节点发布的通知
+    async fn handle_message(&self, pool: &ConnectionPool, payload: &str) {
+        let envelope = match serde_json::from_str::<FanoutEnvelope>(payload) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                warn!("Invalid notification fanout envelope: {}", e);
+                return;
+            },
+        };
+
+        if envelope.node_id == self.node_id {
+            return;
+        }
+
+        match serde_json::to_string(&envelope.notification) {
+            Ok(json) => {
+                let delivered =
+                    deliver_notification(pool, &envelope.notification, Message::Text(json)).await;
+                debug!(
+                    "Notification from node {} delivered to {} connections",
+                    envelope.node_id, delivered
+                );
+            },
+            Err(e) => error!("Failed to serialize notification: {}", e),
+        }
+    }
+}
@@ -1,5 +1,11 @@
 pub mod connection;
+pub mod device;
+pub mod fanout;
 pub mod notification;
+pub mod subscription;
 
 pub use connection::*;
+pub use device::*;
+pub use fanout::*;
 pub use notification::*;
+pub use subscription::*;
@@ -1,11 +1,23 @@
 use axum::extract::ws::Message;
 use serde::{Deserialize, Serialize};
-use tracing::{error, info};
+use tracing::{debug, error, info};
 
-use super::connection::{broadcast_message, ConnectionPool};
+use std::sync::Arc;
+
+use super::{
+    connection::{deliver_notification, ClientConnection, ConnectionPool},
+    fanout::NotificationFanout,
+};
+use crate::{
+    dto::{NotificationHistoryResponse, NotificationResponse},
+    models::{NotificationRecord, TenantContext},
+    repositories::NotificationRepository,
+    services::AlertDispatcher,
+    utils::error::AppError,
+};
 
 /// 通知类型
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum NotificationType {
     SecurityAlert,
@@ -16,7 +28,7 @@ pub enum NotificationType {
 }
 
 /// 通知严重级别
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum NotificationSeverity {
     High,
@@ -25,15 +37,35 @@ pub enum NotificationSeverity {
     Info,
 }
 
+impl NotificationSeverity {
+    /// 严重程度排序值，越大越严重
+    pub fn rank(&self) -> u8 {
+        match self {
+            NotificationSeverity::High => 3,
+            NotificationSeverity::Medium => 2,
+            NotificationSeverity::Low => 1,
+            NotificationSeverity::Info => 0,
+        }
+    }
+}
+
 /// 通知消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notification {
+    /// 持久化后分配的序列号，客户端重连时据此补发遗漏的通知
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<i64>,
     #[serde(rename = "type")]
     pub notification_type: NotificationType,
     pub severity: NotificationSeverity,
     pub title: String,
     pub message: String,
     pub device_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merchant_id: Option<String>,
+    /// 所属租户，未设置时只发给跨租户连接
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
     pub threat_id: Option<String>,
     pub timestamp: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -41,6 +73,53 @@ pub struct Notification {
 }
 
 impl Notification {
+    /// 设置关联商户
+    pub fn with_merchant_id(mut self, merchant_id: String) -> Self {
+        self.merchant_id = Some(merchant_id);
+        self
+    }
+
+    /// 设置所属租户
+    pub fn with_tenant_id(mut self, tenant_id: String) -> Self {
+        self.tenant_id = Some(tenant_id);
+        self
+    }
+
+    /// 转换为持久化记录
+    pub fn to_record(&self) -> NotificationRecord {
+        NotificationRecord {
+            seq: 0,
+            tenant_id: self.tenant_id.clone(),
+            notification_type: enum_name(&self.notification_type),
+            severity: enum_name(&self.severity),
+            title: self.title.clone(),
+            message: self.message.clone(),
+            device_id: self.device_id.clone(),
+            merchant_id: self.merchant_id.clone(),
+            threat_id: self.threat_id.clone(),
+            data: self.data.as_ref().map(|d| d.to_string()),
+            created_at: self.timestamp.clone(),
+            read_at: None,
+        }
+    }
+
+    /// 从持久化记录还原
+    pub fn from_record(record: NotificationRecord) -> Option<Self> {
+        Some(Self {
+            seq: Some(record.seq),
+            notification_type: serde_json::from_value(record.notification_type.into()).ok()?,
+            severity: serde_json::from_value(record.severity.into()).ok()?,
+            title: record.title,
+            message: record.message,
+            device_id: record.device_id,
+            merchant_id: record.merchant_id,
+            tenant_id: record.tenant_id,
+            threat_id: record.threat_id,
+            timestamp: record.created_at,
+            data: record.data.and_then(|d| serde_json::from_str(&d).ok()),
+        })
+    }
+
     /// 创建安全告警通知
     pub fn security_alert(
         device_id: String,
@@ -57,6 +136,9 @@ impl Notification {
             title: "设备安全告警".to_string(),
             message,
             device_id: Some(device_id.clone()),
+            merchant_id: None,
+            tenant_id: None,
+            seq: None,
             threat_id: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
             data: Some(serde_json::json!({
@@ -75,7 +157,7 @@ impl Notification {
         message: String,
     ) -> Self {
         let notification_severity = match severity.as_str() {
-            "HIGH" => NotificationSeverity::High,
+            "CRITICAL" | "HIGH" => NotificationSeverity::High,
             "MEDIUM" => NotificationSeverity::Medium,
             _ => NotificationSeverity::Low,
         };
@@ -86,6 +168,9 @@ impl Notification {
             title: "威胁检测告警".to_string(),
             message,
             device_id: Some(device_id.clone()),
+            merchant_id: None,
+            tenant_id: None,
+            seq: None,
             threat_id: Some(threat_id.clone()),
             timestamp: chrono::Utc::now().to_rfc3339(),
             data: Some(serde_json::json!({
@@ -110,6 +195,9 @@ impl Notification {
             title: "密钥预警".to_string(),
             message,
             device_id: Some(device_id.clone()),
+            merchant_id: None,
+            tenant_id: None,
+            seq: None,
             threat_id: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
             data: Some(serde_json::json!({
@@ -140,6 +228,9 @@ impl Notification {
                 reason.as_ref().map(|r| format!("，原因：{}", r)).unwrap_or_default()
             ),
             device_id: Some(device_id.clone()),
+            merchant_id: None,
+            tenant_id: None,
+            seq: None,
             threat_id: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
             data: Some(serde_json::json!({
@@ -164,6 +255,9 @@ impl Notification {
             title,
             message,
             device_id: None,
+            merchant_id: None,
+            tenant_id: None,
+            seq: None,
             threat_id: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
             data,
@@ -171,33 +265,195 @@ impl Notification {
     }
 }
 
+/// 枚举的序列化名称
+fn enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// 单次重连补发的最大通知数量
+pub const MAX_REPLAY_NOTIFICATIONS: i64 = 500;
+
 /// 通知服务
+///
+/// 配置通知存储后，通知先持久化并分配序列号再推送，客户端可凭 `last_seq` 重连补发
 pub struct NotificationService {
     pool: ConnectionPool,
+    store: Option<NotificationRepository>,
+    fanout: Option<Arc<NotificationFanout>>,
+    alerting: Option<Arc<AlertDispatcher>>,
 }
 
 impl NotificationService {
     /// 创建新的通知服务
     pub fn new(pool: ConnectionPool) -> Self {
-        Self { pool }
+        Self { pool, store: None, fanout: None, alerting: None }
+    }
+
+    /// 配置通知存储
+    pub fn with_store(mut self, store: NotificationRepository) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// 配置跨节点分发，未配置时仅推送给本节点的连接
+    pub fn with_fanout(mut self, fanout: Arc<NotificationFanout>) -> Self {
+        self.fanout = Some(fanout);
+        self
+    }
+
+    /// 配置外部告警渠道（邮件、短信/IM），未配置时只推送到管理端
+    pub fn with_alerting(mut self, alerting: Arc<AlertDispatcher>) -> Self {
+        self.alerting = Some(alerting);
+        self
+    }
+
+    /// 连接池
+    pub fn pool(&self) -> &ConnectionPool {
+        &self.pool
+    }
+
+    /// 注册连接，并补发 `last_seq` 之后租户可见的通知
+    ///
+    /// 注册与补发在连接池写锁内完成，补发范围内的通知不会再被实时推送重复发送
+    pub async fn register_connection(
+        &self,
+        connection_id: String,
+        mut connection: ClientConnection,
+        last_seq: Option<i64>,
+    ) {
+        let mut pool = self.pool.write().await;
+
+        if let (Some(last_seq), Some(store)) = (last_seq, &self.store) {
+            match store
+                .for_tenant(&connection.tenant)
+                .list_since(last_seq, MAX_REPLAY_NOTIFICATIONS)
+                .await
+            {
+                Ok(records) => {
+                    let truncated = records.len() as i64 == MAX_REPLAY_NOTIFICATIONS;
+                    let mut replayed_seq = last_seq;
+
+                    for notification in records.into_iter().filter_map(Notification::from_record) {
+                        replayed_seq = notification.seq.unwrap_or(replayed_seq);
+                        if let Ok(json) = serde_json::to_string(&notification) {
+                            let _ = connection.tx.send(Message::Text(json));
+                        }
+                    }
+
+                    // 截断时客户端可凭 `last_seq` 重连继续补发
+                    connection.replayed_seq = replayed_seq;
+
+                    let complete = serde_json::json!({
+                        "type": "replay_complete",
+                        "last_seq": replayed_seq,
+                        "truncated": truncated,
+                    });
+                    let _ = connection.tx.send(Message::Text(complete.to_string()));
+                },
+                Err(e) => error!("Failed to replay notifications for {}: {}", connection_id, e),
+            }
+        }
+
+        pool.insert(connection_id, connection);
+    }
+
+    /// 分页查询当前用户的历史通知
+    pub async fn list_history(
+        &self,
+        tenant: &TenantContext,
+        user_id: &str,
+        before_seq: Option<i64>,
+        after_seq: Option<i64>,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<NotificationHistoryResponse, AppError> {
+        let store = self.store_for(tenant)?;
+        let records =
+            store.list_for_user(user_id, before_seq, after_seq, unread_only, limit).await?;
+
+        let next_before_seq =
+            if records.len() as i64 == limit { records.last().map(|r| r.seq) } else { None };
+
+        Ok(NotificationHistoryResponse {
+            notifications: records.into_iter().map(NotificationResponse::from).collect(),
+            unread_count: store.count_unread(user_id).await?,
+            next_before_seq,
+        })
+    }
+
+    /// 将单条通知标记为已读
+    pub async fn acknowledge(
+        &self,
+        tenant: &TenantContext,
+        user_id: &str,
+        seq: i64,
+    ) -> Result<(), AppError> {
+        let store = self.store_for(tenant)?;
+        if store.find_by_seq(seq).await?.is_none() {
+            return Err(AppError::NotFound("Notification not found".to_string()));
+        }
+
+        store.mark_read(user_id, seq).await
+    }
+
+    /// 将指定序列号及之前的通知全部标记为已读，返回新标记的数量
+    pub async fn acknowledge_up_to(
+        &self,
+        tenant: &TenantContext,
+        user_id: &str,
+        up_to_seq: i64,
+    ) -> Result<u64, AppError> {
+        self.store_for(tenant)?.mark_read_up_to(user_id, up_to_seq).await
+    }
+
+    fn store_for(&self, tenant: &TenantContext) -> Result<NotificationRepository, AppError> {
+        self.store.as_ref().map(|s| s.for_tenant(tenant)).ok_or_else(|| {
+            AppError::InternalWithMessage("Notification store is not configured".to_string())
+        })
     }
 
     /// 发送通知
-    pub async fn send_notification(&self, notification: Notification) {
+    pub async fn send_notification(&self, mut notification: Notification) {
         info!(
             "Sending notification: type={:?}, severity={:?}, device_id={:?}",
             notification.notification_type, notification.severity, notification.device_id
         );
 
+        // 持久化失败时仍实时推送，仅无法补发
+        if let Some(store) = &self.store {
+            match store.create(&notification.to_record()).await {
+                Ok(seq) => notification.seq = Some(seq),
+                Err(e) => error!("Failed to persist notification: {}", e),
+            }
+        }
+
         match serde_json::to_string(&notification) {
             Ok(json) => {
                 let message = Message::Text(json);
-                broadcast_message(&self.pool, message).await;
+                let delivered = deliver_notification(&self.pool, &notification, message).await;
+                debug!("Notification delivered to {} connections", delivered);
             }
             Err(e) => {
                 error!("Failed to serialize notification: {}", e);
             }
         }
+
+        if let Some(fanout) = &self.fanout {
+            if let Err(e) = fanout.publish(&notification).await {
+                error!("Failed to publish notification to other nodes: {}", e);
+            }
+        }
+
+        // 外部渠道可能较慢，不阻塞实时推送；只由产生通知的节点发送
+        if let Some(alerting) = &self.alerting {
+            let alerting = alerting.clone();
+            tokio::spawn(async move {
+                alerting.dispatch(&notification).await;
+            });
+        }
     }
 
     /// 发送安全告警
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use super::notification::{Notification, NotificationSeverity, NotificationType};
+use crate::models::TenantContext;
+
+/// 通知订阅条件
+///
+/// 未设置的条件不参与过滤，默认订阅全部通知。
+/// `types` 与 `min_severity` 对所有通知生效；`merchant_ids` 与 `device_ids` 共同限定设备范围，
+/// 设备或所属商户命中其一即可，未关联设备和商户的系统通知不受其限制。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Subscription {
+    /// 通知类型
+    pub types: Vec<NotificationType>,
+    /// 最低严重级别
+    pub min_severity: Option<NotificationSeverity>,
+    pub merchant_ids: Vec<String>,
+    pub device_ids: Vec<String>,
+}
+
+impl Subscription {
+    /// 判断通知是否符合订阅条件
+    pub fn matches(&self, notification: &Notification) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&notification.notification_type) {
+            return false;
+        }
+
+        if self.min_severity.is_some_and(|min| notification.severity.rank() < min.rank()) {
+            return false;
+        }
+
+        if self.merchant_ids.is_empty() && self.device_ids.is_empty() {
+            return true;
+        }
+
+        if notification.device_id.is_none() && notification.merchant_id.is_none() {
+            return true;
+        }
+
+        notification.device_id.as_ref().is_some_and(|id| self.device_ids.contains(id))
+            || notification
+                .merchant_id
+                .as_ref()
+                .is_some_and(|id| self.merchant_ids.contains(id))
+    }
+}
+
+/// 判断连接的租户是否可以接收通知
+///
+/// 租户连接只接收本租户的通知；未标记租户的系统通知只发给跨租户连接
+pub fn tenant_can_receive(tenant: &TenantContext, notification: &Notification) -> bool {
+    tenant.cross_tenant || notification.tenant_id.as_ref() == Some(&tenant.tenant_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threat(device_id: &str, merchant_id: Option<&str>, severity: &str) -> Notification {
+        let notification = Notification::threat_alert(
+            device_id.to_string(),
+            "threat-1".to_string(),
+            "ROOT_DETECTED".to_string(),
+            severity.to_string(),
+            "threat".to_string(),
+        );
+        match merchant_id {
+            Some(id) => notification.with_merchant_id(id.to_string()),
+            None => notification,
+        }
+    }
+
+    #[test]
+    fn test_default_matches_everything() {
+        assert!(Subscription::default().matches(&threat("d1", None, "LOW")));
+    }
+
+    #[test]
+    fn test_type_and_severity_filter() {
+        let subscription = Subscription {
+            types: vec![NotificationType::ThreatAlert],
+            min_severity: Some(NotificationSeverity::Medium),
+            ..Default::default()
+        };
+
+        assert!(subscription.matches(&threat("d1", None, "HIGH")));
+        assert!(subscription.matches(&threat("d1", None, "MEDIUM")));
+        assert!(!subscription.matches(&threat("d1", None, "LOW")));
+        assert!(!subscription.matches(&Notification::security_alert(
+            "d1".to_string(),
+            30,
+            "low score".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_device_and_merchant_scope() {
+        let subscription = Subscription {
+            merchant_ids: vec!["m1".to_string()],
+            device_ids: vec!["d2".to_string()],
+            ..Default::default()
+        };
+
+        assert!(subscription.matches(&threat("d1", Some("m1"), "LOW")));
+        assert!(subscription.matches(&threat("d2", None, "LOW")));
+        assert!(!subscription.matches(&threat("d3", Some("m2"), "LOW")));
+
+        // 系统告警不受设备范围限制
+        assert!(subscription.matches(&Notification::system_alert(
+            NotificationSeverity::High,
+            "maintenance".to_string(),
+            "maintenance".to_string(),
+            None
+        )));
+    }
+
+    #[test]
+    fn test_tenant_filter() {
+        let notification = threat("d1", None, "HIGH").with_tenant_id("acq-a".to_string());
+
+        assert!(tenant_can_receive(&TenantContext::new("acq-a"), &notification));
+        assert!(!tenant_can_receive(&TenantContext::new("acq-b"), &notification));
+        assert!(tenant_can_receive(
+            &TenantContext::cross_tenant("default".to_string()),
+            &notification
+        ));
+
+        // 未标记租户的通知只发给跨租户连接
+        let untagged = threat("d1", None, "HIGH");
+        assert!(!tenant_can_receive(&TenantContext::new("acq-a"), &untagged));
+        assert!(tenant_can_receive(&TenantContext::cross_tenant("default".to_string()), &untagged));
+    }
+}
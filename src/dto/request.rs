@@ -1,4 +1,8 @@
-use crate::models::{DeviceMode, TeeType, TransactionType, UpdateType};
+use crate::models::{
+    emv::MAX_EMV_DATA_LENGTH, normalize_tag, redact_emv_data, CommandType, DeviceMode, GroupRules, GroupType,
+    MerchantStatus, StoreStatus, TeeType, TenantBranding, TenantStatus, TransactionType,
+    UpdateType, WebhookEventType,
+};
 use serde::{Deserialize, Serialize};
 
 /// 设备注册请求
@@ -13,6 +17,9 @@ pub struct RegisterDeviceRequest {
     pub device_mode: DeviceMode,
     #[serde(default)]
     pub nfc_present: bool,
+    /// 预审批的一次性注册码，匹配时设备注册后自动激活
+    #[serde(default)]
+    pub enrollment_code: Option<String>,
 }
 
 fn default_device_mode() -> DeviceMode {
@@ -174,7 +181,7 @@ impl RejectDeviceRequest {
     }
 }
 
-/// 设备操作请求（暂停、吊销）
+/// 设备操作请求（暂停、吊销、退役）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceOperationRequest {
     pub reason: String,
@@ -194,6 +201,28 @@ impl DeviceOperationRequest {
     }
 }
 
+/// 设备替换请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaceDeviceRequest {
+    /// 替换设备ID
+    pub replacement_device_id: String,
+    pub reason: String,
+}
+
+impl ReplaceDeviceRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.replacement_device_id.trim().is_empty() {
+            return Err("Replacement device ID cannot be empty".to_string());
+        }
+
+        if self.reason.trim().is_empty() {
+            return Err("Reason cannot be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
 /// 交易鉴证请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -225,7 +254,9 @@ impl AttestTransactionRequest {
 }
 
 /// 交易处理请求
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `Debug` 输出中的EMV数据已脱敏
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProcessTransactionRequest {
     pub device_id: String,
@@ -246,6 +277,38 @@ pub struct ProcessTransactionRequest {
     pub location_accuracy: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub location_timestamp: Option<String>,
+    /// 原交易ID，退款、撤销、请款和追加授权（预授权）时填写
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_transaction_id: Option<String>,
+    /// 客户端生成的交易ID，未携带 `Idempotency-Key` 请求头时作为幂等键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_transaction_id: Option<String>,
+    /// 非接/芯片交易的EMV数据（BER-TLV十六进制），磁道和主账号等敏感标签不保存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emv_data: Option<String>,
+}
+
+impl std::fmt::Debug for ProcessTransactionRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessTransactionRequest")
+            .field("device_id", &self.device_id)
+            .field("transaction_type", &self.transaction_type)
+            .field("amount", &self.amount)
+            .field("currency", &self.currency)
+            .field("encrypted_pin_block", &self.encrypted_pin_block)
+            .field("ksn", &self.ksn)
+            .field("card_number_masked", &self.card_number_masked)
+            .field("transaction_token", &self.transaction_token)
+            .field("client_ip", &self.client_ip)
+            .field("latitude", &self.latitude)
+            .field("longitude", &self.longitude)
+            .field("location_accuracy", &self.location_accuracy)
+            .field("location_timestamp", &self.location_timestamp)
+            .field("original_transaction_id", &self.original_transaction_id)
+            .field("client_transaction_id", &self.client_transaction_id)
+            .field("emv_data", &self.emv_data.as_deref().map(redact_emv_data))
+            .finish()
+    }
 }
 
 impl ProcessTransactionRequest {
@@ -270,10 +333,51 @@ impl ProcessTransactionRequest {
             return Err("Transaction token cannot be empty".to_string());
         }
 
+        if self.emv_data.as_ref().is_some_and(|data| data.len() > MAX_EMV_DATA_LENGTH * 2) {
+            return Err(format!("EMV data cannot exceed {} bytes", MAX_EMV_DATA_LENGTH));
+        }
+
         Ok(())
     }
 }
 
+/// 设备签名凭证
+///
+/// 设备使用注册公钥对应的私钥对 [`DeviceCredentials::signing_message`] 签名（Base64编码），
+/// 签名覆盖请求方法、路径和请求体摘要，随机数在时间戳允许偏差内只能使用一次
+#[derive(Debug, Clone)]
+pub struct DeviceCredentials {
+    pub timestamp: i64,
+    pub nonce: String,
+    pub signature: String,
+    /// 请求方法（大写）
+    pub method: String,
+    /// 请求路径，不含查询参数
+    pub path: String,
+    /// 请求体SHA-256摘要（小写十六进制），空请求体同样计算摘要
+    pub body_sha256: String,
+}
+
+impl DeviceCredentials {
+    /// 被签名的消息：`{device_id}:{timestamp}:{nonce}:{method}:{path}:{body_sha256}`
+    pub fn signing_message(&self, device_id: &str) -> String {
+        format!(
+            "{}:{}:{}:{}:{}:{}",
+            device_id, self.timestamp, self.nonce, self.method, self.path, self.body_sha256
+        )
+    }
+}
+
+/// 冲正申请（设备未收到交易应答时使用），交易ID和客户端交易ID二选一
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverseTransactionRequest {
+    #[serde(default)]
+    pub transaction_id: Option<String>,
+    #[serde(default)]
+    pub client_transaction_id: Option<String>,
+}
+
 /// SDK版本创建请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateVersionRequest {
@@ -422,3 +526,506 @@ impl ReportThreatRequest {
         Ok(())
     }
 }
+
+/// 创建商户请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMerchantRequest {
+    pub name: String,
+    pub mcc: String,
+    pub country: String,
+    pub currency: String,
+    pub acquirer_mid: Option<String>,
+    pub address: Option<String>,
+}
+
+impl CreateMerchantRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Merchant name cannot be empty".to_string());
+        }
+
+        // MCC为4位数字
+        if self.mcc.len() != 4 || !self.mcc.chars().all(|c| c.is_ascii_digit()) {
+            return Err("MCC must be 4 digits".to_string());
+        }
+
+        // 国家代码为2位大写字母
+        if self.country.len() != 2 || !self.country.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err("Country must be an ISO 3166-1 alpha-2 code".to_string());
+        }
+
+        // 币种为3位大写字母
+        if self.currency.len() != 3 || !self.currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err("Currency must be an ISO 4217 code".to_string());
+        }
+
+        if let Some(mid) = &self.acquirer_mid {
+            if mid.trim().is_empty() || mid.len() > 15 {
+                return Err("Acquirer MID must be 1-15 characters".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 更新商户请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateMerchantRequest {
+    pub name: Option<String>,
+    pub address: Option<String>,
+    pub acquirer_mid: Option<String>,
+    pub status: Option<MerchantStatus>,
+}
+
+impl UpdateMerchantRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.name {
+            if name.trim().is_empty() {
+                return Err("Merchant name cannot be empty".to_string());
+            }
+        }
+
+        if let Some(mid) = &self.acquirer_mid {
+            if mid.trim().is_empty() || mid.len() > 15 {
+                return Err("Acquirer MID must be 1-15 characters".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 创建门店请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStoreRequest {
+    pub name: String,
+    pub address: Option<String>,
+}
+
+impl CreateStoreRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Store name cannot be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// 更新门店请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStoreRequest {
+    pub name: Option<String>,
+    pub address: Option<String>,
+    pub status: Option<StoreStatus>,
+}
+
+impl UpdateStoreRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.name {
+            if name.trim().is_empty() {
+                return Err("Store name cannot be empty".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 设备分配门店请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssignDeviceRequest {
+    pub store_id: String,
+}
+
+impl AssignDeviceRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.store_id.trim().is_empty() {
+            return Err("Store ID cannot be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// 注册码最短长度
+const MIN_ENROLLMENT_CODE_LENGTH: usize = 8;
+
+/// 设备导入行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceImportRow {
+    pub imei: String,
+    pub model: String,
+    #[serde(default)]
+    pub merchant_id: Option<String>,
+    #[serde(default)]
+    pub store_id: Option<String>,
+    /// 一次性注册码，由导入方交给设备，设备注册时携带
+    #[serde(default)]
+    pub enrollment_code: Option<String>,
+}
+
+impl DeviceImportRow {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.imei.len() != 15 || !self.imei.chars().all(|c| c.is_ascii_digit()) {
+            return Err("IMEI must be 15 digits".to_string());
+        }
+
+        if self.model.trim().is_empty() {
+            return Err("Model cannot be empty".to_string());
+        }
+
+        if self.merchant_id.is_some() && self.store_id.is_none() {
+            return Err("store_id is required when merchant_id is set".to_string());
+        }
+
+        match &self.enrollment_code {
+            None => return Err("enrollment_code is required".to_string()),
+            Some(code) if !(MIN_ENROLLMENT_CODE_LENGTH..=64).contains(&code.len()) => {
+                return Err(format!(
+                    "enrollment_code must be {} to 64 characters",
+                    MIN_ENROLLMENT_CODE_LENGTH
+                ));
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+}
+
+/// 创建租户请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTenantRequest {
+    pub id: String,
+    pub name: String,
+    /// 租户专属BDK，保存前以密钥加密密钥包装
+    pub bdk: Option<String>,
+    /// HSM中租户专属BDK的密钥引用，与 `bdk` 二选一
+    #[serde(default)]
+    pub bdk_key_ref: Option<String>,
+    pub rate_limit_rps: Option<i64>,
+    pub rate_limit_burst: Option<i64>,
+    pub branding: Option<TenantBranding>,
+}
+
+impl CreateTenantRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        // 租户ID用于JWT和API密钥，仅允许小写字母、数字和连字符
+        if self.id.is_empty()
+            || self.id.len() > 64
+            || !self.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err("Tenant ID must be 1-64 lowercase letters, digits or '-'".to_string());
+        }
+
+        if self.name.trim().is_empty() {
+            return Err("Tenant name cannot be empty".to_string());
+        }
+
+        validate_tenant_settings(
+            &self.bdk,
+            &self.bdk_key_ref,
+            self.rate_limit_rps,
+            self.rate_limit_burst,
+        )
+    }
+}
+
+/// 更新租户请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateTenantRequest {
+    pub name: Option<String>,
+    pub status: Option<TenantStatus>,
+    /// 租户专属BDK，保存前以密钥加密密钥包装
+    pub bdk: Option<String>,
+    /// HSM中租户专属BDK的密钥引用，与 `bdk` 二选一
+    #[serde(default)]
+    pub bdk_key_ref: Option<String>,
+    pub rate_limit_rps: Option<i64>,
+    pub rate_limit_burst: Option<i64>,
+    pub branding: Option<TenantBranding>,
+}
+
+impl UpdateTenantRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.name {
+            if name.trim().is_empty() {
+                return Err("Tenant name cannot be empty".to_string());
+            }
+        }
+
+        validate_tenant_settings(
+            &self.bdk,
+            &self.bdk_key_ref,
+            self.rate_limit_rps,
+            self.rate_limit_burst,
+        )
+    }
+}
+
+fn validate_tenant_settings(
+    bdk: &Option<String>,
+    bdk_key_ref: &Option<String>,
+    rate_limit_rps: Option<i64>,
+    rate_limit_burst: Option<i64>,
+) -> Result<(), String> {
+    if let Some(bdk) = bdk {
+        if bdk.len() < 16 {
+            return Err("BDK must be at least 16 characters".to_string());
+        }
+    }
+
+    match bdk_key_ref {
+        Some(_) if bdk.is_some() => {
+            return Err("Specify either a BDK or an HSM key reference, not both".to_string());
+        },
+        Some(key_ref) if key_ref.trim().is_empty() => {
+            return Err("HSM key reference cannot be empty".to_string());
+        },
+        _ => {},
+    }
+
+    if rate_limit_rps.is_some_and(|rps| rps <= 0) {
+        return Err("Rate limit rps must be positive".to_string());
+    }
+
+    if rate_limit_burst.is_some_and(|burst| burst <= 0) {
+        return Err("Rate limit burst must be positive".to_string());
+    }
+
+    Ok(())
+}
+
+/// 创建API密钥请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    #[serde(default = "default_api_key_role")]
+    pub role: String,
+}
+
+fn default_api_key_role() -> String {
+    "operator".to_string()
+}
+
+impl CreateApiKeyRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("API key name cannot be empty".to_string());
+        }
+
+        // API密钥不能授予超级管理员权限
+        if !matches!(self.role.as_str(), "admin" | "operator" | "viewer") {
+            return Err("API key role must be admin, operator or viewer".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// 创建设备分组请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateDeviceGroupRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub group_type: GroupType,
+    /// 动态分组规则
+    pub rules: Option<GroupRules>,
+    /// 静态分组初始成员
+    #[serde(default)]
+    pub device_ids: Vec<String>,
+}
+
+impl CreateDeviceGroupRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_group_name(&self.name)?;
+
+        match self.group_type {
+            GroupType::Static => {
+                if self.rules.is_some() {
+                    return Err("Static groups cannot have rules".to_string());
+                }
+            },
+            GroupType::Dynamic => {
+                self.rules
+                    .as_ref()
+                    .ok_or_else(|| "Dynamic groups require rules".to_string())?
+                    .validate()?;
+
+                if !self.device_ids.is_empty() {
+                    return Err("Dynamic groups cannot have explicit members".to_string());
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// 更新设备分组请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateDeviceGroupRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// 仅动态分组可更新规则
+    pub rules: Option<GroupRules>,
+}
+
+impl UpdateDeviceGroupRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.name {
+            validate_group_name(name)?;
+        }
+
+        if let Some(rules) = &self.rules {
+            rules.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_group_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.len() > 128 {
+        return Err("Group name must be 1-128 characters".to_string());
+    }
+
+    Ok(())
+}
+
+/// 设备分组成员请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMembersRequest {
+    pub device_ids: Vec<String>,
+}
+
+impl GroupMembersRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.device_ids.is_empty() {
+            return Err("Device IDs cannot be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// 设置设备标签请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetDeviceTagsRequest {
+    pub tags: Vec<String>,
+}
+
+impl SetDeviceTagsRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tags.len() > 32 {
+            return Err("A device can have at most 32 tags".to_string());
+        }
+
+        for tag in &self.tags {
+            normalize_tag(tag)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 创建设备指令请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateDeviceCommandRequest {
+    pub command_type: CommandType,
+    pub payload: Option<serde_json::Value>,
+    /// 单次投递后等待设备回执的超时时间（秒）
+    pub timeout_seconds: Option<i64>,
+    /// 最大投递次数
+    pub max_attempts: Option<i64>,
+}
+
+impl CreateDeviceCommandRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.command_type == CommandType::UpdateConfig
+            && !self.payload.as_ref().is_some_and(|p| p.is_object())
+        {
+            return Err("UPDATE_CONFIG requires an object payload".to_string());
+        }
+
+        if self.timeout_seconds.is_some_and(|t| !(10..=86400).contains(&t)) {
+            return Err("Timeout must be between 10 and 86400 seconds".to_string());
+        }
+
+        if self.max_attempts.is_some_and(|n| !(1..=10).contains(&n)) {
+            return Err("Max attempts must be between 1 and 10".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// 设备指令执行结果上报请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandResultRequest {
+    pub success: bool,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+/// 批量标记通知已读请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AckNotificationsRequest {
+    /// 标记该序列号及之前的全部通知
+    pub up_to_seq: i64,
+}
+
+/// 创建Webhook订阅请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub merchant_id: String,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+}
+
+impl CreateWebhookRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_webhook_url(&self.url)?;
+        validate_webhook_event_types(&self.event_types)
+    }
+}
+
+/// 更新Webhook订阅请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub event_types: Option<Vec<WebhookEventType>>,
+    pub active: Option<bool>,
+}
+
+impl UpdateWebhookRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(url) = &self.url {
+            validate_webhook_url(url)?;
+        }
+
+        if let Some(event_types) = &self.event_types {
+            validate_webhook_event_types(event_types)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_webhook_url(url: &str) -> Result<(), String> {
+    if !(url.starts_with("https://") || url.starts_with("http://")) || url.len() > 2048 {
+        return Err("Webhook URL must be an http(s) URL of at most 2048 characters".to_string());
+    }
+
+    Ok(())
+}
+
+fn validate_webhook_event_types(event_types: &[WebhookEventType]) -> Result<(), String> {
+    if event_types.is_empty() {
+        return Err("At least one event type is required".to_string());
+    }
+
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use crate::models::{
-    AuditLog, Device, DeviceMode, DeviceStatus, OperationResult, SdkVersion, TeeType, Transaction,
-    TransactionStatus,
+    ApiKey, AuditLog, Device, DeviceCommand, DeviceGroup, DeviceImportJob, DeviceMode,
+    DeviceStatus, DeviceStatusHistory, GroupRules, Merchant, NotificationRecord, OperationResult,
+    ReconciliationItem, ReconciliationRun, ReversalStatus, SdkVersion, SettlementBatch, SettlementTotal, Store, TeeType, Tenant,
+    TenantBranding, Transaction, TransactionStatus, WebhookDelivery, WebhookEventType, WebhookSubscription,
 };
 use serde::{Deserialize, Serialize};
 
@@ -53,6 +55,10 @@ pub struct DeviceResponse {
     pub device_mode: DeviceMode,
     pub status: DeviceStatus,
     pub security_score: i32,
+    pub merchant_id: Option<String>,
+    pub merchant_name: Option<String>,
+    pub store_id: Option<String>,
+    pub terminal_id: Option<String>,
     pub ksn: Option<String>,
     pub key_injected_at: Option<String>,
     pub key_updated_at: Option<String>,
@@ -73,6 +79,10 @@ impl From<Device> for DeviceResponse {
             device_mode: DeviceMode::from_str(&device.device_mode).unwrap_or(DeviceMode::FullPos),
             status: DeviceStatus::from_str(&device.status).unwrap_or(DeviceStatus::Pending),
             security_score: device.security_score,
+            merchant_id: device.merchant_id,
+            merchant_name: device.merchant_name,
+            store_id: device.store_id,
+            terminal_id: device.terminal_id,
             ksn: Some(device.current_ksn),
             key_injected_at: device.ipek_injected_at,
             key_updated_at: Some(device.updated_at.clone()),
@@ -111,6 +121,7 @@ pub struct UserInfo {
     pub user_id: String,
     pub username: String,
     pub role: String,
+    pub tenant_id: String,
 }
 
 /// 健康检查响应
@@ -185,6 +196,20 @@ pub struct ProcessTransactionResponse {
     pub transaction_id: String,
     pub status: TransactionStatus,
     pub authorization_code: Option<String>,
+    /// 批准金额（分），部分批准时小于交易金额
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approved_amount: Option<i64>,
+    pub message: String,
+}
+
+/// 冲正申请响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReverseTransactionResponse {
+    pub transaction_id: String,
+    pub status: TransactionStatus,
+    /// 冲正状态，交易无需冲正时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reversal_status: Option<ReversalStatus>,
     pub message: String,
 }
 
@@ -214,6 +239,17 @@ pub struct TransactionResponse {
     pub location_accuracy: Option<f32>,
     #[serde(rename = "locationTimestamp", skip_serializing_if = "Option::is_none")]
     pub location_timestamp: Option<String>,
+    #[serde(rename = "originalTransactionId", skip_serializing_if = "Option::is_none")]
+    pub original_transaction_id: Option<String>,
+    #[serde(rename = "authExpiresAt", skip_serializing_if = "Option::is_none")]
+    pub auth_expires_at: Option<String>,
+    #[serde(rename = "batchId", skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    #[serde(rename = "settledAt", skip_serializing_if = "Option::is_none")]
+    pub settled_at: Option<String>,
+    /// 随交易保存的EMV数据（白名单标签）
+    #[serde(rename = "emvData", skip_serializing_if = "Option::is_none")]
+    pub emv_data: Option<String>,
     #[serde(rename = "timestamp")]
     pub created_at: String,
 }
@@ -247,6 +283,11 @@ impl From<Transaction> for TransactionResponse {
                 chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(ts, chrono::Utc)
                     .to_rfc3339()
             }),
+            original_transaction_id: tx.original_transaction_id,
+            auth_expires_at: tx.auth_expires_at,
+            batch_id: tx.batch_id,
+            settled_at: tx.settled_at,
+            emv_data: tx.emv_data,
             created_at: tx.created_at,
         }
     }
@@ -332,6 +373,14 @@ pub struct AuditLogListResponse {
     pub total: i64,
 }
 
+/// 设备状态时间线响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceTimelineResponse {
+    pub device_id: String,
+    pub current_status: String,
+    pub history: Vec<DeviceStatusHistory>,
+}
+
 /// 设备统计响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceStatisticsResponse {
@@ -466,3 +515,566 @@ pub struct AbnormalDevice {
     pub security_score: i32,
     pub last_check_at: String,
 }
+
+/// 商户响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerchantResponse {
+    pub id: String,
+    pub name: String,
+    pub mcc: String,
+    pub country: String,
+    pub currency: String,
+    pub acquirer_mid: Option<String>,
+    pub address: Option<String>,
+    pub status: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<Merchant> for MerchantResponse {
+    fn from(merchant: Merchant) -> Self {
+        Self {
+            id: merchant.id,
+            name: merchant.name,
+            mcc: merchant.mcc,
+            country: merchant.country,
+            currency: merchant.currency,
+            acquirer_mid: merchant.acquirer_mid,
+            address: merchant.address,
+            status: merchant.status,
+            created_at: merchant.created_at,
+            updated_at: merchant.updated_at,
+        }
+    }
+}
+
+/// 商户列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerchantListResponse {
+    pub merchants: Vec<MerchantResponse>,
+    pub total: i64,
+}
+
+/// 门店响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreResponse {
+    pub id: String,
+    pub merchant_id: String,
+    pub name: String,
+    pub address: Option<String>,
+    pub status: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<Store> for StoreResponse {
+    fn from(store: Store) -> Self {
+        Self {
+            id: store.id,
+            merchant_id: store.merchant_id,
+            name: store.name,
+            address: store.address,
+            status: store.status,
+            created_at: store.created_at,
+            updated_at: store.updated_at,
+        }
+    }
+}
+
+/// 门店列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreListResponse {
+    pub stores: Vec<StoreResponse>,
+    pub total: i64,
+}
+
+/// 租户响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantResponse {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    /// 是否配置了租户专属BDK（BDK本身不返回）
+    pub has_custom_bdk: bool,
+    /// HSM中租户专属BDK的密钥引用
+    pub bdk_key_ref: Option<String>,
+    pub rate_limit_rps: Option<i64>,
+    pub rate_limit_burst: Option<i64>,
+    pub branding: TenantBranding,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<Tenant> for TenantResponse {
+    fn from(tenant: Tenant) -> Self {
+        let branding = tenant.branding();
+        let has_custom_bdk = tenant.has_custom_bdk();
+        Self {
+            id: tenant.id,
+            name: tenant.name,
+            status: tenant.status,
+            has_custom_bdk,
+            bdk_key_ref: tenant.bdk_key_ref,
+            rate_limit_rps: tenant.rate_limit_rps,
+            rate_limit_burst: tenant.rate_limit_burst,
+            branding,
+            created_at: tenant.created_at,
+            updated_at: tenant.updated_at,
+        }
+    }
+}
+
+/// 租户列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantListResponse {
+    pub tenants: Vec<TenantResponse>,
+    pub total: i64,
+}
+
+/// API密钥响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub tenant_id: String,
+    pub name: String,
+    pub key_prefix: String,
+    pub role: String,
+    pub created_by: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id,
+            tenant_id: key.tenant_id,
+            name: key.name,
+            key_prefix: key.key_prefix,
+            role: key.role,
+            created_by: key.created_by,
+            created_at: key.created_at,
+            last_used_at: key.last_used_at,
+            revoked_at: key.revoked_at,
+        }
+    }
+}
+
+/// 创建API密钥响应，明文密钥仅返回这一次
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateApiKeyResponse {
+    #[serde(flatten)]
+    pub key: ApiKeyResponse,
+    pub api_key: String,
+}
+
+/// 设备导入任务响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceImportJobResponse {
+    pub id: String,
+    pub status: String,
+    pub format: String,
+    pub total_rows: i64,
+    pub success_rows: i64,
+    pub failed_rows: i64,
+    pub created_by: String,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+impl From<DeviceImportJob> for DeviceImportJobResponse {
+    fn from(job: DeviceImportJob) -> Self {
+        Self {
+            id: job.id,
+            status: job.status,
+            format: job.format,
+            total_rows: job.total_rows,
+            success_rows: job.success_rows,
+            failed_rows: job.failed_rows,
+            created_by: job.created_by,
+            created_at: job.created_at,
+            started_at: job.started_at,
+            completed_at: job.completed_at,
+        }
+    }
+}
+
+/// 设备导入任务列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceImportJobListResponse {
+    pub jobs: Vec<DeviceImportJobResponse>,
+    pub total: i64,
+}
+
+/// 设备分组响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceGroupResponse {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub group_type: String,
+    pub rules: Option<GroupRules>,
+    pub created_by: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<DeviceGroup> for DeviceGroupResponse {
+    fn from(group: DeviceGroup) -> Self {
+        Self {
+            rules: group.parsed_rules(),
+            id: group.id,
+            name: group.name,
+            description: group.description,
+            group_type: group.group_type,
+            created_by: group.created_by,
+            created_at: group.created_at,
+            updated_at: group.updated_at,
+        }
+    }
+}
+
+/// 设备分组列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceGroupListResponse {
+    pub groups: Vec<DeviceGroupResponse>,
+    pub total: i64,
+}
+
+/// 设备标签响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceTagsResponse {
+    pub device_id: String,
+    pub tags: Vec<String>,
+}
+
+/// 设备指令响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCommandResponse {
+    pub id: String,
+    pub device_id: String,
+    pub command_type: String,
+    pub payload: Option<serde_json::Value>,
+    pub status: String,
+    pub attempts: i64,
+    pub max_attempts: i64,
+    pub timeout_seconds: i64,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+    pub acknowledged_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub deadline_at: Option<String>,
+}
+
+impl From<DeviceCommand> for DeviceCommandResponse {
+    fn from(command: DeviceCommand) -> Self {
+        let parse = |v: Option<String>| v.and_then(|v| serde_json::from_str(&v).ok());
+
+        Self {
+            id: command.id,
+            device_id: command.device_id,
+            command_type: command.command_type,
+            payload: parse(command.payload),
+            status: command.status,
+            attempts: command.attempts,
+            max_attempts: command.max_attempts,
+            timeout_seconds: command.timeout_seconds,
+            result: parse(command.result),
+            error: command.error,
+            created_by: command.created_by,
+            created_at: command.created_at,
+            delivered_at: command.delivered_at,
+            acknowledged_at: command.acknowledged_at,
+            completed_at: command.completed_at,
+            deadline_at: command.deadline_at,
+        }
+    }
+}
+
+/// 设备指令列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCommandListResponse {
+    pub commands: Vec<DeviceCommandResponse>,
+    pub total: i64,
+}
+
+/// 在线设备响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectedDeviceResponse {
+    pub device_id: String,
+    pub imei: String,
+    pub model: String,
+    pub status: String,
+    pub connection_id: String,
+    pub connected_at: String,
+}
+
+/// 在线设备列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectedDeviceListResponse {
+    pub devices: Vec<ConnectedDeviceResponse>,
+    pub total: i64,
+}
+
+/// 历史通知响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationResponse {
+    pub seq: i64,
+    pub notification_type: String,
+    pub severity: String,
+    pub title: String,
+    pub message: String,
+    pub device_id: Option<String>,
+    pub merchant_id: Option<String>,
+    pub threat_id: Option<String>,
+    pub data: Option<serde_json::Value>,
+    pub created_at: String,
+    pub read: bool,
+    pub read_at: Option<String>,
+}
+
+impl From<NotificationRecord> for NotificationResponse {
+    fn from(record: NotificationRecord) -> Self {
+        Self {
+            seq: record.seq,
+            notification_type: record.notification_type,
+            severity: record.severity,
+            title: record.title,
+            message: record.message,
+            device_id: record.device_id,
+            merchant_id: record.merchant_id,
+            threat_id: record.threat_id,
+            data: record.data.and_then(|d| serde_json::from_str(&d).ok()),
+            created_at: record.created_at,
+            read: record.read_at.is_some(),
+            read_at: record.read_at,
+        }
+    }
+}
+
+/// 历史通知分页响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationHistoryResponse {
+    pub notifications: Vec<NotificationResponse>,
+    pub unread_count: i64,
+    /// 下一页的 `before_seq`，没有更多数据时为空
+    pub next_before_seq: Option<i64>,
+}
+
+/// Webhook订阅响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSubscriptionResponse {
+    pub id: String,
+    pub merchant_id: String,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+    pub active: bool,
+    /// 签名密钥，仅在创建订阅时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<WebhookSubscription> for WebhookSubscriptionResponse {
+    fn from(subscription: WebhookSubscription) -> Self {
+        Self {
+            event_types: subscription.event_type_list(),
+            id: subscription.id,
+            merchant_id: subscription.merchant_id,
+            url: subscription.url,
+            active: subscription.active,
+            secret: None,
+            created_by: subscription.created_by,
+            created_at: subscription.created_at,
+            updated_at: subscription.updated_at,
+        }
+    }
+}
+
+/// Webhook订阅列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSubscriptionListResponse {
+    pub subscriptions: Vec<WebhookSubscriptionResponse>,
+    pub total: i64,
+}
+
+/// Webhook投递响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDeliveryResponse {
+    pub id: String,
+    pub subscription_id: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: Option<serde_json::Value>,
+    pub status: String,
+    pub attempts: i64,
+    pub max_attempts: i64,
+    pub next_attempt_at: Option<String>,
+    pub last_attempt_at: Option<String>,
+    pub response_status: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+impl From<WebhookDelivery> for WebhookDeliveryResponse {
+    fn from(delivery: WebhookDelivery) -> Self {
+        let pending = delivery.status == crate::models::WebhookDeliveryStatus::Pending.as_str();
+
+        Self {
+            id: delivery.id,
+            subscription_id: delivery.subscription_id,
+            event_id: delivery.event_id,
+            event_type: delivery.event_type,
+            payload: serde_json::from_str(&delivery.payload).ok(),
+            status: delivery.status,
+            attempts: delivery.attempts,
+            max_attempts: delivery.max_attempts,
+            next_attempt_at: pending.then_some(delivery.next_attempt_at),
+            last_attempt_at: delivery.last_attempt_at,
+            response_status: delivery.response_status,
+            last_error: delivery.last_error,
+            created_at: delivery.created_at,
+            delivered_at: delivery.delivered_at,
+        }
+    }
+}
+
+/// Webhook投递列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDeliveryListResponse {
+    pub deliveries: Vec<WebhookDeliveryResponse>,
+    pub total: i64,
+}
+
+/// 结算批次响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementBatchResponse {
+    pub id: String,
+    pub device_id: String,
+    pub merchant_id: Option<String>,
+    pub batch_number: i64,
+    pub status: String,
+    pub transaction_count: i64,
+    pub processor: Option<String>,
+    pub processor_reference: Option<String>,
+    pub response_code: Option<String>,
+    pub failure_reason: Option<String>,
+    pub last_error: Option<String>,
+    pub submit_attempts: i64,
+    pub next_attempt_at: Option<String>,
+    pub uploaded: bool,
+    pub opened_at: String,
+    pub closed_at: Option<String>,
+    pub closed_by: Option<String>,
+    pub settled_at: Option<String>,
+}
+
+impl From<SettlementBatch> for SettlementBatchResponse {
+    fn from(batch: SettlementBatch) -> Self {
+        Self {
+            id: batch.id,
+            device_id: batch.device_id,
+            merchant_id: batch.merchant_id,
+            batch_number: batch.batch_number,
+            status: batch.status,
+            transaction_count: batch.transaction_count,
+            processor: batch.processor,
+            processor_reference: batch.processor_reference,
+            response_code: batch.response_code,
+            failure_reason: batch.failure_reason,
+            last_error: batch.last_error,
+            submit_attempts: batch.submit_attempts,
+            next_attempt_at: batch.next_attempt_at,
+            uploaded: batch.uploaded,
+            opened_at: batch.opened_at,
+            closed_at: batch.closed_at,
+            closed_by: batch.closed_by,
+            settled_at: batch.settled_at,
+        }
+    }
+}
+
+/// 结算批次列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementBatchListResponse {
+    pub batches: Vec<SettlementBatchResponse>,
+    pub total: i64,
+}
+
+/// 结算批次详情响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementBatchDetailResponse {
+    pub batch: SettlementBatchResponse,
+    /// 按交易类型和币种汇总的合计，关批后才有
+    pub totals: Vec<SettlementTotal>,
+    /// 各币种净额（借记减贷记）
+    pub net_amounts: Vec<SettlementNetAmount>,
+    pub transactions: Vec<TransactionResponse>,
+}
+
+/// 单一币种的结算净额
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementNetAmount {
+    pub currency: String,
+    pub amount: i64,
+}
+
+/// 对账任务响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationRunResponse {
+    pub id: String,
+    pub layout: String,
+    pub file_name: Option<String>,
+    pub merchant_id: Option<String>,
+    pub period_start: String,
+    pub period_end: String,
+    pub total_records: i64,
+    pub matched_count: i64,
+    pub amount_mismatch_count: i64,
+    pub unmatched_ours_count: i64,
+    pub unmatched_theirs_count: i64,
+    pub created_by: String,
+    pub created_at: String,
+}
+
+impl From<ReconciliationRun> for ReconciliationRunResponse {
+    fn from(run: ReconciliationRun) -> Self {
+        Self {
+            id: run.id,
+            layout: run.layout,
+            file_name: run.file_name,
+            merchant_id: run.merchant_id,
+            period_start: run.period_start,
+            period_end: run.period_end,
+            total_records: run.total_records,
+            matched_count: run.matched_count,
+            amount_mismatch_count: run.amount_mismatch_count,
+            unmatched_ours_count: run.unmatched_ours_count,
+            unmatched_theirs_count: run.unmatched_theirs_count,
+            created_by: run.created_by,
+            created_at: run.created_at,
+        }
+    }
+}
+
+/// 对账任务列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationRunListResponse {
+    pub runs: Vec<ReconciliationRunResponse>,
+    pub total: i64,
+}
+
+/// 对账结果明细列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationItemListResponse {
+    pub items: Vec<ReconciliationItem>,
+    pub total: i64,
+}
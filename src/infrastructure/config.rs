@@ -1,5 +1,9 @@
 use serde::Deserialize;
-use std::env;
+use std::{collections::HashMap, env};
+
+use crate::api::websocket::{NotificationSeverity, NotificationType};
+use crate::infrastructure::payment::PaymentOperation;
+use crate::models::SettlementFileLayout;
 
 /// 应用配置
 #[derive(Debug, Deserialize, Clone)]
@@ -15,6 +19,22 @@ pub struct Config {
     pub logging: LoggingConfig,
     #[serde(default)]
     pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub notification: NotificationConfig,
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+    #[serde(default)]
+    pub alerting: AlertingConfig,
+    #[serde(default)]
+    pub outbox: OutboxConfig,
+    #[serde(default)]
+    pub payment: PaymentConfig,
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+    #[serde(default)]
+    pub settlement: SettlementConfig,
+    #[serde(default)]
+    pub reconciliation: ReconciliationConfig,
 }
 
 /// 服务器配置
@@ -66,17 +86,37 @@ pub struct HsmConfig {
 /// 安全配置
 #[derive(Debug, Deserialize, Clone)]
 pub struct SecurityConfig {
+    #[serde(default = "default_bdk")]
     pub bdk: String,
+    /// 包装租户专属BDK的密钥加密密钥（64位十六进制），未配置时租户只能使用HSM中的BDK
+    #[serde(default)]
+    pub key_encryption_key: Option<String>,
+    /// 交易鉴证可使用的健康检查最长时间（秒），超过后须重新检查
+    #[serde(default = "default_health_check_max_age_seconds")]
+    pub health_check_max_age_seconds: u64,
+    /// 超级管理员账号，未配置时不能以超级管理员登录
+    #[serde(default)]
+    pub super_admin: Option<SuperAdminConfig>,
 }
 
 impl Default for SecurityConfig {
     fn default() -> Self {
         Self {
-            bdk: "0123456789ABCDEFFEDCBA9876543210".to_string(),
+            bdk: default_bdk(),
+            key_encryption_key: None,
+            health_check_max_age_seconds: default_health_check_max_age_seconds(),
+            super_admin: None,
         }
     }
 }
 
+/// 超级管理员账号，只保存密码的Argon2哈希
+#[derive(Debug, Deserialize, Clone)]
+pub struct SuperAdminConfig {
+    pub username: String,
+    pub password_hash: String,
+}
+
 /// 日志配置
 #[derive(Debug, Deserialize, Clone)]
 pub struct LoggingConfig {
@@ -95,6 +135,312 @@ pub struct RateLimitConfig {
     pub burst_size: u32,
 }
 
+/// 通知配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationConfig {
+    /// 同一设备同类告警的冷却时间（秒），为0时不去重
+    #[serde(default = "default_alert_cooldown_seconds")]
+    pub alert_cooldown_seconds: u64,
+}
+
+/// 商户Webhook配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhookConfig {
+    /// 单个事件的最大投递次数，超过后进入死信
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: i64,
+    /// 首次重试间隔（秒），之后每次翻倍
+    #[serde(default = "default_webhook_retry_base_seconds")]
+    pub retry_base_seconds: u64,
+    /// 单次请求超时（秒）
+    #[serde(default = "default_webhook_timeout_seconds")]
+    pub timeout_seconds: u64,
+    /// 投递队列轮询间隔（秒）
+    #[serde(default = "default_webhook_poll_interval_seconds")]
+    pub poll_interval_seconds: u64,
+}
+
+/// 领域事件发件箱配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct OutboxConfig {
+    /// 单个事件的最大转发次数，超过后标记为失败
+    #[serde(default = "default_outbox_max_attempts")]
+    pub max_attempts: i64,
+    /// 首次重试间隔（秒），之后每次翻倍
+    #[serde(default = "default_outbox_retry_base_seconds")]
+    pub retry_base_seconds: u64,
+    /// 发件箱轮询间隔（毫秒）
+    #[serde(default = "default_outbox_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+/// 交易幂等配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct IdempotencyConfig {
+    /// 幂等键有效期（秒），过期后同一幂等键视为新请求
+    #[serde(default = "default_idempotency_key_ttl_seconds")]
+    pub key_ttl_seconds: u64,
+    /// 处理锁超时（秒），超时未完成的请求可被重新领取
+    #[serde(default = "default_idempotency_lock_timeout_seconds")]
+    pub lock_timeout_seconds: u64,
+}
+
+/// 批次结算配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct SettlementConfig {
+    /// 日切时间（UTC，HH:MM），之前开批的开放批次自动关批
+    #[serde(default = "default_settlement_cutoff_time")]
+    pub cutoff_time: String,
+    /// 日切和待提交批次的检查间隔（秒）
+    #[serde(default = "default_settlement_check_interval_seconds")]
+    pub check_interval_seconds: u64,
+    /// 单个批次的最大提交次数，超过后标记为失败
+    #[serde(default = "default_settlement_max_submit_attempts")]
+    pub max_submit_attempts: i64,
+    /// 首次重试间隔（秒），之后每次翻倍
+    #[serde(default = "default_settlement_retry_base_seconds")]
+    pub retry_base_seconds: u64,
+}
+
+impl SettlementConfig {
+    /// 解析日切时间，格式无效时返回None
+    pub fn cutoff(&self) -> Option<chrono::NaiveTime> {
+        chrono::NaiveTime::parse_from_str(&self.cutoff_time, "%H:%M").ok()
+    }
+}
+
+/// 对账配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReconciliationConfig {
+    /// 金额容差（分），差额不超过该值视为金额一致
+    #[serde(default)]
+    pub amount_tolerance: i64,
+    /// 日期容差（天），清算文件日期与交易日期相差不超过该天数视为同一笔
+    #[serde(default = "default_reconciliation_date_tolerance_days")]
+    pub date_tolerance_days: i64,
+    /// 收单机构清算文件格式，导入时按名称引用
+    #[serde(default)]
+    pub layouts: Vec<SettlementFileLayout>,
+}
+
+/// 支付处理器配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct PaymentConfig {
+    /// 未命中路由规则时使用的连接器
+    #[serde(default = "default_payment_connector")]
+    pub default_connector: String,
+    /// 连接器列表；未配置名为 `simulator` 的连接器时自动提供全部批准的模拟器
+    #[serde(default)]
+    pub connectors: Vec<PaymentConnectorConfig>,
+    /// 路由规则，按顺序匹配
+    #[serde(default)]
+    pub routes: Vec<PaymentRoute>,
+    /// 预授权有效期（小时），过期后不能请款或追加授权
+    #[serde(default = "default_preauth_validity_hours")]
+    pub preauth_validity_hours: i64,
+    /// 结果未知交易的最大冲正次数，超过后标记为失败等待人工处理
+    #[serde(default = "default_reversal_max_attempts")]
+    pub reversal_max_attempts: i64,
+    /// 首次冲正重试间隔（秒），之后每次翻倍
+    #[serde(default = "default_reversal_retry_base_seconds")]
+    pub reversal_retry_base_seconds: u64,
+    /// 冲正队列轮询间隔（秒）
+    #[serde(default = "default_reversal_poll_interval_seconds")]
+    pub reversal_poll_interval_seconds: u64,
+}
+
+/// 支付连接器类型
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentConnectorType {
+    Simulator,
+    HttpGateway,
+    /// ISO 8583 over TCP直连收单机构
+    Iso8583Tcp,
+}
+
+/// 支付连接器配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct PaymentConnectorConfig {
+    /// 连接器名，路由规则通过名称引用
+    pub name: String,
+    #[serde(rename = "type")]
+    pub connector_type: PaymentConnectorType,
+    /// 网关地址（http_gateway）
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// 收单机构地址 `host:port`（iso8583_tcp）
+    #[serde(default)]
+    pub address: Option<String>,
+    /// ISO 8583字段规格文件（iso8583_tcp），见 `config/iso8583/`
+    #[serde(default)]
+    pub spec_file: Option<String>,
+    /// 回响报文保活间隔（秒，iso8583_tcp），为0时不保活
+    #[serde(default = "default_echo_interval_seconds")]
+    pub echo_interval_seconds: u64,
+    #[serde(default = "default_payment_timeout_seconds")]
+    pub timeout_seconds: u64,
+    /// 模拟处理延迟（毫秒，simulator）
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// 脚本场景（simulator），按顺序匹配，未命中时批准
+    #[serde(default)]
+    pub scenarios: Vec<SimulatorScenario>,
+}
+
+/// 模拟器脚本场景，未设置的匹配条件视为任意
+#[derive(Debug, Deserialize, Clone)]
+pub struct SimulatorScenario {
+    /// 匹配的交易金额（分）
+    #[serde(default)]
+    pub amount: Option<i64>,
+    /// 匹配的操作，为空时匹配所有操作
+    #[serde(default)]
+    pub operations: Vec<PaymentOperation>,
+    #[serde(default)]
+    pub merchant_id: Option<String>,
+    pub result: SimulatorResult,
+    /// 拒绝应答码（decline必填）
+    #[serde(default)]
+    pub response_code: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    /// 批准金额（partial_approval必填）
+    #[serde(default)]
+    pub approved_amount: Option<i64>,
+}
+
+/// 模拟器场景结果
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulatorResult {
+    Approve,
+    Decline,
+    Timeout,
+    PartialApproval,
+}
+
+/// 支付路由规则，商户ID或收单机构商户号前缀任一命中即路由到 `connector`
+#[derive(Debug, Deserialize, Clone)]
+pub struct PaymentRoute {
+    pub connector: String,
+    #[serde(default)]
+    pub merchant_ids: Vec<String>,
+    #[serde(default)]
+    pub acquirer_mid_prefixes: Vec<String>,
+}
+
+/// 外部告警渠道配置（邮件、短信/IM）
+#[derive(Debug, Deserialize, Clone)]
+pub struct AlertingConfig {
+    /// 是否启用外部告警，关闭时告警只推送到管理端WebSocket
+    #[serde(default)]
+    pub enabled: bool,
+    /// SMTP邮件渠道，渠道名为 `email`
+    #[serde(default)]
+    pub email: Option<EmailChannelConfig>,
+    /// 通用HTTP渠道（短信网关、钉钉、企业微信、Slack等）
+    #[serde(default)]
+    pub http_channels: Vec<HttpChannelConfig>,
+    /// 路由规则，一条告警可命中多条规则
+    #[serde(default)]
+    pub routes: Vec<AlertRoute>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    /// 升级检查间隔（秒）
+    #[serde(default = "default_escalation_check_interval_seconds")]
+    pub escalation_check_interval_seconds: u64,
+}
+
+/// SMTP邮件渠道配置
+///
+/// 仅支持明文SMTP，生产环境应投递到本机或内网的邮件中继
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmailChannelConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    #[serde(default = "default_email_subject_template")]
+    pub subject_template: String,
+    #[serde(default = "default_email_body_template")]
+    pub body_template: String,
+    #[serde(default = "default_alert_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+/// 通用HTTP告警渠道配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct HttpChannelConfig {
+    /// 渠道名，路由规则通过名称引用
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// 请求体模板；包含 `{{recipient}}` 时按接收人逐个发送
+    pub body_template: String,
+    #[serde(default = "default_alert_content_type")]
+    pub content_type: String,
+    #[serde(default = "default_alert_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+/// 告警路由规则
+#[derive(Debug, Deserialize, Clone)]
+pub struct AlertRoute {
+    pub name: String,
+    /// 最低严重级别
+    #[serde(default = "default_route_min_severity")]
+    pub min_severity: NotificationSeverity,
+    /// 匹配的通知类型，为空时匹配所有类型
+    #[serde(default)]
+    pub notification_types: Vec<NotificationType>,
+    pub targets: Vec<AlertTarget>,
+    /// 告警未确认时的升级策略
+    #[serde(default)]
+    pub escalation: Option<EscalationPolicy>,
+}
+
+/// 告警发送目标
+#[derive(Debug, Deserialize, Clone)]
+pub struct AlertTarget {
+    /// 渠道名：`email` 或HTTP渠道的名称
+    pub channel: String,
+    /// 接收人（邮箱、手机号等），群机器人类渠道可为空
+    #[serde(default)]
+    pub recipients: Vec<String>,
+}
+
+/// 告警升级策略
+#[derive(Debug, Deserialize, Clone)]
+pub struct EscalationPolicy {
+    /// 告警发出后多久仍未确认则升级（秒）
+    pub after_seconds: u64,
+    pub targets: Vec<AlertTarget>,
+}
+
+/// 免打扰时段
+///
+/// 时段内低于 `bypass_severity` 的告警不发送外部通知，仍推送到管理端；
+/// 其升级计时从时段结束开始
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuietHours {
+    /// 开始时间（HH:MM）
+    pub start: String,
+    /// 结束时间（HH:MM），早于开始时间表示跨午夜
+    pub end: String,
+    /// 时区相对UTC的偏移（分钟），例如北京时间为480
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// 达到该级别的告警不受免打扰限制
+    #[serde(default = "default_route_min_severity")]
+    pub bypass_severity: NotificationSeverity,
+}
+
 // 默认值函数
 fn default_host() -> String {
     "0.0.0.0".to_string()
@@ -120,6 +466,14 @@ fn default_timeout_seconds() -> u64 {
     30
 }
 
+fn default_bdk() -> String {
+    "0123456789ABCDEFFEDCBA9876543210".to_string()
+}
+
+fn default_health_check_max_age_seconds() -> u64 {
+    300
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
@@ -136,6 +490,122 @@ fn default_burst_size() -> u32 {
     200
 }
 
+fn default_alert_cooldown_seconds() -> u64 {
+    300
+}
+
+fn default_webhook_max_attempts() -> i64 {
+    8
+}
+
+fn default_webhook_retry_base_seconds() -> u64 {
+    30
+}
+
+fn default_webhook_timeout_seconds() -> u64 {
+    10
+}
+
+fn default_webhook_poll_interval_seconds() -> u64 {
+    5
+}
+
+fn default_outbox_max_attempts() -> i64 {
+    10
+}
+
+fn default_outbox_retry_base_seconds() -> u64 {
+    5
+}
+
+fn default_outbox_poll_interval_ms() -> u64 {
+    500
+}
+
+fn default_idempotency_key_ttl_seconds() -> u64 {
+    86400
+}
+
+fn default_idempotency_lock_timeout_seconds() -> u64 {
+    60
+}
+
+fn default_settlement_cutoff_time() -> String {
+    "23:00".to_string()
+}
+
+fn default_settlement_check_interval_seconds() -> u64 {
+    60
+}
+
+fn default_settlement_max_submit_attempts() -> i64 {
+    crate::models::DEFAULT_SETTLEMENT_MAX_ATTEMPTS
+}
+
+fn default_settlement_retry_base_seconds() -> u64 {
+    60
+}
+
+fn default_reconciliation_date_tolerance_days() -> i64 {
+    1
+}
+
+fn default_payment_connector() -> String {
+    crate::infrastructure::payment::SIMULATOR_CONNECTOR.to_string()
+}
+
+fn default_payment_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_preauth_validity_hours() -> i64 {
+    168
+}
+
+fn default_reversal_max_attempts() -> i64 {
+    10
+}
+
+fn default_reversal_retry_base_seconds() -> u64 {
+    30
+}
+
+fn default_reversal_poll_interval_seconds() -> u64 {
+    5
+}
+
+fn default_echo_interval_seconds() -> u64 {
+    60
+}
+
+fn default_escalation_check_interval_seconds() -> u64 {
+    30
+}
+
+fn default_smtp_port() -> u16 {
+    25
+}
+
+fn default_email_subject_template() -> String {
+    "{{escalation}}[{{severity}}] {{title}}".to_string()
+}
+
+fn default_email_body_template() -> String {
+    "{{message}}\n\n类型: {{type}}\n设备: {{device_id}}\n商户: {{merchant_id}}\n时间: {{timestamp}}\n通知序号: {{seq}}".to_string()
+}
+
+fn default_alert_content_type() -> String {
+    "application/json".to_string()
+}
+
+fn default_alert_timeout_seconds() -> u64 {
+    10
+}
+
+fn default_route_min_severity() -> NotificationSeverity {
+    NotificationSeverity::High
+}
+
 impl Default for LoggingConfig {
     fn default() -> Self {
         Self {
@@ -154,6 +624,92 @@ impl Default for RateLimitConfig {
     }
 }
 
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            alert_cooldown_seconds: default_alert_cooldown_seconds(),
+        }
+    }
+}
+
+impl Default for AlertingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            email: None,
+            http_channels: Vec::new(),
+            routes: Vec::new(),
+            quiet_hours: None,
+            escalation_check_interval_seconds: default_escalation_check_interval_seconds(),
+        }
+    }
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_webhook_max_attempts(),
+            retry_base_seconds: default_webhook_retry_base_seconds(),
+            timeout_seconds: default_webhook_timeout_seconds(),
+            poll_interval_seconds: default_webhook_poll_interval_seconds(),
+        }
+    }
+}
+
+impl Default for PaymentConfig {
+    fn default() -> Self {
+        Self {
+            default_connector: default_payment_connector(),
+            connectors: Vec::new(),
+            routes: Vec::new(),
+            preauth_validity_hours: default_preauth_validity_hours(),
+            reversal_max_attempts: default_reversal_max_attempts(),
+            reversal_retry_base_seconds: default_reversal_retry_base_seconds(),
+            reversal_poll_interval_seconds: default_reversal_poll_interval_seconds(),
+        }
+    }
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_outbox_max_attempts(),
+            retry_base_seconds: default_outbox_retry_base_seconds(),
+            poll_interval_ms: default_outbox_poll_interval_ms(),
+        }
+    }
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            key_ttl_seconds: default_idempotency_key_ttl_seconds(),
+            lock_timeout_seconds: default_idempotency_lock_timeout_seconds(),
+        }
+    }
+}
+
+impl Default for SettlementConfig {
+    fn default() -> Self {
+        Self {
+            cutoff_time: default_settlement_cutoff_time(),
+            check_interval_seconds: default_settlement_check_interval_seconds(),
+            max_submit_attempts: default_settlement_max_submit_attempts(),
+            retry_base_seconds: default_settlement_retry_base_seconds(),
+        }
+    }
+}
+
+impl Default for ReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            amount_tolerance: 0,
+            date_tolerance_days: default_reconciliation_date_tolerance_days(),
+            layouts: Vec::new(),
+        }
+    }
+}
+
 impl Config {
     /// 从配置文件和环境变量加载配置
     pub fn load() -> Result<Self, config::ConfigError> {
@@ -170,10 +726,70 @@ impl Config {
             .set_default("jwt.expiration_hours", default_expiration_hours())?
             .set_default("jwt.refresh_expiration_days", default_refresh_expiration_days())?
             .set_default("hsm.timeout_seconds", default_timeout_seconds() as i64)?
+            .set_default(
+                "security.health_check_max_age_seconds",
+                default_health_check_max_age_seconds() as i64,
+            )?
             .set_default("logging.level", default_log_level())?
             .set_default("logging.format", default_log_format())?
             .set_default("rate_limit.requests_per_second", default_requests_per_second() as i64)?
             .set_default("rate_limit.burst_size", default_burst_size() as i64)?
+            .set_default(
+                "notification.alert_cooldown_seconds",
+                default_alert_cooldown_seconds() as i64,
+            )?
+            .set_default("webhook.max_attempts", default_webhook_max_attempts())?
+            .set_default("webhook.retry_base_seconds", default_webhook_retry_base_seconds() as i64)?
+            .set_default("webhook.timeout_seconds", default_webhook_timeout_seconds() as i64)?
+            .set_default(
+                "webhook.poll_interval_seconds",
+                default_webhook_poll_interval_seconds() as i64,
+            )?
+            .set_default("outbox.max_attempts", default_outbox_max_attempts())?
+            .set_default("outbox.retry_base_seconds", default_outbox_retry_base_seconds() as i64)?
+            .set_default("outbox.poll_interval_ms", default_outbox_poll_interval_ms() as i64)?
+            .set_default("payment.default_connector", default_payment_connector())?
+            .set_default("payment.preauth_validity_hours", default_preauth_validity_hours())?
+            .set_default("payment.reversal_max_attempts", default_reversal_max_attempts())?
+            .set_default(
+                "payment.reversal_retry_base_seconds",
+                default_reversal_retry_base_seconds() as i64,
+            )?
+            .set_default(
+                "payment.reversal_poll_interval_seconds",
+                default_reversal_poll_interval_seconds() as i64,
+            )?
+            .set_default(
+                "idempotency.key_ttl_seconds",
+                default_idempotency_key_ttl_seconds() as i64,
+            )?
+            .set_default(
+                "idempotency.lock_timeout_seconds",
+                default_idempotency_lock_timeout_seconds() as i64,
+            )?
+            .set_default("settlement.cutoff_time", default_settlement_cutoff_time())?
+            .set_default(
+                "settlement.check_interval_seconds",
+                default_settlement_check_interval_seconds() as i64,
+            )?
+            .set_default(
+                "settlement.max_submit_attempts",
+                default_settlement_max_submit_attempts(),
+            )?
+            .set_default(
+                "settlement.retry_base_seconds",
+                default_settlement_retry_base_seconds() as i64,
+            )?
+            .set_default("reconciliation.amount_tolerance", 0)?
+            .set_default(
+                "reconciliation.date_tolerance_days",
+                default_reconciliation_date_tolerance_days(),
+            )?
+            .set_default("alerting.enabled", false)?
+            .set_default(
+                "alerting.escalation_check_interval_seconds",
+                default_escalation_check_interval_seconds() as i64,
+            )?
             // 加载环境特定的配置文件
             .add_source(
                 config::File::with_name(&format!("config/{}", run_env))
@@ -244,6 +860,25 @@ impl Config {
             ));
         }
 
+        // 验证日切时间
+        if self.settlement.cutoff().is_none() {
+            return Err(config::ConfigError::Message(
+                "Settlement cutoff time must be HH:MM".to_string(),
+            ));
+        }
+
+        // 验证对账配置
+        if self.reconciliation.amount_tolerance < 0 || self.reconciliation.date_tolerance_days < 0
+        {
+            return Err(config::ConfigError::Message(
+                "Reconciliation tolerances cannot be negative".to_string(),
+            ));
+        }
+
+        for layout in &self.reconciliation.layouts {
+            layout.validate().map_err(config::ConfigError::Message)?;
+        }
+
         Ok(())
     }
 }
@@ -264,6 +899,9 @@ mod tests {
         assert_eq!(default_log_format(), "json");
         assert_eq!(default_requests_per_second(), 100);
         assert_eq!(default_burst_size(), 200);
+        assert_eq!(default_alert_cooldown_seconds(), 300);
+        assert_eq!(default_webhook_max_attempts(), 8);
+        assert_eq!(default_webhook_retry_base_seconds(), 30);
     }
 
     #[test]
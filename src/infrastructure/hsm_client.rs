@@ -18,6 +18,9 @@ pub struct HsmClient {
 struct DeriveIpekRequest {
     ksn: String,
     device_id: String,
+    /// 租户专属BDK的密钥引用，为空时使用HSM默认BDK
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bdk_key_ref: Option<String>,
 }
 
 /// IPEK派生响应
@@ -69,7 +72,7 @@ impl HsmClient {
         tracing::debug!("Deriving IPEK for device: {}, KSN: {}", device_id, ksn);
 
         // 尝试调用HSM API
-        match self.call_hsm_derive_ipek(ksn, device_id).await {
+        match self.call_hsm_derive_ipek(ksn, device_id, None).await {
             Ok(ipek) => {
                 tracing::info!("IPEK derived successfully from HSM");
                 Ok(ipek)
@@ -82,6 +85,20 @@ impl HsmClient {
         }
     }
 
+    /// 使用HSM中的租户专属BDK派生IPEK
+    ///
+    /// 租户BDK只存在于HSM中，HSM不可用时直接返回错误，不使用本地后备
+    pub async fn derive_ipek_with_key_ref(
+        &self,
+        ksn: &str,
+        device_id: &str,
+        bdk_key_ref: &str,
+    ) -> Result<Vec<u8>, AppError> {
+        tracing::debug!("Deriving IPEK for device: {}, BDK key: {}", device_id, bdk_key_ref);
+
+        self.call_hsm_derive_ipek(ksn, device_id, Some(bdk_key_ref)).await
+    }
+
     /// 派生Working Key
     /// 
     /// 在实际环境中，这会调用HSM的API来派生Working Key
@@ -130,12 +147,14 @@ impl HsmClient {
         &self,
         ksn: &str,
         device_id: &str,
+        bdk_key_ref: Option<&str>,
     ) -> Result<Vec<u8>, AppError> {
         let url = format!("{}/api/v1/derive-ipek", self.config.base_url);
 
         let request = DeriveIpekRequest {
             ksn: ksn.to_string(),
             device_id: device_id.to_string(),
+            bdk_key_ref: bdk_key_ref.map(str::to_string),
         };
 
         let response = self
@@ -319,6 +338,15 @@ mod tests {
         assert_eq!(working_key.len(), 32);
     }
 
+    #[tokio::test]
+    async fn test_derive_ipek_with_key_ref_has_no_fallback() {
+        let client = create_test_client();
+        let ksn = "FFFF00000064657669630000";
+
+        // 租户BDK只在HSM中，HSM不可用时不能用本地BDK代替
+        assert!(client.derive_ipek_with_key_ref(ksn, "device123", "tenant-bdk-1").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_health_check() {
         let client = create_test_client();
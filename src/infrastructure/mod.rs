@@ -2,7 +2,9 @@ pub mod config;
 pub mod database;
 pub mod hsm_client;
 pub mod logging;
+pub mod payment;
 pub mod redis;
+pub mod smtp;
 
 pub use config::Config;
 pub use config::HsmConfig;
@@ -11,4 +13,6 @@ pub use database::{
 };
 pub use hsm_client::HsmClient;
 pub use logging::SqlxLogLayer;
+pub use payment::{PaymentProcessor, PaymentRouter};
 pub use redis::{RedisClient, RedisConfig};
+pub use smtp::{EmailMessage, SmtpClient};
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, time::Duration};
+
+use futures::future::BoxFuture;
+use reqwest::Client;
+
+use super::{
+    PaymentOperation, PaymentProcessor, PaymentRequest, PaymentResponse, SettlementRequest,
+    SettlementResponse,
+};
+use crate::{infrastructure::config::PaymentConnectorConfig, utils::error::AppError};
+
+/// HTTP/JSON支付网关连接器
+///
+/// 每个操作POST到 `{url}/{operation}`（如 `/authorize`、`/refund`），请求体为 [`PaymentRequest`]，
+/// 网关以 [`PaymentResponse`] 应答；拒绝也应返回2xx。批次结算POST到 `{url}/settlement`，
+/// 请求体为 [`SettlementRequest`]，应答为 [`SettlementResponse`]。健康检查为 `GET {url}/health`
+pub struct HttpGatewayConnector {
+    name: String,
+    http: Client,
+    url: String,
+    headers: HashMap<String, String>,
+    timeout: Duration,
+}
+
+impl HttpGatewayConnector {
+    /// 创建连接器
+    pub fn new(name: impl Into<String>, url: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            name: name.into(),
+            http: Client::new(),
+            url: url.into().trim_end_matches('/').to_string(),
+            headers: HashMap::new(),
+            timeout,
+        }
+    }
+
+    /// 根据配置创建连接器，未配置URL时返回错误
+    pub fn from_config(config: &PaymentConnectorConfig) -> Result<Self, AppError> {
+        let url = config.url.as_deref().ok_or_else(|| {
+            AppError::Configuration(format!("Payment gateway {} requires url", config.name))
+        })?;
+
+        Ok(Self::new(&config.name, url, Duration::from_secs(config.timeout_seconds))
+            .with_headers(config.headers.clone()))
+    }
+
+    /// 设置附加请求头（如API Key）
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    async fn send<Req: serde::Serialize, Resp: serde::de::DeserializeOwned>(
+        &self,
+        operation: PaymentOperation,
+        request: &Req,
+    ) -> Result<Resp, AppError> {
+        let mut builder = self
+            .http
+            .post(format!("{}/{}", self.url, operation.as_str()))
+            .timeout(self.timeout)
+            .json(request);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+
+        let response = builder.send().await.map_err(|e| self.error(operation, e))?;
+
+        // 网关拒绝的请求未被处理；服务端错误时网关可能已转发给收单机构，结果未知
+        let status = response.status();
+        if !status.is_success() {
+            let message = format!(
+                "Payment gateway {} returned HTTP {} for {}",
+                self.name,
+                status,
+                operation.as_str()
+            );
+            return Err(if status.is_client_error() {
+                AppError::External(message)
+            } else {
+                AppError::ProcessorOutcomeUnknown(message)
+            });
+        }
+
+        response.json::<Resp>().await.map_err(|e| {
+            AppError::ProcessorOutcomeUnknown(format!(
+                "Payment gateway {} returned an invalid response for {}: {}",
+                self.name,
+                operation.as_str(),
+                e
+            ))
+        })
+    }
+
+    /// 区分请求发出前后的通信错误：连接失败时请求未发出，超时和其他错误时结果未知
+    fn error(&self, operation: PaymentOperation, e: reqwest::Error) -> AppError {
+        if e.is_timeout() {
+            AppError::ProcessorTimeout(format!(
+                "Payment gateway {} timed out for {}",
+                self.name,
+                operation.as_str()
+            ))
+        } else if e.is_connect() || e.is_builder() {
+            AppError::External(format!(
+                "Payment gateway {} failed for {}: {}",
+                self.name,
+                operation.as_str(),
+                e
+            ))
+        } else {
+            AppError::ProcessorOutcomeUnknown(format!(
+                "Payment gateway {} failed for {}: {}",
+                self.name,
+                operation.as_str(),
+                e
+            ))
+        }
+    }
+}
+
+impl PaymentProcessor for HttpGatewayConnector {
+    fn authorize<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.send(PaymentOperation::Authorize, request))
+    }
+
+    fn capture<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.send(PaymentOperation::Capture, request))
+    }
+
+    fn refund<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.send(PaymentOperation::Refund, request))
+    }
+
+    fn void<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.send(PaymentOperation::Void, request))
+    }
+
+    fn reversal<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.send(PaymentOperation::Reversal, request))
+    }
+
+    fn settle<'a>(
+        &'a self,
+        request: &'a SettlementRequest,
+    ) -> BoxFuture<'a, Result<SettlementResponse, AppError>> {
+        Box::pin(self.send(PaymentOperation::Settlement, request))
+    }
+
+    fn health(&self) -> BoxFuture<'_, Result<(), AppError>> {
+        Box::pin(async move {
+            let response = self
+                .http
+                .get(format!("{}/health", self.url))
+                .timeout(self.timeout)
+                .send()
+                .await
+                .map_err(|e| {
+                    AppError::External(format!("Payment gateway {} unreachable: {}", self.name, e))
+                })?;
+
+            if !response.status().is_success() {
+                return Err(AppError::External(format!(
+                    "Payment gateway {} health returned HTTP {}",
+                    self.name,
+                    response.status()
+                )));
+            }
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,486 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+
+use crate::utils::error::AppError;
+
+/// ISO 8583报文版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Iso8583Version {
+    #[serde(rename = "1987")]
+    V1987,
+    #[serde(rename = "1993")]
+    V1993,
+}
+
+impl Iso8583Version {
+    /// 报文类型码（MTI）的版本位
+    fn mti_prefix(&self) -> char {
+        match self {
+            Iso8583Version::V1987 => '0',
+            Iso8583Version::V1993 => '1',
+        }
+    }
+
+    /// 根据报文类别（如 `200` 金融请求）生成MTI
+    pub fn mti(&self, class: &str) -> String {
+        format!("{}{}", self.mti_prefix(), class)
+    }
+}
+
+/// 字段编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldEncoding {
+    /// ASCII字符，长度按字符计
+    Ascii,
+    /// 压缩BCD，长度按数字位计，奇数位左补0
+    Bcd,
+    /// 原始字节，长度按字节计
+    Binary,
+}
+
+/// 位图编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BitmapEncoding {
+    Binary,
+    /// 十六进制ASCII字符
+    Hex,
+}
+
+/// 字段长度类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthType {
+    Fixed,
+    /// 2位长度前缀
+    Llvar,
+    /// 3位长度前缀
+    Lllvar,
+}
+
+/// 字段定义
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldSpec {
+    #[serde(default)]
+    pub name: String,
+    pub length_type: LengthType,
+    /// 定长字段的长度或变长字段的最大长度
+    pub length: usize,
+    pub encoding: FieldEncoding,
+}
+
+fn default_mti_encoding() -> FieldEncoding {
+    FieldEncoding::Ascii
+}
+
+fn default_bitmap_encoding() -> BitmapEncoding {
+    BitmapEncoding::Binary
+}
+
+fn default_length_prefix_encoding() -> FieldEncoding {
+    FieldEncoding::Ascii
+}
+
+/// 报文规格，描述MTI、位图和各字段的编码方式
+///
+/// 不同收单机构的字段定义有差异，规格从JSON文件加载
+#[derive(Debug, Clone, Deserialize)]
+pub struct Iso8583Spec {
+    pub version: Iso8583Version,
+    #[serde(default = "default_mti_encoding")]
+    pub mti_encoding: FieldEncoding,
+    #[serde(default = "default_bitmap_encoding")]
+    pub bitmap_encoding: BitmapEncoding,
+    /// 变长字段长度前缀的编码（ASCII或BCD）
+    #[serde(default = "default_length_prefix_encoding")]
+    pub length_prefix_encoding: FieldEncoding,
+    /// 字段号（2-128）到字段定义
+    pub fields: BTreeMap<u8, FieldSpec>,
+}
+
+impl Iso8583Spec {
+    /// 从JSON规格文件加载
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AppError::Configuration(format!(
+                "Failed to read ISO 8583 spec {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_json(&content)
+    }
+
+    /// 从JSON字符串解析
+    pub fn from_json(content: &str) -> Result<Self, AppError> {
+        let spec: Self = serde_json::from_str(content)
+            .map_err(|e| AppError::Configuration(format!("Invalid ISO 8583 spec: {}", e)))?;
+
+        if spec.fields.keys().any(|&n| !(2..=128).contains(&n)) {
+            return Err(AppError::Configuration(
+                "ISO 8583 spec fields must be numbered 2-128".to_string(),
+            ));
+        }
+        if spec.mti_encoding == FieldEncoding::Binary
+            || spec.length_prefix_encoding == FieldEncoding::Binary
+        {
+            return Err(AppError::Configuration(
+                "ISO 8583 MTI and length prefixes must be ascii or bcd".to_string(),
+            ));
+        }
+
+        Ok(spec)
+    }
+
+    fn field(&self, number: u8) -> Result<&FieldSpec, AppError> {
+        self.fields
+            .get(&number)
+            .ok_or_else(|| codec_error(format!("field {} is not defined in the spec", number)))
+    }
+}
+
+/// ISO 8583报文
+///
+/// ASCII和BCD字段的值保存为字符（BCD即数字字符串），二进制字段保存原始字节
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsoMessage {
+    pub mti: String,
+    fields: BTreeMap<u8, Vec<u8>>,
+}
+
+impl IsoMessage {
+    pub fn new(mti: impl Into<String>) -> Self {
+        Self { mti: mti.into(), fields: BTreeMap::new() }
+    }
+
+    /// 设置字段值
+    pub fn set(&mut self, field: u8, value: impl Into<Vec<u8>>) -> &mut Self {
+        self.fields.insert(field, value.into());
+        self
+    }
+
+    pub fn get(&self, field: u8) -> Option<&[u8]> {
+        self.fields.get(&field).map(Vec::as_slice)
+    }
+
+    /// 按字符串读取字段值，二进制字段或非UTF-8内容返回None
+    pub fn get_str(&self, field: u8) -> Option<&str> {
+        self.get(field).and_then(|v| std::str::from_utf8(v).ok())
+    }
+
+    pub fn has(&self, field: u8) -> bool {
+        self.fields.contains_key(&field)
+    }
+
+    /// 已设置的字段号（升序）
+    pub fn field_numbers(&self) -> impl Iterator<Item = u8> + '_ {
+        self.fields.keys().copied()
+    }
+
+    /// 按规格打包为报文字节（不含长度头）
+    pub fn pack(&self, spec: &Iso8583Spec) -> Result<Vec<u8>, AppError> {
+        let mut out = Vec::new();
+
+        if self.mti.len() != 4 || !self.mti.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(codec_error(format!("invalid MTI {}", self.mti)));
+        }
+        match spec.mti_encoding {
+            FieldEncoding::Bcd => out.extend(bcd_encode(&self.mti)?),
+            _ => out.extend(self.mti.as_bytes()),
+        }
+
+        let secondary = self.fields.keys().any(|&n| n > 64);
+        let mut bitmap = vec![0u8; if secondary { 16 } else { 8 }];
+        if secondary {
+            bitmap[0] |= 0x80;
+        }
+        for &number in self.fields.keys() {
+            if !(2..=128).contains(&number) {
+                return Err(codec_error(format!("invalid field number {}", number)));
+            }
+            let bit = (number - 1) as usize;
+            bitmap[bit / 8] |= 0x80 >> (bit % 8);
+        }
+        match spec.bitmap_encoding {
+            BitmapEncoding::Binary => out.extend(&bitmap),
+            BitmapEncoding::Hex => out.extend(hex::encode_upper(&bitmap).as_bytes()),
+        }
+
+        for (&number, value) in &self.fields {
+            pack_field(&mut out, spec, number, value)?;
+        }
+
+        Ok(out)
+    }
+
+    /// 按规格解析报文字节（不含长度头）
+    pub fn unpack(spec: &Iso8583Spec, data: &[u8]) -> Result<Self, AppError> {
+        let mut cursor = Cursor { data, pos: 0 };
+
+        let mti = match spec.mti_encoding {
+            FieldEncoding::Bcd => bcd_decode(cursor.take(2)?, 4),
+            _ => ascii(cursor.take(4)?)?,
+        };
+
+        let mut bitmap = read_bitmap(&mut cursor, spec.bitmap_encoding)?;
+        if bitmap[0] & 0x80 != 0 {
+            bitmap.extend(read_bitmap(&mut cursor, spec.bitmap_encoding)?);
+        }
+
+        let mut message = Self::new(mti);
+        for number in 2..=(bitmap.len() * 8) as u8 {
+            let bit = (number - 1) as usize;
+            if bitmap[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                let value = unpack_field(&mut cursor, spec, number)?;
+                message.fields.insert(number, value);
+            }
+        }
+
+        if cursor.pos != data.len() {
+            return Err(codec_error(format!(
+                "{} trailing bytes after last field",
+                data.len() - cursor.pos
+            )));
+        }
+
+        Ok(message)
+    }
+}
+
+/// 报文长度头：2字节大端长度
+pub fn frame(message: &[u8]) -> Result<Vec<u8>, AppError> {
+    let length = u16::try_from(message.len())
+        .map_err(|_| codec_error(format!("message too long: {} bytes", message.len())))?;
+    let mut framed = length.to_be_bytes().to_vec();
+    framed.extend_from_slice(message);
+    Ok(framed)
+}
+
+fn pack_field(
+    out: &mut Vec<u8>,
+    spec: &Iso8583Spec,
+    number: u8,
+    value: &[u8],
+) -> Result<(), AppError> {
+    let field = spec.field(number)?;
+
+    if field.encoding != FieldEncoding::Binary && !value.is_ascii() {
+        return Err(codec_error(format!("field {} must be ASCII", number)));
+    }
+    if field.encoding == FieldEncoding::Bcd && !value.iter().all(u8::is_ascii_digit) {
+        return Err(codec_error(format!("field {} must be numeric", number)));
+    }
+
+    let length = value.len();
+    match field.length_type {
+        LengthType::Fixed if length != field.length => {
+            return Err(codec_error(format!(
+                "field {} must be {} long, got {}",
+                number, field.length, length
+            )));
+        },
+        LengthType::Llvar | LengthType::Lllvar if length > field.length => {
+            return Err(codec_error(format!(
+                "field {} exceeds maximum length {}",
+                number, field.length
+            )));
+        },
+        _ => {},
+    }
+
+    let prefix_digits = match field.length_type {
+        LengthType::Fixed => 0,
+        LengthType::Llvar => 2,
+        LengthType::Lllvar => 3,
+    };
+    if prefix_digits > 0 {
+        let prefix = format!("{:0width$}", length, width = prefix_digits);
+        match spec.length_prefix_encoding {
+            FieldEncoding::Bcd => out.extend(bcd_encode(&prefix)?),
+            _ => out.extend(prefix.as_bytes()),
+        }
+    }
+
+    match field.encoding {
+        FieldEncoding::Bcd => {
+            out.extend(bcd_encode(std::str::from_utf8(value).unwrap_or_default())?)
+        },
+        _ => out.extend_from_slice(value),
+    }
+
+    Ok(())
+}
+
+fn unpack_field(cursor: &mut Cursor, spec: &Iso8583Spec, number: u8) -> Result<Vec<u8>, AppError> {
+    let field = spec.field(number)?;
+
+    let length = match field.length_type {
+        LengthType::Fixed => field.length,
+        LengthType::Llvar | LengthType::Lllvar => {
+            let digits: usize = if field.length_type == LengthType::Llvar { 2 } else { 3 };
+            let prefix = match spec.length_prefix_encoding {
+                FieldEncoding::Bcd => bcd_decode(cursor.take(digits.div_ceil(2))?, digits),
+                _ => ascii(cursor.take(digits)?)?,
+            };
+            let length: usize = prefix
+                .parse()
+                .map_err(|_| codec_error(format!("field {} has invalid length prefix", number)))?;
+            if length > field.length {
+                return Err(codec_error(format!(
+                    "field {} exceeds maximum length {}",
+                    number, field.length
+                )));
+            }
+            length
+        },
+    };
+
+    let value = match field.encoding {
+        FieldEncoding::Bcd => bcd_decode(cursor.take(length.div_ceil(2))?, length).into_bytes(),
+        _ => cursor.take(length)?.to_vec(),
+    };
+
+    Ok(value)
+}
+
+fn read_bitmap(cursor: &mut Cursor, encoding: BitmapEncoding) -> Result<Vec<u8>, AppError> {
+    match encoding {
+        BitmapEncoding::Binary => Ok(cursor.take(8)?.to_vec()),
+        BitmapEncoding::Hex => {
+            hex::decode(cursor.take(16)?).map_err(|_| codec_error("invalid hex bitmap"))
+        },
+    }
+}
+
+/// 数字串编码为压缩BCD，奇数位左补0
+fn bcd_encode(digits: &str) -> Result<Vec<u8>, AppError> {
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(codec_error(format!("{} is not numeric", digits)));
+    }
+    let padded = if digits.len() % 2 == 1 {
+        format!("0{}", digits)
+    } else {
+        digits.to_string()
+    };
+    Ok(padded
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| ((pair[0] - b'0') << 4) | (pair[1] - b'0'))
+        .collect())
+}
+
+/// 压缩BCD解码为 `digits` 位数字串，去掉奇数位时的左补位
+fn bcd_decode(bytes: &[u8], digits: usize) -> String {
+    let all: String = bytes
+        .iter()
+        .flat_map(|b| [b >> 4, b & 0x0F])
+        .map(|nibble| char::from_digit(nibble as u32, 16).unwrap_or('0').to_ascii_uppercase())
+        .collect();
+    all[all.len().saturating_sub(digits)..].to_string()
+}
+
+fn ascii(bytes: &[u8]) -> Result<String, AppError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| codec_error("invalid ASCII data"))
+}
+
+fn codec_error(message: impl Into<String>) -> AppError {
+    AppError::InvalidRequest(format!("ISO 8583: {}", message.into()))
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], AppError> {
+        let end = self.pos + n;
+        if end > self.data.len() {
+            return Err(codec_error("message truncated"));
+        }
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r#"{
+        "version": "1987",
+        "mti_encoding": "bcd",
+        "length_prefix_encoding": "bcd",
+        "fields": {
+            "2": { "length_type": "llvar", "length": 19, "encoding": "bcd" },
+            "3": { "length_type": "fixed", "length": 6, "encoding": "bcd" },
+            "4": { "length_type": "fixed", "length": 12, "encoding": "bcd" },
+            "11": { "length_type": "fixed", "length": 6, "encoding": "bcd" },
+            "41": { "length_type": "fixed", "length": 8, "encoding": "ascii" },
+            "52": { "length_type": "fixed", "length": 8, "encoding": "binary" },
+            "55": { "length_type": "lllvar", "length": 255, "encoding": "binary" },
+            "70": { "length_type": "fixed", "length": 3, "encoding": "ascii" }
+        }
+    }"#;
+
+    #[test]
+    fn test_pack_unpack_roundtrip() {
+        let spec = Iso8583Spec::from_json(SPEC).unwrap();
+        let mut message = IsoMessage::new("0200");
+        message
+            .set(2, "4761739001010119")
+            .set(3, "000000")
+            .set(4, "000000010000")
+            .set(11, "000123")
+            .set(41, "TERM0001")
+            .set(52, vec![0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0])
+            .set(55, vec![0x9F, 0x02, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00]);
+
+        let packed = message.pack(&spec).unwrap();
+        // BCD MTI + 8字节主位图，无二级位图
+        assert_eq!(&packed[..2], &[0x02, 0x00]);
+        assert_eq!(packed[2] & 0x80, 0);
+        // 字段2：BCD长度前缀16，8字节数据
+        assert_eq!(packed[10], 0x16);
+
+        assert_eq!(IsoMessage::unpack(&spec, &packed).unwrap(), message);
+    }
+
+    #[test]
+    fn test_secondary_bitmap_and_odd_bcd() {
+        let spec = Iso8583Spec::from_json(SPEC).unwrap();
+        let mut message = IsoMessage::new("0800");
+        message.set(2, "476173900101011").set(70, "301");
+
+        let packed = message.pack(&spec).unwrap();
+        assert_eq!(packed[2] & 0x80, 0x80);
+        assert_eq!(packed.len(), 2 + 16 + 1 + 8 + 3);
+        assert_eq!(IsoMessage::unpack(&spec, &packed).unwrap(), message);
+    }
+
+    #[test]
+    fn test_invalid_fields_rejected() {
+        let spec = Iso8583Spec::from_json(SPEC).unwrap();
+
+        let mut wrong_length = IsoMessage::new("0200");
+        wrong_length.set(3, "0000");
+        assert!(wrong_length.pack(&spec).is_err());
+
+        let mut not_numeric = IsoMessage::new("0200");
+        not_numeric.set(4, "00000001000A");
+        assert!(not_numeric.pack(&spec).is_err());
+
+        let mut undefined = IsoMessage::new("0200");
+        undefined.set(39, "00");
+        assert!(undefined.pack(&spec).is_err());
+
+        let mut message = IsoMessage::new("0200");
+        message.set(11, "000001");
+        let packed = message.pack(&spec).unwrap();
+        assert!(IsoMessage::unpack(&spec, &packed[..packed.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,504 @@
+pub mod http_gateway;
+pub mod iso8583;
+pub mod simulator;
+pub mod tcp;
+
+use std::{collections::HashMap, sync::Arc};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    infrastructure::config::{PaymentConfig, PaymentConnectorType, PaymentRoute},
+    models::{SettlementTotal, Transaction, TransactionType},
+    utils::error::AppError,
+};
+
+pub use http_gateway::HttpGatewayConnector;
+pub use simulator::SimulatorConnector;
+pub use tcp::TcpAcquirerConnector;
+
+/// 内置模拟器连接器的名称
+pub const SIMULATOR_CONNECTOR: &str = "simulator";
+
+/// 支付处理器操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentOperation {
+    Authorize,
+    Capture,
+    Refund,
+    Void,
+    Reversal,
+    /// 批次结算（对账），只通过 [`PaymentProcessor::settle`] 执行
+    Settlement,
+}
+
+impl PaymentOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentOperation::Authorize => "authorize",
+            PaymentOperation::Capture => "capture",
+            PaymentOperation::Refund => "refund",
+            PaymentOperation::Void => "void",
+            PaymentOperation::Reversal => "reversal",
+            PaymentOperation::Settlement => "settlement",
+        }
+    }
+
+    /// 交易类型对应的处理器操作；消费和预授权均为授权请求
+    pub fn for_transaction_type(transaction_type: &TransactionType) -> Self {
+        match transaction_type {
+            TransactionType::Payment | TransactionType::PreAuth => PaymentOperation::Authorize,
+            TransactionType::Capture => PaymentOperation::Capture,
+            TransactionType::Refund => PaymentOperation::Refund,
+            TransactionType::Void => PaymentOperation::Void,
+        }
+    }
+}
+
+/// 发往支付处理器的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    pub transaction_id: String,
+    pub transaction_type: TransactionType,
+    /// 金额（分）
+    pub amount: i64,
+    pub currency: String,
+    pub merchant_id: Option<String>,
+    /// 收单机构分配的商户号
+    pub acquirer_mid: Option<String>,
+    pub terminal_id: Option<String>,
+    pub card_number_masked: Option<String>,
+    pub encrypted_pin_block: Option<String>,
+    pub ksn: String,
+    /// 原交易的处理器参考号（请款、退款、撤销、冲正时使用）
+    pub original_reference: Option<String>,
+    /// EMV芯片数据（BER-TLV十六进制）
+    pub emv_data: Option<String>,
+    /// 本次报文使用的数据元，未设置时由连接器分配
+    #[serde(skip)]
+    pub identifiers: Option<MessageIdentifiers>,
+    /// 被冲正报文的数据元（冲正时使用）
+    #[serde(skip)]
+    pub original_identifiers: Option<MessageIdentifiers>,
+}
+
+impl PaymentRequest {
+    /// 根据交易记录构造请求
+    pub fn from_transaction(transaction: &Transaction, acquirer_mid: Option<String>) -> Self {
+        Self {
+            transaction_id: transaction.id.clone(),
+            transaction_type: transaction.transaction_type.clone(),
+            amount: transaction.amount,
+            currency: transaction.currency.clone(),
+            merchant_id: transaction.merchant_id.clone(),
+            acquirer_mid,
+            terminal_id: transaction.terminal_id.clone(),
+            card_number_masked: transaction.card_number_masked.clone(),
+            encrypted_pin_block: transaction.encrypted_pin_block.clone(),
+            ksn: transaction.ksn.clone(),
+            original_reference: None,
+            emv_data: transaction.emv_data.clone(),
+            identifiers: MessageIdentifiers::from_transaction(transaction),
+            original_identifiers: None,
+        }
+    }
+}
+
+/// 报文的原始数据元，冲正报文须携带被冲正报文的这些数据元
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageIdentifiers {
+    pub mti: String,
+    /// 系统跟踪号（STAN）
+    pub stan: String,
+    /// 检索参考号（RRN）
+    pub rrn: String,
+    /// 传输时间（MMDDhhmmss）
+    pub transmission_time: String,
+}
+
+impl MessageIdentifiers {
+    /// 交易记录中保存的数据元，未保存时为None
+    pub fn from_transaction(transaction: &Transaction) -> Option<Self> {
+        Some(Self {
+            mti: transaction.message_mti.clone()?,
+            stan: transaction.stan.clone()?,
+            rrn: transaction.rrn.clone()?,
+            transmission_time: transaction.transmission_time.clone()?,
+        })
+    }
+
+    /// 保存到交易记录
+    pub fn apply_to(&self, transaction: &mut Transaction) {
+        transaction.message_mti = Some(self.mti.clone());
+        transaction.stan = Some(self.stan.clone());
+        transaction.rrn = Some(self.rrn.clone());
+        transaction.transmission_time = Some(self.transmission_time.clone());
+    }
+}
+
+/// 处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PaymentOutcome {
+    Approved,
+    /// 部分批准，实际批准金额见 `approved_amount`
+    PartiallyApproved,
+    Declined,
+}
+
+/// 支付处理器的应答
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentResponse {
+    pub outcome: PaymentOutcome,
+    /// 批准金额（分），拒绝时为0
+    pub approved_amount: i64,
+    pub authorization_code: Option<String>,
+    /// ISO 8583应答码
+    pub response_code: String,
+    pub response_message: String,
+    /// 处理器返回的参考号，后续请款、退款、撤销时引用
+    pub processor_reference: Option<String>,
+}
+
+impl PaymentResponse {
+    pub fn is_approved(&self) -> bool {
+        self.outcome != PaymentOutcome::Declined
+    }
+}
+
+/// 发往支付处理器的批次结算请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementRequest {
+    pub batch_id: String,
+    pub batch_number: i64,
+    pub merchant_id: Option<String>,
+    /// 收单机构分配的商户号
+    pub acquirer_mid: Option<String>,
+    pub terminal_id: Option<String>,
+    /// 按交易类型和币种汇总的合计
+    pub totals: Vec<SettlementTotal>,
+    /// 批内明细，仅在对账不平后上送批次时填写
+    pub transactions: Vec<SettlementItem>,
+}
+
+impl SettlementRequest {
+    /// 是否为上送明细后的重新结算
+    pub fn is_upload(&self) -> bool {
+        !self.transactions.is_empty()
+    }
+}
+
+/// 批次上送的交易明细
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementItem {
+    pub transaction_id: String,
+    pub transaction_type: TransactionType,
+    /// 计入结算的金额（分）
+    pub amount: i64,
+    pub currency: String,
+    pub processor_reference: Option<String>,
+    pub authorization_code: Option<String>,
+    pub card_number_masked: Option<String>,
+}
+
+/// 结算结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SettlementOutcome {
+    /// 合计一致，结算完成
+    Settled,
+    /// 处理器合计与批次合计不一致，需上送明细
+    TotalsMismatch,
+    Rejected,
+}
+
+/// 支付处理器的结算应答
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementResponse {
+    pub outcome: SettlementOutcome,
+    pub response_code: String,
+    pub response_message: String,
+    pub processor_reference: Option<String>,
+}
+
+/// 支付处理器连接器
+///
+/// 处理器明确拒绝时返回 `outcome` 为 `Declined` 的应答；超时返回 [`AppError::ProcessorTimeout`]，
+/// 请求发出后连接中断或应答无效返回 [`AppError::ProcessorOutcomeUnknown`]，两者结果均未知；
+/// 请求发出前的通信错误（连接、写入失败）返回 [`AppError::External`]
+pub trait PaymentProcessor: Send + Sync {
+    fn authorize<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>>;
+
+    fn capture<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>>;
+
+    fn refund<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>>;
+
+    fn void<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>>;
+
+    fn reversal<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>>;
+
+    /// 提交批次结算；合计不平时返回 `TotalsMismatch`，由调用方上送明细后重新提交
+    fn settle<'a>(
+        &'a self,
+        request: &'a SettlementRequest,
+    ) -> BoxFuture<'a, Result<SettlementResponse, AppError>>;
+
+    /// 处理器连通性检查
+    fn health(&self) -> BoxFuture<'_, Result<(), AppError>>;
+
+    /// 是否按报文数据元匹配原交易；是则冲正须携带原报文的数据元
+    fn uses_message_identifiers(&self) -> bool {
+        false
+    }
+
+    /// 为即将发送的请求分配报文数据元，调用方在发送前随交易保存，冲正时通过
+    /// `original_identifiers` 传回；不使用报文数据元的连接器返回None
+    fn assign_identifiers(
+        &self,
+        _operation: PaymentOperation,
+        _request: &PaymentRequest,
+    ) -> Option<MessageIdentifiers> {
+        None
+    }
+}
+
+impl dyn PaymentProcessor {
+    /// 执行指定的交易操作，结算须调用 [`PaymentProcessor::settle`]
+    pub async fn execute(
+        &self,
+        operation: PaymentOperation,
+        request: &PaymentRequest,
+    ) -> Result<PaymentResponse, AppError> {
+        match operation {
+            PaymentOperation::Authorize => self.authorize(request).await,
+            PaymentOperation::Capture => self.capture(request).await,
+            PaymentOperation::Refund => self.refund(request).await,
+            PaymentOperation::Void => self.void(request).await,
+            PaymentOperation::Reversal => self.reversal(request).await,
+            PaymentOperation::Settlement => {
+                Err(AppError::BadRequest("Settlement is not a transaction operation".to_string()))
+            },
+        }
+    }
+}
+
+/// 支付处理器路由
+///
+/// 按商户ID或收单机构商户号前缀把交易路由到对应的连接器，规则按顺序匹配，
+/// 未命中任何规则时使用默认连接器
+#[derive(Clone)]
+pub struct PaymentRouter {
+    connectors: HashMap<String, Arc<dyn PaymentProcessor>>,
+    routes: Vec<PaymentRoute>,
+    default_connector: String,
+}
+
+impl Default for PaymentRouter {
+    /// 仅包含全部批准的内置模拟器
+    fn default() -> Self {
+        Self::new(SIMULATOR_CONNECTOR, Arc::new(SimulatorConnector::new()))
+    }
+}
+
+impl PaymentRouter {
+    /// 创建只有默认连接器的路由
+    pub fn new(name: impl Into<String>, connector: Arc<dyn PaymentProcessor>) -> Self {
+        let name = name.into();
+        let mut connectors: HashMap<String, Arc<dyn PaymentProcessor>> = HashMap::new();
+        connectors.insert(name.clone(), connector);
+        Self { connectors, routes: Vec::new(), default_connector: name }
+    }
+
+    /// 根据配置创建路由，规则或默认连接器引用了不存在的连接器时返回错误
+    ///
+    /// 未配置名为 `simulator` 的连接器时自动注册一个全部批准的内置模拟器
+    pub fn from_config(config: &PaymentConfig) -> Result<Self, AppError> {
+        let mut router = Self {
+            connectors: HashMap::new(),
+            routes: Vec::new(),
+            default_connector: config.default_connector.clone(),
+        };
+        router
+            .connectors
+            .insert(SIMULATOR_CONNECTOR.to_string(), Arc::new(SimulatorConnector::new()));
+
+        for connector in &config.connectors {
+            let processor: Arc<dyn PaymentProcessor> = match connector.connector_type {
+                PaymentConnectorType::Simulator => {
+                    Arc::new(SimulatorConnector::from_config(connector)?)
+                },
+                PaymentConnectorType::HttpGateway => {
+                    Arc::new(HttpGatewayConnector::from_config(connector)?)
+                },
+                PaymentConnectorType::Iso8583Tcp => {
+                    Arc::new(TcpAcquirerConnector::from_config(connector)?)
+                },
+            };
+            router = router.with_connector(connector.name.clone(), processor);
+        }
+
+        for route in &config.routes {
+            router = router.with_route(route.clone());
+        }
+
+        router.validate()?;
+        Ok(router)
+    }
+
+    /// 注册连接器，同名时覆盖
+    pub fn with_connector(
+        mut self,
+        name: impl Into<String>,
+        connector: Arc<dyn PaymentProcessor>,
+    ) -> Self {
+        self.connectors.insert(name.into(), connector);
+        self
+    }
+
+    /// 追加路由规则
+    pub fn with_route(mut self, route: PaymentRoute) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// 选择连接器，返回连接器名称和连接器
+    pub fn route(
+        &self,
+        merchant_id: Option<&str>,
+        acquirer_mid: Option<&str>,
+    ) -> Result<(&str, Arc<dyn PaymentProcessor>), AppError> {
+        let name = self
+            .routes
+            .iter()
+            .find(|route| route_matches(route, merchant_id, acquirer_mid))
+            .map(|route| route.connector.as_str())
+            .unwrap_or(&self.default_connector);
+
+        let connector = self.connectors.get(name).cloned().ok_or_else(|| {
+            AppError::Configuration(format!("Unknown payment connector: {}", name))
+        })?;
+
+        Ok((name, connector))
+    }
+
+    /// 按名称取得连接器，后续交易（请款、退款、撤销）交给原交易的连接器处理
+    pub fn connector(&self, name: &str) -> Option<Arc<dyn PaymentProcessor>> {
+        self.connectors.get(name).cloned()
+    }
+
+    /// 检查所有连接器，返回各连接器是否可用
+    pub async fn health_check(&self) -> HashMap<String, bool> {
+        let mut results = HashMap::new();
+        for (name, connector) in &self.connectors {
+            let healthy = match connector.health().await {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("Payment connector {} health check failed: {}", name, e);
+                    false
+                },
+            };
+            results.insert(name.clone(), healthy);
+        }
+        results
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        let names = std::iter::once(&self.default_connector)
+            .chain(self.routes.iter().map(|route| &route.connector));
+        for name in names {
+            if !self.connectors.contains_key(name) {
+                return Err(AppError::Configuration(format!(
+                    "Unknown payment connector: {}",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 应答码的描述
+pub(crate) fn response_message(response_code: &str) -> &'static str {
+    match response_code {
+        "00" => "Transaction approved",
+        "10" => "Partial approval",
+        "05" => "Do not honor",
+        "14" => "Invalid card number",
+        "51" => "Insufficient funds",
+        "54" => "Expired card",
+        "55" => "Incorrect PIN",
+        "61" => "Exceeds withdrawal amount limit",
+        "91" => "Issuer unavailable",
+        "95" => "Reconcile error",
+        _ => "Transaction declined",
+    }
+}
+
+fn route_matches(
+    route: &PaymentRoute,
+    merchant_id: Option<&str>,
+    acquirer_mid: Option<&str>,
+) -> bool {
+    let merchant_match = merchant_id.is_some_and(|id| route.merchant_ids.iter().any(|m| m == id));
+    let acquirer_match = acquirer_mid
+        .is_some_and(|mid| route.acquirer_mid_prefixes.iter().any(|p| mid.starts_with(p.as_str())));
+    merchant_match || acquirer_match
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(connector: &str, merchants: &[&str], prefixes: &[&str]) -> PaymentRoute {
+        PaymentRoute {
+            connector: connector.to_string(),
+            merchant_ids: merchants.iter().map(|s| s.to_string()).collect(),
+            acquirer_mid_prefixes: prefixes.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_route_by_merchant_and_acquirer() {
+        let router = PaymentRouter::default()
+            .with_connector("acquirer-a", Arc::new(SimulatorConnector::new()))
+            .with_connector("acquirer-b", Arc::new(SimulatorConnector::new()))
+            .with_route(route("acquirer-a", &["m-1"], &[]))
+            .with_route(route("acquirer-b", &[], &["8881"]));
+
+        assert_eq!(router.route(Some("m-1"), Some("888100001")).unwrap().0, "acquirer-a");
+        assert_eq!(router.route(Some("m-2"), Some("888100001")).unwrap().0, "acquirer-b");
+        assert_eq!(router.route(Some("m-2"), Some("777100001")).unwrap().0, SIMULATOR_CONNECTOR);
+        assert_eq!(router.route(None, None).unwrap().0, SIMULATOR_CONNECTOR);
+    }
+
+    #[test]
+    fn test_unknown_connector_rejected() {
+        let config = PaymentConfig {
+            default_connector: SIMULATOR_CONNECTOR.to_string(),
+            connectors: Vec::new(),
+            routes: vec![route("missing", &["m-1"], &[])],
+            ..PaymentConfig::default()
+        };
+        assert!(PaymentRouter::from_config(&config).is_err());
+
+        let config = PaymentConfig { routes: Vec::new(), ..config };
+        assert!(PaymentRouter::from_config(&config).is_ok());
+    }
+}
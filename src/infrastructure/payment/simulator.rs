@@ -0,0 +1,351 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use ring::digest;
+
+use super::{
+    response_message, PaymentOperation, PaymentOutcome, PaymentProcessor, PaymentRequest,
+    PaymentResponse, SettlementOutcome, SettlementRequest, SettlementResponse,
+};
+use crate::{
+    infrastructure::config::{PaymentConnectorConfig, SimulatorResult, SimulatorScenario},
+    models::{net_amounts, SettlementTotal},
+    utils::error::AppError,
+};
+
+/// 模拟支付处理器
+///
+/// 按脚本场景（金额、操作、商户）决定处理结果，未命中任何场景时批准。
+/// 授权码和参考号由交易ID派生，同一请求总是得到相同的应答，便于测试断言。
+/// 结算只匹配 `operations` 显式包含 `settlement` 的场景，金额为批次净额；
+/// 拒绝码95表示合计不平，上送明细后按明细重新汇总，与合计一致时结算成功
+#[derive(Debug, Clone, Default)]
+pub struct SimulatorConnector {
+    scenarios: Vec<SimulatorScenario>,
+    latency: Duration,
+}
+
+impl SimulatorConnector {
+    /// 创建全部批准、无延迟的模拟器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 根据配置创建模拟器，场景缺少必填字段时返回错误
+    pub fn from_config(config: &PaymentConnectorConfig) -> Result<Self, AppError> {
+        let mut connector = Self::new().with_latency(Duration::from_millis(config.latency_ms));
+        for scenario in &config.scenarios {
+            match scenario.result {
+                SimulatorResult::Decline if scenario.response_code.is_none() => {
+                    return Err(AppError::Configuration(format!(
+                        "Simulator {}: decline scenario requires response_code",
+                        config.name
+                    )));
+                },
+                SimulatorResult::PartialApproval if scenario.approved_amount.is_none() => {
+                    return Err(AppError::Configuration(format!(
+                        "Simulator {}: partial_approval scenario requires approved_amount",
+                        config.name
+                    )));
+                },
+                _ => {},
+            }
+            connector = connector.with_scenario(scenario.clone());
+        }
+        Ok(connector)
+    }
+
+    /// 追加场景，按添加顺序匹配
+    pub fn with_scenario(mut self, scenario: SimulatorScenario) -> Self {
+        self.scenarios.push(scenario);
+        self
+    }
+
+    /// 设置每次请求的处理延迟
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    async fn process(
+        &self,
+        operation: PaymentOperation,
+        request: &PaymentRequest,
+    ) -> Result<PaymentResponse, AppError> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+
+        let scenario = self.scenarios.iter().find(|s| scenario_matches(s, operation, request));
+        let result = scenario.map(|s| s.result).unwrap_or(SimulatorResult::Approve);
+
+        let fingerprint = fingerprint(&request.transaction_id);
+        let reference = Some(format!("SIM{}", &fingerprint[..12]));
+        let authorization_code = Some(fingerprint[..6].to_string());
+
+        let response = match result {
+            SimulatorResult::Approve => PaymentResponse {
+                outcome: PaymentOutcome::Approved,
+                approved_amount: request.amount,
+                authorization_code,
+                response_code: "00".to_string(),
+                response_message: response_message("00").to_string(),
+                processor_reference: reference,
+            },
+            SimulatorResult::PartialApproval => PaymentResponse {
+                outcome: PaymentOutcome::PartiallyApproved,
+                approved_amount: scenario
+                    .and_then(|s| s.approved_amount)
+                    .unwrap_or(request.amount)
+                    .min(request.amount),
+                authorization_code,
+                response_code: "10".to_string(),
+                response_message: response_message("10").to_string(),
+                processor_reference: reference,
+            },
+            SimulatorResult::Decline => {
+                let response_code = scenario
+                    .and_then(|s| s.response_code.clone())
+                    .unwrap_or_else(|| "05".to_string());
+                let response_message = scenario
+                    .and_then(|s| s.message.clone())
+                    .unwrap_or_else(|| response_message(&response_code).to_string());
+                PaymentResponse {
+                    outcome: PaymentOutcome::Declined,
+                    approved_amount: 0,
+                    authorization_code: None,
+                    response_code,
+                    response_message,
+                    processor_reference: reference,
+                }
+            },
+            SimulatorResult::Timeout => {
+                return Err(AppError::ProcessorTimeout(format!(
+                    "Simulated timeout for {} {}",
+                    operation.as_str(),
+                    request.transaction_id
+                )));
+            },
+        };
+
+        Ok(response)
+    }
+
+    async fn process_settlement(
+        &self,
+        request: &SettlementRequest,
+    ) -> Result<SettlementResponse, AppError> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+
+        let net: i64 = request.totals.iter().map(SettlementTotal::signed_amount).sum();
+        let scenario = self.scenarios.iter().find(|s| {
+            s.operations.contains(&PaymentOperation::Settlement)
+                && s.amount.is_none_or(|amount| amount == net)
+                && s.merchant_id.as_ref().is_none_or(|m| request.merchant_id.as_ref() == Some(m))
+        });
+        let result = scenario.map(|s| s.result).unwrap_or(SimulatorResult::Approve);
+        let reference = Some(format!("SIM{}", &fingerprint(&request.batch_id)[..12]));
+
+        let (outcome, response_code) = match result {
+            SimulatorResult::Approve | SimulatorResult::PartialApproval => {
+                (SettlementOutcome::Settled, "00".to_string())
+            },
+            SimulatorResult::Decline => {
+                let response_code = scenario
+                    .and_then(|s| s.response_code.clone())
+                    .unwrap_or_else(|| "05".to_string());
+                match response_code.as_str() {
+                    "95" if request.is_upload() && upload_balances(request) => {
+                        (SettlementOutcome::Settled, "00".to_string())
+                    },
+                    "95" => (SettlementOutcome::TotalsMismatch, response_code),
+                    _ => (SettlementOutcome::Rejected, response_code),
+                }
+            },
+            SimulatorResult::Timeout => {
+                return Err(AppError::ProcessorTimeout(format!(
+                    "Simulated timeout for settlement of batch {}",
+                    request.batch_id
+                )));
+            },
+        };
+
+        let response_message = match scenario.and_then(|s| s.message.clone()) {
+            Some(message) if outcome != SettlementOutcome::Settled => message,
+            _ => response_message(&response_code).to_string(),
+        };
+
+        Ok(SettlementResponse {
+            outcome,
+            response_code,
+            response_message,
+            processor_reference: reference,
+        })
+    }
+}
+
+impl PaymentProcessor for SimulatorConnector {
+    fn authorize<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Authorize, request))
+    }
+
+    fn capture<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Capture, request))
+    }
+
+    fn refund<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Refund, request))
+    }
+
+    fn void<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Void, request))
+    }
+
+    fn reversal<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Reversal, request))
+    }
+
+    fn settle<'a>(
+        &'a self,
+        request: &'a SettlementRequest,
+    ) -> BoxFuture<'a, Result<SettlementResponse, AppError>> {
+        Box::pin(self.process_settlement(request))
+    }
+
+    fn health(&self) -> BoxFuture<'_, Result<(), AppError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+fn scenario_matches(
+    scenario: &SimulatorScenario,
+    operation: PaymentOperation,
+    request: &PaymentRequest,
+) -> bool {
+    scenario.amount.is_none_or(|amount| amount == request.amount)
+        && (scenario.operations.is_empty() || scenario.operations.contains(&operation))
+        && scenario.merchant_id.as_ref().is_none_or(|m| request.merchant_id.as_ref() == Some(m))
+}
+
+/// 按上送的明细重新汇总，与批次合计的各币种净额比较
+fn upload_balances(request: &SettlementRequest) -> bool {
+    let detail: Vec<SettlementTotal> = request
+        .transactions
+        .iter()
+        .map(|item| SettlementTotal {
+            transaction_type: item.transaction_type.clone(),
+            currency: item.currency.clone(),
+            count: 1,
+            amount: item.amount,
+        })
+        .collect();
+    net_amounts(&detail) == net_amounts(&request.totals)
+}
+
+/// 交易ID的SHA-256摘要（大写十六进制）
+fn fingerprint(transaction_id: &str) -> String {
+    hex::encode_upper(digest::digest(&digest::SHA256, transaction_id.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionType;
+
+    fn request(amount: i64) -> PaymentRequest {
+        PaymentRequest {
+            transaction_id: "txn-1".to_string(),
+            transaction_type: TransactionType::Payment,
+            amount,
+            currency: "USD".to_string(),
+            merchant_id: None,
+            acquirer_mid: None,
+            terminal_id: None,
+            card_number_masked: None,
+            encrypted_pin_block: None,
+            ksn: "FFFF9876543210E00000".to_string(),
+            original_reference: None,
+            emv_data: None,
+            identifiers: None,
+            original_identifiers: None,
+        }
+    }
+
+    fn scenario(amount: i64, result: SimulatorResult) -> SimulatorScenario {
+        SimulatorScenario {
+            amount: Some(amount),
+            operations: Vec::new(),
+            merchant_id: None,
+            result,
+            response_code: None,
+            message: None,
+            approved_amount: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scripted_scenarios() {
+        let simulator = SimulatorConnector::new()
+            .with_scenario(SimulatorScenario {
+                response_code: Some("51".to_string()),
+                ..scenario(5100, SimulatorResult::Decline)
+            })
+            .with_scenario(SimulatorScenario {
+                approved_amount: Some(1000),
+                ..scenario(1500, SimulatorResult::PartialApproval)
+            })
+            .with_scenario(scenario(9100, SimulatorResult::Timeout));
+
+        let approved = simulator.authorize(&request(10000)).await.unwrap();
+        assert_eq!(approved.outcome, PaymentOutcome::Approved);
+        assert_eq!(approved.approved_amount, 10000);
+        // 相同交易ID得到相同授权码
+        assert_eq!(
+            approved.authorization_code,
+            simulator.authorize(&request(200)).await.unwrap().authorization_code
+        );
+
+        let declined = simulator.authorize(&request(5100)).await.unwrap();
+        assert_eq!(declined.outcome, PaymentOutcome::Declined);
+        assert_eq!(declined.response_code, "51");
+        assert_eq!(declined.response_message, "Insufficient funds");
+
+        let partial = simulator.authorize(&request(1500)).await.unwrap();
+        assert_eq!(partial.outcome, PaymentOutcome::PartiallyApproved);
+        assert_eq!(partial.approved_amount, 1000);
+
+        assert!(matches!(
+            simulator.authorize(&request(9100)).await,
+            Err(AppError::ProcessorTimeout(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_scenario_limited_to_operations() {
+        let simulator = SimulatorConnector::new().with_scenario(SimulatorScenario {
+            operations: vec![PaymentOperation::Refund],
+            response_code: Some("05".to_string()),
+            ..scenario(500, SimulatorResult::Decline)
+        });
+
+        assert!(simulator.authorize(&request(500)).await.unwrap().is_approved());
+        assert!(!simulator.refund(&request(500)).await.unwrap().is_approved());
+    }
+}
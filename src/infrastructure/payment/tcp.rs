@@ -0,0 +1,838 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex as StdMutex, Weak,
+    },
+    time::Duration,
+};
+
+use chrono::{Datelike, Timelike, Utc};
+use futures::future::BoxFuture;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
+    sync::{oneshot, Mutex},
+    task::JoinHandle,
+};
+
+use super::{
+    iso8583::{frame, Iso8583Spec, Iso8583Version, IsoMessage},
+    response_message, MessageIdentifiers, PaymentOperation, PaymentOutcome, PaymentProcessor,
+    PaymentRequest, PaymentResponse, SettlementItem, SettlementOutcome, SettlementRequest,
+    SettlementResponse,
+};
+use crate::{
+    infrastructure::config::PaymentConnectorConfig,
+    models::{SettlementTotal, TransactionType},
+    utils::error::AppError,
+};
+
+/// ISO 8583 over TCP收单连接器
+///
+/// 与收单机构保持一条长连接，报文带2字节大端长度头。每个请求分配STAN并生成RRN，
+/// 应答按应答MTI和STAN与请求匹配，同一连接上可并发多个请求。连接空闲时按间隔发送
+/// 网络管理回响报文（1987为0800/70=301，1993为1804/24=831）保活，回响失败时断开，
+/// 下一个请求重新建立连接。请款、退款、撤销和冲正以原交易的RRN填入字段37。
+/// 报文数据元（MTI、STAN、RRN、传输时间）由调用方在发送前通过 `assign_identifiers`
+/// 分配并保存，冲正时传回的原报文数据元放入字段90（1987）或56（1993）。
+/// 规格中定义了的可选字段（13、22、24、90、56）才会发送。
+///
+/// 批次结算按币种发送对账报文（1987为0500，1993为1520，处理码920000），借贷笔数和金额
+/// 放在字段74、76、86、88，净额放在字段97，批次号放在字段60。对账不平时逐笔发送0320
+/// 上送明细，再以处理码960000重新对账
+pub struct TcpAcquirerConnector {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    name: String,
+    address: String,
+    spec: Arc<Iso8583Spec>,
+    timeout: Duration,
+    echo_interval: Duration,
+    stan: AtomicU32,
+    connection: Mutex<Option<Arc<Connection>>>,
+}
+
+/// 一条TCP连接及其上等待应答的请求
+struct Connection {
+    writer: Mutex<OwnedWriteHalf>,
+    pending: StdMutex<HashMap<String, oneshot::Sender<IsoMessage>>>,
+    closed: AtomicBool,
+    reader: StdMutex<Option<JoinHandle<()>>>,
+}
+
+impl TcpAcquirerConnector {
+    /// 创建连接器，首个请求时才建立连接
+    pub fn new(
+        name: impl Into<String>,
+        address: impl Into<String>,
+        spec: Iso8583Spec,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                name: name.into(),
+                address: address.into(),
+                spec: Arc::new(spec),
+                timeout,
+                echo_interval: Duration::from_secs(60),
+                stan: AtomicU32::new(0),
+                connection: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// 根据配置创建连接器，缺少地址或规格文件无效时返回错误
+    pub fn from_config(config: &PaymentConnectorConfig) -> Result<Self, AppError> {
+        let address = config.address.as_deref().ok_or_else(|| {
+            AppError::Configuration(format!("ISO 8583 connector {} requires address", config.name))
+        })?;
+        let spec_file = config.spec_file.as_deref().ok_or_else(|| {
+            AppError::Configuration(format!(
+                "ISO 8583 connector {} requires spec_file",
+                config.name
+            ))
+        })?;
+
+        Ok(Self::new(
+            &config.name,
+            address,
+            Iso8583Spec::from_file(spec_file)?,
+            Duration::from_secs(config.timeout_seconds),
+        )
+        .with_echo_interval(Duration::from_secs(config.echo_interval_seconds)))
+    }
+
+    /// 设置回响保活间隔，为0时不保活；须在首次连接前设置
+    pub fn with_echo_interval(mut self, echo_interval: Duration) -> Self {
+        if let Some(inner) = Arc::get_mut(&mut self.inner) {
+            inner.echo_interval = echo_interval;
+        }
+        self
+    }
+
+    /// 发送回响报文
+    pub async fn echo(&self) -> Result<(), AppError> {
+        let connection = self.inner.connection().await?;
+        self.inner.echo_on(&connection).await
+    }
+
+    async fn process(
+        &self,
+        operation: PaymentOperation,
+        request: &PaymentRequest,
+    ) -> Result<PaymentResponse, AppError> {
+        let inner = &self.inner;
+        let (message, rrn) = inner.build_request(operation, request)?;
+
+        let connection = inner.connection().await?;
+        let response = inner.exchange(&connection, &message).await?;
+
+        Ok(inner.parse_response(&response, request, &rrn))
+    }
+
+    async fn settle_batch(
+        &self,
+        request: &SettlementRequest,
+    ) -> Result<SettlementResponse, AppError> {
+        let inner = &self.inner;
+        let connection = inner.connection().await?;
+
+        for item in &request.transactions {
+            let message = inner.build_upload(request, item)?;
+            let response = inner.exchange(&connection, &message).await?;
+            let code = response.get_str(39).map(str::trim).unwrap_or("96");
+            if !matches!(code, "00" | "000") {
+                return Ok(SettlementResponse {
+                    outcome: SettlementOutcome::Rejected,
+                    response_code: code.to_string(),
+                    response_message: format!(
+                        "Batch upload of transaction {} rejected",
+                        item.transaction_id
+                    ),
+                    processor_reference: None,
+                });
+            }
+        }
+
+        let mut currencies: Vec<&str> =
+            request.totals.iter().map(|total| total.currency.as_str()).collect();
+        currencies.sort_unstable();
+        currencies.dedup();
+
+        let mut settled = None;
+        for currency in currencies {
+            let message = inner.build_reconciliation(request, currency)?;
+            let response = inner.exchange(&connection, &message).await?;
+            let (outcome, response_code) = settlement_outcome(response.get_str(39));
+            let result = SettlementResponse {
+                outcome,
+                response_message: response_message(&response_code).to_string(),
+                response_code,
+                processor_reference: response
+                    .get_str(37)
+                    .map(|rrn| rrn.trim().to_string())
+                    .filter(|rrn| !rrn.is_empty()),
+            };
+            if outcome != SettlementOutcome::Settled {
+                return Ok(result);
+            }
+            settled = Some(result);
+        }
+
+        Ok(settled.unwrap_or_else(|| SettlementResponse {
+            outcome: SettlementOutcome::Settled,
+            response_code: "00".to_string(),
+            response_message: response_message("00").to_string(),
+            processor_reference: None,
+        }))
+    }
+}
+
+impl Inner {
+    /// 取得可用连接，没有或已断开时重新连接
+    async fn connection(self: &Arc<Self>) -> Result<Arc<Connection>, AppError> {
+        let mut guard = self.connection.lock().await;
+        if let Some(connection) = guard.as_ref() {
+            if !connection.closed.load(Ordering::SeqCst) {
+                return Ok(connection.clone());
+            }
+        }
+
+        let stream = tokio::time::timeout(self.timeout, TcpStream::connect(&self.address))
+            .await
+            .map_err(|_| AppError::External(format!("Acquirer {} connect timed out", self.name)))?
+            .map_err(|e| {
+                AppError::External(format!("Acquirer {} connect failed: {}", self.name, e))
+            })?;
+        let (reader, writer) = stream.into_split();
+
+        let connection = Arc::new(Connection {
+            writer: Mutex::new(writer),
+            pending: StdMutex::new(HashMap::new()),
+            closed: AtomicBool::new(false),
+            reader: StdMutex::new(None),
+        });
+        let handle = tokio::spawn(read_loop(
+            self.name.clone(),
+            reader,
+            self.spec.clone(),
+            connection.clone(),
+        ));
+        *connection.reader.lock().unwrap() = Some(handle);
+
+        if !self.echo_interval.is_zero() {
+            tokio::spawn(keepalive(Arc::downgrade(self), Arc::downgrade(&connection)));
+        }
+
+        tracing::info!("Connected to acquirer {} at {}", self.name, self.address);
+        *guard = Some(connection.clone());
+        Ok(connection)
+    }
+
+    /// 发送请求并等待匹配的应答
+    async fn exchange(
+        &self,
+        connection: &Connection,
+        message: &IsoMessage,
+    ) -> Result<IsoMessage, AppError> {
+        let packed = frame(&message.pack(&self.spec)?)?;
+        let stan = message.get_str(11).unwrap_or_default();
+        let key = match_key(&response_mti(&message.mti), stan);
+
+        let (tx, rx) = oneshot::channel();
+        connection.pending.lock().unwrap().insert(key.clone(), tx);
+
+        let written = {
+            let mut writer = connection.writer.lock().await;
+            tokio::time::timeout(self.timeout, writer.write_all(&packed)).await
+        };
+        if !matches!(written, Ok(Ok(()))) {
+            connection.pending.lock().unwrap().remove(&key);
+            connection.close();
+            return Err(AppError::External(format!("Acquirer {} write failed", self.name)));
+        }
+
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(response)) => Ok(response),
+            // 请求已写出，收单机构可能已处理
+            Ok(Err(_)) => Err(AppError::ProcessorOutcomeUnknown(format!(
+                "Acquirer {} connection closed before response to {} STAN {}",
+                self.name, message.mti, stan
+            ))),
+            Err(_) => {
+                connection.pending.lock().unwrap().remove(&key);
+                Err(AppError::ProcessorTimeout(format!(
+                    "Acquirer {} did not respond to {} STAN {}",
+                    self.name, message.mti, stan
+                )))
+            },
+        }
+    }
+
+    async fn echo_on(&self, connection: &Connection) -> Result<(), AppError> {
+        let now = Utc::now();
+        let mut message = match self.spec.version {
+            Iso8583Version::V1987 => {
+                let mut m = IsoMessage::new(self.spec.version.mti("800"));
+                m.set(70, "301");
+                m
+            },
+            Iso8583Version::V1993 => {
+                let mut m = IsoMessage::new(self.spec.version.mti("804"));
+                m.set(24, "831");
+                m
+            },
+        };
+        message.set(7, now.format("%m%d%H%M%S").to_string()).set(11, self.next_stan());
+
+        self.exchange(connection, &message).await.map(|_| ())
+    }
+
+    fn next_stan(&self) -> String {
+        let stan = self
+            .stan
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| Some(n % 999_999 + 1))
+            .unwrap_or_default();
+        format!("{:06}", stan % 999_999 + 1)
+    }
+
+    /// 分配报文数据元：新的STAN和传输时间；冲正沿用原报文的RRN，
+    /// 其他后续交易使用原交易的处理器参考号，首笔交易生成新的RRN
+    fn identifiers(
+        &self,
+        operation: PaymentOperation,
+        request: &PaymentRequest,
+    ) -> MessageIdentifiers {
+        let now = Utc::now();
+        let stan = self.next_stan();
+        let class = match (operation, &request.transaction_type) {
+            (PaymentOperation::Authorize, TransactionType::PreAuth) => "100",
+            (PaymentOperation::Capture, _) => "220",
+            (PaymentOperation::Reversal, _) => "400",
+            _ => "200",
+        };
+
+        let original_rrn = match operation {
+            PaymentOperation::Reversal => request.original_identifiers.as_ref().map(|o| &o.rrn),
+            _ => request.original_reference.as_ref(),
+        };
+        let rrn = original_rrn.cloned().unwrap_or_else(|| {
+            format!("{}{:03}{:02}{}", now.year() % 10, now.ordinal(), now.hour(), stan)
+        });
+
+        MessageIdentifiers {
+            mti: self.spec.version.mti(class),
+            stan,
+            rrn,
+            transmission_time: now.format("%m%d%H%M%S").to_string(),
+        }
+    }
+
+    /// 构造交易报文，返回报文和使用的RRN
+    fn build_request(
+        &self,
+        operation: PaymentOperation,
+        request: &PaymentRequest,
+    ) -> Result<(IsoMessage, String), AppError> {
+        let version = self.spec.version;
+        let now = Utc::now();
+        // 冲正报文本身总是分配新的数据元
+        let identifiers = match (operation, &request.identifiers) {
+            (PaymentOperation::Reversal, _) | (_, None) => self.identifiers(operation, request),
+            (_, Some(identifiers)) => identifiers.clone(),
+        };
+        let MessageIdentifiers { mti, stan, rrn, transmission_time } = identifiers;
+
+        let processing_code = match operation {
+            PaymentOperation::Refund => "200000",
+            PaymentOperation::Void => "020000",
+            _ => "000000",
+        };
+
+        let terminal_id = request.terminal_id.as_deref().ok_or_else(|| {
+            AppError::BadRequest("Terminal ID is required for ISO 8583 acquirers".to_string())
+        })?;
+        let merchant_id = request.acquirer_mid.as_deref().ok_or_else(|| {
+            AppError::BadRequest("Acquirer MID is required for ISO 8583 acquirers".to_string())
+        })?;
+        let currency = currency_numeric(&request.currency).ok_or_else(|| {
+            AppError::BadRequest(format!("Unsupported currency {}", request.currency))
+        })?;
+
+        let class = mti.get(1..).unwrap_or_default();
+        let mut message = IsoMessage::new(mti.clone());
+        message
+            .set(3, processing_code)
+            .set(4, format!("{:012}", request.amount))
+            .set(7, transmission_time)
+            .set(11, stan)
+            .set(37, rrn.clone())
+            .set(41, format!("{:<8}", terminal_id))
+            .set(42, format!("{:<15}", merchant_id))
+            .set(49, currency);
+
+        match version {
+            Iso8583Version::V1987 => {
+                message.set(12, now.format("%H%M%S").to_string());
+                self.set_optional(&mut message, 13, now.format("%m%d").to_string());
+            },
+            Iso8583Version::V1993 => {
+                message.set(12, now.format("%y%m%d%H%M%S").to_string());
+                let function_code = match (operation, class) {
+                    (PaymentOperation::Capture, _) => "201",
+                    (PaymentOperation::Reversal, _) => "400",
+                    (_, "100") => "100",
+                    _ => "200",
+                };
+                self.set_optional(&mut message, 24, function_code.to_string());
+            },
+        }
+        self.set_optional(&mut message, 22, "071".to_string());
+
+        if let (PaymentOperation::Reversal, Some(original)) =
+            (operation, &request.original_identifiers)
+        {
+            let original_data = format!(
+                "{}{}{}{:0>22}",
+                original.mti, original.stan, original.transmission_time, ""
+            );
+            match version {
+                Iso8583Version::V1987 => self.set_optional(&mut message, 90, original_data),
+                Iso8583Version::V1993 => {
+                    self.set_optional(&mut message, 56, original_data[..20].to_string())
+                },
+            }
+        }
+
+        if let Some(pin_block) = &request.encrypted_pin_block {
+            message.set(52, decode_hex_field(pin_block, Some(8), "PIN block")?);
+        }
+        if let Some(emv_data) = &request.emv_data {
+            message.set(55, decode_hex_field(emv_data, None, "EMV data")?);
+        }
+
+        Ok((message, rrn))
+    }
+
+    /// 单一币种的对账报文
+    fn build_reconciliation(
+        &self,
+        request: &SettlementRequest,
+        currency: &str,
+    ) -> Result<IsoMessage, AppError> {
+        let version = self.spec.version;
+        let now = Utc::now();
+        let class = match version {
+            Iso8583Version::V1987 => "500",
+            Iso8583Version::V1993 => "520",
+        };
+        let processing_code = if request.is_upload() {
+            "960000"
+        } else {
+            "920000"
+        };
+
+        let (mut debits, mut debit_amount, mut credits, mut credit_amount) = (0, 0, 0, 0);
+        for total in request.totals.iter().filter(|total| total.currency == currency) {
+            match total.transaction_type {
+                TransactionType::Payment | TransactionType::Capture => {
+                    debits += total.count;
+                    debit_amount += total.amount;
+                },
+                TransactionType::Refund => {
+                    credits += total.count;
+                    credit_amount += total.amount;
+                },
+                TransactionType::Void | TransactionType::PreAuth => {},
+            }
+        }
+        let net: i64 = request
+            .totals
+            .iter()
+            .filter(|t| t.currency == currency)
+            .map(SettlementTotal::signed_amount)
+            .sum();
+
+        let mut message = IsoMessage::new(version.mti(class));
+        message
+            .set(3, processing_code)
+            .set(7, now.format("%m%d%H%M%S").to_string())
+            .set(11, self.next_stan());
+        self.set_terminal(
+            &mut message,
+            request.terminal_id.as_deref(),
+            request.acquirer_mid.as_deref(),
+        )?;
+        message.set(49, currency_code(currency)?);
+
+        match version {
+            Iso8583Version::V1987 => {
+                message.set(12, now.format("%H%M%S").to_string());
+                self.set_optional(&mut message, 13, now.format("%m%d").to_string());
+            },
+            Iso8583Version::V1993 => {
+                message.set(12, now.format("%y%m%d%H%M%S").to_string());
+                self.set_optional(&mut message, 24, "500".to_string());
+            },
+        }
+
+        self.set_optional(&mut message, 60, format!("{:06}", request.batch_number));
+        self.set_optional(&mut message, 74, format!("{:010}", credits));
+        self.set_optional(&mut message, 76, format!("{:010}", debits));
+        self.set_optional(&mut message, 86, format!("{:016}", credit_amount));
+        self.set_optional(&mut message, 88, format!("{:016}", debit_amount));
+        // 净额：C为应付商户，D为商户应付
+        self.set_optional(
+            &mut message,
+            97,
+            format!("{}{:016}", if net >= 0 { 'C' } else { 'D' }, net.abs()),
+        );
+
+        Ok(message)
+    }
+
+    /// 批次上送的单笔明细报文
+    fn build_upload(
+        &self,
+        request: &SettlementRequest,
+        item: &SettlementItem,
+    ) -> Result<IsoMessage, AppError> {
+        let now = Utc::now();
+        let stan = self.next_stan();
+        let processing_code = match item.transaction_type {
+            TransactionType::Refund => "200000",
+            TransactionType::Void => "020000",
+            _ => "000000",
+        };
+        let rrn = item.processor_reference.clone().unwrap_or_else(|| {
+            format!("{}{:03}{:02}{}", now.year() % 10, now.ordinal(), now.hour(), stan)
+        });
+
+        let mut message = IsoMessage::new(self.spec.version.mti("320"));
+        message
+            .set(3, processing_code)
+            .set(4, format!("{:012}", item.amount))
+            .set(7, now.format("%m%d%H%M%S").to_string())
+            .set(11, stan)
+            .set(37, format!("{:<12}", rrn));
+        if let Some(code) = &item.authorization_code {
+            message.set(38, format!("{:<6}", code));
+        }
+        self.set_terminal(
+            &mut message,
+            request.terminal_id.as_deref(),
+            request.acquirer_mid.as_deref(),
+        )?;
+        message.set(49, currency_code(&item.currency)?);
+        self.set_optional(&mut message, 60, format!("{:06}", request.batch_number));
+
+        Ok(message)
+    }
+
+    /// 填写终端号（字段41）和收单商户号（字段42）
+    fn set_terminal(
+        &self,
+        message: &mut IsoMessage,
+        terminal_id: Option<&str>,
+        merchant_id: Option<&str>,
+    ) -> Result<(), AppError> {
+        let terminal_id = terminal_id.ok_or_else(|| {
+            AppError::BadRequest("Terminal ID is required for ISO 8583 acquirers".to_string())
+        })?;
+        let merchant_id = merchant_id.ok_or_else(|| {
+            AppError::BadRequest("Acquirer MID is required for ISO 8583 acquirers".to_string())
+        })?;
+        message
+            .set(41, format!("{:<8}", terminal_id))
+            .set(42, format!("{:<15}", merchant_id));
+        Ok(())
+    }
+
+    fn set_optional(&self, message: &mut IsoMessage, field: u8, value: String) {
+        if self.spec.fields.contains_key(&field) {
+            message.set(field, value);
+        }
+    }
+
+    fn parse_response(
+        &self,
+        response: &IsoMessage,
+        request: &PaymentRequest,
+        rrn: &str,
+    ) -> PaymentResponse {
+        // 1993版使用3位行为码
+        let response_code = match response.get_str(39).map(str::trim) {
+            Some("000") | Some("001") => "00".to_string(),
+            Some("002") => "10".to_string(),
+            Some(code) => code.to_string(),
+            None => "96".to_string(),
+        };
+
+        let outcome = match response_code.as_str() {
+            "00" => PaymentOutcome::Approved,
+            "10" => PaymentOutcome::PartiallyApproved,
+            _ => PaymentOutcome::Declined,
+        };
+        let approved_amount = match outcome {
+            PaymentOutcome::Approved => request.amount,
+            PaymentOutcome::PartiallyApproved => response
+                .get_str(4)
+                .and_then(|amount| amount.parse::<i64>().ok())
+                .unwrap_or(request.amount)
+                .min(request.amount),
+            PaymentOutcome::Declined => 0,
+        };
+
+        PaymentResponse {
+            outcome,
+            approved_amount,
+            authorization_code: response
+                .get_str(38)
+                .map(|code| code.trim().to_string())
+                .filter(|code| !code.is_empty()),
+            response_message: response_message(&response_code).to_string(),
+            response_code,
+            processor_reference: Some(
+                response.get_str(37).map(str::trim).unwrap_or(rrn).to_string(),
+            ),
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Ok(guard) = self.connection.try_lock() {
+            if let Some(connection) = guard.as_ref() {
+                connection.close();
+            }
+        }
+    }
+}
+
+impl Connection {
+    /// 标记断开并停止读取，等待中的请求随即返回错误
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        if let Some(reader) = self.reader.lock().unwrap().take() {
+            reader.abort();
+        }
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+impl PaymentProcessor for TcpAcquirerConnector {
+    fn authorize<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Authorize, request))
+    }
+
+    fn capture<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Capture, request))
+    }
+
+    fn refund<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Refund, request))
+    }
+
+    fn void<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Void, request))
+    }
+
+    fn reversal<'a>(
+        &'a self,
+        request: &'a PaymentRequest,
+    ) -> BoxFuture<'a, Result<PaymentResponse, AppError>> {
+        Box::pin(self.process(PaymentOperation::Reversal, request))
+    }
+
+    fn settle<'a>(
+        &'a self,
+        request: &'a SettlementRequest,
+    ) -> BoxFuture<'a, Result<SettlementResponse, AppError>> {
+        Box::pin(self.settle_batch(request))
+    }
+
+    fn health(&self) -> BoxFuture<'_, Result<(), AppError>> {
+        Box::pin(self.echo())
+    }
+
+    fn uses_message_identifiers(&self) -> bool {
+        true
+    }
+
+    fn assign_identifiers(
+        &self,
+        operation: PaymentOperation,
+        request: &PaymentRequest,
+    ) -> Option<MessageIdentifiers> {
+        Some(self.inner.identifiers(operation, request))
+    }
+}
+
+/// 读取应答并交给等待的请求，连接断开时结束
+async fn read_loop(
+    name: String,
+    mut reader: OwnedReadHalf,
+    spec: Arc<Iso8583Spec>,
+    connection: Arc<Connection>,
+) {
+    loop {
+        let mut header = [0u8; 2];
+        if reader.read_exact(&mut header).await.is_err() {
+            break;
+        }
+        let mut body = vec![0u8; u16::from_be_bytes(header) as usize];
+        if reader.read_exact(&mut body).await.is_err() {
+            break;
+        }
+
+        match IsoMessage::unpack(&spec, &body) {
+            Ok(message) => {
+                let key = match_key(&message.mti, message.get_str(11).unwrap_or_default());
+                let waiter = connection.pending.lock().unwrap().remove(&key);
+                match waiter {
+                    Some(waiter) => {
+                        let _ = waiter.send(message);
+                    },
+                    None => tracing::warn!("Acquirer {} sent unmatched message {}", name, key),
+                }
+            },
+            Err(e) => tracing::warn!("Acquirer {} sent invalid message: {}", name, e),
+        }
+    }
+
+    tracing::warn!("Acquirer {} connection closed", name);
+    connection.closed.store(true, Ordering::SeqCst);
+    connection.pending.lock().unwrap().clear();
+}
+
+/// 按间隔发送回响报文，失败时断开连接；连接器释放或连接断开后结束
+async fn keepalive(inner: Weak<Inner>, connection: Weak<Connection>) {
+    loop {
+        let Some(interval) = inner.upgrade().map(|inner| inner.echo_interval) else {
+            return;
+        };
+        tokio::time::sleep(interval).await;
+
+        let (Some(inner), Some(connection)) = (inner.upgrade(), connection.upgrade()) else {
+            return;
+        };
+        if connection.closed.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Err(e) = inner.echo_on(&connection).await {
+            tracing::warn!(
+                "Acquirer {} echo failed, reconnecting on next request: {}",
+                inner.name,
+                e
+            );
+            connection.close();
+            return;
+        }
+    }
+}
+
+/// 请求对应的应答MTI（功能位加1，如0200→0210）
+fn response_mti(mti: &str) -> String {
+    let mut digits: Vec<char> = mti.chars().collect();
+    if let Some(function) = digits.get_mut(2).and_then(|c| c.to_digit(10)) {
+        digits[2] = char::from_digit((function + 1) % 10, 10).unwrap_or('1');
+    }
+    digits.into_iter().collect()
+}
+
+fn match_key(mti: &str, stan: &str) -> String {
+    format!("{}:{}", mti, stan)
+}
+
+fn decode_hex_field(value: &str, length: Option<usize>, label: &str) -> Result<Vec<u8>, AppError> {
+    let bytes = hex::decode(value)
+        .map_err(|_| AppError::BadRequest(format!("{} must be hex encoded", label)))?;
+    if length.is_some_and(|length| bytes.len() != length) {
+        return Err(AppError::BadRequest(format!(
+            "{} must be {} bytes",
+            label,
+            length.unwrap_or_default()
+        )));
+    }
+    Ok(bytes)
+}
+
+/// 对账应答码：1987版00为平账、95为不平；1993版500为平账、501和502为不平
+fn settlement_outcome(code: Option<&str>) -> (SettlementOutcome, String) {
+    match code.map(str::trim) {
+        Some("00") | Some("500") => (SettlementOutcome::Settled, "00".to_string()),
+        Some("95") | Some("501") | Some("502") => {
+            (SettlementOutcome::TotalsMismatch, "95".to_string())
+        },
+        Some(code) => (SettlementOutcome::Rejected, code.to_string()),
+        None => (SettlementOutcome::Rejected, "96".to_string()),
+    }
+}
+
+fn currency_code(currency: &str) -> Result<String, AppError> {
+    currency_numeric(currency)
+        .ok_or_else(|| AppError::BadRequest(format!("Unsupported currency {}", currency)))
+}
+
+/// ISO 4217字母代码转数字代码，已是数字代码时原样返回
+pub(crate) fn currency_numeric(currency: &str) -> Option<String> {
+    if currency.len() == 3 && currency.bytes().all(|b| b.is_ascii_digit()) {
+        return Some(currency.to_string());
+    }
+    let code = match currency.to_ascii_uppercase().as_str() {
+        "USD" => "840",
+        "EUR" => "978",
+        "GBP" => "826",
+        "CNY" => "156",
+        "HKD" => "344",
+        "JPY" => "392",
+        "SGD" => "702",
+        "AUD" => "036",
+        "CAD" => "124",
+        _ => return None,
+    };
+    Some(code.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_mti_and_currency() {
+        assert_eq!(response_mti("0200"), "0210");
+        assert_eq!(response_mti("0800"), "0810");
+        assert_eq!(response_mti("1804"), "1814");
+        assert_eq!(currency_numeric("usd").as_deref(), Some("840"));
+        assert_eq!(currency_numeric("156").as_deref(), Some("156"));
+        assert!(currency_numeric("XYZ").is_none());
+    }
+
+    #[test]
+    fn test_settlement_outcome() {
+        assert_eq!(settlement_outcome(Some("00")).0, SettlementOutcome::Settled);
+        assert_eq!(settlement_outcome(Some("500")).0, SettlementOutcome::Settled);
+        assert_eq!(settlement_outcome(Some("95")).0, SettlementOutcome::TotalsMismatch);
+        assert_eq!(settlement_outcome(Some("502")).0, SettlementOutcome::TotalsMismatch);
+        assert_eq!(settlement_outcome(Some("05")), (SettlementOutcome::Rejected, "05".to_string()));
+        assert_eq!(settlement_outcome(None).0, SettlementOutcome::Rejected);
+    }
+}
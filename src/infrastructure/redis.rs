@@ -1,4 +1,5 @@
-use redis::{aio::ConnectionManager, AsyncCommands, Client, RedisError};
+use futures::stream::Stream;
+use redis::{aio::ConnectionManager, AsyncCommands, Client, Msg, RedisError};
 use std::time::Duration;
 
 /// Redis客户端配置
@@ -12,6 +13,7 @@ pub struct RedisConfig {
 /// Redis客户端封装
 #[derive(Clone)]
 pub struct RedisClient {
+    client: Client,
     manager: ConnectionManager,
 }
 
@@ -25,11 +27,11 @@ impl RedisClient {
         tracing::debug!("Redis URL: {}", mask_redis_url(&redis_url));
 
         let client = Client::open(redis_url.as_str())?;
-        let manager = ConnectionManager::new(client).await?;
+        let manager = ConnectionManager::new(client.clone()).await?;
 
         tracing::info!("Redis client initialized successfully");
 
-        Ok(Self { manager })
+        Ok(Self { client, manager })
     }
 
     /// 获取键的值
@@ -59,6 +61,24 @@ impl RedisClient {
         conn.set_ex(key, value, seconds).await
     }
 
+    /// 键不存在时设置键值并指定过期时间（秒），返回是否设置成功
+    pub async fn set_nx_ex<T>(&self, key: &str, value: T, seconds: u64) -> Result<bool, RedisError>
+    where
+        T: redis::ToRedisArgs + Send + Sync,
+    {
+        let mut conn = self.manager.clone();
+        let result: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("NX")
+            .arg("EX")
+            .arg(seconds.max(1))
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(result.is_some())
+    }
+
     /// 删除键
     pub async fn del(&self, key: &str) -> Result<(), RedisError> {
         let mut conn = self.manager.clone();
@@ -125,6 +145,24 @@ impl RedisClient {
         let mut conn = self.manager.clone();
         redis::cmd("INFO").query_async(&mut conn).await
     }
+
+    /// 向频道发布消息，返回接收到消息的订阅者数量
+    pub async fn publish(&self, channel: &str, message: &str) -> Result<i64, RedisError> {
+        let mut conn = self.manager.clone();
+        conn.publish(channel, message).await
+    }
+
+    /// 订阅频道，返回消息流
+    ///
+    /// 订阅独占一条新连接，连接断开时消息流结束，由调用方负责重新订阅
+    pub async fn subscribe(
+        &self,
+        channel: &str,
+    ) -> Result<impl Stream<Item = Msg> + Send + 'static, RedisError> {
+        let mut pubsub = self.client.get_async_connection().await?.into_pubsub();
+        pubsub.subscribe(channel).await?;
+        Ok(pubsub.into_on_message())
+    }
 }
 
 /// 构建 Redis 连接 URL
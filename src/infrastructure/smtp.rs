@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use crate::{infrastructure::config::EmailChannelConfig, security::crypto, utils::error::AppError};
+
+/// 邮件
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// 简单SMTP客户端
+///
+/// 实现发送纯文本邮件所需的最小命令集（EHLO、AUTH PLAIN、MAIL、RCPT、DATA），
+/// 不支持TLS，应连接本机或内网的邮件中继
+#[derive(Debug, Clone)]
+pub struct SmtpClient {
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+    from: String,
+    timeout: Duration,
+}
+
+impl SmtpClient {
+    /// 根据邮件渠道配置创建客户端
+    pub fn new(config: &EmailChannelConfig) -> Self {
+        let credentials = match (&config.username, &config.password) {
+            (Some(username), Some(password)) => Some((username.clone(), password.clone())),
+            _ => None,
+        };
+
+        Self {
+            host: config.host.clone(),
+            port: config.port,
+            credentials,
+            from: config.from.clone(),
+            timeout: Duration::from_secs(config.timeout_seconds),
+        }
+    }
+
+    /// 发送邮件
+    pub async fn send(&self, message: &EmailMessage) -> Result<(), AppError> {
+        if message.to.is_empty() {
+            return Ok(());
+        }
+
+        tokio::time::timeout(self.timeout, self.send_inner(message))
+            .await
+            .map_err(|_| AppError::InternalWithMessage("SMTP request timed out".to_string()))?
+    }
+
+    async fn send_inner(&self, message: &EmailMessage) -> Result<(), AppError> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| smtp_error(format!("Failed to connect: {}", e)))?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        expect_reply(&mut reader, 220).await?;
+        command(&mut reader, &mut writer, "EHLO softpos-backend", 250).await?;
+
+        if let Some((username, password)) = &self.credentials {
+            let token = crypto::base64_encode(format!("\0{}\0{}", username, password).as_bytes());
+            command(&mut reader, &mut writer, &format!("AUTH PLAIN {}", token), 235).await?;
+        }
+
+        command(&mut reader, &mut writer, &format!("MAIL FROM:<{}>", self.from), 250).await?;
+        for recipient in &message.to {
+            command(&mut reader, &mut writer, &format!("RCPT TO:<{}>", recipient), 250).await?;
+        }
+
+        command(&mut reader, &mut writer, "DATA", 354).await?;
+        write_line(&mut writer, &self.format_message(message)).await?;
+        command(&mut reader, &mut writer, ".", 250).await?;
+
+        // 邮件已被接受，QUIT失败不影响结果
+        let _ = command(&mut reader, &mut writer, "QUIT", 221).await;
+
+        Ok(())
+    }
+
+    /// 生成邮件内容：主题和正文均使用UTF-8 Base64编码，无需处理点号转义
+    fn format_message(&self, message: &EmailMessage) -> String {
+        let body = crypto::base64_encode(message.body.as_bytes());
+        let body_lines: Vec<&str> = body
+            .as_bytes()
+            .chunks(76)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+            .collect();
+
+        [
+            format!("From: <{}>", self.from),
+            format!(
+                "To: {}",
+                message.to.iter().map(|r| format!("<{}>", r)).collect::<Vec<_>>().join(", ")
+            ),
+            format!("Subject: =?UTF-8?B?{}?=", crypto::base64_encode(message.subject.as_bytes())),
+            format!("Date: {}", chrono::Utc::now().to_rfc2822()),
+            format!("Message-ID: <{}@softpos-backend>", uuid::Uuid::new_v4()),
+            "MIME-Version: 1.0".to_string(),
+            "Content-Type: text/plain; charset=utf-8".to_string(),
+            "Content-Transfer-Encoding: base64".to_string(),
+            String::new(),
+            body_lines.join("\r\n"),
+        ]
+        .join("\r\n")
+    }
+}
+
+fn smtp_error(message: String) -> AppError {
+    AppError::InternalWithMessage(format!("SMTP error: {}", message))
+}
+
+async fn write_line(writer: &mut (impl AsyncWriteExt + Unpin), line: &str) -> Result<(), AppError> {
+    writer
+        .write_all(format!("{}\r\n", line).as_bytes())
+        .await
+        .map_err(|e| smtp_error(format!("Failed to write: {}", e)))
+}
+
+/// 发送命令并校验响应码
+async fn command(
+    reader: &mut (impl AsyncBufReadExt + Unpin),
+    writer: &mut (impl AsyncWriteExt + Unpin),
+    line: &str,
+    expected: u16,
+) -> Result<(), AppError> {
+    write_line(writer, line).await?;
+    expect_reply(reader, expected).await
+}
+
+/// 读取一个（可能多行的）响应并校验响应码
+async fn expect_reply(
+    reader: &mut (impl AsyncBufReadExt + Unpin),
+    expected: u16,
+) -> Result<(), AppError> {
+    loop {
+        let mut line = String::new();
+        let read = reader
+            .read_line(&mut line)
+            .await
+            .map_err(|e| smtp_error(format!("Failed to read: {}", e)))?;
+        if read == 0 {
+            return Err(smtp_error("Connection closed".to_string()));
+        }
+
+        let code: u16 = line
+            .get(..3)
+            .and_then(|c| c.parse().ok())
+            .ok_or_else(|| smtp_error(format!("Invalid reply: {}", line.trim_end())))?;
+
+        // "250-..." 为多行响应的中间行
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+
+        if code != expected {
+            return Err(smtp_error(format!("Unexpected reply: {}", line.trim_end())));
+        }
+
+        return Ok(());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use sunbay_softpos_backend::{
@@ -21,6 +21,47 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app_state = Arc::new(AppState::new(config.clone()).await?);
     tracing::info!("Application state initialized with all services");
 
+    // 启动设备指令超时检查
+    app_state.device_command_service.clone().start_timeout_monitor(Duration::from_secs(30));
+
+    // 启动预授权过期检查
+    app_state.transaction_service.clone().start_preauth_expiry_worker(Duration::from_secs(60));
+
+    // 启动结果未知交易的冲正
+    app_state.transaction_service.clone().start_reversal_worker(Duration::from_secs(
+        config.payment.reversal_poll_interval_seconds,
+    ));
+
+    // 启动日切关批和结算提交
+    app_state.settlement_service.clone().start_settlement_worker(Duration::from_secs(
+        config.settlement.check_interval_seconds,
+    ));
+
+    // 启动商户Webhook投递
+    app_state
+        .webhook_service
+        .clone()
+        .start_delivery_worker(Duration::from_secs(config.webhook.poll_interval_seconds));
+
+    // 启动领域事件发件箱分发
+    app_state
+        .outbox_dispatcher
+        .clone()
+        .start_dispatch_worker(Duration::from_millis(config.outbox.poll_interval_ms));
+
+    // 启动过期幂等键清理
+    app_state.idempotency_service.clone().start_cleanup_worker(Duration::from_secs(300));
+
+    // 启动已过期交易令牌使用记录清理
+    app_state.transaction_token_service.clone().start_cleanup_worker(Duration::from_secs(300));
+
+    // 启动未确认告警升级检查
+    if let Some(alert_dispatcher) = &app_state.alert_dispatcher {
+        alert_dispatcher.clone().start_escalation_worker(Duration::from_secs(
+            config.alerting.escalation_check_interval_seconds,
+        ));
+    }
+
     // 使用完整的路由定义（来自 routes.rs）
     let app = create_router(app_state);
 
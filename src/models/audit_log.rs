@@ -17,6 +17,7 @@ pub enum OperationResult {
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct AuditLog {
     pub id: String,
+    pub tenant_id: String,
     pub operation: String,
     pub operator: String,
     pub device_id: Option<String>,
@@ -37,6 +38,7 @@ impl AuditLog {
         let now = chrono::Utc::now().to_rfc3339();
         Self {
             id: uuid::Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
             operation,
             operator,
             device_id: None,
@@ -48,6 +50,12 @@ impl AuditLog {
         }
     }
 
+    /// 设置所属租户
+    pub fn with_tenant_id(mut self, tenant_id: String) -> Self {
+        self.tenant_id = tenant_id;
+        self
+    }
+
     /// 设置设备ID
     pub fn with_device_id(mut self, device_id: String) -> Self {
         self.device_id = Some(device_id);
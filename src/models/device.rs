@@ -7,6 +7,8 @@ use uuid::Uuid;
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Device {
     pub id: String,
+    /// 所属租户
+    pub tenant_id: String,
     pub imei: String,
     pub model: String,
     pub os_version: String,
@@ -16,6 +18,10 @@ pub struct Device {
     pub status: String,
     pub merchant_id: Option<String>,
     pub merchant_name: Option<String>,
+    /// 所属门店ID
+    pub store_id: Option<String>,
+    /// 终端号（TID），商户内唯一
+    pub terminal_id: Option<String>,
     pub security_score: i32,
     pub current_ksn: String,
     pub ipek_injected_at: Option<String>,
@@ -42,6 +48,7 @@ impl Device {
         let now = Utc::now().to_rfc3339();
         Self {
             id: Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
             imei,
             model,
             os_version,
@@ -51,6 +58,8 @@ impl Device {
             status: DeviceStatus::Pending.as_str().to_string(),
             merchant_id: None,
             merchant_name: None,
+            store_id: None,
+            terminal_id: None,
             security_score: 100,
             current_ksn: String::new(),
             ipek_injected_at: None,
@@ -133,6 +142,10 @@ pub enum DeviceStatus {
     Suspended,
     Revoked,
     Rejected,
+    /// 已退役（设备回收、报废）
+    Decommissioned,
+    /// 已被其他设备替换
+    Replaced,
 }
 
 impl DeviceStatus {
@@ -143,6 +156,8 @@ impl DeviceStatus {
             DeviceStatus::Suspended => "SUSPENDED",
             DeviceStatus::Revoked => "REVOKED",
             DeviceStatus::Rejected => "REJECTED",
+            DeviceStatus::Decommissioned => "DECOMMISSIONED",
+            DeviceStatus::Replaced => "REPLACED",
         }
     }
 
@@ -153,6 +168,8 @@ impl DeviceStatus {
             "SUSPENDED" => Some(DeviceStatus::Suspended),
             "REVOKED" => Some(DeviceStatus::Revoked),
             "REJECTED" => Some(DeviceStatus::Rejected),
+            "DECOMMISSIONED" => Some(DeviceStatus::Decommissioned),
+            "REPLACED" => Some(DeviceStatus::Replaced),
             _ => None,
         }
     }
@@ -164,22 +181,72 @@ impl DeviceStatus {
             (DeviceStatus::Pending, DeviceStatus::Active) => true,
             (DeviceStatus::Pending, DeviceStatus::Rejected) => true,
 
-            // ACTIVE可以转换到SUSPENDED或REVOKED
+            // ACTIVE和SUSPENDED可以互相转换，也可以被吊销、退役或替换
             (DeviceStatus::Active, DeviceStatus::Suspended) => true,
-            (DeviceStatus::Active, DeviceStatus::Revoked) => true,
-
-            // SUSPENDED可以转换到ACTIVE或REVOKED
             (DeviceStatus::Suspended, DeviceStatus::Active) => true,
-            (DeviceStatus::Suspended, DeviceStatus::Revoked) => true,
+            (
+                DeviceStatus::Active | DeviceStatus::Suspended,
+                DeviceStatus::Revoked | DeviceStatus::Decommissioned | DeviceStatus::Replaced,
+            ) => true,
+
+            // REVOKED和REJECTED只能退役
+            (DeviceStatus::Revoked | DeviceStatus::Rejected, DeviceStatus::Decommissioned) => true,
 
-            // REVOKED和REJECTED是终态，不能转换
-            (DeviceStatus::Revoked, _) => false,
-            (DeviceStatus::Rejected, _) => false,
+            // DECOMMISSIONED和REPLACED是终态，不能转换
+            (DeviceStatus::Decommissioned, _) => false,
+            (DeviceStatus::Replaced, _) => false,
 
             // 其他转换无效
             _ => false,
         }
     }
+
+    /// 进入该状态时是否需要清零设备密钥
+    pub fn requires_key_zeroization(&self) -> bool {
+        matches!(
+            self,
+            DeviceStatus::Revoked | DeviceStatus::Decommissioned | DeviceStatus::Replaced
+        )
+    }
+}
+
+impl std::fmt::Display for DeviceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 设备状态变更历史
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceStatusHistory {
+    pub id: String,
+    pub device_id: String,
+    /// 变更前状态，设备注册时为空
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub changed_by: String,
+    pub reason: Option<String>,
+    pub created_at: String,
+}
+
+impl DeviceStatusHistory {
+    pub fn new(
+        device_id: String,
+        from_status: Option<DeviceStatus>,
+        to_status: DeviceStatus,
+        changed_by: String,
+        reason: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            device_id,
+            from_status: from_status.map(|s| s.as_str().to_string()),
+            to_status: to_status.as_str().to_string(),
+            changed_by,
+            reason,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
 }
 
 impl Default for DeviceStatus {
@@ -250,6 +317,22 @@ mod tests {
         // REJECTED is terminal
         assert!(!DeviceStatus::Rejected.can_transition_to(DeviceStatus::Active));
         assert!(!DeviceStatus::Rejected.can_transition_to(DeviceStatus::Pending));
+
+        // Decommission and replacement
+        assert!(DeviceStatus::Active.can_transition_to(DeviceStatus::Replaced));
+        assert!(DeviceStatus::Suspended.can_transition_to(DeviceStatus::Decommissioned));
+        assert!(DeviceStatus::Revoked.can_transition_to(DeviceStatus::Decommissioned));
+        assert!(!DeviceStatus::Pending.can_transition_to(DeviceStatus::Replaced));
+        assert!(!DeviceStatus::Decommissioned.can_transition_to(DeviceStatus::Active));
+        assert!(!DeviceStatus::Replaced.can_transition_to(DeviceStatus::Decommissioned));
+    }
+
+    #[test]
+    fn test_key_zeroization_states() {
+        assert!(DeviceStatus::Revoked.requires_key_zeroization());
+        assert!(DeviceStatus::Decommissioned.requires_key_zeroization());
+        assert!(DeviceStatus::Replaced.requires_key_zeroization());
+        assert!(!DeviceStatus::Suspended.requires_key_zeroization());
     }
 
     #[test]
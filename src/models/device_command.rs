@@ -0,0 +1,246 @@
+use std::str::FromStr;
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// 默认单次投递超时（秒）
+pub const DEFAULT_COMMAND_TIMEOUT_SECONDS: i64 = 300;
+
+/// 默认最大投递次数
+pub const DEFAULT_COMMAND_MAX_ATTEMPTS: i64 = 3;
+
+/// 设备远程指令
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceCommand {
+    pub id: String,
+    /// 所属租户
+    pub tenant_id: String,
+    pub device_id: String,
+    pub command_type: String,
+    /// 指令参数（JSON）
+    pub payload: Option<String>,
+    pub status: String,
+    /// 已投递次数
+    pub attempts: i64,
+    pub max_attempts: i64,
+    /// 单次投递后等待设备回执的超时时间（秒）
+    pub timeout_seconds: i64,
+    /// 设备上报的执行结果（JSON）
+    pub result: Option<String>,
+    pub error: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+    pub acknowledged_at: Option<String>,
+    pub completed_at: Option<String>,
+    /// 当前投递的回执截止时间
+    pub deadline_at: Option<String>,
+}
+
+impl DeviceCommand {
+    pub fn new(
+        device_id: String,
+        command_type: CommandType,
+        payload: Option<String>,
+        timeout_seconds: i64,
+        max_attempts: i64,
+        created_by: String,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
+            device_id,
+            command_type: command_type.as_str().to_string(),
+            payload,
+            status: CommandStatus::Pending.as_str().to_string(),
+            attempts: 0,
+            max_attempts,
+            timeout_seconds,
+            result: None,
+            error: None,
+            created_by,
+            created_at: Utc::now().to_rfc3339(),
+            delivered_at: None,
+            acknowledged_at: None,
+            completed_at: None,
+            deadline_at: None,
+        }
+    }
+
+    /// 指令当前状态
+    pub fn current_status(&self) -> Option<CommandStatus> {
+        self.status.parse().ok()
+    }
+
+    /// 本次投递的回执截止时间
+    pub fn next_deadline(&self) -> String {
+        (Utc::now() + Duration::seconds(self.timeout_seconds)).to_rfc3339()
+    }
+
+    /// 下发给设备的消息体
+    pub fn to_message(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "command",
+            "command_id": self.id,
+            "command_type": self.command_type,
+            "payload": self
+                .payload
+                .as_deref()
+                .and_then(|p| serde_json::from_str::<serde_json::Value>(p).ok()),
+            "attempt": self.attempts,
+            "deadline_at": self.deadline_at,
+        })
+    }
+}
+
+/// 指令类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CommandType {
+    /// 锁定设备，禁止发起交易
+    Lock,
+    /// 清除设备上的密钥材料
+    ZeroizeKeys,
+    /// 立即执行健康检查
+    ForceHealthCheck,
+    /// 上传日志
+    UploadLogs,
+    /// 更新SDK配置
+    UpdateConfig,
+    /// 重启SDK
+    RebootSdk,
+}
+
+impl CommandType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandType::Lock => "LOCK",
+            CommandType::ZeroizeKeys => "ZEROIZE_KEYS",
+            CommandType::ForceHealthCheck => "FORCE_HEALTH_CHECK",
+            CommandType::UploadLogs => "UPLOAD_LOGS",
+            CommandType::UpdateConfig => "UPDATE_CONFIG",
+            CommandType::RebootSdk => "REBOOT_SDK",
+        }
+    }
+}
+
+impl FromStr for CommandType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "LOCK" => Ok(CommandType::Lock),
+            "ZEROIZE_KEYS" => Ok(CommandType::ZeroizeKeys),
+            "FORCE_HEALTH_CHECK" => Ok(CommandType::ForceHealthCheck),
+            "UPLOAD_LOGS" => Ok(CommandType::UploadLogs),
+            "UPDATE_CONFIG" => Ok(CommandType::UpdateConfig),
+            "REBOOT_SDK" => Ok(CommandType::RebootSdk),
+            _ => Err(format!("Unknown command type: {}", s)),
+        }
+    }
+}
+
+/// 指令状态
+///
+/// PENDING → DELIVERED → ACKNOWLEDGED → SUCCEEDED / FAILED；
+/// 投递后超时未完成时重新进入PENDING，超过最大投递次数后进入TIMED_OUT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CommandStatus {
+    Pending,
+    Delivered,
+    Acknowledged,
+    Succeeded,
+    Failed,
+    TimedOut,
+    Cancelled,
+}
+
+impl CommandStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandStatus::Pending => "PENDING",
+            CommandStatus::Delivered => "DELIVERED",
+            CommandStatus::Acknowledged => "ACKNOWLEDGED",
+            CommandStatus::Succeeded => "SUCCEEDED",
+            CommandStatus::Failed => "FAILED",
+            CommandStatus::TimedOut => "TIMED_OUT",
+            CommandStatus::Cancelled => "CANCELLED",
+        }
+    }
+
+    /// 是否为终态
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            CommandStatus::Succeeded
+                | CommandStatus::Failed
+                | CommandStatus::TimedOut
+                | CommandStatus::Cancelled
+        )
+    }
+}
+
+impl FromStr for CommandStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "PENDING" => Ok(CommandStatus::Pending),
+            "DELIVERED" => Ok(CommandStatus::Delivered),
+            "ACKNOWLEDGED" => Ok(CommandStatus::Acknowledged),
+            "SUCCEEDED" => Ok(CommandStatus::Succeeded),
+            "FAILED" => Ok(CommandStatus::Failed),
+            "TIMED_OUT" => Ok(CommandStatus::TimedOut),
+            "CANCELLED" => Ok(CommandStatus::Cancelled),
+            _ => Err(format!("Unknown command status: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_type_roundtrip() {
+        for t in [
+            CommandType::Lock,
+            CommandType::ZeroizeKeys,
+            CommandType::ForceHealthCheck,
+            CommandType::UploadLogs,
+            CommandType::UpdateConfig,
+            CommandType::RebootSdk,
+        ] {
+            assert_eq!(CommandType::from_str(t.as_str()).ok(), Some(t));
+        }
+        assert_eq!(CommandType::from_str("WIPE").ok(), None);
+    }
+
+    #[test]
+    fn test_command_message() {
+        let command = DeviceCommand::new(
+            "device-1".to_string(),
+            CommandType::UpdateConfig,
+            Some(r#"{"log_level":"debug"}"#.to_string()),
+            DEFAULT_COMMAND_TIMEOUT_SECONDS,
+            DEFAULT_COMMAND_MAX_ATTEMPTS,
+            "admin".to_string(),
+        );
+        assert_eq!(command.current_status(), Some(CommandStatus::Pending));
+
+        let message = command.to_message();
+        assert_eq!(message["type"], "command");
+        assert_eq!(message["command_type"], "UPDATE_CONFIG");
+        assert_eq!(message["payload"]["log_level"], "debug");
+    }
+
+    #[test]
+    fn test_terminal_status() {
+        assert!(CommandStatus::Succeeded.is_terminal());
+        assert!(CommandStatus::Cancelled.is_terminal());
+        assert!(!CommandStatus::Acknowledged.is_terminal());
+    }
+}
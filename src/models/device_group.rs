@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::Device;
+
+/// 标签最大长度
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// 设备分组
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceGroup {
+    pub id: String,
+    /// 所属租户
+    pub tenant_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// 分组类型（STATIC / DYNAMIC）
+    pub group_type: String,
+    /// 动态分组规则（JSON）
+    pub rules: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl DeviceGroup {
+    pub fn new(
+        name: String,
+        description: Option<String>,
+        group_type: GroupType,
+        rules: Option<&GroupRules>,
+        created_by: String,
+    ) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
+            name,
+            description,
+            group_type: group_type.as_str().to_string(),
+            rules: rules.and_then(|r| serde_json::to_string(r).ok()),
+            created_by,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    /// 分组类型
+    pub fn kind(&self) -> GroupType {
+        GroupType::from_str(&self.group_type).unwrap_or(GroupType::Static)
+    }
+
+    /// 解析动态分组规则
+    pub fn parsed_rules(&self) -> Option<GroupRules> {
+        self.rules.as_deref().and_then(|r| serde_json::from_str(r).ok())
+    }
+}
+
+/// 分组类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GroupType {
+    /// 静态分组：显式维护成员列表
+    Static,
+    /// 动态分组：按规则实时匹配设备
+    Dynamic,
+}
+
+impl GroupType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupType::Static => "STATIC",
+            GroupType::Dynamic => "DYNAMIC",
+        }
+    }
+}
+
+impl FromStr for GroupType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "STATIC" => Ok(GroupType::Static),
+            "DYNAMIC" => Ok(GroupType::Dynamic),
+            _ => Err(format!("Unknown group type: {}", s)),
+        }
+    }
+}
+
+/// 动态分组规则
+///
+/// 各条件之间为"且"关系，同一条件的多个取值之间为"或"关系；未设置的条件不参与匹配。
+/// `tags` 要求设备同时具备所有标签。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GroupRules {
+    /// 设备型号（不区分大小写）
+    pub models: Vec<String>,
+    /// OS版本，支持按版本段前缀匹配（如 "12" 匹配 "12.0.1"）
+    pub os_versions: Vec<String>,
+    pub merchant_ids: Vec<String>,
+    /// TEE类型（QTEE / TRUSTZONE）
+    pub tee_types: Vec<String>,
+    /// 设备模式（FULL_POS / PINPAD）
+    pub device_modes: Vec<String>,
+    /// 设备状态
+    pub statuses: Vec<String>,
+    pub min_security_score: Option<i32>,
+    pub max_security_score: Option<i32>,
+    pub tags: Vec<String>,
+}
+
+impl GroupRules {
+    /// 是否未设置任何条件
+    pub fn is_empty(&self) -> bool {
+        *self == GroupRules::default()
+    }
+
+    /// 验证规则
+    pub fn validate(&self) -> Result<(), String> {
+        if self.is_empty() {
+            return Err("Dynamic group rules cannot be empty".to_string());
+        }
+
+        if self.tee_types.iter().any(|t| crate::models::TeeType::from_str(t).is_none()) {
+            return Err("Invalid tee_type in rules".to_string());
+        }
+
+        if self
+            .device_modes
+            .iter()
+            .any(|m| crate::models::DeviceMode::from_str(m).is_none())
+        {
+            return Err("Invalid device_mode in rules".to_string());
+        }
+
+        if self.statuses.iter().any(|s| crate::models::DeviceStatus::from_str(s).is_none()) {
+            return Err("Invalid status in rules".to_string());
+        }
+
+        for score in [self.min_security_score, self.max_security_score].into_iter().flatten() {
+            if !(0..=100).contains(&score) {
+                return Err("Security score must be between 0 and 100".to_string());
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.min_security_score, self.max_security_score) {
+            if min > max {
+                return Err("min_security_score cannot exceed max_security_score".to_string());
+            }
+        }
+
+        for tag in &self.tags {
+            normalize_tag(tag)?;
+        }
+
+        Ok(())
+    }
+
+    /// 判断设备是否匹配规则
+    pub fn matches(&self, device: &Device, tags: &HashSet<String>) -> bool {
+        (self.models.is_empty()
+            || self.models.iter().any(|m| m.eq_ignore_ascii_case(&device.model)))
+            && (self.os_versions.is_empty()
+                || self.os_versions.iter().any(|v| os_version_matches(v, &device.os_version)))
+            && (self.merchant_ids.is_empty()
+                || device.merchant_id.as_ref().is_some_and(|id| self.merchant_ids.contains(id)))
+            && (self.tee_types.is_empty() || self.tee_types.contains(&device.tee_type))
+            && (self.device_modes.is_empty() || self.device_modes.contains(&device.device_mode))
+            && (self.statuses.is_empty() || self.statuses.contains(&device.status))
+            && self.min_security_score.is_none_or(|min| device.security_score >= min)
+            && self.max_security_score.is_none_or(|max| device.security_score <= max)
+            && self.tags.iter().all(|t| normalize_tag(t).is_ok_and(|t| tags.contains(&t)))
+    }
+}
+
+/// 规范化标签：去除首尾空白并转为小写，仅允许字母、数字和 `-_.:`
+pub fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().to_ascii_lowercase();
+
+    if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+        return Err(format!("Tag must be 1-{} characters", MAX_TAG_LENGTH));
+    }
+
+    if !tag
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+    {
+        return Err(format!("Invalid tag: {}", tag));
+    }
+
+    Ok(tag)
+}
+
+/// 按版本段前缀匹配OS版本
+fn os_version_matches(pattern: &str, version: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim().split('.').collect();
+    let version: Vec<&str> = version.trim().split('.').collect();
+
+    pattern.len() <= version.len() && pattern.iter().zip(&version).all(|(p, v)| p == v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DeviceMode, TeeType};
+
+    fn device() -> Device {
+        let mut device = Device::new(
+            "123456789012345".to_string(),
+            "V2PRO".to_string(),
+            "12.0.1".to_string(),
+            TeeType::TrustZone,
+            vec![],
+            DeviceMode::FullPos,
+            true,
+        );
+        device.security_score = 80;
+        device.merchant_id = Some("m1".to_string());
+        device
+    }
+
+    #[test]
+    fn test_rules_match() {
+        let tags: HashSet<String> = ["pilot".to_string(), "north".to_string()].into();
+        let rules = GroupRules {
+            models: vec!["v2pro".to_string(), "P2".to_string()],
+            os_versions: vec!["12".to_string()],
+            merchant_ids: vec!["m1".to_string()],
+            tee_types: vec!["TRUSTZONE".to_string()],
+            min_security_score: Some(70),
+            tags: vec!["Pilot".to_string()],
+            ..Default::default()
+        };
+        assert!(rules.matches(&device(), &tags));
+
+        let rules = GroupRules { tags: vec!["south".to_string()], ..rules };
+        assert!(!rules.matches(&device(), &tags));
+
+        let rules = GroupRules { max_security_score: Some(60), ..Default::default() };
+        assert!(!rules.matches(&device(), &tags));
+    }
+
+    #[test]
+    fn test_os_version_prefix() {
+        assert!(os_version_matches("12", "12.0.1"));
+        assert!(os_version_matches("12.0.1", "12.0.1"));
+        assert!(!os_version_matches("12.1", "12.0.1"));
+        assert!(!os_version_matches("1", "12.0"));
+        assert!(!os_version_matches("12.0.1.2", "12.0.1"));
+    }
+
+    #[test]
+    fn test_rules_validate() {
+        assert!(GroupRules::default().validate().is_err());
+        assert!(GroupRules { tee_types: vec!["SGX".to_string()], ..Default::default() }
+            .validate()
+            .is_err());
+        assert!(GroupRules {
+            min_security_score: Some(80),
+            max_security_score: Some(50),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(GroupRules { statuses: vec!["ACTIVE".to_string()], ..Default::default() }
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("  Region:North ").unwrap(), "region:north");
+        assert!(normalize_tag("").is_err());
+        assert!(normalize_tag("has space").is_err());
+        assert!(normalize_tag(&"a".repeat(MAX_TAG_LENGTH + 1)).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use std::str::FromStr;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::security::crypto;
+
+/// 设备批量导入任务
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceImportJob {
+    pub id: String,
+    /// 所属租户
+    pub tenant_id: String,
+    pub status: String,
+    /// 导入文件格式（CSV / JSON）
+    pub format: String,
+    pub total_rows: i64,
+    pub success_rows: i64,
+    pub failed_rows: i64,
+    /// 逐行错误报告（JSON数组）
+    pub errors: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+impl DeviceImportJob {
+    pub fn new(format: ImportFormat, created_by: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
+            status: ImportJobStatus::Pending.as_str().to_string(),
+            format: format.as_str().to_string(),
+            total_rows: 0,
+            success_rows: 0,
+            failed_rows: 0,
+            errors: None,
+            created_by,
+            created_at: Utc::now().to_rfc3339(),
+            started_at: None,
+            completed_at: None,
+        }
+    }
+
+    /// 解析逐行错误报告
+    pub fn row_errors(&self) -> Vec<ImportRowError> {
+        self.errors
+            .as_deref()
+            .and_then(|e| serde_json::from_str(e).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// 导入任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImportJobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl ImportJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportJobStatus::Pending => "PENDING",
+            ImportJobStatus::Running => "RUNNING",
+            ImportJobStatus::Completed => "COMPLETED",
+            ImportJobStatus::Failed => "FAILED",
+        }
+    }
+}
+
+impl FromStr for ImportJobStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "PENDING" => Ok(ImportJobStatus::Pending),
+            "RUNNING" => Ok(ImportJobStatus::Running),
+            "COMPLETED" => Ok(ImportJobStatus::Completed),
+            "FAILED" => Ok(ImportJobStatus::Failed),
+            _ => Err(format!("Unknown import job status: {}", s)),
+        }
+    }
+}
+
+/// 导入文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImportFormat {
+    Csv,
+    Json,
+}
+
+impl ImportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv => "CSV",
+            ImportFormat::Json => "JSON",
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "CSV" => Ok(ImportFormat::Csv),
+            "JSON" => Ok(ImportFormat::Json),
+            _ => Err(format!("Unknown import format: {}", s)),
+        }
+    }
+}
+
+/// 导入行错误
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportRowError {
+    /// 行号（从1开始，不含CSV表头）
+    pub row: usize,
+    pub imei: Option<String>,
+    pub error: String,
+}
+
+/// 设备预审批
+///
+/// 批量导入生成的设备槽位，匹配IMEI的设备注册时自动激活并分配门店
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DevicePreapproval {
+    pub id: String,
+    /// 所属租户
+    pub tenant_id: String,
+    pub job_id: String,
+    pub imei: String,
+    pub model: String,
+    pub store_id: Option<String>,
+    pub status: String,
+    /// 匹配注册的设备ID
+    pub device_id: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+    pub consumed_at: Option<String>,
+    /// 一次性注册码的SHA-256（十六进制），未设置时不自动激活
+    #[serde(skip_serializing)]
+    pub enrollment_code_hash: Option<String>,
+}
+
+impl DevicePreapproval {
+    pub fn new(
+        job_id: String,
+        imei: String,
+        model: String,
+        store_id: Option<String>,
+        enrollment_code: &str,
+        created_by: String,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
+            job_id,
+            imei,
+            model,
+            store_id,
+            status: PreapprovalStatus::Pending.as_str().to_string(),
+            device_id: None,
+            created_by,
+            created_at: Utc::now().to_rfc3339(),
+            consumed_at: None,
+            enrollment_code_hash: Some(crypto::sha256_hash_hex(enrollment_code.as_bytes())),
+        }
+    }
+
+    /// 注册码是否与预审批匹配
+    pub fn enrollment_code_matches(&self, code: Option<&str>) -> bool {
+        match (&self.enrollment_code_hash, code) {
+            (Some(hash), Some(code)) => *hash == crypto::sha256_hash_hex(code.as_bytes()),
+            _ => false,
+        }
+    }
+}
+
+/// 预审批状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PreapprovalStatus {
+    /// 等待设备注册
+    Pending,
+    /// 已被注册设备使用
+    Consumed,
+}
+
+impl PreapprovalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreapprovalStatus::Pending => "PENDING",
+            PreapprovalStatus::Consumed => "CONSUMED",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_job_defaults() {
+        let job = DeviceImportJob::new(ImportFormat::Csv, "admin".to_string());
+        assert_eq!(job.status, "PENDING");
+        assert_eq!(job.format, "CSV");
+        assert!(job.row_errors().is_empty());
+    }
+
+    #[test]
+    fn test_row_errors_roundtrip() {
+        let mut job = DeviceImportJob::new(ImportFormat::Json, "admin".to_string());
+        let errors = vec![ImportRowError {
+            row: 2,
+            imei: Some("123".to_string()),
+            error: "IMEI must be 15 digits".to_string(),
+        }];
+        job.errors = Some(serde_json::to_string(&errors).unwrap());
+        assert_eq!(job.row_errors(), errors);
+    }
+}
@@ -0,0 +1,359 @@
+use std::fmt;
+
+use crate::utils::tlv::{self, Tlv};
+
+/// 应用密文（ARQC/TC/AAC）
+pub const TAG_APPLICATION_CRYPTOGRAM: u32 = 0x9F26;
+/// 密文信息数据
+pub const TAG_CRYPTOGRAM_INFORMATION_DATA: u32 = 0x9F27;
+/// 发卡行应用数据
+pub const TAG_ISSUER_APPLICATION_DATA: u32 = 0x9F10;
+/// 不可预知数
+pub const TAG_UNPREDICTABLE_NUMBER: u32 = 0x9F37;
+/// 应用交易计数器（ATC）
+pub const TAG_APPLICATION_TRANSACTION_COUNTER: u32 = 0x9F36;
+/// 终端验证结果（TVR）
+pub const TAG_TERMINAL_VERIFICATION_RESULTS: u32 = 0x95;
+/// 交易日期（YYMMDD）
+pub const TAG_TRANSACTION_DATE: u32 = 0x9A;
+/// 交易类型
+pub const TAG_TRANSACTION_TYPE: u32 = 0x9C;
+/// 交易货币代码（ISO 4217数字代码）
+pub const TAG_TRANSACTION_CURRENCY_CODE: u32 = 0x5F2A;
+/// 应用交互特征（AIP）
+pub const TAG_APPLICATION_INTERCHANGE_PROFILE: u32 = 0x82;
+/// 专用文件名（AID）
+pub const TAG_DEDICATED_FILE_NAME: u32 = 0x84;
+/// 授权金额
+pub const TAG_AMOUNT_AUTHORISED: u32 = 0x9F02;
+
+/// 交易必须携带的关键标签及其允许的长度（字节）
+const REQUIRED_TAGS: [(u32, usize, usize); 11] = [
+    (TAG_APPLICATION_CRYPTOGRAM, 8, 8),
+    (TAG_CRYPTOGRAM_INFORMATION_DATA, 1, 1),
+    (TAG_ISSUER_APPLICATION_DATA, 1, 32),
+    (TAG_UNPREDICTABLE_NUMBER, 4, 4),
+    (TAG_APPLICATION_TRANSACTION_COUNTER, 2, 2),
+    (TAG_TERMINAL_VERIFICATION_RESULTS, 5, 5),
+    (TAG_TRANSACTION_DATE, 3, 3),
+    (TAG_TRANSACTION_TYPE, 1, 1),
+    (TAG_TRANSACTION_CURRENCY_CODE, 2, 2),
+    (TAG_APPLICATION_INTERCHANGE_PROFILE, 2, 2),
+    (TAG_DEDICATED_FILE_NAME, 5, 16),
+];
+
+/// 随交易保存并上送处理器的标签，其余标签丢弃
+const STORED_TAGS: [u32; 20] = [
+    TAG_APPLICATION_CRYPTOGRAM,
+    TAG_CRYPTOGRAM_INFORMATION_DATA,
+    TAG_ISSUER_APPLICATION_DATA,
+    TAG_UNPREDICTABLE_NUMBER,
+    TAG_APPLICATION_TRANSACTION_COUNTER,
+    TAG_TERMINAL_VERIFICATION_RESULTS,
+    TAG_TRANSACTION_DATE,
+    TAG_TRANSACTION_TYPE,
+    TAG_TRANSACTION_CURRENCY_CODE,
+    TAG_APPLICATION_INTERCHANGE_PROFILE,
+    TAG_DEDICATED_FILE_NAME,
+    TAG_AMOUNT_AUTHORISED,
+    0x9F03, // 其他金额
+    0x9F09, // 终端应用版本号
+    0x9F1A, // 终端国家代码
+    0x9F33, // 终端性能
+    0x9F34, // 持卡人验证方法结果
+    0x9F35, // 终端类型
+    0x9F66, // 终端交易属性（非接）
+    0x9F6E, // 卡片形态因子（非接）
+];
+
+/// 敏感标签：二磁道等效数据、主账号、持卡人姓名、卡片有效期和非接二磁道数据
+///
+/// 不保存、不上送，日志中只保留标签和长度
+pub const SENSITIVE_TAGS: [u32; 5] = [0x57, 0x5A, 0x5F20, 0x5F24, 0x9F6B];
+
+/// EMV数据的最大长度（字节）
+pub const MAX_EMV_DATA_LENGTH: usize = 512;
+
+/// 结构化模板的最大嵌套层数，EMV数据实际只有2到3层
+const MAX_TEMPLATE_DEPTH: usize = 3;
+
+/// 卡片生成的密文类型（9F27高两位）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptogramType {
+    /// 卡片拒绝
+    Aac,
+    /// 脱机批准
+    Tc,
+    /// 请求联机授权
+    Arqc,
+}
+
+/// 非接/芯片交易的EMV数据（BER-TLV）
+///
+/// 结构化模板（如77、70）展开为其中的基本数据对象；`Debug` 输出已脱敏
+#[derive(Clone)]
+pub struct EmvData {
+    objects: Vec<Tlv>,
+}
+
+impl EmvData {
+    /// 解析十六进制BER-TLV数据并校验关键标签
+    pub fn from_hex(data: &str) -> Result<Self, String> {
+        let bytes =
+            hex::decode(data.trim()).map_err(|_| "EMV data must be a hex string".to_string())?;
+        if bytes.is_empty() {
+            return Err("EMV data cannot be empty".to_string());
+        }
+        if bytes.len() > MAX_EMV_DATA_LENGTH {
+            return Err(format!("EMV data cannot exceed {} bytes", MAX_EMV_DATA_LENGTH));
+        }
+
+        let mut objects = Vec::new();
+        flatten(
+            tlv::parse(&bytes).map_err(|e| format!("Invalid EMV data: {}", e))?,
+            &mut objects,
+            1,
+        )?;
+
+        for (index, object) in objects.iter().enumerate() {
+            if objects[..index].iter().any(|o| o.tag == object.tag) {
+                return Err(format!("Duplicate EMV tag {:X}", object.tag));
+            }
+        }
+
+        let emv = Self { objects };
+        emv.validate()?;
+
+        Ok(emv)
+    }
+
+    /// 标签的值
+    pub fn value(&self, tag: u32) -> Option<&[u8]> {
+        self.objects.iter().find(|o| o.tag == tag).map(|o| o.value.as_slice())
+    }
+
+    /// 卡片生成的密文类型
+    pub fn cryptogram_type(&self) -> CryptogramType {
+        match self.value(TAG_CRYPTOGRAM_INFORMATION_DATA).map(|v| v[0] & 0xC0) {
+            Some(0x80) => CryptogramType::Arqc,
+            Some(0x40) => CryptogramType::Tc,
+            _ => CryptogramType::Aac,
+        }
+    }
+
+    /// 交易货币数字代码（3位）
+    pub fn currency_code(&self) -> String {
+        let digits = hex::encode(self.value(TAG_TRANSACTION_CURRENCY_CODE).unwrap_or_default());
+        digits[digits.len().saturating_sub(3)..].to_string()
+    }
+
+    /// 卡片签名的授权金额（分），未携带9F02时为None
+    pub fn amount_authorised(&self) -> Option<i64> {
+        self.value(TAG_AMOUNT_AUTHORISED).and_then(|v| hex::encode(v).parse().ok())
+    }
+
+    /// 保存和上送处理器的数据（白名单标签，十六进制）
+    pub fn to_stored_hex(&self) -> String {
+        let stored: Vec<Tlv> =
+            self.objects.iter().filter(|o| STORED_TAGS.contains(&o.tag)).cloned().collect();
+        hex::encode_upper(tlv::encode(&stored))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (tag, min, max) in REQUIRED_TAGS {
+            let value = self.value(tag).ok_or_else(|| format!("Missing EMV tag {:X}", tag))?;
+            if value.len() < min || value.len() > max {
+                return Err(format!("Invalid length for EMV tag {:X}", tag));
+            }
+        }
+
+        if self.value(TAG_CRYPTOGRAM_INFORMATION_DATA).is_some_and(|v| v[0] & 0xC0 == 0xC0) {
+            return Err("Invalid cryptogram information data (9F27)".to_string());
+        }
+
+        let date = hex::encode(self.value(TAG_TRANSACTION_DATE).unwrap_or_default());
+        if chrono::NaiveDate::parse_from_str(&date, "%y%m%d").is_err() {
+            return Err("Invalid EMV transaction date (9A)".to_string());
+        }
+
+        for tag in [TAG_TRANSACTION_TYPE, TAG_TRANSACTION_CURRENCY_CODE, TAG_AMOUNT_AUTHORISED] {
+            if let Some(value) = self.value(tag) {
+                if !is_bcd(value) {
+                    return Err(format!("EMV tag {:X} must be numeric", tag));
+                }
+            }
+        }
+
+        if self.value(TAG_AMOUNT_AUTHORISED).is_some_and(|v| v.len() != 6) {
+            return Err(format!("Invalid length for EMV tag {:X}", TAG_AMOUNT_AUTHORISED));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for EmvData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EmvData").field(&redact_objects(&self.objects, 1)).finish()
+    }
+}
+
+/// EMV数据脱敏后用于日志：敏感标签的值替换为 `*`，无法解析或超长时只保留长度
+pub fn redact_hex(data: &str) -> String {
+    if data.len() > MAX_EMV_DATA_LENGTH * 2 {
+        return format!("<invalid EMV data, {} chars>", data.len());
+    }
+
+    match hex::decode(data.trim()).ok().and_then(|bytes| tlv::parse(&bytes).ok()) {
+        Some(objects) => redact_objects(&objects, 1),
+        None => format!("<invalid EMV data, {} chars>", data.len()),
+    }
+}
+
+/// 脱敏TLV序列，超过最大嵌套层数的模板整体替换为 `*`
+fn redact_objects(objects: &[Tlv], depth: usize) -> String {
+    objects
+        .iter()
+        .map(|object| {
+            let encoded = hex::encode_upper(tlv::encode(std::slice::from_ref(object)));
+            let header = &encoded[..encoded.len() - object.value.len() * 2];
+
+            if SENSITIVE_TAGS.contains(&object.tag) {
+                format!("{}{}", header, "*".repeat(object.value.len() * 2))
+            } else if object.is_constructed() {
+                match tlv::parse(&object.value) {
+                    Ok(children) if depth < MAX_TEMPLATE_DEPTH => {
+                        format!("{}{}", header, redact_objects(&children, depth + 1))
+                    },
+                    _ => format!("{}{}", header, "*".repeat(object.value.len() * 2)),
+                }
+            } else {
+                encoded
+            }
+        })
+        .collect()
+}
+
+/// 展开结构化模板，只保留基本数据对象；`depth` 为 `objects` 所在的层数（从1开始）
+fn flatten(objects: Vec<Tlv>, flat: &mut Vec<Tlv>, depth: usize) -> Result<(), String> {
+    for object in objects {
+        if object.is_constructed() {
+            if depth >= MAX_TEMPLATE_DEPTH {
+                return Err("EMV templates are nested too deeply".to_string());
+            }
+            let children =
+                tlv::parse(&object.value).map_err(|e| format!("Invalid EMV data: {}", e))?;
+            flatten(children, flat, depth + 1)?;
+        } else {
+            flat.push(object);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_bcd(value: &[u8]) -> bool {
+    value.iter().all(|b| b >> 4 <= 9 && b & 0x0F <= 9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMV_DATA: &str = "9F2608A1B2C3D4E5F60718\
+                            9F270180\
+                            9F100706010A03A0A000\
+                            9F370412345678\
+                            9F36020042\
+                            950500000080 00\
+                            9A03240301\
+                            9C0100\
+                            5F2A020840\
+                            82021980\
+                            8407A0000000031010\
+                            9F0206000000001000\
+                            57114111111111111111D25122010000000000\
+                            5A0841111111111111115F3401019F6B0F4111111111111111D2512201000000";
+
+    fn emv_data() -> String {
+        EMV_DATA.replace(' ', "")
+    }
+
+    #[test]
+    fn test_parse_key_tags() {
+        let emv = EmvData::from_hex(&emv_data()).unwrap();
+
+        assert_eq!(emv.cryptogram_type(), CryptogramType::Arqc);
+        assert_eq!(emv.currency_code(), "840");
+        assert_eq!(emv.amount_authorised(), Some(1000));
+        assert_eq!(emv.value(TAG_APPLICATION_TRANSACTION_COUNTER), Some(&[0x00, 0x42][..]));
+    }
+
+    #[test]
+    fn test_stored_subset_excludes_sensitive_tags() {
+        let emv = EmvData::from_hex(&emv_data()).unwrap();
+        let stored = emv.to_stored_hex();
+
+        let objects = tlv::parse(&hex::decode(&stored).unwrap()).unwrap();
+        assert_eq!(objects.len(), 12);
+        assert!(objects.iter().all(|o| STORED_TAGS.contains(&o.tag)));
+        assert!(!stored.contains("4111111111111111"));
+
+        // 保存的数据可以再次解析
+        assert!(EmvData::from_hex(&stored).is_ok());
+    }
+
+    #[test]
+    fn test_redaction() {
+        let redacted = redact_hex(&emv_data());
+        assert!(!redacted.contains("4111111111111111"));
+        assert!(redacted.contains("5A08****************"));
+        assert!(redacted.contains("9F2608A1B2C3D4E5F60718"));
+
+        let emv = EmvData::from_hex(&emv_data()).unwrap();
+        assert!(!format!("{:?}", emv).contains("4111111111111111"));
+
+        // 结构化模板中的敏感标签同样脱敏
+        assert_eq!(redact_hex("770A5A084111111111111111"), "770A5A08****************");
+        assert_eq!(redact_hex("5A0841111111"), "<invalid EMV data, 12 chars>");
+    }
+
+    #[test]
+    fn test_invalid_emv_data() {
+        assert!(EmvData::from_hex("zz").is_err());
+        assert!(EmvData::from_hex("").is_err());
+
+        let missing_atc = emv_data().replace("9F36020042", "");
+        assert!(EmvData::from_hex(&missing_atc).unwrap_err().contains("9F36"));
+
+        let bad_date = emv_data().replace("9A03240301", "9A03241301");
+        assert!(EmvData::from_hex(&bad_date).unwrap_err().contains("9A"));
+
+        let short_arqc = emv_data().replace("9F2608A1B2C3D4E5F60718", "9F2604A1B2C3D4");
+        assert!(EmvData::from_hex(&short_arqc).unwrap_err().contains("9F26"));
+
+        let duplicate = format!("{}9C0100", emv_data());
+        assert!(EmvData::from_hex(&duplicate).unwrap_err().contains("Duplicate"));
+
+        let oversized = format!("{}DF018201F4{}", emv_data(), "00".repeat(500));
+        assert!(EmvData::from_hex(&oversized).unwrap_err().contains("exceed"));
+    }
+
+    #[test]
+    fn test_nesting_depth_limited() {
+        let template = |tag: u32, data: Vec<u8>| tlv::encode(&[Tlv::new(tag, data)]);
+
+        // 77模板中的数据可以解析
+        let nested = template(0x77, hex::decode(emv_data()).unwrap());
+        assert!(EmvData::from_hex(&hex::encode(nested)).is_ok());
+
+        // 深层嵌套的模板返回错误而不是递归展开
+        let mut deep = vec![0x9C, 0x01, 0x00];
+        for _ in 0..100 {
+            deep = template(0x70, deep);
+        }
+        let deep = hex::encode_upper(deep);
+        assert!(EmvData::from_hex(&deep).unwrap_err().contains("nested too deeply"));
+        assert!(redact_hex(&deep).contains('*'));
+    }
+}
@@ -60,6 +60,11 @@ impl HealthCheck {
         self.details = Some(details);
         self
     }
+
+    /// 设备未Root且系统和应用完整性校验通过
+    pub fn is_clean(&self) -> bool {
+        !self.root_status && self.system_integrity && self.app_integrity
+    }
 }
 
 /// 推荐操作
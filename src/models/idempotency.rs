@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 幂等键记录
+///
+/// 以（设备ID, 幂等键）为主键，保存首次请求的摘要和响应，重试时原样返回
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct IdempotencyRecord {
+    pub device_id: String,
+    pub idempotency_key: String,
+    /// 请求内容的SHA-256摘要（十六进制）
+    pub request_hash: String,
+    pub status: String,
+    /// 首次处理的HTTP状态码，处理中时为空
+    pub response_status: Option<i64>,
+    /// 首次处理的响应体，处理中时为空
+    pub response_body: Option<String>,
+    pub transaction_id: Option<String>,
+    pub created_at: String,
+    /// 加锁时间，超过锁超时仍未完成的记录可被重新领取
+    pub locked_at: String,
+    pub expires_at: String,
+}
+
+impl IdempotencyRecord {
+    /// 是否已保存响应
+    pub fn is_completed(&self) -> bool {
+        self.status == IdempotencyStatus::Completed.as_str()
+    }
+}
+
+/// 幂等键状态
+///
+/// IN_PROGRESS → COMPLETED；处理失败时删除记录，允许使用同一幂等键重试
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IdempotencyStatus {
+    InProgress,
+    Completed,
+}
+
+impl IdempotencyStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdempotencyStatus::InProgress => "IN_PROGRESS",
+            IdempotencyStatus::Completed => "COMPLETED",
+        }
+    }
+}
@@ -27,6 +27,7 @@ impl std::fmt::Display for KernelStatus {
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Kernel {
     pub id: String,
+    pub tenant_id: String,
     pub version: String,
     pub file_path: String,
     pub file_hash: String,
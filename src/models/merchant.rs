@@ -0,0 +1,189 @@
+use std::str::FromStr;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// 商户模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Merchant {
+    pub id: String,
+    /// 所属租户
+    pub tenant_id: String,
+    pub name: String,
+    /// 商户类别码（ISO 18245）
+    pub mcc: String,
+    /// 国家代码（ISO 3166-1 alpha-2）
+    pub country: String,
+    /// 结算币种（ISO 4217）
+    pub currency: String,
+    /// 收单机构分配的商户号
+    pub acquirer_mid: Option<String>,
+    pub address: Option<String>,
+    pub status: String,
+    /// 终端号分配序列
+    pub tid_sequence: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Merchant {
+    pub fn new(
+        name: String,
+        mcc: String,
+        country: String,
+        currency: String,
+        acquirer_mid: Option<String>,
+        address: Option<String>,
+    ) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
+            name,
+            mcc,
+            country,
+            currency,
+            acquirer_mid,
+            address,
+            status: MerchantStatus::Active.as_str().to_string(),
+            tid_sequence: 0,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    /// 根据序列号生成终端号（8位数字）
+    pub fn format_terminal_id(sequence: i64) -> String {
+        format!("{:08}", sequence)
+    }
+}
+
+/// 商户状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MerchantStatus {
+    Active,
+    Suspended,
+    Closed,
+}
+
+impl MerchantStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MerchantStatus::Active => "ACTIVE",
+            MerchantStatus::Suspended => "SUSPENDED",
+            MerchantStatus::Closed => "CLOSED",
+        }
+    }
+}
+
+impl FromStr for MerchantStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ACTIVE" => Ok(MerchantStatus::Active),
+            "SUSPENDED" => Ok(MerchantStatus::Suspended),
+            "CLOSED" => Ok(MerchantStatus::Closed),
+            _ => Err(format!("Unknown merchant status: {}", s)),
+        }
+    }
+}
+
+/// 门店模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Store {
+    pub id: String,
+    pub merchant_id: String,
+    pub name: String,
+    pub address: Option<String>,
+    pub status: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Store {
+    pub fn new(merchant_id: String, name: String, address: Option<String>) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            merchant_id,
+            name,
+            address,
+            status: StoreStatus::Active.as_str().to_string(),
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+}
+
+/// 门店状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StoreStatus {
+    Active,
+    Closed,
+}
+
+impl StoreStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StoreStatus::Active => "ACTIVE",
+            StoreStatus::Closed => "CLOSED",
+        }
+    }
+}
+
+impl FromStr for StoreStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ACTIVE" => Ok(StoreStatus::Active),
+            "CLOSED" => Ok(StoreStatus::Closed),
+            _ => Err(format!("Unknown store status: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merchant_status_conversion() {
+        assert_eq!(MerchantStatus::Active.as_str(), "ACTIVE");
+        assert_eq!(MerchantStatus::from_str("SUSPENDED").ok(), Some(MerchantStatus::Suspended));
+        assert_eq!(MerchantStatus::from_str("INVALID").ok(), None);
+    }
+
+    #[test]
+    fn test_store_status_conversion() {
+        assert_eq!(StoreStatus::Closed.as_str(), "CLOSED");
+        assert_eq!(StoreStatus::from_str("ACTIVE").ok(), Some(StoreStatus::Active));
+        assert_eq!(StoreStatus::from_str("SUSPENDED").ok(), None);
+    }
+
+    #[test]
+    fn test_format_terminal_id() {
+        assert_eq!(Merchant::format_terminal_id(1), "00000001");
+        assert_eq!(Merchant::format_terminal_id(12345678), "12345678");
+    }
+
+    #[test]
+    fn test_new_merchant_defaults() {
+        let merchant = Merchant::new(
+            "Coffee Shop".to_string(),
+            "5814".to_string(),
+            "CN".to_string(),
+            "CNY".to_string(),
+            None,
+            None,
+        );
+
+        assert_eq!(merchant.status, "ACTIVE");
+        assert_eq!(merchant.tid_sequence, 0);
+    }
+}
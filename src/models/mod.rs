@@ -1,21 +1,71 @@
 pub mod audit_log;
 pub mod device;
+pub mod device_command;
+pub mod device_group;
+pub mod device_import;
+pub mod emv;
 pub mod health_check;
+pub mod idempotency;
 pub mod kernel;
+pub mod merchant;
+pub mod notification;
+pub mod outbox;
+pub mod reconciliation;
+pub mod reversal;
+pub mod settlement;
+pub mod tenant;
 pub mod threat;
 pub mod transaction;
 pub mod transaction_token;
 pub mod user;
 pub mod version;
+pub mod webhook;
 
 pub use audit_log::{AuditLog, OperationResult};
-pub use device::{Device, DeviceMode, DeviceStatus, TeeType};
+pub use device::{Device, DeviceMode, DeviceStatus, DeviceStatusHistory, TeeType};
+pub use device_command::{
+    CommandStatus, CommandType, DeviceCommand, DEFAULT_COMMAND_MAX_ATTEMPTS,
+    DEFAULT_COMMAND_TIMEOUT_SECONDS,
+};
+pub use device_group::{normalize_tag, DeviceGroup, GroupRules, GroupType};
+pub use device_import::{
+    DeviceImportJob, DevicePreapproval, ImportFormat, ImportJobStatus, ImportRowError,
+    PreapprovalStatus,
+};
+pub use emv::{redact_hex as redact_emv_data, CryptogramType, EmvData};
 pub use health_check::{CheckResult, HealthCheck, RecommendedAction};
+pub use idempotency::{IdempotencyRecord, IdempotencyStatus};
 pub use kernel::{Kernel, KernelStatus};
+pub use merchant::{Merchant, MerchantStatus, Store, StoreStatus};
+pub use notification::{
+    AlertEscalation, EscalationStatus, NotificationRecord, MAX_STORED_NOTIFICATIONS,
+};
+pub use outbox::{DomainEvent, OutboxEvent, OutboxStatus, DEFAULT_OUTBOX_MAX_ATTEMPTS};
+pub use reconciliation::{
+    ReconciliationCategory, ReconciliationItem, ReconciliationRun, SettlementFileColumn,
+    SettlementFileField, SettlementFileFormat, SettlementFileLayout,
+};
+pub use reversal::{
+    ReversalReason, ReversalStatus, TransactionReversal, DEFAULT_REVERSAL_MAX_ATTEMPTS,
+};
+pub use settlement::{
+    net_amounts, BatchStatus, SettlementBatch, SettlementFailureReason, SettlementTotal,
+    DEFAULT_SETTLEMENT_MAX_ATTEMPTS,
+};
+pub use tenant::{
+    ApiKey, Tenant, TenantBranding, TenantContext, TenantStatus, DEFAULT_TENANT_ID,
+};
 pub use threat::{ThreatEvent, ThreatSeverity, ThreatStatus, ThreatType};
-pub use transaction::{Transaction, TransactionStatus, TransactionType};
+pub use transaction::{
+    Transaction, TransactionEvent, TransactionEventType, TransactionStatus, TransactionType,
+};
 pub use transaction_token::{
-    TokenConfig, TokenUsageRecord, TransactionToken, TransactionTokenClaims,
+    transaction_binding_hash, TokenConfig, TokenUsageRecord, TransactionToken,
+    TransactionTokenClaims,
 };
 pub use user::{User, UserRole, UserStatus};
 pub use version::{SdkVersion, UpdateType, VersionStatus};
+pub use webhook::{
+    WebhookDelivery, WebhookDeliveryStatus, WebhookEventType, WebhookSubscription,
+    DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+};
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 通知存储保留的最大条数，超出后删除最早的通知
+pub const MAX_STORED_NOTIFICATIONS: i64 = 10_000;
+
+/// 持久化的通知记录
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct NotificationRecord {
+    /// 单调递增的序列号
+    pub seq: i64,
+    /// 所属租户，为空时只对跨租户访问可见
+    pub tenant_id: Option<String>,
+    pub notification_type: String,
+    pub severity: String,
+    pub title: String,
+    pub message: String,
+    pub device_id: Option<String>,
+    pub merchant_id: Option<String>,
+    pub threat_id: Option<String>,
+    /// 附加数据（JSON）
+    pub data: Option<String>,
+    pub created_at: String,
+    /// 当前用户的已读时间（仅按用户查询时填充）
+    #[sqlx(default)]
+    pub read_at: Option<String>,
+}
+
+/// 外部告警的升级跟踪记录
+///
+/// 告警发出后在 `due_at` 前仍无人确认（标记已读）时，按路由的升级策略再次通知
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AlertEscalation {
+    pub id: String,
+    pub notification_seq: i64,
+    /// 触发升级的路由规则名
+    pub route_name: String,
+    pub status: String,
+    pub due_at: String,
+    pub created_at: String,
+    pub resolved_at: Option<String>,
+}
+
+impl AlertEscalation {
+    pub fn new(notification_seq: i64, route_name: String, due_at: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            notification_seq,
+            route_name,
+            status: EscalationStatus::Pending.as_str().to_string(),
+            due_at,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            resolved_at: None,
+        }
+    }
+}
+
+/// 升级状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EscalationStatus {
+    /// 等待确认
+    Pending,
+    /// 到期前已确认，无需升级
+    Acknowledged,
+    /// 已升级通知
+    Escalated,
+}
+
+impl EscalationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EscalationStatus::Pending => "PENDING",
+            EscalationStatus::Acknowledged => "ACKNOWLEDGED",
+            EscalationStatus::Escalated => "ESCALATED",
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::{ThreatSeverity, ThreatType, TransactionStatus, TransactionType, UpdateType};
+
+/// 默认最大投递次数，超过后标记为FAILED
+pub const DEFAULT_OUTBOX_MAX_ATTEMPTS: i64 = 10;
+
+/// 领域事件
+///
+/// 由Repository在状态变更的同一事务中写入发件箱，后台分发器再转发给通知、Webhook和指标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum DomainEvent {
+    /// 设备注册
+    DeviceRegistered {
+        device_id: String,
+        imei: String,
+        model: String,
+        tee_type: String,
+    },
+    /// 设备状态变更
+    DeviceStatusChanged {
+        device_id: String,
+        from_status: Option<String>,
+        to_status: String,
+        changed_by: String,
+        reason: Option<String>,
+    },
+    /// 设备IPEK注入
+    KeyInjected {
+        device_id: String,
+        ksn: String,
+        key_total_count: Option<i32>,
+    },
+    /// 交易处理完成
+    TransactionProcessed {
+        transaction_id: String,
+        device_id: String,
+        merchant_id: Option<String>,
+        terminal_id: Option<String>,
+        transaction_type: TransactionType,
+        amount: i64,
+        currency: String,
+        status: TransactionStatus,
+        authorization_code: Option<String>,
+        response_code: Option<String>,
+        card_number_masked: Option<String>,
+    },
+    /// 检测到威胁
+    ThreatDetected {
+        threat_id: String,
+        device_id: String,
+        threat_type: ThreatType,
+        severity: ThreatSeverity,
+        description: String,
+    },
+    /// SDK版本发布
+    VersionPublished {
+        version_id: String,
+        version: String,
+        update_type: UpdateType,
+    },
+}
+
+impl DomainEvent {
+    /// 事件类型名
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DomainEvent::DeviceRegistered { .. } => "DeviceRegistered",
+            DomainEvent::DeviceStatusChanged { .. } => "DeviceStatusChanged",
+            DomainEvent::KeyInjected { .. } => "KeyInjected",
+            DomainEvent::TransactionProcessed { .. } => "TransactionProcessed",
+            DomainEvent::ThreatDetected { .. } => "ThreatDetected",
+            DomainEvent::VersionPublished { .. } => "VersionPublished",
+        }
+    }
+
+    /// 事件所属聚合的ID（设备、交易、威胁或版本）
+    pub fn aggregate_id(&self) -> &str {
+        match self {
+            DomainEvent::DeviceRegistered { device_id, .. }
+            | DomainEvent::DeviceStatusChanged { device_id, .. }
+            | DomainEvent::KeyInjected { device_id, .. } => device_id,
+            DomainEvent::TransactionProcessed { transaction_id, .. } => transaction_id,
+            DomainEvent::ThreatDetected { threat_id, .. } => threat_id,
+            DomainEvent::VersionPublished { version_id, .. } => version_id,
+        }
+    }
+}
+
+/// 发件箱记录
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OutboxEvent {
+    /// 写入顺序，分发器按此顺序转发
+    pub seq: i64,
+    pub id: String,
+    pub tenant_id: String,
+    pub event_type: String,
+    pub aggregate_id: String,
+    /// 事件内容（DomainEvent的JSON）
+    pub payload: String,
+    pub status: String,
+    pub attempts: i64,
+    pub next_attempt_at: String,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub dispatched_at: Option<String>,
+}
+
+impl OutboxEvent {
+    /// 创建待写入的记录，`seq` 由数据库分配
+    pub fn new(tenant_id: String, event: &DomainEvent) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            seq: 0,
+            id: Uuid::new_v4().to_string(),
+            tenant_id,
+            event_type: event.event_type().to_string(),
+            aggregate_id: event.aggregate_id().to_string(),
+            payload: serde_json::to_string(event).unwrap_or_default(),
+            status: OutboxStatus::Pending.as_str().to_string(),
+            attempts: 0,
+            next_attempt_at: now.clone(),
+            last_error: None,
+            created_at: now,
+            dispatched_at: None,
+        }
+    }
+
+    /// 解析事件内容
+    pub fn event(&self) -> Option<DomainEvent> {
+        serde_json::from_str(&self.payload).ok()
+    }
+}
+
+/// 发件箱记录状态
+///
+/// PENDING → DISPATCHED；转发失败时保持PENDING并退避重试，超过最大次数后进入FAILED
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OutboxStatus {
+    Pending,
+    Dispatched,
+    Failed,
+}
+
+impl OutboxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "PENDING",
+            OutboxStatus::Dispatched => "DISPATCHED",
+            OutboxStatus::Failed => "FAILED",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outbox_event_roundtrip() {
+        let event = DomainEvent::ThreatDetected {
+            threat_id: "t1".to_string(),
+            device_id: "d1".to_string(),
+            threat_type: ThreatType::RootDetection,
+            severity: ThreatSeverity::High,
+            description: "Root detected".to_string(),
+        };
+
+        let record = OutboxEvent::new("tenant-1".to_string(), &event);
+        assert_eq!(record.event_type, "ThreatDetected");
+        assert_eq!(record.aggregate_id, "t1");
+        assert_eq!(record.status, "PENDING");
+
+        match record.event() {
+            Some(DomainEvent::ThreatDetected { threat_id, severity, .. }) => {
+                assert_eq!(threat_id, "t1");
+                assert_eq!(severity, ThreatSeverity::High);
+            },
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
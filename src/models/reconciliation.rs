@@ -0,0 +1,330 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// 对账任务
+///
+/// 每次导入收单机构清算文件生成一次对账，记录所用格式、覆盖的交易日期和各类结果笔数
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ReconciliationRun {
+    pub id: String,
+    /// 所属租户
+    pub tenant_id: String,
+    /// 清算文件格式名称
+    pub layout: String,
+    pub file_name: Option<String>,
+    /// 只对账该商户的交易，为空时对账租户下全部交易
+    pub merchant_id: Option<String>,
+    /// 清算文件覆盖的交易日期（YYYY-MM-DD）
+    pub period_start: String,
+    pub period_end: String,
+    /// 清算文件明细笔数
+    pub total_records: i64,
+    pub matched_count: i64,
+    pub amount_mismatch_count: i64,
+    pub unmatched_ours_count: i64,
+    pub unmatched_theirs_count: i64,
+    pub created_by: String,
+    pub created_at: String,
+}
+
+impl ReconciliationRun {
+    pub fn new(
+        layout: String,
+        file_name: Option<String>,
+        merchant_id: Option<String>,
+        period_start: String,
+        period_end: String,
+        created_by: String,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
+            layout,
+            file_name,
+            merchant_id,
+            period_start,
+            period_end,
+            total_records: 0,
+            matched_count: 0,
+            amount_mismatch_count: 0,
+            unmatched_ours_count: 0,
+            unmatched_theirs_count: 0,
+            created_by,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// 按对账结果统计各类笔数
+    pub fn tally(&mut self, items: &[ReconciliationItem]) {
+        let count = |category: ReconciliationCategory| {
+            items.iter().filter(|i| i.category == category.as_str()).count() as i64
+        };
+
+        self.matched_count = count(ReconciliationCategory::Matched);
+        self.amount_mismatch_count = count(ReconciliationCategory::AmountMismatch);
+        self.unmatched_ours_count = count(ReconciliationCategory::UnmatchedOurs);
+        self.unmatched_theirs_count = count(ReconciliationCategory::UnmatchedTheirs);
+        self.total_records =
+            self.matched_count + self.amount_mismatch_count + self.unmatched_theirs_count;
+    }
+}
+
+/// 对账结果明细，每条清算文件明细或未出现在文件中的本地交易一条
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ReconciliationItem {
+    pub id: String,
+    pub run_id: String,
+    pub category: String,
+    /// 清算文件中的行号（从1开始，含表头），本地单边交易为空
+    pub line_number: Option<i64>,
+    /// 匹配到的本地交易ID，收单机构单边为空
+    pub transaction_id: Option<String>,
+    pub rrn: Option<String>,
+    pub authorization_code: Option<String>,
+    /// 交易日期（YYYY-MM-DD），优先取清算文件中的日期
+    pub transaction_date: Option<String>,
+    pub currency: Option<String>,
+    /// 本地交易金额（分）
+    pub our_amount: Option<i64>,
+    /// 清算文件金额（分）
+    pub their_amount: Option<i64>,
+    pub details: Option<String>,
+}
+
+impl ReconciliationItem {
+    pub fn new(run_id: String, category: ReconciliationCategory) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            run_id,
+            category: category.as_str().to_string(),
+            line_number: None,
+            transaction_id: None,
+            rrn: None,
+            authorization_code: None,
+            transaction_date: None,
+            currency: None,
+            our_amount: None,
+            their_amount: None,
+            details: None,
+        }
+    }
+}
+
+/// 对账结果分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReconciliationCategory {
+    /// 双方一致
+    Matched,
+    /// 匹配到同一笔交易但金额或币种不一致
+    AmountMismatch,
+    /// 本地有、清算文件中没有
+    UnmatchedOurs,
+    /// 清算文件中有、本地没有
+    UnmatchedTheirs,
+}
+
+impl ReconciliationCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReconciliationCategory::Matched => "MATCHED",
+            ReconciliationCategory::AmountMismatch => "AMOUNT_MISMATCH",
+            ReconciliationCategory::UnmatchedOurs => "UNMATCHED_OURS",
+            ReconciliationCategory::UnmatchedTheirs => "UNMATCHED_THEIRS",
+        }
+    }
+}
+
+/// 清算文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementFileFormat {
+    /// 分隔符文本
+    Csv,
+    /// 定长记录
+    FixedWidth,
+}
+
+/// 清算文件中可识别的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementFileField {
+    Rrn,
+    AuthorizationCode,
+    /// 金额；带小数点时按元解析，否则按分解析，按绝对值核对
+    Amount,
+    Currency,
+    TransactionDate,
+    /// 终端号，双方都有时必须一致
+    TerminalId,
+}
+
+/// 清算文件格式定义（配置项 `reconciliation.layouts`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementFileLayout {
+    /// 格式名称，导入时通过名称引用
+    pub name: String,
+    pub format: SettlementFileFormat,
+    /// 字段分隔符（csv）
+    #[serde(default = "default_layout_delimiter")]
+    pub delimiter: String,
+    /// 首行是否为表头（csv）
+    #[serde(default = "default_layout_has_header")]
+    pub has_header: bool,
+    /// 只处理以该前缀开头的明细行，用于跳过文件头和文件尾记录
+    #[serde(default)]
+    pub record_prefix: Option<String>,
+    /// 交易日期格式（chrono格式）
+    #[serde(default = "default_layout_date_format")]
+    pub date_format: String,
+    pub fields: Vec<SettlementFileColumn>,
+}
+
+/// 清算文件字段位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementFileColumn {
+    pub field: SettlementFileField,
+    /// 表头列名（csv，有表头时）
+    #[serde(default)]
+    pub column: Option<String>,
+    /// 列序号（csv，从0开始）
+    #[serde(default)]
+    pub index: Option<usize>,
+    /// 起始位置（fixed_width，从1开始）
+    #[serde(default)]
+    pub start: Option<usize>,
+    /// 字段长度（fixed_width）
+    #[serde(default)]
+    pub length: Option<usize>,
+}
+
+impl SettlementFileLayout {
+    /// 字段位置定义
+    pub fn column(&self, field: SettlementFileField) -> Option<&SettlementFileColumn> {
+        self.fields.iter().find(|c| c.field == field)
+    }
+
+    /// 校验格式定义：必须包含金额、交易日期以及RRN或授权码，且每个字段的位置完整
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Settlement file layout name cannot be empty".to_string());
+        }
+
+        if self.format == SettlementFileFormat::Csv && self.delimiter.chars().count() != 1 {
+            return Err(format!("Layout {}: delimiter must be a single character", self.name));
+        }
+
+        for field in [SettlementFileField::Amount, SettlementFileField::TransactionDate] {
+            if self.column(field).is_none() {
+                return Err(format!("Layout {}: missing {:?} field", self.name, field));
+            }
+        }
+
+        if self.column(SettlementFileField::Rrn).is_none()
+            && self.column(SettlementFileField::AuthorizationCode).is_none()
+        {
+            return Err(format!(
+                "Layout {}: rrn or authorization_code field is required",
+                self.name
+            ));
+        }
+
+        for column in &self.fields {
+            let positioned = match self.format {
+                SettlementFileFormat::Csv => {
+                    column.index.is_some() || (self.has_header && column.column.is_some())
+                },
+                SettlementFileFormat::FixedWidth => {
+                    column.start.is_some_and(|s| s > 0) && column.length.is_some_and(|l| l > 0)
+                },
+            };
+            if !positioned {
+                return Err(format!(
+                    "Layout {}: {:?} field has no valid position",
+                    self.name, column.field
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn default_layout_delimiter() -> String {
+    ",".to_string()
+}
+
+fn default_layout_has_header() -> bool {
+    true
+}
+
+fn default_layout_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(field: SettlementFileField, start: usize, length: usize) -> SettlementFileColumn {
+        SettlementFileColumn {
+            field,
+            column: None,
+            index: None,
+            start: Some(start),
+            length: Some(length),
+        }
+    }
+
+    #[test]
+    fn test_layout_validation() {
+        let mut layout = SettlementFileLayout {
+            name: "acquirer".to_string(),
+            format: SettlementFileFormat::FixedWidth,
+            delimiter: default_layout_delimiter(),
+            has_header: false,
+            record_prefix: Some("D".to_string()),
+            date_format: "%Y%m%d".to_string(),
+            fields: vec![
+                column(SettlementFileField::Rrn, 2, 12),
+                column(SettlementFileField::Amount, 14, 12),
+                column(SettlementFileField::TransactionDate, 26, 8),
+            ],
+        };
+        assert!(layout.validate().is_ok());
+
+        layout.fields[0].length = None;
+        assert!(layout.validate().is_err());
+
+        layout.fields.remove(0);
+        assert!(layout.validate().unwrap_err().contains("rrn or authorization_code"));
+    }
+
+    #[test]
+    fn test_tally() {
+        let mut run = ReconciliationRun::new(
+            "acquirer".to_string(),
+            None,
+            None,
+            "2024-01-01".to_string(),
+            "2024-01-01".to_string(),
+            "admin".to_string(),
+        );
+        let items = vec![
+            ReconciliationItem::new(run.id.clone(), ReconciliationCategory::Matched),
+            ReconciliationItem::new(run.id.clone(), ReconciliationCategory::Matched),
+            ReconciliationItem::new(run.id.clone(), ReconciliationCategory::AmountMismatch),
+            ReconciliationItem::new(run.id.clone(), ReconciliationCategory::UnmatchedOurs),
+            ReconciliationItem::new(run.id.clone(), ReconciliationCategory::UnmatchedTheirs),
+        ];
+        run.tally(&items);
+
+        assert_eq!(run.matched_count, 2);
+        assert_eq!(run.amount_mismatch_count, 1);
+        assert_eq!(run.unmatched_ours_count, 1);
+        assert_eq!(run.unmatched_theirs_count, 1);
+        assert_eq!(run.total_records, 4);
+    }
+}
@@ -0,0 +1,88 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// 默认最大冲正次数，超过后标记为FAILED，需人工处理
+pub const DEFAULT_REVERSAL_MAX_ATTEMPTS: i64 = 10;
+
+/// 冲正队列记录
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TransactionReversal {
+    pub id: String,
+    pub transaction_id: String,
+    pub reason: String,
+    pub requested_by: String,
+    pub status: String,
+    /// 已尝试次数
+    pub attempts: i64,
+    pub next_attempt_at: String,
+    pub last_error: Option<String>,
+    /// 处理器对冲正的应答码
+    pub response_code: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+impl TransactionReversal {
+    /// 创建立即可执行的冲正记录
+    pub fn new(transaction_id: String, reason: ReversalReason, requested_by: String) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            transaction_id,
+            reason: reason.as_str().to_string(),
+            requested_by,
+            status: ReversalStatus::Pending.as_str().to_string(),
+            attempts: 0,
+            next_attempt_at: now.clone(),
+            last_error: None,
+            response_code: None,
+            created_at: now,
+            completed_at: None,
+        }
+    }
+}
+
+/// 冲正原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReversalReason {
+    /// 处理器超时，交易结果未知
+    ProcessorTimeout,
+    /// 设备未收到应答，主动申请冲正
+    DeviceRequest,
+    /// 操作员发起
+    OperatorRequest,
+}
+
+impl ReversalReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReversalReason::ProcessorTimeout => "PROCESSOR_TIMEOUT",
+            ReversalReason::DeviceRequest => "DEVICE_REQUEST",
+            ReversalReason::OperatorRequest => "OPERATOR_REQUEST",
+        }
+    }
+}
+
+/// 冲正状态
+///
+/// PENDING → COMPLETED；冲正失败时保持PENDING并退避重试，超过最大次数后进入FAILED
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReversalStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+impl ReversalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReversalStatus::Pending => "PENDING",
+            ReversalStatus::Completed => "COMPLETED",
+            ReversalStatus::Failed => "FAILED",
+        }
+    }
+}
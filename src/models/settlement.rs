@@ -0,0 +1,170 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::TransactionType;
+
+/// 默认最大提交次数，超过后批次标记为FAILED，需人工重新开批
+pub const DEFAULT_SETTLEMENT_MAX_ATTEMPTS: i64 = 5;
+
+/// 结算批次
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SettlementBatch {
+    pub id: String,
+    pub device_id: String,
+    pub merchant_id: Option<String>,
+    /// 设备内递增的批次号
+    pub batch_number: i64,
+    pub status: String,
+    /// 关批时计入合计的交易笔数
+    pub transaction_count: i64,
+    /// 处理结算的支付连接器
+    pub processor: Option<String>,
+    pub processor_reference: Option<String>,
+    pub response_code: Option<String>,
+    pub failure_reason: Option<String>,
+    pub last_error: Option<String>,
+    pub submit_attempts: i64,
+    pub next_attempt_at: Option<String>,
+    /// 对账不平时是否已上送批内明细
+    pub uploaded: bool,
+    pub opened_at: String,
+    pub closed_at: Option<String>,
+    pub closed_by: Option<String>,
+    pub settled_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl SettlementBatch {
+    /// 创建开放批次
+    pub fn open(device_id: String, merchant_id: Option<String>, batch_number: i64) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            device_id,
+            merchant_id,
+            batch_number,
+            status: BatchStatus::Open.as_str().to_string(),
+            transaction_count: 0,
+            processor: None,
+            processor_reference: None,
+            response_code: None,
+            failure_reason: None,
+            last_error: None,
+            submit_attempts: 0,
+            next_attempt_at: None,
+            uploaded: false,
+            opened_at: now.clone(),
+            closed_at: None,
+            closed_by: None,
+            settled_at: None,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+}
+
+/// 批次状态
+///
+/// OPEN → CLOSED → SETTLED；处理器拒绝、对账不平或提交次数耗尽时进入FAILED，
+/// 重新开批后回到OPEN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BatchStatus {
+    Open,
+    /// 已关批，等待提交处理器
+    Closed,
+    Settled,
+    Failed,
+}
+
+impl BatchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BatchStatus::Open => "OPEN",
+            BatchStatus::Closed => "CLOSED",
+            BatchStatus::Settled => "SETTLED",
+            BatchStatus::Failed => "FAILED",
+        }
+    }
+}
+
+/// 批次结算失败原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SettlementFailureReason {
+    /// 处理器拒绝结算
+    Rejected,
+    /// 上送明细后合计仍与处理器不一致
+    TotalsMismatch,
+    /// 通信失败且重试耗尽
+    SubmissionFailed,
+}
+
+impl SettlementFailureReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettlementFailureReason::Rejected => "REJECTED",
+            SettlementFailureReason::TotalsMismatch => "TOTALS_MISMATCH",
+            SettlementFailureReason::SubmissionFailed => "SUBMISSION_FAILED",
+        }
+    }
+}
+
+/// 批次按交易类型和币种汇总的合计
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct SettlementTotal {
+    pub transaction_type: TransactionType,
+    pub currency: String,
+    pub count: i64,
+    /// 金额（分）
+    pub amount: i64,
+}
+
+impl SettlementTotal {
+    /// 对净额的影响：消费和请款为借记，退款为贷记，撤销只做统计（被撤销的交易不计入合计）
+    pub fn signed_amount(&self) -> i64 {
+        match self.transaction_type {
+            TransactionType::Payment | TransactionType::Capture => self.amount,
+            TransactionType::Refund => -self.amount,
+            TransactionType::Void | TransactionType::PreAuth => 0,
+        }
+    }
+}
+
+/// 各币种的结算净额（借记减贷记），按币种排序
+pub fn net_amounts(totals: &[SettlementTotal]) -> Vec<(String, i64)> {
+    let mut net: Vec<(String, i64)> = Vec::new();
+    for total in totals {
+        match net.iter_mut().find(|(currency, _)| *currency == total.currency) {
+            Some((_, amount)) => *amount += total.signed_amount(),
+            None => net.push((total.currency.clone(), total.signed_amount())),
+        }
+    }
+    net.sort_by(|a, b| a.0.cmp(&b.0));
+    net
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(transaction_type: TransactionType, currency: &str, amount: i64) -> SettlementTotal {
+        SettlementTotal { transaction_type, currency: currency.to_string(), count: 1, amount }
+    }
+
+    #[test]
+    fn test_net_amounts_per_currency() {
+        let totals = vec![
+            total(TransactionType::Payment, "USD", 1000),
+            total(TransactionType::Capture, "USD", 500),
+            total(TransactionType::Refund, "USD", 300),
+            total(TransactionType::Void, "USD", 200),
+            total(TransactionType::Payment, "EUR", 700),
+        ];
+
+        assert_eq!(net_amounts(&totals), vec![("EUR".to_string(), 700), ("USD".to_string(), 1200)]);
+    }
+}
@@ -0,0 +1,208 @@
+use std::str::FromStr;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// 默认租户ID，多租户改造前的数据均归属于该租户
+pub const DEFAULT_TENANT_ID: &str = "default";
+
+/// 租户模型（收单机构 / ISO）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Tenant {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    /// 以密钥加密密钥包装的租户专属BDK，与 `bdk_key_ref` 均为空时使用全局BDK
+    #[serde(skip_serializing)]
+    pub bdk_wrapped: Option<String>,
+    /// HSM中租户专属BDK的密钥引用
+    pub bdk_key_ref: Option<String>,
+    /// 每秒请求数限制，为空时使用全局配置
+    pub rate_limit_rps: Option<i64>,
+    /// 突发请求数限制，为空时使用全局配置
+    pub rate_limit_burst: Option<i64>,
+    /// 品牌配置（JSON）
+    pub branding: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Tenant {
+    pub fn new(id: String, name: String) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id,
+            name,
+            status: TenantStatus::Active.as_str().to_string(),
+            bdk_wrapped: None,
+            bdk_key_ref: None,
+            rate_limit_rps: None,
+            rate_limit_burst: None,
+            branding: None,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    /// 是否配置了租户专属BDK
+    pub fn has_custom_bdk(&self) -> bool {
+        self.bdk_wrapped.is_some() || self.bdk_key_ref.is_some()
+    }
+
+    /// 解析品牌配置，未配置时使用租户名称作为显示名
+    pub fn branding(&self) -> TenantBranding {
+        self.branding
+            .as_deref()
+            .and_then(|b| serde_json::from_str(b).ok())
+            .unwrap_or_else(|| TenantBranding {
+                display_name: self.name.clone(),
+                ..TenantBranding::default()
+            })
+    }
+}
+
+/// 租户状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TenantStatus {
+    Active,
+    Suspended,
+}
+
+impl TenantStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TenantStatus::Active => "ACTIVE",
+            TenantStatus::Suspended => "SUSPENDED",
+        }
+    }
+}
+
+impl FromStr for TenantStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ACTIVE" => Ok(TenantStatus::Active),
+            "SUSPENDED" => Ok(TenantStatus::Suspended),
+            _ => Err(format!("Unknown tenant status: {}", s)),
+        }
+    }
+}
+
+/// 租户品牌配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantBranding {
+    pub display_name: String,
+    #[serde(default)]
+    pub logo_url: Option<String>,
+    #[serde(default)]
+    pub primary_color: Option<String>,
+    #[serde(default)]
+    pub support_email: Option<String>,
+}
+
+/// 租户API密钥
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    pub id: String,
+    pub tenant_id: String,
+    pub name: String,
+    /// 密钥前缀，用于识别密钥
+    pub key_prefix: String,
+    /// 密钥的SHA256哈希，明文仅在创建时返回一次
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub role: String,
+    pub created_by: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl ApiKey {
+    pub fn new(
+        tenant_id: String,
+        name: String,
+        key_prefix: String,
+        key_hash: String,
+        role: String,
+        created_by: String,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id,
+            name,
+            key_prefix,
+            key_hash,
+            role,
+            created_by,
+            created_at: Utc::now().to_rfc3339(),
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+}
+
+/// 请求的租户上下文
+///
+/// 由认证中间件根据JWT或API密钥解析，Repository据此自动过滤租户数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantContext {
+    /// 当前操作的租户，新建数据归属于该租户
+    pub tenant_id: String,
+    /// 是否跨租户访问（超级管理员未指定租户时）
+    pub cross_tenant: bool,
+}
+
+impl TenantContext {
+    /// 单租户上下文
+    pub fn new(tenant_id: impl Into<String>) -> Self {
+        Self { tenant_id: tenant_id.into(), cross_tenant: false }
+    }
+
+    /// 跨租户上下文（超级管理员、系统任务）
+    pub fn cross_tenant(tenant_id: impl Into<String>) -> Self {
+        Self { tenant_id: tenant_id.into(), cross_tenant: true }
+    }
+
+    /// 查询使用的租户过滤条件，跨租户时为None
+    pub fn scope(&self) -> Option<&str> {
+        if self.cross_tenant {
+            None
+        } else {
+            Some(&self.tenant_id)
+        }
+    }
+}
+
+impl Default for TenantContext {
+    fn default() -> Self {
+        Self::new(DEFAULT_TENANT_ID)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_context_scope() {
+        assert_eq!(TenantContext::new("acq-1").scope(), Some("acq-1"));
+        assert_eq!(TenantContext::cross_tenant("default").scope(), None);
+        assert_eq!(TenantContext::default().scope(), Some(DEFAULT_TENANT_ID));
+    }
+
+    #[test]
+    fn test_branding_fallback() {
+        let mut tenant = Tenant::new("acq-1".to_string(), "Acquirer One".to_string());
+        assert_eq!(tenant.branding().display_name, "Acquirer One");
+
+        tenant.branding = Some(r##"{"display_name":"Acq","primary_color":"#0055ff"}"##.to_string());
+        let branding = tenant.branding();
+        assert_eq!(branding.display_name, "Acq");
+        assert_eq!(branding.primary_color.as_deref(), Some("#0055ff"));
+    }
+}
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
 /// 交易类型
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "TEXT")]
 pub enum TransactionType {
     #[serde(rename = "PAYMENT")]
@@ -31,6 +31,22 @@ pub enum TransactionStatus {
     Failed,
     #[serde(rename = "VOIDED")]
     Voided,
+    /// 预授权已请款
+    #[serde(rename = "CAPTURED")]
+    Captured,
+    #[serde(rename = "PARTIALLY_REFUNDED")]
+    PartiallyRefunded,
+    #[serde(rename = "REFUNDED")]
+    Refunded,
+    /// 预授权超过有效期未请款
+    #[serde(rename = "EXPIRED")]
+    Expired,
+    /// 处理器超时，结果未知，等待冲正
+    #[serde(rename = "UNKNOWN")]
+    Unknown,
+    /// 已冲正
+    #[serde(rename = "REVERSED")]
+    Reversed,
 }
 
 /// 交易记录
@@ -55,6 +71,32 @@ pub struct Transaction {
     pub longitude: Option<f64>,
     pub location_accuracy: Option<f64>,
     pub location_timestamp: Option<chrono::NaiveDateTime>,
+    /// 处理交易的支付连接器
+    pub processor: Option<String>,
+    /// 支付处理器返回的参考号
+    pub processor_reference: Option<String>,
+    /// 批准金额（分），部分批准时小于交易金额
+    pub approved_amount: Option<i64>,
+    /// 原交易ID（退款、撤销、请款和追加授权）
+    pub original_transaction_id: Option<String>,
+    /// 预授权有效期
+    pub auth_expires_at: Option<String>,
+    /// 结算时间，未结算时为None
+    pub settled_at: Option<String>,
+    /// 所属结算批次，只有已批准的交易入批
+    pub batch_id: Option<String>,
+    /// 客户端生成的交易ID
+    pub client_transaction_id: Option<String>,
+    /// 非接/芯片交易的EMV数据（白名单标签，BER-TLV十六进制），不含磁道和主账号等敏感标签
+    pub emv_data: Option<String>,
+    /// 提交处理器的报文类型，与以下数据元在发送前保存，冲正时引用原报文
+    pub message_mti: Option<String>,
+    /// 系统跟踪号（STAN）
+    pub stan: Option<String>,
+    /// 检索参考号（RRN）
+    pub rrn: Option<String>,
+    /// 报文传输时间（MMDDhhmmss）
+    pub transmission_time: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -89,8 +131,104 @@ impl Transaction {
             location_accuracy: None,
             location_timestamp: None,
             response_message: None,
+            processor: None,
+            processor_reference: None,
+            approved_amount: None,
+            original_transaction_id: None,
+            auth_expires_at: None,
+            settled_at: None,
+            batch_id: None,
+            client_transaction_id: None,
+            emv_data: None,
+            message_mti: None,
+            stan: None,
+            rrn: None,
+            transmission_time: None,
             created_at: now.clone(),
             updated_at: now,
         }
     }
 }
+
+/// 交易状态历史
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TransactionEvent {
+    /// 写入顺序
+    pub seq: i64,
+    pub transaction_id: String,
+    pub event_type: String,
+    pub from_status: Option<TransactionStatus>,
+    pub to_status: TransactionStatus,
+    pub response_code: Option<String>,
+    pub details: Option<String>,
+    /// 操作者（操作员、设备或 `system`）
+    pub actor: String,
+    pub created_at: String,
+}
+
+impl TransactionEvent {
+    /// 创建待写入的记录，`seq` 由数据库分配
+    pub fn new(
+        transaction_id: String,
+        event_type: TransactionEventType,
+        from_status: Option<TransactionStatus>,
+        to_status: TransactionStatus,
+        actor: String,
+    ) -> Self {
+        Self {
+            seq: 0,
+            transaction_id,
+            event_type: event_type.as_str().to_string(),
+            from_status,
+            to_status,
+            response_code: None,
+            details: None,
+            actor,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn with_response_code(mut self, response_code: Option<String>) -> Self {
+        self.response_code = response_code;
+        self
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+}
+
+/// 交易状态历史事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionEventType {
+    /// 交易已处理（含结果未知）
+    Processed,
+    /// 后续交易改变了原交易的状态
+    LifecycleChanged,
+    /// 预授权过期
+    Expired,
+    /// 已加入冲正队列
+    ReversalQueued,
+    /// 冲正尝试失败，等待重试
+    ReversalAttemptFailed,
+    /// 冲正成功
+    Reversed,
+    /// 冲正重试耗尽，需人工处理
+    ReversalFailed,
+}
+
+impl TransactionEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionEventType::Processed => "PROCESSED",
+            TransactionEventType::LifecycleChanged => "LIFECYCLE_CHANGED",
+            TransactionEventType::Expired => "EXPIRED",
+            TransactionEventType::ReversalQueued => "REVERSAL_QUEUED",
+            TransactionEventType::ReversalAttemptFailed => "REVERSAL_ATTEMPT_FAILED",
+            TransactionEventType::Reversed => "REVERSED",
+            TransactionEventType::ReversalFailed => "REVERSAL_FAILED",
+        }
+    }
+}
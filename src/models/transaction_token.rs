@@ -18,6 +18,30 @@ pub struct TransactionTokenClaims {
     pub max_amount: i64,          // 最大交易金额（分）
     pub nonce: String,            // 随机数，防重放
     pub token_type: String,       // 令牌类型："transaction"
+
+    // 签发依据的健康检查结果
+    #[serde(default)]
+    pub health_checked_at: String,
+    #[serde(default)]
+    pub root_status: bool,
+    #[serde(default)]
+    pub bootloader_status: bool,
+    #[serde(default)]
+    pub system_integrity: bool,
+    #[serde(default)]
+    pub app_integrity: bool,
+    #[serde(default)]
+    pub tee_status: bool,
+    /// 鉴证时绑定的金额和币种摘要，见 `transaction_binding_hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txn_hash: Option<String>,
+}
+
+/// 交易金额和币种的绑定摘要，处理交易时须与令牌中的 `txn_hash` 一致
+pub fn transaction_binding_hash(amount: i64, currency: &str) -> String {
+    crate::security::crypto::sha256_hash_hex(
+        format!("{}:{}", amount, currency.trim().to_uppercase()).as_bytes(),
+    )
 }
 
 /// 交易令牌响应
@@ -5,6 +5,9 @@ use sqlx::FromRow;
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "TEXT")]
 pub enum UserRole {
+    /// 超级管理员，可跨租户操作
+    #[serde(rename = "SUPER_ADMIN")]
+    SuperAdmin,
     #[serde(rename = "ADMIN")]
     Admin,
     #[serde(rename = "OPERATOR")]
@@ -31,6 +31,7 @@ pub enum VersionStatus {
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct SdkVersion {
     pub id: String,
+    pub tenant_id: String,
     pub version: String,
     pub update_type: UpdateType,
     pub status: VersionStatus,
@@ -58,6 +59,7 @@ impl SdkVersion {
         let now = chrono::Utc::now().to_rfc3339();
         Self {
             id: uuid::Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
             version,
             update_type,
             status: VersionStatus::Draft,
@@ -0,0 +1,252 @@
+use std::str::FromStr;
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// 默认最大投递次数，超过后进入死信状态
+pub const DEFAULT_WEBHOOK_MAX_ATTEMPTS: i64 = 8;
+
+/// 商户Webhook订阅
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WebhookSubscription {
+    pub id: String,
+    /// 所属租户
+    pub tenant_id: String,
+    pub merchant_id: String,
+    /// 接收事件的HTTPS地址
+    pub url: String,
+    /// 签名密钥，仅在创建时返回给调用方
+    pub secret: String,
+    /// 订阅的事件类型（JSON数组）
+    pub event_types: String,
+    pub active: bool,
+    pub created_by: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl WebhookSubscription {
+    pub fn new(
+        merchant_id: String,
+        url: String,
+        secret: String,
+        event_types: &[WebhookEventType],
+        created_by: String,
+    ) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
+            merchant_id,
+            url,
+            secret,
+            event_types: WebhookEventType::to_json(event_types),
+            active: true,
+            created_by,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    /// 订阅的事件类型列表
+    pub fn event_type_list(&self) -> Vec<WebhookEventType> {
+        serde_json::from_str(&self.event_types).unwrap_or_default()
+    }
+
+    /// 是否订阅了指定事件
+    pub fn accepts(&self, event_type: WebhookEventType) -> bool {
+        self.active && self.event_type_list().contains(&event_type)
+    }
+}
+
+/// Webhook事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEventType {
+    /// 设备状态变更
+    #[serde(rename = "device.status_changed")]
+    DeviceStatusChanged,
+    /// 交易处理完成（批准或拒绝）
+    #[serde(rename = "transaction.completed")]
+    TransactionCompleted,
+    /// 检测到设备威胁
+    #[serde(rename = "threat.detected")]
+    ThreatDetected,
+}
+
+impl WebhookEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::DeviceStatusChanged => "device.status_changed",
+            WebhookEventType::TransactionCompleted => "transaction.completed",
+            WebhookEventType::ThreatDetected => "threat.detected",
+        }
+    }
+
+    fn to_json(event_types: &[WebhookEventType]) -> String {
+        serde_json::to_string(event_types).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+impl FromStr for WebhookEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "device.status_changed" => Ok(WebhookEventType::DeviceStatusChanged),
+            "transaction.completed" => Ok(WebhookEventType::TransactionCompleted),
+            "threat.detected" => Ok(WebhookEventType::ThreatDetected),
+            _ => Err(format!("Unknown webhook event type: {}", s)),
+        }
+    }
+}
+
+/// 单次事件投递
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub tenant_id: String,
+    pub subscription_id: String,
+    /// 事件ID，同一事件投递到多个订阅时相同，接收方据此去重
+    pub event_id: String,
+    pub event_type: String,
+    /// 请求体（JSON）
+    pub payload: String,
+    pub status: String,
+    /// 已尝试次数
+    pub attempts: i64,
+    pub max_attempts: i64,
+    /// 下次尝试时间
+    pub next_attempt_at: String,
+    pub last_attempt_at: Option<String>,
+    /// 最近一次响应的HTTP状态码
+    pub response_status: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+impl WebhookDelivery {
+    pub fn new(
+        subscription: &WebhookSubscription,
+        event_id: String,
+        event_type: WebhookEventType,
+        payload: String,
+        max_attempts: i64,
+    ) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tenant_id: subscription.tenant_id.clone(),
+            subscription_id: subscription.id.clone(),
+            event_id,
+            event_type: event_type.as_str().to_string(),
+            payload,
+            status: WebhookDeliveryStatus::Pending.as_str().to_string(),
+            attempts: 0,
+            max_attempts,
+            next_attempt_at: now.clone(),
+            last_attempt_at: None,
+            response_status: None,
+            last_error: None,
+            created_at: now,
+            delivered_at: None,
+        }
+    }
+
+    /// 第 `attempts` 次失败后的重试时间：`base * 2^(attempts-1)`，不超过 `max_delay`
+    pub fn retry_at(attempts: i64, base: Duration, max_delay: Duration) -> String {
+        let exponent = (attempts - 1).clamp(0, 20) as u32;
+        let delay = base
+            .checked_mul(2i32.saturating_pow(exponent))
+            .unwrap_or(max_delay)
+            .min(max_delay);
+        (Utc::now() + delay).to_rfc3339()
+    }
+}
+
+/// 投递状态
+///
+/// PENDING → SUCCEEDED；失败时保持PENDING并按指数退避重试，超过最大次数后进入DEAD_LETTER
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Succeeded,
+    DeadLetter,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => "PENDING",
+            WebhookDeliveryStatus::Succeeded => "SUCCEEDED",
+            WebhookDeliveryStatus::DeadLetter => "DEAD_LETTER",
+        }
+    }
+}
+
+impl FromStr for WebhookDeliveryStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "PENDING" => Ok(WebhookDeliveryStatus::Pending),
+            "SUCCEEDED" => Ok(WebhookDeliveryStatus::Succeeded),
+            "DEAD_LETTER" => Ok(WebhookDeliveryStatus::DeadLetter),
+            _ => Err(format!("Unknown webhook delivery status: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type_roundtrip() {
+        for t in [
+            WebhookEventType::DeviceStatusChanged,
+            WebhookEventType::TransactionCompleted,
+            WebhookEventType::ThreatDetected,
+        ] {
+            assert_eq!(WebhookEventType::from_str(t.as_str()).ok(), Some(t));
+            assert_eq!(serde_json::to_value(t).unwrap(), t.as_str());
+        }
+        assert_eq!(WebhookEventType::from_str("unknown").ok(), None);
+    }
+
+    #[test]
+    fn test_subscription_accepts() {
+        let mut subscription = WebhookSubscription::new(
+            "m1".to_string(),
+            "https://example.com/hook".to_string(),
+            "secret".to_string(),
+            &[WebhookEventType::ThreatDetected],
+            "admin".to_string(),
+        );
+        assert!(subscription.accepts(WebhookEventType::ThreatDetected));
+        assert!(!subscription.accepts(WebhookEventType::TransactionCompleted));
+
+        subscription.active = false;
+        assert!(!subscription.accepts(WebhookEventType::ThreatDetected));
+    }
+
+    #[test]
+    fn test_retry_backoff_is_capped() {
+        let base = Duration::seconds(10);
+        let max = Duration::seconds(60);
+        let parse =
+            |s: String| chrono::DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc);
+
+        let now = Utc::now();
+        let first = parse(WebhookDelivery::retry_at(1, base, max)) - now;
+        let third = parse(WebhookDelivery::retry_at(3, base, max)) - now;
+        let tenth = parse(WebhookDelivery::retry_at(10, base, max)) - now;
+
+        assert!(first >= Duration::seconds(9) && first <= Duration::seconds(11));
+        assert!(third >= Duration::seconds(39) && third <= Duration::seconds(41));
+        assert!(tenth <= Duration::seconds(61));
+    }
+}
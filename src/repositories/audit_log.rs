@@ -1,30 +1,68 @@
-use sqlx::SqlitePool;
-use crate::models::{AuditLog, OperationResult};
+use crate::models::{AuditLog, OperationResult, TenantContext};
+use crate::repositories::{DbExecutor, TenantScope, UnitOfWork};
 use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 审计日志表查询列，用于动态拼接的查询；固定查询使用 `query_as!` 在编译期校验
+const AUDIT_LOG_COLUMNS: &str = r#"
+    id, tenant_id, operation, operator, device_id, result,
+    details, ip_address, user_agent, created_at
+"#;
 
 /// 审计日志Repository
 #[derive(Clone)]
 pub struct AuditLogRepository {
-    pool: SqlitePool,
+    db: DbExecutor,
+    scope: TenantScope,
 }
 
 impl AuditLogRepository {
-    /// 创建新的AuditLogRepository
+    /// 创建新的AuditLogRepository，绑定租户前不匹配任何租户的数据
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的AuditLogRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { db: self.db.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 返回绑定到工作单元的Repository，所有操作在该工作单元的事务中执行
+    pub fn in_unit_of_work(&self, uow: &UnitOfWork) -> Self {
+        Self { db: DbExecutor::UnitOfWork(uow.clone()), scope: self.scope.clone() }
+    }
+
+    /// 开启工作单元；已绑定工作单元时加入外层事务
+    pub async fn begin(&self) -> Result<UnitOfWork, AppError> {
+        self.db.begin().await
     }
 
     /// 创建审计日志
+    ///
+    /// 未绑定租户时（设备端调用、系统任务），日志归属于关联设备所在的租户
     pub async fn create(&self, log: &AuditLog) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        let scope_tenant = self.scope.tenant_id();
         sqlx::query!(
             r#"
             INSERT INTO audit_logs (
-                id, operation, operator, device_id, result,
+                id, tenant_id, operation, operator, device_id, result,
                 details, ip_address, user_agent, created_at
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (
+                ?, COALESCE(?, (SELECT tenant_id FROM devices WHERE id = ?), ?),
+                ?, ?, ?, ?, ?, ?, ?, ?
+            )
             "#,
             log.id,
+            scope_tenant,
+            log.device_id,
+            log.tenant_id,
             log.operation,
             log.operator,
             log.device_id,
@@ -34,7 +72,7 @@ impl AuditLogRepository {
             log.user_agent,
             log.created_at,
         )
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -53,14 +91,9 @@ impl AuditLogRepository {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<AuditLog>, AppError> {
-        let mut query = String::from(
-            r#"
-            SELECT 
-                id, operation, operator, device_id, result,
-                details, ip_address, user_agent, created_at
-            FROM audit_logs
-            WHERE 1=1
-            "#,
+        let mut query = format!(
+            "SELECT {} FROM audit_logs WHERE tenant_id = COALESCE(?, tenant_id)",
+            AUDIT_LOG_COLUMNS
         );
 
         if let Some(dev_id) = device_id {
@@ -90,8 +123,10 @@ impl AuditLogRepository {
         query.push_str(" ORDER BY created_at DESC");
         query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
 
+        let mut conn = self.db.acquire().await?;
         let logs = sqlx::query_as::<_, AuditLog>(&query)
-            .fetch_all(&self.pool)
+            .bind(self.scope.filter())
+            .fetch_all(&mut *conn)
             .await?;
 
         Ok(logs)
@@ -107,7 +142,9 @@ impl AuditLogRepository {
         start_time: Option<&str>,
         end_time: Option<&str>,
     ) -> Result<i64, AppError> {
-        let mut query = String::from("SELECT COUNT(*) as count FROM audit_logs WHERE 1=1");
+        let mut query = String::from(
+            "SELECT COUNT(*) as count FROM audit_logs WHERE tenant_id = COALESCE(?, tenant_id)",
+        );
 
         if let Some(dev_id) = device_id {
             query.push_str(&format!(" AND device_id = '{}'", dev_id));
@@ -133,8 +170,10 @@ impl AuditLogRepository {
             query.push_str(&format!(" AND created_at <= '{}'", end));
         }
 
+        let mut conn = self.db.acquire().await?;
         let result = sqlx::query_scalar::<_, i64>(&query)
-            .fetch_one(&self.pool)
+            .bind(self.scope.filter())
+            .fetch_one(&mut *conn)
             .await?;
 
         Ok(result)
@@ -142,19 +181,22 @@ impl AuditLogRepository {
 
     /// 根据ID查找审计日志
     pub async fn find_by_id(&self, id: &str) -> Result<Option<AuditLog>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let log = sqlx::query_as!(
             AuditLog,
             r#"
-            SELECT 
-                id, operation, operator, device_id,
+            SELECT
+                id, tenant_id, operation, operator, device_id,
                 result as "result: _",
                 details, ip_address, user_agent, created_at
             FROM audit_logs
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
-            id
+            id,
+            filter
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(log)
@@ -167,23 +209,26 @@ impl AuditLogRepository {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<AuditLog>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let logs = sqlx::query_as!(
             AuditLog,
             r#"
-            SELECT 
-                id, operation, operator, device_id,
+            SELECT
+                id, tenant_id, operation, operator, device_id,
                 result as "result: _",
                 details, ip_address, user_agent, created_at
             FROM audit_logs
-            WHERE device_id = ?
+            WHERE device_id = ? AND tenant_id = COALESCE(?, tenant_id)
             ORDER BY created_at DESC
             LIMIT ? OFFSET ?
             "#,
             device_id,
+            filter,
             limit,
             offset
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *conn)
         .await?;
 
         Ok(logs)
@@ -196,23 +241,26 @@ impl AuditLogRepository {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<AuditLog>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let logs = sqlx::query_as!(
             AuditLog,
             r#"
-            SELECT 
-                id, operation, operator, device_id,
+            SELECT
+                id, tenant_id, operation, operator, device_id,
                 result as "result: _",
                 details, ip_address, user_agent, created_at
             FROM audit_logs
-            WHERE operator = ?
+            WHERE operator = ? AND tenant_id = COALESCE(?, tenant_id)
             ORDER BY created_at DESC
             LIMIT ? OFFSET ?
             "#,
             operator,
+            filter,
             limit,
             offset
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *conn)
         .await?;
 
         Ok(logs)
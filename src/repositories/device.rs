@@ -1,30 +1,69 @@
-use crate::models::{Device, DeviceStatus};
+use crate::models::{Device, DeviceStatus, DeviceStatusHistory, DomainEvent, TenantContext};
+use crate::repositories::outbox::{append_event, device_tenant};
+use crate::repositories::{DbExecutor, TenantScope, UnitOfWork, DEVICE_TENANT_FILTER};
 use crate::utils::error::AppError;
-use sqlx::SqlitePool;
+use sqlx::{Connection, SqlitePool};
+use std::collections::{HashMap, HashSet};
+
+/// 设备表查询列，用于动态拼接的查询；固定查询使用 `query_as!` 在编译期校验
+const DEVICE_COLUMNS: &str = r#"
+    id, tenant_id, imei, model, os_version, tee_type, device_mode, public_key,
+    status, merchant_id, merchant_name, store_id, terminal_id,
+    security_score, current_ksn, ipek_injected_at,
+    key_remaining_count, key_total_count,
+    registered_at, approved_at, approved_by, last_active_at, updated_at,
+    nfc_present
+"#;
 
 /// 设备Repository
 #[derive(Clone)]
 pub struct DeviceRepository {
-    pool: SqlitePool,
+    db: DbExecutor,
+    scope: TenantScope,
 }
 
 impl DeviceRepository {
-    /// 创建新的DeviceRepository
+    /// 创建新的DeviceRepository，绑定租户前不匹配任何租户的数据
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::default() }
     }
 
-    /// 创建设备
+    /// 创建不做租户过滤的DeviceRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { db: self.db.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 返回绑定到工作单元的Repository，所有操作在该工作单元的事务中执行
+    pub fn in_unit_of_work(&self, uow: &UnitOfWork) -> Self {
+        Self { db: DbExecutor::UnitOfWork(uow.clone()), scope: self.scope.clone() }
+    }
+
+    /// 开启工作单元；已绑定工作单元时加入外层事务
+    pub async fn begin(&self) -> Result<UnitOfWork, AppError> {
+        self.db.begin().await
+    }
+
+    /// 创建设备，并写入初始状态历史和 `DeviceRegistered` 事件
     pub async fn create(&self, device: &Device) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let tenant_id = self.scope.owner(&device.tenant_id);
         sqlx::query!(
             r#"
             INSERT INTO devices (
-                id, imei, model, os_version, tee_type, device_mode, public_key,
+                id, tenant_id, imei, model, os_version, tee_type, device_mode, public_key,
                 status, security_score, current_ksn, registered_at, nfc_present
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             device.id,
+            tenant_id,
             device.imei,
             device.model,
             device.os_version,
@@ -37,46 +76,73 @@ impl DeviceRepository {
             device.registered_at,
             device.nfc_present,
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        let history = DeviceStatusHistory {
+            id: uuid::Uuid::new_v4().to_string(),
+            device_id: device.id.clone(),
+            from_status: None,
+            to_status: device.status.clone(),
+            changed_by: "system".to_string(),
+            reason: Some("Device registered".to_string()),
+            created_at: device.registered_at.clone(),
+        };
+        insert_history(&mut tx, &history).await?;
+
+        let event = DomainEvent::DeviceRegistered {
+            device_id: device.id.clone(),
+            imei: device.imei.clone(),
+            model: device.model.clone(),
+            tee_type: device.tee_type.clone(),
+        };
+        append_event(&mut tx, self.scope.owner(&device.tenant_id), &event).await?;
+
+        tx.commit().await?;
+
         Ok(())
     }
 
     /// 根据ID查找设备
     pub async fn find_by_id(&self, id: &str) -> Result<Option<Device>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let device = sqlx::query_as!(
             Device,
             r#"
-            SELECT 
-                id, imei, model, os_version, tee_type, device_mode, public_key,
-                status, merchant_id, merchant_name, 
+            SELECT
+                id, tenant_id, imei, model, os_version, tee_type, device_mode, public_key,
+                status, merchant_id, merchant_name, store_id, terminal_id,
                 security_score as "security_score: i32",
-                current_ksn, ipek_injected_at, 
-                key_remaining_count as "key_remaining_count: i32", 
+                current_ksn, ipek_injected_at,
+                key_remaining_count as "key_remaining_count: i32",
                 key_total_count as "key_total_count: i32",
                 registered_at, approved_at, approved_by, last_active_at, updated_at,
                 nfc_present
             FROM devices
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
-            id
+            id,
+            filter
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(device)
     }
 
     /// 检查IMEI是否存在
+    ///
+    /// IMEI全局唯一，不按租户过滤
     pub async fn exists_by_imei(&self, imei: &str) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
         let result = sqlx::query!(
             r#"
             SELECT COUNT(*) as count FROM devices WHERE imei = ?
             "#,
             imei
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(result.count > 0)
@@ -84,24 +150,27 @@ impl DeviceRepository {
 
     /// 根据IMEI查找设备
     pub async fn find_by_imei(&self, imei: &str) -> Result<Option<Device>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let device = sqlx::query_as!(
             Device,
             r#"
-            SELECT 
-                id, imei, model, os_version, tee_type, device_mode, public_key,
-                status, merchant_id, merchant_name, 
+            SELECT
+                id, tenant_id, imei, model, os_version, tee_type, device_mode, public_key,
+                status, merchant_id, merchant_name, store_id, terminal_id,
                 security_score as "security_score: i32",
-                current_ksn, ipek_injected_at, 
-                key_remaining_count as "key_remaining_count: i32", 
+                current_ksn, ipek_injected_at,
+                key_remaining_count as "key_remaining_count: i32",
                 key_total_count as "key_total_count: i32",
                 registered_at, approved_at, approved_by, last_active_at, updated_at,
                 nfc_present
             FROM devices
-            WHERE imei = ?
+            WHERE imei = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
-            imei
+            imei,
+            filter
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(device)
@@ -115,21 +184,9 @@ impl DeviceRepository {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<Device>, AppError> {
-        let mut query = String::from(
-            r#"
-            SELECT 
-                id, imei, model, os_version, 
-                tee_type, device_mode, public_key,
-                status, merchant_id, merchant_name,
-                security_score,
-                current_ksn, ipek_injected_at,
-                key_remaining_count, key_total_count,
-                registered_at, approved_at, approved_by,
-                last_active_at, updated_at,
-                nfc_present
-            FROM devices
-            WHERE 1=1
-            "#,
+        let mut query = format!(
+            "SELECT {} FROM devices WHERE tenant_id = COALESCE(?, tenant_id)",
+            DEVICE_COLUMNS
         );
 
         // 添加状态筛选
@@ -149,7 +206,11 @@ impl DeviceRepository {
         query.push_str(" ORDER BY registered_at DESC");
         query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
 
-        let devices = sqlx::query_as::<_, Device>(&query).fetch_all(&self.pool).await?;
+        let mut conn = self.db.acquire().await?;
+        let devices = sqlx::query_as::<_, Device>(&query)
+            .bind(self.scope.filter())
+            .fetch_all(&mut *conn)
+            .await?;
 
         Ok(devices)
     }
@@ -160,7 +221,9 @@ impl DeviceRepository {
         status: Option<DeviceStatus>,
         search: Option<&str>,
     ) -> Result<i64, AppError> {
-        let mut query = String::from("SELECT COUNT(*) as count FROM devices WHERE 1=1");
+        let mut query = String::from(
+            "SELECT COUNT(*) as count FROM devices WHERE tenant_id = COALESCE(?, tenant_id)",
+        );
 
         if let Some(s) = status {
             query.push_str(&format!(" AND status = '{}'", s.as_str()));
@@ -173,69 +236,125 @@ impl DeviceRepository {
             ));
         }
 
-        let result = sqlx::query_scalar::<_, i64>(&query).fetch_one(&self.pool).await?;
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query_scalar::<_, i64>(&query)
+            .bind(self.scope.filter())
+            .fetch_one(&mut *conn)
+            .await?;
 
         Ok(result)
     }
 
-    /// 更新设备状态
+    /// 迁移设备状态
+    ///
+    /// 按状态机校验迁移合法性，以当前状态为条件更新（防止并发迁移），
+    /// 并在同一事务中写入状态历史和 `DeviceStatusChanged` 事件。审批通过时记录审批人，进入吊销/退役/替换状态时清零密钥。
     pub async fn update_status(
         &self,
         id: &str,
-        status: DeviceStatus,
-        approved_by: Option<&str>,
-    ) -> Result<(), AppError> {
-        let now = chrono::Utc::now().to_rfc3339();
+        from: DeviceStatus,
+        to: DeviceStatus,
+        changed_by: &str,
+        reason: Option<&str>,
+    ) -> Result<DeviceStatusHistory, AppError> {
+        if !from.can_transition_to(to) {
+            return Err(AppError::InvalidDeviceStatus);
+        }
 
-        let status_str = status.as_str();
-
-        let status_upper = status_str.to_uppercase();
-
-        if status == DeviceStatus::Active {
-            // 审批通过
-            sqlx::query!(
-                r#"
-                UPDATE devices
-                SET status = ?, approved_at = ?, approved_by = ?
-                WHERE id = ?
-                "#,
-                status_upper,
-                now,
-                approved_by,
-                id
-            )
-            .execute(&self.pool)
-            .await?;
-        } else {
-            // 其他状态变更
-            sqlx::query!(
-                r#"
-                UPDATE devices
-                SET status = ?
-                WHERE id = ?
-                "#,
-                status_upper,
-                id
-            )
-            .execute(&self.pool)
+        let history = DeviceStatusHistory::new(
+            id.to_string(),
+            Some(from),
+            to,
+            changed_by.to_string(),
+            reason.map(|r| r.to_string()),
+        );
+        let approving = from == DeviceStatus::Pending && to == DeviceStatus::Active;
+
+        let mut sql = String::from("UPDATE devices SET status = ?, updated_at = ?");
+        if approving {
+            sql.push_str(", approved_at = ?, approved_by = ?");
+        }
+        if to.requires_key_zeroization() {
+            sql.push_str(", current_ksn = '', ipek_injected_at = NULL, key_remaining_count = 0");
+        }
+        sql.push_str(" WHERE id = ? AND status = ? AND tenant_id = COALESCE(?, tenant_id)");
+
+        let mut query = sqlx::query(&sql).bind(to.as_str()).bind(&history.created_at);
+        if approving {
+            query = query.bind(&history.created_at).bind(changed_by);
+        }
+
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let result = query
+            .bind(id)
+            .bind(from.as_str())
+            .bind(self.scope.filter())
+            .execute(&mut *tx)
             .await?;
+
+        // 设备不存在或状态已被并发修改
+        if result.rows_affected() == 0 {
+            return Err(AppError::InvalidDeviceStatus);
         }
 
-        Ok(())
+        insert_history(&mut tx, &history).await?;
+
+        let event = DomainEvent::DeviceStatusChanged {
+            device_id: id.to_string(),
+            from_status: history.from_status.clone(),
+            to_status: history.to_status.clone(),
+            changed_by: history.changed_by.clone(),
+            reason: history.reason.clone(),
+        };
+        let tenant_id = device_tenant(&mut tx, id).await?;
+        append_event(&mut tx, &tenant_id, &event).await?;
+
+        tx.commit().await?;
+
+        Ok(history)
+    }
+
+    /// 获取设备状态变更历史（按时间顺序）
+    pub async fn list_status_history(
+        &self,
+        device_id: &str,
+    ) -> Result<Vec<DeviceStatusHistory>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let history = sqlx::query_as::<_, DeviceStatusHistory>(&format!(
+            r#"
+            SELECT id, device_id, from_status, to_status, changed_by, reason, created_at
+            FROM device_status_history
+            WHERE device_id = ? AND {}
+            ORDER BY created_at ASC, rowid ASC
+            "#,
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(device_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(history)
     }
 
     /// 更新安全评分
     pub async fn update_security_score(&self, id: &str, score: i32) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         sqlx::query!(
             r#"
             UPDATE devices
             SET security_score = ?
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
             score,
-            id
+            id,
+            filter
         )
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -243,22 +362,27 @@ impl DeviceRepository {
 
     /// 更新KSN
     pub async fn update_ksn(&self, id: &str, ksn: &str) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         sqlx::query!(
             r#"
             UPDATE devices
             SET current_ksn = ?
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
             ksn,
-            id
+            id,
+            filter
         )
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
     /// 更新密钥信息
+    ///
+    /// 传入注入时间表示完成了IPEK注入，同时写入 `KeyInjected` 事件
     pub async fn update_key_info(
         &self,
         id: &str,
@@ -268,7 +392,11 @@ impl DeviceRepository {
         key_total_count: Option<i32>,
     ) -> Result<(), AppError> {
         let now = chrono::Utc::now().to_rfc3339();
-        sqlx::query!(
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let filter = self.scope.filter();
+        let result = sqlx::query!(
             r#"
             UPDATE devices
             SET current_ksn = ?,
@@ -276,69 +404,290 @@ impl DeviceRepository {
                 key_remaining_count = COALESCE(?, key_remaining_count),
                 key_total_count = COALESCE(?, key_total_count),
                 updated_at = ?
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
             ksn,
             injected_at,
             key_remaining_count,
             key_total_count,
             now,
-            id
+            id,
+            filter
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        if injected_at.is_some() && result.rows_affected() > 0 {
+            let event = DomainEvent::KeyInjected {
+                device_id: id.to_string(),
+                ksn: ksn.to_string(),
+                key_total_count,
+            };
+            let tenant_id = device_tenant(&mut tx, id).await?;
+            append_event(&mut tx, &tenant_id, &event).await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 
     /// 递减密钥使用次数
     pub async fn decrement_key_count(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         sqlx::query!(
             r#"
             UPDATE devices
             SET key_remaining_count = key_remaining_count - 1
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
-            id
+            id,
+            filter
         )
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
-    /// 获取设备统计信息
-    pub async fn get_statistics(&self) -> Result<DeviceStatistics, AppError> {
-        let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM devices")
-            .fetch_one(&self.pool)
-            .await?;
+    /// 分配设备到商户门店
+    pub async fn assign_store(
+        &self,
+        id: &str,
+        merchant_id: &str,
+        merchant_name: &str,
+        store_id: &str,
+        terminal_id: &str,
+    ) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE devices
+            SET merchant_id = ?, merchant_name = ?, store_id = ?, terminal_id = ?, updated_at = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(merchant_id)
+        .bind(merchant_name)
+        .bind(store_id)
+        .bind(terminal_id)
+        .bind(now)
+        .bind(id)
+        .bind(self.scope.filter())
+        .execute(&mut *conn)
+        .await?;
 
-        let active =
-            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM devices WHERE status = 'ACTIVE'")
-                .fetch_one(&self.pool)
-                .await?;
+        Ok(())
+    }
 
-        let pending =
-            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM devices WHERE status = 'PENDING'")
-                .fetch_one(&self.pool)
-                .await?;
+    /// 解除设备的门店分配
+    pub async fn clear_store(&self, id: &str) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE devices
+            SET merchant_id = NULL, merchant_name = NULL, store_id = NULL, terminal_id = NULL,
+                updated_at = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(now)
+        .bind(id)
+        .bind(self.scope.filter())
+        .execute(&mut *conn)
+        .await?;
 
-        let suspended =
-            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM devices WHERE status = 'SUSPENDED'")
-                .fetch_one(&self.pool)
-                .await?;
+        Ok(())
+    }
 
-        let revoked =
-            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM devices WHERE status = 'REVOKED'")
-                .fetch_one(&self.pool)
-                .await?;
+    /// 列出门店下的设备
+    pub async fn list_by_store(&self, store_id: &str) -> Result<Vec<Device>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
+        let devices = sqlx::query_as!(
+            Device,
+            r#"
+            SELECT
+                id, tenant_id, imei, model, os_version, tee_type, device_mode, public_key,
+                status, merchant_id, merchant_name, store_id, terminal_id,
+                security_score as "security_score: i32",
+                current_ksn, ipek_injected_at,
+                key_remaining_count as "key_remaining_count: i32",
+                key_total_count as "key_total_count: i32",
+                registered_at, approved_at, approved_by, last_active_at, updated_at,
+                nfc_present
+            FROM devices
+            WHERE store_id = ? AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY terminal_id
+            "#,
+            store_id,
+            filter
+        )
+        .fetch_all(&mut *conn)
+        .await?;
 
-        let avg_score = sqlx::query_scalar::<_, f64>(
-            "SELECT AVG(security_score) FROM devices WHERE status = 'ACTIVE'",
+        Ok(devices)
+    }
+
+    /// 列出租户内全部设备
+    pub async fn list_all(&self) -> Result<Vec<Device>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
+        let devices = sqlx::query_as!(
+            Device,
+            r#"
+            SELECT
+                id, tenant_id, imei, model, os_version, tee_type, device_mode, public_key,
+                status, merchant_id, merchant_name, store_id, terminal_id,
+                security_score as "security_score: i32",
+                current_ksn, ipek_injected_at,
+                key_remaining_count as "key_remaining_count: i32",
+                key_total_count as "key_total_count: i32",
+                registered_at, approved_at, approved_by, last_active_at, updated_at,
+                nfc_present
+            FROM devices
+            WHERE tenant_id = COALESCE(?, tenant_id)
+            ORDER BY registered_at
+            "#,
+            filter
         )
-        .fetch_one(&self.pool)
+        .fetch_all(&mut *conn)
         .await?;
 
+        Ok(devices)
+    }
+
+    /// 占用设备签名随机数，返回是否占用成功（随机数已被使用时返回false）
+    pub async fn try_use_auth_nonce(
+        &self,
+        device_id: &str,
+        nonce: &str,
+        expires_at: &str,
+    ) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO device_auth_nonces (device_id, nonce, expires_at)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(device_id)
+        .bind(nonce)
+        .bind(expires_at)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 删除设备已过期的签名随机数，返回删除数量
+    pub async fn delete_expired_auth_nonces(
+        &self,
+        device_id: &str,
+        now: &str,
+    ) -> Result<u64, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result =
+            sqlx::query("DELETE FROM device_auth_nonces WHERE device_id = ? AND expires_at <= ?")
+                .bind(device_id)
+                .bind(now)
+                .execute(&mut *conn)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 获取设备标签
+    pub async fn list_tags(&self, device_id: &str) -> Result<Vec<String>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let tags = sqlx::query_scalar::<_, String>(&format!(
+            "SELECT tag FROM device_tags WHERE device_id = ? AND {} ORDER BY tag",
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(device_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(tags)
+    }
+
+    /// 替换设备标签
+    pub async fn set_tags(&self, device_id: &str, tags: &[String]) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(&format!(
+            "DELETE FROM device_tags WHERE device_id = ? AND {}",
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(device_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .execute(&mut *tx)
+        .await?;
+
+        for tag in tags {
+            sqlx::query(
+                "INSERT OR IGNORE INTO device_tags (device_id, tag, created_at) VALUES (?, ?, ?)",
+            )
+            .bind(device_id)
+            .bind(tag)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 获取租户内全部设备的标签，按设备ID分组
+    pub async fn list_tag_map(&self) -> Result<HashMap<String, HashSet<String>>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let rows = sqlx::query_as::<_, (String, String)>(&format!(
+            "SELECT device_id, tag FROM device_tags WHERE {}",
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut map: HashMap<String, HashSet<String>> = HashMap::new();
+        for (device_id, tag) in rows {
+            map.entry(device_id).or_default().insert(tag);
+        }
+
+        Ok(map)
+    }
+
+    /// 获取设备统计信息
+    pub async fn get_statistics(&self) -> Result<DeviceStatistics, AppError> {
+        let total = self.count_where("1=1").await?;
+        let active = self.count_where("status = 'ACTIVE'").await?;
+        let pending = self.count_where("status = 'PENDING'").await?;
+        let suspended = self.count_where("status = 'SUSPENDED'").await?;
+        let revoked = self.count_where("status = 'REVOKED'").await?;
+
+        let mut conn = self.db.acquire().await?;
+        let avg_score = sqlx::query_scalar::<_, Option<f64>>(
+            r#"
+            SELECT AVG(security_score) FROM devices
+            WHERE status = 'ACTIVE' AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(self.scope.filter())
+        .fetch_one(&mut *conn)
+        .await?
+        .unwrap_or(0.0);
+
         Ok(DeviceStatistics {
             total,
             active,
@@ -348,6 +697,44 @@ impl DeviceRepository {
             average_security_score: avg_score,
         })
     }
+
+    async fn count_where(&self, condition: &str) -> Result<i64, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let count = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) FROM devices WHERE {} AND tenant_id = COALESCE(?, tenant_id)",
+            condition
+        ))
+        .bind(self.scope.filter())
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(count)
+    }
+}
+
+async fn insert_history(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    history: &DeviceStatusHistory,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO device_status_history (
+            id, device_id, from_status, to_status, changed_by, reason, created_at
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&history.id)
+    .bind(&history.device_id)
+    .bind(&history.from_status)
+    .bind(&history.to_status)
+    .bind(&history.changed_by)
+    .bind(&history.reason)
+    .bind(&history.created_at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
 }
 
 /// 设备统计信息
@@ -0,0 +1,295 @@
+use crate::models::{CommandStatus, DeviceCommand, TenantContext};
+use crate::repositories::TenantScope;
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 设备指令Repository
+///
+/// 状态更新均以当前状态为条件执行，返回是否更新成功，避免并发投递/回执相互覆盖
+#[derive(Clone)]
+pub struct DeviceCommandRepository {
+    pool: SqlitePool,
+    scope: TenantScope,
+}
+
+impl DeviceCommandRepository {
+    /// 创建新的DeviceCommandRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的DeviceCommandRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 创建指令，未绑定租户时归属于目标设备的租户
+    pub async fn create(&self, command: &DeviceCommand) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO device_commands (
+                id, tenant_id, device_id, command_type, payload, status, attempts,
+                max_attempts, timeout_seconds, created_by, created_at
+            )
+            VALUES (
+                ?, COALESCE(?, (SELECT tenant_id FROM devices WHERE id = ?), ?),
+                ?, ?, ?, ?, ?, ?, ?, ?, ?
+            )
+            "#,
+        )
+        .bind(&command.id)
+        .bind(self.scope.tenant_id())
+        .bind(&command.device_id)
+        .bind(&command.tenant_id)
+        .bind(&command.device_id)
+        .bind(&command.command_type)
+        .bind(&command.payload)
+        .bind(&command.status)
+        .bind(command.attempts)
+        .bind(command.max_attempts)
+        .bind(command.timeout_seconds)
+        .bind(&command.created_by)
+        .bind(&command.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找指令
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<DeviceCommand>, AppError> {
+        let command = sqlx::query_as::<_, DeviceCommand>(
+            "SELECT * FROM device_commands WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(command)
+    }
+
+    /// 列出设备的指令（按创建时间倒序）
+    pub async fn list_by_device(
+        &self,
+        device_id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DeviceCommand>, AppError> {
+        let commands = sqlx::query_as::<_, DeviceCommand>(
+            r#"
+            SELECT * FROM device_commands
+            WHERE device_id = ? AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(device_id)
+        .bind(self.scope.filter())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(commands)
+    }
+
+    /// 统计设备的指令数量
+    pub async fn count_by_device(&self, device_id: &str) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM device_commands
+            WHERE device_id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(device_id)
+        .bind(self.scope.filter())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 列出设备待投递的指令（按创建时间顺序）
+    pub async fn list_pending(&self, device_id: &str) -> Result<Vec<DeviceCommand>, AppError> {
+        let commands = sqlx::query_as::<_, DeviceCommand>(
+            r#"
+            SELECT * FROM device_commands
+            WHERE device_id = ? AND status = ? AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY created_at ASC, rowid ASC
+            "#,
+        )
+        .bind(device_id)
+        .bind(CommandStatus::Pending.as_str())
+        .bind(self.scope.filter())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(commands)
+    }
+
+    /// 标记指令已投递
+    pub async fn mark_delivered(&self, id: &str, deadline_at: &str) -> Result<bool, AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r#"
+            UPDATE device_commands
+            SET status = ?, attempts = attempts + 1, delivered_at = ?, deadline_at = ?
+            WHERE id = ? AND status = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(CommandStatus::Delivered.as_str())
+        .bind(now)
+        .bind(deadline_at)
+        .bind(id)
+        .bind(CommandStatus::Pending.as_str())
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 标记设备已确认收到指令
+    pub async fn mark_acknowledged(&self, id: &str) -> Result<bool, AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r#"
+            UPDATE device_commands
+            SET status = ?, acknowledged_at = ?
+            WHERE id = ? AND status = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(CommandStatus::Acknowledged.as_str())
+        .bind(now)
+        .bind(id)
+        .bind(CommandStatus::Delivered.as_str())
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 记录执行结果
+    pub async fn complete(
+        &self,
+        id: &str,
+        status: CommandStatus,
+        result: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<bool, AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let updated = sqlx::query(
+            r#"
+            UPDATE device_commands
+            SET status = ?, result = ?, error = ?, completed_at = ?, deadline_at = NULL
+            WHERE id = ? AND status IN (?, ?) AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(status.as_str())
+        .bind(result)
+        .bind(error)
+        .bind(now)
+        .bind(id)
+        .bind(CommandStatus::Delivered.as_str())
+        .bind(CommandStatus::Acknowledged.as_str())
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(updated.rows_affected() > 0)
+    }
+
+    /// 取消未完成的指令
+    pub async fn cancel(&self, id: &str) -> Result<bool, AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r#"
+            UPDATE device_commands
+            SET status = ?, completed_at = ?, deadline_at = NULL
+            WHERE id = ? AND status IN (?, ?, ?) AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(CommandStatus::Cancelled.as_str())
+        .bind(now)
+        .bind(id)
+        .bind(CommandStatus::Pending.as_str())
+        .bind(CommandStatus::Delivered.as_str())
+        .bind(CommandStatus::Acknowledged.as_str())
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 列出回执已超时的指令
+    pub async fn list_expired(&self, now: &str) -> Result<Vec<DeviceCommand>, AppError> {
+        let commands = sqlx::query_as::<_, DeviceCommand>(
+            r#"
+            SELECT * FROM device_commands
+            WHERE status IN (?, ?) AND deadline_at < ? AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY deadline_at
+            "#,
+        )
+        .bind(CommandStatus::Delivered.as_str())
+        .bind(CommandStatus::Acknowledged.as_str())
+        .bind(now)
+        .bind(self.scope.filter())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(commands)
+    }
+
+    /// 超时指令重新进入待投递状态
+    pub async fn requeue(&self, id: &str, now: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE device_commands
+            SET status = ?, deadline_at = NULL
+            WHERE id = ? AND status IN (?, ?) AND deadline_at < ?
+              AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(CommandStatus::Pending.as_str())
+        .bind(id)
+        .bind(CommandStatus::Delivered.as_str())
+        .bind(CommandStatus::Acknowledged.as_str())
+        .bind(now)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 标记指令超时失败
+    pub async fn mark_timed_out(&self, id: &str, now: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE device_commands
+            SET status = ?, error = 'Command timed out', completed_at = ?, deadline_at = NULL
+            WHERE id = ? AND status IN (?, ?) AND deadline_at < ?
+              AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(CommandStatus::TimedOut.as_str())
+        .bind(now)
+        .bind(id)
+        .bind(CommandStatus::Delivered.as_str())
+        .bind(CommandStatus::Acknowledged.as_str())
+        .bind(now)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
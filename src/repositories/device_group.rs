@@ -0,0 +1,213 @@
+use crate::models::{Device, DeviceGroup, TenantContext};
+use crate::repositories::TenantScope;
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 设备分组Repository
+#[derive(Clone)]
+pub struct DeviceGroupRepository {
+    pool: SqlitePool,
+    scope: TenantScope,
+}
+
+impl DeviceGroupRepository {
+    /// 创建新的DeviceGroupRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的DeviceGroupRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 创建分组
+    pub async fn create(&self, group: &DeviceGroup) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO device_groups (
+                id, tenant_id, name, description, group_type, rules,
+                created_by, created_at, updated_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&group.id)
+        .bind(self.scope.owner(&group.tenant_id))
+        .bind(&group.name)
+        .bind(&group.description)
+        .bind(&group.group_type)
+        .bind(&group.rules)
+        .bind(&group.created_by)
+        .bind(&group.created_at)
+        .bind(&group.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找分组
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<DeviceGroup>, AppError> {
+        let group = sqlx::query_as::<_, DeviceGroup>(
+            "SELECT * FROM device_groups WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(group)
+    }
+
+    /// 检查分组名称是否已存在
+    pub async fn exists_by_name(&self, name: &str, tenant_id: &str) -> Result<bool, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM device_groups WHERE name = ? AND tenant_id = ?",
+        )
+        .bind(name)
+        .bind(self.scope.owner(tenant_id))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// 列出分组
+    pub async fn list(&self, limit: i64, offset: i64) -> Result<Vec<DeviceGroup>, AppError> {
+        let groups = sqlx::query_as::<_, DeviceGroup>(
+            r#"
+            SELECT * FROM device_groups
+            WHERE tenant_id = COALESCE(?, tenant_id)
+            ORDER BY name
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(self.scope.filter())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(groups)
+    }
+
+    /// 统计分组数量
+    pub async fn count(&self) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM device_groups WHERE tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(self.scope.filter())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 更新分组
+    pub async fn update(&self, group: &DeviceGroup) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE device_groups
+            SET name = ?, description = ?, rules = ?, updated_at = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(&group.name)
+        .bind(&group.description)
+        .bind(&group.rules)
+        .bind(&group.updated_at)
+        .bind(&group.id)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 删除分组
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        sqlx::query(
+            "DELETE FROM device_groups WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 添加静态分组成员，已存在的成员忽略
+    pub async fn add_members(
+        &self,
+        group_id: &str,
+        device_ids: &[String],
+        added_by: &str,
+    ) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+
+        for device_id in device_ids {
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO device_group_members (group_id, device_id, added_by, added_at)
+                VALUES (?, ?, ?, ?)
+                "#,
+            )
+            .bind(group_id)
+            .bind(device_id)
+            .bind(added_by)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 移除静态分组成员
+    pub async fn remove_member(&self, group_id: &str, device_id: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM device_group_members
+            WHERE group_id = ? AND device_id = ?
+              AND group_id IN (
+                  SELECT id FROM device_groups WHERE tenant_id = COALESCE(?, tenant_id)
+              )
+            "#,
+        )
+        .bind(group_id)
+        .bind(device_id)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 列出静态分组的成员设备
+    pub async fn list_member_devices(&self, group_id: &str) -> Result<Vec<Device>, AppError> {
+        let devices = sqlx::query_as::<_, Device>(
+            r#"
+            SELECT d.* FROM devices d
+            JOIN device_group_members m ON m.device_id = d.id
+            WHERE m.group_id = ? AND d.tenant_id = COALESCE(?, d.tenant_id)
+            ORDER BY m.added_at, d.id
+            "#,
+        )
+        .bind(group_id)
+        .bind(self.scope.filter())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+}
@@ -0,0 +1,258 @@
+use crate::models::{
+    DeviceImportJob, DevicePreapproval, ImportJobStatus, PreapprovalStatus, TenantContext,
+};
+use crate::repositories::{DbExecutor, TenantScope, UnitOfWork};
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 设备导入Repository（导入任务与预审批）
+#[derive(Clone)]
+pub struct DeviceImportRepository {
+    db: DbExecutor,
+    scope: TenantScope,
+}
+
+impl DeviceImportRepository {
+    /// 创建新的DeviceImportRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的DeviceImportRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { db: self.db.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 返回绑定到工作单元的Repository，所有操作在该工作单元的事务中执行
+    pub fn in_unit_of_work(&self, uow: &UnitOfWork) -> Self {
+        Self { db: DbExecutor::UnitOfWork(uow.clone()), scope: self.scope.clone() }
+    }
+
+    /// 创建导入任务
+    pub async fn create_job(&self, job: &DeviceImportJob) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO device_import_jobs (
+                id, tenant_id, status, format, total_rows, success_rows, failed_rows,
+                errors, created_by, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&job.id)
+        .bind(self.scope.owner(&job.tenant_id))
+        .bind(&job.status)
+        .bind(&job.format)
+        .bind(job.total_rows)
+        .bind(job.success_rows)
+        .bind(job.failed_rows)
+        .bind(&job.errors)
+        .bind(&job.created_by)
+        .bind(&job.created_at)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找导入任务
+    pub async fn find_job(&self, id: &str) -> Result<Option<DeviceImportJob>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let job = sqlx::query_as::<_, DeviceImportJob>(
+            "SELECT * FROM device_import_jobs WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// 列出导入任务（按创建时间倒序）
+    pub async fn list_jobs(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DeviceImportJob>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let jobs = sqlx::query_as::<_, DeviceImportJob>(
+            r#"
+            SELECT * FROM device_import_jobs
+            WHERE tenant_id = COALESCE(?, tenant_id)
+            ORDER BY created_at DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(self.scope.filter())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(jobs)
+    }
+
+    /// 统计导入任务数量
+    pub async fn count_jobs(&self) -> Result<i64, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM device_import_jobs WHERE tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(self.scope.filter())
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 标记任务开始执行
+    pub async fn mark_running(&self, id: &str, total_rows: i64) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE device_import_jobs
+            SET status = ?, total_rows = ?, started_at = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(ImportJobStatus::Running.as_str())
+        .bind(total_rows)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .bind(self.scope.filter())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 记录任务结果
+    pub async fn finish_job(
+        &self,
+        id: &str,
+        status: ImportJobStatus,
+        success_rows: i64,
+        failed_rows: i64,
+        errors: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE device_import_jobs
+            SET status = ?, success_rows = ?, failed_rows = ?, errors = ?, completed_at = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(status.as_str())
+        .bind(success_rows)
+        .bind(failed_rows)
+        .bind(errors)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .bind(self.scope.filter())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 创建预审批
+    pub async fn create_preapproval(
+        &self,
+        preapproval: &DevicePreapproval,
+    ) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO device_preapprovals (
+                id, tenant_id, job_id, imei, model, store_id, status, created_by, created_at,
+                enrollment_code_hash
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&preapproval.id)
+        .bind(self.scope.owner(&preapproval.tenant_id))
+        .bind(&preapproval.job_id)
+        .bind(&preapproval.imei)
+        .bind(&preapproval.model)
+        .bind(&preapproval.store_id)
+        .bind(&preapproval.status)
+        .bind(&preapproval.created_by)
+        .bind(&preapproval.created_at)
+        .bind(&preapproval.enrollment_code_hash)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 查找IMEI对应的待使用预审批
+    ///
+    /// IMEI全局唯一，不按租户过滤；设备注册时据此确定所属租户
+    pub async fn find_pending_preapproval(
+        &self,
+        imei: &str,
+    ) -> Result<Option<DevicePreapproval>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let preapproval = sqlx::query_as::<_, DevicePreapproval>(
+            "SELECT * FROM device_preapprovals WHERE imei = ? AND status = ?",
+        )
+        .bind(imei)
+        .bind(PreapprovalStatus::Pending.as_str())
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(preapproval)
+    }
+
+    /// 列出任务生成的预审批
+    pub async fn list_preapprovals(
+        &self,
+        job_id: &str,
+    ) -> Result<Vec<DevicePreapproval>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let preapprovals = sqlx::query_as::<_, DevicePreapproval>(
+            r#"
+            SELECT * FROM device_preapprovals
+            WHERE job_id = ? AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY created_at ASC, rowid ASC
+            "#,
+        )
+        .bind(job_id)
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(preapprovals)
+    }
+
+    /// 使用预审批，预审批已被使用时返回false
+    pub async fn consume_preapproval(&self, id: &str, device_id: &str) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE device_preapprovals
+            SET status = ?, device_id = ?, consumed_at = ?
+            WHERE id = ? AND status = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(PreapprovalStatus::Consumed.as_str())
+        .bind(device_id)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .bind(PreapprovalStatus::Pending.as_str())
+        .bind(self.scope.filter())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,19 +1,38 @@
 use crate::{
-    models::HealthCheck,
+    models::{HealthCheck, TenantContext},
+    repositories::{TenantScope, DEVICE_TENANT_FILTER},
     utils::error::AppError,
 };
 use sqlx::SqlitePool;
 
+/// 健康检查表查询列，用于动态拼接的查询；固定查询使用 `query_as!` 在编译期校验
+const HEALTH_CHECK_COLUMNS: &str = r#"
+    id, device_id, security_score, root_status, bootloader_status,
+    system_integrity, app_integrity, tee_status, recommended_action,
+    details, created_at
+"#;
+
 /// 健康检查Repository
 #[derive(Clone)]
 pub struct HealthCheckRepository {
     pool: SqlitePool,
+    scope: TenantScope,
 }
 
 impl HealthCheckRepository {
-    /// 创建新的健康检查Repository
+    /// 创建新的健康检查Repository，绑定租户前不匹配任何租户的数据
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的HealthCheckRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
     }
 
     /// 创建健康检查记录
@@ -47,12 +66,13 @@ impl HealthCheckRepository {
 
     /// 根据设备ID查找健康检查记录
     pub async fn find_by_device_id(&self, device_id: &str) -> Result<Vec<HealthCheck>, AppError> {
+        let filter = self.scope.filter();
         let records = sqlx::query_as!(
             HealthCheck,
             r#"
-            SELECT 
-                id, device_id, 
-                security_score as "security_score: i32", 
+            SELECT
+                id, device_id,
+                security_score as "security_score: i32",
                 root_status as "root_status: bool",
                 bootloader_status as "bootloader_status: bool",
                 system_integrity as "system_integrity: bool",
@@ -62,9 +82,12 @@ impl HealthCheckRepository {
                 details, created_at
             FROM health_checks
             WHERE device_id = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             ORDER BY created_at DESC
             "#,
-            device_id
+            device_id,
+            filter,
+            filter
         )
         .fetch_all(&self.pool)
         .await?;
@@ -81,50 +104,47 @@ impl HealthCheckRepository {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<HealthCheck>, AppError> {
-        let mut query = String::from(
-            r#"
-            SELECT 
-                id, device_id, security_score, root_status, bootloader_status,
-                system_integrity, app_integrity, tee_status, recommended_action,
-                details, created_at
-            FROM health_checks
-            WHERE device_id = ?
-            "#
+        let mut query = format!(
+            "SELECT {} FROM health_checks WHERE device_id = ? AND {}",
+            HEALTH_CHECK_COLUMNS, DEVICE_TENANT_FILTER
         );
 
-        let mut params: Vec<String> = vec![device_id.to_string()];
-
-        if let Some(start) = start_time {
+        if start_time.is_some() {
             query.push_str(" AND created_at >= ?");
-            params.push(start.to_string());
         }
 
-        if let Some(end) = end_time {
+        if end_time.is_some() {
             query.push_str(" AND created_at <= ?");
-            params.push(end.to_string());
         }
 
         query.push_str(" ORDER BY created_at DESC LIMIT ? OFFSET ?");
 
-        let records = sqlx::query_as::<_, HealthCheck>(&query)
+        let mut q = sqlx::query_as::<_, HealthCheck>(&query)
             .bind(device_id)
-            .bind(start_time.unwrap_or(""))
-            .bind(end_time.unwrap_or(""))
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?;
+            .bind(self.scope.filter())
+            .bind(self.scope.filter());
+
+        if let Some(start) = start_time {
+            q = q.bind(start);
+        }
+
+        if let Some(end) = end_time {
+            q = q.bind(end);
+        }
+
+        let records = q.bind(limit).bind(offset).fetch_all(&self.pool).await?;
 
         Ok(records)
     }
 
     /// 获取设备最新的健康检查记录
     pub async fn get_latest_by_device(&self, device_id: &str) -> Result<Option<HealthCheck>, AppError> {
+        let filter = self.scope.filter();
         let record = sqlx::query_as!(
             HealthCheck,
             r#"
-            SELECT 
-                id, device_id, 
+            SELECT
+                id, device_id,
                 security_score as "security_score: i32",
                 root_status as "root_status: bool",
                 bootloader_status as "bootloader_status: bool",
@@ -135,10 +155,13 @@ impl HealthCheckRepository {
                 details, created_at
             FROM health_checks
             WHERE device_id = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             ORDER BY created_at DESC
             LIMIT 1
             "#,
-            device_id
+            device_id,
+            filter,
+            filter
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -148,13 +171,17 @@ impl HealthCheckRepository {
 
     /// 统计设备的健康检查次数
     pub async fn count_by_device(&self, device_id: &str) -> Result<i64, AppError> {
+        let filter = self.scope.filter();
         let result = sqlx::query!(
             r#"
             SELECT COUNT(*) as count
             FROM health_checks
             WHERE device_id = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             "#,
-            device_id
+            device_id,
+            filter,
+            filter
         )
         .fetch_one(&self.pool)
         .await?;
@@ -169,11 +196,12 @@ impl HealthCheckRepository {
         threshold: i32,
         limit: i64,
     ) -> Result<Vec<HealthCheck>, AppError> {
+        let filter = self.scope.filter();
         let records = sqlx::query_as!(
             HealthCheck,
             r#"
-            SELECT 
-                id, device_id, 
+            SELECT
+                id, device_id,
                 security_score as "security_score: i32",
                 root_status as "root_status: bool",
                 bootloader_status as "bootloader_status: bool",
@@ -184,11 +212,14 @@ impl HealthCheckRepository {
                 details, created_at
             FROM health_checks
             WHERE device_id = ? AND security_score < ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             ORDER BY created_at DESC
             LIMIT ?
             "#,
             device_id,
             threshold,
+            filter,
+            filter,
             limit
         )
         .fetch_all(&self.pool)
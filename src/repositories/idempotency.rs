@@ -0,0 +1,136 @@
+use crate::models::{IdempotencyRecord, IdempotencyStatus};
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 幂等键Repository
+///
+/// 领取以主键唯一约束为准，多节点下同一幂等键同时只会被一个请求持有
+#[derive(Clone)]
+pub struct IdempotencyRepository {
+    pool: SqlitePool,
+}
+
+impl IdempotencyRepository {
+    /// 创建新的IdempotencyRepository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 查找幂等键记录
+    pub async fn find(
+        &self,
+        device_id: &str,
+        key: &str,
+    ) -> Result<Option<IdempotencyRecord>, AppError> {
+        let record = sqlx::query_as::<_, IdempotencyRecord>(
+            "SELECT * FROM idempotency_keys WHERE device_id = ? AND idempotency_key = ?",
+        )
+        .bind(device_id)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record)
+    }
+
+    /// 领取幂等键
+    ///
+    /// 先清除该键已过期或锁已超时（`locked_at` 早于 `stale_before`）的记录，再尝试插入；
+    /// 返回是否领取成功
+    pub async fn try_claim(
+        &self,
+        device_id: &str,
+        key: &str,
+        request_hash: &str,
+        now: &str,
+        stale_before: &str,
+        expires_at: &str,
+    ) -> Result<bool, AppError> {
+        sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE device_id = ? AND idempotency_key = ?
+              AND (expires_at <= ? OR (status = ? AND locked_at <= ?))
+            "#,
+        )
+        .bind(device_id)
+        .bind(key)
+        .bind(now)
+        .bind(IdempotencyStatus::InProgress.as_str())
+        .bind(stale_before)
+        .execute(&self.pool)
+        .await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO idempotency_keys (
+                device_id, idempotency_key, request_hash, status, created_at, locked_at, expires_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(device_id)
+        .bind(key)
+        .bind(request_hash)
+        .bind(IdempotencyStatus::InProgress.as_str())
+        .bind(now)
+        .bind(now)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 保存首次处理的响应
+    pub async fn complete(
+        &self,
+        device_id: &str,
+        key: &str,
+        response_status: i64,
+        response_body: &str,
+        transaction_id: Option<&str>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = ?, response_status = ?, response_body = ?, transaction_id = ?
+            WHERE device_id = ? AND idempotency_key = ?
+            "#,
+        )
+        .bind(IdempotencyStatus::Completed.as_str())
+        .bind(response_status)
+        .bind(response_body)
+        .bind(transaction_id)
+        .bind(device_id)
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 释放处理中的幂等键，允许使用同一幂等键重试
+    pub async fn release(&self, device_id: &str, key: &str) -> Result<(), AppError> {
+        sqlx::query(
+            "DELETE FROM idempotency_keys WHERE device_id = ? AND idempotency_key = ? AND status = ?",
+        )
+        .bind(device_id)
+        .bind(key)
+        .bind(IdempotencyStatus::InProgress.as_str())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 删除已过期的幂等键，返回删除数量
+    pub async fn delete_expired(&self, now: &str) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
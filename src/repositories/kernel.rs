@@ -1,16 +1,39 @@
 use sqlx::SqlitePool;
 
-use crate::{models::Kernel, utils::error::AppError};
+use crate::{
+    models::{Kernel, TenantContext},
+    repositories::TenantScope,
+    utils::error::AppError,
+};
 
 #[derive(Clone)]
 pub struct KernelRepository {
     pool: SqlitePool,
+    scope: TenantScope,
 }
 
 impl KernelRepository {
+    /// 创建新的KernelRepository，绑定租户前不匹配任何租户的数据
     pub fn new(pool: SqlitePool) -> Self {
         Self {
             pool,
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// 创建不做租户过滤的KernelRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            scope: TenantScope::all(),
+        }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            scope: TenantScope::new(tenant),
         }
     }
 
@@ -18,12 +41,13 @@ impl KernelRepository {
     pub async fn create(&self, kernel: &Kernel) -> Result<Kernel, AppError> {
         let kernel = sqlx::query_as::<_, Kernel>(
             r#"
-            INSERT INTO kernels (id, version, file_path, file_hash, file_size, status, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO kernels (id, tenant_id, version, file_path, file_hash, file_size, status, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
             RETURNING *
             "#,
         )
         .bind(&kernel.id)
+        .bind(self.scope.owner(&kernel.tenant_id))
         .bind(&kernel.version)
         .bind(&kernel.file_path)
         .bind(&kernel.file_hash)
@@ -41,10 +65,11 @@ impl KernelRepository {
     pub async fn find_by_version(&self, version: &str) -> Result<Option<Kernel>, AppError> {
         let kernel = sqlx::query_as::<_, Kernel>(
             r#"
-            SELECT * FROM kernels WHERE version = ?
+            SELECT * FROM kernels WHERE version = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
         )
         .bind(version)
+        .bind(self.scope.filter())
         .fetch_optional(&self.pool)
         .await?;
 
@@ -55,9 +80,10 @@ impl KernelRepository {
     pub async fn list_all(&self) -> Result<Vec<Kernel>, AppError> {
         let kernels = sqlx::query_as::<_, Kernel>(
             r#"
-            SELECT * FROM kernels ORDER BY created_at DESC
+            SELECT * FROM kernels WHERE tenant_id = COALESCE(?, tenant_id) ORDER BY created_at DESC
             "#,
         )
+        .bind(self.scope.filter())
         .fetch_all(&self.pool)
         .await?;
 
@@ -68,10 +94,11 @@ impl KernelRepository {
     pub async fn list_by_status(&self, status: &str) -> Result<Vec<Kernel>, AppError> {
         let kernels = sqlx::query_as::<_, Kernel>(
             r#"
-            SELECT * FROM kernels WHERE status = ? ORDER BY created_at DESC
+            SELECT * FROM kernels WHERE status = ? AND tenant_id = COALESCE(?, tenant_id) ORDER BY created_at DESC
             "#,
         )
         .bind(status)
+        .bind(self.scope.filter())
         .fetch_all(&self.pool)
         .await?;
 
@@ -83,12 +110,13 @@ impl KernelRepository {
         let now = chrono::Utc::now().to_rfc3339();
         sqlx::query(
             r#"
-            UPDATE kernels SET status = ?, updated_at = ? WHERE version = ?
+            UPDATE kernels SET status = ?, updated_at = ? WHERE version = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
         )
         .bind(status)
         .bind(now)
         .bind(version)
+        .bind(self.scope.filter())
         .execute(&self.pool)
         .await?;
 
@@ -99,10 +127,11 @@ impl KernelRepository {
     pub async fn delete(&self, version: &str) -> Result<(), AppError> {
         sqlx::query(
             r#"
-            DELETE FROM kernels WHERE version = ?
+            DELETE FROM kernels WHERE version = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
         )
         .bind(version)
+        .bind(self.scope.filter())
         .execute(&self.pool)
         .await?;
 
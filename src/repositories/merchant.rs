@@ -0,0 +1,168 @@
+use crate::models::{Merchant, MerchantStatus, TenantContext};
+use crate::repositories::TenantScope;
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 商户Repository
+#[derive(Clone)]
+pub struct MerchantRepository {
+    pool: SqlitePool,
+    scope: TenantScope,
+}
+
+impl MerchantRepository {
+    /// 创建新的MerchantRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的MerchantRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 创建商户
+    pub async fn create(&self, merchant: &Merchant) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO merchants (
+                id, tenant_id, name, mcc, country, currency, acquirer_mid, address,
+                status, tid_sequence, created_at, updated_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&merchant.id)
+        .bind(self.scope.owner(&merchant.tenant_id))
+        .bind(&merchant.name)
+        .bind(&merchant.mcc)
+        .bind(&merchant.country)
+        .bind(&merchant.currency)
+        .bind(&merchant.acquirer_mid)
+        .bind(&merchant.address)
+        .bind(&merchant.status)
+        .bind(merchant.tid_sequence)
+        .bind(&merchant.created_at)
+        .bind(&merchant.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找商户
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<Merchant>, AppError> {
+        let merchant = sqlx::query_as::<_, Merchant>(
+            "SELECT * FROM merchants WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(merchant)
+    }
+
+    /// 根据收单商户号查找商户
+    pub async fn find_by_acquirer_mid(&self, mid: &str) -> Result<Option<Merchant>, AppError> {
+        let merchant = sqlx::query_as::<_, Merchant>(
+            "SELECT * FROM merchants WHERE acquirer_mid = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(mid)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(merchant)
+    }
+
+    /// 列出商户
+    pub async fn list(
+        &self,
+        status: Option<MerchantStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Merchant>, AppError> {
+        let merchants = sqlx::query_as::<_, Merchant>(
+            r#"
+            SELECT * FROM merchants
+            WHERE status = COALESCE(?, status) AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY created_at DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(status.map(|s| s.as_str()))
+        .bind(self.scope.filter())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(merchants)
+    }
+
+    /// 统计商户数量
+    pub async fn count(&self, status: Option<MerchantStatus>) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM merchants
+            WHERE status = COALESCE(?, status) AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(status.map(|s| s.as_str()))
+        .bind(self.scope.filter())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 更新商户信息
+    pub async fn update(&self, merchant: &Merchant) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query(
+            r#"
+            UPDATE merchants
+            SET name = ?, acquirer_mid = ?, address = ?, status = ?, updated_at = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(&merchant.name)
+        .bind(&merchant.acquirer_mid)
+        .bind(&merchant.address)
+        .bind(&merchant.status)
+        .bind(now)
+        .bind(&merchant.id)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 分配下一个终端序列号
+    ///
+    /// 通过单条UPDATE递增序列，保证并发下终端号不重复
+    pub async fn next_tid_sequence(&self, id: &str) -> Result<i64, AppError> {
+        let sequence = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE merchants
+            SET tid_sequence = tid_sequence + 1
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            RETURNING tid_sequence
+            "#,
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Merchant not found".to_string()))?;
+
+        Ok(sequence)
+    }
+}
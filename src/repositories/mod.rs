@@ -1,15 +1,45 @@
 pub mod audit_log;
 pub mod device;
+pub mod device_command;
+pub mod device_group;
+pub mod device_import;
 pub mod health_check;
+pub mod idempotency;
 pub mod kernel;
+pub mod merchant;
+pub mod notification;
+pub mod outbox;
+pub mod reconciliation;
+pub mod scope;
+pub mod settlement;
+pub mod store;
+pub mod tenant;
 pub mod threat;
 pub mod transaction;
+pub mod transaction_token;
+pub mod unit_of_work;
 pub mod version;
+pub mod webhook;
 
 pub use audit_log::AuditLogRepository;
 pub use device::{DeviceRepository, DeviceStatistics};
+pub use device_command::DeviceCommandRepository;
+pub use device_group::DeviceGroupRepository;
+pub use device_import::DeviceImportRepository;
 pub use health_check::HealthCheckRepository;
+pub use idempotency::IdempotencyRepository;
 pub use kernel::KernelRepository;
+pub use merchant::MerchantRepository;
+pub use notification::NotificationRepository;
+pub use outbox::{append_event, OutboxRepository};
+pub use reconciliation::ReconciliationRepository;
+pub use scope::{TenantScope, DEVICE_TENANT_FILTER};
+pub use settlement::SettlementRepository;
+pub use store::StoreRepository;
+pub use tenant::{ApiKeyRepository, TenantRepository};
 pub use threat::{ThreatBySeverity, ThreatRepository, ThreatStatistics};
 pub use transaction::{TransactionRepository, TransactionStats};
+pub use transaction_token::TransactionTokenRepository;
+pub use unit_of_work::{DbConnection, DbExecutor, UnitOfWork};
 pub use version::VersionRepository;
+pub use webhook::WebhookRepository;
@@ -0,0 +1,267 @@
+use crate::models::{
+    AlertEscalation, EscalationStatus, NotificationRecord, TenantContext, MAX_STORED_NOTIFICATIONS,
+};
+use crate::repositories::TenantScope;
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 通知的租户过滤条件：未标记租户的通知只对跨租户访问可见，需绑定两次 `TenantScope::filter()`
+const NOTIFICATION_TENANT_FILTER: &str = "(? IS NULL OR n.tenant_id = ?)";
+
+/// 通知Repository
+#[derive(Clone)]
+pub struct NotificationRepository {
+    pool: SqlitePool,
+    scope: TenantScope,
+}
+
+impl NotificationRepository {
+    /// 创建新的NotificationRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的NotificationRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 保存通知并返回分配的序列号，超出保留条数的旧通知随之删除
+    pub async fn create(&self, record: &NotificationRecord) -> Result<i64, AppError> {
+        let seq = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO notifications (
+                tenant_id, notification_type, severity, title, message,
+                device_id, merchant_id, threat_id, data, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING seq
+            "#,
+        )
+        .bind(&record.tenant_id)
+        .bind(&record.notification_type)
+        .bind(&record.severity)
+        .bind(&record.title)
+        .bind(&record.message)
+        .bind(&record.device_id)
+        .bind(&record.merchant_id)
+        .bind(&record.threat_id)
+        .bind(&record.data)
+        .bind(&record.created_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        sqlx::query("DELETE FROM notifications WHERE seq <= ?")
+            .bind(seq - MAX_STORED_NOTIFICATIONS)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(seq)
+    }
+
+    /// 根据序列号查找通知
+    pub async fn find_by_seq(&self, seq: i64) -> Result<Option<NotificationRecord>, AppError> {
+        let record = sqlx::query_as::<_, NotificationRecord>(&format!(
+            "SELECT n.* FROM notifications n WHERE n.seq = ? AND {}",
+            NOTIFICATION_TENANT_FILTER
+        ))
+        .bind(seq)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record)
+    }
+
+    /// 列出指定序列号之后的通知（按序列号升序），用于断线重连补发
+    pub async fn list_since(
+        &self,
+        after_seq: i64,
+        limit: i64,
+    ) -> Result<Vec<NotificationRecord>, AppError> {
+        let records = sqlx::query_as::<_, NotificationRecord>(&format!(
+            r#"
+            SELECT n.* FROM notifications n
+            WHERE n.seq > ? AND {}
+            ORDER BY n.seq ASC
+            LIMIT ?
+            "#,
+            NOTIFICATION_TENANT_FILTER
+        ))
+        .bind(after_seq)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    /// 按用户分页查询通知（按序列号倒序），附带该用户的已读状态
+    pub async fn list_for_user(
+        &self,
+        user_id: &str,
+        before_seq: Option<i64>,
+        after_seq: Option<i64>,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<NotificationRecord>, AppError> {
+        let records = sqlx::query_as::<_, NotificationRecord>(&format!(
+            r#"
+            SELECT n.*, r.read_at FROM notifications n
+            LEFT JOIN notification_reads r ON r.seq = n.seq AND r.user_id = ?
+            WHERE {}
+              AND (? IS NULL OR n.seq < ?)
+              AND (? IS NULL OR n.seq > ?)
+              AND (? = 0 OR r.read_at IS NULL)
+            ORDER BY n.seq DESC
+            LIMIT ?
+            "#,
+            NOTIFICATION_TENANT_FILTER
+        ))
+        .bind(user_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .bind(before_seq)
+        .bind(before_seq)
+        .bind(after_seq)
+        .bind(after_seq)
+        .bind(unread_only)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    /// 统计用户未读通知数量
+    pub async fn count_unread(&self, user_id: &str) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(&format!(
+            r#"
+            SELECT COUNT(*) FROM notifications n
+            WHERE {}
+              AND NOT EXISTS (
+                  SELECT 1 FROM notification_reads r WHERE r.seq = n.seq AND r.user_id = ?
+              )
+            "#,
+            NOTIFICATION_TENANT_FILTER
+        ))
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 将指定序列号及之前的通知标记为已读，返回新标记的数量
+    pub async fn mark_read_up_to(&self, user_id: &str, up_to_seq: i64) -> Result<u64, AppError> {
+        let result = sqlx::query(&format!(
+            r#"
+            INSERT OR IGNORE INTO notification_reads (seq, user_id, read_at)
+            SELECT n.seq, ?, ? FROM notifications n
+            WHERE n.seq <= ? AND {}
+            "#,
+            NOTIFICATION_TENANT_FILTER
+        ))
+        .bind(user_id)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(up_to_seq)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 将单条通知标记为已读
+    pub async fn mark_read(&self, user_id: &str, seq: i64) -> Result<(), AppError> {
+        sqlx::query(
+            "INSERT OR IGNORE INTO notification_reads (seq, user_id, read_at) VALUES (?, ?, ?)",
+        )
+        .bind(seq)
+        .bind(user_id)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 通知是否已被任一用户确认（标记已读）
+    pub async fn is_acknowledged(&self, seq: i64) -> Result<bool, AppError> {
+        let acknowledged = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM notification_reads WHERE seq = ?)",
+        )
+        .bind(seq)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(acknowledged)
+    }
+
+    /// 登记待升级的告警
+    pub async fn create_escalation(&self, escalation: &AlertEscalation) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO alert_escalations (id, notification_seq, route_name, status, due_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&escalation.id)
+        .bind(escalation.notification_seq)
+        .bind(&escalation.route_name)
+        .bind(&escalation.status)
+        .bind(&escalation.due_at)
+        .bind(&escalation.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 列出已到期的待升级告警
+    pub async fn list_due_escalations(&self, now: &str) -> Result<Vec<AlertEscalation>, AppError> {
+        let escalations = sqlx::query_as::<_, AlertEscalation>(
+            r#"
+            SELECT * FROM alert_escalations
+            WHERE status = ? AND due_at <= ?
+            ORDER BY due_at ASC
+            "#,
+        )
+        .bind(EscalationStatus::Pending.as_str())
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(escalations)
+    }
+
+    /// 结束升级跟踪，返回是否由本次调用完成（多节点同时处理时只有一个成功）
+    pub async fn resolve_escalation(
+        &self,
+        id: &str,
+        status: EscalationStatus,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE alert_escalations SET status = ?, resolved_at = ? WHERE id = ? AND status = ?",
+        )
+        .bind(status.as_str())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .bind(EscalationStatus::Pending.as_str())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
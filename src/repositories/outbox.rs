@@ -0,0 +1,180 @@
+use crate::models::{DomainEvent, OutboxEvent, OutboxStatus, DEFAULT_TENANT_ID};
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 领域事件发件箱Repository
+///
+/// 事件由各Repository通过 [`append_event`] 在状态变更的事务中写入；
+/// 本Repository供分发器领取和更新发件箱记录，领取以当前状态为条件，多节点下同一事件只会被一个节点处理
+#[derive(Clone)]
+pub struct OutboxRepository {
+    pool: SqlitePool,
+}
+
+impl OutboxRepository {
+    /// 创建新的OutboxRepository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 根据ID查找
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<OutboxEvent>, AppError> {
+        let event = sqlx::query_as::<_, OutboxEvent>("SELECT * FROM outbox WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(event)
+    }
+
+    /// 按写入顺序列出聚合的事件
+    pub async fn list_by_aggregate(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Vec<OutboxEvent>, AppError> {
+        let events = sqlx::query_as::<_, OutboxEvent>(
+            "SELECT * FROM outbox WHERE aggregate_id = ? ORDER BY seq ASC",
+        )
+        .bind(aggregate_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// 统计指定状态的事件数
+    pub async fn count_by_status(&self, status: OutboxStatus) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM outbox WHERE status = ?")
+            .bind(status.as_str())
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// 列出已到期待转发的事件（按写入顺序）
+    pub async fn list_due(&self, now: &str, limit: i64) -> Result<Vec<OutboxEvent>, AppError> {
+        let events = sqlx::query_as::<_, OutboxEvent>(
+            r#"
+            SELECT * FROM outbox
+            WHERE status = ? AND next_attempt_at <= ?
+            ORDER BY seq ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(OutboxStatus::Pending.as_str())
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// 领取事件：计入尝试次数，并将下次尝试时间推迟到租约结束，防止被其他节点重复领取
+    pub async fn claim(&self, id: &str, now: &str, lease_until: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE outbox
+            SET attempts = attempts + 1, next_attempt_at = ?
+            WHERE id = ? AND status = ? AND next_attempt_at <= ?
+            "#,
+        )
+        .bind(lease_until)
+        .bind(id)
+        .bind(OutboxStatus::Pending.as_str())
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 记录转发成功
+    pub async fn mark_dispatched(&self, id: &str) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE outbox SET status = ?, last_error = NULL, dispatched_at = ? WHERE id = ?",
+        )
+        .bind(OutboxStatus::Dispatched.as_str())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 记录转发失败；`next_attempt_at` 为None时不再重试，标记为FAILED
+    pub async fn mark_failed(
+        &self,
+        id: &str,
+        error: &str,
+        next_attempt_at: Option<&str>,
+    ) -> Result<(), AppError> {
+        let status = match next_attempt_at {
+            Some(_) => OutboxStatus::Pending,
+            None => OutboxStatus::Failed,
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE outbox
+            SET status = ?, last_error = ?, next_attempt_at = COALESCE(?, next_attempt_at)
+            WHERE id = ?
+            "#,
+        )
+        .bind(status.as_str())
+        .bind(error)
+        .bind(next_attempt_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// 在调用方的事务中写入领域事件，与状态变更一同提交或回滚
+pub async fn append_event(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    tenant_id: &str,
+    event: &DomainEvent,
+) -> Result<(), AppError> {
+    let record = OutboxEvent::new(tenant_id.to_string(), event);
+
+    sqlx::query(
+        r#"
+        INSERT INTO outbox (
+            id, tenant_id, event_type, aggregate_id, payload, status,
+            attempts, next_attempt_at, created_at
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&record.id)
+    .bind(&record.tenant_id)
+    .bind(&record.event_type)
+    .bind(&record.aggregate_id)
+    .bind(&record.payload)
+    .bind(&record.status)
+    .bind(record.attempts)
+    .bind(&record.next_attempt_at)
+    .bind(&record.created_at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// 在事务中查询设备所属租户，设备不存在时归属默认租户
+pub(crate) async fn device_tenant(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    device_id: &str,
+) -> Result<String, AppError> {
+    let tenant_id = sqlx::query_scalar::<_, String>("SELECT tenant_id FROM devices WHERE id = ?")
+        .bind(device_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    Ok(tenant_id.unwrap_or_else(|| DEFAULT_TENANT_ID.to_string()))
+}
@@ -0,0 +1,204 @@
+use crate::models::{ReconciliationCategory, ReconciliationItem, ReconciliationRun, TenantContext};
+use crate::repositories::TenantScope;
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 对账Repository（对账任务与结果明细）
+#[derive(Clone)]
+pub struct ReconciliationRepository {
+    pool: SqlitePool,
+    scope: TenantScope,
+}
+
+impl ReconciliationRepository {
+    /// 创建新的ReconciliationRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的ReconciliationRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 保存对账任务及全部结果明细
+    pub async fn create_run(
+        &self,
+        run: &ReconciliationRun,
+        items: &[ReconciliationItem],
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO reconciliation_runs (
+                id, tenant_id, layout, file_name, merchant_id, period_start, period_end,
+                total_records, matched_count, amount_mismatch_count, unmatched_ours_count,
+                unmatched_theirs_count, created_by, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&run.id)
+        .bind(self.scope.owner(&run.tenant_id))
+        .bind(&run.layout)
+        .bind(&run.file_name)
+        .bind(&run.merchant_id)
+        .bind(&run.period_start)
+        .bind(&run.period_end)
+        .bind(run.total_records)
+        .bind(run.matched_count)
+        .bind(run.amount_mismatch_count)
+        .bind(run.unmatched_ours_count)
+        .bind(run.unmatched_theirs_count)
+        .bind(&run.created_by)
+        .bind(&run.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        for item in items {
+            sqlx::query(
+                r#"
+                INSERT INTO reconciliation_items (
+                    id, run_id, category, line_number, transaction_id, rrn, authorization_code,
+                    transaction_date, currency, our_amount, their_amount, details
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&item.id)
+            .bind(&item.run_id)
+            .bind(&item.category)
+            .bind(item.line_number)
+            .bind(&item.transaction_id)
+            .bind(&item.rrn)
+            .bind(&item.authorization_code)
+            .bind(&item.transaction_date)
+            .bind(&item.currency)
+            .bind(item.our_amount)
+            .bind(item.their_amount)
+            .bind(&item.details)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找对账任务
+    pub async fn find_run(&self, id: &str) -> Result<Option<ReconciliationRun>, AppError> {
+        let run = sqlx::query_as::<_, ReconciliationRun>(
+            "SELECT * FROM reconciliation_runs WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(run)
+    }
+
+    /// 列出对账任务（按创建时间倒序）
+    pub async fn list_runs(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ReconciliationRun>, AppError> {
+        let runs = sqlx::query_as::<_, ReconciliationRun>(
+            r#"
+            SELECT * FROM reconciliation_runs
+            WHERE tenant_id = COALESCE(?, tenant_id)
+            ORDER BY created_at DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(self.scope.filter())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    /// 统计对账任务数量
+    pub async fn count_runs(&self) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM reconciliation_runs WHERE tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(self.scope.filter())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 列出对账结果明细（支持按分类筛选），清算文件明细按行号在前，本地单边交易在后
+    ///
+    /// 调用方需先通过 `find_run` 确认任务属于当前租户
+    pub async fn list_items(
+        &self,
+        run_id: &str,
+        category: Option<ReconciliationCategory>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ReconciliationItem>, AppError> {
+        let items = sqlx::query_as::<_, ReconciliationItem>(
+            r#"
+            SELECT * FROM reconciliation_items
+            WHERE run_id = ? AND category = COALESCE(?, category)
+            ORDER BY line_number IS NULL, line_number, transaction_date, transaction_id
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(run_id)
+        .bind(category.map(|c| c.as_str()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// 统计对账结果明细数量
+    pub async fn count_items(
+        &self,
+        run_id: &str,
+        category: Option<ReconciliationCategory>,
+    ) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM reconciliation_items WHERE run_id = ? AND category = COALESCE(?, category)",
+        )
+        .bind(run_id)
+        .bind(category.map(|c| c.as_str()))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 列出对账差异（除MATCHED外的全部明细），用于导出差异报告
+    pub async fn list_exceptions(&self, run_id: &str) -> Result<Vec<ReconciliationItem>, AppError> {
+        let items = sqlx::query_as::<_, ReconciliationItem>(
+            r#"
+            SELECT * FROM reconciliation_items
+            WHERE run_id = ? AND category != ?
+            ORDER BY category, line_number IS NULL, line_number, transaction_date, transaction_id
+            "#,
+        )
+        .bind(run_id)
+        .bind(ReconciliationCategory::Matched.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::models::TenantContext;
+
+/// 设备从属数据（健康检查、威胁事件、交易）的租户过滤条件
+///
+/// 这些表不单独存储租户ID，通过所属设备判断租户，需绑定两次 `TenantScope::filter()`
+///
+/// 编译期校验的 `query!`/`query_as!` 需要字面量SQL，应内联同样的条件
+pub const DEVICE_TENANT_FILTER: &str =
+    "(? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))";
+
+/// 拒绝访问的作用域使用的租户ID，不对应任何租户
+const DENIED_TENANT: &str = "";
+
+/// Repository的租户作用域
+///
+/// 绑定租户后所有查询自动附加 `tenant_id = COALESCE(?, tenant_id)` 条件，新建数据归属于当前租户；
+/// 不过滤的访问（系统任务、设备端调用）须显式使用 [`TenantScope::all`]（Repository的 `unscoped`），
+/// 默认作用域（Repository的 `new`）不匹配任何租户的数据
+#[derive(Debug, Clone, Default)]
+pub struct TenantScope {
+    access: Access,
+}
+
+#[derive(Debug, Clone, Default)]
+enum Access {
+    #[default]
+    Denied,
+    All,
+    Tenant(TenantContext),
+}
+
+impl TenantScope {
+    /// 创建绑定到指定租户的作用域
+    pub fn new(tenant: &TenantContext) -> Self {
+        Self { access: Access::Tenant(tenant.clone()) }
+    }
+
+    /// 不做租户过滤的作用域，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn all() -> Self {
+        Self { access: Access::All }
+    }
+
+    /// 查询过滤使用的租户ID，None表示不过滤
+    pub fn filter(&self) -> Option<&str> {
+        match &self.access {
+            Access::Denied => Some(DENIED_TENANT),
+            Access::All => None,
+            Access::Tenant(tenant) => tenant.scope(),
+        }
+    }
+
+    /// 绑定的租户ID，不过滤时为None
+    pub fn tenant_id(&self) -> Option<&str> {
+        match &self.access {
+            Access::Denied => Some(DENIED_TENANT),
+            Access::All => None,
+            Access::Tenant(tenant) => Some(&tenant.tenant_id),
+        }
+    }
+
+    /// 新建数据归属的租户ID，不过滤时使用数据自身的租户
+    pub fn owner<'a>(&'a self, fallback: &'a str) -> &'a str {
+        self.tenant_id().unwrap_or(fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unscoped_access_is_explicit() {
+        let scope = TenantScope::all();
+        assert_eq!(scope.filter(), None);
+        assert_eq!(scope.owner("acq-1"), "acq-1");
+
+        // 默认作用域不匹配任何租户
+        let scope = TenantScope::default();
+        assert_eq!(scope.filter(), Some(DENIED_TENANT));
+        assert_eq!(scope.owner("acq-1"), DENIED_TENANT);
+    }
+
+    #[test]
+    fn test_bound_scope() {
+        let scope = TenantScope::new(&TenantContext::new("acq-1"));
+        assert_eq!(scope.filter(), Some("acq-1"));
+        assert_eq!(scope.owner("default"), "acq-1");
+
+        let scope = TenantScope::new(&TenantContext::cross_tenant("default"));
+        assert_eq!(scope.filter(), None);
+        assert_eq!(scope.owner("acq-1"), "default");
+    }
+}
@@ -0,0 +1,551 @@
+use crate::models::{
+    BatchStatus, SettlementBatch, SettlementFailureReason, SettlementTotal, TenantContext,
+    Transaction, TransactionStatus,
+};
+use crate::repositories::{DbExecutor, TenantScope, UnitOfWork, DEVICE_TENANT_FILTER};
+use crate::utils::error::AppError;
+use sqlx::{Connection, SqlitePool};
+
+/// 结算批次Repository
+#[derive(Clone)]
+pub struct SettlementRepository {
+    db: DbExecutor,
+    scope: TenantScope,
+}
+
+impl SettlementRepository {
+    /// 创建新的SettlementRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的SettlementRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { db: self.db.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 返回绑定到工作单元的Repository，所有操作在该工作单元的事务中执行
+    pub fn in_unit_of_work(&self, uow: &UnitOfWork) -> Self {
+        Self { db: DbExecutor::UnitOfWork(uow.clone()), scope: self.scope.clone() }
+    }
+
+    /// 开启工作单元；已绑定工作单元时加入外层事务
+    pub async fn begin(&self) -> Result<UnitOfWork, AppError> {
+        self.db.begin().await
+    }
+
+    /// 把交易加入所属设备和商户的开放批次，没有开放批次时自动开批，返回批次ID
+    pub async fn assign_transaction(&self, transaction: &Transaction) -> Result<String, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let open_batch = r#"
+            SELECT id FROM settlement_batches
+            WHERE device_id = ? AND IFNULL(merchant_id, '') = IFNULL(?, '') AND status = ?
+        "#;
+
+        let existing = sqlx::query_scalar::<_, String>(open_batch)
+            .bind(&transaction.device_id)
+            .bind(&transaction.merchant_id)
+            .bind(BatchStatus::Open.as_str())
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let batch_id = match existing {
+            Some(id) => id,
+            None => {
+                let batch_number = sqlx::query_scalar::<_, i64>(
+                    "SELECT COALESCE(MAX(batch_number), 0) + 1 FROM settlement_batches WHERE device_id = ?",
+                )
+                .bind(&transaction.device_id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                let batch = SettlementBatch::open(
+                    transaction.device_id.clone(),
+                    transaction.merchant_id.clone(),
+                    batch_number,
+                );
+
+                // 并发开批时只有一个成功，其余使用已开的批次
+                sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO settlement_batches (
+                        id, device_id, merchant_id, batch_number, status,
+                        opened_at, created_at, updated_at
+                    )
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&batch.id)
+                .bind(&batch.device_id)
+                .bind(&batch.merchant_id)
+                .bind(batch.batch_number)
+                .bind(&batch.status)
+                .bind(&batch.opened_at)
+                .bind(&batch.created_at)
+                .bind(&batch.updated_at)
+                .execute(&mut *tx)
+                .await?;
+
+                sqlx::query_scalar::<_, String>(open_batch)
+                    .bind(&transaction.device_id)
+                    .bind(&transaction.merchant_id)
+                    .bind(BatchStatus::Open.as_str())
+                    .fetch_one(&mut *tx)
+                    .await?
+            },
+        };
+
+        sqlx::query("UPDATE transactions SET batch_id = ? WHERE id = ?")
+            .bind(&batch_id)
+            .bind(&transaction.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(batch_id)
+    }
+
+    /// 根据ID查找批次
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<SettlementBatch>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let batch = sqlx::query_as::<_, SettlementBatch>(&format!(
+            "SELECT * FROM settlement_batches WHERE id = ? AND {}",
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(batch)
+    }
+
+    /// 列出设备的开放批次
+    pub async fn find_open_by_device(
+        &self,
+        device_id: &str,
+    ) -> Result<Vec<SettlementBatch>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let batches = sqlx::query_as::<_, SettlementBatch>(&format!(
+            "SELECT * FROM settlement_batches WHERE device_id = ? AND status = ? AND {} ORDER BY opened_at",
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(device_id)
+        .bind(BatchStatus::Open.as_str())
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(batches)
+    }
+
+    /// 列出批次（支持按设备和状态筛选）
+    pub async fn list(
+        &self,
+        device_id: Option<&str>,
+        status: Option<BatchStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SettlementBatch>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let batches = sqlx::query_as::<_, SettlementBatch>(&format!(
+            r#"
+            SELECT * FROM settlement_batches
+            WHERE device_id = COALESCE(?, device_id) AND status = COALESCE(?, status) AND {}
+            ORDER BY opened_at DESC, batch_number DESC
+            LIMIT ? OFFSET ?
+            "#,
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(device_id)
+        .bind(status.map(|s| s.as_str()))
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(batches)
+    }
+
+    /// 统计批次数量
+    pub async fn count(
+        &self,
+        device_id: Option<&str>,
+        status: Option<BatchStatus>,
+    ) -> Result<i64, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let count = sqlx::query_scalar::<_, i64>(&format!(
+            r#"
+            SELECT COUNT(*) FROM settlement_batches
+            WHERE device_id = COALESCE(?, device_id) AND status = COALESCE(?, status) AND {}
+            "#,
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(device_id)
+        .bind(status.map(|s| s.as_str()))
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 批次按交易类型和币种汇总的合计（关批时计算）
+    pub async fn list_totals(&self, batch_id: &str) -> Result<Vec<SettlementTotal>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let totals = sqlx::query_as::<_, SettlementTotal>(
+            r#"
+            SELECT transaction_type, currency, count, amount FROM settlement_batch_totals
+            WHERE batch_id = ?
+            ORDER BY currency, transaction_type
+            "#,
+        )
+        .bind(batch_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(totals)
+    }
+
+    /// 关批并计算合计，批次不是OPEN时返回false
+    ///
+    /// 被撤销和已冲正的交易不计入合计；关批后等待提交处理器
+    pub async fn close(&self, id: &str, closed_by: &str) -> Result<bool, AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE settlement_batches
+            SET status = ?, closed_at = ?, closed_by = ?, next_attempt_at = ?, updated_at = ?
+            WHERE id = ? AND status = ?
+            "#,
+        )
+        .bind(BatchStatus::Closed.as_str())
+        .bind(&now)
+        .bind(closed_by)
+        .bind(&now)
+        .bind(&now)
+        .bind(id)
+        .bind(BatchStatus::Open.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("DELETE FROM settlement_batch_totals WHERE batch_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO settlement_batch_totals (batch_id, transaction_type, currency, count, amount)
+            SELECT ?, transaction_type, currency, COUNT(*), SUM(COALESCE(approved_amount, amount))
+            FROM transactions
+            WHERE batch_id = ? AND status NOT IN (?, ?)
+            GROUP BY transaction_type, currency
+            "#,
+        )
+        .bind(id)
+        .bind(id)
+        .bind(TransactionStatus::Voided)
+        .bind(TransactionStatus::Reversed)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE settlement_batches
+            SET transaction_count = (
+                SELECT COALESCE(SUM(count), 0) FROM settlement_batch_totals WHERE batch_id = ?
+            )
+            WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// 列出在日切时间之前开批、仍为OPEN的批次
+    pub async fn list_due_for_close(&self, cutoff: &str) -> Result<Vec<SettlementBatch>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let batches = sqlx::query_as::<_, SettlementBatch>(&format!(
+            r#"
+            SELECT * FROM settlement_batches
+            WHERE status = ? AND opened_at < ? AND {}
+            ORDER BY opened_at ASC
+            "#,
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(BatchStatus::Open.as_str())
+        .bind(cutoff)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(batches)
+    }
+
+    /// 列出已关批、到期待提交的批次（按关批顺序）
+    pub async fn list_due_submissions(
+        &self,
+        now: &str,
+        limit: i64,
+    ) -> Result<Vec<SettlementBatch>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let batches = sqlx::query_as::<_, SettlementBatch>(&format!(
+            r#"
+            SELECT * FROM settlement_batches
+            WHERE status = ? AND next_attempt_at <= ? AND {}
+            ORDER BY closed_at ASC
+            LIMIT ?
+            "#,
+            DEVICE_TENANT_FILTER
+        ))
+        .bind(BatchStatus::Closed.as_str())
+        .bind(now)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(batches)
+    }
+
+    /// 领取提交：计入提交次数，并将下次提交时间推迟到租约结束，防止被其他节点重复提交
+    pub async fn claim_submission(
+        &self,
+        id: &str,
+        now: &str,
+        lease_until: &str,
+    ) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE settlement_batches
+            SET submit_attempts = submit_attempts + 1, next_attempt_at = ?, updated_at = ?
+            WHERE id = ? AND status = ? AND next_attempt_at <= ?
+            "#,
+        )
+        .bind(lease_until)
+        .bind(now)
+        .bind(id)
+        .bind(BatchStatus::Closed.as_str())
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 记录结算成功，批内计入合计的交易同时标记为已结算；空批次不经处理器，processor为空
+    pub async fn mark_settled(
+        &self,
+        id: &str,
+        processor: Option<&str>,
+        processor_reference: Option<&str>,
+        response_code: Option<&str>,
+        uploaded: bool,
+    ) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE settlement_batches
+            SET status = ?, processor = ?, processor_reference = ?, response_code = ?,
+                uploaded = ?, failure_reason = NULL, last_error = NULL, next_attempt_at = NULL,
+                settled_at = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(BatchStatus::Settled.as_str())
+        .bind(processor)
+        .bind(processor_reference)
+        .bind(response_code)
+        .bind(uploaded)
+        .bind(&now)
+        .bind(&now)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE transactions
+            SET settled_at = ?, updated_at = ?
+            WHERE batch_id = ? AND status NOT IN (?, ?)
+            "#,
+        )
+        .bind(&now)
+        .bind(&now)
+        .bind(id)
+        .bind(TransactionStatus::Voided)
+        .bind(TransactionStatus::Reversed)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 记录可重试的提交失败，批次保持CLOSED等待下次提交
+    pub async fn record_submission_error(
+        &self,
+        id: &str,
+        processor: Option<&str>,
+        error: &str,
+        next_attempt_at: &str,
+    ) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE settlement_batches
+            SET processor = COALESCE(?, processor), last_error = ?, next_attempt_at = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(processor)
+        .bind(error)
+        .bind(next_attempt_at)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 记录结算失败，批次进入FAILED等待人工重新开批
+    pub async fn mark_failed(
+        &self,
+        id: &str,
+        processor: Option<&str>,
+        reason: SettlementFailureReason,
+        error: &str,
+        response_code: Option<&str>,
+        uploaded: bool,
+    ) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE settlement_batches
+            SET status = ?, processor = COALESCE(?, processor), failure_reason = ?, last_error = ?,
+                response_code = COALESCE(?, response_code), uploaded = ?, next_attempt_at = NULL,
+                updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(BatchStatus::Failed.as_str())
+        .bind(processor)
+        .bind(reason.as_str())
+        .bind(error)
+        .bind(response_code)
+        .bind(uploaded)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 重新开放失败的批次，批次不是FAILED时返回false
+    ///
+    /// 设备和商户已有新的开放批次时，新批次的交易并入重新开放的批次，新批次删除
+    pub async fn reopen(&self, id: &str) -> Result<bool, AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let Some(batch) = sqlx::query_as::<_, SettlementBatch>(
+            "SELECT * FROM settlement_batches WHERE id = ? AND status = ?",
+        )
+        .bind(id)
+        .bind(BatchStatus::Failed.as_str())
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(false);
+        };
+
+        let newer = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT id FROM settlement_batches
+            WHERE device_id = ? AND IFNULL(merchant_id, '') = IFNULL(?, '') AND status = ?
+            "#,
+        )
+        .bind(&batch.device_id)
+        .bind(&batch.merchant_id)
+        .bind(BatchStatus::Open.as_str())
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(newer) = newer {
+            sqlx::query("UPDATE transactions SET batch_id = ? WHERE batch_id = ?")
+                .bind(id)
+                .bind(&newer)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM settlement_batches WHERE id = ?")
+                .bind(&newer)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query("DELETE FROM settlement_batch_totals WHERE batch_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE settlement_batches
+            SET status = ?, transaction_count = 0, processor = NULL, processor_reference = NULL,
+                response_code = NULL, failure_reason = NULL, last_error = NULL,
+                submit_attempts = 0, next_attempt_at = NULL, uploaded = 0,
+                closed_at = NULL, closed_by = NULL, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(BatchStatus::Open.as_str())
+        .bind(&now)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+}
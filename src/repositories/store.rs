@@ -0,0 +1,110 @@
+use crate::models::{Store, TenantContext};
+use crate::repositories::TenantScope;
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 门店Repository
+///
+/// 门店通过所属商户归属租户
+#[derive(Clone)]
+pub struct StoreRepository {
+    pool: SqlitePool,
+    scope: TenantScope,
+}
+
+impl StoreRepository {
+    /// 创建新的StoreRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的StoreRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 创建门店
+    pub async fn create(&self, store: &Store) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO stores (id, merchant_id, name, address, status, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&store.id)
+        .bind(&store.merchant_id)
+        .bind(&store.name)
+        .bind(&store.address)
+        .bind(&store.status)
+        .bind(&store.created_at)
+        .bind(&store.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找门店
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<Store>, AppError> {
+        let store = sqlx::query_as::<_, Store>(
+            r#"
+            SELECT s.* FROM stores s
+            JOIN merchants m ON m.id = s.merchant_id
+            WHERE s.id = ? AND m.tenant_id = COALESCE(?, m.tenant_id)
+            "#,
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(store)
+    }
+
+    /// 列出商户下的门店
+    pub async fn list_by_merchant(&self, merchant_id: &str) -> Result<Vec<Store>, AppError> {
+        let stores = sqlx::query_as::<_, Store>(
+            r#"
+            SELECT s.* FROM stores s
+            JOIN merchants m ON m.id = s.merchant_id
+            WHERE s.merchant_id = ? AND m.tenant_id = COALESCE(?, m.tenant_id)
+            ORDER BY s.created_at ASC
+            "#,
+        )
+        .bind(merchant_id)
+        .bind(self.scope.filter())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(stores)
+    }
+
+    /// 更新门店信息
+    pub async fn update(&self, store: &Store) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query(
+            r#"
+            UPDATE stores
+            SET name = ?, address = ?, status = ?, updated_at = ?
+            WHERE id = ? AND merchant_id IN (
+                SELECT id FROM merchants WHERE tenant_id = COALESCE(?, tenant_id)
+            )
+            "#,
+        )
+        .bind(&store.name)
+        .bind(&store.address)
+        .bind(&store.status)
+        .bind(now)
+        .bind(&store.id)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
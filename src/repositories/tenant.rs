@@ -0,0 +1,204 @@
+use crate::models::{ApiKey, Tenant};
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 租户Repository
+#[derive(Clone)]
+pub struct TenantRepository {
+    pool: SqlitePool,
+}
+
+impl TenantRepository {
+    /// 创建新的TenantRepository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 创建租户
+    pub async fn create(&self, tenant: &Tenant) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO tenants (
+                id, name, status, bdk_wrapped, bdk_key_ref, rate_limit_rps, rate_limit_burst,
+                branding, created_at, updated_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&tenant.id)
+        .bind(&tenant.name)
+        .bind(&tenant.status)
+        .bind(&tenant.bdk_wrapped)
+        .bind(&tenant.bdk_key_ref)
+        .bind(tenant.rate_limit_rps)
+        .bind(tenant.rate_limit_burst)
+        .bind(&tenant.branding)
+        .bind(&tenant.created_at)
+        .bind(&tenant.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找租户
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<Tenant>, AppError> {
+        let tenant = sqlx::query_as::<_, Tenant>("SELECT * FROM tenants WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(tenant)
+    }
+
+    /// 列出所有租户
+    pub async fn list(&self) -> Result<Vec<Tenant>, AppError> {
+        let tenants = sqlx::query_as::<_, Tenant>("SELECT * FROM tenants ORDER BY created_at ASC")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tenants)
+    }
+
+    /// 更新租户
+    pub async fn update(&self, tenant: &Tenant) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query(
+            r#"
+            UPDATE tenants
+            SET name = ?, status = ?, bdk_wrapped = ?, bdk_key_ref = ?, rate_limit_rps = ?,
+                rate_limit_burst = ?, branding = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&tenant.name)
+        .bind(&tenant.status)
+        .bind(&tenant.bdk_wrapped)
+        .bind(&tenant.bdk_key_ref)
+        .bind(tenant.rate_limit_rps)
+        .bind(tenant.rate_limit_burst)
+        .bind(&tenant.branding)
+        .bind(now)
+        .bind(&tenant.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 列出仍以明文保存BDK的租户（升级前的数据），返回 (租户ID, BDK)
+    pub async fn list_plaintext_bdks(&self) -> Result<Vec<(String, String)>, AppError> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            "SELECT id, bdk FROM tenants WHERE bdk IS NOT NULL",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// 以包装后的BDK替换明文BDK
+    pub async fn replace_plaintext_bdk(&self, id: &str, bdk_wrapped: &str) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE tenants SET bdk_wrapped = ?, bdk = NULL, updated_at = ? WHERE id = ?",
+        )
+        .bind(bdk_wrapped)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// API密钥Repository
+#[derive(Clone)]
+pub struct ApiKeyRepository {
+    pool: SqlitePool,
+}
+
+impl ApiKeyRepository {
+    /// 创建新的ApiKeyRepository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 创建API密钥
+    pub async fn create(&self, key: &ApiKey) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO api_keys (
+                id, tenant_id, name, key_prefix, key_hash, role, created_by, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&key.id)
+        .bind(&key.tenant_id)
+        .bind(&key.name)
+        .bind(&key.key_prefix)
+        .bind(&key.key_hash)
+        .bind(&key.role)
+        .bind(&key.created_by)
+        .bind(&key.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据哈希查找未吊销的API密钥
+    pub async fn find_active_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        let key = sqlx::query_as::<_, ApiKey>(
+            "SELECT * FROM api_keys WHERE key_hash = ? AND revoked_at IS NULL",
+        )
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(key)
+    }
+
+    /// 列出租户的API密钥
+    pub async fn list_by_tenant(&self, tenant_id: &str) -> Result<Vec<ApiKey>, AppError> {
+        let keys = sqlx::query_as::<_, ApiKey>(
+            "SELECT * FROM api_keys WHERE tenant_id = ? ORDER BY created_at DESC",
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(keys)
+    }
+
+    /// 记录最近使用时间
+    pub async fn touch(&self, id: &str) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query("UPDATE api_keys SET last_used_at = ? WHERE id = ?")
+            .bind(now)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 吊销API密钥
+    pub async fn revoke(&self, tenant_id: &str, id: &str) -> Result<bool, AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r#"
+            UPDATE api_keys SET revoked_at = ?
+            WHERE id = ? AND tenant_id = ? AND revoked_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(id)
+        .bind(tenant_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
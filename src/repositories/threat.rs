@@ -1,23 +1,57 @@
 use crate::{
-    models::{ThreatEvent, ThreatStatus, ThreatSeverity, ThreatType},
+    models::{DomainEvent, TenantContext, ThreatEvent, ThreatStatus, ThreatSeverity, ThreatType},
+    repositories::{
+        outbox::{append_event, device_tenant},
+        DbExecutor, TenantScope, UnitOfWork, DEVICE_TENANT_FILTER,
+    },
     utils::error::AppError,
 };
-use sqlx::SqlitePool;
+use sqlx::{Connection, SqlitePool};
+
+/// 威胁事件表查询列，用于动态拼接的查询；固定查询使用 `query_as!` 在编译期校验
+const THREAT_COLUMNS: &str = r#"
+    id, device_id, threat_type, severity, status, description,
+    detected_at, resolved_at, resolved_by
+"#;
 
 /// 威胁事件Repository
 #[derive(Clone)]
 pub struct ThreatRepository {
-    pool: SqlitePool,
+    db: DbExecutor,
+    scope: TenantScope,
 }
 
 impl ThreatRepository {
-    /// 创建新的威胁Repository
+    /// 创建新的威胁Repository，绑定租户前不匹配任何租户的数据
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的ThreatRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { db: self.db.clone(), scope: TenantScope::new(tenant) }
     }
 
-    /// 创建威胁事件
+    /// 返回绑定到工作单元的Repository，所有操作在该工作单元的事务中执行
+    pub fn in_unit_of_work(&self, uow: &UnitOfWork) -> Self {
+        Self { db: DbExecutor::UnitOfWork(uow.clone()), scope: self.scope.clone() }
+    }
+
+    /// 开启工作单元；已绑定工作单元时加入外层事务
+    pub async fn begin(&self) -> Result<UnitOfWork, AppError> {
+        self.db.begin().await
+    }
+
+    /// 创建威胁事件，并写入 `ThreatDetected` 事件
     pub async fn create(&self, threat: &ThreatEvent) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
         sqlx::query!(
             r#"
             INSERT INTO threat_events (
@@ -36,18 +70,32 @@ impl ThreatRepository {
             threat.resolved_at,
             threat.resolved_by,
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        let event = DomainEvent::ThreatDetected {
+            threat_id: threat.id.clone(),
+            device_id: threat.device_id.clone(),
+            threat_type: threat.threat_type,
+            severity: threat.severity,
+            description: threat.description.clone(),
+        };
+        let tenant_id = device_tenant(&mut tx, &threat.device_id).await?;
+        append_event(&mut tx, &tenant_id, &event).await?;
+
+        tx.commit().await?;
+
         Ok(())
     }
 
     /// 根据ID查找威胁事件
     pub async fn find_by_id(&self, id: &str) -> Result<Option<ThreatEvent>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let threat = sqlx::query_as!(
             ThreatEvent,
             r#"
-            SELECT 
+            SELECT
                 id, device_id,
                 threat_type as "threat_type: _",
                 severity as "severity: _",
@@ -55,10 +103,13 @@ impl ThreatRepository {
                 description, detected_at, resolved_at, resolved_by
             FROM threat_events
             WHERE id = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             "#,
-            id
+            id,
+            filter,
+            filter
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(threat)
@@ -74,14 +125,9 @@ impl ThreatRepository {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<ThreatEvent>, AppError> {
-        let mut query = String::from(
-            r#"
-            SELECT 
-                id, device_id, threat_type, severity, status, description,
-                detected_at, resolved_at, resolved_by
-            FROM threat_events
-            WHERE 1=1
-            "#
+        let mut query = format!(
+            "SELECT {} FROM threat_events WHERE {}",
+            THREAT_COLUMNS, DEVICE_TENANT_FILTER
         );
 
         if device_id.is_some() {
@@ -99,7 +145,9 @@ impl ThreatRepository {
 
         query.push_str(" ORDER BY detected_at DESC LIMIT ? OFFSET ?");
 
-        let mut q = sqlx::query_as::<_, ThreatEvent>(&query);
+        let mut q = sqlx::query_as::<_, ThreatEvent>(&query)
+            .bind(self.scope.filter())
+            .bind(self.scope.filter());
 
         if let Some(did) = device_id {
             q = q.bind(did);
@@ -116,7 +164,8 @@ impl ThreatRepository {
 
         q = q.bind(limit).bind(offset);
 
-        let threats = q.fetch_all(&self.pool).await?;
+        let mut conn = self.db.acquire().await?;
+        let threats = q.fetch_all(&mut *conn).await?;
 
         Ok(threats)
     }
@@ -134,18 +183,23 @@ impl ThreatRepository {
             None
         };
 
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         sqlx::query!(
             r#"
             UPDATE threat_events
             SET status = ?, resolved_at = ?, resolved_by = ?
             WHERE id = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             "#,
             status,
             resolved_at,
             resolved_by,
-            id
+            id,
+            filter,
+            filter
         )
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -153,44 +207,41 @@ impl ThreatRepository {
 
     /// 统计威胁数量（按状态）
     pub async fn count_by_status(&self, status: Option<ThreatStatus>) -> Result<i64, AppError> {
-        let count = if let Some(s) = status {
-            sqlx::query!(
-                r#"
-                SELECT COUNT(*) as count
-                FROM threat_events
-                WHERE status = ?
-                "#,
-                s
-            )
-            .fetch_one(&self.pool)
-            .await?
-            .count
-        } else {
-            sqlx::query!(
-                r#"
-                SELECT COUNT(*) as count
-                FROM threat_events
-                "#
-            )
-            .fetch_one(&self.pool)
-            .await?
-            .count
-        };
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM threat_events
+            WHERE status = COALESCE(?, status)
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
+            "#,
+            status,
+            filter,
+            filter
+        )
+        .fetch_one(&mut *conn)
+        .await?;
 
-        Ok(count as i64)
+        Ok(result.count as i64)
     }
 
     /// 统计设备的威胁数量
     pub async fn count_by_device(&self, device_id: &str) -> Result<i64, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let result = sqlx::query!(
             r#"
             SELECT COUNT(*) as count
             FROM threat_events
             WHERE device_id = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             "#,
-            device_id
+            device_id,
+            filter,
+            filter
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(result.count as i64)
@@ -198,10 +249,12 @@ impl ThreatRepository {
 
     /// 获取设备的活跃威胁
     pub async fn get_active_threats(&self, device_id: &str) -> Result<Vec<ThreatEvent>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let threats = sqlx::query_as!(
             ThreatEvent,
             r#"
-            SELECT 
+            SELECT
                 id, device_id,
                 threat_type as "threat_type: _",
                 severity as "severity: _",
@@ -209,16 +262,49 @@ impl ThreatRepository {
                 description, detected_at, resolved_at, resolved_by
             FROM threat_events
             WHERE device_id = ? AND status = 'Active'
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             ORDER BY detected_at DESC
             "#,
-            device_id
+            device_id,
+            filter,
+            filter
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *conn)
         .await?;
 
         Ok(threats)
     }
 
+    /// 关闭设备的所有活跃威胁，返回关闭数量
+    pub async fn resolve_active_by_device(
+        &self,
+        device_id: &str,
+        resolved_by: &str,
+    ) -> Result<u64, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let resolved_at = chrono::Utc::now().to_rfc3339();
+        let filter = self.scope.filter();
+        let result = sqlx::query!(
+            r#"
+            UPDATE threat_events
+            SET status = ?, resolved_at = ?, resolved_by = ?
+            WHERE device_id = ? AND status = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
+            "#,
+            ThreatStatus::Resolved,
+            resolved_at,
+            resolved_by,
+            device_id,
+            ThreatStatus::Active,
+            filter,
+            filter
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// 获取威胁统计信息
     pub async fn get_statistics(&self) -> Result<ThreatStatistics, AppError> {
         let total = self.count_by_status(None).await?;
@@ -226,49 +312,10 @@ impl ThreatRepository {
         let resolved = self.count_by_status(Some(ThreatStatus::Resolved)).await?;
 
         // 按严重程度统计
-        let critical = sqlx::query!(
-            r#"
-            SELECT COUNT(*) as count
-            FROM threat_events
-            WHERE severity = 'Critical' AND status = 'Active'
-            "#
-        )
-        .fetch_one(&self.pool)
-        .await?
-        .count as i64;
-
-        let high = sqlx::query!(
-            r#"
-            SELECT COUNT(*) as count
-            FROM threat_events
-            WHERE severity = 'High' AND status = 'Active'
-            "#
-        )
-        .fetch_one(&self.pool)
-        .await?
-        .count as i64;
-
-        let medium = sqlx::query!(
-            r#"
-            SELECT COUNT(*) as count
-            FROM threat_events
-            WHERE severity = 'Medium' AND status = 'Active'
-            "#
-        )
-        .fetch_one(&self.pool)
-        .await?
-        .count as i64;
-
-        let low = sqlx::query!(
-            r#"
-            SELECT COUNT(*) as count
-            FROM threat_events
-            WHERE severity = 'Low' AND status = 'Active'
-            "#
-        )
-        .fetch_one(&self.pool)
-        .await?
-        .count as i64;
+        let critical = self.count_active_by_severity(ThreatSeverity::Critical).await?;
+        let high = self.count_active_by_severity(ThreatSeverity::High).await?;
+        let medium = self.count_active_by_severity(ThreatSeverity::Medium).await?;
+        let low = self.count_active_by_severity(ThreatSeverity::Low).await?;
 
         Ok(ThreatStatistics {
             total,
@@ -282,6 +329,26 @@ impl ThreatRepository {
             },
         })
     }
+
+    async fn count_active_by_severity(&self, severity: ThreatSeverity) -> Result<i64, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM threat_events
+            WHERE severity = ? AND status = 'Active'
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
+            "#,
+            severity,
+            filter,
+            filter
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(result.count as i64)
+    }
 }
 
 /// 威胁统计信息
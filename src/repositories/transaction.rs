@@ -1,21 +1,64 @@
-use crate::models::{Transaction, TransactionStatus, TransactionType};
+use crate::models::{
+    DomainEvent, ReversalStatus, TenantContext, Transaction, TransactionEvent,
+    TransactionEventType, TransactionReversal, TransactionStatus, TransactionType,
+};
+use crate::repositories::outbox::{append_event, device_tenant};
+use crate::repositories::{DbExecutor, TenantScope, UnitOfWork, DEVICE_TENANT_FILTER};
 use crate::utils::error::AppError;
-use sqlx::SqlitePool;
+use sqlx::{Connection, SqlitePool};
+
+/// 交易表查询列，用于动态拼接的查询；固定查询使用 `query_as!` 在编译期校验
+const TRANSACTION_COLUMNS: &str = r#"
+    id, device_id, transaction_type, amount, currency,
+    status, encrypted_pin_block, ksn, card_number_masked,
+    merchant_id, terminal_id, authorization_code,
+    response_code, response_message,
+    client_ip, latitude, longitude, location_accuracy, location_timestamp,
+    processor, processor_reference, approved_amount,
+    original_transaction_id, auth_expires_at, settled_at, batch_id, client_transaction_id,
+    emv_data, message_mti, stan, rrn, transmission_time, created_at, updated_at
+"#;
 
 /// 交易Repository
 #[derive(Clone)]
 pub struct TransactionRepository {
-    pool: SqlitePool,
+    db: DbExecutor,
+    scope: TenantScope,
 }
 
 impl TransactionRepository {
-    /// 创建新的TransactionRepository
+    /// 创建新的TransactionRepository，绑定租户前不匹配任何租户的数据
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的TransactionRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { db: DbExecutor::Pool(pool), scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { db: self.db.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 返回绑定到工作单元的Repository，所有操作在该工作单元的事务中执行
+    pub fn in_unit_of_work(&self, uow: &UnitOfWork) -> Self {
+        Self { db: DbExecutor::UnitOfWork(uow.clone()), scope: self.scope.clone() }
+    }
+
+    /// 开启工作单元；已绑定工作单元时加入外层事务
+    pub async fn begin(&self) -> Result<UnitOfWork, AppError> {
+        self.db.begin().await
     }
 
     /// 创建交易
+    ///
+    /// 已有处理结果（非PENDING）的交易同时写入 `TransactionProcessed` 事件
     pub async fn create(&self, transaction: &Transaction) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
         sqlx::query!(
             r#"
             INSERT INTO transactions (
@@ -24,9 +67,12 @@ impl TransactionRepository {
                 merchant_id, terminal_id, authorization_code,
                 response_code, response_message,
                 client_ip, latitude, longitude, location_accuracy, location_timestamp,
-                created_at, updated_at
+                processor, processor_reference, approved_amount,
+                original_transaction_id, auth_expires_at, settled_at, batch_id, client_transaction_id,
+                emv_data, message_mti, stan, rrn, transmission_time, created_at, updated_at
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                    ?, ?, ?, ?, ?, ?)
             "#,
             transaction.id,
             transaction.device_id,
@@ -47,21 +93,82 @@ impl TransactionRepository {
             transaction.longitude,
             transaction.location_accuracy,
             transaction.location_timestamp,
+            transaction.processor,
+            transaction.processor_reference,
+            transaction.approved_amount,
+            transaction.original_transaction_id,
+            transaction.auth_expires_at,
+            transaction.settled_at,
+            transaction.batch_id,
+            transaction.client_transaction_id,
+            transaction.emv_data,
+            transaction.message_mti,
+            transaction.stan,
+            transaction.rrn,
+            transaction.transmission_time,
             transaction.created_at,
             transaction.updated_at,
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        if transaction.status != TransactionStatus::Pending {
+            let tenant_id = device_tenant(&mut tx, &transaction.device_id).await?;
+            append_event(&mut tx, &tenant_id, &processed_event(transaction)).await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 
+    /// 记录PENDING交易的处理结果，同时写入 `TransactionProcessed` 事件
+    ///
+    /// 交易已不是PENDING（如已被冲正）时不更新，返回false
+    pub async fn record_outcome(&self, transaction: &Transaction) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE transactions
+            SET status = ?, approved_amount = ?, authorization_code = ?, response_code = ?,
+                response_message = ?, processor_reference = ?, auth_expires_at = ?, updated_at = ?
+            WHERE id = ? AND status = ?
+            "#,
+        )
+        .bind(&transaction.status)
+        .bind(transaction.approved_amount)
+        .bind(&transaction.authorization_code)
+        .bind(&transaction.response_code)
+        .bind(&transaction.response_message)
+        .bind(&transaction.processor_reference)
+        .bind(&transaction.auth_expires_at)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(&transaction.id)
+        .bind(TransactionStatus::Pending)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        let tenant_id = device_tenant(&mut tx, &transaction.device_id).await?;
+        append_event(&mut tx, &tenant_id, &processed_event(transaction)).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
     /// 根据ID查找交易
     pub async fn find_by_id(&self, id: &str) -> Result<Option<Transaction>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         let transaction = sqlx::query_as!(
             Transaction,
             r#"
-            SELECT 
+            SELECT
                 id, device_id,
                 transaction_type as "transaction_type: _",
                 amount, currency,
@@ -69,18 +176,50 @@ impl TransactionRepository {
                 encrypted_pin_block, ksn, card_number_masked,
                 merchant_id, terminal_id, authorization_code,
                 response_code, response_message,
-                client_ip, 
-                latitude as "latitude: f64", 
-                longitude as "longitude: f64", 
-                location_accuracy as "location_accuracy: f64", 
+                client_ip,
+                latitude as "latitude: f64",
+                longitude as "longitude: f64",
+                location_accuracy as "location_accuracy: f64",
                 location_timestamp as "location_timestamp: chrono::NaiveDateTime",
+                processor, processor_reference, approved_amount,
+                original_transaction_id, auth_expires_at, settled_at, batch_id,
+                client_transaction_id, emv_data, message_mti, stan, rrn, transmission_time,
                 created_at, updated_at
             FROM transactions
             WHERE id = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             "#,
-            id
+            id,
+            filter,
+            filter
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(transaction)
+    }
+
+    /// 根据客户端交易ID查找设备的交易
+    pub async fn find_by_client_transaction_id(
+        &self,
+        device_id: &str,
+        client_transaction_id: &str,
+    ) -> Result<Option<Transaction>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let transaction = sqlx::query_as::<_, Transaction>(&format!(
+            r#"
+            SELECT {} FROM transactions
+            WHERE device_id = ? AND client_transaction_id = ? AND {}
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+            TRANSACTION_COLUMNS, DEVICE_TENANT_FILTER
+        ))
+        .bind(device_id)
+        .bind(client_transaction_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(transaction)
@@ -95,18 +234,9 @@ impl TransactionRepository {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<Transaction>, AppError> {
-        let mut query = String::from(
-            r#"
-            SELECT 
-                id, device_id, transaction_type, amount, currency,
-                status, encrypted_pin_block, ksn, card_number_masked,
-                merchant_id, terminal_id, authorization_code,
-                response_code, response_message,
-                client_ip, latitude, longitude, location_accuracy, location_timestamp,
-                created_at, updated_at
-            FROM transactions
-            WHERE 1=1
-            "#,
+        let mut query = format!(
+            "SELECT {} FROM transactions WHERE {}",
+            TRANSACTION_COLUMNS, DEVICE_TENANT_FILTER
         );
 
         if let Some(dev_id) = device_id {
@@ -124,7 +254,12 @@ impl TransactionRepository {
         query.push_str(" ORDER BY created_at DESC");
         query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
 
-        let transactions = sqlx::query_as::<_, Transaction>(&query).fetch_all(&self.pool).await?;
+        let mut conn = self.db.acquire().await?;
+        let transactions = sqlx::query_as::<_, Transaction>(&query)
+            .bind(self.scope.filter())
+            .bind(self.scope.filter())
+            .fetch_all(&mut *conn)
+            .await?;
 
         Ok(transactions)
     }
@@ -136,7 +271,8 @@ impl TransactionRepository {
         status: Option<TransactionStatus>,
         transaction_type: Option<TransactionType>,
     ) -> Result<i64, AppError> {
-        let mut query = String::from("SELECT COUNT(*) as count FROM transactions WHERE 1=1");
+        let mut query =
+            format!("SELECT COUNT(*) as count FROM transactions WHERE {}", DEVICE_TENANT_FILTER);
 
         if let Some(dev_id) = device_id {
             query.push_str(&format!(" AND device_id = '{}'", dev_id));
@@ -150,7 +286,12 @@ impl TransactionRepository {
             query.push_str(&format!(" AND transaction_type = '{:?}'", t));
         }
 
-        let result = sqlx::query_scalar::<_, i64>(&query).fetch_one(&self.pool).await?;
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query_scalar::<_, i64>(&query)
+            .bind(self.scope.filter())
+            .bind(self.scope.filter())
+            .fetch_one(&mut *conn)
+            .await?;
 
         Ok(result)
     }
@@ -166,6 +307,8 @@ impl TransactionRepository {
     ) -> Result<(), AppError> {
         let now = chrono::Utc::now().to_rfc3339();
 
+        let mut conn = self.db.acquire().await?;
+        let filter = self.scope.filter();
         sqlx::query!(
             r#"
             UPDATE transactions
@@ -175,15 +318,515 @@ impl TransactionRepository {
                 response_message = ?,
                 updated_at = ?
             WHERE id = ?
+                AND (? IS NULL OR device_id IN (SELECT id FROM devices WHERE tenant_id = ?))
             "#,
             status,
             authorization_code,
             response_code,
             response_message,
             now,
-            id
+            id,
+            filter,
+            filter
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 查找引用指定原交易的后续交易（退款、撤销、请款、追加授权）
+    pub async fn find_linked(
+        &self,
+        original_transaction_id: &str,
+    ) -> Result<Vec<Transaction>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let transactions = sqlx::query_as::<_, Transaction>(&format!(
+            "SELECT {} FROM transactions WHERE original_transaction_id = ? AND {} ORDER BY created_at",
+            TRANSACTION_COLUMNS, DEVICE_TENANT_FILTER
+        ))
+        .bind(original_transaction_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// 按创建顺序列出结算批次中的交易
+    pub async fn list_by_batch(&self, batch_id: &str) -> Result<Vec<Transaction>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let transactions = sqlx::query_as::<_, Transaction>(&format!(
+            "SELECT {} FROM transactions WHERE batch_id = ? AND {} ORDER BY created_at",
+            TRANSACTION_COLUMNS, DEVICE_TENANT_FILTER
+        ))
+        .bind(batch_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// 列出参与对账的交易：创建时间在 `[from, to)` 内、经处理器批准的消费、退款和请款
+    ///
+    /// 被撤销、已冲正以及预授权不参与清算；`merchant_id` 为None时不按商户筛选
+    pub async fn list_for_reconciliation(
+        &self,
+        from: &str,
+        to: &str,
+        merchant_id: Option<&str>,
+    ) -> Result<Vec<Transaction>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let transactions = sqlx::query_as::<_, Transaction>(&format!(
+            r#"
+            SELECT {} FROM transactions
+            WHERE created_at >= ? AND created_at < ?
+              AND transaction_type IN (?, ?, ?)
+              AND status IN (?, ?, ?)
+              AND (? IS NULL OR merchant_id = ?)
+              AND {}
+            ORDER BY created_at
+            "#,
+            TRANSACTION_COLUMNS, DEVICE_TENANT_FILTER
+        ))
+        .bind(from)
+        .bind(to)
+        .bind(TransactionType::Payment)
+        .bind(TransactionType::Refund)
+        .bind(TransactionType::Capture)
+        .bind(TransactionStatus::Approved)
+        .bind(TransactionStatus::PartiallyRefunded)
+        .bind(TransactionStatus::Refunded)
+        .bind(merchant_id)
+        .bind(merchant_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// 更新原交易的生命周期状态，`auth_expires_at` 为None时保持原有效期
+    pub async fn update_lifecycle(
+        &self,
+        id: &str,
+        status: TransactionStatus,
+        auth_expires_at: Option<&str>,
+    ) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE transactions
+            SET status = ?,
+                auth_expires_at = COALESCE(?, auth_expires_at),
+                updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(status)
+        .bind(auth_expires_at)
+        .bind(now)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 后续交易提交处理器前原子占用原交易，返回是否占用成功
+    ///
+    /// 退款累加原交易已占用的退款金额，累计不超过批准金额；请款、撤销和追加授权占用原交易，
+    /// 同一原交易同时只能有一笔进行中，撤销要求原交易没有已占用的退款
+    pub async fn reserve_follow_up(
+        &self,
+        original_id: &str,
+        transaction: &Transaction,
+    ) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = match transaction.transaction_type {
+            TransactionType::Payment => return Ok(true),
+            TransactionType::Refund => {
+                sqlx::query(
+                    r#"
+                    UPDATE transactions
+                    SET refunded_amount = refunded_amount + ?
+                    WHERE id = ? AND status IN (?, ?) AND pending_follow_up_id IS NULL
+                      AND refunded_amount + ? <= COALESCE(approved_amount, amount)
+                    "#,
+                )
+                .bind(transaction.amount)
+                .bind(original_id)
+                .bind(TransactionStatus::Approved)
+                .bind(TransactionStatus::PartiallyRefunded)
+                .bind(transaction.amount)
+                .execute(&mut *conn)
+                .await?
+            },
+            TransactionType::Capture | TransactionType::PreAuth | TransactionType::Void => {
+                sqlx::query(
+                    r#"
+                    UPDATE transactions
+                    SET pending_follow_up_id = ?
+                    WHERE id = ? AND status = ? AND pending_follow_up_id IS NULL
+                      AND (? = 0 OR refunded_amount = 0)
+                    "#,
+                )
+                .bind(&transaction.id)
+                .bind(original_id)
+                .bind(TransactionStatus::Approved)
+                .bind(transaction.transaction_type == TransactionType::Void)
+                .execute(&mut *conn)
+                .await?
+            },
+        };
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 释放后续交易对原交易的占用（后续交易被拒绝、失败或已冲正）
+    pub async fn release_follow_up(
+        &self,
+        original_id: &str,
+        transaction: &Transaction,
+    ) -> Result<(), AppError> {
+        match transaction.transaction_type {
+            TransactionType::Payment => {},
+            TransactionType::Refund => self.release_refund(original_id, transaction.amount).await?,
+            TransactionType::Capture | TransactionType::PreAuth | TransactionType::Void => {
+                let mut conn = self.db.acquire().await?;
+                sqlx::query(
+                    r#"
+                    UPDATE transactions SET pending_follow_up_id = NULL
+                    WHERE id = ? AND pending_follow_up_id = ?
+                    "#,
+                )
+                .bind(original_id)
+                .bind(&transaction.id)
+                .execute(&mut *conn)
+                .await?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// 释放原交易已占用的退款金额（退款未成功或已被撤销）
+    pub async fn release_refund(&self, original_id: &str, amount: i64) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            "UPDATE transactions SET refunded_amount = MAX(refunded_amount - ?, 0) WHERE id = ?",
+        )
+        .bind(amount)
+        .bind(original_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 把超过有效期仍未请款的预授权标记为EXPIRED，返回更新的数量
+    ///
+    /// 状态历史与状态更新在同一事务中写入
+    pub async fn expire_preauths(&self, now: &str) -> Result<u64, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let condition = format!(
+            "transaction_type = ? AND status = ? AND auth_expires_at <= ? AND {}",
+            DEVICE_TENANT_FILTER
+        );
+
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO transaction_events (
+                transaction_id, event_type, from_status, to_status, actor, created_at
+            )
+            SELECT id, ?, status, ?, 'system', ? FROM transactions WHERE {}
+            "#,
+            condition
+        ))
+        .bind(TransactionEventType::Expired.as_str())
+        .bind(TransactionStatus::Expired)
+        .bind(now)
+        .bind(TransactionType::PreAuth)
+        .bind(TransactionStatus::Approved)
+        .bind(now)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query(&format!(
+            "UPDATE transactions SET status = ?, updated_at = ? WHERE {}",
+            condition
+        ))
+        .bind(TransactionStatus::Expired)
+        .bind(now)
+        .bind(TransactionType::PreAuth)
+        .bind(TransactionStatus::Approved)
+        .bind(now)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 追加交易状态历史
+    pub async fn record_event(&self, event: &TransactionEvent) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO transaction_events (
+                transaction_id, event_type, from_status, to_status,
+                response_code, details, actor, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&event.transaction_id)
+        .bind(&event.event_type)
+        .bind(&event.from_status)
+        .bind(&event.to_status)
+        .bind(&event.response_code)
+        .bind(&event.details)
+        .bind(&event.actor)
+        .bind(&event.created_at)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 按写入顺序列出交易的状态历史
+    pub async fn list_events(&self, transaction_id: &str) -> Result<Vec<TransactionEvent>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let events = sqlx::query_as::<_, TransactionEvent>(&format!(
+            r#"
+            SELECT e.* FROM transaction_events e
+            JOIN transactions t ON t.id = e.transaction_id
+            WHERE e.transaction_id = ? AND {}
+            ORDER BY e.seq ASC
+            "#,
+            DEVICE_TENANT_FILTER.replace("device_id", "t.device_id")
+        ))
+        .bind(transaction_id)
+        .bind(self.scope.filter())
+        .bind(self.scope.filter())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// 加入冲正队列；交易已有冲正记录时不重复加入，返回是否新加入
+    pub async fn enqueue_reversal(&self, reversal: &TransactionReversal) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO transaction_reversals (
+                id, transaction_id, reason, requested_by, status,
+                attempts, next_attempt_at, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&reversal.id)
+        .bind(&reversal.transaction_id)
+        .bind(&reversal.reason)
+        .bind(&reversal.requested_by)
+        .bind(&reversal.status)
+        .bind(reversal.attempts)
+        .bind(&reversal.next_attempt_at)
+        .bind(&reversal.created_at)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 取消尚未开始执行的冲正（提交处理器前预登记、处理器已给出明确结果），返回是否取消
+    pub async fn cancel_reversal(&self, transaction_id: &str) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query(
+            "DELETE FROM transaction_reversals WHERE transaction_id = ? AND status = ? AND attempts = 0",
+        )
+        .bind(transaction_id)
+        .bind(ReversalStatus::Pending.as_str())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 调整尚未开始执行的冲正的执行时间，返回是否调整
+    pub async fn reschedule_reversal(
+        &self,
+        transaction_id: &str,
+        next_attempt_at: &str,
+    ) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE transaction_reversals SET next_attempt_at = ?
+            WHERE transaction_id = ? AND status = ? AND attempts = 0
+            "#,
+        )
+        .bind(next_attempt_at)
+        .bind(transaction_id)
+        .bind(ReversalStatus::Pending.as_str())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 查找交易的冲正记录
+    pub async fn find_reversal(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Option<TransactionReversal>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let reversal = sqlx::query_as::<_, TransactionReversal>(
+            "SELECT * FROM transaction_reversals WHERE transaction_id = ?",
+        )
+        .bind(transaction_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(reversal)
+    }
+
+    /// 重新排队已失败的冲正，重置尝试次数
+    pub async fn requeue_reversal(&self, id: &str, requested_by: &str) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE transaction_reversals
+            SET status = ?, attempts = 0, next_attempt_at = ?, requested_by = ?, completed_at = NULL
+            WHERE id = ? AND status = ?
+            "#,
+        )
+        .bind(ReversalStatus::Pending.as_str())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(requested_by)
+        .bind(id)
+        .bind(ReversalStatus::Failed.as_str())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 列出已到期待执行的冲正（按创建顺序）
+    pub async fn list_due_reversals(
+        &self,
+        now: &str,
+        limit: i64,
+    ) -> Result<Vec<TransactionReversal>, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let reversals = sqlx::query_as::<_, TransactionReversal>(
+            r#"
+            SELECT * FROM transaction_reversals
+            WHERE status = ? AND next_attempt_at <= ?
+            ORDER BY created_at ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(ReversalStatus::Pending.as_str())
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(reversals)
+    }
+
+    /// 领取冲正：计入尝试次数，并将下次尝试时间推迟到租约结束，防止被其他节点重复领取
+    pub async fn claim_reversal(
+        &self,
+        id: &str,
+        now: &str,
+        lease_until: &str,
+    ) -> Result<bool, AppError> {
+        let mut conn = self.db.acquire().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE transaction_reversals
+            SET attempts = attempts + 1, next_attempt_at = ?
+            WHERE id = ? AND status = ? AND next_attempt_at <= ?
+            "#,
+        )
+        .bind(lease_until)
+        .bind(id)
+        .bind(ReversalStatus::Pending.as_str())
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 记录冲正成功
+    pub async fn complete_reversal(&self, id: &str, response_code: &str) -> Result<(), AppError> {
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE transaction_reversals
+            SET status = ?, response_code = ?, last_error = NULL, completed_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(ReversalStatus::Completed.as_str())
+        .bind(response_code)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 记录冲正失败；`next_attempt_at` 为None时不再重试，标记为FAILED
+    pub async fn fail_reversal(
+        &self,
+        id: &str,
+        error: &str,
+        response_code: Option<&str>,
+        next_attempt_at: Option<&str>,
+    ) -> Result<(), AppError> {
+        let status = match next_attempt_at {
+            Some(_) => ReversalStatus::Pending,
+            None => ReversalStatus::Failed,
+        };
+
+        let mut conn = self.db.acquire().await?;
+        sqlx::query(
+            r#"
+            UPDATE transaction_reversals
+            SET status = ?, last_error = ?, response_code = COALESCE(?, response_code),
+                next_attempt_at = COALESCE(?, next_attempt_at),
+                completed_at = CASE WHEN ? IS NULL THEN ? ELSE NULL END
+            WHERE id = ?
+            "#,
         )
-        .execute(&self.pool)
+        .bind(status.as_str())
+        .bind(error)
+        .bind(response_code)
+        .bind(next_attempt_at)
+        .bind(next_attempt_at)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -194,31 +837,32 @@ impl TransactionRepository {
         &self,
         device_id: &str,
     ) -> Result<TransactionStats, AppError> {
+        let mut conn = self.db.acquire().await?;
         let total =
             sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM transactions WHERE device_id = ?")
                 .bind(device_id)
-                .fetch_one(&self.pool)
+                .fetch_one(&mut *conn)
                 .await?;
 
         let approved = sqlx::query_scalar::<_, i64>(
             "SELECT COUNT(*) FROM transactions WHERE device_id = ? AND status = 'Approved'",
         )
         .bind(device_id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         let declined = sqlx::query_scalar::<_, i64>(
             "SELECT COUNT(*) FROM transactions WHERE device_id = ? AND status = 'Declined'",
         )
         .bind(device_id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         let total_amount = sqlx::query_scalar::<_, Option<i64>>(
             "SELECT SUM(amount) FROM transactions WHERE device_id = ? AND status = 'Approved'",
         )
         .bind(device_id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(TransactionStats { total, approved, declined, total_amount: total_amount.unwrap_or(0) })
@@ -233,3 +877,20 @@ pub struct TransactionStats {
     pub declined: i64,
     pub total_amount: i64,
 }
+
+/// 交易处理完成的领域事件
+fn processed_event(transaction: &Transaction) -> DomainEvent {
+    DomainEvent::TransactionProcessed {
+        transaction_id: transaction.id.clone(),
+        device_id: transaction.device_id.clone(),
+        merchant_id: transaction.merchant_id.clone(),
+        terminal_id: transaction.terminal_id.clone(),
+        transaction_type: transaction.transaction_type.clone(),
+        amount: transaction.amount,
+        currency: transaction.currency.clone(),
+        status: transaction.status.clone(),
+        authorization_code: transaction.authorization_code.clone(),
+        response_code: transaction.response_code.clone(),
+        card_number_masked: transaction.card_number_masked.clone(),
+    }
+}
@@ -0,0 +1,65 @@
+use crate::models::TokenUsageRecord;
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// 交易令牌使用记录Repository
+///
+/// 以 `jti` 主键唯一约束保证同一令牌只能被占用一次
+#[derive(Clone)]
+pub struct TransactionTokenRepository {
+    pool: SqlitePool,
+}
+
+impl TransactionTokenRepository {
+    /// 创建新的TransactionTokenRepository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 令牌是否已使用
+    pub async fn is_used(&self, jti: &str) -> Result<bool, AppError> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM transaction_token_usage WHERE jti = ?")
+                .bind(jti)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(count > 0)
+    }
+
+    /// 占用令牌，返回是否占用成功（令牌已被使用时返回false）
+    pub async fn try_mark_used(
+        &self,
+        jti: &str,
+        record: &TokenUsageRecord,
+        expires_at: &str,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO transaction_token_usage (
+                jti, device_id, transaction_id, used_at, expires_at
+            )
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(jti)
+        .bind(&record.device_id)
+        .bind(&record.transaction_id)
+        .bind(&record.used_at)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 删除令牌已过期的使用记录，返回删除数量
+    pub async fn delete_expired(&self, now: &str) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM transaction_token_usage WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
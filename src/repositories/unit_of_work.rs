@@ -0,0 +1,202 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use sqlx::{pool::PoolConnection, Sqlite, SqliteConnection, SqlitePool};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::utils::error::AppError;
+
+type SharedTransaction = Arc<Mutex<Option<sqlx::Transaction<'static, Sqlite>>>>;
+
+/// 工作单元
+///
+/// 持有一个数据库事务，通过各Repository的 `in_unit_of_work` 绑定后，多个Repository的操作在同一事务中执行。
+/// 调用 [`UnitOfWork::commit`] 后一并生效；未提交即丢弃（如中途返回错误）时全部回滚。
+/// 在已绑定工作单元的Repository上再次开启时加入外层事务，由外层负责提交
+#[derive(Clone)]
+pub struct UnitOfWork {
+    tx: SharedTransaction,
+    nested: bool,
+}
+
+impl UnitOfWork {
+    /// 在连接池上开启新的工作单元
+    pub async fn begin(pool: &SqlitePool) -> Result<Self, AppError> {
+        let tx = pool.begin().await?;
+        Ok(Self { tx: Arc::new(Mutex::new(Some(tx))), nested: false })
+    }
+
+    /// 提交事务；加入外层事务的工作单元由外层提交，此处不做操作
+    pub async fn commit(self) -> Result<(), AppError> {
+        if self.nested {
+            return Ok(());
+        }
+
+        let tx = self.tx.lock().await.take().ok_or_else(finished)?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 回滚事务；加入外层事务时回滚整个外层事务
+    pub async fn rollback(self) -> Result<(), AppError> {
+        if let Some(tx) = self.tx.lock().await.take() {
+            tx.rollback().await?;
+        }
+
+        Ok(())
+    }
+
+    fn join(&self) -> Self {
+        Self { tx: self.tx.clone(), nested: true }
+    }
+}
+
+/// Repository的执行器：直接使用连接池（每条语句自动提交），或在工作单元的事务中执行
+#[derive(Clone)]
+pub enum DbExecutor {
+    Pool(SqlitePool),
+    UnitOfWork(UnitOfWork),
+}
+
+impl DbExecutor {
+    /// 获取执行语句的连接；工作单元模式下独占其事务直到连接释放
+    pub async fn acquire(&self) -> Result<DbConnection, AppError> {
+        match self {
+            DbExecutor::Pool(pool) => Ok(DbConnection::Pool(pool.acquire().await?)),
+            DbExecutor::UnitOfWork(uow) => {
+                let guard = uow.tx.clone().lock_owned().await;
+                if guard.is_none() {
+                    return Err(finished());
+                }
+                Ok(DbConnection::UnitOfWork(guard))
+            },
+        }
+    }
+
+    /// 开启工作单元；已在工作单元中时加入该事务
+    pub async fn begin(&self) -> Result<UnitOfWork, AppError> {
+        match self {
+            DbExecutor::Pool(pool) => UnitOfWork::begin(pool).await,
+            DbExecutor::UnitOfWork(uow) => Ok(uow.join()),
+        }
+    }
+}
+
+/// 由 [`DbExecutor::acquire`] 获取的连接
+pub enum DbConnection {
+    Pool(PoolConnection<Sqlite>),
+    UnitOfWork(OwnedMutexGuard<Option<sqlx::Transaction<'static, Sqlite>>>),
+}
+
+impl Deref for DbConnection {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            DbConnection::Pool(conn) => conn,
+            // acquire时已确认事务未结束
+            DbConnection::UnitOfWork(guard) => guard.as_ref().expect("unit of work finished"),
+        }
+    }
+}
+
+impl DerefMut for DbConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            DbConnection::Pool(conn) => conn,
+            DbConnection::UnitOfWork(guard) => guard.as_mut().expect("unit of work finished"),
+        }
+    }
+}
+
+fn finished() -> AppError {
+    AppError::InternalWithMessage("Unit of work already committed or rolled back".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::Connection;
+
+    async fn setup() -> SqlitePool {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE items (name TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn insert(db: &DbExecutor, name: &str) {
+        let mut conn = db.acquire().await.unwrap();
+        sqlx::query("INSERT INTO items (name) VALUES (?)")
+            .bind(name)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+    }
+
+    async fn count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM items").fetch_one(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_commit_and_drop() {
+        let pool = setup().await;
+
+        let uow = UnitOfWork::begin(&pool).await.unwrap();
+        insert(&DbExecutor::UnitOfWork(uow.clone()), "a").await;
+        uow.commit().await.unwrap();
+        assert_eq!(count(&pool).await, 1);
+
+        // 未提交即丢弃时回滚
+        let uow = UnitOfWork::begin(&pool).await.unwrap();
+        insert(&DbExecutor::UnitOfWork(uow.clone()), "b").await;
+        drop(uow);
+        assert_eq!(count(&pool).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_nested_joins_outer() {
+        let pool = setup().await;
+        let outer = UnitOfWork::begin(&pool).await.unwrap();
+        let db = DbExecutor::UnitOfWork(outer.clone());
+
+        let inner = db.begin().await.unwrap();
+        insert(&DbExecutor::UnitOfWork(inner.clone()), "a").await;
+        inner.commit().await.unwrap();
+
+        // 内层提交不生效，外层回滚后全部撤销
+        outer.rollback().await.unwrap();
+        assert_eq!(count(&pool).await, 0);
+        assert!(db.acquire().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_savepoint_within_unit_of_work() {
+        let pool = setup().await;
+        let uow = UnitOfWork::begin(&pool).await.unwrap();
+        let db = DbExecutor::UnitOfWork(uow.clone());
+
+        insert(&db, "a").await;
+        {
+            let mut conn = db.acquire().await.unwrap();
+            let mut savepoint = conn.begin().await.unwrap();
+            sqlx::query("INSERT INTO items (name) VALUES ('b')")
+                .execute(&mut *savepoint)
+                .await
+                .unwrap();
+            savepoint.rollback().await.unwrap();
+        }
+        uow.commit().await.unwrap();
+
+        assert_eq!(count(&pool).await, 1);
+    }
+}
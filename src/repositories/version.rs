@@ -1,31 +1,54 @@
 use sqlx::SqlitePool;
-use crate::models::{SdkVersion, VersionStatus};
+use crate::models::{DomainEvent, SdkVersion, TenantContext, VersionStatus};
+use crate::repositories::{outbox::append_event, TenantScope};
 use crate::utils::error::AppError;
 
+/// 版本表查询列，用于动态拼接的查询；固定查询使用 `query_as!` 在编译期校验
+const VERSION_COLUMNS: &str = r#"
+    id, tenant_id, version, update_type, status, download_url,
+    checksum, file_size, release_notes, min_os_version,
+    target_devices, distribution_strategy, created_at, released_at
+"#;
+
 /// SDK版本Repository
 #[derive(Clone)]
 pub struct VersionRepository {
     pool: SqlitePool,
+    scope: TenantScope,
 }
 
 impl VersionRepository {
-    /// 创建新的VersionRepository
+    /// 创建新的VersionRepository，绑定租户前不匹配任何租户的数据
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的VersionRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
     }
 
-    /// 创建版本
+    /// 创建版本，直接以发布状态创建时写入 `VersionPublished` 事件
     pub async fn create(&self, version: &SdkVersion) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let tenant_id = self.scope.owner(&version.tenant_id);
         sqlx::query!(
             r#"
             INSERT INTO sdk_versions (
-                id, version, update_type, status, download_url,
+                id, tenant_id, version, update_type, status, download_url,
                 checksum, file_size, release_notes, min_os_version,
                 target_devices, distribution_strategy, created_at, released_at
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             version.id,
+            tenant_id,
             version.version,
             version.update_type,
             version.status,
@@ -39,28 +62,36 @@ impl VersionRepository {
             version.created_at,
             version.released_at,
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        if version.status == VersionStatus::Released {
+            append_published(&mut tx, &version.id).await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 
     /// 根据ID查找版本
     pub async fn find_by_id(&self, id: &str) -> Result<Option<SdkVersion>, AppError> {
+        let filter = self.scope.filter();
         let version = sqlx::query_as!(
             SdkVersion,
             r#"
-            SELECT 
-                id, version,
+            SELECT
+                id, tenant_id, version,
                 update_type as "update_type: _",
                 status as "status: _",
                 download_url, checksum, file_size, release_notes,
                 min_os_version, target_devices, distribution_strategy,
                 created_at, released_at
             FROM sdk_versions
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
-            id
+            id,
+            filter
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -70,20 +101,22 @@ impl VersionRepository {
 
     /// 根据版本号查找
     pub async fn find_by_version(&self, version: &str) -> Result<Option<SdkVersion>, AppError> {
+        let filter = self.scope.filter();
         let sdk_version = sqlx::query_as!(
             SdkVersion,
             r#"
-            SELECT 
-                id, version,
+            SELECT
+                id, tenant_id, version,
                 update_type as "update_type: _",
                 status as "status: _",
                 download_url, checksum, file_size, release_notes,
                 min_os_version, target_devices, distribution_strategy,
                 created_at, released_at
             FROM sdk_versions
-            WHERE version = ?
+            WHERE version = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
-            version
+            version,
+            filter
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -98,15 +131,9 @@ impl VersionRepository {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<SdkVersion>, AppError> {
-        let mut query = String::from(
-            r#"
-            SELECT 
-                id, version, update_type, status, download_url,
-                checksum, file_size, release_notes, min_os_version,
-                target_devices, distribution_strategy, created_at, released_at
-            FROM sdk_versions
-            WHERE 1=1
-            "#,
+        let mut query = format!(
+            "SELECT {} FROM sdk_versions WHERE tenant_id = COALESCE(?, tenant_id)",
+            VERSION_COLUMNS
         );
 
         if let Some(s) = status {
@@ -117,6 +144,7 @@ impl VersionRepository {
         query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
 
         let versions = sqlx::query_as::<_, SdkVersion>(&query)
+            .bind(self.scope.filter())
             .fetch_all(&self.pool)
             .await?;
 
@@ -125,21 +153,28 @@ impl VersionRepository {
 
     /// 统计版本总数
     pub async fn count(&self, status: Option<VersionStatus>) -> Result<i64, AppError> {
-        let mut query = String::from("SELECT COUNT(*) as count FROM sdk_versions WHERE 1=1");
+        let mut query = String::from(
+            "SELECT COUNT(*) as count FROM sdk_versions WHERE tenant_id = COALESCE(?, tenant_id)",
+        );
 
         if let Some(s) = status {
             query.push_str(&format!(" AND status = '{:?}'", s));
         }
 
         let result = sqlx::query_scalar::<_, i64>(&query)
+            .bind(self.scope.filter())
             .fetch_one(&self.pool)
             .await?;
 
         Ok(result)
     }
 
-    /// 更新版本
+    /// 更新版本，状态变为发布时写入 `VersionPublished` 事件
     pub async fn update(&self, version: &SdkVersion) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let previous = current_status(&mut tx, &version.id).await?;
+
+        let filter = self.scope.filter();
         sqlx::query!(
             r#"
             UPDATE sdk_versions
@@ -152,7 +187,7 @@ impl VersionRepository {
                 target_devices = ?,
                 distribution_strategy = ?,
                 released_at = ?
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
             version.status,
             version.download_url,
@@ -164,14 +199,21 @@ impl VersionRepository {
             version.distribution_strategy,
             version.released_at,
             version.id,
+            filter
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        if version.status == VersionStatus::Released && previous != Some(VersionStatus::Released) {
+            append_published(&mut tx, &version.id).await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 
-    /// 更新版本状态
+    /// 更新版本状态，状态变为发布时写入 `VersionPublished` 事件
     pub async fn update_status(&self, id: &str, status: VersionStatus) -> Result<(), AppError> {
         let released_at = if status == VersionStatus::Released {
             Some(chrono::Utc::now().to_rfc3339())
@@ -179,39 +221,52 @@ impl VersionRepository {
             None
         };
 
+        let mut tx = self.pool.begin().await?;
+        let previous = current_status(&mut tx, id).await?;
+
+        let filter = self.scope.filter();
         sqlx::query!(
             r#"
             UPDATE sdk_versions
             SET status = ?, released_at = ?
-            WHERE id = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
             "#,
             status,
             released_at,
-            id
+            id,
+            filter
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        if status == VersionStatus::Released && previous != Some(VersionStatus::Released) {
+            append_published(&mut tx, id).await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 
     /// 获取最新的已发布版本
     pub async fn get_latest_released(&self) -> Result<Option<SdkVersion>, AppError> {
+        let filter = self.scope.filter();
         let version = sqlx::query_as!(
             SdkVersion,
             r#"
-            SELECT 
-                id, version,
+            SELECT
+                id, tenant_id, version,
                 update_type as "update_type: _",
                 status as "status: _",
                 download_url, checksum, file_size, release_notes,
                 min_os_version, target_devices, distribution_strategy,
                 created_at, released_at
             FROM sdk_versions
-            WHERE status = 'Released'
+            WHERE status = 'Released' AND tenant_id = COALESCE(?, tenant_id)
             ORDER BY released_at DESC
             LIMIT 1
-            "#
+            "#,
+            filter
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -219,3 +274,40 @@ impl VersionRepository {
         Ok(version)
     }
 }
+
+/// 在事务中读取版本当前状态
+async fn current_status(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    id: &str,
+) -> Result<Option<VersionStatus>, AppError> {
+    let status = sqlx::query_scalar::<_, VersionStatus>("SELECT status FROM sdk_versions WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    Ok(status)
+}
+
+/// 在事务中写入版本发布事件
+async fn append_published(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    id: &str,
+) -> Result<(), AppError> {
+    let Some(version) = sqlx::query_as::<_, SdkVersion>(&format!(
+        "SELECT {} FROM sdk_versions WHERE id = ?",
+        VERSION_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut **tx)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let event = DomainEvent::VersionPublished {
+        version_id: version.id.clone(),
+        version: version.version.clone(),
+        update_type: version.update_type.clone(),
+    };
+    append_event(tx, &version.tenant_id, &event).await
+}
@@ -0,0 +1,376 @@
+use crate::models::{TenantContext, WebhookDelivery, WebhookDeliveryStatus, WebhookSubscription};
+use crate::repositories::TenantScope;
+use crate::utils::error::AppError;
+use sqlx::SqlitePool;
+
+/// Webhook订阅与投递队列Repository
+///
+/// 投递状态更新以当前状态为条件执行，多个节点同时处理队列时同一投递只会被领取一次
+#[derive(Clone)]
+pub struct WebhookRepository {
+    pool: SqlitePool,
+    scope: TenantScope,
+}
+
+impl WebhookRepository {
+    /// 创建新的WebhookRepository，绑定租户前不匹配任何租户的数据
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::default() }
+    }
+
+    /// 创建不做租户过滤的WebhookRepository，仅用于系统任务和已按设备鉴权的设备端调用
+    pub fn unscoped(pool: SqlitePool) -> Self {
+        Self { pool, scope: TenantScope::all() }
+    }
+
+    /// 返回绑定到指定租户的Repository
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { pool: self.pool.clone(), scope: TenantScope::new(tenant) }
+    }
+
+    /// 创建订阅
+    pub async fn create_subscription(
+        &self,
+        subscription: &WebhookSubscription,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_subscriptions (
+                id, tenant_id, merchant_id, url, secret, event_types, active,
+                created_by, created_at, updated_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&subscription.id)
+        .bind(self.scope.owner(&subscription.tenant_id))
+        .bind(&subscription.merchant_id)
+        .bind(&subscription.url)
+        .bind(&subscription.secret)
+        .bind(&subscription.event_types)
+        .bind(subscription.active)
+        .bind(&subscription.created_by)
+        .bind(&subscription.created_at)
+        .bind(&subscription.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找订阅
+    pub async fn find_subscription(
+        &self,
+        id: &str,
+    ) -> Result<Option<WebhookSubscription>, AppError> {
+        let subscription = sqlx::query_as::<_, WebhookSubscription>(
+            "SELECT * FROM webhook_subscriptions WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(subscription)
+    }
+
+    /// 列出订阅（按创建时间倒序）
+    pub async fn list_subscriptions(
+        &self,
+        merchant_id: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookSubscription>, AppError> {
+        let subscriptions = sqlx::query_as::<_, WebhookSubscription>(
+            r#"
+            SELECT * FROM webhook_subscriptions
+            WHERE merchant_id = COALESCE(?, merchant_id) AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(merchant_id)
+        .bind(self.scope.filter())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    /// 统计订阅数量
+    pub async fn count_subscriptions(&self, merchant_id: Option<&str>) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM webhook_subscriptions
+            WHERE merchant_id = COALESCE(?, merchant_id) AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(merchant_id)
+        .bind(self.scope.filter())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 列出商户启用中的订阅
+    pub async fn list_active_for_merchant(
+        &self,
+        tenant_id: &str,
+        merchant_id: &str,
+    ) -> Result<Vec<WebhookSubscription>, AppError> {
+        let subscriptions = sqlx::query_as::<_, WebhookSubscription>(
+            r#"
+            SELECT * FROM webhook_subscriptions
+            WHERE tenant_id = ? AND merchant_id = ? AND active = 1
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(merchant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    /// 更新订阅的地址、事件类型和启用状态
+    pub async fn update_subscription(
+        &self,
+        subscription: &WebhookSubscription,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_subscriptions
+            SET url = ?, event_types = ?, active = ?, updated_at = ?
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(&subscription.url)
+        .bind(&subscription.event_types)
+        .bind(subscription.active)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(&subscription.id)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 删除订阅（投递记录随之删除）
+    pub async fn delete_subscription(&self, id: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "DELETE FROM webhook_subscriptions WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 加入投递队列
+    pub async fn create_delivery(&self, delivery: &WebhookDelivery) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (
+                id, tenant_id, subscription_id, event_id, event_type, payload, status,
+                attempts, max_attempts, next_attempt_at, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&delivery.id)
+        .bind(&delivery.tenant_id)
+        .bind(&delivery.subscription_id)
+        .bind(&delivery.event_id)
+        .bind(&delivery.event_type)
+        .bind(&delivery.payload)
+        .bind(&delivery.status)
+        .bind(delivery.attempts)
+        .bind(delivery.max_attempts)
+        .bind(&delivery.next_attempt_at)
+        .bind(&delivery.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 根据ID查找投递
+    pub async fn find_delivery(&self, id: &str) -> Result<Option<WebhookDelivery>, AppError> {
+        let delivery = sqlx::query_as::<_, WebhookDelivery>(
+            "SELECT * FROM webhook_deliveries WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)",
+        )
+        .bind(id)
+        .bind(self.scope.filter())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    /// 列出订阅的投递记录（按创建时间倒序）
+    pub async fn list_deliveries(
+        &self,
+        subscription_id: &str,
+        status: Option<WebhookDeliveryStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE subscription_id = ? AND status = COALESCE(?, status)
+              AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(status.map(|s| s.as_str()))
+        .bind(self.scope.filter())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// 统计订阅的投递数量
+    pub async fn count_deliveries(
+        &self,
+        subscription_id: &str,
+        status: Option<WebhookDeliveryStatus>,
+    ) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM webhook_deliveries
+            WHERE subscription_id = ? AND status = COALESCE(?, status)
+              AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(status.map(|s| s.as_str()))
+        .bind(self.scope.filter())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 列出已到重试时间的待投递记录
+    pub async fn list_due(&self, now: &str, limit: i64) -> Result<Vec<WebhookDelivery>, AppError> {
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE status = ? AND next_attempt_at <= ? AND tenant_id = COALESCE(?, tenant_id)
+            ORDER BY next_attempt_at ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(WebhookDeliveryStatus::Pending.as_str())
+        .bind(now)
+        .bind(self.scope.filter())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// 领取一次投递：计入尝试次数，并将下次尝试时间推迟到租约结束，防止被其他节点重复领取
+    pub async fn claim(&self, id: &str, now: &str, lease_until: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET attempts = attempts + 1, last_attempt_at = ?, next_attempt_at = ?
+            WHERE id = ? AND status = ? AND next_attempt_at <= ?
+            "#,
+        )
+        .bind(now)
+        .bind(lease_until)
+        .bind(id)
+        .bind(WebhookDeliveryStatus::Pending.as_str())
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 记录投递成功
+    pub async fn mark_succeeded(&self, id: &str, response_status: i64) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = ?, response_status = ?, last_error = NULL, delivered_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(WebhookDeliveryStatus::Succeeded.as_str())
+        .bind(response_status)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 记录投递失败，`next_attempt_at` 为None时进入死信状态
+    pub async fn mark_failed(
+        &self,
+        id: &str,
+        response_status: Option<i64>,
+        error: &str,
+        next_attempt_at: Option<&str>,
+    ) -> Result<(), AppError> {
+        let status = match next_attempt_at {
+            Some(_) => WebhookDeliveryStatus::Pending,
+            None => WebhookDeliveryStatus::DeadLetter,
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = ?, response_status = ?, last_error = ?,
+                next_attempt_at = COALESCE(?, next_attempt_at)
+            WHERE id = ?
+            "#,
+        )
+        .bind(status.as_str())
+        .bind(response_status)
+        .bind(error)
+        .bind(next_attempt_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 重新加入投递队列：重置尝试次数并立即可投递
+    pub async fn requeue(&self, id: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = ?, attempts = 0, next_attempt_at = ?, delivered_at = NULL
+            WHERE id = ? AND tenant_id = COALESCE(?, tenant_id)
+            "#,
+        )
+        .bind(WebhookDeliveryStatus::Pending.as_str())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(id)
+        .bind(self.scope.filter())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -62,6 +62,59 @@ pub fn verify_signature(
     }
 }
 
+/// 使用设备注册公钥验证签名
+///
+/// 公钥支持PEM或Base64/DER编码的SubjectPublicKeyInfo（RSA或EC P-256）及PKCS#1 RSA公钥；
+/// RSA使用PKCS#1 v1.5 + SHA-256，EC使用ASN.1编码的ECDSA P-256 + SHA-256。
+pub fn verify_device_signature(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<bool, AppError> {
+    use x509_parser::{
+        oid_registry::{OID_KEY_TYPE_EC_PUBLIC_KEY, OID_PKCS1_RSAENCRYPTION},
+        prelude::FromDer,
+        x509::SubjectPublicKeyInfo,
+    };
+
+    let (label, der) = decode_public_key(public_key)?;
+
+    if label.as_deref() == Some("RSA PUBLIC KEY") {
+        return verify_signature(&der, message, signature);
+    }
+
+    let (_, spki) = SubjectPublicKeyInfo::from_der(&der)
+        .map_err(|_| AppError::BadRequest("Invalid device public key".to_string()))?;
+    let key = spki.subject_public_key.data.as_ref();
+
+    if spki.algorithm.algorithm == OID_PKCS1_RSAENCRYPTION {
+        verify_signature(key, message, signature)
+    } else if spki.algorithm.algorithm == OID_KEY_TYPE_EC_PUBLIC_KEY {
+        let public_key = UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, key);
+        Ok(public_key.verify(message, signature).is_ok())
+    } else {
+        Err(AppError::BadRequest("Unsupported device public key algorithm".to_string()))
+    }
+}
+
+/// 解码公钥，返回PEM标签（如有）和DER内容
+fn decode_public_key(public_key: &[u8]) -> Result<(Option<String>, Vec<u8>), AppError> {
+    let invalid = || AppError::BadRequest("Invalid device public key".to_string());
+
+    if public_key.starts_with(b"-----BEGIN") {
+        let (_, pem) = x509_parser::pem::parse_x509_pem(public_key).map_err(|_| invalid())?;
+        return Ok((Some(pem.label), pem.contents));
+    }
+
+    // 0x30为DER SEQUENCE标签，否则按Base64文本处理
+    if public_key.first() == Some(&0x30) {
+        return Ok((None, public_key.to_vec()));
+    }
+
+    let text = std::str::from_utf8(public_key).map_err(|_| invalid())?;
+    Ok((None, base64_decode(text.trim()).map_err(|_| invalid())?))
+}
+
 /// 生成随机字节
 pub fn generate_random_bytes(length: usize) -> Vec<u8> {
     use rand::Rng;
@@ -98,6 +151,13 @@ pub fn sha256_hash_hex(data: &[u8]) -> String {
     hex::encode(sha256_hash(data))
 }
 
+/// 计算HMAC-SHA256并返回十六进制字符串
+pub fn hmac_sha256_hex(key: &[u8], data: &[u8]) -> String {
+    use ring::hmac;
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hex::encode(hmac::sign(&key, data).as_ref())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,6 +199,41 @@ mod tests {
         assert_eq!(data.to_vec(), decoded);
     }
 
+    #[test]
+    fn test_verify_device_signature_ec() {
+        use ring::{rand::SystemRandom, signature::KeyPair};
+
+        // P-256 SubjectPublicKeyInfo DER前缀
+        const SPKI_PREFIX: &str = "3059301306072a8648ce3d020106082a8648ce3d030107034200";
+
+        let rng = SystemRandom::new();
+        let pkcs8 =
+            signature::EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+                .unwrap();
+        let key_pair = signature::EcdsaKeyPair::from_pkcs8(
+            &signature::ECDSA_P256_SHA256_ASN1_SIGNING,
+            pkcs8.as_ref(),
+            &rng,
+        )
+        .unwrap();
+
+        let mut spki = hex::decode(SPKI_PREFIX).unwrap();
+        spki.extend_from_slice(key_pair.public_key().as_ref());
+        let pem = format!(
+            "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+            base64_encode(&spki)
+        );
+
+        let sig = key_pair.sign(&rng, b"device-1:1700000000").unwrap();
+
+        for key in [spki.clone(), base64_encode(&spki).into_bytes(), pem.into_bytes()] {
+            assert!(verify_device_signature(&key, b"device-1:1700000000", sig.as_ref()).unwrap());
+            assert!(!verify_device_signature(&key, b"device-1:1700000001", sig.as_ref()).unwrap());
+        }
+
+        assert!(verify_device_signature(b"not a key", b"msg", sig.as_ref()).is_err());
+    }
+
     #[test]
     fn test_sha256() {
         let data = b"Hello, World!";
@@ -148,4 +243,13 @@ mod tests {
         assert_eq!(hash.len(), 32); // SHA256 produces 32 bytes
         assert_eq!(hash_hex.len(), 64); // 32 bytes = 64 hex chars
     }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 测试用例2
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use crate::utils::error::AppError;
-use crate::models::TransactionTokenClaims;
+use crate::models::{TransactionTokenClaims, DEFAULT_TENANT_ID};
 
 /// JWT Claims
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,10 +9,24 @@ pub struct Claims {
     pub sub: String,      // Subject (user ID)
     pub username: String, // Username
     pub role: String,     // User role
+    #[serde(default = "default_tenant_id")]
+    pub tenant_id: String, // Tenant ID
     pub exp: i64,         // Expiration time
     pub iat: i64,         // Issued at
 }
 
+/// 多租户改造前签发的Token不含租户ID，归属默认租户
+fn default_tenant_id() -> String {
+    DEFAULT_TENANT_ID.to_string()
+}
+
+impl Claims {
+    /// 是否为超级管理员
+    pub fn is_super_admin(&self) -> bool {
+        self.role == "super_admin"
+    }
+}
+
 /// JWT Service
 #[derive(Clone)]
 pub struct JwtService {
@@ -37,6 +51,7 @@ impl JwtService {
         user_id: &str,
         username: &str,
         role: &str,
+        tenant_id: &str,
     ) -> Result<String, AppError> {
         let now = chrono::Utc::now().timestamp();
         let exp = now + self.access_token_expiry;
@@ -45,6 +60,7 @@ impl JwtService {
             sub: user_id.to_string(),
             username: username.to_string(),
             role: role.to_string(),
+            tenant_id: tenant_id.to_string(),
             exp,
             iat: now,
         };
@@ -65,6 +81,7 @@ impl JwtService {
         user_id: &str,
         username: &str,
         role: &str,
+        tenant_id: &str,
     ) -> Result<String, AppError> {
         let now = chrono::Utc::now().timestamp();
         let exp = now + self.refresh_token_expiry;
@@ -73,6 +90,7 @@ impl JwtService {
             sub: user_id.to_string(),
             username: username.to_string(),
             role: role.to_string(),
+            tenant_id: tenant_id.to_string(),
             exp,
             iat: now,
         };
@@ -105,9 +123,14 @@ impl JwtService {
         let claims = self.verify_token(refresh_token)?;
 
         // 生成新的access token和refresh token
-        let access_token = self.generate_token(&claims.sub, &claims.username, &claims.role)?;
-        let new_refresh_token =
-            self.generate_refresh_token(&claims.sub, &claims.username, &claims.role)?;
+        let access_token =
+            self.generate_token(&claims.sub, &claims.username, &claims.role, &claims.tenant_id)?;
+        let new_refresh_token = self.generate_refresh_token(
+            &claims.sub,
+            &claims.username,
+            &claims.role,
+            &claims.tenant_id,
+        )?;
 
         Ok((access_token, new_refresh_token))
     }
@@ -167,7 +190,7 @@ mod tests {
         let service = JwtService::new("test_secret".to_string(), 3600);
 
         let token = service
-            .generate_token("user123", "testuser", "admin")
+            .generate_token("user123", "testuser", "admin", "acq-1")
             .unwrap();
 
         let claims = service.verify_token(&token).unwrap();
@@ -175,6 +198,7 @@ mod tests {
         assert_eq!(claims.sub, "user123");
         assert_eq!(claims.username, "testuser");
         assert_eq!(claims.role, "admin");
+        assert_eq!(claims.tenant_id, "acq-1");
     }
 
     #[test]
@@ -182,7 +206,7 @@ mod tests {
         let service = JwtService::new("test_secret".to_string(), 3600);
 
         let refresh_token = service
-            .generate_refresh_token("user123", "testuser", "admin")
+            .generate_refresh_token("user123", "testuser", "admin", "acq-1")
             .unwrap();
 
         let (new_access_token, new_refresh_token) =
@@ -197,7 +221,7 @@ mod tests {
         let service = JwtService::new("test_secret".to_string(), 3600);
 
         let token = service
-            .generate_token("user123", "testuser", "admin")
+            .generate_token("user123", "testuser", "admin", "acq-1")
             .unwrap();
 
         let user_id = service.extract_user_id(&token).unwrap();
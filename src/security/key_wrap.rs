@@ -0,0 +1,109 @@
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+
+use crate::security::crypto;
+use crate::utils::error::AppError;
+
+/// 密钥加密密钥（KEK）
+///
+/// 以AES-256-GCM包装租户BDK等需要落库的密钥，包装结果为 `Base64(nonce || 密文 || 标签)`。
+/// 包装时绑定上下文（如租户ID）作为附加数据，密文挪用到其他上下文时无法解包。
+#[derive(Clone)]
+pub struct KeyEncryptionKey {
+    key: Vec<u8>,
+}
+
+impl KeyEncryptionKey {
+    /// 从64位十六进制字符串（32字节）创建
+    pub fn from_hex(hex_key: &str) -> Result<Self, AppError> {
+        let key = hex::decode(hex_key.trim())
+            .ok()
+            .filter(|key| key.len() == AES_256_GCM.key_len())
+            .ok_or_else(|| {
+                AppError::InternalWithMessage(
+                    "Key encryption key must be 64 hex characters".to_string(),
+                )
+            })?;
+
+        Ok(Self { key })
+    }
+
+    /// 包装密钥
+    pub fn wrap(&self, key: &[u8], context: &str) -> Result<String, AppError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).map_err(|_| AppError::Internal)?;
+
+        let mut sealed = key.to_vec();
+        self.sealing_key()?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(context.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| AppError::Internal)?;
+
+        let mut wrapped = nonce.to_vec();
+        wrapped.extend_from_slice(&sealed);
+        Ok(crypto::base64_encode(&wrapped))
+    }
+
+    /// 解包密钥，密文被篡改、上下文不符或KEK不匹配时返回错误
+    pub fn unwrap(&self, wrapped: &str, context: &str) -> Result<Vec<u8>, AppError> {
+        let failed = || AppError::InternalWithMessage("Failed to unwrap key".to_string());
+
+        let wrapped = crypto::base64_decode(wrapped).map_err(|_| failed())?;
+        if wrapped.len() < NONCE_LEN {
+            return Err(failed());
+        }
+        let (nonce, sealed) = wrapped.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| failed())?;
+
+        let mut sealed = sealed.to_vec();
+        let key = self
+            .sealing_key()?
+            .open_in_place(nonce, Aad::from(context.as_bytes()), &mut sealed)
+            .map_err(|_| failed())?;
+
+        Ok(key.to_vec())
+    }
+
+    fn sealing_key(&self) -> Result<LessSafeKey, AppError> {
+        let key = UnboundKey::new(&AES_256_GCM, &self.key).map_err(|_| AppError::Internal)?;
+        Ok(LessSafeKey::new(key))
+    }
+}
+
+impl std::fmt::Debug for KeyEncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KeyEncryptionKey(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEK: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn test_wrap_and_unwrap() {
+        let kek = KeyEncryptionKey::from_hex(KEK).unwrap();
+        let wrapped = kek.wrap(b"0123456789ABCDEF", "acq-1").unwrap();
+
+        assert!(!wrapped.contains("0123456789ABCDEF"));
+        assert_eq!(kek.unwrap(&wrapped, "acq-1").unwrap(), b"0123456789ABCDEF");
+
+        // 上下文不符或KEK不匹配时无法解包
+        assert!(kek.unwrap(&wrapped, "acq-2").is_err());
+        let other = KeyEncryptionKey::from_hex(&"ff".repeat(32)).unwrap();
+        assert!(other.unwrap(&wrapped, "acq-1").is_err());
+    }
+
+    #[test]
+    fn test_invalid_key_encryption_key() {
+        assert!(KeyEncryptionKey::from_hex("0011").is_err());
+        assert!(KeyEncryptionKey::from_hex(&"zz".repeat(32)).is_err());
+    }
+}
@@ -1,7 +1,9 @@
 pub mod crypto;
 pub mod dukpt;
 pub mod jwt;
+pub mod key_wrap;
 
 pub use crypto::*;
 pub use dukpt::DukptKeyDerivation;
 pub use jwt::{Claims, JwtService};
+pub use key_wrap::KeyEncryptionKey;
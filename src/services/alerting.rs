@@ -0,0 +1,489 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Timelike, Utc};
+use futures::future::BoxFuture;
+use reqwest::Client;
+
+use crate::{
+    api::websocket::{Notification, NotificationSeverity},
+    infrastructure::{
+        config::{
+            AlertRoute, AlertTarget, AlertingConfig, EmailChannelConfig, HttpChannelConfig,
+            QuietHours,
+        },
+        EmailMessage, SmtpClient,
+    },
+    models::{AlertEscalation, EscalationStatus},
+    repositories::NotificationRepository,
+    utils::error::AppError,
+};
+
+/// 邮件渠道名
+pub const EMAIL_CHANNEL: &str = "email";
+
+/// 外部告警消息
+#[derive(Debug, Clone)]
+pub struct AlertMessage {
+    pub notification: Notification,
+    /// 是否为未确认后的升级通知
+    pub escalated: bool,
+}
+
+impl AlertMessage {
+    pub fn new(notification: Notification) -> Self {
+        Self { notification, escalated: false }
+    }
+
+    /// 渲染消息模板
+    ///
+    /// 支持的占位符：`{{title}}` `{{message}}` `{{severity}}` `{{type}}` `{{device_id}}`
+    /// `{{merchant_id}}` `{{tenant_id}}` `{{threat_id}}` `{{timestamp}}` `{{seq}}`
+    /// `{{recipient}}` `{{escalation}}`；`json_escape` 为true时按JSON字符串内容转义取值
+    pub fn render(&self, template: &str, recipient: Option<&str>, json_escape: bool) -> String {
+        let n = &self.notification;
+        let name = |v: serde_json::Value| v.as_str().unwrap_or_default().to_string();
+        let values = [
+            ("title", n.title.clone()),
+            ("message", n.message.clone()),
+            ("severity", name(serde_json::to_value(n.severity).unwrap_or_default())),
+            ("type", name(serde_json::to_value(n.notification_type).unwrap_or_default())),
+            ("device_id", n.device_id.clone().unwrap_or_default()),
+            ("merchant_id", n.merchant_id.clone().unwrap_or_default()),
+            ("tenant_id", n.tenant_id.clone().unwrap_or_default()),
+            ("threat_id", n.threat_id.clone().unwrap_or_default()),
+            ("timestamp", n.timestamp.clone()),
+            ("seq", n.seq.map(|s| s.to_string()).unwrap_or_default()),
+            ("recipient", recipient.unwrap_or_default().to_string()),
+            (
+                "escalation",
+                if self.escalated {
+                    "【升级】".to_string()
+                } else {
+                    String::new()
+                },
+            ),
+        ];
+
+        let mut rendered = template.to_string();
+        for (key, value) in values {
+            let value = if json_escape {
+                let quoted = serde_json::Value::String(value).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            } else {
+                value
+            };
+            rendered = rendered.replace(&format!("{{{{{}}}}}", key), &value);
+        }
+
+        rendered
+    }
+}
+
+/// 外部告警渠道
+///
+/// 实现方负责把告警发送给指定接收人，失败时返回错误由调用方记录
+pub trait Notifier: Send + Sync {
+    fn send<'a>(
+        &'a self,
+        recipients: &'a [String],
+        alert: &'a AlertMessage,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+}
+
+/// SMTP邮件渠道
+pub struct EmailNotifier {
+    smtp: SmtpClient,
+    subject_template: String,
+    body_template: String,
+}
+
+impl EmailNotifier {
+    /// 根据配置创建邮件渠道
+    pub fn new(config: &EmailChannelConfig) -> Self {
+        Self {
+            smtp: SmtpClient::new(config),
+            subject_template: config.subject_template.clone(),
+            body_template: config.body_template.clone(),
+        }
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn send<'a>(
+        &'a self,
+        recipients: &'a [String],
+        alert: &'a AlertMessage,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let message = EmailMessage {
+                to: recipients.to_vec(),
+                subject: alert.render(&self.subject_template, None, false),
+                body: alert.render(&self.body_template, None, false),
+            };
+            self.smtp.send(&message).await
+        })
+    }
+}
+
+/// 通用HTTP渠道（短信网关、钉钉/企业微信/Slack机器人等）
+///
+/// 请求体模板包含 `{{recipient}}` 时按接收人逐个发送，否则整条告警只发送一次
+pub struct HttpNotifier {
+    name: String,
+    http: Client,
+    url: String,
+    headers: HashMap<String, String>,
+    body_template: String,
+    content_type: String,
+    timeout: Duration,
+}
+
+impl HttpNotifier {
+    /// 根据配置创建HTTP渠道
+    pub fn new(config: &HttpChannelConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            http: Client::new(),
+            url: config.url.clone(),
+            headers: config.headers.clone(),
+            body_template: config.body_template.clone(),
+            content_type: config.content_type.clone(),
+            timeout: Duration::from_secs(config.timeout_seconds),
+        }
+    }
+
+    async fn post(&self, body: String) -> Result<(), AppError> {
+        let mut request = self
+            .http
+            .post(&self.url)
+            .timeout(self.timeout)
+            .header(reqwest::header::CONTENT_TYPE, &self.content_type);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        let response = request.body(body).send().await.map_err(|e| {
+            AppError::InternalWithMessage(format!("Alert channel {} failed: {}", self.name, e))
+        })?;
+
+        if !response.status().is_success() {
+            return Err(AppError::InternalWithMessage(format!(
+                "Alert channel {} returned HTTP {}",
+                self.name,
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Notifier for HttpNotifier {
+    fn send<'a>(
+        &'a self,
+        recipients: &'a [String],
+        alert: &'a AlertMessage,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let json = self.content_type.contains("json");
+
+            if self.body_template.contains("{{recipient}}") {
+                for recipient in recipients {
+                    self.post(alert.render(&self.body_template, Some(recipient), json)).await?;
+                }
+                return Ok(());
+            }
+
+            self.post(alert.render(&self.body_template, None, json)).await
+        })
+    }
+}
+
+/// 外部告警分发器
+///
+/// 按路由规则（严重级别、通知类型）把已持久化的通知发送到邮件或HTTP渠道；
+/// 免打扰时段内低于豁免级别的告警不发送。配置了升级策略的规则会登记升级跟踪，
+/// 到期仍无人确认时通知升级接收人。
+pub struct AlertDispatcher {
+    channels: HashMap<String, Arc<dyn Notifier>>,
+    routes: Vec<AlertRoute>,
+    quiet_hours: Option<QuietHours>,
+    store: NotificationRepository,
+}
+
+impl AlertDispatcher {
+    /// 创建未配置渠道和规则的分发器
+    pub fn new(store: NotificationRepository) -> Self {
+        Self { channels: HashMap::new(), routes: Vec::new(), quiet_hours: None, store }
+    }
+
+    /// 根据配置创建分发器，规则引用了不存在的渠道或免打扰时间格式错误时返回错误
+    pub fn from_config(
+        config: &AlertingConfig,
+        store: NotificationRepository,
+    ) -> Result<Self, AppError> {
+        let mut dispatcher = Self::new(store);
+
+        if let Some(email) = &config.email {
+            dispatcher =
+                dispatcher.with_channel(EMAIL_CHANNEL, Arc::new(EmailNotifier::new(email)));
+        }
+        for channel in &config.http_channels {
+            dispatcher =
+                dispatcher.with_channel(channel.name.clone(), Arc::new(HttpNotifier::new(channel)));
+        }
+
+        if let Some(quiet_hours) = &config.quiet_hours {
+            parse_minutes(&quiet_hours.start)?;
+            parse_minutes(&quiet_hours.end)?;
+            dispatcher = dispatcher.with_quiet_hours(quiet_hours.clone());
+        }
+
+        for route in &config.routes {
+            let targets = route
+                .targets
+                .iter()
+                .chain(route.escalation.iter().flat_map(|e| e.targets.iter()));
+            for target in targets {
+                if !dispatcher.channels.contains_key(&target.channel) {
+                    return Err(AppError::InternalWithMessage(format!(
+                        "Alert route {} references unknown channel {}",
+                        route.name, target.channel
+                    )));
+                }
+            }
+            dispatcher = dispatcher.with_route(route.clone());
+        }
+
+        Ok(dispatcher)
+    }
+
+    /// 注册渠道
+    pub fn with_channel(mut self, name: impl Into<String>, notifier: Arc<dyn Notifier>) -> Self {
+        self.channels.insert(name.into(), notifier);
+        self
+    }
+
+    /// 添加路由规则
+    pub fn with_route(mut self, route: AlertRoute) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// 设置免打扰时段
+    pub fn with_quiet_hours(mut self, quiet_hours: QuietHours) -> Self {
+        self.quiet_hours = Some(quiet_hours);
+        self
+    }
+
+    /// 分发通知，返回实际发送的目标数
+    pub async fn dispatch(&self, notification: &Notification) -> usize {
+        self.dispatch_at(notification, Utc::now()).await
+    }
+
+    /// 以指定时间判断免打扰时段并分发通知
+    pub async fn dispatch_at(&self, notification: &Notification, now: DateTime<Utc>) -> usize {
+        let alert = AlertMessage::new(notification.clone());
+        let mut sent = 0;
+
+        let quiet = self.is_quiet(notification.severity, now);
+
+        for route in self.routes.iter().filter(|r| route_matches(r, notification)) {
+            if quiet {
+                tracing::info!(
+                    "Alert route {} suppressed during quiet hours for notification {:?}",
+                    route.name,
+                    notification.seq
+                );
+            } else {
+                sent += self.send_to_targets(&route.name, &route.targets, &alert).await;
+            }
+
+            if let (Some(escalation), Some(seq)) = (&route.escalation, notification.seq) {
+                // 被免打扰压制的告警从时段结束开始计时；升级时间落入免打扰时段的顺延到时段结束
+                let start = if quiet {
+                    self.quiet_hours_end(now)
+                } else {
+                    now
+                };
+                let mut due_at = start + chrono::Duration::seconds(escalation.after_seconds as i64);
+                if self.is_quiet(notification.severity, due_at) {
+                    due_at = self.quiet_hours_end(due_at);
+                }
+
+                let record = AlertEscalation::new(seq, route.name.clone(), due_at.to_rfc3339());
+                if let Err(e) = self.store.create_escalation(&record).await {
+                    tracing::error!(
+                        "Failed to schedule escalation for notification {}: {}",
+                        seq,
+                        e
+                    );
+                }
+            }
+        }
+
+        sent
+    }
+
+    /// 处理到期的升级：已确认的结束跟踪，未确认的通知升级接收人，返回升级数
+    pub async fn process_escalations(&self) -> Result<usize, AppError> {
+        let due = self.store.list_due_escalations(&Utc::now().to_rfc3339()).await?;
+
+        let mut escalated = 0;
+        for escalation in due {
+            let notification = self
+                .store
+                .find_by_seq(escalation.notification_seq)
+                .await?
+                .and_then(Notification::from_record);
+            let policy = self
+                .routes
+                .iter()
+                .find(|r| r.name == escalation.route_name)
+                .and_then(|r| r.escalation.as_ref());
+
+            // 通知已被确认或清理、规则已移除时结束跟踪
+            let (Some(notification), Some(policy)) = (notification, policy) else {
+                self.store
+                    .resolve_escalation(&escalation.id, EscalationStatus::Acknowledged)
+                    .await?;
+                continue;
+            };
+            if self.store.is_acknowledged(escalation.notification_seq).await? {
+                self.store
+                    .resolve_escalation(&escalation.id, EscalationStatus::Acknowledged)
+                    .await?;
+                continue;
+            }
+
+            if !self
+                .store
+                .resolve_escalation(&escalation.id, EscalationStatus::Escalated)
+                .await?
+            {
+                continue;
+            }
+
+            tracing::warn!(
+                "Escalating unacknowledged notification {} via route {}",
+                escalation.notification_seq,
+                escalation.route_name
+            );
+
+            let alert = AlertMessage { notification, escalated: true };
+            self.send_to_targets(&escalation.route_name, &policy.targets, &alert).await;
+            escalated += 1;
+        }
+
+        Ok(escalated)
+    }
+
+    /// 启动后台升级检查任务
+    pub fn start_escalation_worker(
+        self: Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.process_escalations().await {
+                    tracing::error!("Failed to process alert escalations: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn send_to_targets(
+        &self,
+        route_name: &str,
+        targets: &[AlertTarget],
+        alert: &AlertMessage,
+    ) -> usize {
+        let mut sent = 0;
+        for target in targets {
+            let Some(channel) = self.channels.get(&target.channel) else {
+                tracing::warn!(
+                    "Alert route {} uses unknown channel {}",
+                    route_name,
+                    target.channel
+                );
+                continue;
+            };
+
+            match channel.send(&target.recipients, alert).await {
+                Ok(()) => sent += 1,
+                Err(e) => tracing::error!(
+                    "Failed to send alert via {} for route {}: {}",
+                    target.channel,
+                    route_name,
+                    e
+                ),
+            }
+        }
+
+        sent
+    }
+
+    /// 指定时间是否处于免打扰时段且告警级别低于豁免级别
+    fn is_quiet(&self, severity: NotificationSeverity, at: DateTime<Utc>) -> bool {
+        let Some(quiet) = &self.quiet_hours else {
+            return false;
+        };
+        if severity.rank() >= quiet.bypass_severity.rank() {
+            return false;
+        }
+
+        let (Ok(start), Ok(end)) = (parse_minutes(&quiet.start), parse_minutes(&quiet.end)) else {
+            return false;
+        };
+        let minute = local_minute_of_day(at, quiet.utc_offset_minutes);
+
+        if start <= end {
+            minute >= start && minute < end
+        } else {
+            minute >= start || minute < end
+        }
+    }
+
+    /// 指定时间之后免打扰时段的结束时间
+    fn quiet_hours_end(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let Some(quiet) = &self.quiet_hours else {
+            return at;
+        };
+        let Ok(end) = parse_minutes(&quiet.end) else {
+            return at;
+        };
+
+        let minute = local_minute_of_day(at, quiet.utc_offset_minutes);
+        let wait = (end - minute).rem_euclid(24 * 60);
+        let at = at.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(at);
+        at + chrono::Duration::minutes(wait as i64)
+    }
+}
+
+/// 规则是否匹配通知的严重级别和类型
+fn route_matches(route: &AlertRoute, notification: &Notification) -> bool {
+    notification.severity.rank() >= route.min_severity.rank()
+        && (route.notification_types.is_empty()
+            || route.notification_types.contains(&notification.notification_type))
+}
+
+/// 解析 HH:MM 为当天的分钟数
+fn parse_minutes(value: &str) -> Result<i32, AppError> {
+    let invalid = || AppError::InternalWithMessage(format!("Invalid quiet hours time: {}", value));
+    let (hour, minute) = value.split_once(':').ok_or_else(invalid)?;
+    let hour: i32 = hour.trim().parse().map_err(|_| invalid())?;
+    let minute: i32 = minute.trim().parse().map_err(|_| invalid())?;
+
+    if !(0..24).contains(&hour) || !(0..60).contains(&minute) {
+        return Err(invalid());
+    }
+
+    Ok(hour * 60 + minute)
+}
+
+fn local_minute_of_day(at: DateTime<Utc>, utc_offset_minutes: i32) -> i32 {
+    let local = at + chrono::Duration::minutes(utc_offset_minutes as i64);
+    (local.hour() * 60 + local.minute()) as i32
+}
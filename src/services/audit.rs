@@ -1,6 +1,6 @@
 use crate::{
     dto::{AuditLogResponse, AuditLogListResponse},
-    models::{AuditLog, OperationResult},
+    models::{AuditLog, OperationResult, TenantContext},
     repositories::AuditLogRepository,
     utils::error::AppError,
 };
@@ -17,6 +17,11 @@ impl AuditService {
         Self { audit_repo }
     }
 
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self { audit_repo: self.audit_repo.for_tenant(tenant) }
+    }
+
     /// 记录操作日志
     pub async fn log_operation(
         &self,
@@ -1,19 +1,30 @@
 use crate::{
     dto::{
-        ApproveDeviceRequest, DeviceListResponse, DeviceResponse, RegisterDeviceRequest,
-        RegisterDeviceResponse, RejectDeviceRequest,
+        ApproveDeviceRequest, DeviceCredentials, DeviceListResponse, DeviceResponse,
+        DeviceTagsResponse, DeviceTimelineResponse, RegisterDeviceRequest, RegisterDeviceResponse,
+        RejectDeviceRequest, ReplaceDeviceRequest, SetDeviceTagsRequest,
     },
     infrastructure::HsmClient,
-    models::{AuditLog, Device, DeviceStatus, OperationResult},
-    repositories::{AuditLogRepository, DeviceRepository},
-    security::DukptKeyDerivation,
+    models::{normalize_tag, AuditLog, Device, DeviceStatus, OperationResult, TenantContext},
+    repositories::{AuditLogRepository, DeviceRepository, ThreatRepository, UnitOfWork},
+    security::{crypto, DukptKeyDerivation},
     utils::error::AppError,
 };
 
+/// 恢复设备所需的最低安全评分
+const MIN_RESUME_SECURITY_SCORE: i32 = 60;
+
+/// 设备签名时间戳允许的最大偏差（秒）
+const DEVICE_AUTH_MAX_SKEW_SECONDS: i64 = 300;
+
+/// 设备签名随机数最大长度
+const DEVICE_AUTH_MAX_NONCE_LENGTH: usize = 64;
+
 /// 设备服务
 #[derive(Clone)]
 pub struct DeviceService {
     device_repo: DeviceRepository,
+    threat_repo: ThreatRepository,
     audit_repo: AuditLogRepository,
     dukpt: DukptKeyDerivation,
     hsm_client: Option<HsmClient>,
@@ -23,11 +34,34 @@ impl DeviceService {
     /// 创建新的设备服务
     pub fn new(
         device_repo: DeviceRepository,
+        threat_repo: ThreatRepository,
         audit_repo: AuditLogRepository,
         dukpt: DukptKeyDerivation,
         hsm_client: Option<HsmClient>,
     ) -> Self {
-        Self { device_repo, audit_repo, dukpt, hsm_client }
+        Self { device_repo, threat_repo, audit_repo, dukpt, hsm_client }
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            device_repo: self.device_repo.for_tenant(tenant),
+            threat_repo: self.threat_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            dukpt: self.dukpt.clone(),
+            hsm_client: self.hsm_client.clone(),
+        }
+    }
+
+    /// 返回绑定到工作单元的服务，状态变更和审计日志在同一事务中写入
+    pub(crate) fn in_unit_of_work(&self, uow: &UnitOfWork) -> Self {
+        Self {
+            device_repo: self.device_repo.in_unit_of_work(uow),
+            threat_repo: self.threat_repo.in_unit_of_work(uow),
+            audit_repo: self.audit_repo.in_unit_of_work(uow),
+            dukpt: self.dukpt.clone(),
+            hsm_client: self.hsm_client.clone(),
+        }
     }
 
     /// 注册设备
@@ -72,8 +106,11 @@ impl DeviceService {
         // 更新设备的KSN
         device.current_ksn = ksn.clone();
 
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
         // 保存设备
-        self.device_repo.create(&device).await?;
+        service.device_repo.create(&device).await?;
 
         // 记录审计日志
         let audit_log = AuditLog::new(
@@ -84,7 +121,8 @@ impl DeviceService {
         .with_device_id(device.id.clone())
         .with_details(format!("Device registered: IMEI={}, Model={}", request.imei, device.model));
 
-        self.audit_repo.create(&audit_log).await?;
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Device registered successfully: {}", device.id);
 
@@ -103,22 +141,12 @@ impl DeviceService {
         // 验证请求
         request.validate()?;
 
-        // 检查设备是否存在
-        let device = self
-            .device_repo
-            .find_by_id(&request.device_id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+        let device = self.find_device(&request.device_id).await?;
 
-        // 检查设备状态
-        if device.status != DeviceStatus::Pending.as_str() {
-            return Err(AppError::BadRequest("Device is not in pending status".to_string()));
-        }
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
 
-        // 更新设备状态为Active
-        self.device_repo
-            .update_status(&request.device_id, DeviceStatus::Active, Some(&request.operator))
-            .await?;
+        service.transition(&device, DeviceStatus::Active, &request.operator, None).await?;
 
         // 记录审计日志
         let audit_log = AuditLog::new(
@@ -129,7 +157,8 @@ impl DeviceService {
         .with_device_id(request.device_id.clone())
         .with_details("Device approved and activated".to_string());
 
-        self.audit_repo.create(&audit_log).await?;
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Device approved successfully: {}", request.device_id);
 
@@ -143,21 +172,12 @@ impl DeviceService {
         // 验证请求
         request.validate()?;
 
-        // 检查设备是否存在
-        let device = self
-            .device_repo
-            .find_by_id(&request.device_id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+        let device = self.find_device(&request.device_id).await?;
 
-        // 检查设备状态
-        if device.status != DeviceStatus::Pending.as_str() {
-            return Err(AppError::BadRequest("Device is not in pending status".to_string()));
-        }
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
 
-        // 更新设备状态为Revoked
-        self.device_repo
-            .update_status(&request.device_id, DeviceStatus::Revoked, Some(&request.operator))
+        service.transition(&device, DeviceStatus::Rejected, &request.operator, Some(&request.reason))
             .await?;
 
         // 记录审计日志
@@ -169,13 +189,73 @@ impl DeviceService {
         .with_device_id(request.device_id.clone())
         .with_details(format!("Device rejected: {}", request.reason));
 
-        self.audit_repo.create(&audit_log).await?;
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Device rejected successfully: {}", request.device_id);
 
         Ok(())
     }
 
+    /// 验证设备签名凭证
+    ///
+    /// - 设备使用注册公钥对应的私钥对 [`DeviceCredentials::signing_message`] 签名，
+    ///   时间戳需在允许偏差内
+    /// - 随机数在偏差窗口内只能使用一次，重放的请求被拒绝
+    /// - 只有已激活的设备可以认证
+    pub async fn authenticate_device(
+        &self,
+        device_id: &str,
+        credentials: &DeviceCredentials,
+    ) -> Result<Device, AppError> {
+        let failed = || AppError::Unauthorized("Device authentication failed".to_string());
+
+        let now = chrono::Utc::now().timestamp();
+        if (now - credentials.timestamp).abs() > DEVICE_AUTH_MAX_SKEW_SECONDS {
+            return Err(AppError::Unauthorized("Device credentials expired".to_string()));
+        }
+        if credentials.nonce.is_empty() || credentials.nonce.len() > DEVICE_AUTH_MAX_NONCE_LENGTH {
+            return Err(AppError::Unauthorized("Invalid device nonce".to_string()));
+        }
+
+        let device = self.device_repo.find_by_id(device_id).await?.ok_or_else(failed)?;
+
+        let signature = crypto::base64_decode(&credentials.signature).map_err(|_| failed())?;
+        let message = credentials.signing_message(&device.id);
+
+        match crypto::verify_device_signature(&device.public_key, message.as_bytes(), &signature) {
+            Ok(true) => {},
+            Ok(false) | Err(_) => {
+                tracing::warn!("Device {} failed signature authentication", device_id);
+                return Err(failed());
+            },
+        }
+
+        if device.status != DeviceStatus::Active.as_str() {
+            tracing::warn!("Device {} is {} and cannot authenticate", device_id, device.status);
+            return Err(AppError::Forbidden("Device is not active".to_string()));
+        }
+
+        // 随机数保留到时间戳超出允许偏差为止，之后的重放会因过期被拒绝
+        let expires_at = chrono::DateTime::from_timestamp(
+            credentials.timestamp + DEVICE_AUTH_MAX_SKEW_SECONDS,
+            0,
+        )
+        .ok_or_else(failed)?
+        .to_rfc3339();
+        self.device_repo
+            .delete_expired_auth_nonces(&device.id, &chrono::Utc::now().to_rfc3339())
+            .await?;
+        let fresh =
+            self.device_repo.try_use_auth_nonce(&device.id, &credentials.nonce, &expires_at).await?;
+        if !fresh {
+            tracing::warn!("Device {} reused a nonce", device_id);
+            return Err(AppError::Unauthorized("Device nonce already used".to_string()));
+        }
+
+        Ok(device)
+    }
+
     /// 获取设备详情
     pub async fn get_device(&self, device_id: &str) -> Result<DeviceResponse, AppError> {
         tracing::debug!("Getting device: {}", device_id);
@@ -218,21 +298,13 @@ impl DeviceService {
     ) -> Result<(), AppError> {
         tracing::info!("Suspending device: {}", device_id);
 
-        // 检查设备是否存在且为Active状态
-        let device = self
-            .device_repo
-            .find_by_id(device_id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+        let device = self.find_device(device_id).await?;
 
-        if device.status != DeviceStatus::Active.as_str() {
-            return Err(AppError::BadRequest("Device is not in active status".to_string()));
-        }
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
+        service.transition(&device, DeviceStatus::Suspended, operator, Some(reason)).await?;
 
-        // 更新设备状态为Suspended
-        self.device_repo
-            .update_status(device_id, DeviceStatus::Suspended, Some(operator))
-            .await?;
 
         // 记录审计日志
         let audit_log = AuditLog::new(
@@ -243,7 +315,8 @@ impl DeviceService {
         .with_device_id(device_id.to_string())
         .with_details(format!("Device suspended: {}", reason));
 
-        self.audit_repo.create(&audit_log).await?;
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Device suspended successfully: {}", device_id);
 
@@ -251,24 +324,23 @@ impl DeviceService {
     }
 
     /// 恢复设备
+    ///
+    /// 安全评分需达到恢复阈值；恢复后关闭设备的所有活跃威胁
     pub async fn resume_device(&self, device_id: &str, operator: &str) -> Result<(), AppError> {
         tracing::info!("Resuming device: {}", device_id);
 
-        // 检查设备是否存在且为Suspended状态
-        let device = self
-            .device_repo
-            .find_by_id(device_id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+        let device = self.find_device(device_id).await?;
 
-        if device.status != DeviceStatus::Suspended.as_str() {
-            return Err(AppError::BadRequest("Device is not in suspended status".to_string()));
+        if device.security_score < MIN_RESUME_SECURITY_SCORE {
+            return Err(AppError::DeviceSecurityScoreTooLow(device.security_score as i16));
         }
 
-        // 更新设备状态为Active
-        self.device_repo
-            .update_status(device_id, DeviceStatus::Active, Some(operator))
-            .await?;
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
+        service.transition(&device, DeviceStatus::Active, operator, None).await?;
+
+        let closed = service.threat_repo.resolve_active_by_device(device_id, operator).await?;
 
         // 记录审计日志
         let audit_log = AuditLog::new(
@@ -277,9 +349,10 @@ impl DeviceService {
             OperationResult::Success,
         )
         .with_device_id(device_id.to_string())
-        .with_details("Device resumed and activated".to_string());
+        .with_details(format!("Device resumed and activated, {} active threats closed", closed));
 
-        self.audit_repo.create(&audit_log).await?;
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Device resumed successfully: {}", device_id);
 
@@ -287,6 +360,8 @@ impl DeviceService {
     }
 
     /// 吊销设备
+    ///
+    /// 吊销时清零设备密钥
     pub async fn revoke_device(
         &self,
         device_id: &str,
@@ -295,44 +370,204 @@ impl DeviceService {
     ) -> Result<(), AppError> {
         tracing::info!("Revoking device: {}", device_id);
 
-        // 检查设备是否存在
-        let device = self
-            .device_repo
-            .find_by_id(device_id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+        let device = self.find_device(device_id).await?;
+
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
+        service.transition(&device, DeviceStatus::Revoked, operator, Some(reason)).await?;
+
+
+        // 记录审计日志
+        let audit_log = AuditLog::new(
+            "DEVICE_REVOCATION".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_device_id(device_id.to_string())
+        .with_details(format!("Device revoked and keys zeroized: {}", reason));
+
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
+
+        tracing::info!("Device revoked successfully: {}", device_id);
 
-        // 只有Active或Suspended状态的设备可以被吊销
-        let current_status =
-            DeviceStatus::from_str(&device.status).ok_or_else(|| AppError::Internal)?;
+        Ok(())
+    }
+
+    /// 退役设备
+    ///
+    /// 退役时清零设备密钥并解除门店分配
+    pub async fn decommission_device(
+        &self,
+        device_id: &str,
+        operator: &str,
+        reason: &str,
+    ) -> Result<(), AppError> {
+        tracing::info!("Decommissioning device: {}", device_id);
+
+        let device = self.find_device(device_id).await?;
+
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
+        service.transition(&device, DeviceStatus::Decommissioned, operator, Some(reason)).await?;
+        service.device_repo.clear_store(device_id).await?;
+
+        // 记录审计日志
+        let audit_log = AuditLog::new(
+            "DEVICE_DECOMMISSION".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_device_id(device_id.to_string())
+        .with_details(format!("Device decommissioned: {}", reason));
+
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
+
+        tracing::info!("Device decommissioned successfully: {}", device_id);
+
+        Ok(())
+    }
 
-        if !matches!(current_status, DeviceStatus::Active | DeviceStatus::Suspended) {
+    /// 替换设备
+    ///
+    /// 替换设备需为待审批或已激活且未分配门店；原设备的门店和终端号转移到替换设备，
+    /// 原设备密钥清零
+    pub async fn replace_device(
+        &self,
+        device_id: &str,
+        request: ReplaceDeviceRequest,
+        operator: &str,
+    ) -> Result<(), AppError> {
+        tracing::info!(
+            "Replacing device {} with {}",
+            device_id,
+            request.replacement_device_id
+        );
+
+        request.validate()?;
+
+        if request.replacement_device_id == device_id {
+            return Err(AppError::BadRequest("Device cannot replace itself".to_string()));
+        }
+
+        let device = self.find_device(device_id).await?;
+        let replacement = self.find_device(&request.replacement_device_id).await?;
+
+        if !matches!(
+            DeviceStatus::from_str(&replacement.status),
+            Some(DeviceStatus::Pending | DeviceStatus::Active)
+        ) {
+            return Err(AppError::BadRequest(
+                "Replacement device must be pending or active".to_string(),
+            ));
+        }
+        if replacement.store_id.is_some() {
             return Err(AppError::BadRequest(
-                "Device cannot be revoked in current status".to_string(),
+                "Replacement device is already assigned to a store".to_string(),
             ));
         }
 
-        // 更新设备状态为Revoked
-        self.device_repo
-            .update_status(device_id, DeviceStatus::Revoked, Some(operator))
-            .await?;
+        let reason = format!("Replaced by {}: {}", replacement.id, request.reason);
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
+        service.transition(&device, DeviceStatus::Replaced, operator, Some(&reason)).await?;
+
+        // 转移门店分配（先解除原设备，避免终端号唯一约束冲突）
+        if let (Some(merchant_id), Some(merchant_name), Some(store_id), Some(terminal_id)) = (
+            &device.merchant_id,
+            &device.merchant_name,
+            &device.store_id,
+            &device.terminal_id,
+        ) {
+            service.device_repo.clear_store(device_id).await?;
+            service
+                .device_repo
+                .assign_store(&replacement.id, merchant_id, merchant_name, store_id, terminal_id)
+                .await?;
+        }
 
         // 记录审计日志
         let audit_log = AuditLog::new(
-            "DEVICE_REVOCATION".to_string(),
+            "DEVICE_REPLACEMENT".to_string(),
             operator.to_string(),
             OperationResult::Success,
         )
         .with_device_id(device_id.to_string())
-        .with_details(format!("Device revoked: {}", reason));
+        .with_details(reason);
 
-        self.audit_repo.create(&audit_log).await?;
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
 
-        tracing::info!("Device revoked successfully: {}", device_id);
+        tracing::info!("Device {} replaced by {}", device_id, replacement.id);
 
         Ok(())
     }
 
+    /// 获取设备状态时间线
+    pub async fn get_device_timeline(
+        &self,
+        device_id: &str,
+    ) -> Result<DeviceTimelineResponse, AppError> {
+        let device = self.find_device(device_id).await?;
+        let history = self.device_repo.list_status_history(device_id).await?;
+
+        Ok(DeviceTimelineResponse {
+            device_id: device.id,
+            current_status: device.status,
+            history,
+        })
+    }
+
+    /// 获取设备标签
+    pub async fn get_device_tags(&self, device_id: &str) -> Result<DeviceTagsResponse, AppError> {
+        let device = self.find_device(device_id).await?;
+        let tags = self.device_repo.list_tags(&device.id).await?;
+
+        Ok(DeviceTagsResponse { device_id: device.id, tags })
+    }
+
+    /// 设置设备标签（整体替换）
+    pub async fn set_device_tags(
+        &self,
+        device_id: &str,
+        request: SetDeviceTagsRequest,
+        operator: &str,
+    ) -> Result<DeviceTagsResponse, AppError> {
+        request.validate()?;
+
+        let device = self.find_device(device_id).await?;
+
+        let mut tags = request
+            .tags
+            .iter()
+            .map(|t| normalize_tag(t))
+            .collect::<Result<Vec<_>, _>>()?;
+        tags.sort();
+        tags.dedup();
+
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
+        service.device_repo.set_tags(&device.id, &tags).await?;
+
+        let audit_log = AuditLog::new(
+            "DEVICE_TAGS_UPDATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_device_id(device.id.clone())
+        .with_details(format!("Tags: [{}]", tags.join(", ")));
+
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
+
+        Ok(DeviceTagsResponse { device_id: device.id, tags })
+    }
+
     /// 更新设备安全评分
     pub async fn update_security_score(
         &self,
@@ -356,8 +591,11 @@ impl DeviceService {
             .await?
             .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
 
+        let uow = self.device_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
         // 更新安全评分
-        self.device_repo.update_security_score(device_id, score).await?;
+        service.device_repo.update_security_score(device_id, score).await?;
 
         // 记录审计日志
         let audit_log = AuditLog::new(
@@ -371,7 +609,8 @@ impl DeviceService {
             device.security_score, score
         ));
 
-        self.audit_repo.create(&audit_log).await?;
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Security score updated for device: {}", device_id);
 
@@ -400,6 +639,28 @@ impl DeviceService {
             message: "PINPad attestation simulated".to_string(),
         })
     }
+
+    async fn find_device(&self, device_id: &str) -> Result<Device, AppError> {
+        self.device_repo
+            .find_by_id(device_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))
+    }
+
+    /// 按状态机迁移设备状态，非法迁移返回InvalidDeviceStatus
+    async fn transition(
+        &self,
+        device: &Device,
+        to: DeviceStatus,
+        operator: &str,
+        reason: Option<&str>,
+    ) -> Result<(), AppError> {
+        let from = DeviceStatus::from_str(&device.status).ok_or(AppError::Internal)?;
+
+        self.device_repo.update_status(&device.id, from, to, operator, reason).await?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
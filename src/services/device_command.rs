@@ -0,0 +1,433 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    api::websocket::DeviceChannel,
+    dto::{
+        CommandResultRequest, ConnectedDeviceListResponse, ConnectedDeviceResponse,
+        CreateDeviceCommandRequest, DeviceCommandListResponse, DeviceCommandResponse,
+    },
+    models::{
+        AuditLog, CommandStatus, Device, DeviceCommand, DeviceStatus, OperationResult,
+        TenantContext, DEFAULT_COMMAND_MAX_ATTEMPTS, DEFAULT_COMMAND_TIMEOUT_SECONDS,
+    },
+    repositories::{AuditLogRepository, DeviceCommandRepository, DeviceRepository},
+    utils::error::AppError,
+};
+
+/// 设备指令服务
+///
+/// 指令优先通过设备WebSocket通道实时下发，设备离线时保持待投递状态，
+/// 在设备重新连接或轮询时投递。投递后超时未完成的指令按最大投递次数重试。
+#[derive(Clone)]
+pub struct DeviceCommandService {
+    command_repo: DeviceCommandRepository,
+    device_repo: DeviceRepository,
+    audit_repo: AuditLogRepository,
+    channel: DeviceChannel,
+}
+
+impl DeviceCommandService {
+    /// 创建新的设备指令服务
+    pub fn new(
+        command_repo: DeviceCommandRepository,
+        device_repo: DeviceRepository,
+        audit_repo: AuditLogRepository,
+        channel: DeviceChannel,
+    ) -> Self {
+        Self { command_repo, device_repo, audit_repo, channel }
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            command_repo: self.command_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            channel: self.channel.clone(),
+        }
+    }
+
+    /// 创建指令并尝试立即下发
+    pub async fn create_command(
+        &self,
+        device_id: &str,
+        request: CreateDeviceCommandRequest,
+        operator: &str,
+    ) -> Result<DeviceCommandResponse, AppError> {
+        request.validate()?;
+
+        let device = self.find_reachable_device(device_id).await?;
+
+        let command = DeviceCommand::new(
+            device.id.clone(),
+            request.command_type,
+            request.payload.map(|p| p.to_string()),
+            request.timeout_seconds.unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECONDS),
+            request.max_attempts.unwrap_or(DEFAULT_COMMAND_MAX_ATTEMPTS),
+            operator.to_string(),
+        );
+
+        self.command_repo.create(&command).await?;
+
+        self.audit(
+            "DEVICE_COMMAND_CREATE",
+            operator,
+            &command,
+            OperationResult::Success,
+            format!("Command {} ({}) created", command.id, command.command_type),
+        )
+        .await?;
+
+        tracing::info!(
+            "Command {} ({}) created for device {}",
+            command.id,
+            command.command_type,
+            device.id
+        );
+
+        self.try_deliver(&command).await?;
+
+        self.get_command(&command.id).await
+    }
+
+    /// 获取指令
+    pub async fn get_command(&self, command_id: &str) -> Result<DeviceCommandResponse, AppError> {
+        Ok(DeviceCommandResponse::from(self.find_command(command_id).await?))
+    }
+
+    /// 列出设备的指令
+    pub async fn list_commands(
+        &self,
+        device_id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<DeviceCommandListResponse, AppError> {
+        let commands = self.command_repo.list_by_device(device_id, limit, offset).await?;
+        let total = self.command_repo.count_by_device(device_id).await?;
+
+        Ok(DeviceCommandListResponse {
+            commands: commands.into_iter().map(DeviceCommandResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 取消未完成的指令
+    pub async fn cancel_command(
+        &self,
+        command_id: &str,
+        operator: &str,
+    ) -> Result<DeviceCommandResponse, AppError> {
+        let command = self.find_command(command_id).await?;
+
+        if !self.command_repo.cancel(&command.id).await? {
+            return Err(AppError::BadRequest("Command is already completed".to_string()));
+        }
+
+        self.audit(
+            "DEVICE_COMMAND_CANCEL",
+            operator,
+            &command,
+            OperationResult::Success,
+            format!("Command {} cancelled", command.id),
+        )
+        .await?;
+
+        self.get_command(&command.id).await
+    }
+
+    /// 列出当前在线的设备（按租户过滤）
+    pub async fn list_connected_devices(&self) -> Result<ConnectedDeviceListResponse, AppError> {
+        let mut devices = Vec::new();
+        for connection in self.channel.connections().await {
+            if let Some(device) = self.device_repo.find_by_id(&connection.device_id).await? {
+                devices.push(ConnectedDeviceResponse {
+                    device_id: device.id,
+                    imei: device.imei,
+                    model: device.model,
+                    status: device.status,
+                    connection_id: connection.connection_id,
+                    connected_at: connection.connected_at,
+                });
+            }
+        }
+        devices.sort_by(|a, b| a.connected_at.cmp(&b.connected_at));
+
+        let total = devices.len() as i64;
+        Ok(ConnectedDeviceListResponse { devices, total })
+    }
+
+    /// 校验设备可以建立指令通道
+    pub async fn connect_device(&self, device_id: &str) -> Result<(), AppError> {
+        self.find_reachable_device(device_id).await.map(|_| ())
+    }
+
+    /// 设备轮询待执行的指令（WebSocket不可用时的降级方式）
+    pub async fn poll_commands(
+        &self,
+        device_id: &str,
+    ) -> Result<Vec<DeviceCommandResponse>, AppError> {
+        let device = self.find_reachable_device(device_id).await?;
+
+        let mut delivered = Vec::new();
+        for command in self.command_repo.list_pending(&device.id).await? {
+            if self.mark_delivered(&command, "polling").await? {
+                delivered.push(self.get_command(&command.id).await?);
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// 通过WebSocket补发设备的待投递指令
+    pub async fn deliver_pending(&self, device_id: &str) -> Result<usize, AppError> {
+        let mut count = 0;
+        for command in self.command_repo.list_pending(device_id).await? {
+            if self.try_deliver(&command).await? {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// 设备确认收到指令
+    pub async fn acknowledge(&self, device_id: &str, command_id: &str) -> Result<(), AppError> {
+        let command = self.find_device_command(device_id, command_id).await?;
+
+        if !self.command_repo.mark_acknowledged(&command.id).await? {
+            return Err(AppError::BadRequest(
+                "Command is not awaiting acknowledgement".to_string(),
+            ));
+        }
+
+        self.audit(
+            "DEVICE_COMMAND_ACK",
+            device_id,
+            &command,
+            OperationResult::Success,
+            format!("Command {} acknowledged", command.id),
+        )
+        .await
+    }
+
+    /// 设备上报指令执行结果
+    pub async fn report_result(
+        &self,
+        device_id: &str,
+        command_id: &str,
+        success: bool,
+        result: Option<serde_json::Value>,
+        error: Option<String>,
+    ) -> Result<DeviceCommandResponse, AppError> {
+        let command = self.find_device_command(device_id, command_id).await?;
+
+        let status = if success {
+            CommandStatus::Succeeded
+        } else {
+            CommandStatus::Failed
+        };
+        let result = result.map(|r| r.to_string());
+
+        if !self
+            .command_repo
+            .complete(&command.id, status, result.as_deref(), error.as_deref())
+            .await?
+        {
+            return Err(AppError::BadRequest("Command is not awaiting a result".to_string()));
+        }
+
+        self.audit(
+            "DEVICE_COMMAND_RESULT",
+            device_id,
+            &command,
+            if success {
+                OperationResult::Success
+            } else {
+                OperationResult::Failure
+            },
+            format!(
+                "Command {} {}{}",
+                command.id,
+                status.as_str(),
+                error.map(|e| format!(": {}", e)).unwrap_or_default()
+            ),
+        )
+        .await?;
+
+        tracing::info!("Command {} completed with {}", command.id, status.as_str());
+
+        self.get_command(&command.id).await
+    }
+
+    /// 设备通过HTTP上报执行结果
+    pub async fn submit_result(
+        &self,
+        device_id: &str,
+        command_id: &str,
+        request: CommandResultRequest,
+    ) -> Result<DeviceCommandResponse, AppError> {
+        self.report_result(device_id, command_id, request.success, request.result, request.error)
+            .await
+    }
+
+    /// 处理回执超时的指令
+    ///
+    /// 未超过最大投递次数的指令重新进入待投递状态并尝试下发，否则标记为超时。
+    /// 返回处理的指令数量。
+    pub async fn process_timeouts(&self) -> Result<usize, AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let expired = self.command_repo.list_expired(&now).await?;
+
+        let mut processed = 0;
+        for command in expired {
+            if command.attempts < command.max_attempts {
+                if !self.command_repo.requeue(&command.id, &now).await? {
+                    continue;
+                }
+
+                tracing::warn!(
+                    "Command {} timed out (attempt {}/{}), retrying",
+                    command.id,
+                    command.attempts,
+                    command.max_attempts
+                );
+
+                if let Some(command) = self.command_repo.find_by_id(&command.id).await? {
+                    self.try_deliver(&command).await?;
+                }
+            } else {
+                if !self.command_repo.mark_timed_out(&command.id, &now).await? {
+                    continue;
+                }
+
+                tracing::warn!(
+                    "Command {} timed out after {} attempts",
+                    command.id,
+                    command.attempts
+                );
+
+                self.audit(
+                    "DEVICE_COMMAND_TIMEOUT",
+                    "system",
+                    &command,
+                    OperationResult::Failure,
+                    format!("Command {} timed out after {} attempts", command.id, command.attempts),
+                )
+                .await?;
+            }
+
+            processed += 1;
+        }
+
+        Ok(processed)
+    }
+
+    /// 启动后台超时检查任务
+    pub fn start_timeout_monitor(
+        self: Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.process_timeouts().await {
+                    tracing::error!("Failed to process command timeouts: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 设备在线时通过WebSocket下发指令
+    async fn try_deliver(&self, command: &DeviceCommand) -> Result<bool, AppError> {
+        if !self.channel.is_connected(&command.device_id).await {
+            return Ok(false);
+        }
+
+        if !self.mark_delivered(command, "websocket").await? {
+            return Ok(false);
+        }
+
+        // 投递状态已更新，发送失败时等待超时后重试
+        let delivered = self.find_command(&command.id).await?;
+        let sent = self.channel.send_json(&command.device_id, &delivered.to_message()).await;
+        if !sent {
+            tracing::warn!("Failed to push command {} to device {}", command.id, command.device_id);
+        }
+
+        Ok(sent)
+    }
+
+    async fn mark_delivered(&self, command: &DeviceCommand, via: &str) -> Result<bool, AppError> {
+        if !self.command_repo.mark_delivered(&command.id, &command.next_deadline()).await? {
+            return Ok(false);
+        }
+
+        self.audit(
+            "DEVICE_COMMAND_DELIVER",
+            "system",
+            command,
+            OperationResult::Success,
+            format!(
+                "Command {} delivered via {} (attempt {})",
+                command.id,
+                via,
+                command.attempts + 1
+            ),
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    async fn audit(
+        &self,
+        operation: &str,
+        operator: &str,
+        command: &DeviceCommand,
+        result: OperationResult,
+        details: String,
+    ) -> Result<(), AppError> {
+        let audit_log = AuditLog::new(operation.to_string(), operator.to_string(), result)
+            .with_device_id(command.device_id.clone())
+            .with_details(details);
+
+        self.audit_repo.create(&audit_log).await
+    }
+
+    async fn find_reachable_device(&self, device_id: &str) -> Result<Device, AppError> {
+        let device = self
+            .device_repo
+            .find_by_id(device_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+
+        // 待审批、已拒绝和已退役的设备不接收指令；挂起和吊销的设备仍需能接收锁定、清除密钥等指令
+        match DeviceStatus::from_str(&device.status) {
+            Some(DeviceStatus::Active | DeviceStatus::Suspended | DeviceStatus::Revoked) => {
+                Ok(device)
+            },
+            _ => Err(AppError::InvalidDeviceStatus),
+        }
+    }
+
+    async fn find_command(&self, command_id: &str) -> Result<DeviceCommand, AppError> {
+        self.command_repo
+            .find_by_id(command_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Command not found".to_string()))
+    }
+
+    async fn find_device_command(
+        &self,
+        device_id: &str,
+        command_id: &str,
+    ) -> Result<DeviceCommand, AppError> {
+        let command = self.find_command(command_id).await?;
+        if command.device_id != device_id {
+            return Err(AppError::NotFound("Command not found".to_string()));
+        }
+
+        Ok(command)
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+
+use crate::{
+    dto::{
+        CreateDeviceGroupRequest, DeviceGroupListResponse, DeviceGroupResponse, DeviceListResponse,
+        DeviceResponse, GroupMembersRequest, UpdateDeviceGroupRequest,
+    },
+    models::{AuditLog, Device, DeviceGroup, GroupType, OperationResult, TenantContext},
+    repositories::{AuditLogRepository, DeviceGroupRepository, DeviceRepository},
+    utils::error::AppError,
+};
+
+/// 设备分组服务
+///
+/// 提供分组管理以及分组解析能力，版本推送、内核发布、密钥更新等需要按设备子集下发的
+/// 服务通过 `resolve_devices` / `resolve_device_ids` 获取目标设备
+#[derive(Clone)]
+pub struct DeviceGroupService {
+    group_repo: DeviceGroupRepository,
+    device_repo: DeviceRepository,
+    audit_repo: AuditLogRepository,
+}
+
+impl DeviceGroupService {
+    /// 创建新的设备分组服务
+    pub fn new(
+        group_repo: DeviceGroupRepository,
+        device_repo: DeviceRepository,
+        audit_repo: AuditLogRepository,
+    ) -> Self {
+        Self { group_repo, device_repo, audit_repo }
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            group_repo: self.group_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+        }
+    }
+
+    /// 创建分组
+    pub async fn create_group(
+        &self,
+        request: CreateDeviceGroupRequest,
+        operator: &str,
+    ) -> Result<DeviceGroupResponse, AppError> {
+        request.validate()?;
+
+        let group = DeviceGroup::new(
+            request.name.trim().to_string(),
+            request.description,
+            request.group_type,
+            request.rules.as_ref(),
+            operator.to_string(),
+        );
+
+        if self.group_repo.exists_by_name(&group.name, &group.tenant_id).await? {
+            return Err(AppError::BadRequest("Device group name already exists".to_string()));
+        }
+
+        self.ensure_devices_exist(&request.device_ids).await?;
+
+        self.group_repo.create(&group).await?;
+
+        if !request.device_ids.is_empty() {
+            self.group_repo.add_members(&group.id, &request.device_ids, operator).await?;
+        }
+
+        let audit_log = AuditLog::new(
+            "DEVICE_GROUP_CREATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!(
+            "Device group created: {} ({}, {})",
+            group.id, group.name, group.group_type
+        ));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        tracing::info!("Device group created: {}", group.id);
+
+        self.get_group(&group.id).await
+    }
+
+    /// 获取分组
+    pub async fn get_group(&self, group_id: &str) -> Result<DeviceGroupResponse, AppError> {
+        Ok(DeviceGroupResponse::from(self.find_group(group_id).await?))
+    }
+
+    /// 列出分组
+    pub async fn list_groups(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<DeviceGroupListResponse, AppError> {
+        let groups = self.group_repo.list(limit, offset).await?;
+        let total = self.group_repo.count().await?;
+
+        Ok(DeviceGroupListResponse {
+            groups: groups.into_iter().map(DeviceGroupResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 更新分组
+    pub async fn update_group(
+        &self,
+        group_id: &str,
+        request: UpdateDeviceGroupRequest,
+        operator: &str,
+    ) -> Result<DeviceGroupResponse, AppError> {
+        request.validate()?;
+
+        let mut group = self.find_group(group_id).await?;
+
+        if let Some(name) = request.name {
+            let name = name.trim().to_string();
+            if name != group.name && self.group_repo.exists_by_name(&name, &group.tenant_id).await?
+            {
+                return Err(AppError::BadRequest("Device group name already exists".to_string()));
+            }
+            group.name = name;
+        }
+
+        if let Some(description) = request.description {
+            group.description = Some(description);
+        }
+
+        if let Some(rules) = request.rules {
+            if group.kind() != GroupType::Dynamic {
+                return Err(AppError::BadRequest("Static groups cannot have rules".to_string()));
+            }
+            group.rules = Some(serde_json::to_string(&rules).map_err(|e| {
+                AppError::InternalWithMessage(format!("Failed to encode rules: {}", e))
+            })?);
+        }
+
+        group.updated_at = chrono::Utc::now().to_rfc3339();
+        self.group_repo.update(&group).await?;
+
+        let audit_log = AuditLog::new(
+            "DEVICE_GROUP_UPDATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!("Device group updated: {}", group.id));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        Ok(DeviceGroupResponse::from(group))
+    }
+
+    /// 删除分组
+    pub async fn delete_group(&self, group_id: &str, operator: &str) -> Result<(), AppError> {
+        let group = self.find_group(group_id).await?;
+
+        self.group_repo.delete(&group.id).await?;
+
+        let audit_log = AuditLog::new(
+            "DEVICE_GROUP_DELETE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!("Device group deleted: {} ({})", group.id, group.name));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        Ok(())
+    }
+
+    /// 添加静态分组成员
+    pub async fn add_members(
+        &self,
+        group_id: &str,
+        request: GroupMembersRequest,
+        operator: &str,
+    ) -> Result<(), AppError> {
+        request.validate()?;
+
+        let group = self.find_static_group(group_id).await?;
+        self.ensure_devices_exist(&request.device_ids).await?;
+
+        self.group_repo.add_members(&group.id, &request.device_ids, operator).await?;
+
+        let audit_log = AuditLog::new(
+            "DEVICE_GROUP_ADD_MEMBERS".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!(
+            "Added {} device(s) to group {}",
+            request.device_ids.len(),
+            group.id
+        ));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        Ok(())
+    }
+
+    /// 移除静态分组成员
+    pub async fn remove_member(
+        &self,
+        group_id: &str,
+        device_id: &str,
+        operator: &str,
+    ) -> Result<(), AppError> {
+        let group = self.find_static_group(group_id).await?;
+
+        if !self.group_repo.remove_member(&group.id, device_id).await? {
+            return Err(AppError::NotFound("Device is not a member of the group".to_string()));
+        }
+
+        let audit_log = AuditLog::new(
+            "DEVICE_GROUP_REMOVE_MEMBER".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_device_id(device_id.to_string())
+        .with_details(format!("Removed from group {}", group.id));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        Ok(())
+    }
+
+    /// 列出分组当前包含的设备
+    pub async fn list_group_devices(&self, group_id: &str) -> Result<DeviceListResponse, AppError> {
+        let devices = self.resolve_devices(group_id).await?;
+
+        Ok(DeviceListResponse {
+            total: devices.len() as i64,
+            devices: devices.into_iter().map(DeviceResponse::from).collect(),
+        })
+    }
+
+    /// 解析分组包含的设备
+    ///
+    /// 静态分组返回成员列表，动态分组按规则匹配当前租户内的设备
+    pub async fn resolve_devices(&self, group_id: &str) -> Result<Vec<Device>, AppError> {
+        let group = self.find_group(group_id).await?;
+
+        match group.kind() {
+            GroupType::Static => self.group_repo.list_member_devices(&group.id).await,
+            GroupType::Dynamic => {
+                let rules = group.parsed_rules().ok_or_else(|| {
+                    AppError::InternalWithMessage(format!("Invalid rules for group {}", group.id))
+                })?;
+                let tag_map = self.device_repo.list_tag_map().await?;
+                let no_tags = HashSet::new();
+
+                Ok(self
+                    .device_repo
+                    .list_all()
+                    .await?
+                    .into_iter()
+                    .filter(|d| rules.matches(d, tag_map.get(&d.id).unwrap_or(&no_tags)))
+                    .collect())
+            },
+        }
+    }
+
+    /// 解析多个分组的设备ID并集（保持首次出现的顺序）
+    pub async fn resolve_device_ids(&self, group_ids: &[String]) -> Result<Vec<String>, AppError> {
+        let mut seen = HashSet::new();
+        let mut device_ids = Vec::new();
+
+        for group_id in group_ids {
+            for device in self.resolve_devices(group_id).await? {
+                if seen.insert(device.id.clone()) {
+                    device_ids.push(device.id);
+                }
+            }
+        }
+
+        Ok(device_ids)
+    }
+
+    async fn find_group(&self, group_id: &str) -> Result<DeviceGroup, AppError> {
+        self.group_repo
+            .find_by_id(group_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Device group not found".to_string()))
+    }
+
+    async fn find_static_group(&self, group_id: &str) -> Result<DeviceGroup, AppError> {
+        let group = self.find_group(group_id).await?;
+        if group.kind() != GroupType::Static {
+            return Err(AppError::BadRequest(
+                "Members can only be managed on static groups".to_string(),
+            ));
+        }
+
+        Ok(group)
+    }
+
+    async fn ensure_devices_exist(&self, device_ids: &[String]) -> Result<(), AppError> {
+        for device_id in device_ids {
+            if self.device_repo.find_by_id(device_id).await?.is_none() {
+                return Err(AppError::NotFound(format!("Device not found: {}", device_id)));
+            }
+        }
+
+        Ok(())
+    }
+}
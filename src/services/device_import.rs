@@ -0,0 +1,543 @@
+use std::collections::HashSet;
+
+use crate::{
+    dto::{
+        ApproveDeviceRequest, AssignDeviceRequest, DeviceImportJobListResponse,
+        DeviceImportJobResponse, DeviceImportRow, RegisterDeviceRequest, RegisterDeviceResponse,
+    },
+    models::{
+        AuditLog, DeviceImportJob, DevicePreapproval, DeviceStatus, ImportFormat, ImportJobStatus,
+        ImportRowError, OperationResult, StoreStatus, TenantContext, DEFAULT_TENANT_ID,
+    },
+    repositories::{AuditLogRepository, DeviceImportRepository, DeviceRepository, StoreRepository},
+    services::{DeviceService, MerchantService},
+    utils::{csv, error::AppError},
+};
+
+/// 单次导入的最大行数
+const MAX_IMPORT_ROWS: usize = 5000;
+
+/// 设备批量导入服务
+///
+/// 导入文件在后台任务中逐行校验并生成预审批槽位；匹配IMEI并携带注册码的设备注册时自动激活并分配门店
+#[derive(Clone)]
+pub struct DeviceImportService {
+    import_repo: DeviceImportRepository,
+    device_repo: DeviceRepository,
+    store_repo: StoreRepository,
+    audit_repo: AuditLogRepository,
+    device_service: DeviceService,
+    merchant_service: MerchantService,
+}
+
+impl DeviceImportService {
+    /// 创建新的设备导入服务
+    pub fn new(
+        import_repo: DeviceImportRepository,
+        device_repo: DeviceRepository,
+        store_repo: StoreRepository,
+        audit_repo: AuditLogRepository,
+        device_service: DeviceService,
+        merchant_service: MerchantService,
+    ) -> Self {
+        Self {
+            import_repo,
+            device_repo,
+            store_repo,
+            audit_repo,
+            device_service,
+            merchant_service,
+        }
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            import_repo: self.import_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            store_repo: self.store_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            device_service: self.device_service.for_tenant(tenant),
+            merchant_service: self.merchant_service.for_tenant(tenant),
+        }
+    }
+
+    /// 提交导入任务，任务在后台执行
+    pub async fn start_import(
+        &self,
+        format: ImportFormat,
+        content: String,
+        operator: &str,
+    ) -> Result<DeviceImportJobResponse, AppError> {
+        if content.trim().is_empty() {
+            return Err(AppError::BadRequest("Import file is empty".to_string()));
+        }
+
+        let job = DeviceImportJob::new(format, operator.to_string());
+        self.import_repo.create_job(&job).await?;
+
+        let audit_log = AuditLog::new(
+            "DEVICE_IMPORT_START".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!(
+            "Device import job {} submitted ({})",
+            job.id,
+            format.as_str()
+        ));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        let service = self.clone();
+        let job_id = job.id.clone();
+        let operator = operator.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = service.run_import(&job_id, format, &content, &operator).await {
+                tracing::error!("Device import job {} failed: {}", job_id, e);
+
+                let errors = serde_json::to_string(&[ImportRowError {
+                    row: 0,
+                    imei: None,
+                    error: e.to_string(),
+                }])
+                .ok();
+                if let Err(e) = service
+                    .import_repo
+                    .finish_job(&job_id, ImportJobStatus::Failed, 0, 0, errors.as_deref())
+                    .await
+                {
+                    tracing::error!("Failed to record import job {} failure: {}", job_id, e);
+                }
+            }
+        });
+
+        tracing::info!("Device import job submitted: {}", job.id);
+
+        self.get_job(&job.id).await
+    }
+
+    /// 获取导入任务
+    pub async fn get_job(&self, job_id: &str) -> Result<DeviceImportJobResponse, AppError> {
+        Ok(DeviceImportJobResponse::from(self.find_job(job_id).await?))
+    }
+
+    /// 列出导入任务
+    pub async fn list_jobs(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<DeviceImportJobListResponse, AppError> {
+        let jobs = self.import_repo.list_jobs(limit, offset).await?;
+        let total = self.import_repo.count_jobs().await?;
+
+        Ok(DeviceImportJobListResponse {
+            jobs: jobs.into_iter().map(DeviceImportJobResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 生成导入错误报告（CSV）
+    pub async fn get_report(&self, job_id: &str) -> Result<String, AppError> {
+        let job = self.find_job(job_id).await?;
+
+        let mut report = String::from("row,imei,error\n");
+        for error in job.row_errors() {
+            report.push_str(&format!(
+                "{},{},{}\n",
+                error.row,
+                csv::escape(error.imei.as_deref().unwrap_or("")),
+                csv::escape(&error.error)
+            ));
+        }
+
+        Ok(report)
+    }
+
+    /// 注册设备
+    ///
+    /// IMEI存在预审批时设备注册到预审批所属租户；型号和一次性注册码都匹配时自动激活、分配门店
+    pub async fn register_device(
+        &self,
+        tenant: &TenantContext,
+        request: RegisterDeviceRequest,
+        operator: &str,
+    ) -> Result<RegisterDeviceResponse, AppError> {
+        let Some(preapproval) = self.import_repo.find_pending_preapproval(&request.imei).await?
+        else {
+            return self.device_service.for_tenant(tenant).register_device(request, operator).await;
+        };
+
+        // 未携带租户凭证的注册进入默认租户，此时以预审批的租户为准
+        if !tenant.cross_tenant
+            && tenant.tenant_id != preapproval.tenant_id
+            && tenant.tenant_id != DEFAULT_TENANT_ID
+        {
+            return Err(AppError::Forbidden("IMEI is pre-approved for another tenant".to_string()));
+        }
+
+        let owner = TenantContext::new(preapproval.tenant_id.clone());
+        let model_matches = preapproval.model.eq_ignore_ascii_case(request.model.trim());
+        let code_matches = preapproval.enrollment_code_matches(request.enrollment_code.as_deref());
+
+        let mut response = self
+            .device_service
+            .for_tenant(&owner)
+            .register_device(request, operator)
+            .await?;
+
+        if response.status != DeviceStatus::Pending {
+            return Ok(response);
+        }
+
+        if !model_matches {
+            tracing::warn!(
+                "Device {} model does not match pre-approval {}, awaiting manual approval",
+                response.device_id,
+                preapproval.id
+            );
+            return Ok(response);
+        }
+
+        // 注册接口无需认证，只凭IMEI不能激活设备
+        if !code_matches {
+            tracing::warn!(
+                "Device {} enrollment code does not match pre-approval {}, awaiting approval",
+                response.device_id,
+                preapproval.id
+            );
+            return Ok(response);
+        }
+
+        let activated =
+            self.for_tenant(&owner).activate_preapproved(&preapproval, &response.device_id).await;
+        match activated {
+            Ok(true) => {
+                response.status = DeviceStatus::Active;
+                response.message = "Device registered and activated by pre-approval.".to_string();
+            },
+            Ok(false) => {},
+            // 激活失败时预审批未被使用，设备保持待审批
+            Err(e) => tracing::warn!(
+                "Failed to activate device {} by pre-approval {}: {}",
+                response.device_id,
+                preapproval.id,
+                e
+            ),
+        }
+
+        Ok(response)
+    }
+
+    /// 执行导入任务
+    async fn run_import(
+        &self,
+        job_id: &str,
+        format: ImportFormat,
+        content: &str,
+        operator: &str,
+    ) -> Result<(), AppError> {
+        let rows = parse_rows(format, content)?;
+        if rows.len() > MAX_IMPORT_ROWS {
+            return Err(AppError::BadRequest(format!(
+                "Import file exceeds {} rows",
+                MAX_IMPORT_ROWS
+            )));
+        }
+
+        self.import_repo.mark_running(job_id, rows.len() as i64).await?;
+
+        let mut seen = HashSet::new();
+        let mut errors = Vec::new();
+        let mut success_rows = 0;
+
+        for (index, row) in rows.into_iter().enumerate() {
+            let result = match &row {
+                Ok(row) => self.import_row(job_id, row, &mut seen, operator).await,
+                Err(e) => Err(AppError::Validation(e.clone())),
+            };
+
+            match result {
+                Ok(()) => success_rows += 1,
+                Err(e) => errors.push(ImportRowError {
+                    row: index + 1,
+                    imei: row.ok().map(|r| r.imei),
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        let failed_rows = errors.len() as i64;
+        let errors = if errors.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&errors).map_err(|e| {
+                AppError::InternalWithMessage(format!("Failed to encode import report: {}", e))
+            })?)
+        };
+
+        self.import_repo
+            .finish_job(
+                job_id,
+                ImportJobStatus::Completed,
+                success_rows,
+                failed_rows,
+                errors.as_deref(),
+            )
+            .await?;
+
+        let audit_log = AuditLog::new(
+            "DEVICE_IMPORT_COMPLETE".to_string(),
+            operator.to_string(),
+            if failed_rows == 0 {
+                OperationResult::Success
+            } else {
+                OperationResult::Partial
+            },
+        )
+        .with_details(format!(
+            "Device import job {} completed: {} pre-approved, {} failed",
+            job_id, success_rows, failed_rows
+        ));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        tracing::info!(
+            "Device import job {} completed: {} succeeded, {} failed",
+            job_id,
+            success_rows,
+            failed_rows
+        );
+
+        Ok(())
+    }
+
+    /// 校验单行并生成预审批
+    async fn import_row(
+        &self,
+        job_id: &str,
+        row: &DeviceImportRow,
+        seen: &mut HashSet<String>,
+        operator: &str,
+    ) -> Result<(), AppError> {
+        row.validate()?;
+
+        if !seen.insert(row.imei.clone()) {
+            return Err(AppError::Validation("Duplicate IMEI in import file".to_string()));
+        }
+
+        if self.device_repo.exists_by_imei(&row.imei).await? {
+            return Err(AppError::Validation("Device already registered".to_string()));
+        }
+
+        if self.import_repo.find_pending_preapproval(&row.imei).await?.is_some() {
+            return Err(AppError::Validation("IMEI already pre-approved".to_string()));
+        }
+
+        if let Some(store_id) = &row.store_id {
+            let store = self
+                .store_repo
+                .find_by_id(store_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Store not found".to_string()))?;
+
+            if store.status != StoreStatus::Active.as_str() {
+                return Err(AppError::Validation("Store is not active".to_string()));
+            }
+
+            if let Some(merchant_id) = &row.merchant_id {
+                if *merchant_id != store.merchant_id {
+                    return Err(AppError::Validation(
+                        "Store does not belong to merchant".to_string(),
+                    ));
+                }
+            }
+        }
+
+        let preapproval = DevicePreapproval::new(
+            job_id.to_string(),
+            row.imei.clone(),
+            row.model.trim().to_string(),
+            row.store_id.clone(),
+            row.enrollment_code.as_deref().unwrap_or_default(),
+            operator.to_string(),
+        );
+
+        self.import_repo.create_preapproval(&preapproval).await
+    }
+
+    /// 使用预审批激活设备并分配门店
+    ///
+    /// 预审批的使用、设备激活和审计日志在同一事务中写入，激活失败时预审批保持可用
+    async fn activate_preapproved(
+        &self,
+        preapproval: &DevicePreapproval,
+        device_id: &str,
+    ) -> Result<bool, AppError> {
+        let uow = self.device_repo.begin().await?;
+
+        if !self
+            .import_repo
+            .in_unit_of_work(&uow)
+            .consume_preapproval(&preapproval.id, device_id)
+            .await?
+        {
+            uow.rollback().await?;
+            return Ok(false);
+        }
+
+        self.device_service
+            .in_unit_of_work(&uow)
+            .approve_device(ApproveDeviceRequest {
+                device_id: device_id.to_string(),
+                operator: preapproval.created_by.clone(),
+            })
+            .await?;
+
+        let audit_log = AuditLog::new(
+            "DEVICE_PREAPPROVAL_ACTIVATED".to_string(),
+            preapproval.created_by.clone(),
+            OperationResult::Success,
+        )
+        .with_device_id(device_id.to_string())
+        .with_details(format!("Activated by import job {}", preapproval.job_id));
+
+        self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+        uow.commit().await?;
+
+        if let Some(store_id) = &preapproval.store_id {
+            let request = AssignDeviceRequest { store_id: store_id.clone() };
+            if let Err(e) = self
+                .merchant_service
+                .assign_device(device_id, request, &preapproval.created_by)
+                .await
+            {
+                // 门店在导入后被关闭等情况下，设备保持激活但需要人工分配
+                tracing::warn!("Failed to assign pre-approved device {}: {}", device_id, e);
+
+                let audit_log = AuditLog::new(
+                    "DEVICE_ASSIGN_STORE".to_string(),
+                    preapproval.created_by.clone(),
+                    OperationResult::Failure,
+                )
+                .with_device_id(device_id.to_string())
+                .with_details(format!("Pre-approved store assignment failed: {}", e));
+
+                self.audit_repo.create(&audit_log).await?;
+            }
+        }
+
+        tracing::info!("Device {} activated by pre-approval {}", device_id, preapproval.id);
+
+        Ok(true)
+    }
+
+    async fn find_job(&self, job_id: &str) -> Result<DeviceImportJob, AppError> {
+        self.import_repo
+            .find_job(job_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Import job not found".to_string()))
+    }
+}
+
+/// 解析导入文件，逐行返回解析结果
+fn parse_rows(
+    format: ImportFormat,
+    content: &str,
+) -> Result<Vec<Result<DeviceImportRow, String>>, AppError> {
+    match format {
+        ImportFormat::Json => {
+            let values: Vec<serde_json::Value> = serde_json::from_str(content).map_err(|e| {
+                AppError::BadRequest(format!("Import file must be a JSON array: {}", e))
+            })?;
+
+            Ok(values
+                .into_iter()
+                .map(|v| serde_json::from_value(v).map_err(|e| format!("Invalid row: {}", e)))
+                .collect())
+        },
+        ImportFormat::Csv => parse_csv(content),
+    }
+}
+
+/// 解析CSV导入文件，首行为表头（imei, model, merchant_id, store_id, enrollment_code）
+fn parse_csv(content: &str) -> Result<Vec<Result<DeviceImportRow, String>>, AppError> {
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+
+    let header: Vec<String> = lines
+        .next()
+        .map(|line| csv::parse_line(line, ','))
+        .ok_or_else(|| AppError::BadRequest("Missing CSV header".to_string()))?
+        .into_iter()
+        .map(|h| h.trim().to_ascii_lowercase())
+        .collect();
+
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (Some(imei_col), Some(model_col)) = (column("imei"), column("model")) else {
+        return Err(AppError::BadRequest(
+            "CSV header must contain imei and model columns".to_string(),
+        ));
+    };
+    let merchant_col = column("merchant_id");
+    let store_col = column("store_id");
+    let code_col = column("enrollment_code");
+
+    let optional = |fields: &[String], col: Option<usize>| {
+        col.and_then(|c| fields.get(c))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    Ok(lines
+        .map(|line| {
+            let fields = csv::parse_line(line, ',');
+            if fields.len() != header.len() {
+                return Err(format!("Expected {} columns, found {}", header.len(), fields.len()));
+            }
+
+            Ok(DeviceImportRow {
+                imei: fields[imei_col].trim().to_string(),
+                model: fields[model_col].trim().to_string(),
+                merchant_id: optional(&fields, merchant_col),
+                store_id: optional(&fields, store_col),
+                enrollment_code: optional(&fields, code_col),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_rows() {
+        let rows =
+            parse_csv("IMEI,Model,store_id\n123456789012345,V2PRO,s1\n\n123,V2PRO\n").unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let first = rows[0].as_ref().unwrap();
+        assert_eq!(first.imei, "123456789012345");
+        assert_eq!(first.store_id.as_deref(), Some("s1"));
+        assert!(first.merchant_id.is_none());
+        assert!(rows[1].is_err());
+    }
+
+    #[test]
+    fn test_parse_csv_requires_columns() {
+        assert!(parse_csv("imei,store_id\n123456789012345,s1").is_err());
+        assert!(parse_csv("").is_err());
+    }
+
+    #[test]
+    fn test_parse_json_rows() {
+        let rows = parse_rows(
+            ImportFormat::Json,
+            r#"[{"imei":"123456789012345","model":"V2PRO"},{"model":"V2PRO"}]"#,
+        )
+        .unwrap();
+        assert!(rows[0].is_ok());
+        assert!(rows[1].is_err());
+        assert!(parse_rows(ImportFormat::Json, "{}").is_err());
+    }
+}
@@ -3,14 +3,18 @@ use crate::{
         HealthCheckListResponse, HealthCheckRequest, HealthCheckResponse, HealthOverviewResponse,
     },
     models::{
-        DeviceStatus, HealthCheck, RecommendedAction, ThreatEvent, ThreatSeverity, ThreatType,
+        DeviceStatus, HealthCheck, RecommendedAction, TenantContext, ThreatEvent, ThreatSeverity,
+        ThreatType,
     },
     repositories::{AuditLogRepository, DeviceRepository, HealthCheckRepository, ThreatRepository},
     security::crypto,
-    services::ThreatDetectionService,
+    services::{NotificationServiceWrapper, ThreatDetectionService},
     utils::error::AppError,
 };
 
+/// 低于该安全评分时向管理端发送安全告警
+const LOW_SCORE_ALERT_THRESHOLD: i32 = 60;
+
 /// 健康检查服务
 #[derive(Clone)]
 pub struct HealthCheckService {
@@ -19,6 +23,7 @@ pub struct HealthCheckService {
     threat_repo: ThreatRepository,
     audit_repo: AuditLogRepository,
     threat_detection_service: ThreatDetectionService,
+    notifier: Option<NotificationServiceWrapper>,
 }
 
 impl HealthCheckService {
@@ -36,6 +41,25 @@ impl HealthCheckService {
             threat_repo,
             audit_repo,
             threat_detection_service,
+            notifier: None,
+        }
+    }
+
+    /// 启用管理端通知
+    pub fn with_notifier(mut self, notifier: NotificationServiceWrapper) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            health_check_repo: self.health_check_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            threat_repo: self.threat_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            threat_detection_service: self.threat_detection_service.for_tenant(tenant),
+            notifier: self.notifier.clone(),
         }
     }
 
@@ -57,7 +81,7 @@ impl HealthCheckService {
         }
 
         // 检查设备是否存在
-        let _device = self
+        let device = self
             .device_repo
             .find_by_id(&request.device_id)
             .await?
@@ -91,6 +115,19 @@ impl HealthCheckService {
             .update_security_score(&request.device_id, security_score)
             .await?;
 
+        // 评分过低时告警
+        if security_score < LOW_SCORE_ALERT_THRESHOLD {
+            if let Some(notifier) = &self.notifier {
+                let message = format!(
+                    "设备 {} 安全评分降至 {}，建议操作：{}",
+                    request.device_id,
+                    security_score,
+                    self.get_recommended_action(security_score)
+                );
+                notifier.send_security_alert(&device, security_score, message);
+            }
+        }
+
         // 检测威胁类型
         let detected_threat_types = self.detect_threat_types(&health_check);
 
@@ -345,9 +382,15 @@ impl HealthCheckService {
             // 根据威胁严重程度采取行动
             match threat.severity {
                 ThreatSeverity::Critical => {
-                    // 自动暂停设备
+                    // 自动暂停设备（仅处于激活状态的设备）
                     self.device_repo
-                        .update_status(device_id, DeviceStatus::Suspended, Some("system"))
+                        .update_status(
+                            device_id,
+                            DeviceStatus::Active,
+                            DeviceStatus::Suspended,
+                            "system",
+                            Some("Critical threat detected"),
+                        )
                         .await?;
 
                     tracing::warn!("Device {} suspended due to critical threat", device_id);
@@ -390,6 +433,11 @@ impl HealthCheckService {
         self.submit_health_check(request, "system").await
     }
 
+    /// 获取设备最新的健康检查记录
+    pub async fn get_latest_check(&self, device_id: &str) -> Result<Option<HealthCheck>, AppError> {
+        self.health_check_repo.get_latest_by_device(device_id).await
+    }
+
     /// 列出健康检查记录
     pub async fn list_health_checks(
         &self,
@@ -465,10 +513,10 @@ mod tests {
     #[tokio::test]
     async fn test_calculate_security_score() {
         let pool = sqlx::SqlitePool::connect("").await.unwrap();
-        let health_check_repo = HealthCheckRepository::new(pool.clone());
-        let device_repo = DeviceRepository::new(pool.clone());
-        let threat_repo = ThreatRepository::new(pool.clone());
-        let audit_repo = AuditLogRepository::new(pool.clone());
+        let health_check_repo = HealthCheckRepository::unscoped(pool.clone());
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        let threat_repo = ThreatRepository::unscoped(pool.clone());
+        let audit_repo = AuditLogRepository::unscoped(pool.clone());
         let threat_detection_service = ThreatDetectionService::new(
             threat_repo.clone(),
             device_repo.clone(),
@@ -0,0 +1,169 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+
+use crate::{
+    dto::ProcessTransactionRequest,
+    repositories::IdempotencyRepository,
+    security::crypto,
+    utils::error::AppError,
+};
+
+/// 幂等键最大长度
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 128;
+
+/// 领取幂等键的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotentOutcome {
+    /// 首次请求，调用方处理完成后须调用 `complete` 或 `release`
+    Started,
+    /// 重复请求，原样返回首次处理的响应
+    Replay { status: u16, body: String },
+}
+
+/// 交易幂等服务
+///
+/// 设备通过 `Idempotency-Key` 请求头或 `clientTransactionId` 标识一次交易请求。
+/// 首次请求领取幂等键并加锁，处理完成后保存响应；相同内容的重试直接返回保存的响应，
+/// 内容不同的重试被拒绝，处理中的重复请求返回冲突。锁超时后视为处理方已失效，允许重新领取。
+#[derive(Clone)]
+pub struct IdempotencyService {
+    repo: IdempotencyRepository,
+    key_ttl: Duration,
+    lock_timeout: Duration,
+}
+
+impl IdempotencyService {
+    /// 创建新的交易幂等服务
+    pub fn new(repo: IdempotencyRepository) -> Self {
+        Self {
+            repo,
+            key_ttl: Duration::from_secs(24 * 3600),
+            lock_timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// 设置幂等键有效期和处理锁超时
+    pub fn with_expiry(mut self, key_ttl: Duration, lock_timeout: Duration) -> Self {
+        self.key_ttl = key_ttl;
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    /// 领取幂等键
+    pub async fn begin(
+        &self,
+        device_id: &str,
+        key: &str,
+        request: &ProcessTransactionRequest,
+    ) -> Result<IdempotentOutcome, AppError> {
+        if key.trim().is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+            return Err(AppError::BadRequest(format!(
+                "Idempotency key must be 1-{} characters",
+                MAX_IDEMPOTENCY_KEY_LENGTH
+            )));
+        }
+
+        let request_hash = request_hash(request)?;
+        let now = Utc::now();
+        let stale_before = now - to_chrono(self.lock_timeout);
+        let expires_at = now + to_chrono(self.key_ttl);
+
+        let claimed = self
+            .repo
+            .try_claim(
+                device_id,
+                key,
+                &request_hash,
+                &now.to_rfc3339(),
+                &stale_before.to_rfc3339(),
+                &expires_at.to_rfc3339(),
+            )
+            .await?;
+
+        if claimed {
+            return Ok(IdempotentOutcome::Started);
+        }
+
+        // 领取失败但记录已被并发释放时同样按处理中返回，由客户端稍后重试
+        let Some(record) = self.repo.find(device_id, key).await? else {
+            return Err(AppError::RequestInProgress);
+        };
+
+        if record.request_hash != request_hash {
+            return Err(AppError::IdempotencyKeyMismatch);
+        }
+
+        match (record.is_completed(), record.response_status, record.response_body) {
+            (true, Some(status), Some(body)) => {
+                tracing::info!(
+                    "Replaying idempotent response for device {}, key {}",
+                    device_id,
+                    key
+                );
+                Ok(IdempotentOutcome::Replay {
+                    status: status as u16,
+                    body,
+                })
+            },
+            _ => Err(AppError::RequestInProgress),
+        }
+    }
+
+    /// 幂等键对应的请求是否仍在处理中
+    pub async fn is_in_progress(&self, device_id: &str, key: &str) -> Result<bool, AppError> {
+        Ok(self.repo.find(device_id, key).await?.is_some_and(|record| !record.is_completed()))
+    }
+
+    /// 保存首次处理的响应
+    pub async fn complete(
+        &self,
+        device_id: &str,
+        key: &str,
+        status: u16,
+        body: &str,
+        transaction_id: Option<&str>,
+    ) -> Result<(), AppError> {
+        self.repo.complete(device_id, key, status as i64, body, transaction_id).await
+    }
+
+    /// 释放幂等键（处理失败时调用），之后同一幂等键可重新提交
+    pub async fn release(&self, device_id: &str, key: &str) -> Result<(), AppError> {
+        self.repo.release(device_id, key).await
+    }
+
+    /// 删除已过期的幂等键
+    pub async fn purge_expired(&self) -> Result<u64, AppError> {
+        let removed = self.repo.delete_expired(&Utc::now().to_rfc3339()).await?;
+        if removed > 0 {
+            tracing::debug!("Purged {} expired idempotency keys", removed);
+        }
+
+        Ok(removed)
+    }
+
+    /// 启动后台清理任务
+    pub fn start_cleanup_worker(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.purge_expired().await {
+                    tracing::error!("Failed to purge idempotency keys: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// 请求内容摘要
+fn request_hash(request: &ProcessTransactionRequest) -> Result<String, AppError> {
+    let payload = serde_json::to_vec(request)
+        .map_err(|e| AppError::InternalWithMessage(format!("Failed to hash request: {}", e)))?;
+
+    Ok(crypto::sha256_hash_hex(&payload))
+}
+
+fn to_chrono(duration: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(duration).unwrap_or_else(|_| chrono::Duration::zero())
+}
@@ -4,7 +4,7 @@ use tokio::fs;
 use uuid::Uuid;
 
 use crate::{
-    models::{Kernel, KernelStatus},
+    models::{Kernel, KernelStatus, TenantContext},
     repositories::KernelRepository,
     utils::error::AppError,
 };
@@ -23,6 +23,14 @@ impl KernelService {
         }
     }
 
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            kernel_repo: self.kernel_repo.for_tenant(tenant),
+            storage_path: self.storage_path.clone(),
+        }
+    }
+
     /// 上传内核文件
     pub async fn upload_kernel(
         &self,
@@ -63,6 +71,7 @@ impl KernelService {
         let now = chrono::Utc::now().to_rfc3339();
         let kernel = Kernel {
             id,
+            tenant_id: crate::models::DEFAULT_TENANT_ID.to_string(),
             version: version.to_string(),
             file_path: file_path.to_string_lossy().to_string(),
             file_hash,
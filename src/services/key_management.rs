@@ -3,13 +3,24 @@ use crate::{
         InjectKeyRequest, InjectKeyResponse, UpdateKeyRequest, UpdateKeyResponse,
         KeyStatusResponse, EncryptPinRequest, EncryptPinResponse,
     },
-    models::{Device, DeviceStatus, AuditLog, OperationResult},
-    repositories::{DeviceRepository, AuditLogRepository},
-    security::{DukptKeyDerivation, crypto},
+    models::{Device, DeviceStatus, AuditLog, OperationResult, TenantContext},
+    repositories::{DeviceRepository, AuditLogRepository, TenantRepository},
+    security::{DukptKeyDerivation, KeyEncryptionKey, crypto},
     infrastructure::HsmClient,
+    services::NotificationServiceWrapper,
     utils::error::AppError,
 };
 
+/// 设备所属租户的BDK
+enum TenantBdk {
+    /// 未配置租户专属BDK，使用全局BDK
+    Global,
+    /// 解包后的租户专属BDK，在本地派生
+    Local(DukptKeyDerivation),
+    /// HSM中租户专属BDK的密钥引用
+    Hsm(String),
+}
+
 /// 密钥管理服务
 #[derive(Clone)]
 pub struct KeyManagementService {
@@ -17,6 +28,9 @@ pub struct KeyManagementService {
     audit_repo: AuditLogRepository,
     dukpt: DukptKeyDerivation,
     hsm_client: Option<HsmClient>,
+    tenant_repo: Option<TenantRepository>,
+    kek: Option<KeyEncryptionKey>,
+    notifier: Option<NotificationServiceWrapper>,
 }
 
 impl KeyManagementService {
@@ -32,6 +46,85 @@ impl KeyManagementService {
             audit_repo,
             dukpt,
             hsm_client,
+            tenant_repo: None,
+            kek: None,
+            notifier: None,
+        }
+    }
+
+    /// 启用租户专属BDK，本地BDK以 `kek` 包装保存，未配置时租户只能使用HSM中的BDK
+    pub fn with_tenant_repo(
+        mut self,
+        tenant_repo: TenantRepository,
+        kek: Option<KeyEncryptionKey>,
+    ) -> Self {
+        self.tenant_repo = Some(tenant_repo);
+        self.kek = kek;
+        self
+    }
+
+    /// 启用管理端通知
+    pub fn with_notifier(mut self, notifier: NotificationServiceWrapper) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            device_repo: self.device_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            dukpt: self.dukpt.clone(),
+            hsm_client: self.hsm_client.clone(),
+            tenant_repo: self.tenant_repo.clone(),
+            kek: self.kek.clone(),
+            notifier: self.notifier.clone(),
+        }
+    }
+
+    /// 解析设备所属租户的BDK
+    async fn tenant_bdk(&self, device: &Device) -> Result<TenantBdk, AppError> {
+        let Some(ref tenant_repo) = self.tenant_repo else {
+            return Ok(TenantBdk::Global);
+        };
+        let Some(tenant) = tenant_repo.find_by_id(&device.tenant_id).await? else {
+            return Ok(TenantBdk::Global);
+        };
+
+        if let Some(key_ref) = tenant.bdk_key_ref {
+            return Ok(TenantBdk::Hsm(key_ref));
+        }
+
+        match tenant.bdk_wrapped {
+            Some(wrapped) => {
+                let kek = self.kek.as_ref().ok_or_else(|| {
+                    AppError::InternalWithMessage(
+                        "Tenant BDK is wrapped but no key encryption key is configured".to_string(),
+                    )
+                })?;
+                let bdk = kek.unwrap(&wrapped, &tenant.id)?;
+                Ok(TenantBdk::Local(DukptKeyDerivation::new(bdk)))
+            },
+            None => Ok(TenantBdk::Global),
+        }
+    }
+
+    /// 派生设备IPEK
+    ///
+    /// - 租户BDK在HSM中时按密钥引用由HSM派生
+    /// - 本地包装的租户BDK解包后在本地派生，即使配置了HSM
+    /// - 未配置租户BDK时使用全局BDK，配置了HSM时由HSM派生
+    async fn derive_ipek(&self, device: &Device, ksn: &str) -> Result<Vec<u8>, AppError> {
+        match (self.tenant_bdk(device).await?, &self.hsm_client) {
+            (TenantBdk::Hsm(key_ref), Some(hsm_client)) => {
+                hsm_client.derive_ipek_with_key_ref(ksn, &device.id, &key_ref).await
+            },
+            (TenantBdk::Hsm(_), None) => Err(AppError::InternalWithMessage(
+                "Tenant BDK is held in the HSM but no HSM client is configured".to_string(),
+            )),
+            (TenantBdk::Local(dukpt), _) => dukpt.derive_ipek(ksn),
+            (TenantBdk::Global, Some(hsm_client)) => hsm_client.derive_ipek(ksn, &device.id).await,
+            (TenantBdk::Global, None) => self.dukpt.derive_ipek(ksn),
         }
     }
 
@@ -68,14 +161,8 @@ impl KeyManagementService {
 
         let ksn = &device.current_ksn;
 
-        // 派生IPEK
-        let ipek = if let Some(ref hsm_client) = self.hsm_client {
-            // 使用HSM派生IPEK
-            hsm_client.derive_ipek(ksn, &request.device_id).await?
-        } else {
-            // 使用本地DUKPT派生IPEK
-            self.dukpt.derive_ipek(ksn)?
-        };
+        // 派生IPEK（租户专属BDK）
+        let ipek = self.derive_ipek(&device, ksn).await?;
 
         // 使用设备公钥加密IPEK
         let public_key_pem = String::from_utf8(device.public_key.clone())
@@ -83,9 +170,13 @@ impl KeyManagementService {
         let encrypted_ipek = crypto::encrypt_with_public_key(&public_key_pem, &ipek)?;
         let encrypted_ipek_b64 = crypto::base64_encode(&encrypted_ipek);
 
+        // 密钥信息和审计日志在同一事务中写入
+        let uow = self.device_repo.begin().await?;
+
         // 更新设备密钥信息
         let now = chrono::Utc::now().to_rfc3339();
         self.device_repo
+            .in_unit_of_work(&uow)
             .update_key_info(
                 &request.device_id,
                 ksn,
@@ -104,7 +195,8 @@ impl KeyManagementService {
         .with_device_id(request.device_id.clone())
         .with_details("IPEK injected successfully".to_string());
 
-        self.audit_repo.create(&audit_log).await?;
+        self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Key injected successfully for device: {}", request.device_id);
 
@@ -153,11 +245,7 @@ impl KeyManagementService {
         let new_ksn = self.dukpt.increment_ksn(current_ksn)?;
 
         // 派生新的IPEK
-        let new_ipek = if let Some(ref hsm_client) = self.hsm_client {
-            hsm_client.derive_ipek(&new_ksn, &request.device_id).await?
-        } else {
-            self.dukpt.derive_ipek(&new_ksn)?
-        };
+        let new_ipek = self.derive_ipek(&device, &new_ksn).await?;
 
         // 使用设备公钥加密新IPEK
         let public_key_pem = String::from_utf8(device.public_key.clone())
@@ -165,9 +253,13 @@ impl KeyManagementService {
         let encrypted_ipek = crypto::encrypt_with_public_key(&public_key_pem, &new_ipek)?;
         let encrypted_ipek_b64 = crypto::base64_encode(&encrypted_ipek);
 
+        // 密钥信息和审计日志在同一事务中写入
+        let uow = self.device_repo.begin().await?;
+
         // 更新设备密钥信息
         let now = chrono::Utc::now().to_rfc3339();
         self.device_repo
+            .in_unit_of_work(&uow)
             .update_key_info(
                 &request.device_id,
                 &new_ksn,
@@ -186,7 +278,8 @@ impl KeyManagementService {
         .with_device_id(request.device_id.clone())
         .with_details(format!("Key updated from KSN {} to {}", current_ksn, new_ksn));
 
-        self.audit_repo.create(&audit_log).await?;
+        self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Key updated successfully for device: {}", request.device_id);
 
@@ -280,12 +373,8 @@ impl KeyManagementService {
 
         let ksn = &device.current_ksn;
 
-        // 派生IPEK和Working Key
-        let ipek = if let Some(ref hsm_client) = self.hsm_client {
-            hsm_client.derive_ipek(ksn, &request.device_id).await?
-        } else {
-            self.dukpt.derive_ipek(ksn)?
-        };
+        // 派生IPEK和Working Key（Working Key只依赖IPEK和KSN）
+        let ipek = self.derive_ipek(&device, ksn).await?;
 
         let working_key = if let Some(ref hsm_client) = self.hsm_client {
             hsm_client.derive_working_key(&ipek, ksn).await?
@@ -297,8 +386,11 @@ impl KeyManagementService {
         let encrypted_pin_block = self.dukpt.encrypt_pin_block(&request.pin, &working_key)?;
         let encrypted_pin_block_hex = hex::encode(&encrypted_pin_block);
 
+        // 密钥计数和审计日志在同一事务中写入
+        let uow = self.device_repo.begin().await?;
+
         // 递增密钥使用次数
-        self.device_repo.decrement_key_count(&request.device_id).await?;
+        self.device_repo.in_unit_of_work(&uow).decrement_key_count(&request.device_id).await?;
 
         // 记录审计日志
         let audit_log = AuditLog::new(
@@ -309,7 +401,25 @@ impl KeyManagementService {
         .with_device_id(request.device_id.clone())
         .with_details("PIN encrypted successfully".to_string());
 
-        self.audit_repo.create(&audit_log).await?;
+        self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+        uow.commit().await?;
+
+        // 剩余次数不足10%或耗尽时预警
+        if let Some(ref notifier) = self.notifier {
+            let mut device = device.clone();
+            device.key_remaining_count -= 1;
+
+            if device.key_remaining_count <= 0 {
+                let message = format!("设备 {} 密钥已耗尽，需要更新密钥", device.id);
+                notifier.send_key_warning(&device, message);
+            } else if device.key_remaining_count < device.key_total_count / 10 {
+                let message = format!(
+                    "设备 {} 密钥剩余 {} 次，请及时更新",
+                    device.id, device.key_remaining_count
+                );
+                notifier.send_key_warning(&device, message);
+            }
+        }
 
         let now = chrono::Utc::now().to_rfc3339();
 
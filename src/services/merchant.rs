@@ -0,0 +1,361 @@
+use crate::{
+    dto::{
+        AssignDeviceRequest, CreateMerchantRequest, CreateStoreRequest, DeviceResponse,
+        MerchantListResponse, MerchantResponse, StoreListResponse, StoreResponse,
+        UpdateMerchantRequest, UpdateStoreRequest,
+    },
+    models::{
+        AuditLog, DeviceStatus, Merchant, MerchantStatus, OperationResult, Store, StoreStatus,
+        TenantContext,
+    },
+    repositories::{AuditLogRepository, DeviceRepository, MerchantRepository, StoreRepository},
+    utils::error::AppError,
+};
+
+/// 商户服务
+#[derive(Clone)]
+pub struct MerchantService {
+    merchant_repo: MerchantRepository,
+    store_repo: StoreRepository,
+    device_repo: DeviceRepository,
+    audit_repo: AuditLogRepository,
+}
+
+impl MerchantService {
+    /// 创建新的商户服务
+    pub fn new(
+        merchant_repo: MerchantRepository,
+        store_repo: StoreRepository,
+        device_repo: DeviceRepository,
+        audit_repo: AuditLogRepository,
+    ) -> Self {
+        Self { merchant_repo, store_repo, device_repo, audit_repo }
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            merchant_repo: self.merchant_repo.for_tenant(tenant),
+            store_repo: self.store_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+        }
+    }
+
+    /// 创建商户
+    pub async fn create_merchant(
+        &self,
+        request: CreateMerchantRequest,
+        operator: &str,
+    ) -> Result<MerchantResponse, AppError> {
+        tracing::info!("Creating merchant: {}", request.name);
+
+        request.validate()?;
+
+        // 收单商户号必须唯一
+        if let Some(mid) = &request.acquirer_mid {
+            if self.merchant_repo.find_by_acquirer_mid(mid).await?.is_some() {
+                return Err(AppError::BadRequest(format!(
+                    "Merchant with acquirer MID {} already exists",
+                    mid
+                )));
+            }
+        }
+
+        let merchant = Merchant::new(
+            request.name,
+            request.mcc,
+            request.country,
+            request.currency,
+            request.acquirer_mid,
+            request.address,
+        );
+
+        self.merchant_repo.create(&merchant).await?;
+
+        let audit_log = AuditLog::new(
+            "MERCHANT_CREATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!("Merchant created: {} ({})", merchant.name, merchant.id));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        tracing::info!("Merchant created successfully: {}", merchant.id);
+
+        Ok(MerchantResponse::from(merchant))
+    }
+
+    /// 获取商户详情
+    pub async fn get_merchant(&self, merchant_id: &str) -> Result<MerchantResponse, AppError> {
+        let merchant = self.find_merchant(merchant_id).await?;
+
+        Ok(MerchantResponse::from(merchant))
+    }
+
+    /// 列出商户
+    pub async fn list_merchants(
+        &self,
+        status: Option<MerchantStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<MerchantListResponse, AppError> {
+        let merchants = self.merchant_repo.list(status, limit, offset).await?;
+        let total = self.merchant_repo.count(status).await?;
+
+        Ok(MerchantListResponse {
+            merchants: merchants.into_iter().map(MerchantResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 更新商户
+    pub async fn update_merchant(
+        &self,
+        merchant_id: &str,
+        request: UpdateMerchantRequest,
+        operator: &str,
+    ) -> Result<MerchantResponse, AppError> {
+        tracing::info!("Updating merchant: {}", merchant_id);
+
+        request.validate()?;
+
+        let mut merchant = self.find_merchant(merchant_id).await?;
+
+        if let Some(mid) = &request.acquirer_mid {
+            if let Some(existing) = self.merchant_repo.find_by_acquirer_mid(mid).await? {
+                if existing.id != merchant.id {
+                    return Err(AppError::BadRequest(format!(
+                        "Merchant with acquirer MID {} already exists",
+                        mid
+                    )));
+                }
+            }
+            merchant.acquirer_mid = Some(mid.clone());
+        }
+
+        if let Some(name) = request.name {
+            merchant.name = name;
+        }
+        if let Some(address) = request.address {
+            merchant.address = Some(address);
+        }
+        if let Some(status) = request.status {
+            merchant.status = status.as_str().to_string();
+        }
+
+        self.merchant_repo.update(&merchant).await?;
+
+        let audit_log = AuditLog::new(
+            "MERCHANT_UPDATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!("Merchant updated: {}", merchant.id));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        let merchant = self.find_merchant(merchant_id).await?;
+
+        Ok(MerchantResponse::from(merchant))
+    }
+
+    /// 创建门店
+    pub async fn create_store(
+        &self,
+        merchant_id: &str,
+        request: CreateStoreRequest,
+        operator: &str,
+    ) -> Result<StoreResponse, AppError> {
+        tracing::info!("Creating store for merchant: {}", merchant_id);
+
+        request.validate()?;
+
+        let merchant = self.find_merchant(merchant_id).await?;
+        if merchant.status != MerchantStatus::Active.as_str() {
+            return Err(AppError::BadRequest("Merchant is not active".to_string()));
+        }
+
+        let store = Store::new(merchant.id, request.name, request.address);
+
+        self.store_repo.create(&store).await?;
+
+        let audit_log = AuditLog::new(
+            "STORE_CREATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!("Store created: {} for merchant {}", store.id, store.merchant_id));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        Ok(StoreResponse::from(store))
+    }
+
+    /// 获取门店详情
+    pub async fn get_store(&self, store_id: &str) -> Result<StoreResponse, AppError> {
+        let store = self.find_store(store_id).await?;
+
+        Ok(StoreResponse::from(store))
+    }
+
+    /// 列出商户下的门店
+    pub async fn list_stores(&self, merchant_id: &str) -> Result<StoreListResponse, AppError> {
+        // 确认商户存在
+        self.find_merchant(merchant_id).await?;
+
+        let stores = self.store_repo.list_by_merchant(merchant_id).await?;
+        let total = stores.len() as i64;
+
+        Ok(StoreListResponse {
+            stores: stores.into_iter().map(StoreResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 更新门店
+    pub async fn update_store(
+        &self,
+        store_id: &str,
+        request: UpdateStoreRequest,
+        operator: &str,
+    ) -> Result<StoreResponse, AppError> {
+        tracing::info!("Updating store: {}", store_id);
+
+        request.validate()?;
+
+        let mut store = self.find_store(store_id).await?;
+
+        if let Some(name) = request.name {
+            store.name = name;
+        }
+        if let Some(address) = request.address {
+            store.address = Some(address);
+        }
+        if let Some(status) = request.status {
+            store.status = status.as_str().to_string();
+        }
+
+        self.store_repo.update(&store).await?;
+
+        let audit_log = AuditLog::new(
+            "STORE_UPDATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!("Store updated: {}", store.id));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        let store = self.find_store(store_id).await?;
+
+        Ok(StoreResponse::from(store))
+    }
+
+    /// 列出门店下的设备
+    pub async fn list_store_devices(
+        &self,
+        store_id: &str,
+    ) -> Result<Vec<DeviceResponse>, AppError> {
+        self.find_store(store_id).await?;
+
+        let devices = self.device_repo.list_by_store(store_id).await?;
+
+        Ok(devices.into_iter().map(DeviceResponse::from).collect())
+    }
+
+    /// 分配设备到门店
+    ///
+    /// 首次分配到商户时生成终端号（TID）；同一商户内更换门店保留原终端号
+    pub async fn assign_device(
+        &self,
+        device_id: &str,
+        request: AssignDeviceRequest,
+        operator: &str,
+    ) -> Result<DeviceResponse, AppError> {
+        tracing::info!("Assigning device {} to store {}", device_id, request.store_id);
+
+        request.validate()?;
+
+        let device = self
+            .device_repo
+            .find_by_id(device_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+
+        let status = DeviceStatus::from_str(&device.status);
+        if matches!(
+            status,
+            Some(
+                DeviceStatus::Revoked
+                    | DeviceStatus::Rejected
+                    | DeviceStatus::Decommissioned
+                    | DeviceStatus::Replaced
+            )
+        ) {
+            return Err(AppError::BadRequest(
+                "Revoked, rejected or retired devices cannot be assigned".to_string(),
+            ));
+        }
+
+        let store = self.find_store(&request.store_id).await?;
+        if store.status != StoreStatus::Active.as_str() {
+            return Err(AppError::BadRequest("Store is not active".to_string()));
+        }
+
+        let merchant = self.find_merchant(&store.merchant_id).await?;
+        if merchant.status != MerchantStatus::Active.as_str() {
+            return Err(AppError::BadRequest("Merchant is not active".to_string()));
+        }
+
+        let terminal_id = match (&device.merchant_id, &device.terminal_id) {
+            (Some(current), Some(tid)) if *current == merchant.id => tid.clone(),
+            _ => {
+                let sequence = self.merchant_repo.next_tid_sequence(&merchant.id).await?;
+                Merchant::format_terminal_id(sequence)
+            },
+        };
+
+        self.device_repo
+            .assign_store(device_id, &merchant.id, &merchant.name, &store.id, &terminal_id)
+            .await?;
+
+        let audit_log = AuditLog::new(
+            "DEVICE_ASSIGN_STORE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_device_id(device_id.to_string())
+        .with_details(format!(
+            "Device assigned to merchant {} store {} with TID {}",
+            merchant.id, store.id, terminal_id
+        ));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        tracing::info!("Device {} assigned with TID {}", device_id, terminal_id);
+
+        let device = self
+            .device_repo
+            .find_by_id(device_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+
+        Ok(DeviceResponse::from(device))
+    }
+
+    async fn find_merchant(&self, merchant_id: &str) -> Result<Merchant, AppError> {
+        self.merchant_repo
+            .find_by_id(merchant_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Merchant not found".to_string()))
+    }
+
+    async fn find_store(&self, store_id: &str) -> Result<Store, AppError> {
+        self.store_repo
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".to_string()))
+    }
+}
@@ -1,21 +1,45 @@
+pub mod alerting;
 pub mod audit;
 pub mod device;
+pub mod device_command;
+pub mod device_group;
+pub mod device_import;
 pub mod health_check;
+pub mod idempotency;
 pub mod kernel;
 pub mod key_management;
+pub mod merchant;
 pub mod notification;
+pub mod outbox;
+pub mod reconciliation;
+pub mod settlement;
+pub mod tenant;
 pub mod threat_detection;
 pub mod transaction;
 pub mod transaction_token;
 pub mod version;
+pub mod webhook;
 
+pub use alerting::{
+    AlertDispatcher, AlertMessage, EmailNotifier, HttpNotifier, Notifier, EMAIL_CHANNEL,
+};
 pub use audit::AuditService;
 pub use device::DeviceService;
+pub use device_command::DeviceCommandService;
+pub use device_group::DeviceGroupService;
+pub use device_import::DeviceImportService;
 pub use health_check::HealthCheckService;
+pub use idempotency::{IdempotencyService, IdempotentOutcome};
 pub use kernel::KernelService;
 pub use key_management::KeyManagementService;
-pub use notification::NotificationServiceWrapper;
+pub use merchant::MerchantService;
+pub use notification::{NotificationServiceWrapper, NotificationSink, DEFAULT_ALERT_COOLDOWN};
+pub use outbox::OutboxDispatcher;
+pub use reconciliation::ReconciliationService;
+pub use settlement::SettlementService;
+pub use tenant::TenantService;
 pub use threat_detection::ThreatDetectionService;
-pub use transaction::TransactionService;
+pub use transaction::{PreparedTransaction, TransactionService};
 pub use transaction_token::TransactionTokenService;
 pub use version::VersionService;
+pub use webhook::{sign_webhook_payload, WebhookService};
@@ -1,91 +1,200 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tracing::info;
 
-use crate::api::NotificationService;
+use crate::{
+    api::{
+        websocket::{Notification, NotificationSeverity},
+        NotificationService,
+    },
+    models::{Device, ThreatEvent, Transaction},
+};
+
+/// 告警默认冷却时间：同一设备的同类告警在冷却期内只发送一次
+pub const DEFAULT_ALERT_COOLDOWN: Duration = Duration::from_secs(300);
+
+/// 通知接收端
+///
+/// 业务服务通过该接口发出通知，不关心通知如何推送；实现方不应阻塞调用方
+pub trait NotificationSink: Send + Sync {
+    fn notify(&self, notification: Notification);
+}
+
+impl NotificationSink for Arc<NotificationService> {
+    fn notify(&self, notification: Notification) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            service.send_notification(notification).await;
+        });
+    }
+}
 
 /// 通知服务包装器
-/// 用于在业务逻辑层触发通知
+/// 用于在业务逻辑层触发通知，按设备标记租户和商户，并对告警去重；
+/// 设备状态变更和威胁告警由发件箱分发器调用
+#[derive(Clone)]
 pub struct NotificationServiceWrapper {
-    notification_service: Arc<NotificationService>,
+    sink: Arc<dyn NotificationSink>,
+    cooldown: Duration,
+    /// 告警去重键 -> 最近一次发送时间
+    recent_alerts: Arc<Mutex<HashMap<String, Instant>>>,
 }
 
 impl NotificationServiceWrapper {
     /// 创建新的通知服务包装器
-    pub fn new(notification_service: Arc<NotificationService>) -> Self {
+    pub fn new(sink: Arc<dyn NotificationSink>) -> Self {
         Self {
-            notification_service,
+            sink,
+            cooldown: DEFAULT_ALERT_COOLDOWN,
+            recent_alerts: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// 设置告警冷却时间，为零时不去重
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
     /// 发送安全告警
-    pub async fn send_security_alert(
-        &self,
-        device_id: String,
-        security_score: i32,
-        message: String,
-    ) {
+    pub fn send_security_alert(&self, device: &Device, security_score: i32, message: String) {
+        if !self.should_alert(format!("security:{}", device.id)) {
+            return;
+        }
+
         info!(
             "Triggering security alert for device {}: score={}, message={}",
-            device_id, security_score, message
+            device.id, security_score, message
         );
-        
-        self.notification_service
-            .send_security_alert(device_id, security_score, message)
-            .await;
+
+        self.emit(device, Notification::security_alert(device.id.clone(), security_score, message));
     }
 
     /// 发送威胁告警
-    pub async fn send_threat_alert(
-        &self,
-        device_id: String,
-        threat_id: String,
-        threat_type: String,
-        severity: String,
-        message: String,
-    ) {
+    pub fn send_threat_alert(&self, device: &Device, threat: &ThreatEvent) {
+        let threat_type = threat.threat_type.to_string();
+
+        if !self.should_alert(format!("threat:{}:{}", device.id, threat_type)) {
+            return;
+        }
+
         info!(
             "Triggering threat alert for device {}: threat_id={}, type={}, severity={}",
-            device_id, threat_id, threat_type, severity
+            device.id, threat.id, threat_type, threat.severity
+        );
+
+        self.emit(
+            device,
+            Notification::threat_alert(
+                device.id.clone(),
+                threat.id.clone(),
+                threat_type,
+                threat.severity.to_string().to_uppercase(),
+                threat.description.clone(),
+            ),
         );
-        
-        self.notification_service
-            .send_threat_alert(device_id, threat_id, threat_type, severity, message)
-            .await;
     }
 
     /// 发送密钥预警
-    pub async fn send_key_warning(
-        &self,
-        device_id: String,
-        remaining_count: i32,
-        total_count: i32,
-        message: String,
-    ) {
+    pub fn send_key_warning(&self, device: &Device, message: String) {
+        if !self.should_alert(format!("key:{}", device.id)) {
+            return;
+        }
+
         info!(
             "Triggering key warning for device {}: remaining={}/{}, message={}",
-            device_id, remaining_count, total_count, message
+            device.id, device.key_remaining_count, device.key_total_count, message
         );
-        
-        self.notification_service
-            .send_key_warning(device_id, remaining_count, total_count, message)
-            .await;
+
+        self.emit(
+            device,
+            Notification::key_warning(
+                device.id.clone(),
+                device.key_remaining_count,
+                device.key_total_count,
+                message,
+            ),
+        );
+    }
+
+    /// 发送冲正告警：冲正无法自动完成，需要人工核实原交易
+    pub fn send_reversal_alert(&self, transaction: &Transaction, message: String) {
+        if !self.should_alert(format!("reversal:{}", transaction.id)) {
+            return;
+        }
+
+        info!(
+            "Triggering reversal alert for transaction {}: message={}",
+            transaction.id, message
+        );
+
+        let mut notification = Notification::system_alert(
+            NotificationSeverity::High,
+            "冲正待人工处理".to_string(),
+            message,
+            Some(serde_json::json!({
+                "transaction_id": transaction.id,
+                "processor": transaction.processor,
+            })),
+        );
+        notification.device_id = Some(transaction.device_id.clone());
+        if let Some(merchant_id) = &transaction.merchant_id {
+            notification = notification.with_merchant_id(merchant_id.clone());
+        }
+
+        self.sink.notify(notification);
     }
 
     /// 发送设备状态变更通知
-    pub async fn send_device_status_change(
+    ///
+    /// 状态变更是离散事件，不参与去重
+    pub fn send_device_status_change(
         &self,
-        device_id: String,
-        old_status: String,
+        device: &Device,
         new_status: String,
         reason: Option<String>,
     ) {
         info!(
             "Triggering device status change notification for device {}: {} -> {}",
-            device_id, old_status, new_status
+            device.id, device.status, new_status
         );
-        
-        self.notification_service
-            .send_device_status_change(device_id, old_status, new_status, reason)
-            .await;
+
+        self.emit(
+            device,
+            Notification::device_status_change(
+                device.id.clone(),
+                device.status.clone(),
+                new_status,
+                reason,
+            ),
+        );
+    }
+
+    /// 标记租户和商户后发送
+    fn emit(&self, device: &Device, notification: Notification) {
+        let mut notification = notification.with_tenant_id(device.tenant_id.clone());
+        if let Some(merchant_id) = &device.merchant_id {
+            notification = notification.with_merchant_id(merchant_id.clone());
+        }
+
+        self.sink.notify(notification);
+    }
+
+    /// 判断告警是否已过冷却期，是则记录本次发送时间
+    fn should_alert(&self, key: String) -> bool {
+        let now = Instant::now();
+        let mut recent = self.recent_alerts.lock().unwrap();
+
+        recent.retain(|_, sent_at| now.duration_since(*sent_at) < self.cooldown);
+        if recent.contains_key(&key) {
+            tracing::debug!("Suppressing duplicate alert {}", key);
+            return false;
+        }
+
+        recent.insert(key, now);
+        true
     }
 }
@@ -0,0 +1,315 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::{
+    api::middleware::PrometheusMetrics,
+    models::{
+        DomainEvent, OutboxEvent, ThreatSeverity, WebhookEventType, DEFAULT_OUTBOX_MAX_ATTEMPTS,
+    },
+    repositories::{DeviceRepository, OutboxRepository, ThreatRepository},
+    services::{NotificationServiceWrapper, WebhookService},
+    utils::error::AppError,
+};
+
+/// 每轮处理的最大事件数
+const DISPATCH_BATCH_SIZE: i64 = 100;
+
+/// 领取后的租约时长，超时未完成的事件可被其他节点重新领取
+const DISPATCH_LEASE: Duration = Duration::from_secs(60);
+
+/// 两次重试之间的最长间隔
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(600);
+
+/// 发件箱分发器
+///
+/// 按写入顺序领取发件箱中的领域事件，转发给管理端通知、商户Webhook和Prometheus指标，
+/// 全部成功后标记为已分发。转发失败时按指数退避重试（至少一次投递），
+/// 超过最大次数后标记为FAILED。Webhook先于通知转发，重试只可能重复入队Webhook事件。
+#[derive(Clone)]
+pub struct OutboxDispatcher {
+    outbox_repo: OutboxRepository,
+    device_repo: DeviceRepository,
+    threat_repo: ThreatRepository,
+    notifier: Option<NotificationServiceWrapper>,
+    webhooks: Option<WebhookService>,
+    metrics: Option<Arc<PrometheusMetrics>>,
+    max_attempts: i64,
+    retry_base: Duration,
+}
+
+impl OutboxDispatcher {
+    /// 创建新的发件箱分发器
+    pub fn new(
+        outbox_repo: OutboxRepository,
+        device_repo: DeviceRepository,
+        threat_repo: ThreatRepository,
+    ) -> Self {
+        Self {
+            outbox_repo,
+            device_repo,
+            threat_repo,
+            notifier: None,
+            webhooks: None,
+            metrics: None,
+            max_attempts: DEFAULT_OUTBOX_MAX_ATTEMPTS,
+            retry_base: Duration::from_secs(5),
+        }
+    }
+
+    /// 设置管理端通知
+    pub fn with_notifier(mut self, notifier: NotificationServiceWrapper) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// 设置商户Webhook推送
+    pub fn with_webhooks(mut self, webhooks: WebhookService) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// 设置Prometheus指标
+    pub fn with_metrics(mut self, metrics: Arc<PrometheusMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// 设置重试策略
+    pub fn with_retry_policy(mut self, max_attempts: i64, retry_base: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_base = retry_base;
+        self
+    }
+
+    /// 分发所有到期的事件，返回成功分发的数量
+    pub async fn dispatch_pending(&self) -> Result<usize, AppError> {
+        let now = Utc::now();
+        let now_str = now.to_rfc3339();
+        let lease_until = (now
+            + chrono::Duration::from_std(DISPATCH_LEASE).unwrap_or(chrono::Duration::minutes(1)))
+        .to_rfc3339();
+
+        let due = self.outbox_repo.list_due(&now_str, DISPATCH_BATCH_SIZE).await?;
+
+        let mut dispatched = 0;
+        for record in due {
+            if !self.outbox_repo.claim(&record.id, &now_str, &lease_until).await? {
+                continue;
+            }
+
+            match self.relay(&record).await {
+                Ok(()) => {
+                    self.outbox_repo.mark_dispatched(&record.id).await?;
+                    dispatched += 1;
+                },
+                Err(e) => {
+                    let attempts = record.attempts + 1;
+                    let next_attempt_at =
+                        (attempts < self.max_attempts).then(|| self.retry_at(attempts));
+                    tracing::warn!(
+                        "Failed to dispatch outbox event {} ({}), attempt {}: {}",
+                        record.id,
+                        record.event_type,
+                        attempts,
+                        e
+                    );
+                    self.outbox_repo
+                        .mark_failed(&record.id, &e.to_string(), next_attempt_at.as_deref())
+                        .await?;
+                },
+            }
+        }
+
+        Ok(dispatched)
+    }
+
+    /// 启动后台分发任务
+    pub fn start_dispatch_worker(
+        self: Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.dispatch_pending().await {
+                    tracing::error!("Failed to dispatch outbox events: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 转发单个事件
+    async fn relay(&self, record: &OutboxEvent) -> Result<(), AppError> {
+        let event = record.event().ok_or_else(|| {
+            AppError::InternalWithMessage(format!("Invalid outbox payload: {}", record.id))
+        })?;
+
+        match event {
+            DomainEvent::DeviceRegistered { device_id, .. } => {
+                tracing::debug!("Device registered: {}", device_id);
+            },
+            DomainEvent::DeviceStatusChanged {
+                device_id, from_status, to_status, reason, ..
+            } => {
+                let Some(mut device) = self.device_repo.find_by_id(&device_id).await? else {
+                    return Ok(());
+                };
+
+                self.publish_webhook(
+                    &record.tenant_id,
+                    device.merchant_id.as_deref(),
+                    WebhookEventType::DeviceStatusChanged,
+                    serde_json::json!({
+                        "device_id": device_id,
+                        "old_status": from_status,
+                        "new_status": to_status,
+                        "reason": reason,
+                    }),
+                )
+                .await?;
+
+                if let Some(notifier) = &self.notifier {
+                    device.status = from_status.unwrap_or_default();
+                    notifier.send_device_status_change(&device, to_status, reason);
+                }
+            },
+            DomainEvent::KeyInjected { .. } => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_key_operation("inject", "ipek", "success");
+                }
+            },
+            DomainEvent::TransactionProcessed {
+                transaction_id,
+                device_id,
+                merchant_id,
+                terminal_id,
+                transaction_type,
+                amount,
+                currency,
+                status,
+                authorization_code,
+                response_code,
+                card_number_masked,
+            } => {
+                self.publish_webhook(
+                    &record.tenant_id,
+                    merchant_id.as_deref(),
+                    WebhookEventType::TransactionCompleted,
+                    serde_json::json!({
+                        "transaction_id": transaction_id,
+                        "device_id": device_id,
+                        "terminal_id": terminal_id,
+                        "transaction_type": transaction_type,
+                        "amount": amount,
+                        "currency": currency,
+                        "status": status,
+                        "authorization_code": authorization_code,
+                        "response_code": response_code,
+                        "card_number_masked": card_number_masked,
+                    }),
+                )
+                .await?;
+
+                if let Some(metrics) = &self.metrics {
+                    let device_mode = self
+                        .device_repo
+                        .find_by_id(&device_id)
+                        .await?
+                        .map(|d| d.device_mode)
+                        .unwrap_or_default();
+                    metrics.record_transaction(
+                        &label(&transaction_type),
+                        &label(&status),
+                        &device_mode,
+                    );
+                }
+            },
+            DomainEvent::ThreatDetected {
+                threat_id,
+                device_id,
+                threat_type,
+                severity,
+                description,
+            } => {
+                let device = self.device_repo.find_by_id(&device_id).await?;
+
+                self.publish_webhook(
+                    &record.tenant_id,
+                    device.as_ref().and_then(|d| d.merchant_id.as_deref()),
+                    WebhookEventType::ThreatDetected,
+                    serde_json::json!({
+                        "device_id": device_id,
+                        "threat_id": threat_id,
+                        "threat_type": threat_type.to_string(),
+                        "severity": severity.to_string().to_uppercase(),
+                        "description": description,
+                    }),
+                )
+                .await?;
+
+                // 低危威胁只记录不告警
+                if let (Some(notifier), Some(device)) = (&self.notifier, &device) {
+                    if severity != ThreatSeverity::Low {
+                        if let Some(threat) = self.threat_repo.find_by_id(&threat_id).await? {
+                            notifier.send_threat_alert(device, &threat);
+                        }
+                    }
+                }
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_threat(
+                        &threat_type.to_string(),
+                        &severity.to_string(),
+                        "detected",
+                    );
+                }
+            },
+            DomainEvent::VersionPublished { update_type, .. } => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_version_operation("publish", &label(&update_type), "success");
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    /// 设备归属商户时将事件加入该商户的Webhook投递队列
+    async fn publish_webhook(
+        &self,
+        tenant_id: &str,
+        merchant_id: Option<&str>,
+        event_type: WebhookEventType,
+        data: serde_json::Value,
+    ) -> Result<(), AppError> {
+        let (Some(webhooks), Some(merchant_id)) = (&self.webhooks, merchant_id) else {
+            return Ok(());
+        };
+
+        webhooks.publish_event(tenant_id, merchant_id, event_type, data).await?;
+
+        Ok(())
+    }
+
+    /// 第 `attempts` 次失败后的重试时间：`base * 2^(attempts-1)`，不超过最长间隔
+    fn retry_at(&self, attempts: i64) -> String {
+        let exponent = (attempts - 1).clamp(0, 20) as u32;
+        let delay = self
+            .retry_base
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(MAX_RETRY_DELAY);
+        (Utc::now() + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero()))
+            .to_rfc3339()
+    }
+}
+
+/// 枚举的序列化名称，用作指标标签
+fn label<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
@@ -0,0 +1,993 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+use crate::{
+    dto::{
+        ReconciliationItemListResponse, ReconciliationRunListResponse, ReconciliationRunResponse,
+    },
+    models::{
+        AuditLog, OperationResult, ReconciliationCategory, ReconciliationItem, ReconciliationRun,
+        SettlementFileField, SettlementFileFormat, SettlementFileLayout, TenantContext,
+        Transaction, TransactionType,
+    },
+    repositories::{AuditLogRepository, ReconciliationRepository, TransactionRepository},
+    utils::{csv, error::AppError},
+};
+
+/// 默认日期容差（天）：收单机构按清算日期出文件，可能晚于交易日期一天
+const DEFAULT_DATE_TOLERANCE_DAYS: i64 = 1;
+
+/// 对账服务
+///
+/// 按配置的格式解析收单机构清算文件，逐笔与本地交易核对：先按RRN匹配，再按授权码匹配，
+/// 最后按金额、币种和日期匹配，交易日期相差不超过日期容差；后两种方式要求候选交易唯一。
+/// 匹配后金额差额在金额容差内为一致，否则为金额不符。
+/// 文件中未匹配的明细为收单机构单边，文件覆盖日期内未出现在文件中的本地交易为本地单边
+#[derive(Clone)]
+pub struct ReconciliationService {
+    reconciliation_repo: ReconciliationRepository,
+    transaction_repo: TransactionRepository,
+    audit_repo: AuditLogRepository,
+    layouts: Vec<SettlementFileLayout>,
+    tolerance: Tolerance,
+}
+
+/// 对账容差
+#[derive(Debug, Clone, Copy)]
+struct Tolerance {
+    /// 金额容差（分）
+    amount: i64,
+    /// 日期容差（天）
+    days: i64,
+}
+
+/// 清算文件明细
+#[derive(Debug, Clone)]
+struct SettlementRecord {
+    line_number: i64,
+    rrn: Option<String>,
+    authorization_code: Option<String>,
+    /// 金额（分），退款等贷记明细为负数
+    amount: i64,
+    currency: Option<String>,
+    transaction_date: NaiveDate,
+    terminal_id: Option<String>,
+}
+
+impl ReconciliationService {
+    /// 创建新的对账服务，默认金额容差为0、日期容差为1天
+    pub fn new(
+        reconciliation_repo: ReconciliationRepository,
+        transaction_repo: TransactionRepository,
+        audit_repo: AuditLogRepository,
+    ) -> Self {
+        Self {
+            reconciliation_repo,
+            transaction_repo,
+            audit_repo,
+            layouts: Vec::new(),
+            tolerance: Tolerance { amount: 0, days: DEFAULT_DATE_TOLERANCE_DAYS },
+        }
+    }
+
+    /// 设置可用的清算文件格式
+    pub fn with_layouts(mut self, layouts: Vec<SettlementFileLayout>) -> Self {
+        self.layouts = layouts;
+        self
+    }
+
+    /// 设置对账容差：金额容差（分）和日期容差（天）
+    pub fn with_tolerance(mut self, amount: i64, days: i64) -> Self {
+        self.tolerance = Tolerance { amount: amount.max(0), days: days.max(0) };
+        self
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            reconciliation_repo: self.reconciliation_repo.for_tenant(tenant),
+            transaction_repo: self.transaction_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            ..self.clone()
+        }
+    }
+
+    /// 导入清算文件并对账，保存对账任务和全部结果明细
+    pub async fn run_reconciliation(
+        &self,
+        layout: &str,
+        content: &str,
+        file_name: Option<String>,
+        merchant_id: Option<String>,
+        operator: &str,
+    ) -> Result<ReconciliationRunResponse, AppError> {
+        let layout = self.layouts.iter().find(|l| l.name == layout).ok_or_else(|| {
+            AppError::BadRequest(format!("Unknown settlement file layout: {}", layout))
+        })?;
+
+        if content.trim().is_empty() {
+            return Err(AppError::BadRequest("Settlement file is empty".to_string()));
+        }
+
+        let records = parse_settlement_file(layout, content)?;
+        let (Some(period_start), Some(period_end)) = (
+            records.iter().map(|r| r.transaction_date).min(),
+            records.iter().map(|r| r.transaction_date).max(),
+        ) else {
+            return Err(AppError::BadRequest("Settlement file has no records".to_string()));
+        };
+
+        // 按日期容差放宽本地交易的查询范围，范围外的交易只参与匹配，不计入本地单边
+        let from = period_start - chrono::Duration::days(self.tolerance.days);
+        let to = period_end + chrono::Duration::days(self.tolerance.days + 1);
+        let transactions = self
+            .transaction_repo
+            .list_for_reconciliation(
+                &from.format("%Y-%m-%d").to_string(),
+                &to.format("%Y-%m-%d").to_string(),
+                merchant_id.as_deref(),
+            )
+            .await?;
+
+        let mut run = ReconciliationRun::new(
+            layout.name.clone(),
+            file_name,
+            merchant_id,
+            period_start.format("%Y-%m-%d").to_string(),
+            period_end.format("%Y-%m-%d").to_string(),
+            operator.to_string(),
+        );
+        let items =
+            reconcile(&run.id, &records, &transactions, (period_start, period_end), self.tolerance);
+        run.tally(&items);
+
+        self.reconciliation_repo.create_run(&run, &items).await?;
+
+        let audit_log = AuditLog::new(
+            "RECONCILIATION_RUN".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_details(format!(
+            "Reconciliation run {} ({}, {} to {}): {} matched, {} amount mismatches, {} unmatched ours, {} unmatched theirs",
+            run.id,
+            run.layout,
+            run.period_start,
+            run.period_end,
+            run.matched_count,
+            run.amount_mismatch_count,
+            run.unmatched_ours_count,
+            run.unmatched_theirs_count
+        ));
+        self.audit_repo.create(&audit_log).await?;
+
+        tracing::info!(
+            "Reconciliation run {} completed: {} records, {} exceptions",
+            run.id,
+            run.total_records,
+            run.amount_mismatch_count + run.unmatched_ours_count + run.unmatched_theirs_count
+        );
+
+        Ok(ReconciliationRunResponse::from(run))
+    }
+
+    /// 列出对账任务
+    pub async fn list_runs(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<ReconciliationRunListResponse, AppError> {
+        let runs = self.reconciliation_repo.list_runs(limit, offset).await?;
+        let total = self.reconciliation_repo.count_runs().await?;
+
+        Ok(ReconciliationRunListResponse {
+            runs: runs.into_iter().map(ReconciliationRunResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 获取对账任务
+    pub async fn get_run(&self, run_id: &str) -> Result<ReconciliationRunResponse, AppError> {
+        Ok(ReconciliationRunResponse::from(self.find_run(run_id).await?))
+    }
+
+    /// 列出对账结果明细（支持按分类筛选）
+    pub async fn list_items(
+        &self,
+        run_id: &str,
+        category: Option<ReconciliationCategory>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<ReconciliationItemListResponse, AppError> {
+        let run = self.find_run(run_id).await?;
+        let items = self.reconciliation_repo.list_items(&run.id, category, limit, offset).await?;
+        let total = self.reconciliation_repo.count_items(&run.id, category).await?;
+
+        Ok(ReconciliationItemListResponse { items, total })
+    }
+
+    /// 生成差异报告（CSV），包含金额不符和双方单边的明细
+    pub async fn get_exception_report(&self, run_id: &str) -> Result<String, AppError> {
+        let run = self.find_run(run_id).await?;
+        let items = self.reconciliation_repo.list_exceptions(&run.id).await?;
+
+        let mut report = String::from(
+            "category,line_number,transaction_id,rrn,authorization_code,transaction_date,currency,our_amount,their_amount,difference,details\n",
+        );
+        for item in items {
+            let difference =
+                item.our_amount.zip(item.their_amount).map(|(ours, theirs)| theirs - ours);
+            report.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{}\n",
+                item.category,
+                optional(item.line_number),
+                csv::escape(item.transaction_id.as_deref().unwrap_or("")),
+                csv::escape(item.rrn.as_deref().unwrap_or("")),
+                csv::escape(item.authorization_code.as_deref().unwrap_or("")),
+                item.transaction_date.as_deref().unwrap_or(""),
+                csv::escape(item.currency.as_deref().unwrap_or("")),
+                optional(item.our_amount),
+                optional(item.their_amount),
+                optional(difference),
+                csv::escape(item.details.as_deref().unwrap_or(""))
+            ));
+        }
+
+        Ok(report)
+    }
+
+    async fn find_run(&self, run_id: &str) -> Result<ReconciliationRun, AppError> {
+        self.reconciliation_repo
+            .find_run(run_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Reconciliation run not found".to_string()))
+    }
+}
+
+fn optional(value: Option<i64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// 按格式定义解析清算文件，任一明细行无法解析时整个文件被拒绝
+fn parse_settlement_file(
+    layout: &SettlementFileLayout,
+    content: &str,
+) -> Result<Vec<SettlementRecord>, AppError> {
+    let delimiter = layout.delimiter.chars().next().unwrap_or(',');
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i as i64 + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    // 字段在每行中的位置：csv为列序号，定长为字符区间
+    let mut positions: Vec<(SettlementFileField, usize, usize)> = Vec::new();
+    match layout.format {
+        SettlementFileFormat::Csv => {
+            let header = if layout.has_header {
+                let (_, line) = lines.next().ok_or_else(|| {
+                    AppError::BadRequest("Missing settlement file header".to_string())
+                })?;
+                csv::parse_line(line, delimiter)
+                    .into_iter()
+                    .map(|h| h.trim().to_ascii_lowercase())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            for column in &layout.fields {
+                let index = match (&column.column, column.index) {
+                    (Some(name), _) if layout.has_header => {
+                        header.iter().position(|h| *h == name.trim().to_ascii_lowercase())
+                    },
+                    (_, index) => index,
+                }
+                .ok_or_else(|| {
+                    AppError::BadRequest(format!(
+                        "Settlement file header is missing the {:?} column",
+                        column.field
+                    ))
+                })?;
+                positions.push((column.field, index, 0));
+            }
+        },
+        SettlementFileFormat::FixedWidth => {
+            for column in &layout.fields {
+                let (Some(start), Some(length)) = (column.start, column.length) else {
+                    return Err(AppError::BadRequest(format!(
+                        "Layout {} has no position for {:?}",
+                        layout.name, column.field
+                    )));
+                };
+                positions.push((column.field, start.saturating_sub(1), length));
+            }
+        },
+    }
+
+    lines
+        .filter(|(_, line)| {
+            layout.record_prefix.as_deref().is_none_or(|prefix| line.starts_with(prefix))
+        })
+        .map(|(line_number, line)| {
+            let values: Vec<String> = match layout.format {
+                SettlementFileFormat::Csv => csv::parse_line(line, delimiter),
+                SettlementFileFormat::FixedWidth => Vec::new(),
+            };
+            let value = |field: SettlementFileField| {
+                positions.iter().find(|(f, _, _)| *f == field).and_then(|&(_, index, length)| {
+                    let value = match layout.format {
+                        SettlementFileFormat::Csv => values.get(index).cloned()?,
+                        SettlementFileFormat::FixedWidth => {
+                            line.chars().skip(index).take(length).collect()
+                        },
+                    };
+                    Some(value.trim().to_string()).filter(|v| !v.is_empty())
+                })
+            };
+
+            parse_record(line_number, &value, &layout.date_format)
+                .map_err(|e| AppError::BadRequest(format!("Line {}: {}", line_number, e)))
+        })
+        .collect()
+}
+
+fn parse_record(
+    line_number: i64,
+    value: &dyn Fn(SettlementFileField) -> Option<String>,
+    date_format: &str,
+) -> Result<SettlementRecord, String> {
+    let amount = value(SettlementFileField::Amount).ok_or("missing amount")?;
+    let date = value(SettlementFileField::TransactionDate).ok_or("missing transaction date")?;
+
+    Ok(SettlementRecord {
+        line_number,
+        rrn: value(SettlementFileField::Rrn),
+        authorization_code: value(SettlementFileField::AuthorizationCode),
+        amount: parse_amount(&amount).ok_or_else(|| format!("invalid amount '{}'", amount))?,
+        currency: value(SettlementFileField::Currency).map(|c| c.to_ascii_uppercase()),
+        transaction_date: parse_date(&date, date_format)
+            .ok_or_else(|| format!("invalid transaction date '{}'", date))?,
+        terminal_id: value(SettlementFileField::TerminalId),
+    })
+}
+
+/// 解析金额为分：带小数点时按元解析（最多两位小数），否则按分解析；保留符号
+fn parse_amount(value: &str) -> Option<i64> {
+    let (sign, digits) = match value.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    if digits.starts_with(['-', '+']) {
+        return None;
+    }
+
+    let amount = match digits.split_once('.') {
+        Some((units, fraction)) if fraction.len() <= 2 => {
+            let fraction = format!("{:0<2}", fraction);
+            units.parse::<i64>().ok()?.checked_mul(100)?.checked_add(fraction.parse().ok()?)?
+        },
+        Some(_) => return None,
+        None => digits.parse::<i64>().ok()?,
+    };
+
+    amount.checked_mul(sign)
+}
+
+/// 按格式解析交易日期，格式包含时间时取日期部分
+fn parse_date(value: &str, format: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, format)
+        .or_else(|_| NaiveDateTime::parse_from_str(value, format).map(|dt| dt.date()))
+        .ok()
+}
+
+/// 本地交易的交易日期（UTC）
+fn transaction_date(transaction: &Transaction) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(&transaction.created_at)
+        .ok()
+        .map(|dt| dt.naive_utc().date())
+}
+
+/// 逐笔核对清算文件明细与本地交易
+///
+/// 每笔本地交易最多匹配一条明细，按以下顺序分三轮匹配，前一轮的匹配结果优先：
+/// 1. RRN：候选交易中优先选择金额在容差内、差额最小、日期最近的
+/// 2. 授权码：授权码可能跨终端、跨日期重复，要求唯一候选（多个候选时取唯一金额在容差内的）
+/// 3. 金额、币种和日期：用于没有RRN和授权码的明细，要求唯一候选且双方已有的键不冲突
+///
+/// 本地交易按RRN和授权码预先建立索引，前两轮每条明细只检查键相同的候选交易
+fn reconcile(
+    run_id: &str,
+    records: &[SettlementRecord],
+    transactions: &[Transaction],
+    period: (NaiveDate, NaiveDate),
+    tolerance: Tolerance,
+) -> Vec<ReconciliationItem> {
+    let dates: Vec<Option<NaiveDate>> = transactions.iter().map(transaction_date).collect();
+    let by_rrn = index_by(transactions, |t| t.processor_reference.as_deref());
+    let by_authorization_code = index_by(transactions, |t| t.authorization_code.as_deref());
+    let mut matched = vec![false; transactions.len()];
+    let mut found: Vec<Option<(usize, &str)>> = vec![None; records.len()];
+    let mut ambiguity: Vec<Option<String>> = vec![None; records.len()];
+
+    // 未匹配、日期在容差内且终端号不冲突的交易才可作为候选
+    let eligible = |record: &SettlementRecord, i: usize, matched: &[bool]| {
+        !matched[i]
+            && dates[i].is_some_and(|date| {
+                (date - record.transaction_date).num_days().abs() <= tolerance.days
+            })
+            && match (&record.terminal_id, &transactions[i].terminal_id) {
+                (Some(theirs), Some(ours)) => theirs == ours,
+                _ => true,
+            }
+    };
+    let difference =
+        |record: &SettlementRecord, i: usize| (our_amount(&transactions[i]) - record.amount).abs();
+    let keyed = |index: &HashMap<String, Vec<usize>>, value: &str| -> Vec<usize> {
+        index.get(&normalize_key(value)).cloned().unwrap_or_default()
+    };
+
+    for (r, record) in records.iter().enumerate() {
+        let Some(rrn) = record.rrn.as_deref() else {
+            continue;
+        };
+        let best = keyed(&by_rrn, rrn)
+            .into_iter()
+            .filter(|i| eligible(record, *i, &matched))
+            .min_by_key(|i| {
+                let difference = difference(record, *i);
+                let days = dates[*i]
+                    .map(|date| (date - record.transaction_date).num_days().abs())
+                    .unwrap_or_default();
+                (difference > tolerance.amount, difference, days)
+            });
+        if let Some(i) = best {
+            matched[i] = true;
+            found[r] = Some((i, "RRN"));
+        }
+    }
+
+    for (r, record) in records.iter().enumerate() {
+        let Some(code) = record.authorization_code.as_deref().filter(|_| found[r].is_none()) else {
+            continue;
+        };
+        let candidates: Vec<usize> = keyed(&by_authorization_code, code)
+            .into_iter()
+            .filter(|i| eligible(record, *i, &matched))
+            .collect();
+        let within: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|i| difference(record, *i) <= tolerance.amount)
+            .collect();
+
+        match (candidates.as_slice(), within.as_slice()) {
+            ([i], _) | (_, [i]) => {
+                matched[*i] = true;
+                found[r] = Some((*i, "authorization code"));
+            },
+            ([], _) => {},
+            _ => {
+                ambiguity[r] = Some(format!(
+                    "Ambiguous: {} transactions share authorization code {}",
+                    candidates.len(),
+                    code
+                ));
+            },
+        }
+    }
+
+    for (r, record) in records.iter().enumerate() {
+        if found[r].is_some() {
+            continue;
+        }
+        let candidates: Vec<usize> = (0..transactions.len())
+            .filter(|i| eligible(record, *i, &matched))
+            .filter(|i| difference(record, *i) <= tolerance.amount)
+            .filter(|i| {
+                record.currency.as_deref().is_none_or(|currency| {
+                    currency.eq_ignore_ascii_case(&transactions[*i].currency)
+                })
+            })
+            .filter(|i| !keys_conflict(record, &transactions[*i]))
+            .collect();
+
+        match candidates.as_slice() {
+            [i] => {
+                matched[*i] = true;
+                found[r] = Some((*i, "amount and date"));
+            },
+            [] => {},
+            _ => {
+                ambiguity[r].get_or_insert_with(|| {
+                    format!(
+                        "Ambiguous: {} transactions match amount, currency and date",
+                        candidates.len()
+                    )
+                });
+            },
+        }
+    }
+
+    let mut items = Vec::with_capacity(records.len());
+    for (r, record) in records.iter().enumerate() {
+        let mut item = match found[r] {
+            Some((index, key)) => {
+                let transaction = &transactions[index];
+                let ours = our_amount(transaction);
+                let difference = record.amount - ours;
+
+                let currency_matches = record
+                    .currency
+                    .as_deref()
+                    .is_none_or(|currency| currency.eq_ignore_ascii_case(&transaction.currency));
+                let (category, details) = if !currency_matches {
+                    (
+                        ReconciliationCategory::AmountMismatch,
+                        format!(
+                            "Matched by {}; currency {} does not match {}",
+                            key,
+                            record.currency.as_deref().unwrap_or_default(),
+                            transaction.currency
+                        ),
+                    )
+                } else if difference.abs() > tolerance.amount {
+                    (
+                        ReconciliationCategory::AmountMismatch,
+                        format!("Matched by {}; amount differs by {}", key, difference),
+                    )
+                } else if difference != 0 {
+                    (
+                        ReconciliationCategory::Matched,
+                        format!(
+                            "Matched by {}; amount differs by {} within tolerance",
+                            key, difference
+                        ),
+                    )
+                } else {
+                    (ReconciliationCategory::Matched, format!("Matched by {}", key))
+                };
+
+                let mut item = ReconciliationItem::new(run_id.to_string(), category);
+                item.transaction_id = Some(transaction.id.clone());
+                item.rrn = record.rrn.clone().or_else(|| transaction.processor_reference.clone());
+                item.authorization_code = record
+                    .authorization_code
+                    .clone()
+                    .or_else(|| transaction.authorization_code.clone());
+                item.currency =
+                    record.currency.clone().or_else(|| Some(transaction.currency.clone()));
+                item.our_amount = Some(ours);
+                item.details = Some(details);
+                item
+            },
+            None => {
+                let mut item = ReconciliationItem::new(
+                    run_id.to_string(),
+                    ReconciliationCategory::UnmatchedTheirs,
+                );
+                item.rrn = record.rrn.clone();
+                item.authorization_code = record.authorization_code.clone();
+                item.currency = record.currency.clone();
+                item.details = Some(
+                    ambiguity[r].clone().unwrap_or_else(|| "No matching transaction".to_string()),
+                );
+                item
+            },
+        };
+
+        item.line_number = Some(record.line_number);
+        item.transaction_date = Some(record.transaction_date.format("%Y-%m-%d").to_string());
+        item.their_amount = Some(record.amount);
+        items.push(item);
+    }
+
+    // 文件覆盖日期内未匹配的本地交易
+    for (index, transaction) in transactions.iter().enumerate() {
+        let Some(date) = dates[index] else {
+            continue;
+        };
+        if matched[index] || date < period.0 || date > period.1 {
+            continue;
+        }
+
+        let mut item =
+            ReconciliationItem::new(run_id.to_string(), ReconciliationCategory::UnmatchedOurs);
+        item.transaction_id = Some(transaction.id.clone());
+        item.rrn = transaction.processor_reference.clone();
+        item.authorization_code = transaction.authorization_code.clone();
+        item.transaction_date = Some(date.format("%Y-%m-%d").to_string());
+        item.currency = Some(transaction.currency.clone());
+        item.our_amount = Some(our_amount(transaction));
+        item.details = Some("Not present in settlement file".to_string());
+        items.push(item);
+    }
+
+    items
+}
+
+/// 明细和本地交易都有RRN或授权码且不一致
+fn keys_conflict(record: &SettlementRecord, transaction: &Transaction) -> bool {
+    let differs = |theirs: Option<&str>, ours: Option<&str>| matches!((theirs, ours), (Some(theirs), Some(ours)) if normalize_key(theirs) != normalize_key(ours));
+
+    differs(record.rrn.as_deref(), transaction.processor_reference.as_deref())
+        || differs(record.authorization_code.as_deref(), transaction.authorization_code.as_deref())
+}
+
+/// 按匹配键索引本地交易，键相同的交易保持原有顺序
+fn index_by(
+    transactions: &[Transaction],
+    key: fn(&Transaction) -> Option<&str>,
+) -> HashMap<String, Vec<usize>> {
+    let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, transaction) in transactions.iter().enumerate() {
+        if let Some(value) = key(transaction) {
+            index.entry(normalize_key(value)).or_default().push(i);
+        }
+    }
+    index
+}
+
+/// 匹配键忽略首尾空白和大小写
+fn normalize_key(value: &str) -> String {
+    value.trim().to_ascii_uppercase()
+}
+
+/// 本地交易的清算金额：部分批准时为批准金额，退款和撤销为负数
+fn our_amount(transaction: &Transaction) -> i64 {
+    let amount = transaction.approved_amount.unwrap_or(transaction.amount);
+    match transaction.transaction_type {
+        TransactionType::Refund | TransactionType::Void => -amount,
+        _ => amount,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SettlementFileColumn, TransactionStatus};
+
+    fn column(field: SettlementFileField, name: &str) -> SettlementFileColumn {
+        SettlementFileColumn {
+            field,
+            column: Some(name.to_string()),
+            index: None,
+            start: None,
+            length: None,
+        }
+    }
+
+    fn fixed(field: SettlementFileField, start: usize, length: usize) -> SettlementFileColumn {
+        SettlementFileColumn {
+            field,
+            column: None,
+            index: None,
+            start: Some(start),
+            length: Some(length),
+        }
+    }
+
+    fn csv_layout() -> SettlementFileLayout {
+        SettlementFileLayout {
+            name: "acquirer_csv".to_string(),
+            format: SettlementFileFormat::Csv,
+            delimiter: ",".to_string(),
+            has_header: true,
+            record_prefix: None,
+            date_format: "%Y-%m-%d".to_string(),
+            fields: vec![
+                column(SettlementFileField::Rrn, "RRN"),
+                column(SettlementFileField::AuthorizationCode, "Auth Code"),
+                column(SettlementFileField::Amount, "Amount"),
+                column(SettlementFileField::Currency, "Currency"),
+                column(SettlementFileField::TransactionDate, "Date"),
+            ],
+        }
+    }
+
+    fn transaction(
+        id: &str,
+        rrn: Option<&str>,
+        auth: Option<&str>,
+        amount: i64,
+        date: &str,
+    ) -> Transaction {
+        let mut transaction = Transaction::new(
+            "device-1".to_string(),
+            TransactionType::Payment,
+            amount,
+            "CNY".to_string(),
+            "ksn".to_string(),
+        );
+        transaction.id = id.to_string();
+        transaction.status = TransactionStatus::Approved;
+        transaction.processor_reference = rrn.map(str::to_string);
+        transaction.authorization_code = auth.map(str::to_string);
+        transaction.created_at = format!("{}T10:00:00+00:00", date);
+        transaction
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("12345"), Some(12345));
+        assert_eq!(parse_amount("000000012345"), Some(12345));
+        assert_eq!(parse_amount("123.45"), Some(12345));
+        assert_eq!(parse_amount("123.4"), Some(12340));
+        assert_eq!(parse_amount("-10.00"), Some(-1000));
+        assert_eq!(parse_amount("+10.00"), Some(1000));
+        assert_eq!(parse_amount("-00000001500"), Some(-1500));
+        assert_eq!(parse_amount("--10"), None);
+        assert_eq!(parse_amount("1.234"), None);
+        assert_eq!(parse_amount("abc"), None);
+    }
+
+    #[test]
+    fn test_parse_csv_file() {
+        let content = "rrn,auth code,amount,currency,date\n\
+                       000000000001,A1,100.00,cny,2024-01-01\n\
+                       \n\
+                       ,A2,5000,CNY,2024-01-02\n";
+        let records = parse_settlement_file(&csv_layout(), content).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].line_number, 2);
+        assert_eq!(records[0].rrn.as_deref(), Some("000000000001"));
+        assert_eq!(records[0].amount, 10000);
+        assert_eq!(records[0].currency.as_deref(), Some("CNY"));
+        assert_eq!(records[1].line_number, 4);
+        assert!(records[1].rrn.is_none());
+        assert_eq!(records[1].transaction_date, date("2024-01-02"));
+    }
+
+    #[test]
+    fn test_parse_csv_file_errors() {
+        let layout = csv_layout();
+        assert!(parse_settlement_file(&layout, "rrn,amount,date\n1,100,2024-01-01").is_err());
+
+        let err = parse_settlement_file(
+            &layout,
+            "rrn,auth code,amount,currency,date\n1,A1,12x,CNY,2024-01-01",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Line 2"));
+    }
+
+    #[test]
+    fn test_parse_fixed_width_file() {
+        let layout = SettlementFileLayout {
+            name: "acquirer_fixed".to_string(),
+            format: SettlementFileFormat::FixedWidth,
+            delimiter: ",".to_string(),
+            has_header: false,
+            record_prefix: Some("D".to_string()),
+            date_format: "%Y%m%d".to_string(),
+            fields: vec![
+                fixed(SettlementFileField::Rrn, 2, 12),
+                fixed(SettlementFileField::AuthorizationCode, 14, 6),
+                fixed(SettlementFileField::Amount, 20, 12),
+                fixed(SettlementFileField::TransactionDate, 32, 8),
+            ],
+        };
+        let content = "H20240101ACQUIRER\n\
+                       D000000000001A1    00000001000020240101\n\
+                       T000001\n";
+        let records = parse_settlement_file(&layout, content).unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].line_number, 2);
+        assert_eq!(records[0].authorization_code.as_deref(), Some("A1"));
+        assert_eq!(records[0].amount, 10000);
+        assert_eq!(records[0].transaction_date, date("2024-01-01"));
+    }
+
+    #[test]
+    fn test_reconcile_categories() {
+        let record = |line: i64, rrn: Option<&str>, auth: Option<&str>, amount: i64, day: &str| {
+            SettlementRecord {
+                line_number: line,
+                rrn: rrn.map(str::to_string),
+                authorization_code: auth.map(str::to_string),
+                amount,
+                currency: None,
+                transaction_date: date(day),
+                terminal_id: None,
+            }
+        };
+        let records = vec![
+            record(2, Some("R1"), None, 1000, "2024-01-01"),
+            record(3, Some("R-UNKNOWN"), Some("A2"), 2002, "2024-01-02"),
+            record(4, Some("R3"), None, 3500, "2024-01-01"),
+            record(5, Some("R9"), None, 900, "2024-01-01"),
+        ];
+        let transactions = vec![
+            transaction("t1", Some("R1"), None, 1000, "2024-01-01"),
+            transaction("t2", None, Some("A2"), 2000, "2024-01-01"),
+            transaction("t3", Some("R3"), None, 3000, "2024-01-01"),
+            transaction("t4", Some("R4"), None, 4000, "2024-01-02"),
+            // 日期容差范围外的交易只参与匹配，不计入本地单边
+            transaction("t5", Some("R5"), None, 5000, "2023-12-31"),
+        ];
+
+        let items = reconcile(
+            "run-1",
+            &records,
+            &transactions,
+            (date("2024-01-01"), date("2024-01-02")),
+            Tolerance { amount: 5, days: 1 },
+        );
+        let category = |id: &str| {
+            items
+                .iter()
+                .find(|i| i.transaction_id.as_deref() == Some(id))
+                .map(|i| i.category.clone())
+        };
+
+        assert_eq!(category("t1").as_deref(), Some("MATCHED"));
+        assert_eq!(category("t2").as_deref(), Some("MATCHED"));
+        assert_eq!(category("t3").as_deref(), Some("AMOUNT_MISMATCH"));
+        assert_eq!(category("t4").as_deref(), Some("UNMATCHED_OURS"));
+        assert_eq!(category("t5"), None);
+        assert!(items
+            .iter()
+            .any(|i| i.category == "UNMATCHED_THEIRS" && i.line_number == Some(5)));
+        assert_eq!(items.len(), 5);
+    }
+
+    #[test]
+    fn test_reconcile_date_tolerance_and_single_use() {
+        let records = vec![
+            SettlementRecord {
+                line_number: 2,
+                rrn: Some("R1".to_string()),
+                authorization_code: None,
+                amount: 1000,
+                currency: Some("USD".to_string()),
+                transaction_date: date("2024-01-02"),
+                terminal_id: None,
+            },
+            SettlementRecord {
+                line_number: 3,
+                rrn: Some("R1".to_string()),
+                authorization_code: None,
+                amount: 1000,
+                currency: None,
+                transaction_date: date("2024-01-05"),
+                terminal_id: None,
+            },
+        ];
+        let transactions = vec![transaction("t1", Some("R1"), None, 1000, "2024-01-01")];
+
+        let items = reconcile(
+            "run-1",
+            &records,
+            &transactions,
+            (date("2024-01-02"), date("2024-01-05")),
+            Tolerance { amount: 0, days: 1 },
+        );
+
+        assert_eq!(items[0].category, "AMOUNT_MISMATCH");
+        assert!(items[0].details.as_deref().unwrap().contains("currency USD"));
+        assert_eq!(items[1].category, "UNMATCHED_THEIRS");
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn test_reconcile_compares_signed_amounts() {
+        let record = |line: i64, rrn: &str, amount: i64| SettlementRecord {
+            line_number: line,
+            rrn: Some(rrn.to_string()),
+            authorization_code: None,
+            amount,
+            currency: None,
+            transaction_date: date("2024-01-01"),
+            terminal_id: None,
+        };
+        let mut refund = transaction("t1", Some("R1"), None, 1500, "2024-01-01");
+        refund.transaction_type = TransactionType::Refund;
+        let mut credited_as_sale = transaction("t2", Some("r2 "), None, 1500, "2024-01-01");
+        credited_as_sale.transaction_type = TransactionType::Refund;
+
+        let items = reconcile(
+            "run-1",
+            &[record(2, "R1", -1500), record(3, "R2", 1500)],
+            &[refund, credited_as_sale],
+            (date("2024-01-01"), date("2024-01-01")),
+            Tolerance { amount: 0, days: 0 },
+        );
+
+        assert_eq!(items[0].category, "MATCHED");
+        assert_eq!(items[0].our_amount, Some(-1500));
+        // 收单机构按借记清算的退款金额不符
+        assert_eq!(items[1].category, "AMOUNT_MISMATCH");
+        assert_eq!(items[1].transaction_id.as_deref(), Some("t2"));
+        assert!(items[1].details.as_deref().unwrap().contains("differs by 3000"));
+    }
+
+    #[test]
+    fn test_reconcile_without_keys_requires_single_candidate() {
+        let record = |line: i64, amount: i64, currency: Option<&str>| SettlementRecord {
+            line_number: line,
+            rrn: None,
+            authorization_code: None,
+            amount,
+            currency: currency.map(str::to_string),
+            transaction_date: date("2024-01-01"),
+            terminal_id: None,
+        };
+        let transactions = vec![
+            transaction("t1", Some("R1"), None, 1000, "2024-01-01"),
+            transaction("t2", Some("R2"), None, 2500, "2024-01-01"),
+            transaction("t3", Some("R3"), None, 2500, "2024-01-01"),
+        ];
+
+        let items = reconcile(
+            "run-1",
+            &[
+                record(2, 1000, Some("CNY")),
+                record(3, 2500, None),
+                record(4, 1000, Some("USD")),
+            ],
+            &transactions,
+            (date("2024-01-01"), date("2024-01-01")),
+            Tolerance { amount: 0, days: 0 },
+        );
+
+        assert_eq!(items[0].category, "MATCHED");
+        assert_eq!(items[0].transaction_id.as_deref(), Some("t1"));
+        assert_eq!(items[0].details.as_deref(), Some("Matched by amount and date"));
+        assert_eq!(items[1].category, "UNMATCHED_THEIRS");
+        assert!(items[1].details.as_deref().unwrap().starts_with("Ambiguous: 2 transactions"));
+        assert_eq!(items[2].category, "UNMATCHED_THEIRS");
+        assert_eq!(items[2].details.as_deref(), Some("No matching transaction"));
+        assert_eq!(items.iter().filter(|i| i.category == "UNMATCHED_OURS").count(), 2);
+    }
+
+    #[test]
+    fn test_reconcile_ambiguous_authorization_code() {
+        let record = |line: i64, amount: i64, terminal: Option<&str>| SettlementRecord {
+            line_number: line,
+            rrn: None,
+            authorization_code: Some("A1".to_string()),
+            amount,
+            currency: None,
+            transaction_date: date("2024-01-02"),
+            terminal_id: terminal.map(str::to_string),
+        };
+        let mut first = transaction("t1", None, Some("A1"), 1000, "2024-01-01");
+        first.terminal_id = Some("T1".to_string());
+        let mut second = transaction("t2", None, Some("A1"), 1000, "2024-01-02");
+        second.terminal_id = Some("T2".to_string());
+        let mut third = transaction("t3", None, Some("A1"), 3000, "2024-01-02");
+        third.terminal_id = Some("T2".to_string());
+
+        let transactions = [first, second, third];
+        let period = (date("2024-01-01"), date("2024-01-02"));
+        let tolerance = Tolerance { amount: 0, days: 1 };
+
+        let items = reconcile(
+            "run-1",
+            &[record(2, 1000, None), record(3, 3000, None)],
+            &transactions,
+            period,
+            tolerance,
+        );
+
+        // 同一授权码跨终端、跨日期出现两笔相同金额的交易，不能确定对应哪一笔
+        assert_eq!(items[0].category, "UNMATCHED_THEIRS");
+        assert!(items[0].details.as_deref().unwrap().contains("share authorization code A1"));
+        // 金额区分出唯一候选
+        assert_eq!(items[1].category, "MATCHED");
+        assert_eq!(items[1].transaction_id.as_deref(), Some("t3"));
+
+        // 终端号区分出唯一候选
+        let items =
+            reconcile("run-2", &[record(2, 1000, Some("T2"))], &transactions, period, tolerance);
+        assert_eq!(items[0].category, "MATCHED");
+        assert_eq!(items[0].transaction_id.as_deref(), Some("t2"));
+    }
+}
@@ -0,0 +1,531 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, NaiveTime, Utc};
+
+use crate::{
+    dto::{
+        SettlementBatchDetailResponse, SettlementBatchListResponse, SettlementBatchResponse,
+        SettlementNetAmount, TransactionResponse,
+    },
+    infrastructure::{
+        payment::{SettlementItem, SettlementOutcome, SettlementRequest, SettlementResponse},
+        PaymentRouter,
+    },
+    models::{
+        net_amounts, AuditLog, BatchStatus, OperationResult, SettlementBatch,
+        SettlementFailureReason, TenantContext, TransactionStatus, DEFAULT_SETTLEMENT_MAX_ATTEMPTS,
+    },
+    repositories::{
+        AuditLogRepository, DeviceRepository, MerchantRepository, SettlementRepository,
+        TransactionRepository,
+    },
+    utils::error::AppError,
+};
+
+/// 每轮提交的最大批次数
+const SUBMISSION_BATCH_SIZE: i64 = 20;
+
+/// 领取提交后的租约时长，超时未完成的提交可被其他节点重新领取
+const SUBMISSION_LEASE: Duration = Duration::from_secs(300);
+
+/// 两次提交重试之间的最长间隔
+const MAX_SUBMISSION_RETRY_DELAY: Duration = Duration::from_secs(3600);
+
+/// 系统操作者
+const SYSTEM_ACTOR: &str = "system";
+
+/// 结算批次服务
+///
+/// 已批准的交易按设备和商户自动入批；每天日切时间之前开批的批次由后台任务关批，
+/// 设备也可主动结算。关批时按交易类型和币种汇总合计并提交处理器：合计不平时上送明细
+/// 重新对账，仍不平或被拒绝时批次进入FAILED；通信失败按指数退避重试，超过最大次数后
+/// 进入FAILED。失败的批次可重新开批，修正后随下次结算再次提交
+#[derive(Clone)]
+pub struct SettlementService {
+    settlement_repo: SettlementRepository,
+    transaction_repo: TransactionRepository,
+    device_repo: DeviceRepository,
+    audit_repo: AuditLogRepository,
+    payment_router: Arc<PaymentRouter>,
+    merchant_repo: Option<MerchantRepository>,
+    cutoff: NaiveTime,
+    max_attempts: i64,
+    retry_base: Duration,
+}
+
+impl SettlementService {
+    /// 创建新的结算服务，默认日切时间为UTC 23:00
+    pub fn new(
+        settlement_repo: SettlementRepository,
+        transaction_repo: TransactionRepository,
+        device_repo: DeviceRepository,
+        audit_repo: AuditLogRepository,
+    ) -> Self {
+        Self {
+            settlement_repo,
+            transaction_repo,
+            device_repo,
+            audit_repo,
+            payment_router: Arc::new(PaymentRouter::default()),
+            merchant_repo: None,
+            cutoff: NaiveTime::from_hms_opt(23, 0, 0).unwrap_or_default(),
+            max_attempts: DEFAULT_SETTLEMENT_MAX_ATTEMPTS,
+            retry_base: Duration::from_secs(60),
+        }
+    }
+
+    /// 设置支付处理器路由，批次按商户路由到与交易相同的连接器
+    pub fn with_payment_router(mut self, payment_router: Arc<PaymentRouter>) -> Self {
+        self.payment_router = payment_router;
+        self
+    }
+
+    /// 设置商户Repository，用于取得收单机构商户号
+    pub fn with_merchant_repo(mut self, merchant_repo: MerchantRepository) -> Self {
+        self.merchant_repo = Some(merchant_repo);
+        self
+    }
+
+    /// 设置日切时间（UTC）
+    pub fn with_cutoff(mut self, cutoff: NaiveTime) -> Self {
+        self.cutoff = cutoff;
+        self
+    }
+
+    /// 设置提交重试策略：最大提交次数和首次重试间隔
+    pub fn with_retry_policy(mut self, max_attempts: i64, retry_base: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_base = retry_base;
+        self
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            settlement_repo: self.settlement_repo.for_tenant(tenant),
+            transaction_repo: self.transaction_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            merchant_repo: self.merchant_repo.as_ref().map(|repo| repo.for_tenant(tenant)),
+            ..self.clone()
+        }
+    }
+
+    /// 设备结算：关闭设备的所有开放批次并立即提交，返回提交后的批次
+    pub async fn close_device_batches(
+        &self,
+        device_id: &str,
+        actor: &str,
+    ) -> Result<Vec<SettlementBatchResponse>, AppError> {
+        self.device_repo.find_by_id(device_id).await?.ok_or(AppError::DeviceNotFound)?;
+
+        let mut closed = Vec::new();
+        for batch in self.settlement_repo.find_open_by_device(device_id).await? {
+            if self.close_batch(&batch, actor).await? {
+                closed.push(batch.id);
+            }
+        }
+
+        let now = Utc::now();
+        let lease_until = lease_until(now);
+        let mut batches = Vec::new();
+        for batch_id in closed {
+            if self
+                .settlement_repo
+                .claim_submission(&batch_id, &now.to_rfc3339(), &lease_until)
+                .await?
+            {
+                if let Some(batch) = self.settlement_repo.find_by_id(&batch_id).await? {
+                    self.submit_batch(&batch).await?;
+                }
+            }
+            if let Some(batch) = self.settlement_repo.find_by_id(&batch_id).await? {
+                batches.push(SettlementBatchResponse::from(batch));
+            }
+        }
+
+        Ok(batches)
+    }
+
+    /// 关闭日切时间之前开批的开放批次，返回关批数量
+    pub async fn close_due_batches(&self) -> Result<usize, AppError> {
+        let cutoff = last_cutoff(Utc::now(), self.cutoff).to_rfc3339();
+        let mut closed = 0;
+
+        for batch in self.settlement_repo.list_due_for_close(&cutoff).await? {
+            if self.close_batch(&batch, SYSTEM_ACTOR).await? {
+                closed += 1;
+            }
+        }
+
+        Ok(closed)
+    }
+
+    /// 提交已关批、到期的批次，返回结算成功的数量
+    pub async fn submit_pending(&self) -> Result<usize, AppError> {
+        let now = Utc::now();
+        let now_str = now.to_rfc3339();
+        let lease_until = lease_until(now);
+
+        let due = self
+            .settlement_repo
+            .list_due_submissions(&now_str, SUBMISSION_BATCH_SIZE)
+            .await?;
+        let mut settled = 0;
+
+        for batch in due {
+            // 其他节点已领取
+            if !self.settlement_repo.claim_submission(&batch.id, &now_str, &lease_until).await? {
+                continue;
+            }
+
+            let batch = SettlementBatch { submit_attempts: batch.submit_attempts + 1, ..batch };
+            match self.submit_batch(&batch).await {
+                Ok(BatchStatus::Settled) => settled += 1,
+                Ok(_) => {},
+                // 记录结果失败时保持领取状态，租约到期后重试
+                Err(e) => tracing::error!("Failed to submit settlement batch {}: {}", batch.id, e),
+            }
+        }
+
+        Ok(settled)
+    }
+
+    /// 启动后台结算任务：日切关批并提交待结算批次
+    pub fn start_settlement_worker(
+        self: Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.close_due_batches().await {
+                    tracing::error!("Failed to close settlement batches: {}", e);
+                }
+                if let Err(e) = self.submit_pending().await {
+                    tracing::error!("Failed to submit settlement batches: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 列出批次
+    pub async fn list_batches(
+        &self,
+        device_id: Option<&str>,
+        status: Option<BatchStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<SettlementBatchListResponse, AppError> {
+        let batches = self.settlement_repo.list(device_id, status, limit, offset).await?;
+        let total = self.settlement_repo.count(device_id, status).await?;
+
+        Ok(SettlementBatchListResponse {
+            batches: batches.into_iter().map(SettlementBatchResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 获取批次详情：合计、各币种净额和批内交易
+    pub async fn get_batch(
+        &self,
+        batch_id: &str,
+    ) -> Result<SettlementBatchDetailResponse, AppError> {
+        let batch = self.find_batch(batch_id).await?;
+        let totals = self.settlement_repo.list_totals(&batch.id).await?;
+        let transactions = self.transaction_repo.list_by_batch(&batch.id).await?;
+
+        Ok(SettlementBatchDetailResponse {
+            net_amounts: net_amounts(&totals)
+                .into_iter()
+                .map(|(currency, amount)| SettlementNetAmount { currency, amount })
+                .collect(),
+            batch: SettlementBatchResponse::from(batch),
+            totals,
+            transactions: transactions.into_iter().map(TransactionResponse::from).collect(),
+        })
+    }
+
+    /// 重新开放失败的批次
+    ///
+    /// 设备和商户已有新的开放批次时两者合并，批内交易随下次关批重新汇总提交
+    pub async fn reopen_batch(
+        &self,
+        batch_id: &str,
+        operator: &str,
+    ) -> Result<SettlementBatchResponse, AppError> {
+        let batch = self.find_batch(batch_id).await?;
+        if batch.status != BatchStatus::Failed.as_str()
+            || !self.settlement_repo.reopen(&batch.id).await?
+        {
+            return Err(AppError::BadRequest(format!(
+                "Settlement batch in status {} cannot be reopened",
+                batch.status
+            )));
+        }
+
+        self.audit(
+            "SETTLEMENT_BATCH_REOPEN",
+            operator,
+            OperationResult::Success,
+            &batch.device_id,
+            format!(
+                "Settlement batch {} #{} reopened, previous failure: {}",
+                batch.id,
+                batch.batch_number,
+                batch.failure_reason.as_deref().unwrap_or_default()
+            ),
+        )
+        .await?;
+
+        Ok(SettlementBatchResponse::from(self.find_batch(&batch.id).await?))
+    }
+
+    async fn find_batch(&self, batch_id: &str) -> Result<SettlementBatch, AppError> {
+        self.settlement_repo
+            .find_by_id(batch_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Settlement batch not found".to_string()))
+    }
+
+    /// 关批并写审计日志，批次已被关闭时返回false
+    async fn close_batch(&self, batch: &SettlementBatch, actor: &str) -> Result<bool, AppError> {
+        if !self.settlement_repo.close(&batch.id, actor).await? {
+            return Ok(false);
+        }
+
+        tracing::info!("Settlement batch {} #{} closed by {}", batch.id, batch.batch_number, actor);
+        self.audit(
+            "SETTLEMENT_BATCH_CLOSE",
+            actor,
+            OperationResult::Success,
+            &batch.device_id,
+            format!("Settlement batch {} #{} closed", batch.id, batch.batch_number),
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    /// 提交批次并记录结果，返回批次的新状态
+    ///
+    /// 合计不平时上送明细重新对账；通信失败未达最大次数时批次保持CLOSED等待重试
+    async fn submit_batch(&self, batch: &SettlementBatch) -> Result<BatchStatus, AppError> {
+        let totals = self.settlement_repo.list_totals(&batch.id).await?;
+
+        // 空批次无需提交处理器
+        if totals.is_empty() {
+            self.settlement_repo.mark_settled(&batch.id, None, None, None, false).await?;
+            return Ok(BatchStatus::Settled);
+        }
+
+        let acquirer_mid = match (&self.merchant_repo, &batch.merchant_id) {
+            (Some(repo), Some(merchant_id)) => {
+                repo.find_by_id(merchant_id).await?.and_then(|m| m.acquirer_mid)
+            },
+            _ => None,
+        };
+        let terminal_id =
+            self.device_repo.find_by_id(&batch.device_id).await?.and_then(|d| d.terminal_id);
+        let (processor_name, processor) = self
+            .payment_router
+            .route(batch.merchant_id.as_deref(), acquirer_mid.as_deref())?;
+
+        let mut request = SettlementRequest {
+            batch_id: batch.id.clone(),
+            batch_number: batch.batch_number,
+            merchant_id: batch.merchant_id.clone(),
+            acquirer_mid,
+            terminal_id,
+            totals,
+            transactions: Vec::new(),
+        };
+
+        let mut result = processor.settle(&request).await;
+        if matches!(&result, Ok(response) if response.outcome == SettlementOutcome::TotalsMismatch)
+        {
+            tracing::warn!("Settlement batch {} out of balance, uploading batch", batch.id);
+            request.transactions = self.upload_items(&batch.id).await?;
+            result = processor.settle(&request).await;
+        }
+
+        let (status, details) = match result {
+            Ok(SettlementResponse {
+                outcome: SettlementOutcome::Settled,
+                response_code,
+                processor_reference,
+                ..
+            }) => {
+                self.settlement_repo
+                    .mark_settled(
+                        &batch.id,
+                        Some(processor_name),
+                        processor_reference.as_deref(),
+                        Some(&response_code),
+                        request.is_upload(),
+                    )
+                    .await?;
+                (BatchStatus::Settled, format!("settled, response_code={}", response_code))
+            },
+            Ok(response) => {
+                let reason = if response.outcome == SettlementOutcome::TotalsMismatch {
+                    SettlementFailureReason::TotalsMismatch
+                } else {
+                    SettlementFailureReason::Rejected
+                };
+                let error = format!("{} {}", response.response_code, response.response_message);
+                self.settlement_repo
+                    .mark_failed(
+                        &batch.id,
+                        Some(processor_name),
+                        reason,
+                        &error,
+                        Some(&response.response_code),
+                        request.is_upload(),
+                    )
+                    .await?;
+                (BatchStatus::Failed, format!("{}: {}", reason.as_str(), error))
+            },
+            Err(
+                e @ (AppError::ProcessorTimeout(_)
+                | AppError::ProcessorOutcomeUnknown(_)
+                | AppError::External(_)),
+            ) => {
+                tracing::warn!(
+                    "Settlement batch {} submission to {} failed (attempt {}): {}",
+                    batch.id,
+                    processor_name,
+                    batch.submit_attempts,
+                    e
+                );
+                if batch.submit_attempts >= self.max_attempts {
+                    self.settlement_repo
+                        .mark_failed(
+                            &batch.id,
+                            Some(processor_name),
+                            SettlementFailureReason::SubmissionFailed,
+                            &e.to_string(),
+                            None,
+                            false,
+                        )
+                        .await?;
+                    (BatchStatus::Failed, format!("SUBMISSION_FAILED: {}", e))
+                } else {
+                    self.settlement_repo
+                        .record_submission_error(
+                            &batch.id,
+                            Some(processor_name),
+                            &e.to_string(),
+                            &self.next_attempt_at(batch.submit_attempts),
+                        )
+                        .await?;
+                    (BatchStatus::Closed, format!("retry scheduled: {}", e))
+                }
+            },
+            Err(e) => return Err(e),
+        };
+
+        let result = if status == BatchStatus::Settled {
+            OperationResult::Success
+        } else {
+            OperationResult::Failure
+        };
+        self.audit(
+            "SETTLEMENT_BATCH_SUBMIT",
+            SYSTEM_ACTOR,
+            result,
+            &batch.device_id,
+            format!(
+                "Settlement batch {} #{} submitted to {} (attempt {}): {}",
+                batch.id, batch.batch_number, processor_name, batch.submit_attempts, details
+            ),
+        )
+        .await?;
+
+        Ok(status)
+    }
+
+    /// 上送批次的明细：计入合计的交易（不含被撤销和已冲正的交易）
+    async fn upload_items(&self, batch_id: &str) -> Result<Vec<SettlementItem>, AppError> {
+        Ok(self
+            .transaction_repo
+            .list_by_batch(batch_id)
+            .await?
+            .into_iter()
+            .filter(|t| {
+                !matches!(t.status, TransactionStatus::Voided | TransactionStatus::Reversed)
+            })
+            .map(|t| SettlementItem {
+                amount: t.approved_amount.unwrap_or(t.amount),
+                transaction_id: t.id,
+                transaction_type: t.transaction_type,
+                currency: t.currency,
+                processor_reference: t.processor_reference,
+                authorization_code: t.authorization_code,
+                card_number_masked: t.card_number_masked,
+            })
+            .collect())
+    }
+
+    /// 按指数退避计算下次提交时间
+    fn next_attempt_at(&self, attempts: i64) -> String {
+        let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+        let delay = self
+            .retry_base
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(MAX_SUBMISSION_RETRY_DELAY);
+        (Utc::now() + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero()))
+            .to_rfc3339()
+    }
+
+    async fn audit(
+        &self,
+        operation: &str,
+        operator: &str,
+        result: OperationResult,
+        device_id: &str,
+        details: String,
+    ) -> Result<(), AppError> {
+        let log = AuditLog::new(operation.to_string(), operator.to_string(), result)
+            .with_device_id(device_id.to_string())
+            .with_details(details);
+
+        self.audit_repo.create(&log).await
+    }
+}
+
+/// 最近一次日切时间：当天已过日切时间时为当天，否则为前一天
+fn last_cutoff(now: DateTime<Utc>, cutoff: NaiveTime) -> DateTime<Utc> {
+    let today = now.date_naive().and_time(cutoff).and_utc();
+    if now >= today {
+        today
+    } else {
+        today - chrono::Duration::days(1)
+    }
+}
+
+fn lease_until(now: DateTime<Utc>) -> String {
+    (now + chrono::Duration::from_std(SUBMISSION_LEASE).unwrap_or(chrono::Duration::zero()))
+        .to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_last_cutoff() {
+        let cutoff = NaiveTime::from_hms_opt(23, 0, 0).unwrap();
+
+        let before = Utc.with_ymd_and_hms(2024, 12, 25, 22, 59, 0).unwrap();
+        assert_eq!(
+            last_cutoff(before, cutoff),
+            Utc.with_ymd_and_hms(2024, 12, 24, 23, 0, 0).unwrap()
+        );
+
+        let after = Utc.with_ymd_and_hms(2024, 12, 25, 23, 0, 0).unwrap();
+        assert_eq!(last_cutoff(after, cutoff), after);
+    }
+}
@@ -0,0 +1,318 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::RwLock;
+
+use crate::{
+    dto::{
+        ApiKeyResponse, CreateApiKeyRequest, CreateApiKeyResponse, CreateTenantRequest,
+        TenantListResponse, TenantResponse, UpdateTenantRequest,
+    },
+    models::{ApiKey, AuditLog, OperationResult, Tenant, TenantBranding, TenantStatus},
+    repositories::{ApiKeyRepository, AuditLogRepository, TenantRepository},
+    security::{crypto, KeyEncryptionKey},
+    utils::error::AppError,
+};
+
+/// API密钥前缀
+const API_KEY_PREFIX: &str = "sk_";
+
+/// 租户服务
+///
+/// 管理租户配置与API密钥；租户配置在每个请求上都会读取（限流、BDK），因此做进程内缓存
+#[derive(Clone)]
+pub struct TenantService {
+    tenant_repo: TenantRepository,
+    api_key_repo: ApiKeyRepository,
+    audit_repo: AuditLogRepository,
+    kek: Option<KeyEncryptionKey>,
+    cache: Arc<RwLock<HashMap<String, Tenant>>>,
+}
+
+impl TenantService {
+    /// 创建新的租户服务
+    pub fn new(
+        tenant_repo: TenantRepository,
+        api_key_repo: ApiKeyRepository,
+        audit_repo: AuditLogRepository,
+    ) -> Self {
+        Self {
+            tenant_repo,
+            api_key_repo,
+            audit_repo,
+            kek: None,
+            cache: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 启用租户专属BDK的包装存储
+    pub fn with_key_encryption_key(mut self, kek: KeyEncryptionKey) -> Self {
+        self.kek = Some(kek);
+        self
+    }
+
+    /// 包装升级前以明文保存的租户BDK，返回处理的租户数量
+    ///
+    /// 存在明文BDK但未配置密钥加密密钥时返回错误，避免继续使用明文密钥
+    pub async fn wrap_plaintext_bdks(&self) -> Result<usize, AppError> {
+        let plaintext = self.tenant_repo.list_plaintext_bdks().await?;
+        if !plaintext.is_empty() && self.kek.is_none() {
+            return Err(AppError::InternalWithMessage(format!(
+                "{} tenants have plaintext BDKs but no key encryption key is configured",
+                plaintext.len()
+            )));
+        }
+
+        for (tenant_id, bdk) in &plaintext {
+            let wrapped = self.wrap_bdk(tenant_id, bdk)?;
+            self.tenant_repo.replace_plaintext_bdk(tenant_id, &wrapped).await?;
+            self.cache.write().await.remove(tenant_id);
+            tracing::info!("Wrapped plaintext BDK of tenant {}", tenant_id);
+        }
+
+        Ok(plaintext.len())
+    }
+
+    /// 创建租户
+    pub async fn create_tenant(
+        &self,
+        request: CreateTenantRequest,
+        operator: &str,
+    ) -> Result<TenantResponse, AppError> {
+        tracing::info!("Creating tenant: {}", request.id);
+
+        request.validate()?;
+
+        if self.tenant_repo.find_by_id(&request.id).await?.is_some() {
+            return Err(AppError::BadRequest(format!("Tenant {} already exists", request.id)));
+        }
+
+        let mut tenant = Tenant::new(request.id, request.name);
+        if let Some(bdk) = request.bdk {
+            tenant.bdk_wrapped = Some(self.wrap_bdk(&tenant.id, &bdk)?);
+        }
+        tenant.bdk_key_ref = request.bdk_key_ref;
+        tenant.rate_limit_rps = request.rate_limit_rps;
+        tenant.rate_limit_burst = request.rate_limit_burst;
+        tenant.branding = request.branding.map(|b| encode_branding(&b)).transpose()?;
+
+        self.tenant_repo.create(&tenant).await?;
+
+        let audit_log = AuditLog::new(
+            "TENANT_CREATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_tenant_id(tenant.id.clone())
+        .with_details(format!("Tenant created: {}", tenant.name));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        tracing::info!("Tenant created successfully: {}", tenant.id);
+
+        Ok(TenantResponse::from(tenant))
+    }
+
+    /// 获取租户详情
+    pub async fn get_tenant(&self, tenant_id: &str) -> Result<TenantResponse, AppError> {
+        let tenant = self.find_tenant(tenant_id).await?;
+        Ok(TenantResponse::from(tenant))
+    }
+
+    /// 列出所有租户
+    pub async fn list_tenants(&self) -> Result<TenantListResponse, AppError> {
+        let tenants = self.tenant_repo.list().await?;
+        let total = tenants.len() as i64;
+
+        Ok(TenantListResponse {
+            tenants: tenants.into_iter().map(TenantResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 更新租户
+    pub async fn update_tenant(
+        &self,
+        tenant_id: &str,
+        request: UpdateTenantRequest,
+        operator: &str,
+    ) -> Result<TenantResponse, AppError> {
+        request.validate()?;
+
+        let mut tenant = self.find_tenant(tenant_id).await?;
+
+        if let Some(name) = request.name {
+            tenant.name = name;
+        }
+        if let Some(status) = request.status {
+            tenant.status = status.as_str().to_string();
+        }
+        // 租户专属BDK只保留一种来源
+        if let Some(bdk) = request.bdk {
+            tenant.bdk_wrapped = Some(self.wrap_bdk(&tenant.id, &bdk)?);
+            tenant.bdk_key_ref = None;
+        }
+        if let Some(key_ref) = request.bdk_key_ref {
+            tenant.bdk_key_ref = Some(key_ref);
+            tenant.bdk_wrapped = None;
+        }
+        if let Some(rps) = request.rate_limit_rps {
+            tenant.rate_limit_rps = Some(rps);
+        }
+        if let Some(burst) = request.rate_limit_burst {
+            tenant.rate_limit_burst = Some(burst);
+        }
+        if let Some(branding) = request.branding {
+            tenant.branding = Some(encode_branding(&branding)?);
+        }
+
+        self.tenant_repo.update(&tenant).await?;
+        self.cache.write().await.remove(tenant_id);
+
+        let audit_log = AuditLog::new(
+            "TENANT_UPDATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_tenant_id(tenant.id.clone())
+        .with_details(format!("Tenant updated: {}", tenant.id));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        let tenant = self.find_tenant(tenant_id).await?;
+        Ok(TenantResponse::from(tenant))
+    }
+
+    /// 获取租户品牌配置
+    pub async fn get_branding(&self, tenant_id: &str) -> Result<TenantBranding, AppError> {
+        Ok(self.find_tenant(tenant_id).await?.branding())
+    }
+
+    /// 获取租户配置（带缓存），租户不存在时返回None
+    pub async fn get_cached(&self, tenant_id: &str) -> Result<Option<Tenant>, AppError> {
+        if let Some(tenant) = self.cache.read().await.get(tenant_id) {
+            return Ok(Some(tenant.clone()));
+        }
+
+        let tenant = self.tenant_repo.find_by_id(tenant_id).await?;
+        if let Some(ref t) = tenant {
+            self.cache.write().await.insert(tenant_id.to_string(), t.clone());
+        }
+
+        Ok(tenant)
+    }
+
+    /// 创建API密钥，明文密钥仅在此处返回一次
+    pub async fn create_api_key(
+        &self,
+        tenant_id: &str,
+        request: CreateApiKeyRequest,
+        operator: &str,
+    ) -> Result<CreateApiKeyResponse, AppError> {
+        request.validate()?;
+
+        let tenant = self.find_tenant(tenant_id).await?;
+
+        let secret = format!("{}{}", API_KEY_PREFIX, crypto::generate_random_hex(24));
+        let key = ApiKey::new(
+            tenant.id.clone(),
+            request.name,
+            secret[..API_KEY_PREFIX.len() + 8].to_string(),
+            crypto::sha256_hash_hex(secret.as_bytes()),
+            request.role,
+            operator.to_string(),
+        );
+
+        self.api_key_repo.create(&key).await?;
+
+        let audit_log = AuditLog::new(
+            "API_KEY_CREATE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_tenant_id(tenant.id)
+        .with_details(format!("API key created: {} ({})", key.name, key.key_prefix));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        Ok(CreateApiKeyResponse { key: ApiKeyResponse::from(key), api_key: secret })
+    }
+
+    /// 列出租户的API密钥
+    pub async fn list_api_keys(&self, tenant_id: &str) -> Result<Vec<ApiKeyResponse>, AppError> {
+        self.find_tenant(tenant_id).await?;
+
+        let keys = self.api_key_repo.list_by_tenant(tenant_id).await?;
+        Ok(keys.into_iter().map(ApiKeyResponse::from).collect())
+    }
+
+    /// 吊销API密钥
+    pub async fn revoke_api_key(
+        &self,
+        tenant_id: &str,
+        key_id: &str,
+        operator: &str,
+    ) -> Result<(), AppError> {
+        if !self.api_key_repo.revoke(tenant_id, key_id).await? {
+            return Err(AppError::NotFound("API key not found".to_string()));
+        }
+
+        let audit_log = AuditLog::new(
+            "API_KEY_REVOKE".to_string(),
+            operator.to_string(),
+            OperationResult::Success,
+        )
+        .with_tenant_id(tenant_id.to_string())
+        .with_details(format!("API key revoked: {}", key_id));
+
+        self.audit_repo.create(&audit_log).await?;
+
+        Ok(())
+    }
+
+    /// 解析API密钥
+    ///
+    /// 密钥无效、已吊销或所属租户已停用时返回None
+    pub async fn resolve_api_key(&self, secret: &str) -> Result<Option<ApiKey>, AppError> {
+        if !secret.starts_with(API_KEY_PREFIX) {
+            return Ok(None);
+        }
+
+        let hash = crypto::sha256_hash_hex(secret.as_bytes());
+        let Some(key) = self.api_key_repo.find_active_by_hash(&hash).await? else {
+            return Ok(None);
+        };
+
+        match self.get_cached(&key.tenant_id).await? {
+            Some(tenant) if tenant.status == TenantStatus::Active.as_str() => {},
+            _ => return Ok(None),
+        }
+
+        self.api_key_repo.touch(&key.id).await?;
+
+        Ok(Some(key))
+    }
+
+    /// 以密钥加密密钥包装租户BDK，绑定租户ID
+    fn wrap_bdk(&self, tenant_id: &str, bdk: &str) -> Result<String, AppError> {
+        let kek = self.kek.as_ref().ok_or_else(|| {
+            AppError::BadRequest(
+                "Tenant BDK requires a configured key encryption key; use an HSM key reference"
+                    .to_string(),
+            )
+        })?;
+
+        kek.wrap(bdk.as_bytes(), tenant_id)
+    }
+
+    async fn find_tenant(&self, tenant_id: &str) -> Result<Tenant, AppError> {
+        self.tenant_repo
+            .find_by_id(tenant_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Tenant not found".to_string()))
+    }
+}
+
+fn encode_branding(branding: &TenantBranding) -> Result<String, AppError> {
+    serde_json::to_string(branding)
+        .map_err(|e| AppError::InternalWithMessage(format!("Failed to encode branding: {}", e)))
+}
@@ -1,10 +1,12 @@
 use crate::{
     dto::{ThreatListResponse, ThreatResponse, ThreatStatisticsResponse},
     models::{
-        AuditLog, DeviceStatus, OperationResult, ThreatEvent, ThreatSeverity, ThreatStatus,
-        ThreatType,
+        AuditLog, DeviceStatus, OperationResult, TenantContext, ThreatEvent, ThreatSeverity,
+        ThreatStatus, ThreatType,
+    },
+    repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, ThreatRepository, UnitOfWork,
     },
-    repositories::{AuditLogRepository, DeviceRepository, HealthCheckRepository, ThreatRepository},
     utils::error::AppError,
 };
 
@@ -28,6 +30,26 @@ impl ThreatDetectionService {
         Self { threat_repo, device_repo, health_check_repo, audit_repo }
     }
 
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            threat_repo: self.threat_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            health_check_repo: self.health_check_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+        }
+    }
+
+    /// 返回绑定到工作单元的服务，威胁记录、设备状态变更和审计日志在同一事务中写入
+    fn in_unit_of_work(&self, uow: &UnitOfWork) -> Self {
+        Self {
+            threat_repo: self.threat_repo.in_unit_of_work(uow),
+            device_repo: self.device_repo.in_unit_of_work(uow),
+            health_check_repo: self.health_check_repo.clone(),
+            audit_repo: self.audit_repo.in_unit_of_work(uow),
+        }
+    }
+
     /// 处理威胁（已废弃，使用 handle_health_check_threats）
     #[allow(dead_code)]
     pub async fn handle_threats(
@@ -37,25 +59,44 @@ impl ThreatDetectionService {
     ) -> Result<(), AppError> {
         tracing::info!("Handling {} threats for device: {}", threats.len(), device_id);
 
+        let uow = self.threat_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
         for threat in threats {
             // 保存威胁事件
-            self.threat_repo.create(threat).await?;
+            service.threat_repo.create(threat).await?;
 
             // 评估威胁严重程度并采取行动
-            let action = self.assess_threat_severity(threat).await?;
+            let action = service.assess_threat_severity(threat).await?;
 
-            self.execute_threat_action(device_id, threat, action).await?;
+            service.execute_threat_action(device_id, threat, action).await?;
         }
 
-        Ok(())
+        uow.commit().await
     }
 
     /// 处理健康检查结果并创建威胁（新方法）
+    ///
+    /// 所有威胁记录及其响应动作在同一事务中执行，任一步失败全部回滚
     pub async fn handle_health_check_threats(
         &self,
         device_id: &str,
         security_score: i32,
         detected_threats: Vec<ThreatType>,
+    ) -> Result<(), AppError> {
+        let uow = self.threat_repo.begin().await?;
+        self.in_unit_of_work(&uow)
+            .record_health_check_threats(device_id, security_score, detected_threats)
+            .await?;
+        uow.commit().await
+    }
+
+    /// 记录健康检查发现的威胁并执行响应动作
+    async fn record_health_check_threats(
+        &self,
+        device_id: &str,
+        security_score: i32,
+        detected_threats: Vec<ThreatType>,
     ) -> Result<(), AppError> {
         tracing::info!(
             "Handling health check threats for device {} with score {}",
@@ -115,9 +156,8 @@ impl ThreatDetectionService {
     ) -> Result<(), AppError> {
         match action {
             ThreatAction::Revoke => {
-                self.device_repo
-                    .update_status(device_id, DeviceStatus::Revoked, Some("system"))
-                    .await?;
+                let reason = format!("Threat detected: {:?}", threat.threat_type);
+                self.apply_system_status(device_id, DeviceStatus::Revoked, &reason).await?;
 
                 tracing::error!(
                     "Device {} revoked due to threat: {:?}",
@@ -140,9 +180,8 @@ impl ThreatDetectionService {
                 self.audit_repo.create(&audit_log).await?;
             },
             ThreatAction::Suspend => {
-                self.device_repo
-                    .update_status(device_id, DeviceStatus::Suspended, Some("system"))
-                    .await?;
+                let reason = format!("Threat detected: {:?}", threat.threat_type);
+                self.apply_system_status(device_id, DeviceStatus::Suspended, &reason).await?;
 
                 tracing::warn!(
                     "Device {} suspended due to threat: {:?}",
@@ -368,8 +407,11 @@ impl ThreatDetectionService {
             return Err(AppError::BadRequest("Threat already resolved".to_string()));
         }
 
+        let uow = self.threat_repo.begin().await?;
+
         // 更新威胁状态
         self.threat_repo
+            .in_unit_of_work(&uow)
             .update_status(threat_id, ThreatStatus::Resolved, Some(operator))
             .await?;
 
@@ -388,7 +430,8 @@ impl ThreatDetectionService {
         .with_device_id(threat.device_id)
         .with_details(details);
 
-        self.audit_repo.create(&audit_log).await?;
+        self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Threat resolved: {}", threat_id);
 
@@ -438,31 +481,29 @@ impl ThreatDetectionService {
         tracing::info!("Device {} reporting threat: {:?}", device_id, threat_type);
 
         // 验证设备是否存在
-        let _device = self
-            .device_repo
-            .find_by_id(device_id)
-            .await?
-            .ok_or_else(|| AppError::DeviceNotFound)?;
+        self.device_repo.find_by_id(device_id).await?.ok_or(AppError::DeviceNotFound)?;
 
         // 创建威胁事件
         let threat = ThreatEvent::new(device_id.to_string(), threat_type, severity, description);
 
+        // 威胁事件、设备状态变更和审计日志在同一事务中写入
+        let uow = self.threat_repo.begin().await?;
+        let service = self.in_unit_of_work(&uow);
+
         // 保存威胁事件
-        self.threat_repo.create(&threat).await?;
+        service.threat_repo.create(&threat).await?;
 
         // 评估威胁并采取行动
-        let action = self.assess_threat_severity(&threat).await?;
+        let action = service.assess_threat_severity(&threat).await?;
 
         match action {
             ThreatAction::Suspend => {
-                self.device_repo
-                    .update_status(device_id, DeviceStatus::Suspended, Some("system"))
+                service.apply_system_status(device_id, DeviceStatus::Suspended, "Threat reported by device")
                     .await?;
                 tracing::warn!("Device {} suspended due to reported threat", device_id);
             },
             ThreatAction::Revoke => {
-                self.device_repo
-                    .update_status(device_id, DeviceStatus::Revoked, Some("system"))
+                service.apply_system_status(device_id, DeviceStatus::Revoked, "Threat reported by device")
                     .await?;
                 tracing::error!("Device {} revoked due to reported threat", device_id);
             },
@@ -481,7 +522,8 @@ impl ThreatDetectionService {
             threat_type, severity
         ));
 
-        self.audit_repo.create(&audit_log).await?;
+        service.audit_repo.create(&audit_log).await?;
+        uow.commit().await?;
 
         Ok(ThreatResponse::from(threat))
     }
@@ -528,7 +570,7 @@ impl ThreatDetectionService {
             .ok_or_else(|| AppError::DeviceNotFound)?;
 
         // 只有暂停状态的设备才考虑自动恢复
-        if device.status != "Suspended" {
+        if device.status != DeviceStatus::Suspended.as_str() {
             tracing::debug!("Device {} is not suspended, skipping auto-recovery", device_id);
             return Ok(false);
         }
@@ -554,10 +596,20 @@ impl ThreatDetectionService {
                 current_score
             );
 
+            let uow = self.device_repo.begin().await?;
+
             self.device_repo
-                .update_status(device_id, DeviceStatus::Active, Some("system"))
+                .in_unit_of_work(&uow)
+                .update_status(
+                    device_id,
+                    DeviceStatus::Suspended,
+                    DeviceStatus::Active,
+                    "system",
+                    Some("Security score recovered"),
+                )
                 .await?;
 
+
             // 记录审计日志
             let audit_log = AuditLog::new(
                 "DEVICE_AUTO_RECOVERED".to_string(),
@@ -567,7 +619,8 @@ impl ThreatDetectionService {
             .with_device_id(device_id.to_string())
             .with_details(format!("Device auto-recovered: score improved to {}", current_score));
 
-            self.audit_repo.create(&audit_log).await?;
+            self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+            uow.commit().await?;
 
             return Ok(true);
         }
@@ -580,6 +633,31 @@ impl ThreatDetectionService {
 
         Ok(false)
     }
+
+    /// 系统自动迁移设备状态，当前状态不允许该迁移时跳过
+    async fn apply_system_status(
+        &self,
+        device_id: &str,
+        to: DeviceStatus,
+        reason: &str,
+    ) -> Result<bool, AppError> {
+        let device = self
+            .device_repo
+            .find_by_id(device_id)
+            .await?
+            .ok_or(AppError::DeviceNotFound)?;
+        let from = DeviceStatus::from_str(&device.status).ok_or(AppError::Internal)?;
+
+        if !from.can_transition_to(to) {
+            tracing::debug!("Device {} is {}, skipping transition to {}", device_id, from, to);
+            return Ok(false);
+        }
+
+        self.device_repo.update_status(device_id, from, to, "system", Some(reason)).await?;
+
+
+        Ok(true)
+    }
 }
 
 /// 威胁处理动作
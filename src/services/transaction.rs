@@ -2,29 +2,97 @@ use crate::{
     dto::{
         AttestPinpadRequest, AttestPinpadResponse, AttestTransactionRequest,
         AttestTransactionResponse, ProcessTransactionRequest, ProcessTransactionResponse,
-        TransactionListResponse, TransactionResponse,
+        ReverseTransactionRequest, ReverseTransactionResponse, TransactionListResponse,
+        TransactionResponse,
+    },
+    infrastructure::{
+        payment::{
+            tcp::currency_numeric, MessageIdentifiers, PaymentOperation, PaymentProcessor,
+            PaymentRequest, PaymentResponse,
+        },
+        HsmClient, PaymentRouter,
     },
-    infrastructure::HsmClient,
     models::{
-        AuditLog, DeviceMode, DeviceStatus, OperationResult, Transaction, TransactionStatus,
-        TransactionType,
+        AuditLog, BatchStatus, CryptogramType, DeviceMode, DeviceStatus, EmvData, OperationResult,
+        ReversalReason, ReversalStatus, TenantContext, Transaction, TransactionEvent, TransactionEventType, TransactionReversal,
+        TransactionStatus, TransactionTokenClaims, TransactionType, DEFAULT_REVERSAL_MAX_ATTEMPTS,
+    },
+    repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, MerchantRepository,
+        SettlementRepository, TransactionRepository, UnitOfWork,
     },
-    repositories::{AuditLogRepository, DeviceRepository, TransactionRepository},
     security::{crypto, DukptKeyDerivation},
-    services::TransactionTokenService,
+    services::{NotificationServiceWrapper, TransactionTokenService},
     utils::error::AppError,
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+
+/// 每轮处理的最大冲正数
+const REVERSAL_BATCH_SIZE: i64 = 50;
+
+/// 领取冲正后的租约时长，超时未完成的冲正可被其他节点重新领取
+const REVERSAL_LEASE: Duration = Duration::from_secs(120);
+
+/// 两次冲正重试之间的最长间隔
+const MAX_REVERSAL_RETRY_DELAY: Duration = Duration::from_secs(600);
+
+/// 处理器找不到原交易的应答码，视为无需冲正
+const ORIGINAL_NOT_FOUND_CODE: &str = "25";
+
+/// 预登记冲正的生效延迟，须长于处理器超时；处理器给出结果后冲正即被撤销
+const PROVISIONAL_REVERSAL_DELAY: Duration = Duration::from_secs(300);
+
+/// 请求未能提交处理器时的应答码
+const SYSTEM_ERROR_CODE: &str = "96";
+
+/// 系统操作者
+const SYSTEM_ACTOR: &str = "system";
 
 /// 交易服务
+///
+/// 处理器超时的交易记为UNKNOWN并加入冲正队列，由后台任务按指数退避重试冲正，
+/// 超过最大次数后标记为FAILED等待人工处理；交易的每次状态变化都写入状态历史
 #[derive(Clone)]
 pub struct TransactionService {
     transaction_repo: TransactionRepository,
     device_repo: DeviceRepository,
     audit_repo: AuditLogRepository,
+    health_check_repo: HealthCheckRepository,
     dukpt: DukptKeyDerivation,
     hsm_client: Option<HsmClient>,
     transaction_token_service: Arc<TransactionTokenService>,
+    payment_router: Arc<PaymentRouter>,
+    merchant_repo: Option<MerchantRepository>,
+    settlement_repo: Option<SettlementRepository>,
+    preauth_validity: chrono::Duration,
+    reversal_max_attempts: i64,
+    reversal_retry_base: Duration,
+    notifier: Option<NotificationServiceWrapper>,
+}
+
+/// 后续交易引用的原交易
+struct LinkedOriginal {
+    original: Transaction,
+    /// 原交易已批准的退款合计（不含本笔）
+    refunded: i64,
+    /// 撤销请款或退款后需要恢复状态的上一级交易
+    restore: Option<(String, TransactionStatus)>,
+}
+
+/// 已通过全部校验、尚未产生任何副作用的交易请求
+pub struct PreparedTransaction {
+    transaction: Transaction,
+    token_claims: TransactionTokenClaims,
+    linked: Option<LinkedOriginal>,
+    acquirer_mid: Option<String>,
+    processor: Arc<dyn PaymentProcessor>,
+}
+
+impl PreparedTransaction {
+    /// 执行后将创建的交易ID
+    pub fn transaction_id(&self) -> &str {
+        &self.transaction.id
+    }
 }
 
 impl TransactionService {
@@ -33,6 +101,7 @@ impl TransactionService {
         transaction_repo: TransactionRepository,
         device_repo: DeviceRepository,
         audit_repo: AuditLogRepository,
+        health_check_repo: HealthCheckRepository,
         dukpt: DukptKeyDerivation,
         hsm_client: Option<HsmClient>,
         transaction_token_service: Arc<TransactionTokenService>,
@@ -41,9 +110,74 @@ impl TransactionService {
             transaction_repo,
             device_repo,
             audit_repo,
+            health_check_repo,
             dukpt,
             hsm_client,
             transaction_token_service,
+            payment_router: Arc::new(PaymentRouter::default()),
+            merchant_repo: None,
+            settlement_repo: None,
+            preauth_validity: chrono::Duration::days(7),
+            reversal_max_attempts: DEFAULT_REVERSAL_MAX_ATTEMPTS,
+            reversal_retry_base: Duration::from_secs(30),
+            notifier: None,
+        }
+    }
+
+    /// 设置支付处理器路由，未设置时使用全部批准的内置模拟器
+    pub fn with_payment_router(mut self, payment_router: Arc<PaymentRouter>) -> Self {
+        self.payment_router = payment_router;
+        self
+    }
+
+    /// 设置商户Repository，用于按收单机构商户号路由
+    pub fn with_merchant_repo(mut self, merchant_repo: MerchantRepository) -> Self {
+        self.merchant_repo = Some(merchant_repo);
+        self
+    }
+
+    /// 设置结算批次Repository，设置后已批准的交易自动加入所属设备和商户的开放批次
+    pub fn with_settlement_repo(mut self, settlement_repo: SettlementRepository) -> Self {
+        self.settlement_repo = Some(settlement_repo);
+        self
+    }
+
+    /// 设置预授权有效期，默认7天
+    pub fn with_preauth_validity(mut self, preauth_validity: chrono::Duration) -> Self {
+        self.preauth_validity = preauth_validity;
+        self
+    }
+
+    /// 设置冲正重试策略
+    pub fn with_reversal_policy(mut self, max_attempts: i64, retry_base: Duration) -> Self {
+        self.reversal_max_attempts = max_attempts;
+        self.reversal_retry_base = retry_base;
+        self
+    }
+
+    /// 设置通知服务，冲正需要人工处理时告警
+    pub fn with_notifier(mut self, notifier: NotificationServiceWrapper) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            transaction_repo: self.transaction_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            health_check_repo: self.health_check_repo.for_tenant(tenant),
+            dukpt: self.dukpt.clone(),
+            hsm_client: self.hsm_client.clone(),
+            transaction_token_service: self.transaction_token_service.clone(),
+            payment_router: self.payment_router.clone(),
+            merchant_repo: self.merchant_repo.as_ref().map(|repo| repo.for_tenant(tenant)),
+            settlement_repo: self.settlement_repo.as_ref().map(|repo| repo.for_tenant(tenant)),
+            preauth_validity: self.preauth_validity,
+            reversal_max_attempts: self.reversal_max_attempts,
+            reversal_retry_base: self.reversal_retry_base,
+            notifier: self.notifier.clone(),
         }
     }
 
@@ -82,25 +216,22 @@ impl TransactionService {
             ));
         }
 
-        // 创建模拟的健康检查对象（简化版本，实际应该从数据库获取最新的健康检查）
-        let mock_health_check = crate::models::HealthCheck {
-            id: uuid::Uuid::new_v4().to_string(),
-            device_id: request.device_id.clone(),
-            security_score: 85, // 默认安全分数
-            root_status: false,
-            bootloader_status: false,
-            system_integrity: true,
-            app_integrity: true,
-            tee_status: true,
-            recommended_action: crate::models::RecommendedAction::None,
-            details: None,
-            created_at: chrono::Utc::now().to_rfc3339(),
-        };
+        // 依据设备最新的健康检查签发令牌，过期或未通过时由令牌服务要求重新检查
+        let health_check = self
+            .health_check_repo
+            .get_latest_by_device(&request.device_id)
+            .await?
+            .ok_or(AppError::HealthCheckRequired("NO_HEALTH_CHECK"))?;
 
-        // 使用交易令牌服务生成JWT令牌
+        // 使用交易令牌服务生成绑定金额和币种的JWT令牌
         let token_result = self
             .transaction_token_service
-            .generate_token(&request.device_id, &mock_health_check)
+            .generate_bound_token(
+                &request.device_id,
+                &health_check,
+                request.amount,
+                &request.currency,
+            )
             .await?;
 
         let transaction_token = token_result.token;
@@ -116,8 +247,8 @@ impl TransactionService {
         )
         .with_device_id(request.device_id.clone())
         .with_details(format!(
-            "Transaction attested: amount={}, currency={}",
-            request.amount, request.currency
+            "Transaction attested: amount={}, currency={}, health_check={}, score={}",
+            request.amount, request.currency, health_check.id, health_check.security_score
         ));
 
         self.audit_repo.create(&audit_log).await?;
@@ -138,6 +269,17 @@ impl TransactionService {
         request: ProcessTransactionRequest,
         operator: &str,
     ) -> Result<ProcessTransactionResponse, AppError> {
+        let prepared = self.prepare_transaction(request).await?;
+        self.execute_transaction(prepared, operator).await
+    }
+
+    /// 校验交易请求并选择支付连接器
+    ///
+    /// 只读取数据，不占用令牌也不写入交易，失败时请求可以原样重试
+    pub async fn prepare_transaction(
+        &self,
+        request: ProcessTransactionRequest,
+    ) -> Result<PreparedTransaction, AppError> {
         tracing::info!("Processing transaction for device: {}", request.device_id);
 
         // 验证请求
@@ -154,6 +296,23 @@ impl TransactionService {
             return Err(AppError::BadRequest("Device must be in active status".to_string()));
         }
 
+        // 校验交易鉴证签发的令牌：设备绑定、金额和币种绑定及最大金额
+        let token_claims = self
+            .transaction_token_service
+            .verify_for_transaction(
+                &request.transaction_token,
+                &request.device_id,
+                request.amount,
+                &request.currency,
+            )
+            .await?;
+
+        // 令牌校验通过后再解析并校验EMV数据，只有白名单标签随交易保存
+        let emv = request.emv_data.as_deref().map(EmvData::from_hex).transpose()?;
+        if let Some(emv) = &emv {
+            check_emv(&request, emv)?;
+        }
+
         // 验证KSN（暂时注释掉以保障流程顺利）
         // TODO: 在生产环境中应该启用 KSN 验证
         let device_ksn = &device.current_ksn;
@@ -168,6 +327,9 @@ impl TransactionService {
         //     return Err(AppError::BadRequest("Invalid KSN".to_string()));
         // }
 
+        // 退款、撤销、请款和追加授权须引用符合生命周期规则的原交易
+        let linked = self.check_lifecycle(&request, device.merchant_id.as_deref()).await?;
+
         // 创建交易记录
         let mut transaction = Transaction::new(
             request.device_id.clone(),
@@ -177,6 +339,9 @@ impl TransactionService {
             request.ksn.clone(),
         );
 
+        // 记录交易发生时设备所属的商户和终端号
+        transaction.merchant_id = device.merchant_id.clone();
+        transaction.terminal_id = device.terminal_id.clone();
         transaction.encrypted_pin_block = Some(request.encrypted_pin_block);
         transaction.card_number_masked = request.card_number_masked;
         transaction.client_ip = request.client_ip;
@@ -186,22 +351,221 @@ impl TransactionService {
         transaction.location_timestamp = request
             .location_timestamp
             .and_then(|ts| chrono::DateTime::parse_from_rfc3339(&ts).map(|dt| dt.naive_utc()).ok());
+        transaction.original_transaction_id = request.original_transaction_id.clone();
+        transaction.client_transaction_id = request.client_transaction_id.clone();
+        transaction.emv_data = emv.as_ref().map(EmvData::to_stored_hex);
+
+        // 选择支付连接器，后续交易交给原交易的连接器
+        let original = linked.as_ref().map(|l| &l.original);
+        let acquirer_mid = self.acquirer_mid(&transaction).await?;
+        let processor =
+            self.route_to_processor(&mut transaction, acquirer_mid.as_deref(), original)?;
+
+        Ok(PreparedTransaction {
+            transaction,
+            token_claims,
+            linked,
+            acquirer_mid,
+            processor,
+        })
+    }
 
-        // 模拟交易处理（在实际环境中，这里会调用支付网关）
-        let (status, auth_code, response_code, response_message) =
-            self.simulate_transaction_processing(&transaction).await?;
+    /// 执行已校验的交易：占用令牌、保存交易并提交处理器
+    ///
+    /// 占用令牌后交易已产生副作用，此后的失败不能通过重新提交同一请求恢复
+    pub async fn execute_transaction(
+        &self,
+        prepared: PreparedTransaction,
+        operator: &str,
+    ) -> Result<ProcessTransactionResponse, AppError> {
+        let PreparedTransaction {
+            mut transaction,
+            token_claims,
+            mut linked,
+            acquirer_mid,
+            processor,
+        } = prepared;
+        let original = linked.as_ref().map(|l| &l.original);
+
+        // 提交处理器前原子占用令牌，并发请求中只有一个能使用同一令牌
+        self.transaction_token_service.mark_token_used(&token_claims, &transaction.id).await?;
+
+        // 报文数据元随PENDING交易一起在发送前保存，结果未知时冲正据此引用原报文
+        let operation = PaymentOperation::for_transaction_type(&transaction.transaction_type);
+        let mut request = PaymentRequest::from_transaction(&transaction, acquirer_mid);
+        request.original_reference = original.and_then(|o| o.processor_reference.clone());
+        if let Some(identifiers) = processor.assign_identifiers(operation, &request) {
+            identifiers.apply_to(&mut transaction);
+            request.identifiers = Some(identifiers);
+        }
 
-        transaction.status = status.clone();
-        transaction.authorization_code = auth_code.clone();
-        transaction.response_code = response_code.clone();
-        transaction.response_message = response_message.clone();
+        // 提交处理器前先保存PENDING交易并预登记冲正：处理器批准后进程崩溃或结果写入失败时，
+        // 预登记的冲正到期后由后台任务执行，不会留下没有本地记录的扣款
+        let mut provisional = TransactionReversal::new(
+            transaction.id.clone(),
+            ReversalReason::ProcessorTimeout,
+            SYSTEM_ACTOR.to_string(),
+        );
+        provisional.next_attempt_at = (chrono::Utc::now()
+            + chrono::Duration::from_std(PROVISIONAL_REVERSAL_DELAY)
+                .unwrap_or(chrono::Duration::zero()))
+        .to_rfc3339();
+
+        let uow = self.transaction_repo.begin().await?;
+        self.transaction_repo.in_unit_of_work(&uow).create(&transaction).await?;
+        self.transaction_repo.in_unit_of_work(&uow).enqueue_reversal(&provisional).await?;
+
+        // 原子占用原交易，并发的退款累计不会超过批准金额，同一原交易不会同时请款或撤销
+        if let Some(linked) = &linked {
+            if !self
+                .transaction_repo
+                .in_unit_of_work(&uow)
+                .reserve_follow_up(&linked.original.id, &transaction)
+                .await?
+            {
+                uow.rollback().await?;
+                return Err(AppError::BadRequest(
+                    if transaction.transaction_type == TransactionType::Refund {
+                        "Refund amount exceeds refundable amount"
+                    } else {
+                        "Original transaction has another operation in progress"
+                    }
+                    .to_string(),
+                ));
+            }
+        }
+        uow.commit().await?;
+
+        if let Err(e) = self
+            .submit_to_processor(&mut transaction, &processor, &request)
+            .await
+        {
+            // 请求未发出，交易记为FAILED并撤销预登记的冲正
+            transaction.status = TransactionStatus::Failed;
+            transaction.response_code = Some(SYSTEM_ERROR_CODE.to_string());
+            transaction.response_message = Some(e.to_string());
+
+            let uow = self.transaction_repo.begin().await?;
+            self.transaction_repo.in_unit_of_work(&uow).cancel_reversal(&transaction.id).await?;
+            self.transaction_repo.in_unit_of_work(&uow).record_outcome(&transaction).await?;
+            if let Some(linked) = &linked {
+                self.transaction_repo
+                    .in_unit_of_work(&uow)
+                    .release_follow_up(&linked.original.id, &transaction)
+                    .await?;
+            }
+            uow.commit().await?;
+            return Err(e);
+        }
 
-        // 保存交易记录
-        self.transaction_repo.create(&transaction).await?;
+        let expires_at = (chrono::Utc::now() + self.preauth_validity).to_rfc3339();
+        if transaction.status == TransactionStatus::Approved
+            && transaction.transaction_type == TransactionType::PreAuth
+            && linked.is_none()
+        {
+            transaction.auth_expires_at = Some(expires_at.clone());
+        }
+
+        // 处理结果、冲正队列、密钥计数和审计日志在同一事务中写入，任一步失败全部回滚，
+        // 交易保持PENDING，由预登记的冲正到期后冲正
+        let uow = self.transaction_repo.begin().await?;
+
+        // 处理器已给出明确结果时撤销预登记的冲正；冲正已开始执行时交易结果以冲正为准
+        if transaction.status != TransactionStatus::Unknown
+            && !self
+                .transaction_repo
+                .in_unit_of_work(&uow)
+                .cancel_reversal(&transaction.id)
+                .await?
+        {
+            transaction.status = TransactionStatus::Unknown;
+            transaction.approved_amount = None;
+            transaction.auth_expires_at = None;
+        }
+        let status = transaction.status.clone();
+
+        // 保存处理结果
+        if !self.transaction_repo.in_unit_of_work(&uow).record_outcome(&transaction).await? {
+            uow.rollback().await?;
+            return Err(AppError::InternalWithMessage(format!(
+                "Transaction {} was reversed before its outcome was recorded",
+                transaction.id
+            )));
+        }
+        self.transaction_repo
+            .in_unit_of_work(&uow)
+            .record_event(
+                &TransactionEvent::new(
+                    transaction.id.clone(),
+                    TransactionEventType::Processed,
+                    Some(TransactionStatus::Pending),
+                    status.clone(),
+                    operator.to_string(),
+                )
+                .with_response_code(transaction.response_code.clone())
+                .with_details(format!(
+                    "processor={}",
+                    transaction.processor.as_deref().unwrap_or_default()
+                )),
+            )
+            .await?;
+
+        // 结果未知，预登记的冲正立即生效，避免处理器已批准而商户不知情；
+        // 对原交易的占用保留到冲正完成
+        if status == TransactionStatus::Unknown {
+            self.schedule_provisional_reversal(&uow, &transaction).await?;
+        } else if let Some(linked) = &mut linked {
+            self.settle_follow_up(&uow, &transaction, linked).await?;
+        }
+
+        // 已批准的交易加入开放批次，预授权在请款时才入批
+        if let (TransactionStatus::Approved, Some(settlement_repo)) =
+            (&status, &self.settlement_repo)
+        {
+            if transaction.transaction_type != TransactionType::PreAuth {
+                let batch_id =
+                    settlement_repo.in_unit_of_work(&uow).assign_transaction(&transaction).await?;
+                transaction.batch_id = Some(batch_id);
+            }
+        }
+
+        // 批准后更新原交易的状态
+        if let (TransactionStatus::Approved, Some(linked)) = (&status, &linked) {
+            for (id, original_status, original_expires_at) in
+                lifecycle_updates(&transaction, linked, &expires_at)
+            {
+                self.transaction_repo
+                    .in_unit_of_work(&uow)
+                    .update_lifecycle(&id, original_status.clone(), original_expires_at.as_deref())
+                    .await?;
+
+                let from_status =
+                    (id == linked.original.id).then(|| linked.original.status.clone());
+                self.transaction_repo
+                    .in_unit_of_work(&uow)
+                    .record_event(
+                        &TransactionEvent::new(
+                            id,
+                            TransactionEventType::LifecycleChanged,
+                            from_status,
+                            original_status,
+                            operator.to_string(),
+                        )
+                        .with_details(format!(
+                            "{:?} {}",
+                            transaction.transaction_type, transaction.id
+                        )),
+                    )
+                    .await?;
+            }
+        }
 
         // 如果交易成功，递增密钥使用次数
         if status == TransactionStatus::Approved {
-            self.device_repo.decrement_key_count(&request.device_id).await?;
+            self.device_repo
+                .in_unit_of_work(&uow)
+                .decrement_key_count(&transaction.device_id)
+                .await?;
         }
 
         // 记录审计日志
@@ -211,23 +575,38 @@ impl TransactionService {
             OperationResult::Failure
         };
 
-        let audit_log =
-            AuditLog::new("TRANSACTION_PROCESSING".to_string(), operator.to_string(), audit_result)
-                .with_device_id(request.device_id.clone())
-                .with_details(format!(
-                    "Transaction processed: type={:?}, amount={}, status={:?}",
-                    request.transaction_type, request.amount, status
-                ));
+        let audit_log = AuditLog::new(
+            "TRANSACTION_PROCESSING".to_string(),
+            operator.to_string(),
+            audit_result,
+        )
+        .with_device_id(transaction.device_id.clone())
+        .with_details(format!(
+            "Transaction processed: type={:?}, amount={}, status={:?}, processor={}, original={}",
+            transaction.transaction_type,
+            transaction.amount,
+            status,
+            transaction.processor.as_deref().unwrap_or_default(),
+            transaction.original_transaction_id.as_deref().unwrap_or_default()
+        ));
 
-        self.audit_repo.create(&audit_log).await?;
+        self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+        uow.commit().await?;
 
         tracing::info!("Transaction processed: {} - {:?}", transaction.id, status);
 
+        let message = if status == TransactionStatus::Unknown {
+            "Transaction outcome unknown, reversal scheduled".to_string()
+        } else {
+            transaction.response_message.unwrap_or_else(|| "Transaction processed".to_string())
+        };
+
         Ok(ProcessTransactionResponse {
             transaction_id: transaction.id,
             status,
-            authorization_code: auth_code,
-            message: response_message.unwrap_or_else(|| "Transaction processed".to_string()),
+            authorization_code: transaction.authorization_code,
+            approved_amount: transaction.approved_amount,
+            message,
         })
     }
 
@@ -346,58 +725,988 @@ impl TransactionService {
         Ok(stats)
     }
 
-    /// 模拟交易处理
+    /// 把超过有效期仍未请款的预授权标记为EXPIRED，返回更新的数量
+    pub async fn expire_preauths(&self) -> Result<u64, AppError> {
+        let expired =
+            self.transaction_repo.expire_preauths(&chrono::Utc::now().to_rfc3339()).await?;
+        if expired > 0 {
+            tracing::info!("Expired {} pre-authorizations", expired);
+        }
+        Ok(expired)
+    }
+
+    /// 启动预授权过期检查
+    pub fn start_preauth_expiry_worker(
+        self: Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.expire_preauths().await {
+                    tracing::error!("Failed to expire pre-authorizations: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 设备申请冲正（设备未收到交易应答时使用）
+    ///
+    /// 按交易ID或客户端交易ID查找该设备的交易；找不到时说明交易未被处理
+    pub async fn request_device_reversal(
+        &self,
+        device_id: &str,
+        request: &ReverseTransactionRequest,
+    ) -> Result<ReverseTransactionResponse, AppError> {
+        let transaction = match (&request.transaction_id, &request.client_transaction_id) {
+            (Some(transaction_id), _) => self
+                .transaction_repo
+                .find_by_id(transaction_id)
+                .await?
+                .filter(|t| t.device_id == device_id),
+            (None, Some(client_transaction_id)) => {
+                self.transaction_repo
+                    .find_by_client_transaction_id(device_id, client_transaction_id)
+                    .await?
+            },
+            (None, None) => {
+                return Err(AppError::BadRequest(
+                    "transactionId or clientTransactionId is required".to_string(),
+                ));
+            },
+        }
+        .ok_or(AppError::TransactionNotFound)?;
+
+        self.request_reversal(transaction, ReversalReason::DeviceRequest, &format!("device:{}", device_id))
+            .await
+    }
+
+    /// 操作员申请冲正
+    pub async fn request_operator_reversal(
+        &self,
+        transaction_id: &str,
+        operator: &str,
+    ) -> Result<ReverseTransactionResponse, AppError> {
+        let transaction = self
+            .transaction_repo
+            .find_by_id(transaction_id)
+            .await?
+            .ok_or(AppError::TransactionNotFound)?;
+
+        self.request_reversal(transaction, ReversalReason::OperatorRequest, operator).await
+    }
+
+    /// 查询交易的状态历史
+    pub async fn get_transaction_events(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<TransactionEvent>, AppError> {
+        self.transaction_repo
+            .find_by_id(transaction_id)
+            .await?
+            .ok_or(AppError::TransactionNotFound)?;
+
+        self.transaction_repo.list_events(transaction_id).await
+    }
+
+    /// 执行已到期的冲正，返回冲正成功的数量
+    pub async fn process_reversals(&self) -> Result<usize, AppError> {
+        let now = chrono::Utc::now();
+        let now_str = now.to_rfc3339();
+        let lease_until = (now
+            + chrono::Duration::from_std(REVERSAL_LEASE).unwrap_or(chrono::Duration::zero()))
+        .to_rfc3339();
+
+        let due = self.transaction_repo.list_due_reversals(&now_str, REVERSAL_BATCH_SIZE).await?;
+        let mut reversed = 0;
+
+        for reversal in due {
+            // 其他节点已领取
+            if !self.transaction_repo.claim_reversal(&reversal.id, &now_str, &lease_until).await? {
+                continue;
+            }
+
+            match self.execute_reversal(&reversal, reversal.attempts + 1).await {
+                Ok(true) => reversed += 1,
+                Ok(false) => {},
+                // 记录结果失败时保持领取状态，租约到期后重试
+                Err(e) => tracing::error!(
+                    "Failed to record reversal result for transaction {}: {}",
+                    reversal.transaction_id,
+                    e
+                ),
+            }
+        }
+
+        Ok(reversed)
+    }
+
+    /// 启动后台冲正任务
+    pub fn start_reversal_worker(
+        self: Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.process_reversals().await {
+                    tracing::error!("Failed to process reversals: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 为交易申请冲正
+    ///
+    /// - 结果未知的交易，以及未结算、没有后续交易的已批准消费/预授权可以冲正
+    /// - 已冲正或正在冲正的交易直接返回当前状态；冲正失败的交易重新排队
+    /// - 被拒绝或失败的交易没有扣款，无需冲正
+    async fn request_reversal(
+        &self,
+        transaction: Transaction,
+        reason: ReversalReason,
+        actor: &str,
+    ) -> Result<ReverseTransactionResponse, AppError> {
+        let existing = self.transaction_repo.find_reversal(&transaction.id).await?;
+
+        match transaction.status {
+            TransactionStatus::Unknown | TransactionStatus::Reversed => {},
+            TransactionStatus::Approved => {
+                if !matches!(
+                    transaction.transaction_type,
+                    TransactionType::Payment | TransactionType::PreAuth
+                ) || transaction.original_transaction_id.is_some()
+                {
+                    return Err(AppError::BadRequest(
+                        "Only payments and pre-authorizations can be reversed; use void or refund"
+                            .to_string(),
+                    ));
+                }
+                if transaction.settled_at.is_some() {
+                    return Err(AppError::BadRequest(
+                        "Settled transactions cannot be reversed; use refund".to_string(),
+                    ));
+                }
+                if !self.in_open_batch(&transaction).await? {
+                    return Err(AppError::BadRequest(
+                        "Transactions in a closed batch cannot be reversed; use refund"
+                            .to_string(),
+                    ));
+                }
+                let has_follow_ups = self
+                    .transaction_repo
+                    .find_linked(&transaction.id)
+                    .await?
+                    .iter()
+                    .any(|t| t.status == TransactionStatus::Approved);
+                if has_follow_ups {
+                    return Err(AppError::BadRequest(
+                        "Transactions with follow-up transactions cannot be reversed".to_string(),
+                    ));
+                }
+            },
+            TransactionStatus::Declined | TransactionStatus::Failed => {
+                return Ok(ReverseTransactionResponse {
+                    transaction_id: transaction.id,
+                    status: transaction.status,
+                    reversal_status: None,
+                    message: "Transaction was not approved, no reversal needed".to_string(),
+                });
+            },
+            _ => {
+                return Err(AppError::BadRequest(format!(
+                    "Transaction in status {:?} cannot be reversed",
+                    transaction.status
+                )));
+            },
+        }
+
+        let reversal_status = match existing {
+            Some(reversal) if reversal.status == ReversalStatus::Failed.as_str() => {
+                let uow = self.transaction_repo.begin().await?;
+                self.transaction_repo
+                    .in_unit_of_work(&uow)
+                    .requeue_reversal(&reversal.id, actor)
+                    .await?;
+                self.transaction_repo
+                    .in_unit_of_work(&uow)
+                    .record_event(
+                        &TransactionEvent::new(
+                            transaction.id.clone(),
+                            TransactionEventType::ReversalQueued,
+                            Some(transaction.status.clone()),
+                            transaction.status.clone(),
+                            actor.to_string(),
+                        )
+                        .with_details(format!("requeued: {}", reason.as_str())),
+                    )
+                    .await?;
+                uow.commit().await?;
+                ReversalStatus::Pending
+            },
+            Some(reversal) => {
+                if reversal.status == ReversalStatus::Completed.as_str() {
+                    ReversalStatus::Completed
+                } else {
+                    ReversalStatus::Pending
+                }
+            },
+            None if transaction.status == TransactionStatus::Reversed => ReversalStatus::Completed,
+            None => {
+                let uow = self.transaction_repo.begin().await?;
+                self.queue_reversal(&uow, &transaction, reason, actor).await?;
+                uow.commit().await?;
+                ReversalStatus::Pending
+            },
+        };
+
+        let audit_log = AuditLog::new(
+            "TRANSACTION_REVERSAL_REQUEST".to_string(),
+            actor.to_string(),
+            OperationResult::Success,
+        )
+        .with_device_id(transaction.device_id.clone())
+        .with_details(format!(
+            "Reversal requested: transaction={}, reason={}, reversal_status={:?}",
+            transaction.id,
+            reason.as_str(),
+            reversal_status
+        ));
+        self.audit_repo.create(&audit_log).await?;
+
+        let message = match reversal_status {
+            ReversalStatus::Completed => "Transaction has been reversed",
+            _ => "Reversal scheduled",
+        };
+
+        Ok(ReverseTransactionResponse {
+            transaction_id: transaction.id,
+            status: transaction.status,
+            reversal_status: Some(reversal_status),
+            message: message.to_string(),
+        })
+    }
+
+    /// 在工作单元中加入冲正队列并记录状态历史
+    async fn queue_reversal(
+        &self,
+        uow: &UnitOfWork,
+        transaction: &Transaction,
+        reason: ReversalReason,
+        actor: &str,
+    ) -> Result<(), AppError> {
+        let reversal =
+            TransactionReversal::new(transaction.id.clone(), reason, actor.to_string());
+        if self.transaction_repo.in_unit_of_work(uow).enqueue_reversal(&reversal).await? {
+            self.transaction_repo
+                .in_unit_of_work(uow)
+                .record_event(
+                    &TransactionEvent::new(
+                        transaction.id.clone(),
+                        TransactionEventType::ReversalQueued,
+                        Some(transaction.status.clone()),
+                        transaction.status.clone(),
+                        actor.to_string(),
+                    )
+                    .with_details(reason.as_str()),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 让预登记的冲正立即执行并记录状态历史
+    async fn schedule_provisional_reversal(
+        &self,
+        uow: &UnitOfWork,
+        transaction: &Transaction,
+    ) -> Result<(), AppError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        if self
+            .transaction_repo
+            .in_unit_of_work(uow)
+            .reschedule_reversal(&transaction.id, &now)
+            .await?
+        {
+            self.transaction_repo
+                .in_unit_of_work(uow)
+                .record_event(
+                    &TransactionEvent::new(
+                        transaction.id.clone(),
+                        TransactionEventType::ReversalQueued,
+                        Some(transaction.status.clone()),
+                        transaction.status.clone(),
+                        SYSTEM_ACTOR.to_string(),
+                    )
+                    .with_details(ReversalReason::ProcessorTimeout.as_str()),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 后续交易有明确结果后更新对原交易的占用，并在事务内重新计算原交易的退款合计
     ///
-    /// 在实际环境中，这里会调用真实的支付网关或处理器
-    async fn simulate_transaction_processing(
+    /// 未批准时释放占用；批准的退款只保留批准金额，批准的撤销退款释放原消费上的退款金额
+    async fn settle_follow_up(
         &self,
+        uow: &UnitOfWork,
         transaction: &Transaction,
-    ) -> Result<(TransactionStatus, Option<String>, Option<String>, Option<String>), AppError> {
-        // 模拟处理延迟
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-
-        // 简单的模拟逻辑
-        let success_rate = match transaction.transaction_type {
-            TransactionType::Payment => 0.95,
-            TransactionType::PreAuth => 0.98,
-            TransactionType::Refund => 0.99,
-            TransactionType::Void => 0.99,
-            TransactionType::Capture => 0.97,
+        linked: &mut LinkedOriginal,
+    ) -> Result<(), AppError> {
+        let repo = self.transaction_repo.in_unit_of_work(uow);
+        let original = &linked.original;
+
+        match (&transaction.status, &transaction.transaction_type) {
+            (TransactionStatus::Approved, TransactionType::Refund) => {
+                let unapproved = transaction.amount - approved_amount(transaction);
+                if unapproved > 0 {
+                    repo.release_refund(&original.id, unapproved).await?;
+                }
+                linked.refunded =
+                    refunded_amount(&repo, &original.id, Some(&transaction.id)).await?;
+            },
+            (TransactionStatus::Approved, TransactionType::Void) => {
+                repo.release_follow_up(&original.id, transaction).await?;
+                if let (TransactionType::Refund, Some(payment_id)) =
+                    (&original.transaction_type, &original.original_transaction_id)
+                {
+                    repo.release_refund(payment_id, approved_amount(original)).await?;
+                    let remaining = refunded_amount(&repo, payment_id, Some(&original.id)).await?;
+                    let status = if remaining > 0 {
+                        TransactionStatus::PartiallyRefunded
+                    } else {
+                        TransactionStatus::Approved
+                    };
+                    linked.restore = Some((payment_id.clone(), status));
+                }
+            },
+            _ => repo.release_follow_up(&original.id, transaction).await?,
+        }
+
+        Ok(())
+    }
+
+    /// 执行一次冲正并记录结果，返回是否冲正成功
+    ///
+    /// 处理器批准冲正或找不到原交易（应答码25）时交易记为REVERSED；
+    /// 拒绝、超时或通信失败时按指数退避重试，超过最大次数后标记为FAILED
+    async fn execute_reversal(
+        &self,
+        reversal: &TransactionReversal,
+        attempts: i64,
+    ) -> Result<bool, AppError> {
+        let Some(transaction) = self.transaction_repo.find_by_id(&reversal.transaction_id).await?
+        else {
+            self.transaction_repo
+                .fail_reversal(&reversal.id, "Transaction not found", None, None)
+                .await?;
+            return Ok(false);
         };
 
-        // 使用交易金额作为随机种子
-        let random_factor = (transaction.amount % 100) as f64 / 100.0;
+        let outcome = self.send_reversal(&transaction).await;
+
+        // 处理器按报文数据元匹配原交易而交易没有保存数据元时，"找不到原交易"不能说明
+        // 原交易未被处理，冲正保持待处理并告警
+        let missing_original = MessageIdentifiers::from_transaction(&transaction).is_none()
+            && transaction
+                .processor
+                .as_deref()
+                .and_then(|name| self.payment_router.connector(name))
+                .is_some_and(|processor| processor.uses_message_identifiers());
+        let original_not_found = outcome
+            .as_ref()
+            .is_ok_and(|response| response.response_code == ORIGINAL_NOT_FOUND_CODE);
+
+        if let Ok(response) = &outcome {
+            if response.is_approved() || (original_not_found && !missing_original) {
+                let uow = self.transaction_repo.begin().await?;
+                self.transaction_repo
+                    .in_unit_of_work(&uow)
+                    .complete_reversal(&reversal.id, &response.response_code)
+                    .await?;
+                self.transaction_repo
+                    .in_unit_of_work(&uow)
+                    .update_lifecycle(&transaction.id, TransactionStatus::Reversed, None)
+                    .await?;
+
+                // 结果未决的后续交易冲正后释放对原交易的占用
+                if let (
+                    TransactionStatus::Pending | TransactionStatus::Unknown,
+                    Some(original_id),
+                ) = (&transaction.status, &transaction.original_transaction_id)
+                {
+                    self.transaction_repo
+                        .in_unit_of_work(&uow)
+                        .release_follow_up(original_id, &transaction)
+                        .await?;
+                }
+                self.transaction_repo
+                    .in_unit_of_work(&uow)
+                    .record_event(
+                        &TransactionEvent::new(
+                            transaction.id.clone(),
+                            TransactionEventType::Reversed,
+                            Some(transaction.status.clone()),
+                            TransactionStatus::Reversed,
+                            SYSTEM_ACTOR.to_string(),
+                        )
+                        .with_response_code(Some(response.response_code.clone()))
+                        .with_details(format!("attempt {}", attempts)),
+                    )
+                    .await?;
+
+                let audit_log = AuditLog::new(
+                    "TRANSACTION_REVERSAL".to_string(),
+                    SYSTEM_ACTOR.to_string(),
+                    OperationResult::Success,
+                )
+                .with_device_id(transaction.device_id.clone())
+                .with_details(format!(
+                    "Transaction reversed: transaction={}, response_code={}, attempts={}",
+                    transaction.id, response.response_code, attempts
+                ));
+                self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+                uow.commit().await?;
 
-        if random_factor < success_rate {
-            // 交易成功
-            let auth_code = crypto::generate_random_hex(6).to_uppercase();
-            Ok((
-                TransactionStatus::Approved,
-                Some(auth_code),
-                Some("00".to_string()),
-                Some("Transaction approved".to_string()),
-            ))
+                tracing::info!("Transaction {} reversed after {} attempt(s)", transaction.id, attempts);
+                return Ok(true);
+            }
+        }
+
+        let (error, response_code, retryable) = match outcome {
+            Ok(response) if original_not_found => (
+                format!(
+                    "Processor could not find the original transaction ({} {}) and its original \
+                     data elements were not recorded",
+                    response.response_code, response.response_message
+                ),
+                Some(response.response_code),
+                true,
+            ),
+            Ok(response) => (
+                format!(
+                    "Reversal declined: {} {}",
+                    response.response_code, response.response_message
+                ),
+                Some(response.response_code),
+                true,
+            ),
+            Err(e) => {
+                let retryable = matches!(
+                    e,
+                    AppError::ProcessorTimeout(_)
+                        | AppError::ProcessorOutcomeUnknown(_)
+                        | AppError::External(_)
+                );
+                (e.to_string(), None, retryable)
+            },
+        };
+
+        let next_attempt_at = (retryable
+            && (attempts < self.reversal_max_attempts || original_not_found))
+            .then(|| self.reversal_retry_at(attempts));
+        let event_type = if next_attempt_at.is_some() {
+            TransactionEventType::ReversalAttemptFailed
         } else {
-            // 交易失败
-            let decline_codes = vec![
-                ("05", "Do not honor"),
-                ("14", "Invalid card number"),
-                ("51", "Insufficient funds"),
-                ("54", "Expired card"),
-                ("61", "Exceeds withdrawal amount limit"),
-            ];
-
-            let code_index = (transaction.amount % decline_codes.len() as i64) as usize;
-            let (response_code, response_message) = decline_codes[code_index];
-
-            Ok((
-                TransactionStatus::Declined,
-                None,
-                Some(response_code.to_string()),
-                Some(response_message.to_string()),
-            ))
+            TransactionEventType::ReversalFailed
+        };
+
+        let uow = self.transaction_repo.begin().await?;
+        self.transaction_repo
+            .in_unit_of_work(&uow)
+            .fail_reversal(
+                &reversal.id,
+                &error,
+                response_code.as_deref(),
+                next_attempt_at.as_deref(),
+            )
+            .await?;
+        self.transaction_repo
+            .in_unit_of_work(&uow)
+            .record_event(
+                &TransactionEvent::new(
+                    transaction.id.clone(),
+                    event_type,
+                    Some(transaction.status.clone()),
+                    transaction.status.clone(),
+                    SYSTEM_ACTOR.to_string(),
+                )
+                .with_response_code(response_code)
+                .with_details(format!("attempt {}: {}", attempts, error)),
+            )
+            .await?;
+
+        if next_attempt_at.is_none() {
+            let audit_log = AuditLog::new(
+                "TRANSACTION_REVERSAL".to_string(),
+                SYSTEM_ACTOR.to_string(),
+                OperationResult::Failure,
+            )
+            .with_device_id(transaction.device_id.clone())
+            .with_details(format!(
+                "Reversal failed: transaction={}, attempts={}, error={}",
+                transaction.id, attempts, error
+            ));
+            self.audit_repo.in_unit_of_work(&uow).create(&audit_log).await?;
+        }
+        uow.commit().await?;
+
+        if original_not_found {
+            tracing::error!(
+                "Reversal for transaction {} left pending, manual action required: {}",
+                transaction.id,
+                error
+            );
+            if let Some(notifier) = &self.notifier {
+                notifier.send_reversal_alert(&transaction, error);
+            }
+        } else if next_attempt_at.is_none() {
+            tracing::error!(
+                "Reversal for transaction {} failed after {} attempt(s), manual action required: {}",
+                transaction.id,
+                attempts,
+                error
+            );
+        } else {
+            tracing::warn!(
+                "Reversal attempt {} for transaction {} failed: {}",
+                attempts,
+                transaction.id,
+                error
+            );
         }
+
+        Ok(false)
+    }
+
+    /// 通过原交易的连接器发送冲正
+    async fn send_reversal(&self, transaction: &Transaction) -> Result<PaymentResponse, AppError> {
+        let name = transaction.processor.as_deref().unwrap_or_default();
+        let processor = self.payment_router.connector(name).ok_or_else(|| {
+            AppError::Configuration(format!("Unknown payment connector: {}", name))
+        })?;
+
+        let mut request =
+            PaymentRequest::from_transaction(transaction, self.acquirer_mid(transaction).await?);
+        request.original_reference = transaction.processor_reference.clone();
+        request.original_identifiers = request.identifiers.take();
+
+        processor.execute(PaymentOperation::Reversal, &request).await
+    }
+
+    /// 第 `attempts` 次冲正失败后的重试时间：`base * 2^(attempts-1)`，不超过最长间隔
+    fn reversal_retry_at(&self, attempts: i64) -> String {
+        let exponent = (attempts - 1).clamp(0, 20) as u32;
+        let delay = self
+            .reversal_retry_base
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(MAX_REVERSAL_RETRY_DELAY);
+        (chrono::Utc::now() + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero()))
+            .to_rfc3339()
     }
+
+    /// 交易未入批或所属批次仍开放；已关批的交易只能退款
+    async fn in_open_batch(&self, transaction: &Transaction) -> Result<bool, AppError> {
+        let (Some(settlement_repo), Some(batch_id)) = (&self.settlement_repo, &transaction.batch_id)
+        else {
+            return Ok(true);
+        };
+        Ok(settlement_repo
+            .find_by_id(batch_id)
+            .await?
+            .is_none_or(|batch| batch.status == BatchStatus::Open.as_str()))
+    }
+
+    /// 校验后续交易与原交易的生命周期规则
+    ///
+    /// - 消费和首笔预授权不引用原交易；退款、撤销、请款必须引用原交易，
+    ///   引用预授权的预授权为追加授权
+    /// - 原交易须属于同一商户（未分配商户时为同一设备）且币种一致
+    /// - 请款和追加授权只能针对未过期、未请款的预授权，请款金额不超过累计授权金额
+    /// - 退款只能针对消费或请款，累计退款不超过批准金额
+    /// - 撤销只能针对当天、未结算且所属批次未关批的已批准交易，金额须等于原批准金额
+    async fn check_lifecycle(
+        &self,
+        request: &ProcessTransactionRequest,
+        merchant_id: Option<&str>,
+    ) -> Result<Option<LinkedOriginal>, AppError> {
+        let original_id = match (&request.transaction_type, &request.original_transaction_id) {
+            (TransactionType::Payment, Some(_)) => {
+                return Err(AppError::BadRequest(
+                    "Payment cannot reference an original transaction".to_string(),
+                ));
+            },
+            (TransactionType::Payment | TransactionType::PreAuth, None) => return Ok(None),
+            (transaction_type, None) => {
+                return Err(AppError::BadRequest(format!(
+                    "{:?} requires original_transaction_id",
+                    transaction_type
+                )));
+            },
+            (_, Some(id)) => id,
+        };
+
+        let original = self
+            .transaction_repo
+            .find_by_id(original_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Original transaction not found".to_string()))?;
+
+        let same_owner = match (original.merchant_id.as_deref(), merchant_id) {
+            (Some(original_merchant), Some(merchant)) => original_merchant == merchant,
+            (None, None) => original.device_id == request.device_id,
+            _ => false,
+        };
+        if !same_owner {
+            return Err(AppError::BadRequest(
+                "Original transaction belongs to a different merchant".to_string(),
+            ));
+        }
+        if original.currency != request.currency {
+            return Err(AppError::BadRequest(
+                "Currency does not match the original transaction".to_string(),
+            ));
+        }
+
+        let now = chrono::Utc::now();
+        let original_amount = approved_amount(&original);
+        let mut refunded = 0;
+        let mut restore = None;
+
+        match request.transaction_type {
+            TransactionType::PreAuth | TransactionType::Capture => {
+                if original.transaction_type != TransactionType::PreAuth
+                    || original.original_transaction_id.is_some()
+                {
+                    return Err(AppError::BadRequest(
+                        "Original transaction must be a pre-authorization".to_string(),
+                    ));
+                }
+                match original.status {
+                    TransactionStatus::Approved => {},
+                    TransactionStatus::Captured => {
+                        return Err(AppError::BadRequest(
+                            "Pre-authorization has already been captured".to_string(),
+                        ));
+                    },
+                    TransactionStatus::Expired => {
+                        return Err(AppError::BadRequest(
+                            "Pre-authorization has expired".to_string(),
+                        ));
+                    },
+                    _ => {
+                        return Err(AppError::BadRequest(format!(
+                            "Pre-authorization in status {:?} cannot be used",
+                            original.status
+                        )));
+                    },
+                }
+                if original
+                    .auth_expires_at
+                    .as_deref()
+                    .is_some_and(|at| at <= now.to_rfc3339().as_str())
+                {
+                    self.transaction_repo
+                        .update_lifecycle(&original.id, TransactionStatus::Expired, None)
+                        .await?;
+                    self.transaction_repo
+                        .record_event(&TransactionEvent::new(
+                            original.id.clone(),
+                            TransactionEventType::Expired,
+                            Some(original.status.clone()),
+                            TransactionStatus::Expired,
+                            SYSTEM_ACTOR.to_string(),
+                        ))
+                        .await?;
+                    return Err(AppError::BadRequest("Pre-authorization has expired".to_string()));
+                }
+
+                if request.transaction_type == TransactionType::Capture {
+                    let incremental: i64 = self
+                        .transaction_repo
+                        .find_linked(&original.id)
+                        .await?
+                        .iter()
+                        .filter(|t| {
+                            t.transaction_type == TransactionType::PreAuth
+                                && t.status == TransactionStatus::Approved
+                        })
+                        .map(approved_amount)
+                        .sum();
+                    let authorized = original_amount + incremental;
+                    if request.amount > authorized {
+                        return Err(AppError::BadRequest(format!(
+                            "Capture amount exceeds authorized amount {}",
+                            authorized
+                        )));
+                    }
+                }
+            },
+            TransactionType::Refund => {
+                if !matches!(
+                    original.transaction_type,
+                    TransactionType::Payment | TransactionType::Capture
+                ) {
+                    return Err(AppError::BadRequest(
+                        "Only payments and captures can be refunded".to_string(),
+                    ));
+                }
+                if !matches!(
+                    original.status,
+                    TransactionStatus::Approved | TransactionStatus::PartiallyRefunded
+                ) {
+                    return Err(AppError::BadRequest(format!(
+                        "Transaction in status {:?} cannot be refunded",
+                        original.status
+                    )));
+                }
+
+                refunded = refunded_amount(&self.transaction_repo, &original.id, None).await?;
+                let refundable = original_amount - refunded;
+                if request.amount > refundable {
+                    return Err(AppError::BadRequest(format!(
+                        "Refund amount exceeds refundable amount {}",
+                        refundable
+                    )));
+                }
+            },
+            TransactionType::Void => {
+                if original.transaction_type == TransactionType::Void {
+                    return Err(AppError::BadRequest("A void cannot be voided".to_string()));
+                }
+                if original.status != TransactionStatus::Approved {
+                    return Err(AppError::BadRequest(format!(
+                        "Transaction in status {:?} cannot be voided",
+                        original.status
+                    )));
+                }
+                if original.settled_at.is_some() {
+                    return Err(AppError::BadRequest(
+                        "Settled transactions cannot be voided".to_string(),
+                    ));
+                }
+                if !self.in_open_batch(&original).await? {
+                    return Err(AppError::BadRequest(
+                        "Transactions in a closed batch cannot be voided".to_string(),
+                    ));
+                }
+                let same_day = chrono::DateTime::parse_from_rfc3339(&original.created_at)
+                    .map(|created| {
+                        created.with_timezone(&chrono::Utc).date_naive() == now.date_naive()
+                    })
+                    .unwrap_or(false);
+                if !same_day {
+                    return Err(AppError::BadRequest(
+                        "Transactions can only be voided on the day they were made".to_string(),
+                    ));
+                }
+                if request.amount != original_amount {
+                    return Err(AppError::BadRequest(format!(
+                        "Void amount must equal the original approved amount {}",
+                        original_amount
+                    )));
+                }
+
+                // 撤销请款时预授权恢复为可请款，撤销退款时按剩余退款恢复原消费的状态
+                restore = match (&original.transaction_type, &original.original_transaction_id) {
+                    (TransactionType::Capture, Some(preauth_id)) => {
+                        Some((preauth_id.clone(), TransactionStatus::Approved))
+                    },
+                    (TransactionType::Refund, Some(payment_id)) => {
+                        let remaining =
+                            refunded_amount(&self.transaction_repo, payment_id, Some(&original.id))
+                                .await?;
+                        let status = if remaining > 0 {
+                            TransactionStatus::PartiallyRefunded
+                        } else {
+                            TransactionStatus::Approved
+                        };
+                        Some((payment_id.clone(), status))
+                    },
+                    _ => None,
+                };
+            },
+            TransactionType::Payment => {},
+        }
+
+        Ok(Some(LinkedOriginal { original, refunded, restore }))
+    }
+
+    /// 交易所属商户的收单机构商户号
+    async fn acquirer_mid(&self, transaction: &Transaction) -> Result<Option<String>, AppError> {
+        Ok(match (&self.merchant_repo, &transaction.merchant_id) {
+            (Some(repo), Some(merchant_id)) => {
+                repo.find_by_id(merchant_id).await?.and_then(|m| m.acquirer_mid)
+            },
+            _ => None,
+        })
+    }
+
+    /// 选择处理交易的支付连接器并记录在交易上，后续交易交给原交易的连接器
+    fn route_to_processor(
+        &self,
+        transaction: &mut Transaction,
+        acquirer_mid: Option<&str>,
+        original: Option<&Transaction>,
+    ) -> Result<Arc<dyn PaymentProcessor>, AppError> {
+        let (processor_name, processor) = match original.and_then(|o| o.processor.as_deref()) {
+            Some(name) => {
+                let processor = self.payment_router.connector(name).ok_or_else(|| {
+                    AppError::Configuration(format!("Unknown payment connector: {}", name))
+                })?;
+                (name, processor)
+            },
+            None => self.payment_router.route(transaction.merchant_id.as_deref(), acquirer_mid)?,
+        };
+        transaction.processor = Some(processor_name.to_string());
+
+        Ok(processor)
+    }
+
+    /// 将交易提交给支付处理器，并把应答写回交易记录
+    ///
+    /// 处理器超时或请求发出后未收到有效应答时结果未知，交易记为UNKNOWN（应答码68）等待冲正；
+    /// 请求发出前通信失败时未送达，交易记为FAILED（应答码91）；配置错误等其他错误直接返回
+    async fn submit_to_processor(
+        &self,
+        transaction: &mut Transaction,
+        processor: &Arc<dyn PaymentProcessor>,
+        request: &PaymentRequest,
+    ) -> Result<(), AppError> {
+        let operation = PaymentOperation::for_transaction_type(&transaction.transaction_type);
+
+        match processor.execute(operation, request).await {
+            Ok(response) => {
+                transaction.status = if response.is_approved() {
+                    TransactionStatus::Approved
+                } else {
+                    TransactionStatus::Declined
+                };
+                transaction.approved_amount =
+                    response.is_approved().then_some(response.approved_amount);
+                transaction.authorization_code = response.authorization_code;
+                transaction.response_code = Some(response.response_code);
+                transaction.response_message = Some(response.response_message);
+                transaction.processor_reference = response.processor_reference;
+            },
+            Err(
+                e @ (AppError::ProcessorTimeout(_)
+                | AppError::ProcessorOutcomeUnknown(_)
+                | AppError::External(_)),
+            ) => {
+                tracing::warn!(
+                    "Payment processor {} failed for transaction {}: {}",
+                    transaction.processor.as_deref().unwrap_or_default(),
+                    transaction.id,
+                    e
+                );
+                // 请求已发出时处理器可能已处理，结果未知，保留预登记的冲正
+                let (status, response_code) = if matches!(e, AppError::External(_)) {
+                    (TransactionStatus::Failed, "91")
+                } else {
+                    (TransactionStatus::Unknown, "68")
+                };
+                transaction.status = status;
+                transaction.response_code = Some(response_code.to_string());
+                transaction.response_message = Some(e.to_string());
+            },
+            Err(e) => return Err(e),
+        }
+
+        Ok(())
+    }
+}
+
+/// 校验EMV数据与交易请求一致：卡片未拒绝交易，卡片签名的金额和币种与请求相同
+fn check_emv(request: &ProcessTransactionRequest, emv: &EmvData) -> Result<(), AppError> {
+    if emv.cryptogram_type() == CryptogramType::Aac {
+        return Err(AppError::BadRequest("Card declined the transaction (AAC)".to_string()));
+    }
+
+    if emv.amount_authorised().is_some_and(|amount| amount != request.amount) {
+        return Err(AppError::BadRequest(
+            "EMV amount does not match transaction amount".to_string(),
+        ));
+    }
+
+    if currency_numeric(&request.currency).is_some_and(|code| code != emv.currency_code()) {
+        return Err(AppError::BadRequest(
+            "EMV currency does not match transaction currency".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 原交易已批准的退款合计，可排除指定的退款
+async fn refunded_amount(
+    repo: &TransactionRepository,
+    original_id: &str,
+    exclude: Option<&str>,
+) -> Result<i64, AppError> {
+    Ok(repo
+        .find_linked(original_id)
+        .await?
+        .iter()
+        .filter(|t| {
+            t.transaction_type == TransactionType::Refund
+                && t.status == TransactionStatus::Approved
+                && exclude != Some(t.id.as_str())
+        })
+        .map(approved_amount)
+        .sum())
+}
+
+/// 交易的批准金额，未记录时为交易金额
+fn approved_amount(transaction: &Transaction) -> i64 {
+    transaction.approved_amount.unwrap_or(transaction.amount)
+}
+
+/// 后续交易批准后原交易（及上一级交易）的状态和有效期更新
+fn lifecycle_updates(
+    transaction: &Transaction,
+    linked: &LinkedOriginal,
+    expires_at: &str,
+) -> Vec<(String, TransactionStatus, Option<String>)> {
+    let original = &linked.original;
+    let mut updates = Vec::new();
+
+    match transaction.transaction_type {
+        TransactionType::Capture => {
+            updates.push((original.id.clone(), TransactionStatus::Captured, None));
+        },
+        // 追加授权延长预授权有效期
+        TransactionType::PreAuth => {
+            updates.push((
+                original.id.clone(),
+                TransactionStatus::Approved,
+                Some(expires_at.to_string()),
+            ));
+        },
+        TransactionType::Refund => {
+            let refunded = linked.refunded + approved_amount(transaction);
+            let status = if refunded >= approved_amount(original) {
+                TransactionStatus::Refunded
+            } else {
+                TransactionStatus::PartiallyRefunded
+            };
+            updates.push((original.id.clone(), status, None));
+        },
+        TransactionType::Void => {
+            updates.push((original.id.clone(), TransactionStatus::Voided, None));
+            if let Some((id, status)) = &linked.restore {
+                updates.push((id.clone(), status.clone(), None));
+            }
+        },
+        TransactionType::Payment => {},
+    }
+
+    updates
 }
 
 #[cfg(test)]
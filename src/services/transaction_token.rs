@@ -1,19 +1,32 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use uuid::Uuid;
 use chrono::Utc;
 
 use crate::{
-    models::{TransactionToken, TransactionTokenClaims, TokenConfig, TokenUsageRecord, HealthCheck},
+    models::{
+        transaction_binding_hash, TransactionToken, TransactionTokenClaims, TokenConfig,
+        TokenUsageRecord, HealthCheck,
+    },
+    repositories::TransactionTokenRepository,
     security::JwtService,
     infrastructure::RedisClient,
     utils::error::AppError,
 };
 
+/// 签发交易令牌要求的最低安全评分
+const MIN_SECURITY_SCORE: i32 = 60;
+
 /// 交易令牌服务
+///
+/// 只依据近期通过的健康检查签发令牌，健康检查结果写入令牌Claims。
+/// 令牌使用记录以数据库为准（`INSERT OR IGNORE` 原子占用），
+/// Redis仅缓存使用记录，供校验时快速拒绝已使用的令牌
 #[derive(Clone)]
 pub struct TransactionTokenService {
     jwt_service: Arc<JwtService>,
     redis_client: Option<RedisClient>,
+    usage_repo: Option<TransactionTokenRepository>,
+    health_check_max_age: Duration,
     config: TokenConfig,
 }
 
@@ -26,22 +39,61 @@ impl TransactionTokenService {
         Self {
             jwt_service,
             redis_client,
+            usage_repo: None,
+            health_check_max_age: Duration::from_secs(300),
             config: TokenConfig::default(),
         }
     }
 
+    /// 设置健康检查有效期，默认5分钟
+    pub fn with_health_check_max_age(mut self, health_check_max_age: Duration) -> Self {
+        self.health_check_max_age = health_check_max_age;
+        self
+    }
+
+    /// 设置令牌使用记录Repository，防重放的权威记录
+    pub fn with_usage_repo(mut self, usage_repo: TransactionTokenRepository) -> Self {
+        self.usage_repo = Some(usage_repo);
+        self
+    }
+
     /// 生成交易令牌
     pub async fn generate_token(
         &self,
         device_id: &str,
         health_check: &HealthCheck,
     ) -> Result<TransactionToken, AppError> {
-        // 1. 验证设备状态
-        if health_check.security_score < 60 {
-            return Err(AppError::BadRequest(
-                "Security score too low for transaction".to_string()
-            ));
+        self.issue_token(device_id, health_check, None).await
+    }
+
+    /// 生成绑定金额和币种的交易令牌，处理交易时只接受此类令牌
+    pub async fn generate_bound_token(
+        &self,
+        device_id: &str,
+        health_check: &HealthCheck,
+        amount: i64,
+        currency: &str,
+    ) -> Result<TransactionToken, AppError> {
+        let max_amount = self.calculate_max_amount(health_check.security_score);
+        if amount > max_amount {
+            return Err(AppError::BadRequest(format!(
+                "Transaction amount {} exceeds maximum allowed amount {} for security score {}",
+                amount, max_amount, health_check.security_score
+            )));
         }
+
+        self.issue_token(device_id, health_check, Some(transaction_binding_hash(amount, currency)))
+            .await
+    }
+
+    async fn issue_token(
+        &self,
+        device_id: &str,
+        health_check: &HealthCheck,
+        txn_hash: Option<String>,
+    ) -> Result<TransactionToken, AppError> {
+        // 1. 验证健康检查
+        self.ensure_recent_clean_check(device_id, health_check)?;
         
         // 2. 生成JWT Claims
         let now = Utc::now().timestamp();
@@ -58,6 +110,13 @@ impl TransactionTokenService {
             max_amount: self.calculate_max_amount(health_check.security_score),
             nonce: Self::generate_nonce(),
             token_type: "transaction".to_string(),
+            health_checked_at: health_check.created_at.clone(),
+            root_status: health_check.root_status,
+            bootloader_status: health_check.bootloader_status,
+            system_integrity: health_check.system_integrity,
+            app_integrity: health_check.app_integrity,
+            tee_status: health_check.tee_status,
+            txn_hash,
         };
         
         // 3. 生成JWT
@@ -106,14 +165,11 @@ impl TransactionTokenService {
             ));
         }
         
-        // 4. 检查Redis黑名单（是否已使用）
-        if let Some(ref redis) = self.redis_client {
-            let blacklist_key = format!("txn_used:{}", claims.jti);
-            if redis.exists(&blacklist_key).await.unwrap_or(false) {
-                return Err(AppError::BadRequest(
-                    "Token already used".to_string()
-                ));
-            }
+        // 4. 检查是否已使用
+        if self.is_token_used(&claims.jti).await? {
+            return Err(AppError::BadRequest(
+                "Token already used".to_string()
+            ));
         }
         
         tracing::debug!(
@@ -125,37 +181,171 @@ impl TransactionTokenService {
         Ok(claims)
     }
     
+    /// 验证处理交易使用的令牌
+    ///
+    /// 除 `verify_token` 的检查外，要求令牌由交易鉴证签发且绑定的金额和币种与请求一致，
+    /// 金额不超过令牌允许的最大金额
+    pub async fn verify_for_transaction(
+        &self,
+        token: &str,
+        device_id: &str,
+        amount: i64,
+        currency: &str,
+    ) -> Result<TransactionTokenClaims, AppError> {
+        let claims = self.verify_token(token, device_id).await?;
+
+        match claims.txn_hash.as_deref() {
+            None => {
+                return Err(AppError::Unauthorized(
+                    "Token is not bound to a transaction".to_string()
+                ));
+            },
+            Some(hash) if hash != transaction_binding_hash(amount, currency) => {
+                return Err(AppError::Unauthorized(
+                    "Token does not match transaction amount or currency".to_string()
+                ));
+            },
+            Some(_) => {},
+        }
+
+        if amount > claims.max_amount {
+            return Err(AppError::BadRequest(format!(
+                "Transaction amount {} exceeds maximum allowed amount {} for security score {}",
+                amount, claims.max_amount, claims.security_score
+            )));
+        }
+
+        Ok(claims)
+    }
+
     /// 标记令牌已使用
+    ///
+    /// 原子占用令牌，令牌已被其他请求使用时返回错误；应在提交处理器之前调用
     pub async fn mark_token_used(
         &self,
         claims: &TransactionTokenClaims,
         transaction_id: &str,
     ) -> Result<(), AppError> {
-        if let Some(ref redis) = self.redis_client {
-            let blacklist_key = format!("txn_used:{}", claims.jti);
-            let ttl = (claims.exp - Utc::now().timestamp()).max(0);
-            
-            let usage_record = TokenUsageRecord {
-                device_id: claims.sub.clone(),
-                used_at: Utc::now().to_rfc3339(),
-                transaction_id: transaction_id.to_string(),
-            };
-            
-            let value = serde_json::to_string(&usage_record)
-                .map_err(|e| AppError::InternalWithMessage(format!("Failed to serialize usage record: {}", e)))?;
-            
-            redis.set_ex(&blacklist_key, value, ttl as u64).await
-                .map_err(|e| AppError::InternalWithMessage(format!("Failed to set Redis key: {}", e)))?;
-            
-            tracing::info!(
-                "Transaction token marked as used: jti={}, transaction={}",
-                claims.jti,
-                transaction_id
-            );
+        let usage_record = TokenUsageRecord {
+            device_id: claims.sub.clone(),
+            used_at: Utc::now().to_rfc3339(),
+            transaction_id: transaction_id.to_string(),
+        };
+
+        let repo = self.usage_repo.as_ref().ok_or_else(|| {
+            AppError::InternalWithMessage(
+                "No transaction token usage store available".to_string()
+            )
+        })?;
+        let expires_at = chrono::DateTime::from_timestamp(claims.exp, 0)
+            .unwrap_or_else(Utc::now)
+            .to_rfc3339();
+
+        if !repo.try_mark_used(&claims.jti, &usage_record, &expires_at).await? {
+            return Err(AppError::BadRequest(
+                "Token already used".to_string()
+            ));
         }
-        
+
+        self.cache_usage_in_redis(claims, &usage_record).await;
+
+        tracing::info!(
+            "Transaction token marked as used: jti={}, transaction={}",
+            claims.jti,
+            transaction_id
+        );
+
         Ok(())
     }
+
+    /// 删除已过期令牌的使用记录
+    pub async fn purge_expired_usage(&self) -> Result<u64, AppError> {
+        let Some(repo) = &self.usage_repo else {
+            return Ok(0);
+        };
+
+        let removed = repo.delete_expired(&Utc::now().to_rfc3339()).await?;
+        if removed > 0 {
+            tracing::debug!("Purged {} expired transaction token usage records", removed);
+        }
+
+        Ok(removed)
+    }
+
+    /// 启动后台清理任务
+    pub fn start_cleanup_worker(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.purge_expired_usage().await {
+                    tracing::error!("Failed to purge transaction token usage: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 要求健康检查属于该设备、在有效期内且检查通过
+    fn ensure_recent_clean_check(
+        &self,
+        device_id: &str,
+        health_check: &HealthCheck,
+    ) -> Result<(), AppError> {
+        if health_check.device_id != device_id {
+            return Err(AppError::HealthCheckRequired("NO_HEALTH_CHECK"));
+        }
+
+        let checked_at = chrono::DateTime::parse_from_rfc3339(&health_check.created_at)
+            .map_err(|_| AppError::HealthCheckRequired("HEALTH_CHECK_EXPIRED"))?
+            .with_timezone(&Utc);
+        let max_age = chrono::Duration::from_std(self.health_check_max_age)
+            .unwrap_or_else(|_| chrono::Duration::zero());
+        if Utc::now() - checked_at > max_age {
+            return Err(AppError::HealthCheckRequired("HEALTH_CHECK_EXPIRED"));
+        }
+
+        if !health_check.is_clean() || health_check.security_score < MIN_SECURITY_SCORE {
+            return Err(AppError::HealthCheckRequired("HEALTH_CHECK_FAILED"));
+        }
+
+        Ok(())
+    }
+
+    /// 令牌是否已使用（Redis或数据库中任一存在使用记录）
+    async fn is_token_used(&self, jti: &str) -> Result<bool, AppError> {
+        if let Some(ref redis) = self.redis_client {
+            match redis.exists(&format!("txn_used:{}", jti)).await {
+                Ok(true) => return Ok(true),
+                Ok(false) => {},
+                Err(e) => tracing::warn!("Redis unavailable for token check: {}", e),
+            }
+        }
+
+        match &self.usage_repo {
+            Some(repo) => repo.is_used(jti).await,
+            None => Ok(false),
+        }
+    }
+
+    /// 将使用记录缓存到Redis，Redis未配置或不可用时跳过
+    async fn cache_usage_in_redis(
+        &self,
+        claims: &TransactionTokenClaims,
+        usage_record: &TokenUsageRecord,
+    ) {
+        let Some(redis) = self.redis_client.as_ref() else {
+            return;
+        };
+        let blacklist_key = format!("txn_used:{}", claims.jti);
+        let ttl = (claims.exp - Utc::now().timestamp()).max(0);
+        let Ok(value) = serde_json::to_string(usage_record) else {
+            return;
+        };
+
+        if let Err(e) = redis.set_nx_ex(&blacklist_key, value, ttl as u64).await {
+            tracing::warn!("Failed to cache token usage in Redis: {}", e);
+        }
+    }
     
     /// 根据安全评分计算最大交易金额
     fn calculate_max_amount(&self, security_score: i32) -> i64 {
@@ -183,6 +373,8 @@ mod tests {
         let service = TransactionTokenService {
             jwt_service: Arc::new(JwtService::new("test".to_string(), 3600)),
             redis_client: None,
+            usage_repo: None,
+            health_check_max_age: Duration::from_secs(300),
             config: TokenConfig::default(),
         };
 
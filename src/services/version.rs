@@ -3,7 +3,7 @@ use crate::{
         CreateVersionRequest, CreateVersionResponse, VersionResponse, VersionListResponse,
         UpdateVersionRequest,
     },
-    models::{SdkVersion, VersionStatus, UpdateType, AuditLog, OperationResult},
+    models::{SdkVersion, VersionStatus, UpdateType, AuditLog, OperationResult, TenantContext},
     repositories::{VersionRepository, DeviceRepository, AuditLogRepository},
     utils::error::AppError,
 };
@@ -30,6 +30,15 @@ impl VersionService {
         }
     }
 
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            version_repo: self.version_repo.for_tenant(tenant),
+            device_repo: self.device_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+        }
+    }
+
     /// 创建版本
     pub async fn create_version(
         &self,
@@ -384,9 +393,9 @@ mod tests {
     #[tokio::test]
     async fn test_validate_semantic_version() {
         let service = VersionService::new(
-            VersionRepository::new(sqlx::SqlitePool::connect("").await.unwrap()),
-            DeviceRepository::new(sqlx::SqlitePool::connect("").await.unwrap()),
-            AuditLogRepository::new(sqlx::SqlitePool::connect("").await.unwrap()),
+            VersionRepository::unscoped(sqlx::SqlitePool::connect("").await.unwrap()),
+            DeviceRepository::unscoped(sqlx::SqlitePool::connect("").await.unwrap()),
+            AuditLogRepository::unscoped(sqlx::SqlitePool::connect("").await.unwrap()),
         );
 
         assert!(service.validate_semantic_version("1.0.0").is_ok());
@@ -401,9 +410,9 @@ mod tests {
     #[tokio::test]
     async fn test_compare_versions() {
         let service = VersionService::new(
-            VersionRepository::new(sqlx::SqlitePool::connect("").await.unwrap()),
-            DeviceRepository::new(sqlx::SqlitePool::connect("").await.unwrap()),
-            AuditLogRepository::new(sqlx::SqlitePool::connect("").await.unwrap()),
+            VersionRepository::unscoped(sqlx::SqlitePool::connect("").await.unwrap()),
+            DeviceRepository::unscoped(sqlx::SqlitePool::connect("").await.unwrap()),
+            AuditLogRepository::unscoped(sqlx::SqlitePool::connect("").await.unwrap()),
         );
 
         assert!(service.is_newer_version("1.0.1", "1.0.0"));
@@ -0,0 +1,495 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use reqwest::Client;
+
+use crate::{
+    dto::{
+        CreateWebhookRequest, UpdateWebhookRequest, WebhookDeliveryListResponse,
+        WebhookDeliveryResponse, WebhookSubscriptionListResponse, WebhookSubscriptionResponse,
+    },
+    models::{
+        AuditLog, OperationResult, TenantContext, WebhookDelivery, WebhookDeliveryStatus,
+        WebhookEventType, WebhookSubscription, DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+    },
+    repositories::{AuditLogRepository, MerchantRepository, WebhookRepository},
+    security::crypto,
+    utils::error::AppError,
+};
+
+/// 签名时间戳请求头（Unix秒）
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+
+/// 签名请求头：`sha256=<hex(HMAC-SHA256(secret, "{timestamp}.{body}"))>`
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// 事件类型请求头
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Webhook-Event";
+
+/// 投递ID请求头
+pub const WEBHOOK_DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+/// 两次重试之间的最长间隔
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(3600);
+
+/// 每轮处理的最大投递数
+const DELIVERY_BATCH_SIZE: i64 = 100;
+
+/// 错误信息中保留的响应体长度
+const MAX_ERROR_BODY_LENGTH: usize = 512;
+
+/// 计算Webhook签名
+///
+/// 接收方使用订阅密钥对 `"{timestamp}.{body}"` 计算HMAC-SHA256并比对，同时校验时间戳防止重放
+pub fn sign_webhook_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let message = format!("{}.{}", timestamp, body);
+    format!("sha256={}", crypto::hmac_sha256_hex(secret.as_bytes(), message.as_bytes()))
+}
+
+/// 商户Webhook服务
+///
+/// 业务事件按商户的订阅写入持久化投递队列，由后台任务逐条POST到订阅地址。
+/// 非2xx响应或网络错误时按指数退避重试，超过最大投递次数后进入死信状态，可通过API手动重新投递。
+#[derive(Clone)]
+pub struct WebhookService {
+    webhook_repo: WebhookRepository,
+    merchant_repo: MerchantRepository,
+    audit_repo: AuditLogRepository,
+    http: Client,
+    timeout: Duration,
+    max_attempts: i64,
+    retry_base: Duration,
+}
+
+impl WebhookService {
+    /// 创建新的Webhook服务
+    pub fn new(
+        webhook_repo: WebhookRepository,
+        merchant_repo: MerchantRepository,
+        audit_repo: AuditLogRepository,
+    ) -> Self {
+        Self {
+            webhook_repo,
+            merchant_repo,
+            audit_repo,
+            http: Client::new(),
+            timeout: Duration::from_secs(10),
+            max_attempts: DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+            retry_base: Duration::from_secs(30),
+        }
+    }
+
+    /// 设置重试策略：最大投递次数和首次重试间隔
+    pub fn with_retry_policy(mut self, max_attempts: i64, retry_base: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_base = retry_base;
+        self
+    }
+
+    /// 设置单次请求超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 返回绑定到指定租户的服务，所有数据访问均限定在该租户内
+    pub fn for_tenant(&self, tenant: &TenantContext) -> Self {
+        Self {
+            webhook_repo: self.webhook_repo.for_tenant(tenant),
+            merchant_repo: self.merchant_repo.for_tenant(tenant),
+            audit_repo: self.audit_repo.for_tenant(tenant),
+            ..self.clone()
+        }
+    }
+
+    /// 创建订阅，签名密钥只在本次响应中返回
+    pub async fn create_subscription(
+        &self,
+        request: CreateWebhookRequest,
+        operator: &str,
+    ) -> Result<WebhookSubscriptionResponse, AppError> {
+        request.validate()?;
+
+        let merchant = self
+            .merchant_repo
+            .find_by_id(&request.merchant_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Merchant not found".to_string()))?;
+
+        let mut subscription = WebhookSubscription::new(
+            merchant.id.clone(),
+            request.url,
+            format!("whsec_{}", crypto::generate_random_hex(32)),
+            &request.event_types,
+            operator.to_string(),
+        );
+        subscription.tenant_id = merchant.tenant_id.clone();
+
+        self.webhook_repo.create_subscription(&subscription).await?;
+
+        self.audit(
+            "WEBHOOK_CREATE",
+            operator,
+            format!(
+                "Webhook {} created for merchant {}: {}",
+                subscription.id, subscription.merchant_id, subscription.url
+            ),
+        )
+        .await?;
+
+        let secret = subscription.secret.clone();
+        let mut response = WebhookSubscriptionResponse::from(subscription);
+        response.secret = Some(secret);
+
+        Ok(response)
+    }
+
+    /// 获取订阅
+    pub async fn get_subscription(
+        &self,
+        subscription_id: &str,
+    ) -> Result<WebhookSubscriptionResponse, AppError> {
+        Ok(WebhookSubscriptionResponse::from(
+            self.find_subscription(subscription_id).await?,
+        ))
+    }
+
+    /// 列出订阅
+    pub async fn list_subscriptions(
+        &self,
+        merchant_id: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<WebhookSubscriptionListResponse, AppError> {
+        let subscriptions =
+            self.webhook_repo.list_subscriptions(merchant_id, limit, offset).await?;
+        let total = self.webhook_repo.count_subscriptions(merchant_id).await?;
+
+        Ok(WebhookSubscriptionListResponse {
+            subscriptions: subscriptions
+                .into_iter()
+                .map(WebhookSubscriptionResponse::from)
+                .collect(),
+            total,
+        })
+    }
+
+    /// 更新订阅
+    pub async fn update_subscription(
+        &self,
+        subscription_id: &str,
+        request: UpdateWebhookRequest,
+        operator: &str,
+    ) -> Result<WebhookSubscriptionResponse, AppError> {
+        request.validate()?;
+
+        let mut subscription = self.find_subscription(subscription_id).await?;
+        if let Some(url) = request.url {
+            subscription.url = url;
+        }
+        if let Some(event_types) = request.event_types {
+            subscription.event_types = serde_json::to_string(&event_types).map_err(|e| {
+                AppError::InternalWithMessage(format!("Failed to serialize event types: {}", e))
+            })?;
+        }
+        if let Some(active) = request.active {
+            subscription.active = active;
+        }
+
+        self.webhook_repo.update_subscription(&subscription).await?;
+
+        self.audit(
+            "WEBHOOK_UPDATE",
+            operator,
+            format!(
+                "Webhook {} updated: url={}, events={}, active={}",
+                subscription.id, subscription.url, subscription.event_types, subscription.active
+            ),
+        )
+        .await?;
+
+        self.get_subscription(&subscription.id).await
+    }
+
+    /// 删除订阅
+    pub async fn delete_subscription(
+        &self,
+        subscription_id: &str,
+        operator: &str,
+    ) -> Result<(), AppError> {
+        let subscription = self.find_subscription(subscription_id).await?;
+
+        if !self.webhook_repo.delete_subscription(&subscription.id).await? {
+            return Err(AppError::NotFound("Webhook not found".to_string()));
+        }
+
+        self.audit("WEBHOOK_DELETE", operator, format!("Webhook {} deleted", subscription.id))
+            .await
+    }
+
+    /// 列出订阅的投递记录
+    pub async fn list_deliveries(
+        &self,
+        subscription_id: &str,
+        status: Option<WebhookDeliveryStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<WebhookDeliveryListResponse, AppError> {
+        let subscription = self.find_subscription(subscription_id).await?;
+
+        let deliveries = self
+            .webhook_repo
+            .list_deliveries(&subscription.id, status, limit, offset)
+            .await?;
+        let total = self.webhook_repo.count_deliveries(&subscription.id, status).await?;
+
+        Ok(WebhookDeliveryListResponse {
+            deliveries: deliveries.into_iter().map(WebhookDeliveryResponse::from).collect(),
+            total,
+        })
+    }
+
+    /// 获取投递记录
+    pub async fn get_delivery(
+        &self,
+        delivery_id: &str,
+    ) -> Result<WebhookDeliveryResponse, AppError> {
+        Ok(WebhookDeliveryResponse::from(self.find_delivery(delivery_id).await?))
+    }
+
+    /// 重新投递：重置尝试次数并立即投递一次，失败时继续按退避策略重试
+    pub async fn redeliver(
+        &self,
+        delivery_id: &str,
+        operator: &str,
+    ) -> Result<WebhookDeliveryResponse, AppError> {
+        let delivery = self.find_delivery(delivery_id).await?;
+
+        if !self.webhook_repo.requeue(&delivery.id).await? {
+            return Err(AppError::NotFound("Webhook delivery not found".to_string()));
+        }
+
+        self.audit(
+            "WEBHOOK_REDELIVER",
+            operator,
+            format!("Webhook delivery {} ({}) requeued", delivery.id, delivery.event_type),
+        )
+        .await?;
+
+        let requeued = self.find_delivery(&delivery.id).await?;
+        self.attempt_delivery(&requeued).await?;
+
+        self.get_delivery(&delivery.id).await
+    }
+
+    /// 发布业务事件：为商户所有订阅了该事件的启用订阅创建投递，返回创建的投递数
+    pub async fn publish_event(
+        &self,
+        tenant_id: &str,
+        merchant_id: &str,
+        event_type: WebhookEventType,
+        data: serde_json::Value,
+    ) -> Result<usize, AppError> {
+        let subscriptions: Vec<WebhookSubscription> = self
+            .webhook_repo
+            .list_active_for_merchant(tenant_id, merchant_id)
+            .await?
+            .into_iter()
+            .filter(|s| s.accepts(event_type))
+            .collect();
+
+        if subscriptions.is_empty() {
+            return Ok(0);
+        }
+
+        let event_id = uuid::Uuid::new_v4().to_string();
+        let payload = serde_json::json!({
+            "id": event_id,
+            "type": event_type.as_str(),
+            "created_at": Utc::now().to_rfc3339(),
+            "merchant_id": merchant_id,
+            "data": data,
+        })
+        .to_string();
+
+        for subscription in &subscriptions {
+            let delivery = WebhookDelivery::new(
+                subscription,
+                event_id.clone(),
+                event_type,
+                payload.clone(),
+                self.max_attempts,
+            );
+            self.webhook_repo.create_delivery(&delivery).await?;
+        }
+
+        tracing::info!(
+            "Webhook event {} ({}) queued for {} subscriptions of merchant {}",
+            event_id,
+            event_type.as_str(),
+            subscriptions.len(),
+            merchant_id
+        );
+
+        Ok(subscriptions.len())
+    }
+
+    /// 投递所有到期的事件，返回本轮尝试的投递数
+    pub async fn process_due_deliveries(&self) -> Result<usize, AppError> {
+        let now = Utc::now().to_rfc3339();
+        let due = self.webhook_repo.list_due(&now, DELIVERY_BATCH_SIZE).await?;
+
+        let mut attempted = 0;
+        for delivery in due {
+            match self.attempt_delivery(&delivery).await {
+                Ok(true) => attempted += 1,
+                Ok(false) => {},
+                Err(e) => tracing::error!("Failed to deliver webhook {}: {}", delivery.id, e),
+            }
+        }
+
+        Ok(attempted)
+    }
+
+    /// 启动后台投递任务
+    pub fn start_delivery_worker(
+        self: Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.process_due_deliveries().await {
+                    tracing::error!("Failed to process webhook deliveries: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 领取并执行一次投递，返回是否实际发出了请求
+    async fn attempt_delivery(&self, delivery: &WebhookDelivery) -> Result<bool, AppError> {
+        let now = Utc::now();
+        let lease_until = now
+            + chrono::Duration::from_std(self.timeout * 2).unwrap_or(chrono::Duration::minutes(1));
+        if !self
+            .webhook_repo
+            .claim(&delivery.id, &now.to_rfc3339(), &lease_until.to_rfc3339())
+            .await?
+        {
+            return Ok(false);
+        }
+
+        let attempts = delivery.attempts + 1;
+        let subscription = self
+            .webhook_repo
+            .find_subscription(&delivery.subscription_id)
+            .await?
+            .filter(|s| s.active);
+
+        let Some(subscription) = subscription else {
+            self.webhook_repo
+                .mark_failed(&delivery.id, None, "Webhook subscription is disabled", None)
+                .await?;
+            return Ok(true);
+        };
+
+        let timestamp = now.timestamp();
+        let result = self
+            .http
+            .post(&subscription.url)
+            .timeout(self.timeout)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+            .header(
+                WEBHOOK_SIGNATURE_HEADER,
+                sign_webhook_payload(&subscription.secret, timestamp, &delivery.payload),
+            )
+            .header(WEBHOOK_EVENT_HEADER, &delivery.event_type)
+            .header(WEBHOOK_DELIVERY_HEADER, &delivery.id)
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        let (response_status, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                self.webhook_repo
+                    .mark_succeeded(&delivery.id, response.status().as_u16() as i64)
+                    .await?;
+                tracing::info!(
+                    "Webhook delivery {} succeeded on attempt {}",
+                    delivery.id,
+                    attempts
+                );
+                return Ok(true);
+            },
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                let body: String = body.chars().take(MAX_ERROR_BODY_LENGTH).collect();
+                (Some(status.as_u16() as i64), format!("HTTP {}: {}", status, body))
+            },
+            Err(e) => (None, format!("Request failed: {}", e)),
+        };
+
+        let next_attempt_at = (attempts < delivery.max_attempts).then(|| {
+            WebhookDelivery::retry_at(
+                attempts,
+                chrono::Duration::from_std(self.retry_base).unwrap_or(chrono::Duration::zero()),
+                chrono::Duration::from_std(MAX_RETRY_DELAY).unwrap_or(chrono::Duration::hours(1)),
+            )
+        });
+
+        match &next_attempt_at {
+            Some(at) => tracing::warn!(
+                "Webhook delivery {} attempt {} failed, retrying at {}: {}",
+                delivery.id,
+                attempts,
+                at,
+                error
+            ),
+            None => tracing::error!(
+                "Webhook delivery {} moved to dead letter after {} attempts: {}",
+                delivery.id,
+                attempts,
+                error
+            ),
+        }
+
+        self.webhook_repo
+            .mark_failed(&delivery.id, response_status, &error, next_attempt_at.as_deref())
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn find_subscription(
+        &self,
+        subscription_id: &str,
+    ) -> Result<WebhookSubscription, AppError> {
+        self.webhook_repo
+            .find_subscription(subscription_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Webhook not found".to_string()))
+    }
+
+    async fn find_delivery(&self, delivery_id: &str) -> Result<WebhookDelivery, AppError> {
+        self.webhook_repo
+            .find_delivery(delivery_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Webhook delivery not found".to_string()))
+    }
+
+    async fn audit(
+        &self,
+        operation: &str,
+        operator: &str,
+        details: String,
+    ) -> Result<(), AppError> {
+        let log =
+            AuditLog::new(operation.to_string(), operator.to_string(), OperationResult::Success)
+                .with_details(details);
+
+        self.audit_repo.create(&log).await
+    }
+}
@@ -0,0 +1,55 @@
+/// 解析单行分隔文本，支持双引号包裹和""转义
+pub fn parse_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+/// 转义CSV字段，包含逗号、引号或换行时加双引号
+pub fn escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_quotes() {
+        assert_eq!(parse_line("a,\"b,c\",\"d\"\"e\"", ','), vec!["a", "b,c", "d\"e"]);
+        assert_eq!(parse_line("a,,b\r", ','), vec!["a", "", "b"]);
+    }
+
+    #[test]
+    fn test_parse_line_delimiter() {
+        assert_eq!(parse_line("a;\"b;c\";d", ';'), vec!["a", "b;c", "d"]);
+        assert_eq!(parse_line("a|b,c", '|'), vec!["a", "b,c"]);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -40,6 +40,10 @@ pub enum AppError {
     #[error("Device security score too low: {0}")]
     DeviceSecurityScoreTooLow(i16),
 
+    /// 缺少近期通过的健康检查，SDK须重新执行健康检查；参数为原因代码
+    #[error("A new health check is required: {0}")]
+    HealthCheckRequired(&'static str),
+
     #[error("Invalid device mode")]
     InvalidDeviceMode,
 
@@ -57,6 +61,9 @@ pub enum AppError {
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Invalid credentials")]
     InvalidCredentials,
 
@@ -100,6 +107,19 @@ pub enum AppError {
     #[error("Transaction token expired")]
     TransactionTokenExpired,
 
+    #[error("Payment processor timeout: {0}")]
+    ProcessorTimeout(String),
+
+    /// 请求已发给处理器但未收到有效应答，处理器可能已处理
+    #[error("Payment processor outcome unknown: {0}")]
+    ProcessorOutcomeUnknown(String),
+
+    #[error("Idempotency key was already used with a different request")]
+    IdempotencyKeyMismatch,
+
+    #[error("A request with the same idempotency key is in progress")]
+    RequestInProgress,
+
     // Version errors
     #[error("Version not found")]
     VersionNotFound,
@@ -156,11 +176,13 @@ impl AppError {
             AppError::DeviceNotActive => "DEVICE_NOT_ACTIVE",
             AppError::DeviceSecurityCheckFailed => "DEVICE_SECURITY_CHECK_FAILED",
             AppError::DeviceSecurityScoreTooLow(_) => "DEVICE_SECURITY_SCORE_TOO_LOW",
+            AppError::HealthCheckRequired(_) => "HEALTH_CHECK_REQUIRED",
             AppError::InvalidDeviceMode => "INVALID_DEVICE_MODE",
             AppError::KeyExpired => "KEY_EXPIRED",
             AppError::KeyInjectionFailed(_) => "KEY_INJECTION_FAILED",
             AppError::InvalidKsn => "INVALID_KSN",
             AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::Forbidden(_) => "FORBIDDEN",
             AppError::InvalidCredentials => "INVALID_CREDENTIALS",
             AppError::TokenExpired => "TOKEN_EXPIRED",
             AppError::InvalidToken => "INVALID_TOKEN",
@@ -174,6 +196,10 @@ impl AppError {
             AppError::TransactionNotFound => "TRANSACTION_NOT_FOUND",
             AppError::InvalidTransactionToken => "INVALID_TRANSACTION_TOKEN",
             AppError::TransactionTokenExpired => "TRANSACTION_TOKEN_EXPIRED",
+            AppError::ProcessorTimeout(_) => "PROCESSOR_TIMEOUT",
+            AppError::ProcessorOutcomeUnknown(_) => "PROCESSOR_OUTCOME_UNKNOWN",
+            AppError::IdempotencyKeyMismatch => "IDEMPOTENCY_KEY_MISMATCH",
+            AppError::RequestInProgress => "REQUEST_IN_PROGRESS",
             AppError::VersionNotFound => "VERSION_NOT_FOUND",
             AppError::InvalidVersionFormat(_) => "INVALID_VERSION_FORMAT",
             AppError::ThreatNotFound => "THREAT_NOT_FOUND",
@@ -198,7 +224,9 @@ impl AppError {
             | AppError::ThreatNotFound
             | AppError::NotFound(_) => StatusCode::NOT_FOUND,
 
-            AppError::DeviceAlreadyExists(_) => StatusCode::CONFLICT,
+            AppError::DeviceAlreadyExists(_) | AppError::RequestInProgress => StatusCode::CONFLICT,
+
+            AppError::IdempotencyKeyMismatch => StatusCode::UNPROCESSABLE_ENTITY,
 
             AppError::Unauthorized(_)
             | AppError::InvalidCredentials
@@ -214,7 +242,8 @@ impl AppError {
             | AppError::InvalidDeviceMode
             | AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
 
-            AppError::DeviceNotActive
+            AppError::Forbidden(_)
+            | AppError::DeviceNotActive
             | AppError::DeviceSecurityCheckFailed
             | AppError::DeviceSecurityScoreTooLow(_)
             | AppError::KeyExpired
@@ -225,12 +254,29 @@ impl AppError {
             | AppError::HsmConnectionFailed
             | AppError::External(_) => StatusCode::SERVICE_UNAVAILABLE,
 
+            AppError::ProcessorTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+
+            AppError::ProcessorOutcomeUnknown(_) => StatusCode::BAD_GATEWAY,
+
             AppError::TaskQueueFull => StatusCode::TOO_MANY_REQUESTS,
 
+            AppError::HealthCheckRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
+    /// 供客户端据以处理的结构化错误详情
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::HealthCheckRequired(reason) => Some(serde_json::json!({
+                "required_action": "PERFORM_HEALTH_CHECK",
+                "reason": reason,
+            })),
+            _ => None,
+        }
+    }
+
     /// 是否应该记录详细错误信息
     pub fn should_log_details(&self) -> bool {
         matches!(
@@ -242,6 +288,8 @@ impl AppError {
                 | AppError::InternalWithMessage(_)
                 | AppError::Configuration(_)
                 | AppError::External(_)
+                | AppError::ProcessorTimeout(_)
+                | AppError::ProcessorOutcomeUnknown(_)
         )
     }
 }
@@ -251,6 +299,7 @@ impl IntoResponse for AppError {
         let status = self.status_code();
         let error_code = self.error_code().to_string();
         let error_message = self.to_string();
+        let details = self.details();
 
         // 对于内部错误，不暴露详细信息给客户端
         let error_message = if self.should_log_details() {
@@ -267,7 +316,7 @@ impl IntoResponse for AppError {
         let body = Json(ErrorResponse {
             error_code,
             error_message,
-            details: None,
+            details,
         });
 
         (status, body).into_response()
@@ -321,6 +370,10 @@ mod tests {
             AppError::DeviceAlreadyExists("123".to_string()).error_code(),
             "DEVICE_ALREADY_EXISTS"
         );
+        assert_eq!(
+            AppError::HealthCheckRequired("HEALTH_CHECK_EXPIRED").error_code(),
+            "HEALTH_CHECK_REQUIRED"
+        );
     }
 
     #[test]
@@ -341,6 +394,10 @@ mod tests {
             AppError::Internal.status_code(),
             StatusCode::INTERNAL_SERVER_ERROR
         );
+        assert_eq!(
+            AppError::HealthCheckRequired("NO_HEALTH_CHECK").status_code(),
+            StatusCode::PRECONDITION_REQUIRED
+        );
     }
 
     #[test]
@@ -1,3 +1,5 @@
+pub mod csv;
 pub mod error;
+pub mod tlv;
 
 pub use error::{AppError, ErrorResponse};
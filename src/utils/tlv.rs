@@ -0,0 +1,151 @@
+/// BER-TLV数据对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    /// 标签，多字节标签按大端拼接（如 `0x9F26`）
+    pub tag: u32,
+    pub value: Vec<u8>,
+}
+
+impl Tlv {
+    pub fn new(tag: u32, value: Vec<u8>) -> Self {
+        Self { tag, value }
+    }
+
+    /// 是否为结构化对象（值本身是TLV序列）
+    pub fn is_constructed(&self) -> bool {
+        tag_bytes(self.tag)[0] & 0x20 != 0
+    }
+}
+
+/// 解析BER-TLV序列，跳过对象之间的00填充字节
+///
+/// 只解析一层，结构化对象的值需再次调用 `parse`；错误信息不包含数据内容
+pub fn parse(data: &[u8]) -> Result<Vec<Tlv>, String> {
+    let mut objects = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        if data[pos] == 0x00 {
+            pos += 1;
+            continue;
+        }
+
+        let offset = pos;
+        let mut tag = data[pos] as u32;
+        pos += 1;
+        if tag & 0x1F == 0x1F {
+            loop {
+                let byte =
+                    *data.get(pos).ok_or_else(|| format!("Truncated tag at offset {}", offset))?;
+                if tag > 0xFFFF {
+                    return Err(format!("Tag too long at offset {}", offset));
+                }
+                tag = (tag << 8) | byte as u32;
+                pos += 1;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+        }
+
+        let first = *data.get(pos).ok_or_else(|| format!("Missing length for tag {:X}", tag))?;
+        pos += 1;
+        let length = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 3 {
+                return Err(format!("Unsupported length encoding for tag {:X}", tag));
+            }
+            let bytes = data
+                .get(pos..pos + count)
+                .ok_or_else(|| format!("Truncated length for tag {:X}", tag))?;
+            pos += count;
+            bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize)
+        };
+
+        let value = data
+            .get(pos..pos + length)
+            .ok_or_else(|| format!("Value of tag {:X} exceeds data length", tag))?;
+        pos += length;
+
+        objects.push(Tlv::new(tag, value.to_vec()));
+    }
+
+    Ok(objects)
+}
+
+/// 编码为BER-TLV序列，长度使用最短形式
+pub fn encode(objects: &[Tlv]) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    for object in objects {
+        data.extend(tag_bytes(object.tag));
+
+        let length = object.value.len();
+        match length {
+            0..=0x7F => data.push(length as u8),
+            0x80..=0xFF => data.extend([0x81, length as u8]),
+            0x100..=0xFFFF => data.extend([0x82, (length >> 8) as u8, length as u8]),
+            _ => data.extend([0x83, (length >> 16) as u8, (length >> 8) as u8, length as u8]),
+        }
+
+        data.extend(&object.value);
+    }
+
+    data
+}
+
+/// 标签的字节表示，去掉高位的0字节
+fn tag_bytes(tag: u32) -> Vec<u8> {
+    let bytes = tag.to_be_bytes();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(3);
+    bytes[start..].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_encode_roundtrip() {
+        let data = hex::decode(
+            "9F2608A1B2C3D4E5F6071895050000008000820219805A0841111111111111115F2A020840",
+        )
+        .unwrap();
+        let objects = parse(&data).unwrap();
+
+        assert_eq!(objects.len(), 5);
+        assert_eq!(objects[0].tag, 0x9F26);
+        assert_eq!(objects[0].value.len(), 8);
+        assert_eq!(objects[1].tag, 0x95);
+        assert_eq!(objects[3].tag, 0x5A);
+        assert_eq!(objects[4].value, vec![0x08, 0x40]);
+        assert_eq!(encode(&objects), data);
+    }
+
+    #[test]
+    fn test_long_form_length_and_constructed() {
+        let object = Tlv::new(0x77, encode(&[Tlv::new(0x9F10, vec![0xAB; 200])]));
+        let data = encode(std::slice::from_ref(&object));
+        assert_eq!(&data[..5], &[0x77, 0x81, 0xCC, 0x9F, 0x10]);
+
+        let parsed = parse(&data).unwrap();
+        assert!(parsed[0].is_constructed());
+        let inner = parse(&parsed[0].value).unwrap();
+        assert_eq!(inner[0].tag, 0x9F10);
+        assert_eq!(inner[0].value.len(), 200);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_data() {
+        // 填充字节被跳过
+        assert_eq!(parse(&[0x00, 0x9C, 0x01, 0x00, 0x00]).unwrap().len(), 1);
+
+        assert!(parse(&[0x9F]).is_err());
+        assert!(parse(&[0x9C]).is_err());
+        assert!(parse(&[0x9C, 0x02, 0x00]).is_err());
+        assert!(parse(&[0x9C, 0x80]).is_err());
+        assert!(parse(&[0x9C, 0x82, 0x01]).is_err());
+    }
+}
@@ -1,15 +1,15 @@
 // Integration tests for Device API endpoints
 use crate::api::AppState; // Import AppState
 use crate::infrastructure::config::{
-    Config, DatabaseConfig, HsmConfig, JwtConfig, LoggingConfig, RateLimitConfig, RedisConfig,
-    SecurityConfig, ServerConfig,
+    Config, DatabaseConfig, HsmConfig, JwtConfig, LoggingConfig, NotificationConfig,
+    RateLimitConfig, RedisConfig, SecurityConfig, ServerConfig, WebhookConfig, AlertingConfig, OutboxConfig, PaymentConfig, IdempotencyConfig, SettlementConfig, ReconciliationConfig,
 };
-use crate::models::DeviceStatus;
+use crate::models::{DeviceStatus, TenantContext};
 use axum::{
     body::Body,
     http::{Request, StatusCode},
     routing::{get, post},
-    Router,
+    Extension, Router,
 };
 use serde_json::json;
 use sqlx::SqlitePool;
@@ -24,7 +24,11 @@ mod device_api_tests {
         Config {
             server: ServerConfig { host: "0.0.0.0".to_string(), port: 8080 },
             database: DatabaseConfig { url: "sqlite::memory:".to_string(), max_connections: 5 },
-            redis: RedisConfig { url: "redis://localhost".to_string() },
+            redis: RedisConfig {
+                url: "redis://localhost".to_string(),
+                username: None,
+                password: None,
+            },
             jwt: JwtConfig {
                 secret: "test_secret_key_must_be_at_least_32_bytes_long".to_string(),
                 expiration_hours: 1,
@@ -35,9 +39,17 @@ mod device_api_tests {
                 api_key: "test".to_string(),
                 timeout_seconds: 10,
             },
-            security: SecurityConfig { bdk: "0123456789ABCDEFFEDCBA9876543210".to_string() },
+            security: SecurityConfig { bdk: "0123456789ABCDEFFEDCBA9876543210".to_string(), key_encryption_key: None, health_check_max_age_seconds: 300, super_admin: None },
             logging: LoggingConfig { level: "info".to_string(), format: "json".to_string() },
             rate_limit: RateLimitConfig { requests_per_second: 100, burst_size: 200 },
+            notification: NotificationConfig::default(),
+            webhook: WebhookConfig::default(),
+            alerting: AlertingConfig::default(),
+            outbox: OutboxConfig::default(),
+            payment: PaymentConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            settlement: SettlementConfig::default(),
+            reconciliation: ReconciliationConfig::default(),
         }
     }
 
@@ -50,71 +62,147 @@ mod device_api_tests {
 
         let threat_detection_service =
             std::sync::Arc::new(crate::services::ThreatDetectionService::new(
-                crate::repositories::ThreatRepository::new(pool.clone()),
-                crate::repositories::DeviceRepository::new(pool.clone()),
-                crate::repositories::HealthCheckRepository::new(pool.clone()),
-                crate::repositories::AuditLogRepository::new(pool.clone()),
+                crate::repositories::ThreatRepository::unscoped(pool.clone()),
+                crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                crate::repositories::HealthCheckRepository::unscoped(pool.clone()),
+                crate::repositories::AuditLogRepository::unscoped(pool.clone()),
             ));
 
+        let device_service = std::sync::Arc::new(crate::services::DeviceService::new(
+            crate::repositories::DeviceRepository::unscoped(pool.clone()),
+            crate::repositories::ThreatRepository::unscoped(pool.clone()),
+            crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+            crate::security::DukptKeyDerivation::new(vec![]),
+            None,
+        ));
+
+        let merchant_service = std::sync::Arc::new(crate::services::MerchantService::new(
+            crate::repositories::MerchantRepository::unscoped(pool.clone()),
+            crate::repositories::StoreRepository::unscoped(pool.clone()),
+            crate::repositories::DeviceRepository::unscoped(pool.clone()),
+            crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+        ));
+
+        let device_channel = crate::api::websocket::DeviceChannel::new();
+
         Router::new()
             .route("/api/devices", post(register_device))
             .route("/api/devices", get(list_devices))
             .route("/api/devices/:id", get(get_device))
             .route("/api/devices/:id/approve", post(approve_device))
+            .layer(Extension(TenantContext::default()))
             .with_state(std::sync::Arc::new(AppState {
                 config: std::sync::Arc::new(create_test_config()),
                 db_pool: pool.clone(),
                 redis_client: None,
                 hsm_client: None,
+                payment_router: std::sync::Arc::new(crate::infrastructure::PaymentRouter::default()),
                 ws_pool: crate::api::websocket::create_connection_pool(),
                 notification_service: std::sync::Arc::new(
                     crate::api::websocket::NotificationService::new(
                         crate::api::websocket::create_connection_pool(),
                     ),
                 ),
+                device_channel: device_channel.clone(),
                 jwt_service: jwt_service.clone(),
                 dukpt: std::sync::Arc::new(crate::security::DukptKeyDerivation::new(vec![])),
-                device_service: std::sync::Arc::new(crate::services::DeviceService::new(
-                    crate::repositories::DeviceRepository::new(pool.clone()),
-                    crate::repositories::AuditLogRepository::new(pool.clone()),
-                    crate::security::DukptKeyDerivation::new(vec![]),
-                    None,
-                )),
+                device_service: device_service.clone(),
                 key_management_service: std::sync::Arc::new(
                     crate::services::KeyManagementService::new(
-                        crate::repositories::DeviceRepository::new(pool.clone()),
-                        crate::repositories::AuditLogRepository::new(pool.clone()),
+                        crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                        crate::repositories::AuditLogRepository::unscoped(pool.clone()),
                         crate::security::DukptKeyDerivation::new(vec![]),
                         None,
                     ),
                 ),
                 transaction_service: std::sync::Arc::new(crate::services::TransactionService::new(
-                    crate::repositories::TransactionRepository::new(pool.clone()),
-                    crate::repositories::DeviceRepository::new(pool.clone()),
-                    crate::repositories::AuditLogRepository::new(pool.clone()),
+                    crate::repositories::TransactionRepository::unscoped(pool.clone()),
+                    crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                    crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+                    crate::repositories::HealthCheckRepository::unscoped(pool.clone()),
                     crate::security::DukptKeyDerivation::new(vec![]),
                     None,
                     transaction_token_service.clone(),
                 )),
                 audit_service: std::sync::Arc::new(crate::services::AuditService::new(
-                    crate::repositories::AuditLogRepository::new(pool.clone()),
+                    crate::repositories::AuditLogRepository::unscoped(pool.clone()),
                 )),
                 health_check_service: std::sync::Arc::new(
                     crate::services::HealthCheckService::new(
-                        crate::repositories::HealthCheckRepository::new(pool.clone()),
-                        crate::repositories::DeviceRepository::new(pool.clone()),
-                        crate::repositories::ThreatRepository::new(pool.clone()),
-                        crate::repositories::AuditLogRepository::new(pool.clone()),
+                        crate::repositories::HealthCheckRepository::unscoped(pool.clone()),
+                        crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                        crate::repositories::ThreatRepository::unscoped(pool.clone()),
+                        crate::repositories::AuditLogRepository::unscoped(pool.clone()),
                         (*threat_detection_service).clone(),
                     ),
                 ),
                 threat_detection_service,
                 version_service: std::sync::Arc::new(crate::services::VersionService::new(
-                    crate::repositories::VersionRepository::new(pool.clone()),
-                    crate::repositories::DeviceRepository::new(pool.clone()),
-                    crate::repositories::AuditLogRepository::new(pool.clone()),
+                    crate::repositories::VersionRepository::unscoped(pool.clone()),
+                    crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                    crate::repositories::AuditLogRepository::unscoped(pool.clone()),
                 )),
                 transaction_token_service,
+                kernel_service: std::sync::Arc::new(crate::services::KernelService::new(
+                    crate::repositories::KernelRepository::unscoped(pool.clone()),
+                    "uploads".to_string(),
+                )),
+                merchant_service: merchant_service.clone(),
+                tenant_service: std::sync::Arc::new(crate::services::TenantService::new(
+                    crate::repositories::TenantRepository::new(pool.clone()),
+                    crate::repositories::ApiKeyRepository::new(pool.clone()),
+                    crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+                )),
+                device_import_service: std::sync::Arc::new(
+                    crate::services::DeviceImportService::new(
+                        crate::repositories::DeviceImportRepository::unscoped(pool.clone()),
+                        crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                        crate::repositories::StoreRepository::unscoped(pool.clone()),
+                        crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+                        (*device_service).clone(),
+                        (*merchant_service).clone(),
+                    ),
+                ),
+                device_group_service: std::sync::Arc::new(
+                    crate::services::DeviceGroupService::new(
+                        crate::repositories::DeviceGroupRepository::unscoped(pool.clone()),
+                        crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                        crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+                    ),
+                ),
+                device_command_service: std::sync::Arc::new(
+                    crate::services::DeviceCommandService::new(
+                        crate::repositories::DeviceCommandRepository::unscoped(pool.clone()),
+                        crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                        crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+                        device_channel,
+                    ),
+                ),
+                webhook_service: std::sync::Arc::new(crate::services::WebhookService::new(
+                    crate::repositories::WebhookRepository::unscoped(pool.clone()),
+                    crate::repositories::MerchantRepository::unscoped(pool.clone()),
+                    crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+                )),
+                outbox_dispatcher: std::sync::Arc::new(crate::services::OutboxDispatcher::new(
+                    crate::repositories::OutboxRepository::new(pool.clone()),
+                    crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                    crate::repositories::ThreatRepository::unscoped(pool.clone()),
+                )),
+                idempotency_service: std::sync::Arc::new(crate::services::IdempotencyService::new(
+                    crate::repositories::IdempotencyRepository::new(pool.clone()),
+                )),
+                settlement_service: std::sync::Arc::new(crate::services::SettlementService::new(
+                    crate::repositories::SettlementRepository::unscoped(pool.clone()),
+                    crate::repositories::TransactionRepository::unscoped(pool.clone()),
+                    crate::repositories::DeviceRepository::unscoped(pool.clone()),
+                    crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+                )),
+                reconciliation_service: std::sync::Arc::new(crate::services::ReconciliationService::new(
+                    crate::repositories::ReconciliationRepository::unscoped(pool.clone()),
+                    crate::repositories::TransactionRepository::unscoped(pool.clone()),
+                    crate::repositories::AuditLogRepository::unscoped(pool.clone()),
+                )),
+                alert_dispatcher: None,
             }))
     }
 
@@ -1 +1,3 @@
 pub mod device_api_test;
+pub mod websocket_test;
+pub mod notification_fanout_test;
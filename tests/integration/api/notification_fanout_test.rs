@@ -0,0 +1,222 @@
+// Integration tests for cross-node notification fan-out over Redis pub/sub
+#[cfg(test)]
+mod notification_fanout_tests {
+    use crate::api::websocket::{
+        create_connection_pool, ClientConnection, ConnectionInfo, Notification, NotificationFanout,
+        NotificationService, Subscription,
+    };
+    use crate::infrastructure::redis::{RedisClient, RedisConfig};
+    use crate::models::{TenantContext, DEFAULT_TENANT_ID};
+    use axum::extract::ws::Message;
+    use serde_json::Value;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::{tcp::OwnedReadHalf, TcpListener, TcpStream},
+        sync::mpsc,
+    };
+
+    type Subscribers = Arc<Mutex<HashMap<String, Vec<mpsc::UnboundedSender<Vec<u8>>>>>>;
+
+    /// 本地Redis替身：支持连接初始化命令、SUBSCRIBE 与 PUBLISH
+    async fn start_redis_stub() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let subscribers = Subscribers::default();
+
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve_redis_connection(socket, subscribers.clone()));
+            }
+        });
+
+        url
+    }
+
+    async fn serve_redis_connection(socket: TcpStream, subscribers: Subscribers) {
+        let (reader, mut writer) = socket.into_split();
+        let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        tokio::spawn(async move {
+            while let Some(bytes) = rx.recv().await {
+                if writer.write_all(&bytes).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut reader = BufReader::new(reader);
+        while let Some(args) = read_command(&mut reader).await {
+            let reply = match args[0].to_uppercase().as_str() {
+                "SUBSCRIBE" => {
+                    let mut reply = Vec::new();
+                    for (i, channel) in args[1..].iter().enumerate() {
+                        subscribers
+                            .lock()
+                            .unwrap()
+                            .entry(channel.clone())
+                            .or_default()
+                            .push(tx.clone());
+                        reply.extend(bulk_array(&["subscribe", channel]));
+                        reply.extend(format!(":{}\r\n", i + 1).into_bytes());
+                    }
+                    reply
+                },
+                "PUBLISH" => {
+                    let message = bulk_array(&["message", &args[1], &args[2]]);
+                    let mut subscribers = subscribers.lock().unwrap();
+                    let channel = subscribers.entry(args[1].clone()).or_default();
+                    channel.retain(|subscriber| subscriber.send(message.clone()).is_ok());
+                    format!(":{}\r\n", channel.len()).into_bytes()
+                },
+                "PING" => b"+PONG\r\n".to_vec(),
+                _ => b"+OK\r\n".to_vec(),
+            };
+
+            if tx.send(reply).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// 读取一条RESP命令（多行批量字符串数组）
+    async fn read_command(reader: &mut BufReader<OwnedReadHalf>) -> Option<Vec<String>> {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let count: usize = line.trim().strip_prefix('*')?.parse().ok()?;
+
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            line.clear();
+            reader.read_line(&mut line).await.ok()?;
+            let len: usize = line.trim().strip_prefix('$')?.parse().ok()?;
+
+            let mut buf = vec![0u8; len + 2];
+            reader.read_exact(&mut buf).await.ok()?;
+            buf.truncate(len);
+            args.push(String::from_utf8(buf).ok()?);
+        }
+
+        Some(args)
+    }
+
+    fn bulk_array(items: &[&str]) -> Vec<u8> {
+        let mut out = format!("*{}\r\n", items.len());
+        for item in items {
+            out.push_str(&format!("${}\r\n{}\r\n", item.len(), item));
+        }
+        out.into_bytes()
+    }
+
+    /// 启动一个节点：独立的连接池和通知服务，通过Redis互通
+    async fn start_node(redis_url: &str, node_id: &str) -> Arc<NotificationService> {
+        let redis = RedisClient::new(&RedisConfig {
+            url: redis_url.to_string(),
+            username: None,
+            password: None,
+        })
+        .await
+        .unwrap();
+
+        let pool = create_connection_pool();
+        let fanout = Arc::new(NotificationFanout::new(redis, node_id));
+        fanout.clone().subscribe(pool.clone()).await.unwrap();
+
+        Arc::new(NotificationService::new(pool).with_fanout(fanout))
+    }
+
+    /// 在节点上注册一个管理端连接，返回其下行消息
+    async fn connect(
+        service: &NotificationService,
+        tenant: TenantContext,
+    ) -> mpsc::UnboundedReceiver<Message> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Instant::now();
+        let connection = ClientConnection {
+            info: ConnectionInfo {
+                id: id.clone(),
+                user_id: Some("admin".to_string()),
+                connected_at: now,
+                last_ping: now,
+            },
+            tenant,
+            subscription: Subscription::default(),
+            replayed_seq: 0,
+            tx,
+        };
+        service.register_connection(id, connection, None).await;
+        rx
+    }
+
+    async fn recv(rx: &mut mpsc::UnboundedReceiver<Message>) -> Option<Value> {
+        match tokio::time::timeout(Duration::from_millis(200), rx.recv()).await {
+            Ok(Some(Message::Text(text))) => serde_json::from_str(&text).ok(),
+            _ => None,
+        }
+    }
+
+    fn threat(device_id: &str) -> Notification {
+        Notification::threat_alert(
+            device_id.to_string(),
+            format!("threat-{}", device_id),
+            "ROOT_DETECTED".to_string(),
+            "HIGH".to_string(),
+            "threat detected".to_string(),
+        )
+        .with_tenant_id(DEFAULT_TENANT_ID.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_notification_reaches_other_node_once() {
+        let redis_url = start_redis_stub().await;
+        let node_a = start_node(&redis_url, "node-a").await;
+        let node_b = start_node(&redis_url, "node-b").await;
+
+        let mut console_a = connect(&node_a, TenantContext::default()).await;
+        let mut console_b = connect(&node_b, TenantContext::default()).await;
+
+        node_a.send_notification(threat("d1")).await;
+
+        assert_eq!(recv(&mut console_a).await.unwrap()["device_id"], "d1");
+        assert_eq!(recv(&mut console_b).await.unwrap()["device_id"], "d1");
+
+        // 发布节点忽略自己的消息，不会重复推送
+        assert!(recv(&mut console_a).await.is_none());
+        assert!(recv(&mut console_b).await.is_none());
+
+        node_b.send_notification(threat("d2")).await;
+        assert_eq!(recv(&mut console_a).await.unwrap()["device_id"], "d2");
+        assert_eq!(recv(&mut console_b).await.unwrap()["device_id"], "d2");
+        assert!(recv(&mut console_a).await.is_none());
+        assert!(recv(&mut console_b).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_remote_delivery_respects_tenant() {
+        let redis_url = start_redis_stub().await;
+        let node_a = start_node(&redis_url, "node-a").await;
+        let node_b = start_node(&redis_url, "node-b").await;
+
+        let mut tenant_a = connect(&node_b, TenantContext::new("acq-a")).await;
+        let mut tenant_b = connect(&node_b, TenantContext::new("acq-b")).await;
+
+        node_a.send_notification(threat("d1").with_tenant_id("acq-a".to_string())).await;
+
+        assert_eq!(recv(&mut tenant_a).await.unwrap()["device_id"], "d1");
+        assert!(recv(&mut tenant_b).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_in_process_mode_without_redis() {
+        let service = NotificationService::new(create_connection_pool());
+        let mut console = connect(&service, TenantContext::default()).await;
+
+        service.send_notification(threat("d1")).await;
+
+        assert_eq!(recv(&mut console).await.unwrap()["device_id"], "d1");
+    }
+}
@@ -0,0 +1,282 @@
+// Integration tests for admin WebSocket notification subscriptions
+#[cfg(test)]
+mod websocket_tests {
+    use super::setup_test_db;
+    use crate::api::websocket::{
+        create_connection_pool, serve_connection, Notification, NotificationService,
+    };
+    use crate::models::{TenantContext, DEFAULT_TENANT_ID, MAX_STORED_NOTIFICATIONS};
+    use crate::repositories::NotificationRepository;
+    use axum::extract::ws::Message;
+    use futures::{
+        channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+        StreamExt,
+    };
+    use serde_json::{json, Value};
+    use std::{sync::Arc, time::Duration};
+
+    /// 模拟客户端：上行发送原始消息，下行接收服务端推送
+    struct TestClient {
+        tx: UnboundedSender<Result<Message, axum::Error>>,
+        rx: UnboundedReceiver<Message>,
+    }
+
+    impl TestClient {
+        async fn connect(service: &Arc<NotificationService>, tenant: TenantContext) -> Self {
+            Self::resume(service, tenant, None).await
+        }
+
+        async fn resume(
+            service: &Arc<NotificationService>,
+            tenant: TenantContext,
+            last_seq: Option<i64>,
+        ) -> Self {
+            let (tx, server_rx) = unbounded();
+            let (server_tx, rx) = unbounded();
+            tokio::spawn(serve_connection(
+                service.clone(),
+                server_tx,
+                server_rx,
+                "admin".to_string(),
+                tenant,
+                last_seq,
+            ));
+
+            let mut client = Self { tx, rx };
+            assert_eq!(client.recv().await.unwrap()["type"], "connected");
+            client
+        }
+
+        fn send(&self, message: Value) {
+            self.tx.unbounded_send(Ok(Message::Text(message.to_string()))).unwrap();
+        }
+
+        async fn subscribe(&mut self, subscription: Value) {
+            let mut message = subscription;
+            message["type"] = json!("subscribe");
+            self.send(message);
+            assert_eq!(self.recv().await.unwrap()["type"], "subscribed");
+        }
+
+        async fn recv(&mut self) -> Option<Value> {
+            match tokio::time::timeout(Duration::from_millis(200), self.rx.next()).await {
+                Ok(Some(Message::Text(text))) => serde_json::from_str(&text).ok(),
+                _ => None,
+            }
+        }
+    }
+
+    fn threat(device_id: &str, severity: &str) -> Notification {
+        Notification::threat_alert(
+            device_id.to_string(),
+            format!("threat-{}", device_id),
+            "ROOT_DETECTED".to_string(),
+            severity.to_string(),
+            "threat detected".to_string(),
+        )
+        .with_tenant_id(DEFAULT_TENANT_ID.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_filtered_delivery_per_connection() {
+        let service = Arc::new(NotificationService::new(create_connection_pool()));
+
+        let mut all = TestClient::connect(&service, TenantContext::default()).await;
+        let mut high_threats = TestClient::connect(&service, TenantContext::default()).await;
+        high_threats
+            .subscribe(json!({"types": ["threat_alert"], "min_severity": "HIGH"}))
+            .await;
+        let mut merchant = TestClient::connect(&service, TenantContext::default()).await;
+        merchant.subscribe(json!({"merchant_ids": ["m1"], "device_ids": ["d9"]})).await;
+
+        service
+            .send_notification(threat("d1", "LOW").with_merchant_id("m1".to_string()))
+            .await;
+        service.send_notification(threat("d2", "HIGH")).await;
+        service.send_notification(threat("d9", "MEDIUM")).await;
+
+        let received: Vec<Value> = [all.recv().await, all.recv().await, all.recv().await]
+            .into_iter()
+            .map(Option::unwrap)
+            .collect();
+        assert_eq!(received.len(), 3);
+
+        assert_eq!(high_threats.recv().await.unwrap()["device_id"], "d2");
+        assert!(high_threats.recv().await.is_none());
+
+        assert_eq!(merchant.recv().await.unwrap()["device_id"], "d1");
+        assert_eq!(merchant.recv().await.unwrap()["device_id"], "d9");
+        assert!(merchant.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_and_tenant_isolation() {
+        let service = Arc::new(NotificationService::new(create_connection_pool()));
+
+        let mut tenant_a = TestClient::connect(&service, TenantContext::new("acq-a")).await;
+        let mut tenant_b = TestClient::connect(&service, TenantContext::new("acq-b")).await;
+        let mut super_admin =
+            TestClient::connect(&service, TenantContext::cross_tenant("default")).await;
+
+        tenant_a.subscribe(json!({"device_ids": ["d1"]})).await;
+
+        let notification = threat("d2", "HIGH").with_tenant_id("acq-a".to_string());
+        service.send_notification(notification.clone()).await;
+        assert!(tenant_a.recv().await.is_none());
+        assert!(tenant_b.recv().await.is_none());
+        assert_eq!(super_admin.recv().await.unwrap()["device_id"], "d2");
+
+        // 取消订阅后恢复接收本租户全部通知
+        tenant_a.send(json!({"type": "unsubscribe"}));
+        assert_eq!(tenant_a.recv().await.unwrap()["type"], "subscribed");
+        service.send_notification(notification).await;
+        assert_eq!(tenant_a.recv().await.unwrap()["device_id"], "d2");
+        assert!(tenant_b.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_connection_removed_on_close() {
+        let service = Arc::new(NotificationService::new(create_connection_pool()));
+
+        let client = TestClient::connect(&service, TenantContext::default()).await;
+        assert_eq!(service.get_connection_count().await, 1);
+
+        client.tx.unbounded_send(Ok(Message::Close(None))).unwrap();
+        for _ in 0..20 {
+            if service.get_connection_count().await == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(service.get_connection_count().await, 0);
+    }
+
+    async fn stored_service() -> Arc<NotificationService> {
+        Arc::new(
+            NotificationService::new(create_connection_pool())
+                .with_store(NotificationRepository::unscoped(setup_test_db().await)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_notifications_persisted_with_sequence() {
+        let service = stored_service().await;
+        let mut client = TestClient::connect(&service, TenantContext::default()).await;
+
+        service.send_notification(threat("d1", "LOW")).await;
+        service.send_notification(threat("d2", "HIGH")).await;
+
+        let first = client.recv().await.unwrap()["seq"].as_i64().unwrap();
+        let second = client.recv().await.unwrap()["seq"].as_i64().unwrap();
+        assert!(second > first);
+    }
+
+    #[tokio::test]
+    async fn test_resume_replays_gap_without_duplicates() {
+        let service = stored_service().await;
+
+        service.send_notification(threat("d1", "LOW")).await;
+        service
+            .send_notification(threat("d2", "LOW").with_tenant_id("acq-b".to_string()))
+            .await;
+        service
+            .send_notification(threat("d3", "LOW").with_tenant_id("acq-a".to_string()))
+            .await;
+
+        let history = service
+            .list_history(&TenantContext::default(), "admin", None, None, false, 10)
+            .await
+            .unwrap();
+        let first_seq = history.notifications.last().unwrap().seq;
+
+        let mut client =
+            TestClient::resume(&service, TenantContext::new("acq-a"), Some(first_seq)).await;
+
+        // 仅补发本租户可见且在 last_seq 之后的通知
+        assert_eq!(client.recv().await.unwrap()["device_id"], "d3");
+        let complete = client.recv().await.unwrap();
+        assert_eq!(complete["type"], "replay_complete");
+        assert_eq!(complete["truncated"], false);
+
+        service
+            .send_notification(threat("d4", "LOW").with_tenant_id("acq-a".to_string()))
+            .await;
+        assert_eq!(client.recv().await.unwrap()["device_id"], "d4");
+
+        // 其他租户和未标记租户的通知不会发给租户连接
+        service.send_notification(threat("d5", "LOW")).await;
+        let mut system = threat("d6", "LOW");
+        system.tenant_id = None;
+        service.send_notification(system).await;
+        assert!(client.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_history_paging_and_read_state() {
+        let service = stored_service().await;
+        let tenant = TenantContext::default();
+
+        for i in 0..5 {
+            service.send_notification(threat(&format!("d{}", i), "LOW")).await;
+        }
+
+        let page = service.list_history(&tenant, "alice", None, None, false, 3).await.unwrap();
+        assert_eq!(page.notifications.len(), 3);
+        assert_eq!(page.unread_count, 5);
+        assert_eq!(page.notifications[0].device_id.as_deref(), Some("d4"));
+
+        let next = service
+            .list_history(&tenant, "alice", page.next_before_seq, None, false, 3)
+            .await
+            .unwrap();
+        assert_eq!(next.notifications.len(), 2);
+        assert!(next.next_before_seq.is_none());
+
+        // 已读状态按用户隔离
+        service.acknowledge(&tenant, "alice", page.notifications[0].seq).await.unwrap();
+        let acked = service
+            .acknowledge_up_to(&tenant, "alice", next.notifications[0].seq)
+            .await
+            .unwrap();
+        assert_eq!(acked, 2);
+
+        let unread = service.list_history(&tenant, "alice", None, None, true, 10).await.unwrap();
+        assert_eq!(unread.unread_count, 2);
+        assert_eq!(unread.notifications.len(), 2);
+        assert!(unread.notifications.iter().all(|n| !n.read));
+
+        let bob = service.list_history(&tenant, "bob", None, None, false, 10).await.unwrap();
+        assert_eq!(bob.unread_count, 5);
+
+        assert!(service.acknowledge(&tenant, "alice", 9999).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_store_is_bounded() {
+        let repo = NotificationRepository::unscoped(setup_test_db().await);
+        let record = threat("d1", "LOW").to_record();
+
+        let mut last = 0;
+        for _ in 0..MAX_STORED_NOTIFICATIONS + 5 {
+            last = repo.create(&record).await.unwrap();
+        }
+
+        let oldest = repo.list_since(0, 1).await.unwrap();
+        assert_eq!(oldest[0].seq, last - MAX_STORED_NOTIFICATIONS + 1);
+    }
+}
+
+async fn setup_test_db() -> sqlx::SqlitePool {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("Failed to create test database");
+
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    pool
+}
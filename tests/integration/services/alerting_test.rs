@@ -0,0 +1,406 @@
+// Integration tests for external alert channels, routing and escalation
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod alerting_tests {
+    use super::*;
+    use crate::api::websocket::{Notification, NotificationSeverity, NotificationType};
+    use crate::infrastructure::config::{
+        AlertRoute, AlertTarget, AlertingConfig, EmailChannelConfig, EscalationPolicy,
+        HttpChannelConfig, QuietHours,
+    };
+    use crate::repositories::NotificationRepository;
+    use crate::security::crypto;
+    use crate::services::{AlertDispatcher, AlertMessage, EmailNotifier, HttpNotifier, Notifier};
+    use crate::utils::error::AppError;
+    use chrono::{TimeZone, Utc};
+    use futures::future::BoxFuture;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        sync::oneshot,
+    };
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    /// 记录发送内容的测试渠道
+    #[derive(Default)]
+    struct RecordingNotifier {
+        sent: Mutex<Vec<(Vec<String>, AlertMessage)>>,
+    }
+
+    impl RecordingNotifier {
+        fn sent(&self) -> Vec<(Vec<String>, AlertMessage)> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn send<'a>(
+            &'a self,
+            recipients: &'a [String],
+            alert: &'a AlertMessage,
+        ) -> BoxFuture<'a, Result<(), AppError>> {
+            Box::pin(async move {
+                self.sent.lock().unwrap().push((recipients.to_vec(), alert.clone()));
+                Ok(())
+            })
+        }
+    }
+
+    /// 接受单个会话的SMTP服务器，返回收到的命令和邮件内容
+    async fn smtp_sink() -> (u16, oneshot::Receiver<(Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            let mut commands = Vec::new();
+            let mut data = String::new();
+
+            writer.write_all(b"220 test ESMTP\r\n").await.unwrap();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                commands.push(line.clone());
+
+                let reply: &[u8] = if line.starts_with("EHLO") {
+                    b"250-test\r\n250 OK\r\n"
+                } else if line == "DATA" {
+                    writer.write_all(b"354 go ahead\r\n").await.unwrap();
+                    loop {
+                        let mut body = String::new();
+                        reader.read_line(&mut body).await.unwrap();
+                        if body.trim_end() == "." {
+                            break;
+                        }
+                        data.push_str(&body);
+                    }
+                    b"250 queued\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+
+            let _ = tx.send((commands, data));
+        });
+
+        (port, rx)
+    }
+
+    fn high_threat() -> Notification {
+        Notification::threat_alert(
+            "device-1".to_string(),
+            "threat-1".to_string(),
+            "ROOT_DETECTION".to_string(),
+            "HIGH".to_string(),
+            "检测到 \"root\" 权限".to_string(),
+        )
+    }
+
+    fn medium_key_warning() -> Notification {
+        Notification::key_warning("device-1".to_string(), 5, 100, "密钥即将耗尽".to_string())
+    }
+
+    fn target(channel: &str, recipients: &[&str]) -> AlertTarget {
+        AlertTarget {
+            channel: channel.to_string(),
+            recipients: recipients.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    fn route(
+        name: &str,
+        min_severity: NotificationSeverity,
+        notification_types: Vec<NotificationType>,
+        targets: Vec<AlertTarget>,
+    ) -> AlertRoute {
+        AlertRoute {
+            name: name.to_string(),
+            min_severity,
+            notification_types,
+            targets,
+            escalation: None,
+        }
+    }
+
+    async fn persisted(
+        store: &NotificationRepository,
+        mut notification: Notification,
+    ) -> Notification {
+        notification.seq = Some(store.create(&notification.to_record()).await.unwrap());
+        notification
+    }
+
+    #[tokio::test]
+    async fn test_email_channel_sends_via_smtp() {
+        let (port, received) = smtp_sink().await;
+        let notifier = EmailNotifier::new(&EmailChannelConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: None,
+            from: "alerts@example.com".to_string(),
+            subject_template: "{{escalation}}[{{severity}}] {{title}}".to_string(),
+            body_template: "{{message}} ({{device_id}})".to_string(),
+            timeout_seconds: 5,
+        });
+
+        let alert = AlertMessage::new(high_threat());
+        notifier.send(&["ops@example.com".to_string()], &alert).await.unwrap();
+
+        let (commands, data) = received.await.unwrap();
+        assert!(commands.contains(&"MAIL FROM:<alerts@example.com>".to_string()));
+        assert!(commands.contains(&"RCPT TO:<ops@example.com>".to_string()));
+
+        let subject = crypto::base64_encode("[HIGH] 威胁检测告警".as_bytes());
+        assert!(data.contains(&format!("Subject: =?UTF-8?B?{}?=", subject)));
+
+        let body: String = data.split("\r\n\r\n").nth(1).unwrap().split_whitespace().collect();
+        let body = String::from_utf8(crypto::base64_decode(&body).unwrap()).unwrap();
+        assert_eq!(body, "检测到 \"root\" 权限 (device-1)");
+    }
+
+    #[tokio::test]
+    async fn test_http_channel_sends_per_recipient_with_json_escaping() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/sms"))
+            .and(header("x-api-key", "secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let notifier = HttpNotifier::new(&HttpChannelConfig {
+            name: "sms".to_string(),
+            url: format!("{}/sms", server.uri()),
+            headers: HashMap::from([("x-api-key".to_string(), "secret".to_string())]),
+            body_template: r#"{"to":"{{recipient}}","text":"[{{severity}}] {{message}}"}"#
+                .to_string(),
+            content_type: "application/json".to_string(),
+            timeout_seconds: 5,
+        });
+
+        let recipients = vec!["+8613800000001".to_string(), "+8613800000002".to_string()];
+        notifier.send(&recipients, &AlertMessage::new(high_threat())).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let bodies: Vec<serde_json::Value> =
+            requests.iter().map(|r| serde_json::from_slice(&r.body).unwrap()).collect();
+        assert_eq!(bodies[0]["to"], "+8613800000001");
+        assert_eq!(bodies[1]["to"], "+8613800000002");
+        assert_eq!(bodies[0]["text"], "[HIGH] 检测到 \"root\" 权限");
+    }
+
+    #[tokio::test]
+    async fn test_http_channel_error_status_is_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let notifier = HttpNotifier::new(&HttpChannelConfig {
+            name: "im".to_string(),
+            url: server.uri(),
+            headers: HashMap::new(),
+            body_template: r#"{"text":"{{title}}"}"#.to_string(),
+            content_type: "application/json".to_string(),
+            timeout_seconds: 5,
+        });
+
+        let result = notifier.send(&[], &AlertMessage::new(high_threat())).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_routes_match_severity_and_type() {
+        let pool = setup_test_db().await;
+        let sms = Arc::new(RecordingNotifier::default());
+        let im = Arc::new(RecordingNotifier::default());
+
+        let dispatcher = AlertDispatcher::new(NotificationRepository::unscoped(pool.clone()))
+            .with_channel("sms", sms.clone())
+            .with_channel("im", im.clone())
+            .with_route(route(
+                "critical-threats",
+                NotificationSeverity::High,
+                vec![NotificationType::ThreatAlert],
+                vec![target("sms", &["+8613800000001"])],
+            ))
+            .with_route(route(
+                "everything",
+                NotificationSeverity::Medium,
+                vec![],
+                vec![target("im", &[])],
+            ));
+
+        assert_eq!(dispatcher.dispatch(&medium_key_warning()).await, 1);
+        assert!(sms.sent().is_empty());
+        assert_eq!(im.sent().len(), 1);
+
+        assert_eq!(dispatcher.dispatch(&high_threat()).await, 2);
+        assert_eq!(sms.sent()[0].0, vec!["+8613800000001".to_string()]);
+        assert_eq!(im.sent().len(), 2);
+
+        // 低于所有规则的最低级别
+        let info = Notification::device_status_change(
+            "device-1".to_string(),
+            "PENDING".to_string(),
+            "ACTIVE".to_string(),
+            None,
+        );
+        assert_eq!(dispatcher.dispatch(&info).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_quiet_hours_suppress_below_bypass_severity() {
+        let pool = setup_test_db().await;
+        let im = Arc::new(RecordingNotifier::default());
+
+        let dispatcher = AlertDispatcher::new(NotificationRepository::unscoped(pool.clone()))
+            .with_channel("im", im.clone())
+            .with_route(route("all", NotificationSeverity::Low, vec![], vec![target("im", &[])]))
+            .with_quiet_hours(QuietHours {
+                start: "22:00".to_string(),
+                end: "07:00".to_string(),
+                utc_offset_minutes: 480,
+                bypass_severity: NotificationSeverity::High,
+            });
+
+        // 北京时间 23:30
+        let night = Utc.with_ymd_and_hms(2024, 12, 18, 15, 30, 0).unwrap();
+        assert_eq!(dispatcher.dispatch_at(&medium_key_warning(), night).await, 0);
+        assert_eq!(dispatcher.dispatch_at(&high_threat(), night).await, 1);
+
+        // 北京时间 10:00
+        let day = Utc.with_ymd_and_hms(2024, 12, 18, 2, 0, 0).unwrap();
+        assert_eq!(dispatcher.dispatch_at(&medium_key_warning(), day).await, 1);
+    }
+
+    fn escalating_dispatcher(
+        store: NotificationRepository,
+        oncall: Arc<RecordingNotifier>,
+        manager: Arc<RecordingNotifier>,
+    ) -> AlertDispatcher {
+        let mut escalating = route(
+            "threats",
+            NotificationSeverity::High,
+            vec![],
+            vec![target("oncall", &["oncall@example.com"])],
+        );
+        escalating.escalation = Some(EscalationPolicy {
+            after_seconds: 60,
+            targets: vec![target("manager", &["manager@example.com"])],
+        });
+
+        AlertDispatcher::new(store)
+            .with_channel("oncall", oncall)
+            .with_channel("manager", manager)
+            .with_route(escalating)
+    }
+
+    #[tokio::test]
+    async fn test_unacknowledged_alert_is_escalated_once() {
+        let pool = setup_test_db().await;
+        let store = NotificationRepository::unscoped(pool.clone());
+        let oncall = Arc::new(RecordingNotifier::default());
+        let manager = Arc::new(RecordingNotifier::default());
+        let dispatcher = escalating_dispatcher(store.clone(), oncall.clone(), manager.clone());
+
+        let notification = persisted(&store, high_threat()).await;
+        let sent_at = Utc::now() - chrono::Duration::minutes(5);
+        dispatcher.dispatch_at(&notification, sent_at).await;
+        assert_eq!(oncall.sent().len(), 1);
+
+        assert_eq!(dispatcher.process_escalations().await.unwrap(), 1);
+        let escalated = manager.sent();
+        assert_eq!(escalated.len(), 1);
+        assert!(escalated[0].1.escalated);
+        assert_eq!(escalated[0].1.notification.seq, notification.seq);
+
+        // 已升级的不再重复发送
+        assert_eq!(dispatcher.process_escalations().await.unwrap(), 0);
+        assert_eq!(manager.sent().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_acknowledged_alert_is_not_escalated() {
+        let pool = setup_test_db().await;
+        let store = NotificationRepository::unscoped(pool.clone());
+        let oncall = Arc::new(RecordingNotifier::default());
+        let manager = Arc::new(RecordingNotifier::default());
+        let dispatcher = escalating_dispatcher(store.clone(), oncall.clone(), manager.clone());
+
+        let notification = persisted(&store, high_threat()).await;
+        dispatcher
+            .dispatch_at(&notification, Utc::now() - chrono::Duration::minutes(5))
+            .await;
+        store.mark_read("admin", notification.seq.unwrap()).await.unwrap();
+
+        assert_eq!(dispatcher.process_escalations().await.unwrap(), 0);
+        assert!(manager.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_escalation_not_due_yet() {
+        let pool = setup_test_db().await;
+        let store = NotificationRepository::unscoped(pool.clone());
+        let oncall = Arc::new(RecordingNotifier::default());
+        let manager = Arc::new(RecordingNotifier::default());
+        let dispatcher = escalating_dispatcher(store.clone(), oncall.clone(), manager.clone());
+
+        let notification = persisted(&store, high_threat()).await;
+        dispatcher.dispatch(&notification).await;
+
+        assert_eq!(dispatcher.process_escalations().await.unwrap(), 0);
+        assert!(manager.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_from_config_rejects_unknown_channel() {
+        let pool = setup_test_db().await;
+        let config = AlertingConfig {
+            enabled: true,
+            routes: vec![route(
+                "threats",
+                NotificationSeverity::High,
+                vec![],
+                vec![target("pager", &[])],
+            )],
+            ..AlertingConfig::default()
+        };
+
+        let result = AlertDispatcher::from_config(&config, NotificationRepository::unscoped(pool));
+        assert!(result.is_err());
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
@@ -0,0 +1,389 @@
+// Integration tests for the remote device command channel
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod device_command_tests {
+    use super::*;
+    use crate::api::websocket::DeviceChannel;
+    use crate::dto::{CreateDeviceCommandRequest, DeviceCredentials};
+    use crate::models::{CommandType, Device, DeviceMode, DeviceStatus, TeeType, TenantContext};
+    use crate::repositories::{
+        AuditLogRepository, DeviceCommandRepository, DeviceRepository, ThreatRepository,
+    };
+    use crate::security::{base64_encode, sha256_hash_hex, DukptKeyDerivation};
+    use crate::services::{DeviceCommandService, DeviceService};
+    use crate::utils::error::AppError;
+    use axum::extract::ws::Message;
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+    };
+    use tokio::sync::mpsc;
+
+    fn create_service(pool: &SqlitePool, channel: &DeviceChannel) -> DeviceCommandService {
+        DeviceCommandService::new(
+            DeviceCommandRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            channel.clone(),
+        )
+    }
+
+    async fn create_device(pool: &SqlitePool, imei: &str, status: Option<DeviceStatus>) -> String {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0.1".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        if let Some(status) = status {
+            repo.update_status(&device.id, DeviceStatus::Pending, status, "admin", None)
+                .await
+                .unwrap();
+        }
+
+        device.id
+    }
+
+    fn command_request(command_type: CommandType) -> CreateDeviceCommandRequest {
+        CreateDeviceCommandRequest {
+            command_type,
+            payload: None,
+            timeout_seconds: Some(60),
+            max_attempts: Some(2),
+        }
+    }
+
+    async fn expire_deadline(pool: &SqlitePool, command_id: &str) {
+        sqlx::query("UPDATE device_commands SET deadline_at = ? WHERE id = ?")
+            .bind((chrono::Utc::now() - chrono::Duration::seconds(1)).to_rfc3339())
+            .bind(command_id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_poll_ack_and_result() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool, &DeviceChannel::new());
+        let device_id = create_device(&pool, "351234567890001", Some(DeviceStatus::Active)).await;
+
+        let command = service
+            .create_command(&device_id, command_request(CommandType::ForceHealthCheck), "admin")
+            .await
+            .unwrap();
+        assert_eq!(command.status, "PENDING");
+
+        let polled = service.poll_commands(&device_id).await.unwrap();
+        assert_eq!(polled.len(), 1);
+        assert_eq!(polled[0].status, "DELIVERED");
+        assert_eq!(polled[0].attempts, 1);
+        assert!(service.poll_commands(&device_id).await.unwrap().is_empty());
+
+        // 其他设备不能回执该指令
+        assert!(matches!(
+            service.acknowledge("other-device", &command.id).await,
+            Err(AppError::NotFound(_))
+        ));
+
+        service.acknowledge(&device_id, &command.id).await.unwrap();
+        let done = service
+            .report_result(
+                &device_id,
+                &command.id,
+                true,
+                Some(serde_json::json!({"security_score": 90})),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(done.status, "SUCCEEDED");
+        assert_eq!(done.result.unwrap()["security_score"], 90);
+
+        // 终态指令不能重复上报或取消
+        assert!(matches!(
+            service.report_result(&device_id, &command.id, false, None, None).await,
+            Err(AppError::BadRequest(_))
+        ));
+        assert!(matches!(
+            service.cancel_command(&command.id, "admin").await,
+            Err(AppError::BadRequest(_))
+        ));
+
+        let operations: Vec<String> = AuditLogRepository::unscoped(pool.clone())
+            .list_by_device(&device_id, 20, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|log| log.operation)
+            .collect();
+        for op in [
+            "DEVICE_COMMAND_CREATE",
+            "DEVICE_COMMAND_DELIVER",
+            "DEVICE_COMMAND_ACK",
+            "DEVICE_COMMAND_RESULT",
+        ] {
+            assert!(operations.iter().any(|o| o == op), "missing audit {}", op);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_websocket_delivery() {
+        let pool = setup_test_db().await;
+        let channel = DeviceChannel::new();
+        let service = create_service(&pool, &channel);
+        let device_id = create_device(&pool, "351234567890002", Some(DeviceStatus::Active)).await;
+
+        // 离线时创建的指令在连接后补发
+        let queued = service
+            .create_command(&device_id, command_request(CommandType::Lock), "admin")
+            .await
+            .unwrap();
+        assert_eq!(queued.status, "PENDING");
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        channel.register(&device_id, tx).await;
+        assert_eq!(service.deliver_pending(&device_id).await.unwrap(), 1);
+
+        let Some(Message::Text(text)) = rx.recv().await else {
+            panic!("expected message")
+        };
+        let message: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(message["command_id"], queued.id);
+        assert_eq!(message["command_type"], "LOCK");
+
+        // 在线时创建的指令立即下发
+        let mut request = command_request(CommandType::UpdateConfig);
+        request.payload = Some(serde_json::json!({"log_level": "debug"}));
+        let pushed = service.create_command(&device_id, request, "admin").await.unwrap();
+        assert_eq!(pushed.status, "DELIVERED");
+
+        let Some(Message::Text(text)) = rx.recv().await else {
+            panic!("expected message")
+        };
+        let message: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(message["payload"]["log_level"], "debug");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_retry_then_timed_out() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool, &DeviceChannel::new());
+        let device_id = create_device(&pool, "351234567890003", Some(DeviceStatus::Active)).await;
+
+        let command = service
+            .create_command(&device_id, command_request(CommandType::UploadLogs), "admin")
+            .await
+            .unwrap();
+
+        // 第一次投递超时后重新排队
+        service.poll_commands(&device_id).await.unwrap();
+        expire_deadline(&pool, &command.id).await;
+        assert_eq!(service.process_timeouts().await.unwrap(), 1);
+        assert_eq!(service.get_command(&command.id).await.unwrap().status, "PENDING");
+
+        // 达到最大投递次数后标记为超时
+        let polled = service.poll_commands(&device_id).await.unwrap();
+        assert_eq!(polled[0].attempts, 2);
+        expire_deadline(&pool, &command.id).await;
+        assert_eq!(service.process_timeouts().await.unwrap(), 1);
+
+        let command = service.get_command(&command.id).await.unwrap();
+        assert_eq!(command.status, "TIMED_OUT");
+        assert!(service.poll_commands(&device_id).await.unwrap().is_empty());
+        assert_eq!(service.process_timeouts().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_validation() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool, &DeviceChannel::new());
+        let device_id = create_device(&pool, "351234567890004", Some(DeviceStatus::Active)).await;
+        let pending_id = create_device(&pool, "351234567890005", None).await;
+
+        assert!(matches!(
+            service
+                .create_command(&pending_id, command_request(CommandType::Lock), "admin")
+                .await,
+            Err(AppError::InvalidDeviceStatus)
+        ));
+
+        // UPDATE_CONFIG需要配置内容
+        assert!(matches!(
+            service
+                .create_command(&device_id, command_request(CommandType::UpdateConfig), "admin")
+                .await,
+            Err(AppError::Validation(_))
+        ));
+
+        let command = service
+            .create_command(&device_id, command_request(CommandType::RebootSdk), "admin")
+            .await
+            .unwrap();
+        let cancelled = service.cancel_command(&command.id, "admin").await.unwrap();
+        assert_eq!(cancelled.status, "CANCELLED");
+        assert!(service.poll_commands(&device_id).await.unwrap().is_empty());
+
+        let list = service.list_commands(&device_id, 20, 0).await.unwrap();
+        assert_eq!(list.total, 1);
+
+        // 其他租户不可见
+        let other = service.for_tenant(&TenantContext::new("acq-b"));
+        assert!(matches!(other.get_command(&command.id).await, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_device_signature_authentication() {
+        let pool = setup_test_db().await;
+        let device_service = DeviceService::new(
+            DeviceRepository::unscoped(pool.clone()),
+            ThreatRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(vec![]),
+            None,
+        );
+
+        // 使用P-256密钥注册设备
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+                .unwrap();
+        let mut spki = hex::decode("3059301306072a8648ce3d020106082a8648ce3d030107034200").unwrap();
+        spki.extend_from_slice(key_pair.public_key().as_ref());
+
+        let device = Device::new(
+            "351234567890006".to_string(),
+            "V2PRO".to_string(),
+            "12.0.1".to_string(),
+            TeeType::TrustZone,
+            base64_encode(&spki).into_bytes(),
+            DeviceMode::FullPos,
+            true,
+        );
+        DeviceRepository::unscoped(pool.clone()).create(&device).await.unwrap();
+
+        let path = format!("/api/v1/devices/{}/commands/poll", device.id);
+        let credentials = |timestamp: i64, nonce: &str| {
+            let mut credentials = DeviceCredentials {
+                timestamp,
+                nonce: nonce.to_string(),
+                signature: String::new(),
+                method: "GET".to_string(),
+                path: path.clone(),
+                body_sha256: sha256_hash_hex(b""),
+            };
+            let message = credentials.signing_message(&device.id);
+            credentials.signature =
+                base64_encode(key_pair.sign(&rng, message.as_bytes()).unwrap().as_ref());
+            credentials
+        };
+
+        // 待审批设备签名正确也不能认证
+        let now = chrono::Utc::now().timestamp();
+        assert!(matches!(
+            device_service.authenticate_device(&device.id, &credentials(now, "n-0")).await,
+            Err(AppError::Forbidden(_))
+        ));
+        DeviceRepository::unscoped(pool.clone())
+            .update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+
+        let authenticated =
+            device_service.authenticate_device(&device.id, &credentials(now, "n-1")).await.unwrap();
+        assert_eq!(authenticated.id, device.id);
+
+        // 随机数只能使用一次
+        assert!(matches!(
+            device_service.authenticate_device(&device.id, &credentials(now, "n-1")).await,
+            Err(AppError::Unauthorized(_))
+        ));
+
+        // 签名与时间戳、方法、路径或请求体不匹配
+        let tampered = [
+            DeviceCredentials { timestamp: now + 1, ..credentials(now, "n-2") },
+            DeviceCredentials { method: "POST".to_string(), ..credentials(now, "n-3") },
+            DeviceCredentials {
+                path: format!("/api/v1/devices/{}/settlement/close", device.id),
+                ..credentials(now, "n-4")
+            },
+            DeviceCredentials { body_sha256: sha256_hash_hex(b"{}"), ..credentials(now, "n-5") },
+        ];
+        for credentials in &tampered {
+            assert!(matches!(
+                device_service.authenticate_device(&device.id, credentials).await,
+                Err(AppError::Unauthorized(_))
+            ));
+        }
+
+        // 时间戳超出允许偏差
+        let stale = now - 3600;
+        assert!(matches!(
+            device_service.authenticate_device(&device.id, &credentials(stale, "n-6")).await,
+            Err(AppError::Unauthorized(_))
+        ));
+
+        // 未知设备
+        assert!(matches!(
+            device_service.authenticate_device("unknown", &credentials(now, "n-7")).await,
+            Err(AppError::Unauthorized(_))
+        ));
+
+        // 暂停的设备不能认证
+        DeviceRepository::unscoped(pool.clone())
+            .update_status(&device.id, DeviceStatus::Active, DeviceStatus::Suspended, "admin", None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            device_service.authenticate_device(&device.id, &credentials(now, "n-8")).await,
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_connected_devices_by_tenant() {
+        let pool = setup_test_db().await;
+        let channel = DeviceChannel::new();
+        let service = create_service(&pool, &channel);
+        let device_id = create_device(&pool, "351234567890007", Some(DeviceStatus::Active)).await;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let connection_id = channel.register(&device_id, tx).await;
+
+        let connected = service.list_connected_devices().await.unwrap();
+        assert_eq!(connected.total, 1);
+        assert_eq!(connected.devices[0].device_id, device_id);
+        assert_eq!(connected.devices[0].connection_id, connection_id);
+
+        // 其他租户看不到该设备
+        let other = service.for_tenant(&TenantContext::new("acq-b"));
+        assert_eq!(other.list_connected_devices().await.unwrap().total, 0);
+
+        // 旧连接注销不影响新连接
+        let (tx, _rx2) = mpsc::unbounded_channel();
+        let new_connection_id = channel.register(&device_id, tx).await;
+        channel.unregister(&device_id, &connection_id).await;
+        assert!(channel.is_connected(&device_id).await);
+        channel.unregister(&device_id, &new_connection_id).await;
+        assert!(!channel.is_connected(&device_id).await);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
@@ -0,0 +1,234 @@
+// Integration tests for device groups and tags
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod device_group_tests {
+    use super::*;
+    use crate::dto::{
+        CreateDeviceGroupRequest, GroupMembersRequest, SetDeviceTagsRequest,
+        UpdateDeviceGroupRequest,
+    };
+    use crate::models::{Device, DeviceMode, GroupRules, GroupType, TeeType, TenantContext};
+    use crate::repositories::{
+        AuditLogRepository, DeviceGroupRepository, DeviceRepository, ThreatRepository,
+    };
+    use crate::security::DukptKeyDerivation;
+    use crate::services::{DeviceGroupService, DeviceService};
+    use crate::utils::error::AppError;
+
+    fn create_service(pool: &SqlitePool) -> DeviceGroupService {
+        DeviceGroupService::new(
+            DeviceGroupRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+    }
+
+    fn create_device_service(pool: &SqlitePool) -> DeviceService {
+        DeviceService::new(
+            DeviceRepository::unscoped(pool.clone()),
+            ThreatRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(vec![]),
+            None,
+        )
+    }
+
+    async fn create_device(pool: &SqlitePool, imei: &str, model: &str, score: i32) -> String {
+        let device = Device::new(
+            imei.to_string(),
+            model.to_string(),
+            "12.0.1".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        repo.update_security_score(&device.id, score).await.unwrap();
+
+        device.id
+    }
+
+    fn group_request(name: &str, group_type: GroupType) -> CreateDeviceGroupRequest {
+        CreateDeviceGroupRequest {
+            name: name.to_string(),
+            description: None,
+            group_type,
+            rules: None,
+            device_ids: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_group_membership() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let d1 = create_device(&pool, "123456789012345", "V2PRO", 80).await;
+        let d2 = create_device(&pool, "123456789012346", "P2", 50).await;
+
+        let group = service
+            .create_group(
+                CreateDeviceGroupRequest {
+                    device_ids: vec![d1.clone()],
+                    ..group_request("Pilot", GroupType::Static)
+                },
+                "admin",
+            )
+            .await
+            .unwrap();
+
+        service
+            .add_members(
+                &group.id,
+                GroupMembersRequest { device_ids: vec![d1.clone(), d2.clone()] },
+                "admin",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            service.resolve_device_ids(std::slice::from_ref(&group.id)).await.unwrap(),
+            vec![d1.clone(), d2.clone()]
+        );
+
+        service.remove_member(&group.id, &d1, "admin").await.unwrap();
+        assert_eq!(
+            service.resolve_device_ids(std::slice::from_ref(&group.id)).await.unwrap(),
+            vec![d2.clone()]
+        );
+        assert!(matches!(
+            service.remove_member(&group.id, &d1, "admin").await,
+            Err(AppError::NotFound(_))
+        ));
+
+        // 不存在的设备不能加入分组，名称在租户内唯一
+        let result = service
+            .add_members(
+                &group.id,
+                GroupMembersRequest { device_ids: vec!["missing".to_string()] },
+                "admin",
+            )
+            .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        assert!(matches!(
+            service.create_group(group_request("Pilot", GroupType::Static), "admin").await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_group_resolves_rules_and_tags() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+        let device_service = create_device_service(&pool);
+
+        let d1 = create_device(&pool, "123456789012345", "V2PRO", 80).await;
+        let d2 = create_device(&pool, "123456789012346", "V2PRO", 40).await;
+        let d3 = create_device(&pool, "123456789012347", "P2", 90).await;
+
+        let tags = device_service
+            .set_device_tags(
+                &d1,
+                SetDeviceTagsRequest {
+                    tags: vec!["North".to_string(), "pilot".to_string(), "north".to_string()],
+                },
+                "admin",
+            )
+            .await
+            .unwrap();
+        assert_eq!(tags.tags, vec!["north", "pilot"]);
+        device_service
+            .set_device_tags(&d3, SetDeviceTagsRequest { tags: vec!["north".to_string()] }, "admin")
+            .await
+            .unwrap();
+
+        let group = service
+            .create_group(
+                CreateDeviceGroupRequest {
+                    rules: Some(GroupRules {
+                        models: vec!["v2pro".to_string()],
+                        min_security_score: Some(60),
+                        ..Default::default()
+                    }),
+                    ..group_request("Healthy V2", GroupType::Dynamic)
+                },
+                "admin",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            service.resolve_device_ids(std::slice::from_ref(&group.id)).await.unwrap(),
+            vec![d1.clone()]
+        );
+
+        // 规则变更后实时生效
+        service
+            .update_group(
+                &group.id,
+                UpdateDeviceGroupRequest {
+                    name: None,
+                    description: None,
+                    rules: Some(GroupRules {
+                        tags: vec!["north".to_string()],
+                        ..Default::default()
+                    }),
+                },
+                "admin",
+            )
+            .await
+            .unwrap();
+        let mut ids = service.resolve_device_ids(std::slice::from_ref(&group.id)).await.unwrap();
+        ids.sort();
+        let mut expected = vec![d1.clone(), d3.clone()];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // 动态分组不能手动维护成员
+        let result = service
+            .add_members(&group.id, GroupMembersRequest { device_ids: vec![d2.clone()] }, "admin")
+            .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let listed = service.list_group_devices(&group.id).await.unwrap();
+        assert_eq!(listed.total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_groups_are_tenant_scoped() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        create_device(&pool, "123456789012345", "V2PRO", 80).await;
+        let group = service
+            .create_group(
+                CreateDeviceGroupRequest {
+                    rules: Some(GroupRules {
+                        models: vec!["V2PRO".to_string()],
+                        ..Default::default()
+                    }),
+                    ..group_request("All V2", GroupType::Dynamic)
+                },
+                "admin",
+            )
+            .await
+            .unwrap();
+
+        let other = service.for_tenant(&TenantContext::new("acq-b"));
+        assert!(matches!(other.get_group(&group.id).await, Err(AppError::NotFound(_))));
+        assert_eq!(other.list_groups(20, 0).await.unwrap().total, 0);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
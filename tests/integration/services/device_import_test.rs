@@ -0,0 +1,352 @@
+// Integration tests for bulk device import and pre-approval
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod device_import_tests {
+    use super::*;
+    use crate::dto::{
+        CreateMerchantRequest, CreateStoreRequest, CreateTenantRequest, DeviceImportJobResponse,
+        RegisterDeviceRequest, StoreResponse,
+    };
+    use crate::models::{
+        Device, DeviceMode, DeviceStatus, ImportFormat, ImportJobStatus, TeeType, TenantContext,
+    };
+    use crate::repositories::{
+        ApiKeyRepository, AuditLogRepository, DeviceImportRepository, DeviceRepository,
+        MerchantRepository, StoreRepository, TenantRepository, ThreatRepository,
+    };
+    use crate::security::DukptKeyDerivation;
+    use crate::services::{DeviceImportService, DeviceService, MerchantService, TenantService};
+    use crate::utils::error::AppError;
+
+    fn create_merchant_service(pool: &SqlitePool) -> MerchantService {
+        MerchantService::new(
+            MerchantRepository::unscoped(pool.clone()),
+            StoreRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+    }
+
+    fn create_service(pool: &SqlitePool) -> DeviceImportService {
+        DeviceImportService::new(
+            DeviceImportRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            StoreRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            DeviceService::new(
+                DeviceRepository::unscoped(pool.clone()),
+                ThreatRepository::unscoped(pool.clone()),
+                AuditLogRepository::unscoped(pool.clone()),
+                DukptKeyDerivation::new(vec![]),
+                None,
+            ),
+            create_merchant_service(pool),
+        )
+    }
+
+    async fn create_store(pool: &SqlitePool, tenant: &TenantContext) -> StoreResponse {
+        let service = create_merchant_service(pool).for_tenant(tenant);
+        let merchant = service
+            .create_merchant(
+                CreateMerchantRequest {
+                    name: "Coffee Shop".to_string(),
+                    mcc: "5814".to_string(),
+                    country: "CN".to_string(),
+                    currency: "CNY".to_string(),
+                    acquirer_mid: None,
+                    address: None,
+                },
+                "admin",
+            )
+            .await
+            .unwrap();
+
+        service
+            .create_store(
+                &merchant.id,
+                CreateStoreRequest { name: "Downtown".to_string(), address: None },
+                "admin",
+            )
+            .await
+            .unwrap()
+    }
+
+    fn register_request(imei: &str, model: &str, code: Option<&str>) -> RegisterDeviceRequest {
+        RegisterDeviceRequest {
+            imei: imei.to_string(),
+            model: model.to_string(),
+            os_version: "12.0".to_string(),
+            tee_type: TeeType::TrustZone,
+            public_key: "public-key".to_string(),
+            device_mode: DeviceMode::FullPos,
+            nfc_present: true,
+            enrollment_code: code.map(str::to_string),
+        }
+    }
+
+    async fn wait_for_job(service: &DeviceImportService, job_id: &str) -> DeviceImportJobResponse {
+        for _ in 0..100 {
+            let job = service.get_job(job_id).await.unwrap();
+            if job.status == ImportJobStatus::Completed.as_str()
+                || job.status == ImportJobStatus::Failed.as_str()
+            {
+                return job;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("Import job {} did not finish", job_id);
+    }
+
+    #[tokio::test]
+    async fn test_csv_import_reports_row_errors() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+        let store = create_store(&pool, &TenantContext::default()).await;
+
+        let existing = Device::new(
+            "123456789012399".to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        DeviceRepository::unscoped(pool.clone()).create(&existing).await.unwrap();
+
+        let csv = format!(
+            "imei,model,merchant_id,store_id,enrollment_code\n\
+             123456789012345,V2PRO,{merchant},{store},code-0001\n\
+             123456789012346,V2PRO,,,code-0002\n\
+             12345,V2PRO,,,code-0003\n\
+             123456789012345,V2PRO,,,code-0004\n\
+             123456789012399,V2PRO,,,code-0005\n\
+             123456789012347,V2PRO,other-merchant,{store},code-0006\n\
+             123456789012348,V2PRO,,,short\n",
+            merchant = store.merchant_id,
+            store = store.id
+        );
+
+        let job = service.start_import(ImportFormat::Csv, csv, "admin").await.unwrap();
+        assert_eq!(job.format, "CSV");
+
+        let job = wait_for_job(&service, &job.id).await;
+        assert_eq!(job.status, "COMPLETED");
+        assert_eq!(job.total_rows, 7);
+        assert_eq!(job.success_rows, 2);
+        assert_eq!(job.failed_rows, 5);
+
+        let report = service.get_report(&job.id).await.unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "row,imei,error");
+        assert_eq!(lines.len(), 6);
+        assert!(lines[1].starts_with("3,12345,"));
+        assert!(lines[2].starts_with("4,123456789012345,") && lines[2].contains("Duplicate"));
+        assert!(lines[3].contains("already registered"));
+        assert!(lines[4].contains("does not belong to merchant"));
+        assert!(lines[5].contains("enrollment_code"));
+
+        let preapprovals = DeviceImportRepository::unscoped(pool.clone())
+            .list_preapprovals(&job.id)
+            .await
+            .unwrap();
+        assert_eq!(preapprovals.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_file_fails_job() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let job = service
+            .start_import(ImportFormat::Csv, "serial,model\n1,V2PRO\n".to_string(), "admin")
+            .await
+            .unwrap();
+        let job = wait_for_job(&service, &job.id).await;
+        assert_eq!(job.status, "FAILED");
+
+        assert!(matches!(
+            service.start_import(ImportFormat::Json, "  ".to_string(), "admin").await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_preapproved_device_activates_on_registration() {
+        let pool = setup_test_db().await;
+        TenantService::new(
+            TenantRepository::new(pool.clone()),
+            ApiKeyRepository::new(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+        .create_tenant(
+            CreateTenantRequest {
+                id: "acq-a".to_string(),
+                name: "Acquirer A".to_string(),
+                bdk: None,
+                bdk_key_ref: None,
+                rate_limit_rps: None,
+                rate_limit_burst: None,
+                branding: None,
+            },
+            "root",
+        )
+        .await
+        .unwrap();
+
+        let tenant = TenantContext::new("acq-a");
+        let service = create_service(&pool);
+        let store = create_store(&pool, &tenant).await;
+
+        let json = format!(
+            r#"[{{"imei":"123456789012345","model":"V2PRO","store_id":"{}",
+                  "enrollment_code":"code-0001"}},
+                {{"imei":"123456789012346","model":"V2PRO","enrollment_code":"code-0002"}},
+                {{"imei":"123456789012347","model":"V2PRO","enrollment_code":"code-0003"}}]"#,
+            store.id
+        );
+        let job = service.for_tenant(&tenant).start_import(ImportFormat::Json, json, "ops").await;
+        let job = wait_for_job(&service, &job.unwrap().id).await;
+        assert_eq!(job.success_rows, 3);
+
+        // 未携带租户凭证的注册进入预审批所属租户并自动激活
+        let response = service
+            .register_device(
+                &TenantContext::default(),
+                register_request("123456789012345", "v2pro", Some("code-0001")),
+                "system",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, DeviceStatus::Active);
+
+        let device = DeviceRepository::unscoped(pool.clone())
+            .find_by_id(&response.device_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(device.tenant_id, "acq-a");
+        assert_eq!(device.approved_by.as_deref(), Some("ops"));
+        assert_eq!(device.store_id.as_deref(), Some(store.id.as_str()));
+        assert!(device.terminal_id.is_some());
+
+        // 型号不匹配时保持待审批
+        let response = service
+            .register_device(
+                &TenantContext::default(),
+                register_request("123456789012346", "P2", Some("code-0002")),
+                "system",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, DeviceStatus::Pending);
+
+        // 仅凭IMEI或注册码错误时保持待审批
+        let response = service
+            .register_device(
+                &TenantContext::default(),
+                register_request("123456789012347", "V2PRO", Some("code-9999")),
+                "system",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, DeviceStatus::Pending);
+
+        // 仅匹配的预审批被消费
+        let preapprovals = DeviceImportRepository::unscoped(pool.clone())
+            .list_preapprovals(&job.id)
+            .await
+            .unwrap();
+        let consumed: Vec<_> = preapprovals.iter().filter(|p| p.status == "CONSUMED").collect();
+        assert_eq!(consumed.len(), 1);
+        assert_eq!(consumed[0].imei, "123456789012345");
+    }
+
+    #[tokio::test]
+    async fn test_failed_activation_keeps_preapproval() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let job = service
+            .start_import(
+                ImportFormat::Csv,
+                "imei,model,enrollment_code\n123456789012345,V2PRO,code-0001\n".to_string(),
+                "admin",
+            )
+            .await
+            .unwrap();
+        wait_for_job(&service, &job.id).await;
+
+        // 设备激活失败时预审批的使用一并回滚
+        sqlx::query(
+            r#"
+            CREATE TRIGGER fail_activation BEFORE UPDATE OF status ON devices
+            WHEN NEW.status = 'ACTIVE'
+            BEGIN SELECT RAISE(ABORT, 'injected failure'); END
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let response = service
+            .register_device(
+                &TenantContext::default(),
+                register_request("123456789012345", "V2PRO", Some("code-0001")),
+                "system",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, DeviceStatus::Pending);
+
+        let preapprovals = DeviceImportRepository::unscoped(pool.clone())
+            .list_preapprovals(&job.id)
+            .await
+            .unwrap();
+        assert_eq!(preapprovals[0].status, "PENDING");
+        assert!(preapprovals[0].device_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_preapproval_rejects_other_tenant() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let job = service
+            .start_import(
+                ImportFormat::Csv,
+                "imei,model,enrollment_code\n123456789012345,V2PRO,code-0001\n".to_string(),
+                "admin",
+            )
+            .await
+            .unwrap();
+        wait_for_job(&service, &job.id).await;
+
+        let result = service
+            .register_device(
+                &TenantContext::new("acq-b"),
+                register_request("123456789012345", "V2PRO", Some("code-0001")),
+                "system",
+            )
+            .await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+
+        assert!(!DeviceRepository::unscoped(pool.clone())
+            .exists_by_imei("123456789012345")
+            .await
+            .unwrap());
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
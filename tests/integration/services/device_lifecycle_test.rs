@@ -0,0 +1,226 @@
+// Integration tests for the device lifecycle state machine
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod device_lifecycle_tests {
+    use super::*;
+    use crate::dto::{
+        ApproveDeviceRequest, AssignDeviceRequest, CreateMerchantRequest, CreateStoreRequest,
+        ReplaceDeviceRequest,
+    };
+    use crate::models::{Device, DeviceMode, TeeType, ThreatEvent, ThreatSeverity, ThreatType};
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, MerchantRepository, StoreRepository, ThreatRepository,
+    };
+    use crate::security::DukptKeyDerivation;
+    use crate::services::{DeviceService, MerchantService};
+    use crate::utils::error::AppError;
+
+    fn create_service(pool: &SqlitePool) -> DeviceService {
+        DeviceService::new(
+            DeviceRepository::unscoped(pool.clone()),
+            ThreatRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(vec![]),
+            None,
+        )
+    }
+
+    async fn create_active_device(
+        pool: &SqlitePool,
+        service: &DeviceService,
+        imei: &str,
+    ) -> String {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        DeviceRepository::unscoped(pool.clone()).create(&device).await.unwrap();
+
+        service
+            .approve_device(ApproveDeviceRequest {
+                device_id: device.id.clone(),
+                operator: "admin".to_string(),
+            })
+            .await
+            .unwrap();
+
+        device.id
+    }
+
+    #[tokio::test]
+    async fn test_full_lifecycle_records_timeline() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        let threat_repo = ThreatRepository::unscoped(pool.clone());
+
+        let device_id = create_active_device(&pool, &service, "123456789012345").await;
+        device_repo
+            .update_key_info(&device_id, "FFFF9876543210E00001", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+
+        service
+            .suspend_device(&device_id, "admin", "Suspicious activity")
+            .await
+            .unwrap();
+
+        let threat = ThreatEvent::new(
+            device_id.clone(),
+            ThreatType::RootDetection,
+            ThreatSeverity::High,
+            "Root detected".to_string(),
+        );
+        threat_repo.create(&threat).await.unwrap();
+
+        // 恢复设备时关闭活跃威胁
+        service.resume_device(&device_id, "admin").await.unwrap();
+        assert!(threat_repo.get_active_threats(&device_id).await.unwrap().is_empty());
+
+        // 吊销设备时清零密钥
+        service.revoke_device(&device_id, "admin", "Device lost").await.unwrap();
+        let device = device_repo.find_by_id(&device_id).await.unwrap().unwrap();
+        assert_eq!(device.status, "REVOKED");
+        assert_eq!(device.current_ksn, "");
+        assert_eq!(device.key_remaining_count, 0);
+        assert!(device.ipek_injected_at.is_none());
+
+        service
+            .decommission_device(&device_id, "admin", "Returned to vendor")
+            .await
+            .unwrap();
+
+        let timeline = service.get_device_timeline(&device_id).await.unwrap();
+        let statuses: Vec<&str> = timeline.history.iter().map(|h| h.to_status.as_str()).collect();
+        assert_eq!(
+            statuses,
+            vec!["PENDING", "ACTIVE", "SUSPENDED", "ACTIVE", "REVOKED", "DECOMMISSIONED"]
+        );
+        assert_eq!(timeline.current_status, "DECOMMISSIONED");
+        assert!(timeline.history[0].from_status.is_none());
+        assert_eq!(timeline.history[2].reason.as_deref(), Some("Suspicious activity"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_transitions_rejected() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let device_id = create_active_device(&pool, &service, "123456789012345").await;
+
+        // 已激活的设备不能再次审批或恢复
+        let result = service
+            .approve_device(ApproveDeviceRequest {
+                device_id: device_id.clone(),
+                operator: "admin".to_string(),
+            })
+            .await;
+        assert!(matches!(result, Err(AppError::InvalidDeviceStatus)));
+        assert!(matches!(
+            service.resume_device(&device_id, "admin").await,
+            Err(AppError::InvalidDeviceStatus)
+        ));
+
+        service.decommission_device(&device_id, "admin", "End of life").await.unwrap();
+        assert!(matches!(
+            service.suspend_device(&device_id, "admin", "Should fail").await,
+            Err(AppError::InvalidDeviceStatus)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resume_requires_minimum_security_score() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let device_id = create_active_device(&pool, &service, "123456789012345").await;
+        service.suspend_device(&device_id, "admin", "Low score").await.unwrap();
+        DeviceRepository::unscoped(pool.clone())
+            .update_security_score(&device_id, 40)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            service.resume_device(&device_id, "admin").await,
+            Err(AppError::DeviceSecurityScoreTooLow(40))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replace_device_transfers_terminal() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+        let merchant_service = MerchantService::new(
+            MerchantRepository::unscoped(pool.clone()),
+            StoreRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        );
+
+        let merchant = merchant_service
+            .create_merchant(
+                CreateMerchantRequest {
+                    name: "Coffee Shop".to_string(),
+                    mcc: "5814".to_string(),
+                    country: "CN".to_string(),
+                    currency: "CNY".to_string(),
+                    acquirer_mid: None,
+                    address: None,
+                },
+                "admin",
+            )
+            .await
+            .unwrap();
+        let store = merchant_service
+            .create_store(
+                &merchant.id,
+                CreateStoreRequest { name: "Downtown".to_string(), address: None },
+                "admin",
+            )
+            .await
+            .unwrap();
+
+        let old_id = create_active_device(&pool, &service, "123456789012345").await;
+        let new_id = create_active_device(&pool, &service, "123456789012346").await;
+        let assigned = merchant_service
+            .assign_device(&old_id, AssignDeviceRequest { store_id: store.id.clone() }, "admin")
+            .await
+            .unwrap();
+
+        service
+            .replace_device(
+                &old_id,
+                ReplaceDeviceRequest {
+                    replacement_device_id: new_id.clone(),
+                    reason: "Screen broken".to_string(),
+                },
+                "admin",
+            )
+            .await
+            .unwrap();
+
+        let old = service.get_device(&old_id).await.unwrap();
+        let new = service.get_device(&new_id).await.unwrap();
+        assert_eq!(old.status.as_str(), "REPLACED");
+        assert!(old.terminal_id.is_none());
+        assert_eq!(new.store_id.as_deref(), Some(store.id.as_str()));
+        assert_eq!(new.terminal_id, assigned.terminal_id);
+    }
+}
+
+// Helper functions
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    pool
+}
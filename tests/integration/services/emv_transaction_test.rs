@@ -0,0 +1,230 @@
+// Integration tests for EMV chip data on contactless transactions
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod emv_transaction_tests {
+    use super::*;
+    use crate::dto::{AttestTransactionRequest, ProcessTransactionRequest};
+    use crate::models::{
+        Device, DeviceMode, DeviceStatus, EmvData, HealthCheck, TeeType, TransactionStatus,
+        TransactionType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, TransactionRepository,
+        TransactionTokenRepository,
+    };
+    use crate::security::{DukptKeyDerivation, JwtService};
+    use crate::services::{TransactionService, TransactionTokenService};
+    use crate::utils::{
+        error::AppError,
+        tlv::{self, Tlv},
+    };
+    use std::sync::Arc;
+
+    const PAN: &str = "4111111111111111";
+
+    /// 非接交易的EMV数据，金额10.00 USD，包含二磁道等效数据（57）和主账号（5A）
+    fn emv_data(cid: &str, amount: &str) -> String {
+        [
+            "9F2608A1B2C3D4E5F60718",
+            &format!("9F2701{}", cid),
+            "9F100706010A03A0A000",
+            "9F370412345678",
+            "9F36020042",
+            "95050000008000",
+            "9A03240301",
+            "9C0100",
+            "5F2A020840",
+            "82021980",
+            "8407A0000000031010",
+            &format!("9F0206{}", amount),
+            "9F1A020840",
+            &format!("5711{}D25122010000000000", PAN),
+            &format!("5A08{}", PAN),
+        ]
+        .concat()
+    }
+
+    fn transaction_service(pool: &SqlitePool) -> TransactionService {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        TransactionService::new(
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec()),
+            None,
+            Arc::new(
+                TransactionTokenService::new(jwt_service, None)
+                    .with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+            ),
+        )
+    }
+
+    async fn create_active_device(pool: &SqlitePool, imei: &str) -> Device {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        repo.update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        repo.update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+        HealthCheckRepository::unscoped(pool.clone())
+            .create(&HealthCheck::new(device.id.clone(), 95, false, false, true, true, true))
+            .await
+            .unwrap();
+        device
+    }
+
+    async fn attested_request(
+        service: &TransactionService,
+        device_id: &str,
+        emv_data: Option<String>,
+    ) -> ProcessTransactionRequest {
+        let attestation = service
+            .attest_transaction(
+                AttestTransactionRequest {
+                    device_id: device_id.to_string(),
+                    amount: 1000,
+                    currency: "USD".to_string(),
+                    health_check: None,
+                },
+                "device",
+            )
+            .await
+            .unwrap();
+
+        ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type: TransactionType::Payment,
+            amount: 1000,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "encrypted_pin".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1111".to_string()),
+            transaction_token: attestation.transaction_token,
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: None,
+            client_transaction_id: None,
+            emv_data,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_emv_data_stored_without_sensitive_tags() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "740000000000001").await;
+        let service = transaction_service(&pool);
+
+        let request =
+            attested_request(&service, &device.id, Some(emv_data("80", "000000001000"))).await;
+        // 请求的Debug输出同样脱敏
+        assert!(!format!("{:?}", request).contains(PAN));
+        let response = service.process_transaction(request, "device").await.unwrap();
+        assert_eq!(response.status, TransactionStatus::Approved);
+
+        let transaction = service.get_transaction(&response.transaction_id).await.unwrap();
+        let stored = transaction.emv_data.expect("EMV data should be stored");
+        assert!(!stored.contains(PAN));
+        assert!(stored.starts_with("9F2608A1B2C3D4E5F60718"));
+        assert!(stored.contains("9F1A020840"));
+
+        let emv = EmvData::from_hex(&stored).unwrap();
+        assert!(emv.value(0x57).is_none());
+        assert!(emv.value(0x5A).is_none());
+        assert_eq!(emv.amount_authorised(), Some(1000));
+
+        // 数据库和审计日志中都不出现主账号
+        let rows: Vec<String> = sqlx::query_scalar(
+            "SELECT COALESCE(emv_data, '') FROM transactions UNION ALL SELECT COALESCE(details, '') FROM audit_logs",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert!(rows.iter().all(|row| !row.contains(PAN)));
+
+        // 不带EMV数据的交易照常处理
+        let request = attested_request(&service, &device.id, None).await;
+        let response = service.process_transaction(request, "device").await.unwrap();
+        let transaction = service.get_transaction(&response.transaction_id).await.unwrap();
+        assert!(transaction.emv_data.is_none());
+    }
+
+    /// 嵌套 `depth` 层70模板的EMV数据
+    fn nested_templates(depth: usize) -> String {
+        let mut data = hex::decode(emv_data("80", "000000001000")).unwrap();
+        for _ in 0..depth {
+            data = tlv::encode(&[Tlv::new(0x70, data)]);
+        }
+        hex::encode_upper(data)
+    }
+
+    #[tokio::test]
+    async fn test_invalid_emv_data_rejected() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "740000000000002").await;
+        let service = transaction_service(&pool);
+
+        let cases = [
+            // 缺少ATC
+            emv_data("80", "000000001000").replace("9F36020042", ""),
+            // 长度超出数据
+            "9F2610A1B2C3D4".to_string(),
+            "not-hex".to_string(),
+            // 模板嵌套过深
+            nested_templates(40),
+            // 超过最大长度
+            format!("{}DF018201F4{}", emv_data("80", "000000001000"), "00".repeat(500)),
+        ];
+        for emv in cases {
+            let request = attested_request(&service, &device.id, Some(emv)).await;
+            let result = service.process_transaction(request, "device").await;
+            assert!(matches!(result, Err(AppError::Validation(_))), "unexpected: {:?}", result);
+        }
+
+        // 卡片拒绝（AAC）、金额与请求不一致时不提交处理器
+        for emv in [emv_data("00", "000000001000"), emv_data("80", "000000002000")] {
+            let request = attested_request(&service, &device.id, Some(emv)).await;
+            let result = service.process_transaction(request, "device").await;
+            assert!(matches!(result, Err(AppError::BadRequest(_))), "unexpected: {:?}", result);
+        }
+
+        // 令牌校验先于EMV解析，未通过鉴证的请求不会解析EMV数据
+        let mut request = attested_request(&service, &device.id, Some(nested_templates(40))).await;
+        request.transaction_token = "invalid-token".to_string();
+        let result = service.process_transaction(request, "device").await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))), "unexpected: {:?}", result);
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM transactions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
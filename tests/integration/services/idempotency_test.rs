@@ -0,0 +1,132 @@
+// Integration tests for transaction idempotency keys
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod idempotency_tests {
+    use super::*;
+    use crate::dto::ProcessTransactionRequest;
+    use crate::models::TransactionType;
+    use crate::repositories::IdempotencyRepository;
+    use crate::services::{IdempotencyService, IdempotentOutcome};
+    use crate::utils::error::AppError;
+    use std::time::Duration;
+
+    fn request(device_id: &str, amount: i64) -> ProcessTransactionRequest {
+        ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type: TransactionType::Payment,
+            amount,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "encrypted_pin".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1234".to_string()),
+            transaction_token: "token".to_string(),
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: None,
+            client_transaction_id: Some("client-tx-1".to_string()),
+            emv_data: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_completed_request_is_replayed() {
+        let pool = setup_test_db().await;
+        let service = IdempotencyService::new(IdempotencyRepository::new(pool.clone()));
+        let req = request("device-1", 1000);
+
+        let outcome = service.begin("device-1", "key-1", &req).await.unwrap();
+        assert_eq!(outcome, IdempotentOutcome::Started);
+
+        let body = r#"{"transactionId":"tx-1","status":"APPROVED"}"#;
+        service.complete("device-1", "key-1", 200, body, Some("tx-1")).await.unwrap();
+
+        let outcome = service.begin("device-1", "key-1", &req).await.unwrap();
+        assert_eq!(
+            outcome,
+            IdempotentOutcome::Replay {
+                status: 200,
+                body: body.to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_payload_and_in_flight_duplicate_are_rejected() {
+        let pool = setup_test_db().await;
+        let service = IdempotencyService::new(IdempotencyRepository::new(pool.clone()));
+
+        service.begin("device-1", "key-1", &request("device-1", 1000)).await.unwrap();
+
+        // 处理中的相同请求返回冲突，内容不同的请求被拒绝
+        let result = service.begin("device-1", "key-1", &request("device-1", 1000)).await;
+        assert!(matches!(result, Err(AppError::RequestInProgress)), "unexpected: {:?}", result);
+        let result = service.begin("device-1", "key-1", &request("device-1", 2000)).await;
+        assert!(
+            matches!(result, Err(AppError::IdempotencyKeyMismatch)),
+            "unexpected: {:?}",
+            result
+        );
+
+        // 处理失败释放后可使用同一幂等键重新提交
+        service.release("device-1", "key-1").await.unwrap();
+        let outcome = service.begin("device-1", "key-1", &request("device-1", 2000)).await.unwrap();
+        assert_eq!(outcome, IdempotentOutcome::Started);
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_scoped_per_device() {
+        let pool = setup_test_db().await;
+        let service = IdempotencyService::new(IdempotencyRepository::new(pool.clone()));
+
+        let first = service.begin("device-1", "key-1", &request("device-1", 1000)).await.unwrap();
+        let second = service.begin("device-2", "key-1", &request("device-2", 1000)).await.unwrap();
+        assert_eq!(first, IdempotentOutcome::Started);
+        assert_eq!(second, IdempotentOutcome::Started);
+
+        let result = service.begin("device-1", "", &request("device-1", 1000)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))), "unexpected: {:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_stale_locks_and_expired_keys_are_reclaimed() {
+        let pool = setup_test_db().await;
+
+        // 锁超时为0：处理方失效后同一请求可立即重新领取
+        let service = IdempotencyService::new(IdempotencyRepository::new(pool.clone()))
+            .with_expiry(Duration::from_secs(3600), Duration::ZERO);
+        service.begin("device-1", "key-1", &request("device-1", 1000)).await.unwrap();
+        let outcome = service.begin("device-1", "key-1", &request("device-1", 1000)).await.unwrap();
+        assert_eq!(outcome, IdempotentOutcome::Started);
+
+        // 有效期为0：已完成的幂等键过期后视为新请求，并被后台清理
+        let service = IdempotencyService::new(IdempotencyRepository::new(pool.clone()))
+            .with_expiry(Duration::ZERO, Duration::from_secs(60));
+        service.begin("device-1", "key-2", &request("device-1", 1000)).await.unwrap();
+        service.complete("device-1", "key-2", 200, "{}", None).await.unwrap();
+        let outcome = service.begin("device-1", "key-2", &request("device-1", 2000)).await.unwrap();
+        assert_eq!(outcome, IdempotentOutcome::Started);
+
+        assert!(service.purge_expired().await.unwrap() >= 1);
+        let remaining = IdempotencyRepository::new(pool.clone())
+            .find("device-1", "key-2")
+            .await
+            .unwrap();
+        assert!(remaining.is_none());
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
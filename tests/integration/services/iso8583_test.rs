@@ -0,0 +1,534 @@
+// Integration tests for the ISO 8583 TCP acquirer connector against a local mock acquirer
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod iso8583_tests {
+    use super::*;
+    use crate::api::websocket::{Notification, NotificationType};
+    use crate::dto::ProcessTransactionRequest;
+    use crate::infrastructure::config::{
+        PaymentConfig, PaymentConnectorConfig, PaymentConnectorType, PaymentRoute,
+    };
+    use crate::infrastructure::payment::{
+        iso8583::{frame, Iso8583Spec, Iso8583Version, IsoMessage},
+        PaymentOperation, PaymentOutcome, PaymentProcessor, PaymentRequest, PaymentRouter,
+        TcpAcquirerConnector,
+    };
+    use crate::models::{
+        Device, DeviceMode, DeviceStatus, HealthCheck, Merchant, ReversalStatus, Store, TeeType,
+        TransactionStatus, TransactionType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, MerchantRepository,
+        StoreRepository, TransactionRepository, TransactionTokenRepository,
+    };
+    use crate::security::{DukptKeyDerivation, JwtService};
+    use crate::services::{
+        NotificationServiceWrapper, NotificationSink, TransactionService, TransactionTokenService,
+    };
+    use crate::utils::error::AppError;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{tcp::OwnedWriteHalf, TcpListener},
+    };
+
+    const SPEC_1987: &str = "config/iso8583/iso8583_1987.json";
+    const SPEC_1993: &str = "config/iso8583/iso8583_1993.json";
+
+    /// 本地模拟收单机构
+    ///
+    /// 按金额应答：5100拒绝51，1550部分批准1000，9100不应答，7700延迟应答，
+    /// 6600直接断开连接，4400的冲正应答25（找不到原交易），其他金额批准
+    struct MockAcquirer {
+        address: String,
+        received: Arc<Mutex<Vec<IsoMessage>>>,
+    }
+
+    impl MockAcquirer {
+        async fn start(spec_file: &str) -> Self {
+            let spec = Arc::new(Iso8583Spec::from_file(spec_file).unwrap());
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let received = Arc::new(Mutex::new(Vec::new()));
+
+            let log = received.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let (mut reader, writer) = stream.into_split();
+                    let writer = Arc::new(tokio::sync::Mutex::new(writer));
+                    let (spec, log) = (spec.clone(), log.clone());
+                    tokio::spawn(async move {
+                        loop {
+                            let mut header = [0u8; 2];
+                            if reader.read_exact(&mut header).await.is_err() {
+                                return;
+                            }
+                            let mut body = vec![0u8; u16::from_be_bytes(header) as usize];
+                            if reader.read_exact(&mut body).await.is_err() {
+                                return;
+                            }
+                            let request = IsoMessage::unpack(&spec, &body).unwrap();
+                            log.lock().unwrap().push(request.clone());
+
+                            if request.get_str(4) == Some("000000006600") {
+                                writer.lock().await.shutdown().await.ok();
+                                return;
+                            }
+                            tokio::spawn(respond(spec.clone(), writer.clone(), request));
+                        }
+                    });
+                }
+            });
+
+            Self { address, received }
+        }
+
+        fn received(&self, mti: &str) -> Vec<IsoMessage> {
+            self.received.lock().unwrap().iter().filter(|m| m.mti == mti).cloned().collect()
+        }
+    }
+
+    async fn respond(
+        spec: Arc<Iso8583Spec>,
+        writer: Arc<tokio::sync::Mutex<OwnedWriteHalf>>,
+        request: IsoMessage,
+    ) {
+        let amount = request.get_str(4).unwrap_or_default();
+        let financial = request.mti.ends_with("200") || request.mti.ends_with("100");
+        let (code, approved) = match amount {
+            "000000009100" if financial => return,
+            "000000004400" if request.mti.ends_with("400") => ("25", None),
+            "000000007700" => {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                ("00", None)
+            },
+            "000000005100" => ("51", None),
+            "000000001550" => ("10", Some("000000001000")),
+            _ => ("00", None),
+        };
+
+        let mut mti: Vec<char> = request.mti.chars().collect();
+        mti[2] = char::from_digit(mti[2].to_digit(10).unwrap() + 1, 10).unwrap();
+        let mut response = IsoMessage::new(mti.into_iter().collect::<String>());
+        for field in [4, 7, 11, 24, 37, 41, 42, 49, 70] {
+            if let Some(value) = request.get(field) {
+                response.set(field, value.to_vec());
+            }
+        }
+        if let Some(approved) = approved {
+            response.set(4, approved);
+        }
+        match spec.version {
+            Iso8583Version::V1987 => response.set(39, code),
+            Iso8583Version::V1993 => response.set(39, if code == "00" { "000" } else { "116" }),
+        };
+        if code == "00" || code == "10" {
+            response.set(38, "A1B2C3");
+        }
+
+        let packed = frame(&response.pack(&spec).unwrap()).unwrap();
+        writer.lock().await.write_all(&packed).await.ok();
+    }
+
+    fn connector(acquirer: &MockAcquirer, spec_file: &str) -> TcpAcquirerConnector {
+        TcpAcquirerConnector::new(
+            "acquirer",
+            &acquirer.address,
+            Iso8583Spec::from_file(spec_file).unwrap(),
+            Duration::from_millis(500),
+        )
+        .with_echo_interval(Duration::ZERO)
+    }
+
+    fn payment_request(transaction_id: &str, amount: i64) -> PaymentRequest {
+        PaymentRequest {
+            transaction_id: transaction_id.to_string(),
+            transaction_type: TransactionType::Payment,
+            amount,
+            currency: "USD".to_string(),
+            merchant_id: None,
+            acquirer_mid: Some("888100000000001".to_string()),
+            terminal_id: Some("T0000001".to_string()),
+            card_number_masked: None,
+            encrypted_pin_block: Some("0123456789ABCDEF".to_string()),
+            ksn: "FFFF9876543210E00000".to_string(),
+            original_reference: None,
+            emv_data: Some("9F2608A1B2C3D4E5F60718".to_string()),
+            identifiers: None,
+            original_identifiers: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorization_outcomes_and_reversal() {
+        let acquirer = MockAcquirer::start(SPEC_1987).await;
+        let connector: Arc<dyn PaymentProcessor> = Arc::new(connector(&acquirer, SPEC_1987));
+
+        let approved = connector.authorize(&payment_request("txn-1", 2500)).await.unwrap();
+        assert_eq!(approved.outcome, PaymentOutcome::Approved);
+        assert_eq!(approved.approved_amount, 2500);
+        assert_eq!(approved.authorization_code.as_deref(), Some("A1B2C3"));
+
+        let sent = acquirer.received("0200");
+        assert_eq!(sent[0].get_str(3), Some("000000"));
+        assert_eq!(sent[0].get_str(41), Some("T0000001"));
+        assert_eq!(sent[0].get_str(49), Some("840"));
+        assert_eq!(sent[0].get(52), Some(&hex::decode("0123456789ABCDEF").unwrap()[..]));
+        assert_eq!(sent[0].get(55), Some(&hex::decode("9F2608A1B2C3D4E5F60718").unwrap()[..]));
+        assert_eq!(approved.processor_reference.as_deref(), sent[0].get_str(37));
+
+        let declined = connector.authorize(&payment_request("txn-2", 5100)).await.unwrap();
+        assert_eq!(declined.outcome, PaymentOutcome::Declined);
+        assert_eq!(declined.response_code, "51");
+        assert_eq!(declined.response_message, "Insufficient funds");
+
+        let partial = connector.authorize(&payment_request("txn-3", 1550)).await.unwrap();
+        assert_eq!(partial.outcome, PaymentOutcome::PartiallyApproved);
+        assert_eq!(partial.approved_amount, 1000);
+
+        // 退款使用原交易的RRN
+        let refund = PaymentRequest {
+            original_reference: approved.processor_reference.clone(),
+            ..payment_request("txn-4", 2500)
+        };
+        assert!(connector.refund(&refund).await.unwrap().is_approved());
+        let sent = acquirer.received("0200");
+        assert_eq!(sent.last().unwrap().get_str(3), Some("200000"));
+        assert_eq!(sent.last().unwrap().get_str(37), approved.processor_reference.as_deref());
+
+        // 发送前分配的数据元原样用于报文；无应答时超时，随后冲正携带原报文的数据元
+        let mut timed_out = payment_request("txn-5", 9100);
+        let identifiers =
+            connector.assign_identifiers(PaymentOperation::Authorize, &timed_out).unwrap();
+        timed_out.identifiers = Some(identifiers.clone());
+        assert!(matches!(connector.authorize(&timed_out).await, Err(AppError::ProcessorTimeout(_))));
+        let original = acquirer.received("0200").pop().unwrap();
+        assert_eq!(original.get_str(11), Some(identifiers.stan.as_str()));
+        assert_eq!(original.get_str(37), Some(identifiers.rrn.as_str()));
+        assert_eq!(original.get_str(7), Some(identifiers.transmission_time.as_str()));
+
+        let reversal = PaymentRequest {
+            original_identifiers: Some(identifiers.clone()),
+            ..payment_request("txn-5", 9100)
+        };
+        let reversal = connector.execute(PaymentOperation::Reversal, &reversal).await;
+        assert!(reversal.unwrap().is_approved());
+        let sent = acquirer.received("0400").pop().unwrap();
+        assert_eq!(sent.get_str(37), Some(identifiers.rrn.as_str()));
+        assert_ne!(sent.get_str(11), Some(identifiers.stan.as_str()));
+        let original_data = sent.get_str(90).unwrap();
+        assert_eq!(&original_data[..4], "0200");
+        assert_eq!(&original_data[4..10], identifiers.stan);
+        assert_eq!(&original_data[10..20], identifiers.transmission_time);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_matched_by_stan() {
+        let acquirer = MockAcquirer::start(SPEC_1987).await;
+        let connector = connector(&acquirer, SPEC_1987);
+
+        // 先发出的请求后应答
+        let slow_request = payment_request("txn-slow", 7700);
+        let fast_request = payment_request("txn-fast", 1200);
+        let (slow, fast) =
+            tokio::join!(connector.authorize(&slow_request), connector.authorize(&fast_request));
+        assert_eq!(slow.unwrap().approved_amount, 7700);
+        assert_eq!(fast.unwrap().approved_amount, 1200);
+
+        let sent = acquirer.received("0200");
+        assert_eq!(sent.len(), 2);
+        assert_ne!(sent[0].get_str(11), sent[1].get_str(11));
+    }
+
+    #[tokio::test]
+    async fn test_echo_keepalive_and_reconnect() {
+        let acquirer = MockAcquirer::start(SPEC_1987).await;
+        let connector =
+            connector(&acquirer, SPEC_1987).with_echo_interval(Duration::from_millis(100));
+
+        assert!(connector.health().await.is_ok());
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        let echoes = acquirer.received("0800");
+        assert!(echoes.len() >= 3);
+        assert!(echoes.iter().all(|m| m.get_str(70) == Some("301")));
+
+        // 请求写出后收单机构断开连接，结果未知；下一个请求重新连接
+        assert!(matches!(
+            connector.authorize(&payment_request("txn-1", 6600)).await,
+            Err(AppError::ProcessorOutcomeUnknown(_))
+        ));
+        assert!(connector.authorize(&payment_request("txn-2", 800)).await.unwrap().is_approved());
+
+        // 收单机构不可达
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let unreachable = TcpAcquirerConnector::new(
+            "unreachable",
+            address,
+            Iso8583Spec::from_file(SPEC_1987).unwrap(),
+            Duration::from_millis(500),
+        );
+        assert!(matches!(unreachable.health().await, Err(AppError::External(_))));
+    }
+
+    #[tokio::test]
+    async fn test_1993_messages() {
+        let acquirer = MockAcquirer::start(SPEC_1993).await;
+        let connector = connector(&acquirer, SPEC_1993);
+
+        assert!(connector.health().await.is_ok());
+        assert_eq!(acquirer.received("1804")[0].get_str(24), Some("831"));
+
+        let approved = connector.authorize(&payment_request("txn-1", 2500)).await.unwrap();
+        assert_eq!(approved.response_code, "00");
+        assert_eq!(acquirer.received("1200")[0].get_str(24), Some("200"));
+
+        let declined = connector.authorize(&payment_request("txn-2", 5100)).await.unwrap();
+        assert_eq!(declined.outcome, PaymentOutcome::Declined);
+        assert_eq!(declined.response_code, "116");
+    }
+
+    async fn transaction_token(device_id: &str, amount: i64, currency: &str) -> String {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let health_check =
+            HealthCheck::new(device_id.to_string(), 95, false, false, true, true, true);
+        TransactionTokenService::new(jwt_service, None)
+            .generate_bound_token(device_id, &health_check, amount, currency)
+            .await
+            .unwrap()
+            .token
+    }
+
+    /// 记录所有发出的通知
+    #[derive(Default)]
+    struct RecordingSink {
+        notifications: Mutex<Vec<Notification>>,
+    }
+
+    impl NotificationSink for RecordingSink {
+        fn notify(&self, notification: Notification) {
+            self.notifications.lock().unwrap().push(notification);
+        }
+    }
+
+    /// 按收单机构商户号前缀8881路由到模拟收单机构的交易服务
+    fn acquirer_service(pool: &SqlitePool, acquirer: &MockAcquirer) -> TransactionService {
+        let config = PaymentConfig {
+            default_connector: "simulator".to_string(),
+            connectors: vec![PaymentConnectorConfig {
+                name: "acquirer-tcp".to_string(),
+                connector_type: PaymentConnectorType::Iso8583Tcp,
+                url: None,
+                headers: Default::default(),
+                address: Some(acquirer.address.clone()),
+                spec_file: Some(SPEC_1987.to_string()),
+                echo_interval_seconds: 0,
+                timeout_seconds: 5,
+                latency_ms: 0,
+                scenarios: Vec::new(),
+            }],
+            routes: vec![PaymentRoute {
+                connector: "acquirer-tcp".to_string(),
+                merchant_ids: Vec::new(),
+                acquirer_mid_prefixes: vec!["8881".to_string()],
+            }],
+            preauth_validity_hours: 168,
+            reversal_max_attempts: 10,
+            reversal_retry_base_seconds: 30,
+            reversal_poll_interval_seconds: 5,
+        };
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        TransactionService::new(
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec()),
+            None,
+            Arc::new(
+                TransactionTokenService::new(jwt_service, None)
+                    .with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+            ),
+        )
+        .with_payment_router(Arc::new(PaymentRouter::from_config(&config).unwrap()))
+        .with_merchant_repo(MerchantRepository::unscoped(pool.clone()))
+    }
+
+    /// 已激活并分配给收单商户号888100000000001、终端号00000001的设备
+    async fn acquirer_device(pool: &SqlitePool, imei: &str) -> Device {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let devices = DeviceRepository::unscoped(pool.clone());
+        devices.create(&device).await.unwrap();
+        devices
+            .update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        devices
+            .update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+        let merchant = Merchant::new(
+            "Coffee Shop".to_string(),
+            "5812".to_string(),
+            "US".to_string(),
+            "USD".to_string(),
+            Some("888100000000001".to_string()),
+            None,
+        );
+        MerchantRepository::unscoped(pool.clone()).create(&merchant).await.unwrap();
+        let store = Store::new(merchant.id.clone(), "Main".to_string(), None);
+        StoreRepository::unscoped(pool.clone()).create(&store).await.unwrap();
+        devices
+            .assign_store(&device.id, &merchant.id, &merchant.name, &store.id, "00000001")
+            .await
+            .unwrap();
+        device
+    }
+
+    async fn transaction_request(device_id: &str, amount: i64) -> ProcessTransactionRequest {
+        ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type: TransactionType::Payment,
+            amount,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "0123456789ABCDEF".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1234".to_string()),
+            transaction_token: transaction_token(device_id, amount, "USD").await,
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: None,
+            client_transaction_id: None,
+            emv_data: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configured_merchant_routed_to_acquirer() {
+        let pool = setup_test_db().await;
+        let acquirer = MockAcquirer::start(SPEC_1987).await;
+        let service = acquirer_service(&pool, &acquirer);
+        let device = acquirer_device(&pool, "600000000000001").await;
+
+        let request = transaction_request(&device.id, 1550).await;
+        let response = service.process_transaction(request, "device").await.unwrap();
+        assert_eq!(response.status, TransactionStatus::Approved);
+        assert_eq!(response.approved_amount, Some(1000));
+
+        let sent = acquirer.received("0200");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].get_str(41), Some("00000001"));
+        assert_eq!(sent[0].get_str(42), Some("888100000000001"));
+
+        let record = TransactionRepository::unscoped(pool.clone())
+            .find_by_id(&response.transaction_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.processor.as_deref(), Some("acquirer-tcp"));
+        assert_eq!(record.processor_reference.as_deref(), sent[0].get_str(37));
+
+        // 请求写出后收单机构断开连接：结果未知，保留预登记的冲正
+        let request = transaction_request(&device.id, 6600).await;
+        let response = service.process_transaction(request, "device").await.unwrap();
+        assert_eq!(response.status, TransactionStatus::Unknown);
+        assert_eq!(acquirer.received("0200").len(), 2);
+        let reversal = TransactionRepository::unscoped(pool.clone())
+            .find_reversal(&response.transaction_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reversal.status, ReversalStatus::Pending.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_reversal_uses_stored_data_elements() {
+        let pool = setup_test_db().await;
+        let acquirer = MockAcquirer::start(SPEC_1987).await;
+        let sink = Arc::new(RecordingSink::default());
+        let service = acquirer_service(&pool, &acquirer)
+            .with_notifier(NotificationServiceWrapper::new(sink.clone()));
+        let device = acquirer_device(&pool, "600000000000002").await;
+        let repo = TransactionRepository::unscoped(pool.clone());
+
+        // 发送前保存的数据元与报文一致，冲正引用保存的数据元
+        let approved = service
+            .process_transaction(transaction_request(&device.id, 4400).await, "device")
+            .await
+            .unwrap();
+        let sent = acquirer.received("0200").pop().unwrap();
+        let transaction = repo.find_by_id(&approved.transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.message_mti.as_deref(), Some("0200"));
+        assert_eq!(transaction.stan.as_deref(), sent.get_str(11));
+        assert_eq!(transaction.rrn.as_deref(), sent.get_str(37));
+        assert_eq!(transaction.transmission_time.as_deref(), sent.get_str(7));
+
+        service.request_operator_reversal(&approved.transaction_id, "admin").await.unwrap();
+        assert_eq!(service.process_reversals().await.unwrap(), 1);
+        let reversal = acquirer.received("0400").pop().unwrap();
+        let original_data = reversal.get_str(90).unwrap();
+        assert_eq!(&original_data[4..10], transaction.stan.as_deref().unwrap());
+        // 携带原报文数据元时，找不到原交易说明原交易未生效
+        let transaction = repo.find_by_id(&approved.transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Reversed);
+
+        // 没有保存数据元的交易：找不到原交易不视为冲正成功，冲正保持待处理并告警
+        let approved = service
+            .process_transaction(transaction_request(&device.id, 4400).await, "device")
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE transactions SET message_mti = NULL, stan = NULL, rrn = NULL, \
+             transmission_time = NULL WHERE id = ?",
+        )
+        .bind(&approved.transaction_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        service.request_operator_reversal(&approved.transaction_id, "admin").await.unwrap();
+        assert_eq!(service.process_reversals().await.unwrap(), 0);
+        let reversal = acquirer.received("0400").pop().unwrap();
+        assert_eq!(reversal.get_str(90), None);
+
+        let transaction = repo.find_by_id(&approved.transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Approved);
+        let queued = repo.find_reversal(&approved.transaction_id).await.unwrap().unwrap();
+        assert_eq!(queued.status, ReversalStatus::Pending.as_str());
+        assert_eq!(queued.response_code.as_deref(), Some("25"));
+        assert!(queued.last_error.unwrap().contains("original data elements"));
+
+        let alerts = sink.notifications.lock().unwrap().clone();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].notification_type, NotificationType::SystemAlert);
+        assert_eq!(alerts[0].data.as_ref().unwrap()["transaction_id"], approved.transaction_id);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
@@ -0,0 +1,203 @@
+// Integration tests for Merchant Service
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod merchant_service_tests {
+    use super::*;
+    use crate::dto::{AssignDeviceRequest, CreateMerchantRequest, CreateStoreRequest};
+    use crate::models::{Device, DeviceMode, DeviceStatus, TeeType};
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, MerchantRepository, StoreRepository,
+    };
+    use crate::services::MerchantService;
+
+    fn create_service(pool: &SqlitePool) -> MerchantService {
+        MerchantService::new(
+            MerchantRepository::unscoped(pool.clone()),
+            StoreRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+    }
+
+    fn merchant_request(name: &str, mid: Option<&str>) -> CreateMerchantRequest {
+        CreateMerchantRequest {
+            name: name.to_string(),
+            mcc: "5814".to_string(),
+            country: "CN".to_string(),
+            currency: "CNY".to_string(),
+            acquirer_mid: mid.map(|m| m.to_string()),
+            address: None,
+        }
+    }
+
+    fn store_request(name: &str) -> CreateStoreRequest {
+        CreateStoreRequest { name: name.to_string(), address: None }
+    }
+
+    #[tokio::test]
+    async fn test_create_merchant_and_store() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let merchant = service
+            .create_merchant(merchant_request("Coffee Shop", Some("123456789012345")), "admin")
+            .await
+            .unwrap();
+        assert_eq!(merchant.status, "ACTIVE");
+
+        let store = service
+            .create_store(&merchant.id, store_request("Downtown"), "admin")
+            .await
+            .unwrap();
+        assert_eq!(store.merchant_id, merchant.id);
+
+        let stores = service.list_stores(&merchant.id).await.unwrap();
+        assert_eq!(stores.total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_acquirer_mid_rejected() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        service
+            .create_merchant(merchant_request("First", Some("MID0001")), "admin")
+            .await
+            .unwrap();
+        let result = service
+            .create_merchant(merchant_request("Second", Some("MID0001")), "admin")
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_assign_device_generates_sequential_terminal_ids() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let merchant = service
+            .create_merchant(merchant_request("Coffee Shop", None), "admin")
+            .await
+            .unwrap();
+        let store = service
+            .create_store(&merchant.id, store_request("Downtown"), "admin")
+            .await
+            .unwrap();
+
+        let first = create_test_device(&pool, "123456789012345").await;
+        let second = create_test_device(&pool, "123456789012346").await;
+
+        let first = service
+            .assign_device(&first, AssignDeviceRequest { store_id: store.id.clone() }, "admin")
+            .await
+            .unwrap();
+        let second = service
+            .assign_device(&second, AssignDeviceRequest { store_id: store.id.clone() }, "admin")
+            .await
+            .unwrap();
+
+        assert_eq!(first.merchant_id.as_deref(), Some(merchant.id.as_str()));
+        assert_eq!(first.store_id.as_deref(), Some(store.id.as_str()));
+        assert_eq!(first.terminal_id.as_deref(), Some("00000001"));
+        assert_eq!(second.terminal_id.as_deref(), Some("00000002"));
+
+        let devices = service.list_store_devices(&store.id).await.unwrap();
+        assert_eq!(devices.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reassign_within_merchant_keeps_terminal_id() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let merchant = service
+            .create_merchant(merchant_request("Coffee Shop", None), "admin")
+            .await
+            .unwrap();
+        let store_a =
+            service.create_store(&merchant.id, store_request("A"), "admin").await.unwrap();
+        let store_b =
+            service.create_store(&merchant.id, store_request("B"), "admin").await.unwrap();
+        let other = service
+            .create_merchant(merchant_request("Book Shop", None), "admin")
+            .await
+            .unwrap();
+        let store_c = service.create_store(&other.id, store_request("C"), "admin").await.unwrap();
+
+        let device_id = create_test_device(&pool, "123456789012345").await;
+
+        let assigned = service
+            .assign_device(&device_id, AssignDeviceRequest { store_id: store_a.id }, "admin")
+            .await
+            .unwrap();
+        let moved = service
+            .assign_device(
+                &device_id,
+                AssignDeviceRequest { store_id: store_b.id.clone() },
+                "admin",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(moved.terminal_id, assigned.terminal_id);
+        assert_eq!(moved.store_id.as_deref(), Some(store_b.id.as_str()));
+
+        // 跨商户分配时重新分配终端号
+        let transferred = service
+            .assign_device(&device_id, AssignDeviceRequest { store_id: store_c.id }, "admin")
+            .await
+            .unwrap();
+
+        assert_eq!(transferred.merchant_id.as_deref(), Some(other.id.as_str()));
+        assert_eq!(transferred.terminal_id.as_deref(), Some("00000001"));
+    }
+
+    #[tokio::test]
+    async fn test_assign_device_to_unknown_store() {
+        let pool = setup_test_db().await;
+        let service = create_service(&pool);
+
+        let device_id = create_test_device(&pool, "123456789012345").await;
+        let result = service
+            .assign_device(
+                &device_id,
+                AssignDeviceRequest { store_id: uuid::Uuid::new_v4().to_string() },
+                "admin",
+            )
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    async fn create_test_device(pool: &SqlitePool, imei: &str) -> String {
+        let repo = DeviceRepository::unscoped(pool.clone());
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        repo.create(&device).await.unwrap();
+        repo.update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+
+        device.id
+    }
+}
+
+// Helper functions
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    pool
+}
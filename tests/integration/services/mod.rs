@@ -1 +1,21 @@
+pub mod device_command_test;
+pub mod device_group_test;
+pub mod device_import_test;
+pub mod device_lifecycle_test;
+pub mod merchant_service_test;
+pub mod tenant_isolation_test;
 pub mod transaction_service_test;
+pub mod notification_trigger_test;
+pub mod webhook_test;
+pub mod alerting_test;
+pub mod outbox_test;
+pub mod unit_of_work_test;
+pub mod payment_processor_test;
+pub mod iso8583_test;
+pub mod transaction_lifecycle_test;
+pub mod idempotency_test;
+pub mod transaction_reversal_test;
+pub mod transaction_token_test;
+pub mod settlement_test;
+pub mod reconciliation_test;
+pub mod emv_transaction_test;
@@ -0,0 +1,308 @@
+// Integration tests for notifications emitted by business services
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod notification_trigger_tests {
+    use super::*;
+    use crate::api::websocket::{Notification, NotificationType};
+    use crate::dto::{ApproveDeviceRequest, EncryptPinRequest, HealthCheckRequest};
+    use crate::models::{Device, DeviceMode, TeeType, ThreatSeverity, ThreatType};
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, OutboxRepository,
+        ThreatRepository,
+    };
+    use crate::security::DukptKeyDerivation;
+    use crate::services::{
+        DeviceService, HealthCheckService, KeyManagementService, NotificationServiceWrapper,
+        NotificationSink, OutboxDispatcher, ThreatDetectionService,
+    };
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// 记录所有发出的通知
+    #[derive(Default)]
+    struct RecordingSink {
+        notifications: Mutex<Vec<Notification>>,
+    }
+
+    impl NotificationSink for RecordingSink {
+        fn notify(&self, notification: Notification) {
+            self.notifications.lock().unwrap().push(notification);
+        }
+    }
+
+    impl RecordingSink {
+        fn of_type(&self, notification_type: NotificationType) -> Vec<Notification> {
+            self.notifications
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|n| n.notification_type == notification_type)
+                .cloned()
+                .collect()
+        }
+
+        fn clear(&self) {
+            self.notifications.lock().unwrap().clear();
+        }
+    }
+
+    struct Fixture {
+        pool: SqlitePool,
+        sink: Arc<RecordingSink>,
+        device_service: DeviceService,
+        threat_service: ThreatDetectionService,
+        health_service: HealthCheckService,
+        key_service: KeyManagementService,
+        outbox: OutboxDispatcher,
+    }
+
+    async fn setup(cooldown: Duration) -> Fixture {
+        let pool = setup_test_db().await;
+        let sink = Arc::new(RecordingSink::default());
+        let notifier = NotificationServiceWrapper::new(sink.clone()).with_cooldown(cooldown);
+
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        let threat_repo = ThreatRepository::unscoped(pool.clone());
+        let health_check_repo = HealthCheckRepository::unscoped(pool.clone());
+        let audit_repo = AuditLogRepository::unscoped(pool.clone());
+        let dukpt = DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec());
+
+        let device_service = DeviceService::new(
+            device_repo.clone(),
+            threat_repo.clone(),
+            audit_repo.clone(),
+            dukpt.clone(),
+            None,
+        );
+
+        let threat_service = ThreatDetectionService::new(
+            threat_repo.clone(),
+            device_repo.clone(),
+            health_check_repo.clone(),
+            audit_repo.clone(),
+        );
+
+        let health_service = HealthCheckService::new(
+            health_check_repo,
+            device_repo.clone(),
+            threat_repo.clone(),
+            audit_repo.clone(),
+            threat_service.clone(),
+        )
+        .with_notifier(notifier.clone());
+
+        let key_service = KeyManagementService::new(device_repo.clone(), audit_repo, dukpt, None)
+            .with_notifier(notifier.clone());
+
+        // 状态变更和威胁告警由发件箱分发器转发
+        let outbox =
+            OutboxDispatcher::new(OutboxRepository::new(pool.clone()), device_repo, threat_repo)
+                .with_notifier(notifier);
+
+        Fixture { pool, sink, device_service, threat_service, health_service, key_service, outbox }
+    }
+
+    async fn create_active_device(fixture: &Fixture, imei: &str) -> String {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        DeviceRepository::unscoped(fixture.pool.clone()).create(&device).await.unwrap();
+        sqlx::query("UPDATE devices SET merchant_id = 'merchant-1' WHERE id = ?")
+            .bind(&device.id)
+            .execute(&fixture.pool)
+            .await
+            .unwrap();
+
+        fixture
+            .device_service
+            .approve_device(ApproveDeviceRequest {
+                device_id: device.id.clone(),
+                operator: "admin".to_string(),
+            })
+            .await
+            .unwrap();
+
+        // 丢弃注册和审批产生的通知
+        fixture.outbox.dispatch_pending().await.unwrap();
+        fixture.sink.clear();
+
+        device.id
+    }
+
+    fn compromised_check(device_id: &str) -> HealthCheckRequest {
+        HealthCheckRequest {
+            device_id: device_id.to_string(),
+            root_detection: true,
+            emulator_detection: false,
+            debugger_detection: true,
+            hook_detection: true,
+            tampering_detection: false,
+            signature: "signature".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_suspend_and_revoke_emit_status_changes() {
+        let fixture = setup(Duration::from_secs(300)).await;
+        let device_id = create_active_device(&fixture, "100000000000001").await;
+
+        fixture
+            .device_service
+            .suspend_device(&device_id, "admin", "Lost")
+            .await
+            .unwrap();
+        fixture
+            .device_service
+            .revoke_device(&device_id, "admin", "Stolen")
+            .await
+            .unwrap();
+        assert!(fixture.sink.of_type(NotificationType::DeviceStatusChange).is_empty());
+
+        assert_eq!(fixture.outbox.dispatch_pending().await.unwrap(), 2);
+
+        let changes = fixture.sink.of_type(NotificationType::DeviceStatusChange);
+        assert_eq!(changes.len(), 2);
+
+        let suspended = changes[0].data.as_ref().unwrap();
+        assert_eq!(suspended["old_status"], "ACTIVE");
+        assert_eq!(suspended["new_status"], "SUSPENDED");
+        assert_eq!(changes[0].device_id.as_deref(), Some(device_id.as_str()));
+        assert_eq!(changes[0].merchant_id.as_deref(), Some("merchant-1"));
+        assert_eq!(changes[0].tenant_id.as_deref(), Some(crate::models::DEFAULT_TENANT_ID));
+
+        assert_eq!(changes[1].data.as_ref().unwrap()["new_status"], "REVOKED");
+    }
+
+    #[tokio::test]
+    async fn test_low_score_emits_security_and_threat_alerts() {
+        let fixture = setup(Duration::from_secs(300)).await;
+        let device_id = create_active_device(&fixture, "100000000000002").await;
+
+        let response = fixture
+            .health_service
+            .submit_health_check(compromised_check(&device_id), "device")
+            .await
+            .unwrap();
+        assert!(response.security_score < 60);
+
+        let security = fixture.sink.of_type(NotificationType::SecurityAlert);
+        assert_eq!(security.len(), 1);
+        assert_eq!(security[0].data.as_ref().unwrap()["security_score"], response.security_score);
+
+        fixture.outbox.dispatch_pending().await.unwrap();
+
+        // 评分过低触发威胁处理，设备被系统暂停或吊销
+        assert!(!fixture.sink.of_type(NotificationType::ThreatAlert).is_empty());
+        assert!(!fixture.sink.of_type(NotificationType::DeviceStatusChange).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reported_threat_emits_alert() {
+        let fixture = setup(Duration::from_secs(300)).await;
+        let device_id = create_active_device(&fixture, "100000000000003").await;
+
+        let threat = fixture
+            .threat_service
+            .report_threat(
+                &device_id,
+                ThreatType::RootDetection,
+                ThreatSeverity::High,
+                "Root detected".to_string(),
+            )
+            .await
+            .unwrap();
+        fixture.outbox.dispatch_pending().await.unwrap();
+
+        let alerts = fixture.sink.of_type(NotificationType::ThreatAlert);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].threat_id.as_deref(), Some(threat.id.as_str()));
+        assert_eq!(alerts[0].data.as_ref().unwrap()["severity"], "HIGH");
+
+        let changes = fixture.sink.of_type(NotificationType::DeviceStatusChange);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].data.as_ref().unwrap()["new_status"], "SUSPENDED");
+
+        // 低危威胁只记录不告警
+        fixture
+            .threat_service
+            .report_threat(&device_id, ThreatType::Other, ThreatSeverity::Low, "Minor".to_string())
+            .await
+            .unwrap();
+        fixture.outbox.dispatch_pending().await.unwrap();
+        assert_eq!(fixture.sink.of_type(NotificationType::ThreatAlert).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_alerts_deduplicated_within_cooldown() {
+        let fixture = setup(Duration::from_secs(300)).await;
+        let device_id = create_active_device(&fixture, "100000000000004").await;
+
+        for _ in 0..3 {
+            fixture
+                .health_service
+                .submit_health_check(compromised_check(&device_id), "device")
+                .await
+                .unwrap();
+        }
+        assert_eq!(fixture.sink.of_type(NotificationType::SecurityAlert).len(), 1);
+
+        // 冷却时间为零时不去重
+        let fixture = setup(Duration::ZERO).await;
+        let device_id = create_active_device(&fixture, "100000000000005").await;
+
+        for _ in 0..3 {
+            fixture
+                .health_service
+                .submit_health_check(compromised_check(&device_id), "device")
+                .await
+                .unwrap();
+        }
+        assert_eq!(fixture.sink.of_type(NotificationType::SecurityAlert).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_key_exhaustion_emits_key_warning() {
+        let fixture = setup(Duration::from_secs(300)).await;
+        let device_id = create_active_device(&fixture, "100000000000006").await;
+        DeviceRepository::unscoped(fixture.pool.clone())
+            .update_key_info(&device_id, "FFFF9876543210E00001", Some("now"), Some(2), Some(100))
+            .await
+            .unwrap();
+
+        let request = || EncryptPinRequest {
+            device_id: device_id.clone(),
+            pin: "1234".to_string(),
+            attestation_token: "token".to_string(),
+        };
+
+        fixture.key_service.encrypt_pin(request(), "device").await.unwrap();
+        let warnings = fixture.sink.of_type(NotificationType::KeyWarning);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].data.as_ref().unwrap()["remaining_count"], 1);
+
+        // 冷却期内耗尽不再重复预警
+        fixture.key_service.encrypt_pin(request(), "device").await.unwrap();
+        assert_eq!(fixture.sink.of_type(NotificationType::KeyWarning).len(), 1);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
@@ -0,0 +1,307 @@
+// Integration tests for the transactional outbox
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod outbox_tests {
+    use super::*;
+    use crate::api::websocket::{Notification, NotificationType};
+    use crate::models::{
+        Device, DeviceMode, DeviceStatus, DomainEvent, OutboxStatus, SdkVersion, TeeType,
+        ThreatEvent, ThreatSeverity, ThreatType, Transaction, TransactionStatus, TransactionType,
+        UpdateType, VersionStatus,
+    };
+    use crate::repositories::{
+        DeviceRepository, OutboxRepository, ThreatRepository, TransactionRepository,
+        VersionRepository,
+    };
+    use crate::services::{NotificationServiceWrapper, NotificationSink, OutboxDispatcher};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// 记录所有发出的通知
+    #[derive(Default)]
+    struct RecordingSink {
+        notifications: Mutex<Vec<Notification>>,
+    }
+
+    impl NotificationSink for RecordingSink {
+        fn notify(&self, notification: Notification) {
+            self.notifications.lock().unwrap().push(notification);
+        }
+    }
+
+    impl RecordingSink {
+        fn count(&self, notification_type: NotificationType) -> usize {
+            self.notifications
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|n| n.notification_type == notification_type)
+                .count()
+        }
+    }
+
+    fn dispatcher(pool: &SqlitePool, sink: Arc<RecordingSink>) -> OutboxDispatcher {
+        OutboxDispatcher::new(
+            OutboxRepository::new(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            ThreatRepository::unscoped(pool.clone()),
+        )
+        .with_notifier(NotificationServiceWrapper::new(sink).with_cooldown(Duration::ZERO))
+    }
+
+    async fn create_device(pool: &SqlitePool, imei: &str) -> Device {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        DeviceRepository::unscoped(pool.clone()).create(&device).await.unwrap();
+        device
+    }
+
+    fn event_types(events: &[crate::models::OutboxEvent]) -> Vec<&str> {
+        events.iter().map(|e| e.event_type.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_state_changes_write_events() {
+        let pool = setup_test_db().await;
+        let outbox = OutboxRepository::new(pool.clone());
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+
+        let device = create_device(&pool, "300000000000001").await;
+        device_repo
+            .update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        device_repo
+            .update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+        // 仅更新计数不是密钥注入
+        device_repo
+            .update_key_info(&device.id, "FFFF9876543210E00001", None, Some(99), None)
+            .await
+            .unwrap();
+
+        let events = outbox.list_by_aggregate(&device.id).await.unwrap();
+        assert_eq!(
+            event_types(&events),
+            vec!["DeviceRegistered", "DeviceStatusChanged", "KeyInjected"]
+        );
+        match events[1].event() {
+            Some(DomainEvent::DeviceStatusChanged { from_status, to_status, .. }) => {
+                assert_eq!(from_status.as_deref(), Some("PENDING"));
+                assert_eq!(to_status, "ACTIVE");
+            },
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // 待处理交易不产生事件，完成后产生
+        let mut transaction = Transaction::new(
+            device.id.clone(),
+            TransactionType::Payment,
+            1000,
+            "USD".to_string(),
+            "FFFF9876543210E00001".to_string(),
+        );
+        let transaction_repo = TransactionRepository::unscoped(pool.clone());
+        transaction_repo.create(&transaction).await.unwrap();
+        assert!(outbox.list_by_aggregate(&transaction.id).await.unwrap().is_empty());
+
+        transaction.id = uuid::Uuid::new_v4().to_string();
+        transaction.status = TransactionStatus::Approved;
+        transaction_repo.create(&transaction).await.unwrap();
+        assert_eq!(
+            event_types(&outbox.list_by_aggregate(&transaction.id).await.unwrap()),
+            vec!["TransactionProcessed"]
+        );
+
+        let threat = ThreatEvent::new(
+            device.id.clone(),
+            ThreatType::RootDetection,
+            ThreatSeverity::High,
+            "Root detected".to_string(),
+        );
+        ThreatRepository::unscoped(pool.clone()).create(&threat).await.unwrap();
+        assert_eq!(
+            event_types(&outbox.list_by_aggregate(&threat.id).await.unwrap()),
+            vec!["ThreatDetected"]
+        );
+
+        // 只有首次发布产生事件
+        let version = SdkVersion::new(
+            "2.0.0".to_string(),
+            UpdateType::Optional,
+            "https://example.com/sdk.apk".to_string(),
+            "checksum".to_string(),
+            1024,
+            "notes".to_string(),
+        );
+        let version_repo = VersionRepository::unscoped(pool.clone());
+        version_repo.create(&version).await.unwrap();
+        assert!(outbox.list_by_aggregate(&version.id).await.unwrap().is_empty());
+        version_repo.update_status(&version.id, VersionStatus::Released).await.unwrap();
+        version_repo.update_status(&version.id, VersionStatus::Released).await.unwrap();
+        assert_eq!(
+            event_types(&outbox.list_by_aggregate(&version.id).await.unwrap()),
+            vec!["VersionPublished"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_state_change_writes_no_event() {
+        let pool = setup_test_db().await;
+        let outbox = OutboxRepository::new(pool.clone());
+
+        let device = create_device(&pool, "300000000000002").await;
+
+        // 重复注册失败，事务回滚，事件也不写入
+        assert!(DeviceRepository::unscoped(pool.clone()).create(&device).await.is_err());
+        assert_eq!(
+            event_types(&outbox.list_by_aggregate(&device.id).await.unwrap()),
+            vec!["DeviceRegistered"]
+        );
+        assert_eq!(outbox.count_by_status(OutboxStatus::Pending).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_relays_in_order_and_marks_dispatched() {
+        let pool = setup_test_db().await;
+        let sink = Arc::new(RecordingSink::default());
+        let dispatcher = dispatcher(&pool, sink.clone());
+        let outbox = OutboxRepository::new(pool.clone());
+
+        let device = create_device(&pool, "300000000000003").await;
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        device_repo
+            .update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        device_repo
+            .update_status(
+                &device.id,
+                DeviceStatus::Active,
+                DeviceStatus::Suspended,
+                "admin",
+                Some("Lost"),
+            )
+            .await
+            .unwrap();
+
+        let threat = ThreatEvent::new(
+            device.id.clone(),
+            ThreatType::AppTamper,
+            ThreatSeverity::Critical,
+            "App tampered".to_string(),
+        );
+        ThreatRepository::unscoped(pool.clone()).create(&threat).await.unwrap();
+        let low = ThreatEvent::new(
+            device.id.clone(),
+            ThreatType::Other,
+            ThreatSeverity::Low,
+            "Minor".to_string(),
+        );
+        ThreatRepository::unscoped(pool.clone()).create(&low).await.unwrap();
+
+        // 事务提交后才转发，写入时不会发出通知
+        assert_eq!(sink.count(NotificationType::DeviceStatusChange), 0);
+
+        assert_eq!(dispatcher.dispatch_pending().await.unwrap(), 5);
+        assert_eq!(outbox.count_by_status(OutboxStatus::Dispatched).await.unwrap(), 5);
+        assert_eq!(outbox.count_by_status(OutboxStatus::Pending).await.unwrap(), 0);
+
+        let notifications = sink.notifications.lock().unwrap().clone();
+        let changes: Vec<_> = notifications
+            .iter()
+            .filter(|n| n.notification_type == NotificationType::DeviceStatusChange)
+            .map(|n| n.data.as_ref().unwrap()["new_status"].clone())
+            .collect();
+        assert_eq!(changes, vec!["ACTIVE", "SUSPENDED"]);
+
+        // 低危威胁只记录不告警
+        assert_eq!(sink.count(NotificationType::ThreatAlert), 1);
+
+        // 已分发的事件不会重复转发
+        assert_eq!(dispatcher.dispatch_pending().await.unwrap(), 0);
+        assert_eq!(sink.notifications.lock().unwrap().len(), notifications.len());
+    }
+
+    #[tokio::test]
+    async fn test_failed_relay_retried_then_marked_failed() {
+        let pool = setup_test_db().await;
+        let sink = Arc::new(RecordingSink::default());
+        let dispatcher = dispatcher(&pool, sink.clone()).with_retry_policy(2, Duration::ZERO);
+        let outbox = OutboxRepository::new(pool.clone());
+
+        // 无法解析的事件每次转发都会失败
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query(
+            r#"
+            INSERT INTO outbox (id, event_type, aggregate_id, payload, status, attempts, next_attempt_at, created_at)
+            VALUES ('broken', 'Unknown', 'aggregate-1', '{}', 'PENDING', 0, ?, ?)
+            "#,
+        )
+        .bind(&now)
+        .bind(&now)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let device = create_device(&pool, "300000000000004").await;
+
+        // 失败的事件不阻塞后续事件
+        assert_eq!(dispatcher.dispatch_pending().await.unwrap(), 1);
+        let broken = outbox.find_by_id("broken").await.unwrap().unwrap();
+        assert_eq!(broken.status, "PENDING");
+        assert_eq!(broken.attempts, 1);
+        assert!(broken.last_error.unwrap().contains("Invalid outbox payload"));
+        assert_eq!(outbox.list_by_aggregate(&device.id).await.unwrap()[0].status, "DISPATCHED");
+
+        assert_eq!(dispatcher.dispatch_pending().await.unwrap(), 0);
+        let broken = outbox.find_by_id("broken").await.unwrap().unwrap();
+        assert_eq!(broken.status, "FAILED");
+        assert_eq!(broken.attempts, 2);
+
+        // FAILED的事件不再领取
+        assert_eq!(dispatcher.dispatch_pending().await.unwrap(), 0);
+        assert_eq!(outbox.count_by_status(OutboxStatus::Failed).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_claimed_event_not_dispatched_twice() {
+        let pool = setup_test_db().await;
+        let sink = Arc::new(RecordingSink::default());
+        let outbox = OutboxRepository::new(pool.clone());
+
+        let device = create_device(&pool, "300000000000005").await;
+        let event = outbox.list_by_aggregate(&device.id).await.unwrap().remove(0);
+
+        // 其他节点已领取，租约内本节点跳过
+        let now = chrono::Utc::now();
+        let lease_until = (now + chrono::Duration::seconds(60)).to_rfc3339();
+        assert!(outbox.claim(&event.id, &now.to_rfc3339(), &lease_until).await.unwrap());
+        assert!(!outbox.claim(&event.id, &now.to_rfc3339(), &lease_until).await.unwrap());
+
+        assert_eq!(dispatcher(&pool, sink).dispatch_pending().await.unwrap(), 0);
+        assert_eq!(outbox.count_by_status(OutboxStatus::Pending).await.unwrap(), 1);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
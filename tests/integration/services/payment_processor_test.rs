@@ -0,0 +1,451 @@
+// Integration tests for payment processor connectors and routing
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod payment_processor_tests {
+    use super::*;
+    use crate::dto::ProcessTransactionRequest;
+    use crate::infrastructure::config::{
+        PaymentConfig, PaymentConnectorConfig, PaymentConnectorType, PaymentRoute, SimulatorResult,
+        SimulatorScenario,
+    };
+    use crate::infrastructure::payment::{
+        HttpGatewayConnector, PaymentOperation, PaymentOutcome, PaymentProcessor, PaymentRequest,
+        PaymentRouter,
+    };
+    use crate::models::{
+        Device, DeviceMode, DeviceStatus, HealthCheck, Merchant, ReversalStatus, Store, TeeType,
+        TransactionStatus, TransactionType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, MerchantRepository,
+        StoreRepository, TransactionRepository, TransactionTokenRepository,
+    };
+    use crate::security::{DukptKeyDerivation, JwtService};
+    use crate::services::{TransactionService, TransactionTokenService};
+    use crate::utils::error::AppError;
+    use std::{sync::Arc, time::Duration};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn scenario(amount: i64, result: SimulatorResult) -> SimulatorScenario {
+        SimulatorScenario {
+            amount: Some(amount),
+            operations: Vec::new(),
+            merchant_id: None,
+            result,
+            response_code: None,
+            message: None,
+            approved_amount: None,
+        }
+    }
+
+    fn simulator_config(name: &str, scenarios: Vec<SimulatorScenario>) -> PaymentConnectorConfig {
+        PaymentConnectorConfig {
+            name: name.to_string(),
+            connector_type: PaymentConnectorType::Simulator,
+            url: None,
+            headers: Default::default(),
+            address: None,
+            spec_file: None,
+            echo_interval_seconds: 60,
+            timeout_seconds: 30,
+            latency_ms: 0,
+            scenarios,
+        }
+    }
+
+    /// 与开发环境配置一致的脚本场景
+    fn scripted_config() -> PaymentConfig {
+        PaymentConfig {
+            default_connector: "simulator".to_string(),
+            connectors: vec![simulator_config(
+                "simulator",
+                vec![
+                    SimulatorScenario {
+                        response_code: Some("51".to_string()),
+                        ..scenario(5100, SimulatorResult::Decline)
+                    },
+                    SimulatorScenario {
+                        approved_amount: Some(1000),
+                        ..scenario(1550, SimulatorResult::PartialApproval)
+                    },
+                    scenario(9100, SimulatorResult::Timeout),
+                ],
+            )],
+            routes: Vec::new(),
+            preauth_validity_hours: 168,
+            reversal_max_attempts: 10,
+            reversal_retry_base_seconds: 30,
+            reversal_poll_interval_seconds: 5,
+        }
+    }
+
+    fn transaction_service(pool: &SqlitePool, router: PaymentRouter) -> TransactionService {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        TransactionService::new(
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec()),
+            None,
+            Arc::new(
+                TransactionTokenService::new(jwt_service, None)
+                    .with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+            ),
+        )
+        .with_payment_router(Arc::new(router))
+        .with_merchant_repo(MerchantRepository::unscoped(pool.clone()))
+    }
+
+    async fn create_active_device(pool: &SqlitePool, imei: &str) -> Device {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        repo.update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        repo.update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+        device
+    }
+
+    /// 把设备分配给指定收单商户号的商户
+    async fn assign_merchant(pool: &SqlitePool, device_id: &str, acquirer_mid: &str) -> String {
+        let merchant = Merchant::new(
+            "Coffee Shop".to_string(),
+            "5812".to_string(),
+            "US".to_string(),
+            "USD".to_string(),
+            Some(acquirer_mid.to_string()),
+            None,
+        );
+        MerchantRepository::unscoped(pool.clone()).create(&merchant).await.unwrap();
+        let store = Store::new(merchant.id.clone(), "Main".to_string(), None);
+        StoreRepository::unscoped(pool.clone()).create(&store).await.unwrap();
+        DeviceRepository::unscoped(pool.clone())
+            .assign_store(device_id, &merchant.id, &merchant.name, &store.id, "00000001")
+            .await
+            .unwrap();
+        merchant.id
+    }
+
+    async fn transaction_token(device_id: &str, amount: i64, currency: &str) -> String {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let health_check =
+            HealthCheck::new(device_id.to_string(), 95, false, false, true, true, true);
+        TransactionTokenService::new(jwt_service, None)
+            .generate_bound_token(device_id, &health_check, amount, currency)
+            .await
+            .unwrap()
+            .token
+    }
+
+    async fn request(device_id: &str, amount: i64) -> ProcessTransactionRequest {
+        ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type: TransactionType::Payment,
+            amount,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "encrypted_pin".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1234".to_string()),
+            transaction_token: transaction_token(device_id, amount, "USD").await,
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: None,
+            client_transaction_id: None,
+            emv_data: None,
+        }
+    }
+
+    fn payment_request(amount: i64) -> PaymentRequest {
+        PaymentRequest {
+            transaction_id: "txn-1".to_string(),
+            transaction_type: TransactionType::Payment,
+            amount,
+            currency: "USD".to_string(),
+            merchant_id: None,
+            acquirer_mid: None,
+            terminal_id: None,
+            card_number_masked: None,
+            encrypted_pin_block: None,
+            ksn: "FFFF9876543210E00000".to_string(),
+            original_reference: None,
+            emv_data: None,
+            identifiers: None,
+            original_identifiers: None,
+        }
+    }
+
+    async fn key_remaining(pool: &SqlitePool, device_id: &str) -> i32 {
+        DeviceRepository::unscoped(pool.clone())
+            .find_by_id(device_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .key_remaining_count
+    }
+
+    #[tokio::test]
+    async fn test_simulator_scenarios_through_service() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "500000000000001").await;
+        let service =
+            transaction_service(&pool, PaymentRouter::from_config(&scripted_config()).unwrap());
+        let transactions = TransactionRepository::unscoped(pool.clone());
+
+        // 未命中场景时批准
+        let approved = service
+            .process_transaction(request(&device.id, 10000).await, "device")
+            .await
+            .unwrap();
+        assert_eq!(approved.status, TransactionStatus::Approved);
+        assert_eq!(approved.approved_amount, Some(10000));
+        let record = transactions.find_by_id(&approved.transaction_id).await.unwrap().unwrap();
+        assert_eq!(record.processor.as_deref(), Some("simulator"));
+        assert_eq!(record.response_code.as_deref(), Some("00"));
+        assert!(record.processor_reference.is_some());
+        assert_eq!(key_remaining(&pool, &device.id).await, 99);
+
+        let declined = service
+            .process_transaction(request(&device.id, 5100).await, "device")
+            .await
+            .unwrap();
+        assert_eq!(declined.status, TransactionStatus::Declined);
+        assert_eq!(declined.message, "Insufficient funds");
+        assert!(declined.authorization_code.is_none());
+        let record = transactions.find_by_id(&declined.transaction_id).await.unwrap().unwrap();
+        assert_eq!(record.response_code.as_deref(), Some("51"));
+        assert_eq!(key_remaining(&pool, &device.id).await, 99);
+
+        let partial = service
+            .process_transaction(request(&device.id, 1550).await, "device")
+            .await
+            .unwrap();
+        assert_eq!(partial.status, TransactionStatus::Approved);
+        assert_eq!(partial.approved_amount, Some(1000));
+        let record = transactions.find_by_id(&partial.transaction_id).await.unwrap().unwrap();
+        assert_eq!(record.amount, 1550);
+        assert_eq!(record.response_code.as_deref(), Some("10"));
+
+        // 超时的交易结果未知，等待冲正
+        let timeout = service
+            .process_transaction(request(&device.id, 9100).await, "device")
+            .await
+            .unwrap();
+        assert_eq!(timeout.status, TransactionStatus::Unknown);
+        let record = transactions.find_by_id(&timeout.transaction_id).await.unwrap().unwrap();
+        assert_eq!(record.response_code.as_deref(), Some("68"));
+        assert_eq!(key_remaining(&pool, &device.id).await, 98);
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_acquirer_mid() {
+        let pool = setup_test_db().await;
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/authorize"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "outcome": "APPROVED",
+                "approved_amount": 2500,
+                "authorization_code": "GW1234",
+                "response_code": "00",
+                "response_message": "Approved by gateway",
+                "processor_reference": "GW-REF-1",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut config = scripted_config();
+        config.connectors.push(PaymentConnectorConfig {
+            name: "acquirer-gateway".to_string(),
+            connector_type: PaymentConnectorType::HttpGateway,
+            url: Some(server.uri()),
+            ..simulator_config("acquirer-gateway", Vec::new())
+        });
+        config.routes.push(PaymentRoute {
+            connector: "acquirer-gateway".to_string(),
+            merchant_ids: Vec::new(),
+            acquirer_mid_prefixes: vec!["8881".to_string()],
+        });
+        let service = transaction_service(&pool, PaymentRouter::from_config(&config).unwrap());
+
+        // 收单商户号命中规则的商户走网关
+        let routed = create_active_device(&pool, "500000000000002").await;
+        assign_merchant(&pool, &routed.id, "888100000001").await;
+        let response = service
+            .process_transaction(request(&routed.id, 2500).await, "device")
+            .await
+            .unwrap();
+        assert_eq!(response.status, TransactionStatus::Approved);
+        assert_eq!(response.authorization_code.as_deref(), Some("GW1234"));
+        let record = TransactionRepository::unscoped(pool.clone())
+            .find_by_id(&response.transaction_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.processor.as_deref(), Some("acquirer-gateway"));
+        assert_eq!(record.processor_reference.as_deref(), Some("GW-REF-1"));
+
+        // 其他商户走默认模拟器
+        let other = create_active_device(&pool, "500000000000003").await;
+        assign_merchant(&pool, &other.id, "777100000001").await;
+        let response = service
+            .process_transaction(request(&other.id, 5100).await, "device")
+            .await
+            .unwrap();
+        assert_eq!(response.status, TransactionStatus::Declined);
+    }
+
+    #[tokio::test]
+    async fn test_http_gateway_connector() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/refund"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "outcome": "DECLINED",
+                "approved_amount": 0,
+                "authorization_code": null,
+                "response_code": "05",
+                "response_message": "Do not honor",
+                "processor_reference": "GW-REF-2",
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/void"))
+            .respond_with(ResponseTemplate::new(502))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/capture"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/reversal"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/authorize"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/health"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let gateway: Arc<dyn PaymentProcessor> = Arc::new(HttpGatewayConnector::new(
+            "gateway",
+            format!("{}/", server.uri()),
+            Duration::from_millis(300),
+        ));
+
+        let declined =
+            gateway.execute(PaymentOperation::Refund, &payment_request(500)).await.unwrap();
+        assert_eq!(declined.outcome, PaymentOutcome::Declined);
+        assert_eq!(declined.response_code, "05");
+
+        // 网关拒绝的请求未被处理；服务端错误或应答无法解析时结果未知
+        assert!(matches!(
+            gateway.execute(PaymentOperation::Capture, &payment_request(500)).await,
+            Err(AppError::External(_))
+        ));
+        assert!(matches!(
+            gateway.execute(PaymentOperation::Void, &payment_request(500)).await,
+            Err(AppError::ProcessorOutcomeUnknown(_))
+        ));
+        assert!(matches!(
+            gateway.execute(PaymentOperation::Reversal, &payment_request(500)).await,
+            Err(AppError::ProcessorOutcomeUnknown(_))
+        ));
+        assert!(matches!(
+            gateway.execute(PaymentOperation::Authorize, &payment_request(500)).await,
+            Err(AppError::ProcessorTimeout(_))
+        ));
+        assert!(gateway.health().await.is_ok());
+
+        let router = PaymentRouter::default().with_connector("gateway", gateway);
+        let health = router.health_check().await;
+        assert_eq!(health.get("gateway"), Some(&true));
+        assert_eq!(health.get("simulator"), Some(&true));
+    }
+
+    #[tokio::test]
+    async fn test_gateway_errors_after_send_keep_provisional_reversal() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "500000000000004").await;
+        let repo = TransactionRepository::unscoped(pool.clone());
+
+        // 网关返回504：请求已发出，交易结果未知，保留冲正
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/authorize"))
+            .respond_with(ResponseTemplate::new(504))
+            .mount(&server)
+            .await;
+        let gateway = HttpGatewayConnector::new("gateway", server.uri(), Duration::from_secs(5));
+        let service =
+            transaction_service(&pool, PaymentRouter::new("gateway", Arc::new(gateway)));
+
+        let response =
+            service.process_transaction(request(&device.id, 1000).await, "device").await.unwrap();
+        assert_eq!(response.status, TransactionStatus::Unknown);
+        let transaction = repo.find_by_id(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.response_code.as_deref(), Some("68"));
+        let reversal = repo.find_reversal(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(reversal.status, ReversalStatus::Pending.as_str());
+
+        // 网关不可达：请求未发出，交易失败，撤销预登记的冲正
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        let gateway = HttpGatewayConnector::new(
+            "gateway",
+            format!("http://{}", address),
+            Duration::from_secs(5),
+        );
+        let service =
+            transaction_service(&pool, PaymentRouter::new("gateway", Arc::new(gateway)));
+
+        let response =
+            service.process_transaction(request(&device.id, 1000).await, "device").await.unwrap();
+        assert_eq!(response.status, TransactionStatus::Failed);
+        let transaction_id = response.transaction_id;
+        let transaction = repo.find_by_id(&transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.response_code.as_deref(), Some("91"));
+        assert!(repo.find_reversal(&transaction_id).await.unwrap().is_none());
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
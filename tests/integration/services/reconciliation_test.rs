@@ -0,0 +1,325 @@
+// Integration tests for reconciliation against acquirer settlement files
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod reconciliation_tests {
+    use super::*;
+    use crate::models::{
+        Device, DeviceMode, ReconciliationCategory, SettlementFileColumn, SettlementFileField,
+        SettlementFileFormat, SettlementFileLayout, TeeType, TenantContext, Transaction,
+        TransactionStatus, TransactionType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, ReconciliationRepository, TransactionRepository,
+    };
+    use crate::services::ReconciliationService;
+    use crate::utils::error::AppError;
+
+    fn csv_column(field: SettlementFileField, name: &str) -> SettlementFileColumn {
+        SettlementFileColumn {
+            field,
+            column: Some(name.to_string()),
+            index: None,
+            start: None,
+            length: None,
+        }
+    }
+
+    fn fixed_column(
+        field: SettlementFileField,
+        start: usize,
+        length: usize,
+    ) -> SettlementFileColumn {
+        SettlementFileColumn {
+            field,
+            column: None,
+            index: None,
+            start: Some(start),
+            length: Some(length),
+        }
+    }
+
+    fn layouts() -> Vec<SettlementFileLayout> {
+        vec![
+            SettlementFileLayout {
+                name: "acquirer_csv".to_string(),
+                format: SettlementFileFormat::Csv,
+                delimiter: ",".to_string(),
+                has_header: true,
+                record_prefix: None,
+                date_format: "%Y-%m-%d".to_string(),
+                fields: vec![
+                    csv_column(SettlementFileField::Rrn, "rrn"),
+                    csv_column(SettlementFileField::AuthorizationCode, "auth_code"),
+                    csv_column(SettlementFileField::Amount, "amount"),
+                    csv_column(SettlementFileField::Currency, "currency"),
+                    csv_column(SettlementFileField::TransactionDate, "transaction_date"),
+                ],
+            },
+            SettlementFileLayout {
+                name: "acquirer_fixed".to_string(),
+                format: SettlementFileFormat::FixedWidth,
+                delimiter: ",".to_string(),
+                has_header: false,
+                record_prefix: Some("D".to_string()),
+                date_format: "%Y%m%d".to_string(),
+                fields: vec![
+                    fixed_column(SettlementFileField::Rrn, 2, 12),
+                    fixed_column(SettlementFileField::AuthorizationCode, 14, 6),
+                    fixed_column(SettlementFileField::Amount, 20, 12),
+                    fixed_column(SettlementFileField::Currency, 32, 3),
+                    fixed_column(SettlementFileField::TransactionDate, 35, 8),
+                ],
+            },
+        ]
+    }
+
+    fn service(pool: &SqlitePool) -> ReconciliationService {
+        ReconciliationService::new(
+            ReconciliationRepository::unscoped(pool.clone()),
+            TransactionRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+        .with_layouts(layouts())
+        .with_tolerance(0, 1)
+    }
+
+    async fn create_device(pool: &SqlitePool, imei: &str, tenant_id: &str) -> Device {
+        let mut device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        device.tenant_id = tenant_id.to_string();
+        DeviceRepository::unscoped(pool.clone()).create(&device).await.unwrap();
+        device
+    }
+
+    /// 直接写入已处理的交易，`created_at` 为交易日期当天10点（UTC）
+    #[allow(clippy::too_many_arguments)]
+    async fn create_transaction(
+        pool: &SqlitePool,
+        device_id: &str,
+        transaction_type: TransactionType,
+        status: TransactionStatus,
+        amount: i64,
+        rrn: Option<&str>,
+        authorization_code: Option<&str>,
+        date: &str,
+    ) -> String {
+        let mut transaction = Transaction::new(
+            device_id.to_string(),
+            transaction_type,
+            amount,
+            "USD".to_string(),
+            "FFFF9876543210E00000".to_string(),
+        );
+        transaction.status = status;
+        transaction.processor_reference = rrn.map(str::to_string);
+        transaction.authorization_code = authorization_code.map(str::to_string);
+        transaction.created_at = format!("{}T10:00:00+00:00", date);
+        TransactionRepository::unscoped(pool.clone()).create(&transaction).await.unwrap();
+        transaction.id
+    }
+
+    #[tokio::test]
+    async fn test_csv_reconciliation_reports() {
+        let pool = setup_test_db().await;
+        let device = create_device(&pool, "730000000000001", "default").await;
+        let service = service(&pool);
+
+        let approved = |amount, rrn, auth, date| {
+            create_transaction(
+                &pool,
+                &device.id,
+                TransactionType::Payment,
+                TransactionStatus::Approved,
+                amount,
+                rrn,
+                auth,
+                date,
+            )
+        };
+        let matched = approved(1000, Some("000000000001"), Some("A00001"), "2024-03-01").await;
+        let by_auth = approved(2500, None, Some("A00002"), "2024-03-01").await;
+        let mismatch = approved(3000, Some("000000000003"), Some("A00003"), "2024-03-01").await;
+        let missing = approved(4000, Some("000000000004"), Some("A00004"), "2024-03-02").await;
+
+        // 被撤销的交易和预授权不参与清算
+        create_transaction(
+            &pool,
+            &device.id,
+            TransactionType::Payment,
+            TransactionStatus::Voided,
+            5000,
+            Some("000000000005"),
+            Some("A00005"),
+            "2024-03-01",
+        )
+        .await;
+        create_transaction(
+            &pool,
+            &device.id,
+            TransactionType::PreAuth,
+            TransactionStatus::Approved,
+            6000,
+            Some("000000000006"),
+            Some("A00006"),
+            "2024-03-01",
+        )
+        .await;
+
+        let file = "rrn,auth_code,amount,currency,transaction_date\n\
+                    000000000001,A00001,10.00,USD,2024-03-01\n\
+                    ,A00002,2500,USD,2024-03-02\n\
+                    000000000003,A00003,31.00,USD,2024-03-01\n\
+                    000000000009,A00009,9.99,USD,2024-03-02\n";
+        let run = service
+            .run_reconciliation(
+                "acquirer_csv",
+                file,
+                Some("clearing-20240302.csv".to_string()),
+                None,
+                "finance",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(run.period_start, "2024-03-01");
+        assert_eq!(run.period_end, "2024-03-02");
+        assert_eq!(run.total_records, 4);
+        assert_eq!(run.matched_count, 2);
+        assert_eq!(run.amount_mismatch_count, 1);
+        assert_eq!(run.unmatched_ours_count, 1);
+        assert_eq!(run.unmatched_theirs_count, 1);
+
+        let items = service.list_items(&run.id, None, 50, 0).await.unwrap();
+        assert_eq!(items.total, 5);
+        let category = |id: &str| {
+            items
+                .items
+                .iter()
+                .find(|i| i.transaction_id.as_deref() == Some(id))
+                .map(|i| i.category.as_str())
+        };
+        assert_eq!(category(&matched), Some("MATCHED"));
+        assert_eq!(category(&by_auth), Some("MATCHED"));
+        assert_eq!(category(&mismatch), Some("AMOUNT_MISMATCH"));
+        assert_eq!(category(&missing), Some("UNMATCHED_OURS"));
+
+        let theirs = service
+            .list_items(&run.id, Some(ReconciliationCategory::UnmatchedTheirs), 50, 0)
+            .await
+            .unwrap();
+        assert_eq!(theirs.total, 1);
+        assert_eq!(theirs.items[0].line_number, Some(5));
+        assert_eq!(theirs.items[0].their_amount, Some(999));
+
+        // 差异报告只包含金额不符和双方单边
+        let report = service.get_exception_report(&run.id).await.unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("category,line_number,transaction_id"));
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("AMOUNT_MISMATCH,4,") && l.contains(",3000,3100,100,")));
+        assert!(lines.iter().any(|l| l.starts_with("UNMATCHED_OURS,,") && l.contains(&missing)));
+        assert!(!report.contains("MATCHED,2,"));
+
+        let runs = service.list_runs(10, 0).await.unwrap();
+        assert_eq!(runs.total, 1);
+        assert_eq!(runs.runs[0].file_name.as_deref(), Some("clearing-20240302.csv"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_files_rejected() {
+        let pool = setup_test_db().await;
+        let service = service(&pool);
+
+        assert!(matches!(
+            service.run_reconciliation("unknown", "rrn\n1", None, None, "finance").await,
+            Err(AppError::BadRequest(_))
+        ));
+        assert!(matches!(
+            service.run_reconciliation("acquirer_csv", "  \n", None, None, "finance").await,
+            Err(AppError::BadRequest(_))
+        ));
+
+        let file = "rrn,auth_code,amount,currency,transaction_date\n\
+                    000000000001,A00001,10.00,USD,2024-03-01\n\
+                    000000000002,A00002,ten,USD,2024-03-01\n";
+        match service.run_reconciliation("acquirer_csv", file, None, None, "finance").await {
+            Err(AppError::BadRequest(message)) => assert!(message.contains("Line 3")),
+            other => panic!("unexpected result: {:?}", other.map(|r| r.id)),
+        }
+
+        // 被拒绝的文件不生成对账任务
+        assert_eq!(service.list_runs(10, 0).await.unwrap().total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_fixed_width_reconciliation_is_tenant_scoped() {
+        let pool = setup_test_db().await;
+        let device = create_device(&pool, "730000000000002", "acq-a").await;
+        let transaction_id = create_transaction(
+            &pool,
+            &device.id,
+            TransactionType::Refund,
+            TransactionStatus::Approved,
+            1500,
+            Some("000000000011"),
+            Some("B00011"),
+            "2024-03-05",
+        )
+        .await;
+
+        let file = "H20240305ACQUIRER\n\
+                    D000000000011B00011-00000001500USD20240305\n\
+                    T000001\n";
+
+        let tenant_a = service(&pool).for_tenant(&TenantContext::new("acq-a"));
+        let tenant_b = service(&pool).for_tenant(&TenantContext::new("acq-b"));
+
+        let run = tenant_a
+            .run_reconciliation("acquirer_fixed", file, None, None, "finance")
+            .await
+            .unwrap();
+        assert_eq!(run.total_records, 1);
+        assert_eq!(run.matched_count, 1);
+        let items = tenant_a.list_items(&run.id, None, 10, 0).await.unwrap();
+        assert_eq!(items.items[0].transaction_id.as_deref(), Some(transaction_id.as_str()));
+        assert_eq!(items.items[0].line_number, Some(2));
+
+        // 其他租户看不到该交易，也看不到对账任务
+        let other = tenant_b
+            .run_reconciliation("acquirer_fixed", file, None, None, "finance")
+            .await
+            .unwrap();
+        assert_eq!(other.unmatched_theirs_count, 1);
+        assert!(matches!(tenant_b.get_run(&run.id).await, Err(AppError::NotFound(_))));
+        assert_eq!(tenant_b.list_runs(10, 0).await.unwrap().total, 1);
+
+        // 按商户对账时不包含其他商户的交易
+        let scoped = tenant_a
+            .run_reconciliation("acquirer_fixed", file, None, Some("mch-x".to_string()), "finance")
+            .await
+            .unwrap();
+        assert_eq!(scoped.unmatched_theirs_count, 1);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
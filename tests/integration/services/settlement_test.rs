@@ -0,0 +1,363 @@
+// Integration tests for settlement batches, end-of-day close and batch submission
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod settlement_tests {
+    use super::*;
+    use crate::dto::ProcessTransactionRequest;
+    use crate::infrastructure::config::{SimulatorResult, SimulatorScenario};
+    use crate::infrastructure::payment::{
+        PaymentOperation, PaymentRouter, SimulatorConnector, SIMULATOR_CONNECTOR,
+    };
+    use crate::models::{
+        BatchStatus, Device, DeviceMode, DeviceStatus, HealthCheck, SettlementFailureReason,
+        TeeType, TransactionStatus, TransactionType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, SettlementRepository,
+        TransactionRepository, TransactionTokenRepository,
+    };
+    use crate::security::{DukptKeyDerivation, JwtService};
+    use crate::services::{SettlementService, TransactionService, TransactionTokenService};
+    use crate::utils::error::AppError;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn settlement_scenario(
+        amount: i64,
+        result: SimulatorResult,
+        response_code: Option<&str>,
+    ) -> SimulatorScenario {
+        SimulatorScenario {
+            amount: Some(amount),
+            operations: vec![PaymentOperation::Settlement],
+            merchant_id: None,
+            result,
+            response_code: response_code.map(str::to_string),
+            message: None,
+            approved_amount: None,
+        }
+    }
+
+    /// 结算场景按批次净额匹配：3000对账不平，4000拒绝，5000超时
+    fn router() -> Arc<PaymentRouter> {
+        let simulator = SimulatorConnector::new()
+            .with_scenario(settlement_scenario(3000, SimulatorResult::Decline, Some("95")))
+            .with_scenario(settlement_scenario(4000, SimulatorResult::Decline, Some("05")))
+            .with_scenario(settlement_scenario(5000, SimulatorResult::Timeout, None));
+        Arc::new(PaymentRouter::new(SIMULATOR_CONNECTOR, Arc::new(simulator)))
+    }
+
+    fn services(pool: &SqlitePool) -> (TransactionService, SettlementService) {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let router = router();
+        let transaction_service = TransactionService::new(
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec()),
+            None,
+            Arc::new(
+                TransactionTokenService::new(jwt_service, None)
+                    .with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+            ),
+        )
+        .with_payment_router(router.clone())
+        .with_settlement_repo(SettlementRepository::unscoped(pool.clone()));
+
+        let settlement_service = SettlementService::new(
+            SettlementRepository::unscoped(pool.clone()),
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+        .with_payment_router(router)
+        .with_retry_policy(2, Duration::ZERO);
+
+        (transaction_service, settlement_service)
+    }
+
+    async fn create_active_device(pool: &SqlitePool, imei: &str) -> Device {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        repo.update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        repo.update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+        device
+    }
+
+    async fn process(
+        service: &TransactionService,
+        device_id: &str,
+        transaction_type: TransactionType,
+        amount: i64,
+        original_transaction_id: Option<&str>,
+    ) -> Result<String, AppError> {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let health_check =
+            HealthCheck::new(device_id.to_string(), 95, false, false, true, true, true);
+        let token = TransactionTokenService::new(jwt_service, None)
+            .generate_bound_token(device_id, &health_check, amount, "USD")
+            .await
+            .unwrap()
+            .token;
+
+        let request = ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type,
+            amount,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "encrypted_pin".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1234".to_string()),
+            transaction_token: token,
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: original_transaction_id.map(str::to_string),
+            client_transaction_id: None,
+            emv_data: None,
+        };
+
+        let response = service.process_transaction(request, "device").await?;
+        assert_eq!(response.status, TransactionStatus::Approved);
+        Ok(response.transaction_id)
+    }
+
+    #[tokio::test]
+    async fn test_batch_totals_and_device_close() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000001").await;
+        let (transactions, settlement) = services(&pool);
+        let repo = TransactionRepository::unscoped(pool.clone());
+
+        let payment = process(&transactions, &device.id, TransactionType::Payment, 1000, None)
+            .await
+            .unwrap();
+        let voided = process(&transactions, &device.id, TransactionType::Payment, 2000, None)
+            .await
+            .unwrap();
+        process(&transactions, &device.id, TransactionType::Refund, 400, Some(&payment))
+            .await
+            .unwrap();
+        process(&transactions, &device.id, TransactionType::Void, 2000, Some(&voided))
+            .await
+            .unwrap();
+
+        // 同一设备的交易进入同一个开放批次
+        let batch_id = repo.find_by_id(&payment).await.unwrap().unwrap().batch_id.unwrap();
+        let detail = settlement.get_batch(&batch_id).await.unwrap();
+        assert_eq!(detail.batch.status, BatchStatus::Open.as_str());
+        assert_eq!(detail.batch.batch_number, 1);
+        assert_eq!(detail.transactions.len(), 4);
+        assert!(detail.totals.is_empty());
+
+        let batches = settlement.close_device_batches(&device.id, "device").await.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].status, BatchStatus::Settled.as_str());
+        assert_eq!(batches[0].response_code.as_deref(), Some("00"));
+        assert!(!batches[0].uploaded);
+
+        // 被撤销的消费不计入合计，撤销只做统计
+        let detail = settlement.get_batch(&batch_id).await.unwrap();
+        assert_eq!(detail.batch.transaction_count, 3);
+        let totals: Vec<(TransactionType, i64, i64)> = detail
+            .totals
+            .iter()
+            .map(|t| (t.transaction_type.clone(), t.count, t.amount))
+            .collect();
+        assert!(totals.contains(&(TransactionType::Payment, 1, 1000)));
+        assert!(totals.contains(&(TransactionType::Refund, 1, 400)));
+        assert!(totals.contains(&(TransactionType::Void, 1, 2000)));
+        assert_eq!(detail.net_amounts.len(), 1);
+        assert_eq!(detail.net_amounts[0].amount, 600);
+
+        assert!(repo.find_by_id(&payment).await.unwrap().unwrap().settled_at.is_some());
+        assert!(repo.find_by_id(&voided).await.unwrap().unwrap().settled_at.is_none());
+
+        // 关批后的交易进入新批次
+        let next = process(&transactions, &device.id, TransactionType::Payment, 700, None)
+            .await
+            .unwrap();
+        let next_batch = repo.find_by_id(&next).await.unwrap().unwrap().batch_id.unwrap();
+        assert_ne!(next_batch, batch_id);
+        let list = settlement.list_batches(Some(&device.id), None, 10, 0).await.unwrap();
+        assert_eq!(list.total, 2);
+        let open = settlement
+            .list_batches(Some(&device.id), Some(BatchStatus::Open), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(open.batches[0].batch_number, 2);
+
+        // 没有开放批次时设备结算不做任何事
+        let other = create_active_device(&pool, "720000000000002").await;
+        assert!(settlement.close_device_batches(&other.id, "device").await.unwrap().is_empty());
+        assert!(matches!(
+            settlement.close_device_batches("missing", "device").await,
+            Err(AppError::DeviceNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_totals_mismatch_uploads_batch() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000003").await;
+        let (transactions, settlement) = services(&pool);
+
+        process(&transactions, &device.id, TransactionType::Payment, 1000, None)
+            .await
+            .unwrap();
+        process(&transactions, &device.id, TransactionType::Payment, 2000, None)
+            .await
+            .unwrap();
+
+        let batches = settlement.close_device_batches(&device.id, "device").await.unwrap();
+        assert_eq!(batches[0].status, BatchStatus::Settled.as_str());
+        assert!(batches[0].uploaded);
+        assert_eq!(batches[0].submit_attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_batch_reopened() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000004").await;
+        let (transactions, settlement) = services(&pool);
+        let repo = TransactionRepository::unscoped(pool.clone());
+
+        let first = process(&transactions, &device.id, TransactionType::Payment, 4000, None)
+            .await
+            .unwrap();
+        let batches = settlement.close_device_batches(&device.id, "device").await.unwrap();
+        assert_eq!(batches[0].status, BatchStatus::Failed.as_str());
+        assert_eq!(
+            batches[0].failure_reason.as_deref(),
+            Some(SettlementFailureReason::Rejected.as_str())
+        );
+        let failed_id = batches[0].id.clone();
+
+        // 只有失败的批次可以重新开批
+        let second = process(&transactions, &device.id, TransactionType::Payment, 500, None)
+            .await
+            .unwrap();
+        let second_batch = repo.find_by_id(&second).await.unwrap().unwrap().batch_id.unwrap();
+        assert!(matches!(
+            settlement.reopen_batch(&second_batch, "admin").await,
+            Err(AppError::BadRequest(_))
+        ));
+        assert!(matches!(
+            settlement.reopen_batch("missing", "admin").await,
+            Err(AppError::NotFound(_))
+        ));
+
+        // 重新开批时并入设备新的开放批次
+        let reopened = settlement.reopen_batch(&failed_id, "admin").await.unwrap();
+        assert_eq!(reopened.status, BatchStatus::Open.as_str());
+        assert_eq!(reopened.submit_attempts, 0);
+        assert!(reopened.failure_reason.is_none());
+        assert_eq!(
+            repo.find_by_id(&second).await.unwrap().unwrap().batch_id.as_deref(),
+            Some(failed_id.as_str())
+        );
+        assert_eq!(settlement.list_batches(Some(&device.id), None, 10, 0).await.unwrap().total, 1);
+
+        // 合并后净额不再命中拒绝场景
+        let batches = settlement.close_device_batches(&device.id, "device").await.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].status, BatchStatus::Settled.as_str());
+        assert_eq!(batches[0].transaction_count, 2);
+        assert!(repo.find_by_id(&first).await.unwrap().unwrap().settled_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_submission_retries_then_fails() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000005").await;
+        let (transactions, settlement) = services(&pool);
+
+        let payment = process(&transactions, &device.id, TransactionType::Payment, 5000, None)
+            .await
+            .unwrap();
+
+        // 提交超时后批次保持CLOSED等待重试
+        let batches = settlement.close_device_batches(&device.id, "device").await.unwrap();
+        assert_eq!(batches[0].status, BatchStatus::Closed.as_str());
+        assert_eq!(batches[0].submit_attempts, 1);
+        assert!(batches[0].last_error.is_some());
+
+        // 已关批的交易不能撤销
+        let result =
+            process(&transactions, &device.id, TransactionType::Void, 5000, Some(&payment)).await;
+        assert!(
+            matches!(&result, Err(AppError::BadRequest(m)) if m.contains("closed batch")),
+            "unexpected: {:?}",
+            result
+        );
+
+        assert_eq!(settlement.submit_pending().await.unwrap(), 0);
+        let detail = settlement.get_batch(&batches[0].id).await.unwrap();
+        assert_eq!(detail.batch.status, BatchStatus::Failed.as_str());
+        assert_eq!(detail.batch.submit_attempts, 2);
+        assert_eq!(
+            detail.batch.failure_reason.as_deref(),
+            Some(SettlementFailureReason::SubmissionFailed.as_str())
+        );
+        assert_eq!(settlement.submit_pending().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_close_after_cutoff() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000006").await;
+        let (transactions, settlement) = services(&pool);
+
+        process(&transactions, &device.id, TransactionType::Payment, 1200, None)
+            .await
+            .unwrap();
+
+        // 当天开批的批次未到日切
+        assert_eq!(settlement.close_due_batches().await.unwrap(), 0);
+
+        let opened_at = (chrono::Utc::now() - chrono::Duration::days(2)).to_rfc3339();
+        sqlx::query("UPDATE settlement_batches SET opened_at = ?")
+            .bind(&opened_at)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(settlement.close_due_batches().await.unwrap(), 1);
+        assert_eq!(settlement.close_due_batches().await.unwrap(), 0);
+        assert_eq!(settlement.submit_pending().await.unwrap(), 1);
+
+        let batches =
+            settlement.list_batches(None, Some(BatchStatus::Settled), 10, 0).await.unwrap();
+        assert_eq!(batches.total, 1);
+        assert_eq!(batches.batches[0].closed_by.as_deref(), Some("system"));
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
@@ -0,0 +1,335 @@
+// Integration tests for multi-tenant isolation
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod tenant_isolation_tests {
+    use super::*;
+    use crate::dto::{
+        CreateApiKeyRequest, CreateMerchantRequest, CreateTenantRequest, InjectKeyRequest,
+    };
+    use crate::infrastructure::{config::HsmConfig, HsmClient};
+    use crate::models::{
+        AuditLog, Device, DeviceMode, DeviceStatus, OperationResult, TeeType, TenantContext,
+        DEFAULT_TENANT_ID,
+    };
+    use crate::repositories::{
+        ApiKeyRepository, AuditLogRepository, DeviceRepository, MerchantRepository,
+        StoreRepository, TenantRepository,
+    };
+    use crate::security::{base64_encode, DukptKeyDerivation, KeyEncryptionKey};
+    use crate::services::{KeyManagementService, MerchantService, TenantService};
+    use crate::utils::error::AppError;
+
+    const KEK: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const TENANT_BDK: &str = "FEDCBA98765432100123456789ABCDEF";
+    const KSN: &str = "FFFF9876543210E00000";
+
+    fn create_tenant_service(pool: &SqlitePool) -> TenantService {
+        TenantService::new(
+            TenantRepository::new(pool.clone()),
+            ApiKeyRepository::new(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+    }
+
+    fn create_merchant_service(pool: &SqlitePool) -> MerchantService {
+        MerchantService::new(
+            MerchantRepository::unscoped(pool.clone()),
+            StoreRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+    }
+
+    fn tenant_request(id: &str) -> CreateTenantRequest {
+        CreateTenantRequest {
+            id: id.to_string(),
+            name: format!("Tenant {}", id),
+            bdk: None,
+            bdk_key_ref: None,
+            rate_limit_rps: None,
+            rate_limit_burst: None,
+            branding: None,
+        }
+    }
+
+    fn merchant_request(name: &str, mid: &str) -> CreateMerchantRequest {
+        CreateMerchantRequest {
+            name: name.to_string(),
+            mcc: "5814".to_string(),
+            country: "CN".to_string(),
+            currency: "CNY".to_string(),
+            acquirer_mid: Some(mid.to_string()),
+            address: None,
+        }
+    }
+
+    fn test_device(imei: &str) -> Device {
+        Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        )
+    }
+
+    async fn setup_tenants(pool: &SqlitePool) {
+        let service = create_tenant_service(pool);
+        service.create_tenant(tenant_request("acq-a"), "root").await.unwrap();
+        service.create_tenant(tenant_request("acq-b"), "root").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_devices_are_isolated_between_tenants() {
+        let pool = setup_test_db().await;
+        setup_tenants(&pool).await;
+
+        let repo = DeviceRepository::unscoped(pool.clone());
+        let repo_a = repo.for_tenant(&TenantContext::new("acq-a"));
+        let repo_b = repo.for_tenant(&TenantContext::new("acq-b"));
+
+        let device = test_device("111111111111111");
+        repo_a.create(&device).await.unwrap();
+
+        let stored = repo_a.find_by_id(&device.id).await.unwrap().unwrap();
+        assert_eq!(stored.tenant_id, "acq-a");
+        assert!(repo_b.find_by_id(&device.id).await.unwrap().is_none());
+        assert_eq!(repo_b.count(None, None).await.unwrap(), 0);
+
+        // 其他租户的更新不生效
+        repo_b.update_security_score(&device.id, 10).await.unwrap();
+        let stored = repo_a.find_by_id(&device.id).await.unwrap().unwrap();
+        assert_eq!(stored.security_score, device.security_score);
+
+        // 跨租户上下文可以看到所有租户的数据
+        let all = repo.for_tenant(&TenantContext::cross_tenant(DEFAULT_TENANT_ID));
+        assert_eq!(all.count(None, None).await.unwrap(), 1);
+
+        // 未绑定租户的Repository不匹配任何租户的数据
+        let unbound = DeviceRepository::new(pool.clone());
+        assert!(unbound.find_by_id(&device.id).await.unwrap().is_none());
+        assert_eq!(unbound.count(None, None).await.unwrap(), 0);
+        let bound = unbound.for_tenant(&TenantContext::new("acq-a"));
+        assert!(bound.find_by_id(&device.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_merchants_are_isolated_between_tenants() {
+        let pool = setup_test_db().await;
+        setup_tenants(&pool).await;
+
+        let service = create_merchant_service(&pool);
+        let service_a = service.for_tenant(&TenantContext::new("acq-a"));
+        let service_b = service.for_tenant(&TenantContext::new("acq-b"));
+
+        let merchant = service_a
+            .create_merchant(merchant_request("Coffee", "123456789012345"), "admin")
+            .await
+            .unwrap();
+
+        // 收单商户号仅在租户内唯一
+        service_b
+            .create_merchant(merchant_request("Tea", "123456789012345"), "admin")
+            .await
+            .unwrap();
+
+        assert!(service_b.get_merchant(&merchant.id).await.is_err());
+        assert_eq!(service_a.list_merchants(None, 20, 0).await.unwrap().total, 1);
+        assert_eq!(service_b.list_merchants(None, 20, 0).await.unwrap().total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_inherits_device_tenant() {
+        let pool = setup_test_db().await;
+        setup_tenants(&pool).await;
+
+        let device = test_device("222222222222222");
+        DeviceRepository::unscoped(pool.clone())
+            .for_tenant(&TenantContext::new("acq-b"))
+            .create(&device)
+            .await
+            .unwrap();
+
+        // 设备端未认证的调用没有租户上下文，审计日志归属于设备所在租户
+        let audit_repo = AuditLogRepository::unscoped(pool.clone());
+        let log =
+            AuditLog::new("HEALTH_CHECK".to_string(), device.id.clone(), OperationResult::Success)
+                .with_device_id(device.id.clone());
+        audit_repo.create(&log).await.unwrap();
+
+        let scoped_b = audit_repo.for_tenant(&TenantContext::new("acq-b"));
+        let scoped_a = audit_repo.for_tenant(&TenantContext::new("acq-a"));
+        assert_eq!(
+            scoped_b
+                .count(None, None, Some("HEALTH_CHECK"), None, None, None)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            scoped_a
+                .count(None, None, Some("HEALTH_CHECK"), None, None, None)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_key_lifecycle() {
+        let pool = setup_test_db().await;
+        setup_tenants(&pool).await;
+
+        let service = create_tenant_service(&pool);
+        let created = service
+            .create_api_key(
+                "acq-a",
+                CreateApiKeyRequest { name: "backoffice".to_string(), role: "admin".to_string() },
+                "root",
+            )
+            .await
+            .unwrap();
+        assert!(created.api_key.starts_with("sk_"));
+
+        let resolved = service.resolve_api_key(&created.api_key).await.unwrap().unwrap();
+        assert_eq!(resolved.tenant_id, "acq-a");
+        assert_eq!(resolved.role, "admin");
+
+        assert!(service.resolve_api_key("sk_invalid").await.unwrap().is_none());
+
+        // 其他租户不能吊销该密钥
+        assert!(service.revoke_api_key("acq-b", &resolved.id, "root").await.is_err());
+
+        service.revoke_api_key("acq-a", &resolved.id, "root").await.unwrap();
+        assert!(service.resolve_api_key(&created.api_key).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_bdk_is_stored_wrapped() {
+        let pool = setup_test_db().await;
+        let kek = KeyEncryptionKey::from_hex(KEK).unwrap();
+
+        // 未配置密钥加密密钥时不能保存本地BDK
+        let request = CreateTenantRequest {
+            bdk: Some(TENANT_BDK.to_string()),
+            ..tenant_request("acq-a")
+        };
+        assert!(matches!(
+            create_tenant_service(&pool).create_tenant(request.clone(), "root").await,
+            Err(AppError::BadRequest(_))
+        ));
+
+        let service = create_tenant_service(&pool).with_key_encryption_key(kek.clone());
+        let created = service.create_tenant(request, "root").await.unwrap();
+        assert!(created.has_custom_bdk);
+
+        let (bdk, wrapped): (Option<String>, String) =
+            sqlx::query_as("SELECT bdk, bdk_wrapped FROM tenants WHERE id = 'acq-a'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(bdk, None);
+        assert!(!wrapped.contains(TENANT_BDK));
+        assert_eq!(kek.unwrap(&wrapped, "acq-a").unwrap(), TENANT_BDK.as_bytes());
+
+        // 升级前的明文BDK在启动时包装并清空
+        service.create_tenant(tenant_request("acq-b"), "root").await.unwrap();
+        sqlx::query("UPDATE tenants SET bdk = ? WHERE id = 'acq-b'")
+            .bind(TENANT_BDK)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(create_tenant_service(&pool).wrap_plaintext_bdks().await.is_err());
+        assert_eq!(service.wrap_plaintext_bdks().await.unwrap(), 1);
+
+        let (bdk, wrapped): (Option<String>, String) =
+            sqlx::query_as("SELECT bdk, bdk_wrapped FROM tenants WHERE id = 'acq-b'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(bdk, None);
+        assert_eq!(kek.unwrap(&wrapped, "acq-b").unwrap(), TENANT_BDK.as_bytes());
+        assert_eq!(service.wrap_plaintext_bdks().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_key_injection_resolves_tenant_bdk() {
+        let pool = setup_test_db().await;
+        let kek = KeyEncryptionKey::from_hex(KEK).unwrap();
+        let tenants = create_tenant_service(&pool).with_key_encryption_key(kek.clone());
+        let request = CreateTenantRequest {
+            bdk: Some(TENANT_BDK.to_string()),
+            ..tenant_request("acq-a")
+        };
+        tenants.create_tenant(request, "root").await.unwrap();
+        let request = CreateTenantRequest {
+            bdk_key_ref: Some("hsm-bdk-acq-b".to_string()),
+            ..tenant_request("acq-b")
+        };
+        tenants.create_tenant(request, "root").await.unwrap();
+
+        let repo = DeviceRepository::unscoped(pool.clone());
+        let create_device = |tenant_id: &'static str, imei: &'static str| {
+            let repo = repo.for_tenant(&TenantContext::new(tenant_id));
+            async move {
+                let device = test_device(imei);
+                repo.create(&device).await.unwrap();
+                let active = DeviceStatus::Active;
+                repo.update_status(&device.id, DeviceStatus::Pending, active, "admin", None)
+                    .await
+                    .unwrap();
+                repo.update_ksn(&device.id, KSN).await.unwrap();
+                device
+            }
+        };
+        let device_a = create_device("acq-a", "333333333333331").await;
+        let device_b = create_device("acq-b", "333333333333332").await;
+
+        // 本地包装的租户BDK即使配置了HSM也在本地派生
+        let unreachable_hsm = HsmClient::new(HsmConfig {
+            base_url: "http://127.0.0.1:9".to_string(),
+            api_key: "test".to_string(),
+            timeout_seconds: 1,
+        })
+        .unwrap();
+        let global = DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec());
+        let key_service = KeyManagementService::new(
+            repo.clone(),
+            AuditLogRepository::unscoped(pool.clone()),
+            global,
+            Some(unreachable_hsm),
+        )
+        .with_tenant_repo(TenantRepository::new(pool.clone()), Some(kek));
+
+        let response = key_service
+            .inject_key(InjectKeyRequest { device_id: device_a.id.clone() }, "admin")
+            .await
+            .unwrap();
+        let ipek = DukptKeyDerivation::new(TENANT_BDK.as_bytes().to_vec())
+            .derive_ipek(KSN)
+            .unwrap();
+        assert_eq!(response.encrypted_ipek, base64_encode(base64_encode(&ipek).as_bytes()));
+
+        // HSM中的租户BDK不可用时不回退到本地BDK
+        assert!(key_service
+            .inject_key(InjectKeyRequest { device_id: device_b.id.clone() }, "admin")
+            .await
+            .is_err());
+        let stored = repo.find_by_id(&device_b.id).await.unwrap().unwrap();
+        assert!(stored.ipek_injected_at.is_none());
+    }
+}
+
+// Helper functions
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    pool
+}
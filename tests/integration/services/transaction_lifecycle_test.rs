@@ -0,0 +1,411 @@
+// Integration tests for transaction lifecycle rules (refund, void, capture, incremental auth)
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod transaction_lifecycle_tests {
+    use super::*;
+    use crate::dto::ProcessTransactionRequest;
+    use crate::infrastructure::config::{SimulatorResult, SimulatorScenario};
+    use crate::infrastructure::payment::{PaymentRouter, SimulatorConnector, SIMULATOR_CONNECTOR};
+    use crate::models::{
+        Device, DeviceMode, DeviceStatus, HealthCheck, TeeType, Transaction, TransactionStatus,
+        TransactionType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, TransactionRepository,
+        TransactionTokenRepository,
+    };
+    use crate::security::{DukptKeyDerivation, JwtService};
+    use crate::services::{TransactionService, TransactionTokenService};
+    use crate::utils::error::AppError;
+    use std::sync::Arc;
+
+    fn transaction_service(pool: &SqlitePool) -> TransactionService {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let simulator = SimulatorConnector::new().with_scenario(SimulatorScenario {
+            amount: Some(5100),
+            operations: Vec::new(),
+            merchant_id: None,
+            result: SimulatorResult::Decline,
+            response_code: Some("51".to_string()),
+            message: None,
+            approved_amount: None,
+        });
+        TransactionService::new(
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec()),
+            None,
+            Arc::new(
+                TransactionTokenService::new(jwt_service, None)
+                    .with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+            ),
+        )
+        .with_payment_router(Arc::new(PaymentRouter::new(SIMULATOR_CONNECTOR, Arc::new(simulator))))
+    }
+
+    async fn create_active_device(pool: &SqlitePool, imei: &str) -> Device {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        repo.update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        repo.update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+        device
+    }
+
+    async fn transaction_token(device_id: &str, amount: i64, currency: &str) -> String {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let health_check =
+            HealthCheck::new(device_id.to_string(), 95, false, false, true, true, true);
+        TransactionTokenService::new(jwt_service, None)
+            .generate_bound_token(device_id, &health_check, amount, currency)
+            .await
+            .unwrap()
+            .token
+    }
+
+    async fn request(
+        device_id: &str,
+        transaction_type: TransactionType,
+        amount: i64,
+        original: Option<&str>,
+    ) -> ProcessTransactionRequest {
+        ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type,
+            amount,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "encrypted_pin".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1234".to_string()),
+            transaction_token: transaction_token(device_id, amount, "USD").await,
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: original.map(str::to_string),
+            client_transaction_id: None,
+            emv_data: None,
+        }
+    }
+
+    async fn process(
+        service: &TransactionService,
+        device_id: &str,
+        transaction_type: TransactionType,
+        amount: i64,
+        original: Option<&str>,
+    ) -> Result<String, AppError> {
+        let response = service
+            .process_transaction(
+                request(device_id, transaction_type, amount, original).await,
+                "device",
+            )
+            .await?;
+        Ok(response.transaction_id)
+    }
+
+    async fn find(pool: &SqlitePool, id: &str) -> Transaction {
+        TransactionRepository::unscoped(pool.clone()).find_by_id(id).await.unwrap().unwrap()
+    }
+
+    fn assert_rejected<T: std::fmt::Debug>(result: Result<T, AppError>) {
+        assert!(matches!(result, Err(AppError::BadRequest(_))), "unexpected: {:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_refunds_capped_at_refundable_amount() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "700000000000001").await;
+        let service = transaction_service(&pool);
+
+        // 退款必须引用原交易，消费不能引用原交易
+        assert_rejected(process(&service, &device.id, TransactionType::Refund, 100, None).await);
+        let payment = process(&service, &device.id, TransactionType::Payment, 10000, None)
+            .await
+            .unwrap();
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Payment, 100, Some(&payment)).await,
+        );
+
+        let refund =
+            process(&service, &device.id, TransactionType::Refund, 6000, Some(&payment)).await;
+        let refund = find(&pool, &refund.unwrap()).await;
+        assert_eq!(refund.original_transaction_id.as_deref(), Some(payment.as_str()));
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::PartiallyRefunded);
+
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Refund, 5000, Some(&payment)).await,
+        );
+        process(&service, &device.id, TransactionType::Refund, 4000, Some(&payment))
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::Refunded);
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Refund, 1, Some(&payment)).await,
+        );
+
+        // 被拒绝的消费不能退款
+        let declined = process(&service, &device.id, TransactionType::Payment, 5100, None)
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &declined).await.status, TransactionStatus::Declined);
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Refund, 100, Some(&declined)).await,
+        );
+
+        // 其他设备（未分配商户）不能退本设备的交易
+        let other = create_active_device(&pool, "700000000000002").await;
+        let other_payment =
+            process(&service, &other.id, TransactionType::Payment, 300, None).await.unwrap();
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Refund, 100, Some(&other_payment)).await,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refunds_cannot_exceed_approved_amount() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "700000000000006").await;
+        let service = transaction_service(&pool);
+
+        let payment = process(&service, &device.id, TransactionType::Payment, 10000, None)
+            .await
+            .unwrap();
+        let (first, second, third) = tokio::join!(
+            process(&service, &device.id, TransactionType::Refund, 6000, Some(&payment)),
+            process(&service, &device.id, TransactionType::Refund, 6000, Some(&payment)),
+            process(&service, &device.id, TransactionType::Refund, 6000, Some(&payment)),
+        );
+        let results = [first, second, third];
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1, "unexpected: {:?}", results);
+        for result in results.into_iter().filter(Result::is_err) {
+            assert_rejected(result);
+        }
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::PartiallyRefunded);
+
+        // 进行中的退款已占用的金额计入上限，未批准的退款释放占用
+        let in_flight = Transaction::new(
+            device.id.clone(),
+            TransactionType::Refund,
+            3000,
+            "USD".to_string(),
+            "FFFF9876543210E00000".to_string(),
+        );
+        let repo = TransactionRepository::unscoped(pool.clone());
+        assert!(repo.reserve_follow_up(&payment, &in_flight).await.unwrap());
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Refund, 2000, Some(&payment)).await,
+        );
+        repo.release_follow_up(&payment, &in_flight).await.unwrap();
+
+        process(&service, &device.id, TransactionType::Refund, 4000, Some(&payment))
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::Refunded);
+
+        let payment = process(&service, &device.id, TransactionType::Payment, 10000, None)
+            .await
+            .unwrap();
+        let declined = process(&service, &device.id, TransactionType::Refund, 5100, Some(&payment))
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &declined).await.status, TransactionStatus::Declined);
+        process(&service, &device.id, TransactionType::Refund, 10000, Some(&payment))
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::Refunded);
+    }
+
+    #[tokio::test]
+    async fn test_void_same_day_and_unsettled() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "700000000000003").await;
+        let service = transaction_service(&pool);
+
+        let payment = process(&service, &device.id, TransactionType::Payment, 2500, None)
+            .await
+            .unwrap();
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Void, 1000, Some(&payment)).await,
+        );
+        process(&service, &device.id, TransactionType::Void, 2500, Some(&payment))
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::Voided);
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Void, 2500, Some(&payment)).await,
+        );
+
+        // 已结算的交易不能撤销
+        let settled = process(&service, &device.id, TransactionType::Payment, 800, None)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE transactions SET settled_at = ? WHERE id = ?")
+            .bind(chrono::Utc::now().to_rfc3339())
+            .bind(&settled)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Void, 800, Some(&settled)).await,
+        );
+
+        // 前一天的交易只能退款
+        let yesterday = process(&service, &device.id, TransactionType::Payment, 900, None)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE transactions SET created_at = ? WHERE id = ?")
+            .bind((chrono::Utc::now() - chrono::Duration::days(1)).to_rfc3339())
+            .bind(&yesterday)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Void, 900, Some(&yesterday)).await,
+        );
+        process(&service, &device.id, TransactionType::Refund, 900, Some(&yesterday))
+            .await
+            .unwrap();
+
+        // 撤销退款后原消费恢复可退金额
+        let payment = process(&service, &device.id, TransactionType::Payment, 3000, None)
+            .await
+            .unwrap();
+        let first =
+            process(&service, &device.id, TransactionType::Refund, 1000, Some(&payment)).await;
+        let second =
+            process(&service, &device.id, TransactionType::Refund, 2000, Some(&payment)).await;
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::Refunded);
+        process(&service, &device.id, TransactionType::Void, 2000, Some(&second.unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::PartiallyRefunded);
+        process(&service, &device.id, TransactionType::Void, 1000, Some(&first.unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &payment).await.status, TransactionStatus::Approved);
+    }
+
+    #[tokio::test]
+    async fn test_preauth_capture_and_incremental_auth() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "700000000000004").await;
+        let service = transaction_service(&pool).with_preauth_validity(chrono::Duration::hours(2));
+
+        let preauth = process(&service, &device.id, TransactionType::PreAuth, 5000, None)
+            .await
+            .unwrap();
+        let record = find(&pool, &preauth).await;
+        let expires_at = record.auth_expires_at.clone().unwrap();
+        assert!(expires_at > chrono::Utc::now().to_rfc3339());
+
+        assert_rejected(process(&service, &device.id, TransactionType::Capture, 100, None).await);
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Capture, 6000, Some(&preauth)).await,
+        );
+
+        // 追加授权提高可请款金额并延长有效期
+        let incremental =
+            process(&service, &device.id, TransactionType::PreAuth, 2000, Some(&preauth)).await;
+        assert!(incremental.is_ok());
+        assert!(find(&pool, &preauth).await.auth_expires_at.unwrap() >= expires_at);
+
+        let capture =
+            process(&service, &device.id, TransactionType::Capture, 7000, Some(&preauth)).await;
+        let capture = capture.unwrap();
+        assert_eq!(find(&pool, &preauth).await.status, TransactionStatus::Captured);
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Capture, 100, Some(&preauth)).await,
+        );
+
+        // 撤销请款后预授权可再次请款，请款可以退款
+        process(&service, &device.id, TransactionType::Void, 7000, Some(&capture))
+            .await
+            .unwrap();
+        assert_eq!(find(&pool, &preauth).await.status, TransactionStatus::Approved);
+        let capture =
+            process(&service, &device.id, TransactionType::Capture, 4000, Some(&preauth)).await;
+        process(&service, &device.id, TransactionType::Refund, 4000, Some(&capture.unwrap()))
+            .await
+            .unwrap();
+
+        // 被拒绝的预授权不能请款
+        let declined = process(&service, &device.id, TransactionType::PreAuth, 5100, None)
+            .await
+            .unwrap();
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Capture, 100, Some(&declined)).await,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_preauth() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "700000000000005").await;
+        let service = transaction_service(&pool);
+        let past = (chrono::Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+
+        // 请款时发现过期
+        let preauth = process(&service, &device.id, TransactionType::PreAuth, 5000, None)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE transactions SET auth_expires_at = ? WHERE id = ?")
+            .bind(&past)
+            .bind(&preauth)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_rejected(
+            process(&service, &device.id, TransactionType::Capture, 5000, Some(&preauth)).await,
+        );
+        assert_eq!(find(&pool, &preauth).await.status, TransactionStatus::Expired);
+
+        // 后台检查批量过期
+        let stale = process(&service, &device.id, TransactionType::PreAuth, 3000, None)
+            .await
+            .unwrap();
+        let fresh = process(&service, &device.id, TransactionType::PreAuth, 3000, None)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE transactions SET auth_expires_at = ? WHERE id = ?")
+            .bind(&past)
+            .bind(&stale)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(service.expire_preauths().await.unwrap(), 1);
+        assert_eq!(find(&pool, &stale).await.status, TransactionStatus::Expired);
+        assert_eq!(find(&pool, &fresh).await.status, TransactionStatus::Approved);
+        assert_rejected(
+            process(&service, &device.id, TransactionType::PreAuth, 1000, Some(&stale)).await,
+        );
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
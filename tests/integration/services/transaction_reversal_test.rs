@@ -0,0 +1,377 @@
+// Integration tests for uncertain transactions, the reversal queue and transaction events
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod transaction_reversal_tests {
+    use super::*;
+    use crate::dto::{ProcessTransactionRequest, ReverseTransactionRequest};
+    use crate::infrastructure::config::{SimulatorResult, SimulatorScenario};
+    use crate::infrastructure::payment::{
+        PaymentOperation, PaymentRouter, SimulatorConnector, SIMULATOR_CONNECTOR,
+    };
+    use crate::models::{
+        Device, DeviceMode, DeviceStatus, HealthCheck, ReversalStatus, TeeType, TransactionStatus,
+        TransactionType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, TransactionRepository,
+        TransactionTokenRepository,
+    };
+    use crate::security::{DukptKeyDerivation, JwtService};
+    use crate::services::{TransactionService, TransactionTokenService};
+    use crate::utils::error::AppError;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn scenario(
+        amount: i64,
+        operations: Vec<PaymentOperation>,
+        result: SimulatorResult,
+    ) -> SimulatorScenario {
+        SimulatorScenario {
+            amount: Some(amount),
+            operations,
+            merchant_id: None,
+            result,
+            response_code: (result == SimulatorResult::Decline).then(|| "51".to_string()),
+            message: None,
+            approved_amount: None,
+        }
+    }
+
+    fn transaction_service(pool: &SqlitePool) -> TransactionService {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        // 9100：授权超时、冲正成功；9200：授权和冲正都超时；5100：拒绝
+        let simulator = SimulatorConnector::new()
+            .with_scenario(scenario(
+                9100,
+                vec![PaymentOperation::Authorize],
+                SimulatorResult::Timeout,
+            ))
+            .with_scenario(scenario(9200, Vec::new(), SimulatorResult::Timeout))
+            .with_scenario(scenario(5100, Vec::new(), SimulatorResult::Decline));
+        TransactionService::new(
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec()),
+            None,
+            Arc::new(
+                TransactionTokenService::new(jwt_service, None)
+                    .with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+            ),
+        )
+        .with_payment_router(Arc::new(PaymentRouter::new(SIMULATOR_CONNECTOR, Arc::new(simulator))))
+        .with_reversal_policy(2, Duration::ZERO)
+    }
+
+    async fn create_active_device(pool: &SqlitePool, imei: &str) -> Device {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        repo.update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        repo.update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+        device
+    }
+
+    async fn transaction_token(device_id: &str, amount: i64, currency: &str) -> String {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let health_check =
+            HealthCheck::new(device_id.to_string(), 95, false, false, true, true, true);
+        TransactionTokenService::new(jwt_service, None)
+            .generate_bound_token(device_id, &health_check, amount, currency)
+            .await
+            .unwrap()
+            .token
+    }
+
+    async fn request(device_id: &str, amount: i64, client_id: &str) -> ProcessTransactionRequest {
+        ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type: TransactionType::Payment,
+            amount,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "encrypted_pin".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1234".to_string()),
+            transaction_token: transaction_token(device_id, amount, "USD").await,
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: None,
+            client_transaction_id: Some(client_id.to_string()),
+            emv_data: None,
+        }
+    }
+
+    async fn event_types(service: &TransactionService, id: &str) -> Vec<String> {
+        service
+            .get_transaction_events(id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.event_type)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_processor_timeout_is_reversed_automatically() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "710000000000001").await;
+        let service = transaction_service(&pool);
+        let repo = TransactionRepository::unscoped(pool.clone());
+
+        let response = service
+            .process_transaction(request(&device.id, 9100, "c-1").await, "device")
+            .await
+            .unwrap();
+        assert_eq!(response.status, TransactionStatus::Unknown);
+
+        let reversal = repo.find_reversal(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(reversal.status, ReversalStatus::Pending.as_str());
+        assert_eq!(reversal.reason, "PROCESSOR_TIMEOUT");
+
+        assert_eq!(service.process_reversals().await.unwrap(), 1);
+        let transaction = repo.find_by_id(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Reversed);
+        let reversal = repo.find_reversal(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(reversal.status, ReversalStatus::Completed.as_str());
+        assert_eq!(reversal.attempts, 1);
+
+        assert_eq!(
+            event_types(&service, &response.transaction_id).await,
+            vec!["PROCESSED", "REVERSAL_QUEUED", "REVERSED"]
+        );
+        let events = service.get_transaction_events(&response.transaction_id).await.unwrap();
+        assert_eq!(events[0].to_status, TransactionStatus::Unknown);
+        assert_eq!(events[2].from_status, Some(TransactionStatus::Unknown));
+
+        // 已冲正的交易再次申请时直接返回结果
+        let reversed = service
+            .request_device_reversal(
+                &device.id,
+                &ReverseTransactionRequest {
+                    transaction_id: None,
+                    client_transaction_id: Some("c-1".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(reversed.status, TransactionStatus::Reversed);
+        assert_eq!(reversed.reversal_status, Some(ReversalStatus::Completed));
+    }
+
+    #[tokio::test]
+    async fn test_outcome_write_failure_leaves_provisional_reversal() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "710000000000004").await;
+        let service = transaction_service(&pool);
+        let repo = TransactionRepository::unscoped(pool.clone());
+
+        // 处理器给出明确结果后撤销预登记的冲正
+        let approved = service
+            .process_transaction(request(&device.id, 1000, "c-6").await, "device")
+            .await
+            .unwrap();
+        assert_eq!(approved.status, TransactionStatus::Approved);
+        assert!(repo.find_reversal(&approved.transaction_id).await.unwrap().is_none());
+
+        // 处理器批准后结果写入失败：交易保持PENDING，预登记的冲正保留
+        sqlx::query(
+            r#"
+            CREATE TRIGGER fail_outcome BEFORE UPDATE OF status ON transactions
+            WHEN OLD.status = 'Pending'
+            BEGIN SELECT RAISE(ABORT, 'injected failure'); END
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let result =
+            service.process_transaction(request(&device.id, 1000, "c-7").await, "device").await;
+        assert!(result.is_err(), "unexpected: {:?}", result);
+
+        let (transaction_id,): (String,) =
+            sqlx::query_as("SELECT id FROM transactions WHERE client_transaction_id = 'c-7'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let transaction = repo.find_by_id(&transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Pending);
+        let reversal = repo.find_reversal(&transaction_id).await.unwrap().unwrap();
+        assert_eq!(reversal.status, ReversalStatus::Pending.as_str());
+        assert_eq!(reversal.attempts, 0);
+
+        // 预登记的冲正未到期前不执行，到期后由后台任务冲正
+        sqlx::query("DROP TRIGGER fail_outcome").execute(&pool).await.unwrap();
+        assert_eq!(service.process_reversals().await.unwrap(), 0);
+        sqlx::query("UPDATE transaction_reversals SET next_attempt_at = ? WHERE transaction_id = ?")
+            .bind((chrono::Utc::now() - chrono::Duration::seconds(1)).to_rfc3339())
+            .bind(&transaction_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(service.process_reversals().await.unwrap(), 1);
+
+        let transaction = repo.find_by_id(&transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Reversed);
+    }
+
+    #[tokio::test]
+    async fn test_prepared_transaction_has_no_side_effects() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "710000000000005").await;
+        let service = transaction_service(&pool);
+        let req = request(&device.id, 1000, "c-8").await;
+
+        // 校验通过但未执行：不占用令牌也不保存交易，同一请求可以重新提交
+        let prepared = service.prepare_transaction(req.clone()).await.unwrap();
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM transactions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+        drop(prepared);
+
+        let prepared = service.prepare_transaction(req.clone()).await.unwrap();
+        let transaction_id = prepared.transaction_id().to_string();
+        let response = service.execute_transaction(prepared, "device").await.unwrap();
+        assert_eq!(response.transaction_id, transaction_id);
+        assert_eq!(response.status, TransactionStatus::Approved);
+
+        // 执行后令牌已占用，重试在校验阶段即被拒绝
+        let result = service.prepare_transaction(req).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))), "unexpected: {:?}", result.err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_reversals_retry_then_give_up() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "710000000000002").await;
+        let service = transaction_service(&pool);
+        let repo = TransactionRepository::unscoped(pool.clone());
+
+        let response = service
+            .process_transaction(request(&device.id, 9200, "c-2").await, "device")
+            .await
+            .unwrap();
+        assert_eq!(response.status, TransactionStatus::Unknown);
+
+        // 第一次冲正超时后保持PENDING等待重试，达到最大次数后标记为FAILED
+        assert_eq!(service.process_reversals().await.unwrap(), 0);
+        let reversal = repo.find_reversal(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(reversal.status, ReversalStatus::Pending.as_str());
+        assert_eq!(reversal.attempts, 1);
+        assert!(reversal.last_error.is_some());
+
+        assert_eq!(service.process_reversals().await.unwrap(), 0);
+        let reversal = repo.find_reversal(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(reversal.status, ReversalStatus::Failed.as_str());
+        assert_eq!(reversal.attempts, 2);
+        assert!(service.process_reversals().await.unwrap() == 0);
+
+        let transaction = repo.find_by_id(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Unknown);
+        assert_eq!(
+            event_types(&service, &response.transaction_id).await,
+            vec!["PROCESSED", "REVERSAL_QUEUED", "REVERSAL_ATTEMPT_FAILED", "REVERSAL_FAILED"]
+        );
+
+        // 操作员可重新排队已失败的冲正
+        let requeued = service
+            .request_operator_reversal(&response.transaction_id, "admin")
+            .await
+            .unwrap();
+        assert_eq!(requeued.reversal_status, Some(ReversalStatus::Pending));
+        let reversal = repo.find_reversal(&response.transaction_id).await.unwrap().unwrap();
+        assert_eq!(reversal.status, ReversalStatus::Pending.as_str());
+        assert_eq!(reversal.attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_device_requested_reversals() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "710000000000003").await;
+        let other = create_active_device(&pool, "710000000000004").await;
+        let service = transaction_service(&pool);
+        let repo = TransactionRepository::unscoped(pool.clone());
+
+        let by_client_id = |id: &str| ReverseTransactionRequest {
+            transaction_id: None,
+            client_transaction_id: Some(id.to_string()),
+        };
+
+        // 设备未收到应答的已批准消费可以冲正
+        let approved = service
+            .process_transaction(request(&device.id, 1000, "c-3").await, "device")
+            .await
+            .unwrap();
+        let response =
+            service.request_device_reversal(&device.id, &by_client_id("c-3")).await.unwrap();
+        assert_eq!(response.reversal_status, Some(ReversalStatus::Pending));
+        let reversal = repo.find_reversal(&approved.transaction_id).await.unwrap().unwrap();
+        assert_eq!(reversal.reason, "DEVICE_REQUEST");
+        assert_eq!(service.process_reversals().await.unwrap(), 1);
+        let transaction = repo.find_by_id(&approved.transaction_id).await.unwrap().unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Reversed);
+
+        // 被拒绝的交易无需冲正
+        service
+            .process_transaction(request(&device.id, 5100, "c-4").await, "device")
+            .await
+            .unwrap();
+        let response =
+            service.request_device_reversal(&device.id, &by_client_id("c-4")).await.unwrap();
+        assert_eq!(response.status, TransactionStatus::Declined);
+        assert_eq!(response.reversal_status, None);
+
+        // 未处理过的交易和其他设备的交易视为不存在
+        let result = service.request_device_reversal(&device.id, &by_client_id("c-5")).await;
+        assert!(matches!(result, Err(AppError::TransactionNotFound)), "unexpected: {:?}", result);
+        let result = service
+            .request_device_reversal(
+                &other.id,
+                &ReverseTransactionRequest {
+                    transaction_id: Some(approved.transaction_id.clone()),
+                    client_transaction_id: None,
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(AppError::TransactionNotFound)), "unexpected: {:?}", result);
+        let result = service
+            .request_device_reversal(
+                &device.id,
+                &ReverseTransactionRequest { transaction_id: None, client_transaction_id: None },
+            )
+            .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))), "unexpected: {:?}", result);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
@@ -6,24 +6,48 @@ use sqlx::SqlitePool;
 mod transaction_service_tests {
     use super::*;
     use crate::dto::ProcessTransactionRequest;
-    use crate::models::{TransactionStatus, TransactionType};
-    use crate::repositories::{AuditLogRepository, DeviceRepository, TransactionRepository};
+    use crate::models::{HealthCheck, TransactionStatus, TransactionType};
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, TransactionRepository,
+        TransactionTokenRepository,
+    };
     use crate::security::{DukptKeyDerivation, JwtService};
     use crate::services::transaction::TransactionService; // Correct import
     use crate::services::TransactionTokenService;
     use std::sync::Arc;
 
+    async fn transaction_token(device_id: &str, amount: i64, currency: &str) -> String {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let health_check =
+            HealthCheck::new(device_id.to_string(), 95, false, false, true, true, true);
+        TransactionTokenService::new(jwt_service, None)
+            .generate_bound_token(device_id, &health_check, amount, currency)
+            .await
+            .unwrap()
+            .token
+    }
+
     #[tokio::test]
     async fn test_process_transaction_success() {
         let pool = setup_test_db().await;
-        let tx_repo = TransactionRepository::new(pool.clone());
-        let device_repo = DeviceRepository::new(pool.clone());
-        let audit_repo = AuditLogRepository::new(pool.clone());
+        let tx_repo = TransactionRepository::unscoped(pool.clone());
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        let audit_repo = AuditLogRepository::unscoped(pool.clone());
         let dukpt = DukptKeyDerivation::new(vec![0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
         let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
-        let token_service = Arc::new(TransactionTokenService::new(jwt_service, None));
-        let service =
-            TransactionService::new(tx_repo, device_repo, audit_repo, dukpt, None, token_service);
+        let token_service = Arc::new(
+            TransactionTokenService::new(jwt_service, None)
+                .with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+        );
+        let service = TransactionService::new(
+            tx_repo,
+            device_repo,
+            audit_repo,
+            HealthCheckRepository::unscoped(pool.clone()),
+            dukpt,
+            None,
+            token_service,
+        );
 
         let device_id = create_test_device(&pool).await;
 
@@ -35,12 +59,15 @@ mod transaction_service_tests {
             encrypted_pin_block: "encrypted_pin".to_string(),
             ksn: "FFFF9876543210E00000".to_string(),
             card_number_masked: Some("************1234".to_string()),
-            transaction_token: "token".to_string(),
+            transaction_token: transaction_token(&device_id, 10000, "USD").await,
             client_ip: Some("127.0.0.1".to_string()),
             latitude: Some(37.7749),
             longitude: Some(-122.4194),
             location_accuracy: Some(10.0),
             location_timestamp: Some(Utc::now().to_rfc3339()),
+            original_transaction_id: None,
+            client_transaction_id: None,
+            emv_data: None,
         };
 
         let result = service.process_transaction(request, "test_user").await;
@@ -53,14 +80,21 @@ mod transaction_service_tests {
     #[tokio::test]
     async fn test_transaction_with_invalid_device() {
         let pool = setup_test_db().await;
-        let tx_repo = TransactionRepository::new(pool.clone());
-        let device_repo = DeviceRepository::new(pool.clone());
-        let audit_repo = AuditLogRepository::new(pool.clone());
+        let tx_repo = TransactionRepository::unscoped(pool.clone());
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        let audit_repo = AuditLogRepository::unscoped(pool.clone());
         let dukpt = DukptKeyDerivation::new(vec![0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
         let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
         let token_service = Arc::new(TransactionTokenService::new(jwt_service, None));
-        let service =
-            TransactionService::new(tx_repo, device_repo, audit_repo, dukpt, None, token_service);
+        let service = TransactionService::new(
+            tx_repo,
+            device_repo,
+            audit_repo,
+            HealthCheckRepository::unscoped(pool.clone()),
+            dukpt,
+            None,
+            token_service,
+        );
 
         let fake_device_id = uuid::Uuid::new_v4().to_string();
 
@@ -78,6 +112,9 @@ mod transaction_service_tests {
             longitude: Some(-122.4194),
             location_accuracy: Some(10.0),
             location_timestamp: Some(Utc::now().to_rfc3339()),
+            original_transaction_id: None,
+            client_transaction_id: None,
+            emv_data: None,
         };
 
         let result = service.process_transaction(request, "test_user").await;
@@ -87,14 +124,21 @@ mod transaction_service_tests {
     #[tokio::test]
     async fn test_get_transaction_history() {
         let pool = setup_test_db().await;
-        let tx_repo = TransactionRepository::new(pool.clone());
-        let device_repo = DeviceRepository::new(pool.clone());
-        let audit_repo = AuditLogRepository::new(pool.clone());
+        let tx_repo = TransactionRepository::unscoped(pool.clone());
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        let audit_repo = AuditLogRepository::unscoped(pool.clone());
         let dukpt = DukptKeyDerivation::new(vec![0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
         let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
         let token_service = Arc::new(TransactionTokenService::new(jwt_service, None));
-        let service =
-            TransactionService::new(tx_repo, device_repo, audit_repo, dukpt, None, token_service);
+        let service = TransactionService::new(
+            tx_repo,
+            device_repo,
+            audit_repo,
+            HealthCheckRepository::unscoped(pool.clone()),
+            dukpt,
+            None,
+            token_service,
+        );
 
         let device_id = create_test_device(&pool).await;
 
@@ -114,14 +158,21 @@ mod transaction_service_tests {
     #[tokio::test]
     async fn test_transaction_amount_validation() {
         let pool = setup_test_db().await;
-        let tx_repo = TransactionRepository::new(pool.clone());
-        let device_repo = DeviceRepository::new(pool.clone());
-        let audit_repo = AuditLogRepository::new(pool.clone());
+        let tx_repo = TransactionRepository::unscoped(pool.clone());
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        let audit_repo = AuditLogRepository::unscoped(pool.clone());
         let dukpt = DukptKeyDerivation::new(vec![0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
         let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
         let token_service = Arc::new(TransactionTokenService::new(jwt_service, None));
-        let service =
-            TransactionService::new(tx_repo, device_repo, audit_repo, dukpt, None, token_service);
+        let service = TransactionService::new(
+            tx_repo,
+            device_repo,
+            audit_repo,
+            HealthCheckRepository::unscoped(pool.clone()),
+            dukpt,
+            None,
+            token_service,
+        );
 
         let device_id = create_test_device(&pool).await;
 
@@ -140,6 +191,9 @@ mod transaction_service_tests {
             longitude: Some(-122.4194),
             location_accuracy: Some(10.0),
             location_timestamp: Some(Utc::now().to_rfc3339()),
+            original_transaction_id: None,
+            client_transaction_id: None,
+            emv_data: None,
         };
 
         let result = service.process_transaction(request, "test_user").await;
@@ -0,0 +1,310 @@
+// Integration tests for transaction token binding and single use
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod transaction_token_tests {
+    use super::*;
+    use crate::dto::{
+        AttestTransactionRequest, AttestTransactionResponse, ProcessTransactionRequest,
+    };
+    use crate::models::{Device, DeviceMode, DeviceStatus, HealthCheck, TeeType, TransactionType};
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, TransactionRepository,
+        TransactionTokenRepository,
+    };
+    use crate::security::{DukptKeyDerivation, JwtService};
+    use crate::services::{TransactionService, TransactionTokenService};
+    use crate::utils::error::AppError;
+    use std::sync::Arc;
+
+    fn token_service() -> TransactionTokenService {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        TransactionTokenService::new(jwt_service, None)
+    }
+
+    fn transaction_service(
+        pool: &SqlitePool,
+        token_service: TransactionTokenService,
+    ) -> TransactionService {
+        TransactionService::new(
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec()),
+            None,
+            Arc::new(token_service),
+        )
+    }
+
+    async fn create_active_device(pool: &SqlitePool, imei: &str) -> Device {
+        let device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        repo.update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        repo.update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+        device
+    }
+
+    fn health_check(device_id: &str) -> HealthCheck {
+        HealthCheck::new(device_id.to_string(), 95, false, false, true, true, true)
+    }
+
+    fn request(device_id: &str, amount: i64, token: String) -> ProcessTransactionRequest {
+        ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type: TransactionType::Payment,
+            amount,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "encrypted_pin".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1234".to_string()),
+            transaction_token: token,
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: None,
+            client_transaction_id: None,
+            emv_data: None,
+        }
+    }
+
+    async fn usage_count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM transaction_token_usage")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_attested_token_is_bound_and_single_use() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000001").await;
+        let service = transaction_service(
+            &pool,
+            token_service().with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+        );
+        HealthCheckRepository::unscoped(pool.clone())
+            .create(&health_check(&device.id))
+            .await
+            .unwrap();
+
+        let attestation = service
+            .attest_transaction(
+                AttestTransactionRequest {
+                    device_id: device.id.clone(),
+                    amount: 2500,
+                    currency: "usd".to_string(),
+                    health_check: None,
+                },
+                "device",
+            )
+            .await
+            .unwrap();
+        let token = attestation.transaction_token;
+
+        // 金额与鉴证时不一致的请求被拒绝，且不占用令牌
+        let result = service
+            .process_transaction(request(&device.id, 2600, token.clone()), "device")
+            .await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))), "unexpected: {:?}", result);
+        assert_eq!(usage_count(&pool).await, 0);
+
+        service
+            .process_transaction(request(&device.id, 2500, token.clone()), "device")
+            .await
+            .unwrap();
+        assert_eq!(usage_count(&pool).await, 1);
+
+        let result = service.process_transaction(request(&device.id, 2500, token), "device").await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))), "unexpected: {:?}", result);
+
+        // 超过安全评分允许的最大金额时不签发令牌
+        let result = service
+            .attest_transaction(
+                AttestTransactionRequest {
+                    device_id: device.id.clone(),
+                    amount: 2_000_000,
+                    currency: "USD".to_string(),
+                    health_check: None,
+                },
+                "device",
+            )
+            .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))), "unexpected: {:?}", result);
+    }
+
+    fn attest_request(device_id: &str) -> AttestTransactionRequest {
+        AttestTransactionRequest {
+            device_id: device_id.to_string(),
+            amount: 1000,
+            currency: "USD".to_string(),
+            health_check: None,
+        }
+    }
+
+    fn required(result: Result<AttestTransactionResponse, AppError>, reason: &str) {
+        match result {
+            Err(AppError::HealthCheckRequired(r)) => assert_eq!(r, reason),
+            Err(e) => panic!("unexpected error: {:?}", e),
+            Ok(_) => panic!("expected health check to be required"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_attestation_requires_recent_clean_health_check() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000005").await;
+        let health_repo = HealthCheckRepository::unscoped(pool.clone());
+        let service = transaction_service(&pool, token_service());
+
+        // 从未提交健康检查
+        required(
+            service.attest_transaction(attest_request(&device.id), "device").await,
+            "NO_HEALTH_CHECK",
+        );
+
+        // 最近一次检查已过期
+        let mut stale = health_check(&device.id);
+        stale.created_at = (chrono::Utc::now() - chrono::Duration::minutes(10)).to_rfc3339();
+        health_repo.create(&stale).await.unwrap();
+        required(
+            service.attest_transaction(attest_request(&device.id), "device").await,
+            "HEALTH_CHECK_EXPIRED",
+        );
+
+        // 最近一次检查发现设备已Root，即使更早的检查通过也不签发令牌
+        let rooted = HealthCheck::new(device.id.clone(), 95, true, false, true, true, true);
+        health_repo.create(&rooted).await.unwrap();
+        required(
+            service.attest_transaction(attest_request(&device.id), "device").await,
+            "HEALTH_CHECK_FAILED",
+        );
+
+        // 通过的检查：令牌中携带检查的真实结果
+        let mut clean = HealthCheck::new(device.id.clone(), 88, false, true, true, true, false);
+        clean.created_at = (chrono::Utc::now() + chrono::Duration::seconds(1)).to_rfc3339();
+        health_repo.create(&clean).await.unwrap();
+        let attestation =
+            service.attest_transaction(attest_request(&device.id), "device").await.unwrap();
+        let claims = token_service()
+            .verify_token(&attestation.transaction_token, &device.id)
+            .await
+            .unwrap();
+        assert_eq!(claims.security_score, 88);
+        assert_eq!(claims.health_checked_at, clean.created_at);
+        assert!(!claims.root_status);
+        assert!(claims.bootloader_status);
+        assert!(!claims.tee_status);
+    }
+
+    #[tokio::test]
+    async fn test_unbound_and_foreign_tokens_are_rejected() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000002").await;
+        let other = create_active_device(&pool, "720000000000003").await;
+        let tokens = token_service();
+        let service = transaction_service(
+            &pool,
+            token_service().with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+        );
+
+        // 健康检查签发的令牌未绑定交易，不能用于处理交易
+        let unbound = tokens.generate_token(&device.id, &health_check(&device.id)).await.unwrap();
+        let result = service
+            .process_transaction(request(&device.id, 1000, unbound.token), "device")
+            .await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))), "unexpected: {:?}", result);
+
+        let foreign = tokens
+            .generate_bound_token(&other.id, &health_check(&other.id), 1000, "USD")
+            .await
+            .unwrap();
+        let result = service
+            .process_transaction(request(&device.id, 1000, foreign.token), "device")
+            .await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))), "unexpected: {:?}", result);
+
+        let result = service
+            .process_transaction(request(&device.id, 1000, "token".to_string()), "device")
+            .await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))), "unexpected: {:?}", result);
+        assert_eq!(usage_count(&pool).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_with_same_token() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000004").await;
+        let service = transaction_service(
+            &pool,
+            token_service().with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+        );
+
+        let token = token_service()
+            .generate_bound_token(&device.id, &health_check(&device.id), 1000, "USD")
+            .await
+            .unwrap()
+            .token;
+        let (first, second) = tokio::join!(
+            service.process_transaction(request(&device.id, 1000, token.clone()), "device"),
+            service.process_transaction(request(&device.id, 1000, token), "device"),
+        );
+        assert_eq!(first.is_ok() as u8 + second.is_ok() as u8, 1);
+
+        let processed: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE device_id = ?")
+                .bind(&device.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(processed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_usage_store_is_required() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "720000000000005").await;
+        let service = transaction_service(&pool, token_service());
+
+        // 未配置数据库使用记录时无法防重放，拒绝处理
+        let token = token_service()
+            .generate_bound_token(&device.id, &health_check(&device.id), 1000, "USD")
+            .await
+            .unwrap()
+            .token;
+        let result = service.process_transaction(request(&device.id, 1000, token), "device").await;
+        assert!(result.is_err());
+        let processed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM transactions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(processed, 0);
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
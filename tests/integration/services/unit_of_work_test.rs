@@ -0,0 +1,385 @@
+// Integration tests for atomic multi-step service operations
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod unit_of_work_tests {
+    use super::*;
+    use crate::dto::{EncryptPinRequest, ProcessTransactionRequest};
+    use crate::models::{
+        Device, DeviceMode, DeviceStatus, HealthCheck, TeeType, ThreatSeverity, ThreatType,
+        TransactionType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, HealthCheckRepository, ThreatRepository,
+        TransactionRepository, TransactionTokenRepository, UnitOfWork,
+    };
+    use crate::security::{DukptKeyDerivation, JwtService};
+    use crate::services::{
+        DeviceService, KeyManagementService, ThreatDetectionService, TransactionService,
+        TransactionTokenService,
+    };
+    use std::sync::Arc;
+
+    fn dukpt() -> DukptKeyDerivation {
+        DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec())
+    }
+
+    /// 使指定操作的审计日志写入失败，模拟多步操作的最后一步出错
+    async fn fail_audit(pool: &SqlitePool, operation: &str) {
+        sqlx::query(&format!(
+            r#"
+            CREATE TRIGGER fail_audit_{op} BEFORE INSERT ON audit_logs
+            WHEN NEW.operation = '{op}'
+            BEGIN
+                SELECT RAISE(ABORT, 'injected failure');
+            END
+            "#,
+            op = operation
+        ))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn create_active_device(pool: &SqlitePool, imei: &str) -> Device {
+        let mut device = Device::new(
+            imei.to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        let repo = DeviceRepository::unscoped(pool.clone());
+        repo.create(&device).await.unwrap();
+        repo.update_status(&device.id, DeviceStatus::Pending, DeviceStatus::Active, "admin", None)
+            .await
+            .unwrap();
+        repo.update_key_info(&device.id, "FFFF9876543210E00000", Some("now"), Some(100), Some(100))
+            .await
+            .unwrap();
+
+        device.status = DeviceStatus::Active.as_str().to_string();
+        device
+    }
+
+    async fn count(pool: &SqlitePool, sql: &str, id: &str) -> i64 {
+        sqlx::query_scalar::<_, i64>(sql).bind(id).fetch_one(pool).await.unwrap()
+    }
+
+    async fn device_state(pool: &SqlitePool, device_id: &str) -> (String, i32) {
+        let device = DeviceRepository::unscoped(pool.clone())
+            .find_by_id(device_id)
+            .await
+            .unwrap()
+            .unwrap();
+        (device.status, device.key_remaining_count)
+    }
+
+    fn threat_service(pool: &SqlitePool) -> ThreatDetectionService {
+        ThreatDetectionService::new(
+            ThreatRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+    }
+
+    async fn transaction_token(device_id: &str, amount: i64, currency: &str) -> String {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        let health_check =
+            HealthCheck::new(device_id.to_string(), 95, false, false, true, true, true);
+        TransactionTokenService::new(jwt_service, None)
+            .generate_bound_token(device_id, &health_check, amount, currency)
+            .await
+            .unwrap()
+            .token
+    }
+
+    async fn transaction_request(device_id: &str) -> ProcessTransactionRequest {
+        ProcessTransactionRequest {
+            device_id: device_id.to_string(),
+            transaction_type: TransactionType::Payment,
+            amount: 10000,
+            currency: "USD".to_string(),
+            encrypted_pin_block: "encrypted_pin".to_string(),
+            ksn: "FFFF9876543210E00000".to_string(),
+            card_number_masked: Some("************1234".to_string()),
+            transaction_token: transaction_token(device_id, 10000, "USD").await,
+            client_ip: None,
+            latitude: None,
+            longitude: None,
+            location_accuracy: None,
+            location_timestamp: None,
+            original_transaction_id: None,
+            client_transaction_id: None,
+            emv_data: None,
+        }
+    }
+
+    fn transaction_service(pool: &SqlitePool) -> TransactionService {
+        let jwt_service = Arc::new(JwtService::new("test_secret".to_string(), 3600));
+        TransactionService::new(
+            TransactionRepository::unscoped(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            HealthCheckRepository::unscoped(pool.clone()),
+            dukpt(),
+            None,
+            Arc::new(
+                TransactionTokenService::new(jwt_service, None)
+                    .with_usage_repo(TransactionTokenRepository::new(pool.clone())),
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_process_transaction_rolls_back_on_failure() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "400000000000001").await;
+        let service = transaction_service(&pool);
+
+        fail_audit(&pool, "TRANSACTION_PROCESSING").await;
+        assert!(service
+            .process_transaction(transaction_request(&device.id).await, "device")
+            .await
+            .is_err());
+
+        // 处理结果、密钥计数和交易事件均未写入，交易保持PENDING等待预登记的冲正
+        assert_eq!(
+            count(
+                &pool,
+                "SELECT COUNT(*) FROM transactions WHERE device_id = ? AND status = 'Pending'",
+                &device.id
+            )
+            .await,
+            1
+        );
+        assert_eq!(
+            count(
+                &pool,
+                "SELECT COUNT(*) FROM transaction_reversals r JOIN transactions t \
+                 ON t.id = r.transaction_id WHERE t.device_id = ? AND r.attempts = 0",
+                &device.id
+            )
+            .await,
+            1
+        );
+        assert_eq!(device_state(&pool, &device.id).await.1, 100);
+        assert_eq!(
+            count(
+                &pool,
+                "SELECT COUNT(*) FROM outbox WHERE event_type = ?",
+                "TransactionProcessed"
+            )
+            .await,
+            0
+        );
+
+        // 故障排除后正常提交
+        sqlx::query("DROP TRIGGER fail_audit_TRANSACTION_PROCESSING")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let response = service
+            .process_transaction(transaction_request(&device.id).await, "device")
+            .await
+            .unwrap();
+        assert_eq!(
+            count(
+                &pool,
+                "SELECT COUNT(*) FROM transactions WHERE id = ?",
+                &response.transaction_id
+            )
+            .await,
+            1
+        );
+        assert_eq!(device_state(&pool, &device.id).await.1, 99);
+    }
+
+    #[tokio::test]
+    async fn test_threat_action_rolls_back_on_failure() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "400000000000002").await;
+        let service = threat_service(&pool);
+
+        fail_audit(&pool, "DEVICE_SUSPENDED_BY_THREAT").await;
+        assert!(service.handle_health_check_threats(&device.id, 50, vec![]).await.is_err());
+
+        // 威胁记录、设备状态和状态历史均未写入
+        assert_eq!(
+            count(&pool, "SELECT COUNT(*) FROM threat_events WHERE device_id = ?", &device.id)
+                .await,
+            0
+        );
+        assert_eq!(device_state(&pool, &device.id).await.0, "ACTIVE");
+        assert_eq!(
+            count(
+                &pool,
+                "SELECT COUNT(*) FROM device_status_history WHERE device_id = ? AND to_status = 'SUSPENDED'",
+                &device.id
+            )
+            .await,
+            0
+        );
+
+        sqlx::query("DROP TRIGGER fail_audit_DEVICE_SUSPENDED_BY_THREAT")
+            .execute(&pool)
+            .await
+            .unwrap();
+        service.handle_health_check_threats(&device.id, 50, vec![]).await.unwrap();
+        assert_eq!(
+            count(&pool, "SELECT COUNT(*) FROM threat_events WHERE device_id = ?", &device.id)
+                .await,
+            1
+        );
+        assert_eq!(device_state(&pool, &device.id).await.0, "SUSPENDED");
+    }
+
+    #[tokio::test]
+    async fn test_multiple_threats_roll_back_together() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "400000000000003").await;
+
+        // 第一个威胁只监控，第二个威胁吊销设备时失败
+        fail_audit(&pool, "DEVICE_REVOKED_BY_THREAT").await;
+        let result = threat_service(&pool)
+            .handle_health_check_threats(
+                &device.id,
+                70,
+                vec![ThreatType::AppTamper, ThreatType::TeeCompromise],
+            )
+            .await;
+        assert!(result.is_err());
+
+        assert_eq!(
+            count(&pool, "SELECT COUNT(*) FROM threat_events WHERE device_id = ?", &device.id)
+                .await,
+            0
+        );
+        assert_eq!(
+            count(&pool, "SELECT COUNT(*) FROM audit_logs WHERE device_id = ?", &device.id).await,
+            0
+        );
+        assert_eq!(
+            count(&pool, "SELECT COUNT(*) FROM outbox WHERE event_type = ?", "ThreatDetected")
+                .await,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reported_threat_rolls_back_on_failure() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "400000000000004").await;
+
+        fail_audit(&pool, "THREAT_REPORTED").await;
+        let result = threat_service(&pool)
+            .report_threat(
+                &device.id,
+                ThreatType::RootDetection,
+                ThreatSeverity::Critical,
+                "Root".to_string(),
+            )
+            .await;
+        assert!(result.is_err());
+
+        assert_eq!(
+            count(&pool, "SELECT COUNT(*) FROM threat_events WHERE device_id = ?", &device.id)
+                .await,
+            0
+        );
+        assert_eq!(device_state(&pool, &device.id).await.0, "ACTIVE");
+    }
+
+    #[tokio::test]
+    async fn test_device_and_key_operations_roll_back_on_failure() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "400000000000005").await;
+
+        let device_service = DeviceService::new(
+            DeviceRepository::unscoped(pool.clone()),
+            ThreatRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            dukpt(),
+            None,
+        );
+        fail_audit(&pool, "DEVICE_SUSPENSION").await;
+        assert!(device_service.suspend_device(&device.id, "admin", "Lost").await.is_err());
+        assert_eq!(device_state(&pool, &device.id).await.0, "ACTIVE");
+        assert_eq!(
+            count(&pool, "SELECT COUNT(*) FROM outbox WHERE aggregate_id = ? AND event_type = 'DeviceStatusChanged'", &device.id)
+                .await,
+            1
+        );
+
+        let key_service = KeyManagementService::new(
+            DeviceRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            dukpt(),
+            None,
+        );
+        fail_audit(&pool, "PIN_ENCRYPTION").await;
+        let request = EncryptPinRequest {
+            device_id: device.id.clone(),
+            pin: "1234".to_string(),
+            attestation_token: "token".to_string(),
+        };
+        assert!(key_service.encrypt_pin(request, "device").await.is_err());
+        assert_eq!(device_state(&pool, &device.id).await.1, 100);
+    }
+
+    #[tokio::test]
+    async fn test_repositories_share_unit_of_work() {
+        let pool = setup_test_db().await;
+        let device = create_active_device(&pool, "400000000000006").await;
+        let device_repo = DeviceRepository::unscoped(pool.clone());
+        let audit_repo = AuditLogRepository::unscoped(pool.clone());
+
+        let uow = UnitOfWork::begin(&pool).await.unwrap();
+        device_repo.in_unit_of_work(&uow).decrement_key_count(&device.id).await.unwrap();
+
+        // 事务内可见自身写入
+        let in_tx =
+            device_repo.in_unit_of_work(&uow).find_by_id(&device.id).await.unwrap().unwrap();
+        assert_eq!(in_tx.key_remaining_count, 99);
+
+        let log = crate::models::AuditLog::new(
+            "TEST".to_string(),
+            "admin".to_string(),
+            crate::models::OperationResult::Success,
+        )
+        .with_device_id(device.id.clone());
+        audit_repo.in_unit_of_work(&uow).create(&log).await.unwrap();
+
+        uow.rollback().await.unwrap();
+        assert_eq!(device_state(&pool, &device.id).await.1, 100);
+        assert!(audit_repo.find_by_id(&log.id).await.unwrap().is_none());
+
+        // 已结束的工作单元不能再使用
+        assert!(device_repo
+            .in_unit_of_work(&uow_finished(&pool).await)
+            .find_by_id(&device.id)
+            .await
+            .is_err());
+    }
+
+    async fn uow_finished(pool: &SqlitePool) -> UnitOfWork {
+        let uow = UnitOfWork::begin(pool).await.unwrap();
+        uow.clone().commit().await.unwrap();
+        uow
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
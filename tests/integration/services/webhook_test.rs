@@ -0,0 +1,388 @@
+// Integration tests for merchant webhooks
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+#[cfg(test)]
+mod webhook_tests {
+    use super::*;
+    use crate::dto::{ApproveDeviceRequest, CreateWebhookRequest, UpdateWebhookRequest};
+    use crate::models::{
+        Device, DeviceMode, Merchant, TeeType, WebhookDeliveryStatus, WebhookEventType,
+    };
+    use crate::repositories::{
+        AuditLogRepository, DeviceRepository, MerchantRepository, OutboxRepository,
+        ThreatRepository, WebhookRepository,
+    };
+    use crate::security::DukptKeyDerivation;
+    use crate::services::{sign_webhook_payload, DeviceService, OutboxDispatcher, WebhookService};
+    use std::time::Duration;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    async fn setup() -> (SqlitePool, WebhookService, String) {
+        let pool = setup_test_db().await;
+        let service = WebhookService::new(
+            WebhookRepository::unscoped(pool.clone()),
+            MerchantRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+        .with_retry_policy(3, Duration::ZERO)
+        .with_timeout(Duration::from_secs(2));
+
+        let merchant = Merchant::new(
+            "Coffee Shop".to_string(),
+            "5812".to_string(),
+            "US".to_string(),
+            "USD".to_string(),
+            None,
+            None,
+        );
+        MerchantRepository::unscoped(pool.clone()).create(&merchant).await.unwrap();
+
+        (pool, service, merchant.id)
+    }
+
+    async fn subscribe(
+        service: &WebhookService,
+        merchant_id: &str,
+        url: String,
+        event_types: Vec<WebhookEventType>,
+    ) -> (String, String) {
+        let response = service
+            .create_subscription(
+                CreateWebhookRequest { merchant_id: merchant_id.to_string(), url, event_types },
+                "admin",
+            )
+            .await
+            .unwrap();
+
+        (response.id, response.secret.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed_and_filtered_by_event_type() {
+        let (_pool, service, merchant_id) = setup().await;
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hooks"))
+            .and(header("X-Webhook-Event", "transaction.completed"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let (webhook_id, secret) = subscribe(
+            &service,
+            &merchant_id,
+            format!("{}/hooks", server.uri()),
+            vec![WebhookEventType::TransactionCompleted],
+        )
+        .await;
+        assert!(secret.starts_with("whsec_"));
+
+        // 未订阅的事件不入队
+        let queued = service
+            .publish_event(
+                crate::models::DEFAULT_TENANT_ID,
+                &merchant_id,
+                WebhookEventType::ThreatDetected,
+                serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(queued, 0);
+
+        let queued = service
+            .publish_event(
+                crate::models::DEFAULT_TENANT_ID,
+                &merchant_id,
+                WebhookEventType::TransactionCompleted,
+                serde_json::json!({ "transaction_id": "tx-1", "amount": 1000 }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(queued, 1);
+
+        assert_eq!(service.process_due_deliveries().await.unwrap(), 1);
+
+        let requests = server.received_requests().await.unwrap();
+        let request = &requests[0];
+        let body = String::from_utf8(request.body.clone()).unwrap();
+        let timestamp: i64 = request
+            .headers
+            .get("X-Webhook-Timestamp")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let signature = request.headers.get("X-Webhook-Signature").unwrap().to_str().unwrap();
+        assert_eq!(signature, sign_webhook_payload(&secret, timestamp, &body));
+
+        let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["type"], "transaction.completed");
+        assert_eq!(payload["merchant_id"], merchant_id.as_str());
+        assert_eq!(payload["data"]["transaction_id"], "tx-1");
+
+        let deliveries = service.list_deliveries(&webhook_id, None, 20, 0).await.unwrap();
+        assert_eq!(deliveries.total, 1);
+        assert_eq!(deliveries.deliveries[0].status, "SUCCEEDED");
+        assert_eq!(deliveries.deliveries[0].response_status, Some(200));
+        assert_eq!(
+            request.headers.get("X-Webhook-Delivery").unwrap().to_str().unwrap(),
+            deliveries.deliveries[0].id
+        );
+
+        // 已成功的投递不再重复发送
+        assert_eq!(service.process_due_deliveries().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_retries_then_dead_letters() {
+        let (_pool, service, merchant_id) = setup().await;
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let (webhook_id, _) =
+            subscribe(&service, &merchant_id, server.uri(), vec![WebhookEventType::ThreatDetected])
+                .await;
+        service
+            .publish_event(
+                crate::models::DEFAULT_TENANT_ID,
+                &merchant_id,
+                WebhookEventType::ThreatDetected,
+                serde_json::json!({ "threat_id": "t-1" }),
+            )
+            .await
+            .unwrap();
+
+        for attempt in 1..=3 {
+            assert_eq!(service.process_due_deliveries().await.unwrap(), 1);
+
+            let delivery =
+                &service.list_deliveries(&webhook_id, None, 20, 0).await.unwrap().deliveries[0];
+            assert_eq!(delivery.attempts, attempt);
+            assert_eq!(delivery.response_status, Some(500));
+            assert!(delivery.last_error.as_deref().unwrap().contains("boom"));
+        }
+
+        let dead = service
+            .list_deliveries(&webhook_id, Some(WebhookDeliveryStatus::DeadLetter), 20, 0)
+            .await
+            .unwrap();
+        assert_eq!(dead.total, 1);
+        assert!(dead.deliveries[0].next_attempt_at.is_none());
+
+        // 死信不再自动重试
+        assert_eq!(service.process_due_deliveries().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_backoff_delays_next_attempt() {
+        let (pool, _, merchant_id) = setup().await;
+        let service = WebhookService::new(
+            WebhookRepository::unscoped(pool.clone()),
+            MerchantRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+        )
+        .with_retry_policy(5, Duration::from_secs(60));
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let (webhook_id, _) =
+            subscribe(&service, &merchant_id, server.uri(), vec![WebhookEventType::ThreatDetected])
+                .await;
+        service
+            .publish_event(
+                crate::models::DEFAULT_TENANT_ID,
+                &merchant_id,
+                WebhookEventType::ThreatDetected,
+                serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(service.process_due_deliveries().await.unwrap(), 1);
+        // 下一次重试在一分钟后，本轮不会再次投递
+        assert_eq!(service.process_due_deliveries().await.unwrap(), 0);
+
+        let delivery =
+            &service.list_deliveries(&webhook_id, None, 20, 0).await.unwrap().deliveries[0];
+        assert_eq!(delivery.status, "PENDING");
+        let next =
+            chrono::DateTime::parse_from_rfc3339(delivery.next_attempt_at.as_deref().unwrap())
+                .unwrap();
+        assert!(next > chrono::Utc::now() + chrono::Duration::seconds(50));
+    }
+
+    #[tokio::test]
+    async fn test_redeliver_dead_letter() {
+        let (_pool, service, merchant_id) = setup().await;
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let (webhook_id, _) =
+            subscribe(&service, &merchant_id, server.uri(), vec![WebhookEventType::ThreatDetected])
+                .await;
+        service
+            .publish_event(
+                crate::models::DEFAULT_TENANT_ID,
+                &merchant_id,
+                WebhookEventType::ThreatDetected,
+                serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            service.process_due_deliveries().await.unwrap();
+        }
+
+        let delivery =
+            &service.list_deliveries(&webhook_id, None, 20, 0).await.unwrap().deliveries[0];
+        assert_eq!(delivery.status, "DEAD_LETTER");
+
+        let redelivered = service.redeliver(&delivery.id, "admin").await.unwrap();
+        assert_eq!(redelivered.status, "SUCCEEDED");
+        assert_eq!(redelivered.attempts, 1);
+        assert_eq!(redelivered.response_status, Some(204));
+        assert!(redelivered.delivered_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_inactive_subscription_receives_nothing() {
+        let (_pool, service, merchant_id) = setup().await;
+        let (webhook_id, _) = subscribe(
+            &service,
+            &merchant_id,
+            "https://example.invalid/hooks".to_string(),
+            vec![WebhookEventType::DeviceStatusChanged],
+        )
+        .await;
+
+        let updated = service
+            .update_subscription(
+                &webhook_id,
+                UpdateWebhookRequest { url: None, event_types: None, active: Some(false) },
+                "admin",
+            )
+            .await
+            .unwrap();
+        assert!(!updated.active);
+        assert!(updated.secret.is_none());
+
+        let queued = service
+            .publish_event(
+                crate::models::DEFAULT_TENANT_ID,
+                &merchant_id,
+                WebhookEventType::DeviceStatusChanged,
+                serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(queued, 0);
+
+        // 其他租户看不到该订阅
+        let other = service.for_tenant(&crate::models::TenantContext::new("acq-other"));
+        assert!(other.get_subscription(&webhook_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_device_status_change_published_to_merchant() {
+        let (pool, service, merchant_id) = setup().await;
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("X-Webhook-Event", "device.status_changed"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let (webhook_id, _) = subscribe(
+            &service,
+            &merchant_id,
+            server.uri(),
+            vec![WebhookEventType::DeviceStatusChanged],
+        )
+        .await;
+
+        let outbox = OutboxDispatcher::new(
+            OutboxRepository::new(pool.clone()),
+            DeviceRepository::unscoped(pool.clone()),
+            ThreatRepository::unscoped(pool.clone()),
+        )
+        .with_webhooks(service.clone());
+        let device_service = DeviceService::new(
+            DeviceRepository::unscoped(pool.clone()),
+            ThreatRepository::unscoped(pool.clone()),
+            AuditLogRepository::unscoped(pool.clone()),
+            DukptKeyDerivation::new(b"0123456789ABCDEFFEDCBA9876543210".to_vec()),
+            None,
+        );
+
+        let device = Device::new(
+            "200000000000001".to_string(),
+            "V2PRO".to_string(),
+            "12.0".to_string(),
+            TeeType::TrustZone,
+            vec![1, 2, 3],
+            DeviceMode::FullPos,
+            true,
+        );
+        DeviceRepository::unscoped(pool.clone()).create(&device).await.unwrap();
+        sqlx::query("UPDATE devices SET merchant_id = ? WHERE id = ?")
+            .bind(&merchant_id)
+            .bind(&device.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        device_service
+            .approve_device(ApproveDeviceRequest {
+                device_id: device.id.clone(),
+                operator: "admin".to_string(),
+            })
+            .await
+            .unwrap();
+        device_service.suspend_device(&device.id, "admin", "Lost").await.unwrap();
+
+        // 审批和暂停各产生一个状态变更事件，由发件箱分发器入队
+        assert_eq!(outbox.dispatch_pending().await.unwrap(), 3);
+        let deliveries = service.list_deliveries(&webhook_id, None, 20, 0).await.unwrap();
+        assert_eq!(deliveries.total, 2);
+
+        service.process_due_deliveries().await.unwrap();
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        let payload: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(payload["data"]["device_id"], device.id.as_str());
+        assert_eq!(payload["data"]["old_status"], "ACTIVE");
+        assert_eq!(payload["data"]["new_status"], "SUSPENDED");
+    }
+}
+
+async fn setup_test_db() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
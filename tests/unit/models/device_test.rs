@@ -11,6 +11,7 @@ mod device_tests {
     fn test_device_creation() {
         let device = Device {
             id: Uuid::new_v4().to_string(),
+            tenant_id: "default".to_string(),
             imei: "123456789012345".to_string(),
             model: "V2PRO".to_string(),
             os_version: "12.0".to_string(),
@@ -20,6 +21,8 @@ mod device_tests {
             status: DeviceStatus::Pending.as_str().to_string(),
             merchant_id: None,
             merchant_name: None,
+            store_id: None,
+            terminal_id: None,
             security_score: 85,
             current_ksn: "".to_string(),
             ipek_injected_at: None,
@@ -42,6 +45,7 @@ mod device_tests {
     fn test_device_status_transitions() {
         let mut device = Device {
             id: Uuid::new_v4().to_string(),
+            tenant_id: "default".to_string(),
             imei: "123456789012345".to_string(),
             model: "V2PRO".to_string(),
             os_version: "1.0.0".to_string(),
@@ -51,6 +55,8 @@ mod device_tests {
             status: DeviceStatus::Pending.as_str().to_string(),
             merchant_id: None,
             merchant_name: None,
+            store_id: None,
+            terminal_id: None,
             security_score: 100,
             current_ksn: "".to_string(),
             ipek_injected_at: None,